@@ -5,21 +5,54 @@ mod simple_db_cli;
 mod table_print;
 mod utils;
 
+use crate::request::Authentication;
 use crate::simple_db_cli::SimpleDbCli;
+use crate::simpledb_server::TlsOptions;
 use std::env;
 
 fn main() {
-    let (address, password) = get_database_args();
-    let mut app = SimpleDbCli::create(address, password);
+    let (address, authentication, tls_options, timeout_ms) = get_database_args();
+    let mut app = SimpleDbCli::create(address, authentication, tls_options, timeout_ms);
     app.start()
 }
 
-//Address, Password
-fn get_database_args() -> (String, String) {
+//Address, Authentication, TLS options, query timeout in ms. TLS is enabled with --tls-ca <CA certificate path> or --tls-no-verify
+//Statements will be cancelled by the server after --timeout <ms>, 0 (the default) means no timeout
+//Without --user <name>, the password is checked against the server password
+fn get_database_args() -> (String, Authentication, Option<TlsOptions>, u64) {
     let args: Vec<String> = env::args().collect();
     if args.len() < 3 {
-        panic!("Invalid args. Expect <address> <password>")
+        panic!("Invalid args. Expect <address> <password> [--tls-ca <path>] [--tls-no-verify] [--timeout <ms>] [--user <name>]")
     }
 
-    (args[1].clone(), args[2].clone())
+    let mut tls_options: Option<TlsOptions> = None;
+    let mut timeout_ms = 0;
+    let mut user = String::new();
+    let mut current_arg = 3;
+    while current_arg < args.len() {
+        match args[current_arg].as_str() {
+            "--tls-ca" if current_arg + 1 < args.len() => {
+                let tls_options = tls_options.get_or_insert(TlsOptions { ca_cert_path: None, verify_server: true });
+                tls_options.ca_cert_path = Some(args[current_arg + 1].clone());
+                current_arg += 1;
+            },
+            "--tls-no-verify" => {
+                let tls_options = tls_options.get_or_insert(TlsOptions { ca_cert_path: None, verify_server: true });
+                tls_options.verify_server = false;
+            },
+            "--timeout" if current_arg + 1 < args.len() => {
+                timeout_ms = args[current_arg + 1].parse::<u64>()
+                    .unwrap_or_else(|_| panic!("Invalid timeout {}. Expect a number of milliseconds", args[current_arg + 1]));
+                current_arg += 1;
+            },
+            "--user" if current_arg + 1 < args.len() => {
+                user = args[current_arg + 1].clone();
+                current_arg += 1;
+            },
+            other => panic!("Invalid arg {}. Expect <address> <password> [--tls-ca <path>] [--tls-no-verify] [--timeout <ms>] [--user <name>]", other)
+        }
+        current_arg += 1;
+    }
+
+    (args[1].clone(), Authentication { user, password: args[2].clone() }, tls_options, timeout_ms)
 }
@@ -1,12 +1,20 @@
 use bytes::BufMut;
 
+const REQUEST_FLAG_STANDALONE: u8 = 0x01;
+const REQUEST_FLAG_TIMEOUT: u8 = 0x02; //Followed by the query timeout in ms as u64
+
+//An empty user authenticates with the server password
+#[derive(Clone)]
+pub struct Authentication {
+    pub user: String,
+    pub password: String,
+}
+
 pub enum Request {
-    //Password, statement
-    Statement(String, bool, String), //Request Type ID: 1
-    //Password
-    Close(String), //Request Type ID: 2
-    //Password, database
-    UseDatabase(String, String), //Request Type ID: 3
+    //Authentication, standalone, query timeout in ms (0 if none), statement
+    Statement(Authentication, bool, u64, String), //Request Type ID: 1
+    //Authentication
+    Close(Authentication), //Request Type ID: 2
 }
 
 impl Request {
@@ -15,20 +23,22 @@ impl Request {
         serialized.extend(self.serialize_auth());
 
         match self {
-            Request::Statement(_, is_standalone, statement) => {
+            Request::Statement(_, is_standalone, timeout_ms, statement) => {
                 serialized.put_u8(1);
-                serialized.put_u8(*is_standalone as u8);
+                //The timeout is only sent when there is one, so servers without timeouts can still be used
+                let standalone_flag = if *is_standalone { REQUEST_FLAG_STANDALONE } else { 0 };
+                if *timeout_ms > 0 {
+                    serialized.put_u8(standalone_flag | REQUEST_FLAG_TIMEOUT);
+                    serialized.put_u64_le(*timeout_ms);
+                } else {
+                    serialized.put_u8(standalone_flag);
+                }
                 serialized.put_u32_le(statement.len() as u32);
                 serialized.extend(statement.bytes());
             }
             Request::Close(_) => {
                 serialized.put_u8(2);
             }
-            Request::UseDatabase(_, database_name) => {
-                serialized.put_u8(3);
-                serialized.put_u32_le(database_name.len() as u32);
-                serialized.extend(database_name.bytes());
-            }
         };
 
         serialized
@@ -36,17 +46,18 @@ impl Request {
 
     fn serialize_auth(&self) -> Vec<u8>  {
         let mut serialized: Vec<u8> = Vec::new();
-        let password = self.get_password();
-        serialized.put_u32_le(password.len() as u32);
-        serialized.extend(password.bytes());
+        let authentication = self.get_authentication();
+        serialized.put_u32_le(authentication.user.len() as u32);
+        serialized.extend(authentication.user.bytes());
+        serialized.put_u32_le(authentication.password.len() as u32);
+        serialized.extend(authentication.password.bytes());
         serialized
     }
 
-    pub fn get_password(&self) -> &String {
+    pub fn get_authentication(&self) -> &Authentication {
         match self {
-            Request::Statement(password, _, _) => password,
-            Request::Close(password) => password,
-            Request::UseDatabase(password, _) => password,
+            Request::Statement(authentication, _, _, _) => authentication,
+            Request::Close(authentication) => authentication,
         }
     }
 }
\ No newline at end of file
@@ -1,13 +1,28 @@
 use bytes::Bytes;
 use shared::connection::Connection;
-use shared::{utils, ColumnId, ErrorTypeId};
+use shared::{utils, ColumnId, ErrorTypeId, KeyspaceId, SimpleDbError};
 use std::collections::HashMap;
 
+//Rows are sent with the format of db::Row::serialize()
+const ROW_FORMAT_V1: u8 = 1;
+const ROW_FORMAT_V2: u8 = 2;
+//Nº selected columns of V2 rows that haven't been projected
+const EVERY_COLUMN_SELECTED: u16 = u16::MAX;
+//Rows are sent in chunks, each one starting with its nº of rows. A chunk of 0 rows marks the end of the rows
+const END_OF_ROWS_MARKER: u32 = 0;
+//The query failed while the rows were being sent. It is followed by the error
+const ROWS_ERROR_MARKER: u32 = u32::MAX;
+
 pub enum IndexType {
     Primary,
     Secondary,
 }
 
+pub enum IndexState {
+    Building,
+    Ready,
+}
+
 pub enum Response {
     Statement(StatementResponse),
     Error(ErrorTypeId, String), //Error number, error message
@@ -20,8 +35,21 @@ pub enum StatementResponse {
     Databases(Vec<String>),
     Tables(Vec<String>),
     Describe(Vec<Column>),
-    Indexes(Vec<(String, IndexType)>),
-    Explain(Vec<String>)
+    Indexes(Vec<(String, IndexType, IndexState)>),
+    Explain(Vec<(String, Option<(u64, u64)>)>), //Line, Nº rows emitted and elapsed nanoseconds (only with EXPLAIN ANALYZE)
+    TransactionStatus(Option<(u64, Vec<u64>)>), //Transaction ID, Active transactions when it started
+    Stats(Vec<(String, String)>), //Stat name, Value
+    Transactions(Vec<TransactionDesc>),
+    Vacuumed(usize), //Nº of bytes reclaimed
+    CorruptedParts(Vec<String>), //Error message of each corrupted part
+}
+
+pub struct TransactionDesc {
+    pub txn_id: u64,
+    pub isolation_level: String,
+    pub state: String,
+    pub start_time_ms: u64, //Milliseconds since UNIX epoch. 0 if the transaction is no longer active
+    pub n_writes: u64,
 }
 
 pub struct RowsResponse {
@@ -38,7 +66,7 @@ pub struct Column {
     pub column_type: ColumnType,
     pub column_name: String,
     pub is_primary: bool,
-    pub is_indexed: bool,
+    pub secondary_index_keyspace_id: Option<KeyspaceId>,
 }
 
 pub enum ColumnType {
@@ -64,12 +92,17 @@ impl Response {
             1 => {
                 Response::Statement(match connection.read_u8().expect("Cannot read response statement type ID") {
                     1 => StatementResponse::Ok(connection.read_u64().expect("Cannot read Nº Affected rows") as usize),
-                    2 => StatementResponse::Rows(Self::deserialize_query_response(connection)),
+                    2 => return Self::deserialize_query_response(connection),
                     3 => StatementResponse::Databases(Self::deserialize_string_vec(connection)),
                     4 => StatementResponse::Tables(Self::deserialize_string_vec(connection)),
                     5 => StatementResponse::Describe(Self::deserialize_column_dec(connection)),
                     6 => StatementResponse::Indexes(Self::deserialize_indexes(connection)),
                     7 => StatementResponse::Explain(Self::deserialize_explain(connection)),
+                    8 => StatementResponse::TransactionStatus(Self::deserialize_transaction_status(connection)),
+                    9 => StatementResponse::Stats(Self::deserialize_stats(connection)),
+                    10 => StatementResponse::Transactions(Self::deserialize_transactions(connection)),
+                    11 => StatementResponse::Vacuumed(connection.read_u64().expect("Cannot read Nº Bytes reclaimed") as usize),
+                    12 => StatementResponse::CorruptedParts(Self::deserialize_string_vec(connection)),
                     _ => panic!("Invalid statement response type Id")
                 })
             },
@@ -87,20 +120,78 @@ impl Response {
             .expect("Cannot convert error message bytes to UTF-8 String")
     }
 
-    fn deserialize_explain(connection: &mut Connection) -> Vec<String> {
+    fn deserialize_explain(connection: &mut Connection) -> Vec<(String, Option<(u64, u64)>)> {
         let n_lines = connection.read_u32().expect("Cannot read n lines");
         let mut lines = Vec::new();
 
         for _ in 0..n_lines {
             let line_length = connection.read_u32().expect("Cannot read line length");
             let line_bytes = connection.read_n(line_length as usize).expect("Cannot read line bytes");
-            lines.push(String::from_utf8(line_bytes).expect("Cannot convert line to UTF-8 string"));
+            let line = String::from_utf8(line_bytes).expect("Cannot convert line to UTF-8 string");
+            let has_stats = connection.read_u8().expect("Cannot read has stats") != 0;
+            let stats = if has_stats {
+                let n_rows = connection.read_u64().expect("Cannot read n rows");
+                let elapsed_nanos = connection.read_u64().expect("Cannot read elapsed time");
+                Some((n_rows, elapsed_nanos))
+            } else {
+                None
+            };
+            lines.push((line, stats));
         }
 
         lines
     }
 
-    fn deserialize_indexes(connection: &mut Connection) -> Vec<(String, IndexType)> {
+    fn deserialize_transactions(connection: &mut Connection) -> Vec<TransactionDesc> {
+        let n_transactions = connection.read_u32().expect("Cannot read Nº Transactions");
+        let mut transactions = Vec::with_capacity(n_transactions as usize);
+
+        for _ in 0..n_transactions {
+            let txn_id = connection.read_u64().expect("Cannot read transaction ID");
+            let isolation_level = match connection.read_u8().expect("Cannot read isolation level") {
+                1 => "Read uncommited",
+                2 => "Snapshot isolation",
+                _ => panic!("Invalid isolation level")
+            };
+            let state = match connection.read_u8().expect("Cannot read transaction state") {
+                1 => "Active",
+                2 => "Aborted",
+                3 => "Finished",
+                _ => panic!("Invalid transaction state")
+            };
+
+            transactions.push(TransactionDesc {
+                isolation_level: isolation_level.to_string(),
+                state: state.to_string(),
+                start_time_ms: connection.read_u64().expect("Cannot read transaction start time"),
+                n_writes: connection.read_u64().expect("Cannot read transaction Nº writes"),
+                txn_id,
+            });
+        }
+
+        transactions
+    }
+
+    fn deserialize_stats(connection: &mut Connection) -> Vec<(String, String)> {
+        let n_stats = connection.read_u32().expect("Cannot read Nº Stats");
+        let mut stats = Vec::new();
+
+        for _ in 0..n_stats {
+            let stat_name_length = connection.read_u32().expect("Cannot read stat name length");
+            let stat_name_bytes = connection.read_n(stat_name_length as usize).expect("Cannot read stat name bytes");
+            let stat_value_length = connection.read_u32().expect("Cannot read stat value length");
+            let stat_value_bytes = connection.read_n(stat_value_length as usize).expect("Cannot read stat value bytes");
+
+            stats.push((
+                String::from_utf8(stat_name_bytes).expect("Cannot read stat name as UTF-8 String"),
+                String::from_utf8(stat_value_bytes).expect("Cannot read stat value as UTF-8 String"),
+            ));
+        }
+
+        stats
+    }
+
+    fn deserialize_indexes(connection: &mut Connection) -> Vec<(String, IndexType, IndexState)> {
         let n_indexes = connection.read_u32().expect("Cannot read Nº Indexes");
         let mut indexes = Vec::new();
 
@@ -115,8 +206,13 @@ impl Response {
                 2 => IndexType::Secondary,
                 other => panic!("{}", format!("Unknown index type id {}", other))
             };
+            let index_state = match connection.read_u8().expect("Cannot read index state") {
+                1 => IndexState::Building,
+                2 => IndexState::Ready,
+                other => panic!("{}", format!("Unknown index state id {}", other))
+            };
 
-            indexes.push((column_name, index_type));
+            indexes.push((column_name, index_type, index_state));
         }
 
         indexes
@@ -130,7 +226,12 @@ impl Response {
             let column_id = connection.read_u16().expect("Cannot read columns ID");
             let column_type = connection.read_u8().expect("Cannot read column type");
             let is_primary = connection.read_u8().expect("Cannot read is primary") != 0;
-            let is_indexed = connection.read_u64().expect("Cannot read is indexed") != 0xFFFFFFFFFFFFFFFF;
+            let is_secondary_indexed = connection.read_u8().expect("Cannot read is secondary indexed") != 0;
+            let secondary_index_keyspace_id = if is_secondary_indexed {
+                Some(connection.read_u64().expect("Cannot read index keyspace ID") as KeyspaceId)
+            } else {
+                None
+            };
             let column_name_length = connection.read_u32().expect("Cannot read column value length");
             let column_name_bytes = connection.read_n(column_name_length as usize).expect("Cannot read column value bytes");
             let column_name_string = String::from_utf8(column_name_bytes)
@@ -139,7 +240,7 @@ impl Response {
             vec.push(Column {
                 column_type: ColumnType::deserialize(column_type),
                 column_name: column_name_string,
-                is_indexed,
+                secondary_index_keyspace_id,
                 is_primary,
                 column_id,
             });
@@ -148,12 +249,50 @@ impl Response {
         vec
     }
 
-    fn deserialize_query_response(connection: &mut Connection) -> RowsResponse {
+    //Chunks of rows are reassembled into a single response
+    fn deserialize_query_response(connection: &mut Connection) -> Response {
         let columns_desc = Self::deserialize_column_dec(connection);
-        let n_rows = connection.read_u32().expect("Cannot read Nº rows");
-        let mut rows = Vec::with_capacity(n_rows as usize);
+        let mut rows = Vec::new();
+
+        loop {
+            match connection.read_u32().expect("Cannot read Nº rows of chunk") {
+                END_OF_ROWS_MARKER => break,
+                ROWS_ERROR_MARKER => {
+                    let error_type_id = connection.read_u8().expect("Cannot read response error type ID");
+                    return Response::Error(error_type_id, Self::deserialize_error_message(connection));
+                },
+                n_rows => if let Err(error) = Self::deserialize_rows_chunk(connection, n_rows, &mut rows) {
+                    return Response::Error(error.serialize(), format!("{:?}", error));
+                },
+            }
+        }
+
+        Response::Statement(StatementResponse::Rows(RowsResponse {
+            columns_desc,
+            rows
+        }))
+    }
 
+    //The rest of the response can't be read after a row with an unknown format version
+    fn deserialize_rows_chunk(
+        connection: &mut Connection,
+        n_rows: u32,
+        rows: &mut Vec<Row>
+    ) -> Result<(), SimpleDbError> {
         for _ in 0..n_rows {
+            let row_format_version = connection.read_u8().expect("Cannot read row format version");
+            match row_format_version {
+                ROW_FORMAT_V1 => {},
+                //The selected columns are already described by the columns of the response
+                ROW_FORMAT_V2 => {
+                    let n_selected_columns = connection.read_u16().expect("Cannot read Nº selected columns");
+                    if n_selected_columns != EVERY_COLUMN_SELECTED {
+                        connection.read_n(n_selected_columns as usize * 2).expect("Cannot read selected columns");
+                    }
+                },
+                _ => return Err(SimpleDbError::UnknownRowFormatVersion(row_format_version)),
+            }
+
             let n_columns = connection.read_u32().expect("Cannto read Nº Columns");
             let mut columns = HashMap::new();
 
@@ -169,10 +308,22 @@ impl Response {
             rows.push(Row { columns });
         }
 
-        RowsResponse {
-            columns_desc,
-            rows
+        Ok(())
+    }
+
+    fn deserialize_transaction_status(connection: &mut Connection) -> Option<(u64, Vec<u64>)> {
+        if connection.read_u8().expect("Cannot read transaction status") == 0 {
+            return None;
+        }
+
+        let txn_id = connection.read_u64().expect("Cannot read transaction ID");
+        let n_active_transactions = connection.read_u32().expect("Cannot read Nº active transactions");
+        let mut active_transactions = Vec::with_capacity(n_active_transactions as usize);
+        for _ in 0..n_active_transactions {
+            active_transactions.push(connection.read_u64().expect("Cannot read active transaction ID"));
         }
+
+        Some((txn_id, active_transactions))
     }
 
     fn deserialize_string_vec(connection: &mut Connection) -> Vec<String> {
@@ -221,8 +372,8 @@ impl ColumnType {
             ColumnType::I32 => utils::bytes_to_i32_le(value).to_string(),
             ColumnType::U64 => utils::bytes_to_u64_le(value).to_string(),
             ColumnType::I64 => utils::bytes_to_i64_le(value).to_string(),
-            ColumnType::F32 => format!("{:.2}", utils::bytes_to_f32_le(value)).to_string(),
-            ColumnType::F64 => format!("{:.2}", utils::bytes_to_f64_le(value)).to_string(),
+            ColumnType::F32 => utils::f32_to_string(utils::bytes_to_f32_le(value)),
+            ColumnType::F64 => utils::f64_to_string(utils::bytes_to_f64_le(value)),
             ColumnType::Boolean => if value[0] == 0x00 { String::from("false") } else { String::from("true") }
             ColumnType::String => String::from_utf8(value.to_vec()).unwrap(),
             ColumnType::Date => todo!(),
@@ -1,41 +1,55 @@
-use crate::request::Request;
-use crate::response::{Column, IndexType, RowsResponse, Response, StatementResponse};
-use crate::simpledb_server::SimpleDbServer;
+use crate::request::{Authentication, Request};
+use crate::response::{Column, IndexState, IndexType, RowsResponse, Response, StatementResponse, TransactionDesc};
+use crate::simpledb_server::{SimpleDbServer, TlsOptions};
 use crate::table_print::TablePrint;
 use std::cmp::Ordering;
 use std::io;
 use std::io::{stdout, Write};
 use std::process::exit;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use shared::ErrorTypeId;
 use crate::utils::duration_to_string;
 
 pub struct SimpleDbCli {
     server: SimpleDbServer,
-    password: String,
+    authentication: Authentication,
     is_standalone: bool,
+    null_string: String,
+    timeout_ms: u64, //0 if statements have no timeout
+    database: Option<String>, //Selected with USE. Displayed in the prompt
 }
 
 impl SimpleDbCli {
     pub fn create(
         address: String,
-        password: String,
+        authentication: Authentication,
+        tls_options: Option<TlsOptions>,
+        timeout_ms: u64,
     ) -> SimpleDbCli {
         SimpleDbCli {
-            server: SimpleDbServer::create(address),
+            server: SimpleDbServer::create(address, tls_options),
+            null_string: String::from("NULL"),
             is_standalone: true,
-            password
+            database: None,
+            timeout_ms,
+            authentication
         }
     }
 
     pub fn start(&mut self) -> ! {
         loop {
-            print!("simpledb> ");
+            match &self.database {
+                Some(database) => print!("simpledb ({})> ", database),
+                None => print!("simpledb> "),
+            };
             let _ = stdout().flush();
-            let input = self.read_input_from_user().to_lowercase();
+            let raw_input = self.read_input_from_user();
+            let input = raw_input.to_lowercase();
             let input = input.trim_end();
 
-            if input.starts_with("use") {
+            if input.starts_with("\\nullstring") {
+                self.null_string_command(raw_input.trim());
+            } else if input.starts_with("use") {
                 self.use_command(input);
             } else if input.eq("exit") {
                 self.exit_command();
@@ -51,7 +65,7 @@ impl SimpleDbCli {
         }
 
         let (response, duration) = self.server.send_request(Request::Statement(
-            self.password.clone(), self.is_standalone, statement.to_string()
+            self.authentication.clone(), self.is_standalone, self.timeout_ms, statement.to_string()
         ));
         self.print_response(response, duration);
 
@@ -71,6 +85,12 @@ impl SimpleDbCli {
                     StatementResponse::Explain(explain_lines) => self.print_explain_lines(explain_lines, duration),
                     StatementResponse::Describe(desc) => self.print_table_describe(&desc, duration),
                     StatementResponse::Indexes(indexes) => self.print_show_indexes(indexes, duration),
+                    StatementResponse::TransactionStatus(status) => self.print_transaction_status(status, duration),
+                    StatementResponse::Stats(stats) => self.print_stats(stats, duration),
+                    StatementResponse::Transactions(transactions) => self.print_transactions(transactions, duration),
+                    StatementResponse::Vacuumed(bytes_reclaimed) => println!("{} bytes reclaimed! ({})", bytes_reclaimed, duration_to_string(duration)),
+                    StatementResponse::CorruptedParts(corrupted_parts) if corrupted_parts.is_empty() => println!("No corrupted parts found! ({})", duration_to_string(duration)),
+                    StatementResponse::CorruptedParts(corrupted_parts) => self.print_vec_string_as_table("Corrupted parts", corrupted_parts, duration),
                 };
             }
             Response::Error(error_type_id, error_message) => {
@@ -84,33 +104,99 @@ impl SimpleDbCli {
         print!("\n");
     }
 
-    fn print_explain_lines(&self, lines: Vec<String>, duration: Duration) {
+    fn print_explain_lines(&self, lines: Vec<(String, Option<(u64, u64)>)>, duration: Duration) {
         let mut table = TablePrint::create(1);
         table.add_header("Step");
 
-        for line in lines {
+        for (mut line, stats) in lines {
+            if let Some((n_rows, elapsed_nanos)) = stats {
+                line.push_str(&format!(" (rows={} time={:.3}ms)", n_rows, elapsed_nanos as f64 / 1_000_000.0));
+            }
             table.add_column_value(line);
         }
 
         table.print(duration)
     }
 
-    fn print_show_indexes(&self, mut indexes: Vec<(String, IndexType)>, duration: Duration) {
-        let mut table = TablePrint::create(2);
+    fn print_show_indexes(&self, indexes: Vec<(String, IndexType, IndexState)>, duration: Duration) {
+        let mut table = TablePrint::create(3);
         table.add_header("Field");
         table.add_header("Type");
+        table.add_header("State");
 
-        for (index_column_name, index_type) in indexes {
+        for (index_column_name, index_type, index_state) in indexes {
             table.add_column_value(index_column_name);
             match index_type {
                 IndexType::Secondary => table.add_column_value("Secondary".to_string()),
                 IndexType::Primary => table.add_column_value("Primary".to_string())
             };
+            match index_state {
+                IndexState::Building => table.add_column_value("Building".to_string()),
+                IndexState::Ready => table.add_column_value("Ready".to_string())
+            };
         }
         
         table.print(duration);
     }
 
+    fn print_stats(&self, stats: Vec<(String, String)>, duration: Duration) {
+        let mut table = TablePrint::create(2);
+        table.add_header("Stat");
+        table.add_header("Value");
+
+        for (stat_name, stat_value) in stats {
+            table.add_column_value(stat_name);
+            table.add_column_value(stat_value);
+        }
+
+        table.print(duration);
+    }
+
+    fn print_transactions(&self, transactions: Vec<TransactionDesc>, duration: Duration) {
+        let mut table = TablePrint::create(5);
+        table.add_header("Transaction ID");
+        table.add_header("Isolation level");
+        table.add_header("State");
+        table.add_header("Running for");
+        table.add_header("Nº writes");
+
+        let now_ms = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+        for transaction in transactions {
+            table.add_column_value(transaction.txn_id.to_string());
+            table.add_column_value(transaction.isolation_level);
+            table.add_column_value(transaction.state);
+            table.add_column_value(match transaction.start_time_ms {
+                0 => String::new(),
+                start_time_ms => duration_to_string(Duration::from_millis(now_ms.saturating_sub(start_time_ms))),
+            });
+            table.add_column_value(transaction.n_writes.to_string());
+        }
+
+        table.print(duration);
+    }
+
+    fn print_transaction_status(&self, status: Option<(u64, Vec<u64>)>, duration: Duration) {
+        let mut table = TablePrint::create(2);
+        table.add_header("Transaction ID");
+        table.add_header("Active transactions");
+
+        match status {
+            Some((txn_id, active_transactions)) => {
+                let active_transactions: Vec<String> = active_transactions.iter()
+                    .map(|txn_id| txn_id.to_string())
+                    .collect();
+                table.add_column_value(txn_id.to_string());
+                table.add_column_value(active_transactions.join(", "));
+            },
+            None => {
+                table.add_column_value(String::from("none"));
+                table.add_column_value(String::new());
+            }
+        }
+
+        table.print(duration);
+    }
+
     fn print_query_data(&self, query_data: RowsResponse, duration: Duration) {
         let mut columns_desc = query_data.columns_desc;
         columns_desc.sort_by(|a, b| {
@@ -123,6 +209,7 @@ impl SimpleDbCli {
             }
         });
         let mut query_data_table = TablePrint::create(columns_desc.len());
+        query_data_table.set_null_string(self.null_string.as_str());
 
         for current_column_desc in &columns_desc {
             query_data_table.add_header(current_column_desc.column_name.as_str());
@@ -130,11 +217,9 @@ impl SimpleDbCli {
 
         for row in &query_data.rows {
             for current_column_desc in columns_desc.iter() {
-                if let Some(column_value) = row.columns.get(&current_column_desc.column_id) {
-                    query_data_table.add_column_value(current_column_desc.column_type.bytes_to_string(column_value));
-                } else {
-                    query_data_table.add_column_value("N/A".to_string());
-                }
+                let column_value = row.columns.get(&current_column_desc.column_id)
+                    .map(|column_value| current_column_desc.column_type.bytes_to_string(column_value));
+                query_data_table.add_nullable_column_value(column_value);
             }
         }
 
@@ -162,13 +247,11 @@ impl SimpleDbCli {
                 table.add_column_value("False".to_string());
             }
 
-            if !column_desc.is_primary && column_desc.is_indexed {
-                table.add_column_value("True".to_string());
-            } else if column_desc.is_primary {
-                table.add_column_value("True (Primary key)".to_string());
-            } else if !column_desc.is_indexed {
-                table.add_column_value("False".to_string());
-            }
+            match column_desc.secondary_index_keyspace_id {
+                _ if column_desc.is_primary => table.add_column_value("True (Primary key)".to_string()),
+                Some(index_keyspace_id) => table.add_column_value(format!("True (Keyspace {})", index_keyspace_id)),
+                None => table.add_column_value("False".to_string()),
+            };
         }
 
         table.print(duration);
@@ -189,12 +272,18 @@ impl SimpleDbCli {
     }
 
     fn exit_command(&mut self) {
-        self.server.send_request(Request::Close(self.password.clone()));
+        self.server.send_request(Request::Close(self.authentication.clone()));
 
         println!("Bye");
         exit(0)
     }
 
+    //Usage: \nullstring <string>. If no string is given, null values will be displayed as an empty cell
+    fn null_string_command(&mut self, input: &str) {
+        self.null_string = input["\\nullstring".len()..].trim().to_string();
+        println!("Null values will be displayed as \"{}\"\n", self.null_string);
+    }
+
     fn use_command(&mut self, input: &str) {
         let statement_split_by_space: Vec<&str> = input.split_whitespace().collect();
 
@@ -207,9 +296,12 @@ impl SimpleDbCli {
     }
 
     fn connect_to_database(&mut self, database_name: String) {
-        let (response, duration) = self.server.send_request(Request::UseDatabase(
-            self.password.clone(), database_name.clone()
+        let (response, duration) = self.server.send_request(Request::Statement(
+            self.authentication.clone(), self.is_standalone, self.timeout_ms, format!("USE {};", database_name)
         ));
+        if !matches!(response, Response::Error(_, _)) {
+            self.database = Some(database_name);
+        }
         self.print_response(response, duration);
     }
 
@@ -241,6 +333,8 @@ impl SimpleDbCli {
             14 => print!("Invalid column type"),
             16 => print!("Database already exists"),
             17 => print!("Database not found"),
+            102 => print!("Access denied"),
+            108 => print!("Deadlock detected, the transaction has been rolledback"),
             _ => print!("Received error {} code from server", error_type_id)
         };
 
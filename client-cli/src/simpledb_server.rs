@@ -1,26 +1,43 @@
 use crate::request::Request;
 use crate::response::Response;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{ClientConfig, ClientConnection, DigitallySignedStruct, RootCertStore, SignatureScheme, StreamOwned};
 use shared::connection::Connection;
 use std::net::TcpStream;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 pub struct SimpleDbServer {
     connection: Connection,
 }
 
+//The server certificate is verified with the CA certificate. Verification should only be disabled for local development
+pub struct TlsOptions {
+    pub ca_cert_path: Option<String>,
+    pub verify_server: bool,
+}
+
 impl SimpleDbServer {
     pub fn create(
         address: String,
+        tls_options: Option<TlsOptions>,
     ) -> SimpleDbServer {
         println!("simpledb> Connecting to {}!", address);
 
-        match TcpStream::connect(address.clone()) {
-            Ok(stream) => {
-                println!("simpledb> Connected to {}!", address);
-                SimpleDbServer { connection: Connection::create(stream), }
-            },
+        let stream = match TcpStream::connect(address.clone()) {
+            Ok(stream) => stream,
             Err(_) => panic!("ERROR Cannot connect to {}. Make sure the server is running or the address is correct", address)
-        }
+        };
+        let connection = match tls_options {
+            Some(tls_options) => Self::create_tls_connection(&address, stream, tls_options),
+            None => Connection::create(stream),
+        };
+
+        println!("simpledb> Connected to {}!", address);
+        SimpleDbServer { connection }
     }
 
     pub fn send_request(&mut self, request: Request) -> (Response, Duration) {
@@ -33,4 +50,78 @@ impl SimpleDbServer {
 
         (response, duration)
     }
+
+    //The TLS session is established before sending any request, so the password is never sent in plain text
+    fn create_tls_connection(address: &String, stream: TcpStream, tls_options: TlsOptions) -> Connection {
+        let peer_address = stream.peer_addr().expect("Cannot get server address");
+        let crypto_provider = Arc::new(rustls::crypto::ring::default_provider());
+        let config_builder = ClientConfig::builder_with_provider(crypto_provider.clone())
+            .with_safe_default_protocol_versions()
+            .expect("Cannot create TLS configuration");
+
+        let tls_config = if tls_options.verify_server {
+            let ca_cert_path = tls_options.ca_cert_path.expect("ERROR A CA certificate is required to verify the server");
+            let mut root_store = RootCertStore::empty();
+            for ca_cert in CertificateDer::pem_file_iter(&ca_cert_path).expect("ERROR Cannot read CA certificate") {
+                root_store.add(ca_cert.expect("ERROR Invalid CA certificate")).expect("ERROR Invalid CA certificate");
+            }
+            config_builder.with_root_certificates(root_store).with_no_client_auth()
+        } else {
+            config_builder.dangerous()
+                .with_custom_certificate_verifier(Arc::new(NoServerVerification(crypto_provider)))
+                .with_no_client_auth()
+        };
+
+        let host = address.rsplit_once(':').map_or(address.as_str(), |(host, _)| host);
+        let server_name = ServerName::try_from(host.to_string()).expect("ERROR Invalid server name");
+        let tls_connection = ClientConnection::new(Arc::new(tls_config), server_name)
+            .expect("ERROR Cannot create TLS connection");
+        let mut tls_stream = StreamOwned::new(tls_connection, stream);
+        while tls_stream.conn.is_handshaking() {
+            if let Err(error) = tls_stream.conn.complete_io(&mut tls_stream.sock) {
+                panic!("ERROR TLS handshake with {} failed: {}", address, error);
+            }
+        }
+
+        Connection::create_with_stream(Box::new(tls_stream), peer_address)
+    }
+}
+
+//Accepts any server certificate. Handshake signatures are still checked
+#[derive(Debug)]
+struct NoServerVerification(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for NoServerVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
 }
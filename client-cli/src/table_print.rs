@@ -6,6 +6,7 @@ pub struct TablePrint {
     header: Vec<String>,
     rows: Vec<Vec<String>>,
     columns_width: Vec<usize>,
+    null_string: String, //Displayed instead of null values
 
     n_columns: usize,
 }
@@ -20,11 +21,26 @@ impl TablePrint {
         TablePrint {
             header: Vec::new(),
             rows: Vec::new(),
+            null_string: String::from("NULL"),
             columns_width,
             n_columns
         }
     }
 
+    pub fn set_null_string(&mut self, null_string: &str) {
+        self.null_string = null_string.to_string();
+    }
+
+    //Null values are displayed with the null string. Non-null values equal to the null string
+    //are displayed quoted, so they can be told apart from null values.
+    pub fn add_nullable_column_value(&mut self, value: Option<String>) {
+        match value {
+            Some(value) if value == self.null_string => self.add_column_value(format!("\"{}\"", value)),
+            Some(value) => self.add_column_value(value),
+            None => self.add_column_value(self.null_string.clone()),
+        }
+    }
+
     pub fn add_header(&mut self, header: &str) {
         let header = Self::format_cell(header);
 
@@ -129,4 +145,23 @@ impl TablePrint {
         value.insert(0, ' ');
         value
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use crate::table_print::TablePrint;
+
+    #[test]
+    fn add_nullable_column_value() {
+        let mut table = TablePrint::create(3);
+        table.set_null_string("Null");
+        table.add_nullable_column_value(None);
+        table.add_nullable_column_value(Some(String::from("Null")));
+        table.add_nullable_column_value(Some(String::from("jaime")));
+
+        assert_eq!(table.rows[0][0], " Null ");
+        assert_eq!(table.rows[0][1], " \"Null\" ");
+        assert_eq!(table.rows[0][2], " jaime ");
+        assert_ne!(table.rows[0][0], table.rows[0][1]);
+    }
+}
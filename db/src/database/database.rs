@@ -1,11 +1,19 @@
 use crate::database::database_descriptor::DatabaseDescriptor;
 use crate::sql::parser::statement::CreateTableStatement;
+use crate::sql::parser::expression::Expression;
+use crate::table::schema::ColumnDefinition;
 use crate::table::table::Table;
+use crate::table::table_flags::KEYSPACE_TABLE_INDEX;
 use crossbeam_skiplist::SkipMap;
 use shared::SimpleDbError::{CannotCreateDatabaseFolder, TableAlreadyExists};
-use shared::{utils, SimpleDbError, SimpleDbOptions, Type};
+use shared::logger::logger;
+use shared::logger::SimpleDbLayer::DB;
+use shared::{utils, FlagMethods, KeyspaceId, SimpleDbError, SimpleDbOptions, TxnId};
+use std::fs;
+use std::path::Path;
 use std::sync::{Arc, LockResult, Mutex, RwLock, RwLockWriteGuard};
 use storage::transactions::transaction::Transaction;
+use storage::transactions::transaction_manager::{IsolationLevel, TransactionDesc};
 use storage::Storage;
 
 pub struct Database {
@@ -17,7 +25,7 @@ pub struct Database {
     options: Arc<SimpleDbOptions>,
 
     //See self::lock_rollbacks() method docks
-    rollback_lock: RwLock<()>,
+    pub(crate) rollback_lock: RwLock<()>,
 }
 
 impl Database {
@@ -29,14 +37,18 @@ impl Database {
         utils::create_paths(&options.base_path)
             .map_err(|e| CannotCreateDatabaseFolder(database_name.to_string(), e))?;
 
-        Ok(Arc::new(Database {
+        let database = Arc::new(Database {
             database_descriptor: Mutex::new(DatabaseDescriptor::create(options, &database_name.to_string())?),
             storage: Arc::new(Storage::create(options.clone())?),
             rollback_lock: RwLock::new(()),
             name: database_name.to_string(),
             options: options.clone(),
             tables: SkipMap::new(),
-        }))
+        });
+
+        Self::listen_transaction_aborts(&database);
+
+        Ok(database)
     }
 
     pub(crate) fn load_database(
@@ -59,10 +71,61 @@ impl Database {
         });
 
         database.set_tables(Table::load_tables(database_options, &storage, database.clone())?);
+        database.delete_orphan_index_keyspaces()?;
+        Self::listen_transaction_aborts(&database);
 
         Ok(database)
     }
 
+    //The keyspace of an index is created before the index is added to the table descriptor. If the database
+    //crashes while the index is being built, the keyspace won't be referenced by any table
+    fn delete_orphan_index_keyspaces(&self) -> Result<(), SimpleDbError> {
+        let index_keyspaces_id: Vec<KeyspaceId> = self.get_tables().iter()
+            .flat_map(|table| table.secondary_indexes.get_keyspaces_id())
+            .collect();
+
+        for keyspace_id in self.storage.get_keyspaces_id() {
+            if self.storage.get_flags(keyspace_id)?.has(KEYSPACE_TABLE_INDEX) && !index_keyspaces_id.contains(&keyspace_id) {
+                logger().info(DB(self.name.clone()), &format!(
+                    "Deleting index keyspace {} not used by any table", keyspace_id
+                ));
+                self.storage.delete_keyspace(keyspace_id)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    //Transactions aborted by the storage might never be rolledback by their clients, so their unique
+    //values written are discarded when they are aborted
+    fn listen_transaction_aborts(database: &Arc<Database>) {
+        let storage = database.storage.clone();
+        let database = Arc::downgrade(database);
+        storage.set_transaction_abort_listener(Box::new(move |txn_id| {
+            if let Some(database) = database.upgrade() {
+                database.on_transaction_finished(txn_id, false);
+            }
+        }));
+    }
+
+    //Writes a consistent copy of the database into snapshot_path. Table descriptors are copied before the data,
+    //so tables created while the snapshot is running are not included
+    pub(crate) fn snapshot(&self, snapshot_path: &Path) -> Result<(), SimpleDbError> {
+        let to_snapshot_error = |e| SimpleDbError::CannotCreateSnapshot(snapshot_path.to_str().unwrap().to_string(), e);
+        fs::create_dir_all(snapshot_path).map_err(to_snapshot_error)?;
+
+        for file in fs::read_dir(&self.options.base_path).map_err(to_snapshot_error)? {
+            let file = file.map_err(to_snapshot_error)?;
+            let file_name = file.file_name();
+            let file_name = file_name.to_str().unwrap();
+            if file_name == "desc" || file_name.ends_with(".desc") {
+                fs::copy(file.path(), snapshot_path.join(file_name)).map_err(to_snapshot_error)?;
+            }
+        }
+
+        self.storage.snapshot(snapshot_path.to_str().unwrap())
+    }
+
     pub(crate) fn create_mock(options: &Arc<SimpleDbOptions>) -> Arc<Database> {
         Arc::new(Database {
             database_descriptor: Mutex::new(DatabaseDescriptor::mock()),
@@ -82,18 +145,26 @@ impl Database {
             return Err(TableAlreadyExists(statement.table_name.to_string()))
         }
 
+        Table::validate_identifier(&statement.table_name)?;
         Table::validate_new_columns(&statement.columns)?;
-        Ok(())
+        Table::validate_checks(&statement.columns, &statement.checks)?;
+        self.validate_n_columns(&statement.table_name, statement.columns.len())
     }
 
     pub fn create_table(
         self: &Arc<Self>,
         table_name: &str,
-        columns: Vec<(String, Type, bool)>,
+        columns: Vec<ColumnDefinition>,
+        checks: Vec<Expression>,
+        ttl_seconds: Option<u64>,
     ) -> Result<Arc<Table>, SimpleDbError> {
+        self.validate_n_columns(table_name, columns.len())?;
+
         let table = Table::create(
             table_name,
             columns,
+            checks,
+            ttl_seconds,
             &self.options,
             &self.storage,
             self.clone()
@@ -108,15 +179,93 @@ impl Database {
         Ok(table)
     }
 
+    //Tables can only be dropped when no active transaction has used them, so no transaction will end up referencing
+    //the deleted keyspaces. Once dropped, the table can't be used by new statements
+    pub fn drop_table(&self, table_name: &str) -> Result<(), SimpleDbError> {
+        let table = self.get_table_or_err(table_name)?;
+        table.mark_as_dropped(&self.get_active_transactions())?;
+        self.tables.remove(table_name);
+
+        let mut lock_result = self.database_descriptor.lock();
+        let database_descriptor = lock_result.as_mut().unwrap();
+        database_descriptor.remove_table(table_name)?;
+
+        table.delete_files()
+    }
+
+    //Tables can only be truncated when no active transaction has uncommitted writes on them
+    pub fn truncate_table(&self, table_name: &str) -> Result<(), SimpleDbError> {
+        self.get_table_or_err(table_name)?.truncate()
+    }
+
+    //Vacuums the table, or all the tables of the database if table_name is None. Returns the nº of bytes reclaimed
+    pub fn vacuum(&self, table_name: Option<&str>) -> Result<usize, SimpleDbError> {
+        let tables = match table_name {
+            Some(table_name) => vec![self.get_table_or_err(table_name)?],
+            None => self.get_tables(),
+        };
+
+        let mut bytes_reclaimed = 0;
+        for table in tables {
+            bytes_reclaimed += table.vacuum()?;
+        }
+
+        Ok(bytes_reclaimed)
+    }
+
+    //Returns the corrupted parts found in the SSTables of the database
+    pub fn check_integrity(&self) -> Vec<SimpleDbError> {
+        self.storage.check_integrity()
+    }
+
     pub fn add_column(
         &self,
         table_name: &str,
-        columns_to_add: Vec<(String, Type, bool)>
+        columns_to_add: Vec<ColumnDefinition>
     ) -> Result<(), SimpleDbError> {
         let table = self.get_table_or_err(table_name)?;
+        self.validate_n_columns(table_name, table.get_schema().get_columns().len() + columns_to_add.len())?;
         table.add_columns(columns_to_add)
     }
 
+    pub fn drop_column(
+        &self,
+        table_name: &str,
+        column_name: &str
+    ) -> Result<(), SimpleDbError> {
+        let table = self.get_table_or_err(table_name)?;
+        table.drop_column(column_name)
+    }
+
+    pub fn rename_column(
+        &self,
+        table_name: &str,
+        old_column_name: &str,
+        new_column_name: &str
+    ) -> Result<(), SimpleDbError> {
+        let table = self.get_table_or_err(table_name)?;
+        table.rename_column(old_column_name, new_column_name)
+    }
+
+    fn validate_n_columns(&self, table_name: &str, n_columns: usize) -> Result<(), SimpleDbError> {
+        if n_columns > self.options.max_columns_per_table {
+            return Err(SimpleDbError::TooManyColumns(table_name.to_string(), self.options.max_columns_per_table));
+        }
+
+        Ok(())
+    }
+
+    //Like get_table_or_err, but the table can't be dropped until the transaction finishes
+    pub fn get_table_for_transaction(
+        &self,
+        table_name: &str,
+        transaction: &Transaction
+    ) -> Result<Arc<Table>, SimpleDbError> {
+        let table = self.get_table_or_err(table_name)?;
+        table.use_in_transaction(transaction)?;
+        Ok(table)
+    }
+
     pub fn get_table_or_err(&self, table_name: &str) -> Result<Arc<Table>, SimpleDbError> {
         self.tables.get(table_name)
             .map(|entry| entry.value().clone())
@@ -127,13 +276,63 @@ impl Database {
         self.storage.start_transaction()
     }
 
+    pub fn start_transaction_with_isolation(&self, isolation_level: IsolationLevel) -> Transaction {
+        self.storage.start_transaction_with_isolation(isolation_level)
+    }
+
     pub fn rollback_transaction(&self, transaction: &Transaction) -> Result<(), SimpleDbError> {
         let lock = self.rollback_lock.read();
-        self.storage.rollback_transaction(transaction)
+        self.storage.rollback_transaction(transaction)?;
+        self.on_transaction_finished(transaction.id(), false);
+        Ok(())
     }
 
     pub fn commit_transaction(&self, transaction: &Transaction) -> Result<(), SimpleDbError> {
-        self.storage.commit_transaction(transaction)
+        for table in self.tables.iter() {
+            table.value().select_cache.on_commit(transaction.id());
+        }
+
+        match self.storage.commit_transaction(transaction) {
+            Ok(_) => self.on_transaction_finished(transaction.id(), true),
+            //Rolledback by the storage
+            Err(error @ SimpleDbError::TransactionAborted(_)) => {
+                self.on_transaction_finished(transaction.id(), false);
+                return Err(error);
+            },
+            Err(error) => return Err(error),
+        };
+        Ok(())
+    }
+
+    pub fn create_savepoint(&self, transaction: &Transaction, name: &str) {
+        self.storage.create_savepoint(transaction, name)
+    }
+
+    pub fn rollback_to_savepoint(&self, transaction: &Transaction, name: &str) -> Result<(), SimpleDbError> {
+        self.storage.rollback_to_savepoint(transaction, name)
+    }
+
+    pub fn release_savepoint(&self, transaction: &Transaction, name: &str) -> Result<(), SimpleDbError> {
+        self.storage.release_savepoint(transaction, name)
+    }
+
+    fn on_transaction_finished(&self, txn_id: TxnId, committed: bool) {
+        let active_transactions = self.get_active_transactions();
+        for table in self.tables.iter() {
+            table.value().on_transaction_finished(txn_id, committed, &active_transactions);
+        }
+    }
+
+    pub fn get_active_transactions(&self) -> Vec<TxnId> {
+        self.storage.get_active_transactions()
+    }
+
+    pub fn describe_transaction(&self, transaction: &Transaction) -> TransactionDesc {
+        self.storage.describe_transaction(transaction)
+    }
+
+    pub fn describe_active_transactions(&self) -> Vec<TransactionDesc> {
+        self.storage.describe_active_transactions()
     }
 
     pub fn name(&self) -> &String {
@@ -170,3 +369,166 @@ impl Database {
         self.rollback_lock.write()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::IndexState;
+    use crate::simple_db::{Context, StatementResult};
+    use crate::table::table_flags::KEYSPACE_TABLE_INDEX;
+    use crate::test_utils::TestSimpleDb;
+    use shared::{SimpleDbError, Type};
+
+    #[test]
+    fn drop_table() {
+        let simple_db = TestSimpleDb::create("drop_table");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR);");
+        simple_db.execute_sql(&context, "CREATE INDEX ON personas (nombre);");
+
+        simple_db.execute_sql(&context, "CREATE TABLE coches (id I64 PRIMARY KEY);");
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        simple_db.execute_sql(&transaction.context(), "INSERT INTO personas (id, nombre) VALUES (1, \"Jaime\");");
+        assert!(matches!(simple_db.execute(&context, simple_db.parse("DROP TABLE personas;").unwrap()), Err(SimpleDbError::TableInUse(_))));
+        transaction.commit().unwrap();
+
+        //Transactions that have only read the table also prevent it from being dropped
+        let mut transaction = simple_db.begin(&context).unwrap();
+        simple_db.execute_sql(&transaction.context(), "SELECT * FROM personas;");
+        assert!(matches!(simple_db.execute(&context, simple_db.parse("DROP TABLE personas;").unwrap()), Err(SimpleDbError::TableInUse(_))));
+        transaction.commit().unwrap();
+
+        //Transactions using other tables don't
+        let mut other_transaction = simple_db.begin(&context).unwrap();
+        simple_db.execute_sql(&other_transaction.context(), "INSERT INTO coches (id) VALUES (1);");
+        simple_db.execute_sql(&context, "DROP TABLE personas;");
+        assert!(matches!(simple_db.execute(&other_transaction.context(), simple_db.parse("SELECT * FROM personas;").unwrap()), Err(SimpleDbError::TableNotFound(_))));
+        other_transaction.commit().unwrap();
+        simple_db.execute_sql(&context, "DROP TABLE coches;");
+
+        let database = simple_db.get_databases().get_database("test").unwrap();
+        assert!(database.get_tables().is_empty());
+        //No keyspace directories should be left
+        let keyspaces_directories = std::fs::read_dir(simple_db.path.join("test")).unwrap()
+            .filter(|entry| entry.as_ref().unwrap().file_name().to_str().unwrap().parse::<usize>().is_ok())
+            .count();
+        assert_eq!(keyspaces_directories, 0);
+        assert!(matches!(simple_db.execute(&context, simple_db.parse("DROP TABLE personas;").unwrap()), Err(SimpleDbError::TableNotFound(_))));
+
+        //Table can be created again
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY);");
+    }
+
+    #[test]
+    fn truncate_table() {
+        let simple_db = TestSimpleDb::create("truncate_table");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR);");
+        simple_db.execute_sql(&context, "CREATE INDEX ON personas (nombre);");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, nombre) VALUES (1, \"Jaime\");");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, nombre) VALUES (2, \"Pedro\");");
+
+        simple_db.execute_sql(&context, "CREATE TABLE mascotas (id I64 PRIMARY KEY);");
+
+        //Uncommitted writes on the table would be lost
+        let mut writer = simple_db.begin(&context).unwrap();
+        simple_db.execute_sql(&writer.context(), "INSERT INTO personas (id, nombre) VALUES (3, \"Wili\");");
+        assert!(matches!(simple_db.execute(&context, simple_db.parse("TRUNCATE TABLE personas;").unwrap()), Err(SimpleDbError::TableInUse(_))));
+        writer.rollback().unwrap();
+
+        //Readers of the table and writers of other tables don't prevent it
+        let mut reader = simple_db.begin(&context).unwrap();
+        simple_db.execute_sql(&reader.context(), "SELECT * FROM personas;").data().all().unwrap();
+        let mut other_table_writer = simple_db.begin(&context).unwrap();
+        simple_db.execute_sql(&other_table_writer.context(), "INSERT INTO mascotas (id) VALUES (1);");
+        simple_db.execute_sql(&context, "TRUNCATE TABLE personas;");
+        reader.commit().unwrap();
+        other_table_writer.commit().unwrap();
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        let rows = simple_db.execute_sql(&transaction.context(), "SELECT * FROM personas;").data().all().unwrap();
+        assert!(rows.is_empty());
+        let rows = simple_db.execute_sql(&transaction.context(), "SELECT * FROM personas WHERE nombre == \"Jaime\";").data().all().unwrap();
+        assert!(rows.is_empty());
+        transaction.commit().unwrap();
+
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, nombre) VALUES (3, \"Jaime\");");
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        let rows = simple_db.execute_sql(&transaction.context(), "SELECT * FROM personas;").data().all().unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].get_column_value("id").unwrap().get_i64().unwrap(), 3);
+        let rows = simple_db.execute_sql(&transaction.context(), "SELECT * FROM personas WHERE nombre == \"Jaime\";").data().all().unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].get_column_value("id").unwrap().get_i64().unwrap(), 3);
+        transaction.commit().unwrap();
+    }
+
+    #[test]
+    fn orphan_index_keyspace_deleted_on_load() {
+        let mut simple_db = TestSimpleDb::create("orphan_index_keyspace_deleted_on_load");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64);");
+        simple_db.execute_sql(&context, "CREATE INDEX ON personas (dinero);");
+        let table = simple_db.get_databases().get_database_or_err("test").unwrap().get_table_or_err("personas").unwrap();
+        let keyspaces_id = table.storage.get_keyspaces_id();
+        //Simulates a crash while an index was being built: its keyspace exists, but no table references it
+        table.storage.create_keyspace(KEYSPACE_TABLE_INDEX, Type::I64).unwrap();
+        drop(table);
+        simple_db.stop();
+        simple_db.start();
+        let table = simple_db.get_databases().get_database_or_err("test").unwrap().get_table_or_err("personas").unwrap();
+        assert_eq!(table.storage.get_keyspaces_id(), keyspaces_id);
+        let indexes = match simple_db.execute_sql(&context, "SHOW INDEX FROM personas;") {
+            StatementResult::Indexes(indexes) => indexes,
+            _ => panic!()
+        };
+        assert!(indexes.iter().any(|(column, _, state)| column == "dinero" && *state == IndexState::Ready));
+    }
+
+    #[test]
+    fn vacuum() {
+        let simple_db = TestSimpleDb::create_with_options("vacuum", {
+            let mut options_builder = shared::start_simpledb_options_builder();
+            options_builder.memtable_max_size_bytes(1024);
+            options_builder.max_memtables_inactive(1);
+            //Compactions would also remove the deleted rows
+            options_builder.compaction_task_frequency_ms(usize::MAX / 2);
+            options_builder
+        });
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64);");
+        for first_id in (0..500).step_by(25) {
+            let values: Vec<String> = (first_id..first_id + 25).map(|id| format!("({}, {})", id, id)).collect();
+            simple_db.execute_in_transaction(&context, &format!("INSERT INTO personas (id, dinero) VALUES {};", values.join(", ")));
+        }
+        let mut reader_transaction = simple_db.begin(&context).unwrap();
+        for first_id in (0..400).step_by(25) {
+            simple_db.execute_in_transaction(&context, &format!("DELETE FROM personas WHERE id >= {} AND id < {};", first_id, first_id + 25));
+        }
+
+        let table = simple_db.get_databases().get_database_or_err("test").unwrap().get_table_or_err("personas").unwrap();
+        let vacuum = || match simple_db.execute_sql(&context, "VACUUM TABLE personas;") {
+            StatementResult::Vacuumed(bytes_reclaimed) => bytes_reclaimed,
+            _ => panic!()
+        };
+        let n_rows = |context: &Context| simple_db.execute_sql(context, "SELECT * FROM personas;")
+            .data().all().unwrap().len();
+
+        //The deleted rows are still visible by the reader transaction
+        vacuum();
+        assert_eq!(n_rows(&reader_transaction.context()), 500);
+        reader_transaction.commit().unwrap();
+
+        let size_before = table.storage.get_size_bytes(table.storage_keyspace_id).unwrap();
+        let bytes_reclaimed = vacuum();
+        let size_after = table.storage.get_size_bytes(table.storage_keyspace_id).unwrap();
+
+        assert!(bytes_reclaimed > 0);
+        assert!(size_after < size_before);
+        assert_eq!(size_before - size_after, bytes_reclaimed);
+        let mut transaction = simple_db.begin(&context).unwrap();
+        assert_eq!(n_rows(&transaction.context()), 100);
+        transaction.commit().unwrap();
+    }
+}
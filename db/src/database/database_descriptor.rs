@@ -66,6 +66,19 @@ impl DatabaseDescriptor {
         Ok(())
     }
 
+    //The file is rewritten with the remaining tables
+    pub fn remove_table(&mut self, table_name: &str) -> Result<(), SimpleDbError> {
+        self.keyspace_id_by_table_name.remove(table_name);
+
+        let mut bytes = Vec::new();
+        for entry in self.keyspace_id_by_table_name.iter() {
+            bytes.extend(self.serialize_new_table_entry(entry.key(), *entry.value()));
+        }
+
+        self.file.safe_replace(&bytes)
+            .map_err(|e| CannotWriteDatabaseDescriptor(e))
+    }
+
     fn serialize_new_table_entry(&self, table_name: &str, keyspace_id: KeyspaceId) -> Vec<u8> {
         let mut serialized = Vec::new();
         serialized.put_u32_le(table_name.len() as u32);
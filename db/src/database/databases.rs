@@ -20,6 +20,7 @@ impl Databases {
     ) -> Result<Databases, SimpleDbError> {
         let options = shared::start_simpledb_options_builder_from(&options)
             .storage_value_merger(|prev, new, flag, key_type| Self::merge_storage_tables(prev, new, flag, key_type))
+            .storage_value_insert_time(|value, flag| Self::get_storage_value_insert_time(value, flag))
             .build_arc();
 
         let mut databases = Self::load_databases(&options)?;
@@ -30,6 +31,34 @@ impl Databases {
         })
     }
 
+    //Copies the snapshot files into options' base path, which is expected to be empty
+    pub fn restore_snapshot(snapshot_path: &str, options: &Arc<SimpleDbOptions>) -> Result<(), SimpleDbError> {
+        let to_restore_error = |e| SimpleDbError::CannotRestoreSnapshot(snapshot_path.to_string(), e);
+        let base_path = Path::new(&options.base_path);
+        let is_base_path_empty = fs::read_dir(base_path)
+            .map_or(true, |mut files| files.next().is_none());
+        if !is_base_path_empty {
+            return Err(to_restore_error(std::io::Error::new(std::io::ErrorKind::AlreadyExists, "Base path is not empty")));
+        }
+
+        shared::utils::copy_dir_all(Path::new(snapshot_path), base_path).map_err(to_restore_error)
+    }
+
+    //Each database is written to a subdirectory of snapshot_path with its name
+    pub fn snapshot(&self, snapshot_path: &str) -> Result<(), SimpleDbError> {
+        for database in self.get_databases() {
+            database.snapshot(&Path::new(snapshot_path).join(database.name()))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn check_integrity(&self) -> Vec<SimpleDbError> {
+        self.get_databases().iter()
+            .flat_map(|database| database.check_integrity())
+            .collect()
+    }
+
     pub fn get_databases(&self) -> Vec<Arc<Database>> {
         let mut databases = Vec::new();
         for entry in self.databases.iter() {
@@ -103,6 +132,15 @@ impl Databases {
         Ok(databases)
     }
 
+    //Used by storage to remove expired rows of tables with TTL in compaction
+    fn get_storage_value_insert_time(value: &Bytes, flag: Flag) -> Option<u64> {
+        if flag.has(KEYSPACE_TABLE_USER) {
+            Record::deserialize_insert_time(value)
+        } else {
+            None
+        }
+    }
+
     fn merge_storage_tables(prev: &Bytes, new: &Bytes, flag: Flag, key_type: Type) -> StorageValueMergeResult {
         if flag.has(KEYSPACE_TABLE_USER) {
             let tombstone = Bytes::new();
@@ -0,0 +1,110 @@
+use crate::table::record::Record;
+use crate::Column;
+use bytes::{BufMut, Bytes};
+use shared::{SimpleDbError, Value};
+
+const NULL_TAG: u8 = 0x00;
+const NOT_NULL_TAG: u8 = 0x01;
+
+//Encodes the values of a composite index into one key. Comparing two keys byte by byte gives the same
+//result as comparing their values column by column, so a prefix of the columns can be scanned as a range.
+//Each value is prefixed by a tag, so null values are sorted first:
+// - Signed integers: big endian with the sign bit flipped
+// - Unsigned integers: big endian
+// - Floats: big endian, with all the bits flipped if negative, otherwise only the sign bit
+// - Others (strings, blobs, dates): 0x00 bytes are escaped as 0x00 0xFF, terminated by 0x00 0x00
+//Values are expected to have the type of its column
+pub fn encode(values: &Vec<Value>) -> Result<Bytes, SimpleDbError> {
+    let mut encoded = Vec::new();
+
+    for value in values {
+        if value.is_null() {
+            encoded.put_u8(NULL_TAG);
+            continue;
+        }
+
+        encoded.put_u8(NOT_NULL_TAG);
+
+        if value.get_type().is_unsigned_integer_number() {
+            encoded.put_u64(value.get_u64()?);
+        } else if value.is_integer_number() || value.is_boolean() {
+            encoded.put_u64((value.get_i64()? as u64) ^ (1 << 63));
+        } else if value.is_fp_number() {
+            let bits = value.get_f64()?.to_bits();
+            encoded.put_u64(if bits >> 63 == 1 { !bits } else { bits ^ (1 << 63) });
+        } else {
+            for byte in value.get_bytes().iter() {
+                encoded.put_u8(*byte);
+                if *byte == 0x00 {
+                    encoded.put_u8(0xFF);
+                }
+            }
+            encoded.put_u16(0x0000);
+        }
+    }
+
+    Ok(Bytes::from(encoded))
+}
+
+//Missing columns in the record will be encoded as null
+pub fn encode_record(columns: &Vec<Column>, record: &Record) -> Result<Bytes, SimpleDbError> {
+    let mut values = Vec::with_capacity(columns.len());
+
+    for column in columns {
+        values.push(match record.get_column_bytes(column.column_id) {
+            Some(bytes) => Value::create(bytes.clone(), column.column_type)?,
+            None => Value::create_null(),
+        });
+    }
+
+    encode(&values)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::index::composite_key::encode;
+    use bytes::Bytes;
+    use shared::{Type, Value};
+
+    #[test]
+    fn encoded_order() {
+        let sorted = vec![
+            vec![Value::create_null(), Value::create_i64(1)],
+            vec![Value::create_string(String::from("a")), Value::create_i64(-5)],
+            vec![Value::create_string(String::from("a")), Value::create_i64(3)],
+            vec![Value::create_string(String::from("a\0")), Value::create_i64(1)],
+            vec![Value::create_string(String::from("ab")), Value::create_null()],
+            vec![Value::create_string(String::from("ab")), Value::create_i64(-1)],
+            vec![Value::create_string(String::from("b")), Value::create_i64(0)],
+        ];
+
+        for index in 1..sorted.len() {
+            assert!(encode(&sorted[index - 1]).unwrap() < encode(&sorted[index]).unwrap());
+        }
+
+        assert!(encode(&vec![Value::create_f64(-2.5)]).unwrap() < encode(&vec![Value::create_f64(-1.0)]).unwrap());
+        assert!(encode(&vec![Value::create_f64(-1.0)]).unwrap() < encode(&vec![Value::create_f64(0.5)]).unwrap());
+    }
+
+    #[test]
+    fn encoded_order_unsigned() {
+        let u64_value = |value: u64| Value::create(Bytes::from(value.to_le_bytes().to_vec()), Type::U64).unwrap();
+        let u32_value = |value: u32| Value::create(Bytes::from(value.to_le_bytes().to_vec()), Type::U32).unwrap();
+
+        //Values above i64::MAX and i32::MAX go after the smaller ones
+        assert!(encode(&vec![u64_value(1)]).unwrap() < encode(&vec![u64_value(i64::MAX as u64)]).unwrap());
+        assert!(encode(&vec![u64_value(i64::MAX as u64)]).unwrap() < encode(&vec![u64_value(i64::MAX as u64 + 1)]).unwrap());
+        assert!(encode(&vec![u64_value(i64::MAX as u64 + 1)]).unwrap() < encode(&vec![u64_value(u64::MAX)]).unwrap());
+        assert!(encode(&vec![u32_value(1)]).unwrap() < encode(&vec![u32_value(u32::MAX)]).unwrap());
+    }
+
+    #[test]
+    fn prefix() {
+        let prefix = encode(&vec![Value::create_string(String::from("a"))]).unwrap();
+        let key = encode(&vec![Value::create_string(String::from("a")), Value::create_i64(1)]).unwrap();
+        let other_key = encode(&vec![Value::create_string(String::from("ab")), Value::create_i64(1)]).unwrap();
+
+        assert!(key.starts_with(&prefix));
+        assert!(!other_key.starts_with(&prefix));
+    }
+}
@@ -1,4 +1,5 @@
 use crate::database::database::Database;
+use crate::index::composite_key;
 use crate::index::posting_list::PostingList;
 use crate::table::record::Record;
 use crate::table::table::Table;
@@ -8,11 +9,44 @@ use shared::iterators::storage_iterator::StorageIterator;
 use shared::logger::logger;
 use shared::logger::SimpleDbLayer::DB;
 use shared::{KeyspaceId, SimpleDbError, Value};
-use std::sync::mpsc::{Receiver, Sender};
+use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::mpsc::{Receiver, SyncSender};
 use std::sync::{mpsc, Arc};
 use storage::transactions::transaction::Transaction;
 use storage::Storage;
 
+//A progress message is sent every this nº of rows processed
+const PROGRESS_N_ROWS: usize = 1024;
+//Progress messages that haven't been received yet. Further progress messages are discarded
+const MAX_PENDING_MESSAGES: usize = 16;
+
+pub enum IndexCreationMessage {
+    Progress(IndexCreationProgress),
+    Finished(Result<usize, SimpleDbError>), //Nº of rows indexed
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct IndexCreationProgress {
+    pub n_rows_processed: usize,
+    pub n_rows_estimated: Option<usize>, //None if the nº of rows of the table is unknown
+}
+
+//Cancels the index creation. The half built index and its keyspace will be removed
+#[derive(Clone)]
+pub struct IndexCreationHandle {
+    cancelled: Arc<AtomicBool>,
+}
+
+//Returned when an index creation is started. The index is built in its own thread. The build never waits for
+//progress messages to be received, as it blocks rollbacks of the database while it runs
+pub struct IndexCreation {
+    pub handle: IndexCreationHandle,
+    pub receiver: Receiver<IndexCreationMessage>,
+    indexed_columns_names: String,
+}
+
 pub struct IndexCreationTask {
     table: Arc<Table>,
     database: Arc<Database>,
@@ -21,25 +55,53 @@ pub struct IndexCreationTask {
     table_keyspace_id: KeyspaceId,
     storage: Arc<Storage>,
 
-    secondary_indexed_column: Column,
+    //More than one column if the index is composite
+    secondary_indexed_columns: Vec<Column>,
+    unique: bool,
+
+    sender: SyncSender<IndexCreationMessage>,
+    cancelled: Arc<AtomicBool>,
+}
 
-    n_affected_rows_sender: Sender<Result<usize, SimpleDbError>>
+impl IndexCreationHandle {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Relaxed);
+    }
+}
+
+impl IndexCreation {
+    //Blocks until the index creation finishes. Progress messages are passed to on_progress
+    pub fn wait(&self, on_progress: &mut dyn FnMut(IndexCreationProgress)) -> Result<usize, SimpleDbError> {
+        loop {
+            //The sender is dropped without a Finished message if the build thread panics
+            let message = self.receiver.recv()
+                .map_err(|_| SimpleDbError::IndexCreationInterrupted(self.indexed_columns_names.clone()))?;
+            match message {
+                IndexCreationMessage::Progress(progress) => on_progress(progress),
+                IndexCreationMessage::Finished(result) => return result,
+            }
+        }
+    }
 }
 
 impl IndexCreationTask {
     pub fn create(
-        secondary_indexed_column: Column,
+        secondary_indexed_columns: Vec<Column>,
+        unique: bool,
         index_keyspace_id: KeyspaceId,
         table_keyspace_id: KeyspaceId,
         database: Arc<Database>,
         storage: Arc<Storage>,
         table: Arc<Table>,
-    ) -> (IndexCreationTask, Receiver<Result<usize, SimpleDbError>>) {
-        let (send, receiver) = mpsc::channel();
+    ) -> (IndexCreationTask, IndexCreation) {
+        let (sender, receiver) = mpsc::sync_channel(MAX_PENDING_MESSAGES);
+        let cancelled = Arc::new(AtomicBool::new(false));
 
         let index = IndexCreationTask {
-            secondary_indexed_column,
-            n_affected_rows_sender: send,
+            secondary_indexed_columns,
+            cancelled: cancelled.clone(),
+            sender,
+            unique,
             index_keyspace_id,
             table_keyspace_id,
             database,
@@ -47,53 +109,100 @@ impl IndexCreationTask {
             table,
         };
 
-        (index, receiver)
+        let indexed_columns_names = index.indexed_columns_names();
+        (index, IndexCreation { handle: IndexCreationHandle { cancelled }, receiver, indexed_columns_names })
     }
 
+    //If the index creation fails or gets cancelled, the half built index is removed
     pub fn start(&self) {
+        let result = self.index_rows()
+            .and_then(|n_affected_rows| {
+                self.table.on_index_created(&self.secondary_indexed_columns, self.index_keyspace_id)?;
+                Ok(n_affected_rows)
+            });
+
+        if let Err(error) = &result {
+            logger().error(DB(self.table.table_name.clone()), &format!(
+                "Cannot create secondary index for table {} on {}: {:?}", self.table.table_name, self.indexed_columns_names(), error
+            ));
+
+            let _ = self.table.on_index_creation_failed(&self.secondary_indexed_columns, self.index_keyspace_id);
+        }
+
+        //The receiver is dropped if nobody waits for the index creation. No lock is held at this point, so it is fine
+        //to wait until there is room for the message
+        let _ = self.sender.send(IndexCreationMessage::Finished(result));
+    }
+
+    fn index_rows(&self) -> Result<usize, SimpleDbError> {
+        let n_rows_estimated = self.table.estimate_n_rows()?;
+        let mut n_rows_processed = 0;
         let primary_column_type = &self.table.get_schema().get_primary_column().column_type;
-        let secondary_column_type = &self.secondary_indexed_column.column_type;
         let mut n_affected_rows = 0;
+        //Only used by unique indexes. Indexed value -> primary key
+        let mut indexed_values: HashMap<Bytes, Bytes> = HashMap::new();
 
         let mut iterator = self.storage.scan_all_with_transaction(
             &Transaction::none(),
             self.table_keyspace_id,
-        ).unwrap();
+        )?;
 
         logger().info(DB(self.table.table_name.clone()), &format!(
             "Creating secondary index for table {} Secondary index keyspace ID: {} Column indexed: {}",
-            self.table.table_name.clone(), self.index_keyspace_id, self.secondary_indexed_column.column_name
+            self.table.table_name.clone(), self.index_keyspace_id, self.indexed_columns_names()
         ));
 
         //This will get unlocked when it goes out of scope
         let lock = self.database.lock_rollbacks();
 
         while iterator.next() {
+            if self.cancelled.load(Relaxed) {
+                return Err(SimpleDbError::IndexCreationCancelled(self.indexed_columns_names()));
+            }
+            n_rows_processed += 1;
+            if n_rows_processed % PROGRESS_N_ROWS == 0 {
+                //Rollbacks are locked, so it can't wait for the receiver
+                let _ = self.sender.try_send(IndexCreationMessage::Progress(IndexCreationProgress { n_rows_processed, n_rows_estimated }));
+            }
+
             let primary_key = iterator.key();
             let mut record_bytes = iterator.value();
-            let mut record = Record::deserialize(&mut record_bytes);
-
-            if let Some(value_to_be_indexed) = record.remove_column(self.secondary_indexed_column.column_id) {
-                let posting_list = PostingList::crate_only_one_entry(primary_key);
-                n_affected_rows += 1;
-
-                logger().debug(DB(self.table.table_name.clone()), &format!(
-                    "Adding secondary index entry. Table: {} Primary key: {:?} Secondary key: {:?}",
-                    self.table.table_name,
-                    Value::create(primary_key.as_bytes().clone(), primary_column_type.clone()).unwrap().to_string(),
-                    Value::create(value_to_be_indexed.clone(), secondary_column_type.clone()).unwrap().to_string(),
-                ));
-
-                if let Err(error) = self.storage.set_with_transaction(
-                    self.index_keyspace_id,
-                    &Transaction::create(primary_key.txn_id()),
-                    value_to_be_indexed,
-                    &Bytes::from(posting_list.serialize())
-                ) {
-                    self.n_affected_rows_sender.send(Err(error)).unwrap();
-                    return;
+            let record = Record::deserialize(&mut record_bytes);
+
+            let value_to_be_indexed = match self.get_value_to_be_indexed(record) {
+                Ok(Some(value_to_be_indexed)) => value_to_be_indexed,
+                Ok(None) => continue,
+                Err(error) => return Err(error),
+            };
+
+            if self.unique {
+                let indexed_primary_key = indexed_values.entry(value_to_be_indexed.clone())
+                    .or_insert(primary_key.as_bytes().clone());
+                if !(*indexed_primary_key).eq(primary_key.as_bytes()) {
+                    return Err(SimpleDbError::UniqueConstraintViolation(self.indexed_columns_names()));
                 }
             }
+
+            let posting_list = PostingList::crate_only_one_entry(primary_key);
+            n_affected_rows += 1;
+
+            logger().debug(DB(self.table.table_name.clone()), &format!(
+                "Adding secondary index entry. Table: {} Primary key: {:?} Secondary key: {:?}",
+                self.table.table_name,
+                Value::create(primary_key.as_bytes().clone(), primary_column_type.clone()).unwrap().to_string(),
+                value_to_be_indexed,
+            ));
+
+            self.storage.set_with_transaction(
+                self.index_keyspace_id,
+                &Transaction::create(primary_key.txn_id()),
+                value_to_be_indexed,
+                &Bytes::from(posting_list.serialize())
+            )?;
+        }
+
+        if self.cancelled.load(Relaxed) {
+            return Err(SimpleDbError::IndexCreationCancelled(self.indexed_columns_names()));
         }
 
         logger().info(DB(self.table.table_name.clone()), &format!(
@@ -101,6 +210,121 @@ impl IndexCreationTask {
             self.table.table_name.clone(), n_affected_rows
         ));
 
-        self.n_affected_rows_sender.send(Ok(n_affected_rows)).unwrap();
+        Ok(n_affected_rows)
+    }
+
+    //Rows without the indexed column won't be indexed. Composite indexes will index all the rows
+    fn get_value_to_be_indexed(&self, mut record: Record) -> Result<Option<Bytes>, SimpleDbError> {
+        match self.secondary_indexed_columns.as_slice() {
+            [secondary_indexed_column] => Ok(record.remove_column(secondary_indexed_column.column_id)),
+            _ => composite_key::encode_record(&self.secondary_indexed_columns, &record).map(Some),
+        }
+    }
+
+    fn indexed_columns_names(&self) -> String {
+        self.secondary_indexed_columns.iter()
+            .map(|column| column.column_name.clone())
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{CreateIndexStatement, IndexState};
+    use crate::simple_db::{Context, StatementResult};
+    use crate::test_utils::TestSimpleDb;
+    use shared::SimpleDbError;
+    use std::time::Duration;
+
+    #[test]
+    fn cancel_index_creation() {
+        let simple_db = TestSimpleDb::create("cancel_index_creation");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64);");
+        for first_id in (0..3000).step_by(25) {
+            let values: Vec<String> = (first_id..first_id + 25).map(|id| format!("({}, {})", id, id)).collect();
+            simple_db.execute_in_transaction(&context, &format!("INSERT INTO personas (id, dinero) VALUES {};", values.join(", ")));
+        }
+
+        let database = simple_db.get_databases().get_database_or_err("test").unwrap();
+        let table = database.get_table_or_err("personas").unwrap();
+        let keyspaces_id = table.storage.get_keyspaces_id();
+        //The creation can't start indexing rows while rollbacks are being done, so it can't finish before it is cancelled
+        let rollback_lock = database.rollback_lock.read().unwrap();
+        let index_creation = simple_db.start_index_creation(&context, CreateIndexStatement {
+            table_name: String::from("personas"),
+            column_names: vec![String::from("dinero")],
+            unique: false,
+            wait: false,
+        }).unwrap();
+
+        let indexes = match simple_db.execute_sql(&context, "SHOW INDEX FROM personas;") {
+            StatementResult::Indexes(indexes) => indexes,
+            _ => panic!()
+        };
+        assert!(indexes.iter().any(|(column, _, state)| column == "dinero" && *state == IndexState::Building));
+
+        index_creation.handle.cancel();
+        drop(rollback_lock);
+        assert!(matches!(index_creation.wait(&mut |_| {}), Err(SimpleDbError::IndexCreationCancelled(_))));
+
+        //The half built index keyspace has been deleted
+        assert_eq!(table.storage.get_keyspaces_id(), keyspaces_id);
+        let indexes = match simple_db.execute_sql(&context, "SHOW INDEX FROM personas;") {
+            StatementResult::Indexes(indexes) => indexes,
+            _ => panic!()
+        };
+        assert_eq!(indexes.len(), 1);
+        assert_eq!(indexes[0].0, "id");
+
+        //The index can be created again
+        simple_db.execute_sql(&context, "CREATE INDEX ON personas (dinero);");
+        let indexes = match simple_db.execute_sql(&context, "SHOW INDEX FROM personas;") {
+            StatementResult::Indexes(indexes) => indexes,
+            _ => panic!()
+        };
+        assert!(indexes.iter().any(|(column, _, state)| column == "dinero" && *state == IndexState::Ready));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn index_creation_not_received() {
+        let simple_db = TestSimpleDb::create("index_creation_not_received");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64);");
+        for first_id in (0..3000).step_by(25) {
+            let values: Vec<String> = (first_id..first_id + 25).map(|id| format!("({}, {})", id, id)).collect();
+            simple_db.execute_in_transaction(&context, &format!("INSERT INTO personas (id, dinero) VALUES {};", values.join(", ")));
+        }
+
+        let index_creation = simple_db.start_index_creation(&context, CreateIndexStatement {
+            table_name: String::from("personas"),
+            column_names: vec![String::from("dinero")],
+            unique: false,
+            wait: false,
+        }).unwrap();
+
+        //The index is created even if its progress messages are not received
+        let started = std::time::Instant::now();
+        loop {
+            let indexes = match simple_db.execute_sql(&context, "SHOW INDEX FROM personas;") {
+                StatementResult::Indexes(indexes) => indexes,
+                _ => panic!()
+            };
+            if indexes.iter().any(|(column, _, state)| column == "dinero" && *state == IndexState::Ready) {
+                break;
+            }
+            assert!(started.elapsed() < Duration::from_secs(60));
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        //Rollbacks are not blocked by the finished creation
+        let mut transaction = simple_db.begin(&context).unwrap();
+        simple_db.execute_sql(&transaction.context(), "INSERT INTO personas (id, dinero) VALUES (3000, 1);");
+        transaction.rollback().unwrap();
+
+        let mut n_progress_messages = 0;
+        assert_eq!(index_creation.wait(&mut |_| n_progress_messages += 1).unwrap(), 3000);
+        assert_eq!(n_progress_messages, 2);
+    }
+}
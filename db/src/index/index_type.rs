@@ -10,4 +10,18 @@ impl IndexType {
             IndexType::Secondary => 2
         }
     }
-}
\ No newline at end of file
+}
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IndexState {
+    Building, //Rows are still being indexed, it can't be used by queries
+    Ready,
+}
+
+impl IndexState {
+    pub fn serialize(&self) -> u8 {
+        match &self {
+            IndexState::Building => 1,
+            IndexState::Ready => 2,
+        }
+    }
+}
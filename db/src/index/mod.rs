@@ -4,4 +4,5 @@ pub mod posting_list;
 pub mod index_creation_task;
 mod posting_list_iterator;
 pub mod secondary_index_iterator;
-pub mod index_type;
\ No newline at end of file
+pub mod index_type;
+pub mod composite_key;
\ No newline at end of file
@@ -57,24 +57,22 @@ impl PostingList {
         PostingList { entries }
     }
 
-    //A is after B
+    //A is after B. For each primary key, the entry written by the last transaction is kept.
+    //Deleted entries are kept, so they keep hiding the entries stored in SSTables not taking part in a compaction
     pub fn merge(a: &PostingList, b: &PostingList) -> PostingList {
         let mut final_posting_list = PostingList::create_empty();
 
         for current_entry_a in &a.entries {
-            if current_entry_a.is_present {
-                final_posting_list.entries.push(current_entry_a.clone());
-            }
+            match b.get_entry_by_key_bytes(&current_entry_a.primary_key) {
+                Some(entry_b) if entry_b.primary_key.txn_id() > current_entry_a.primary_key.txn_id() => {},
+                _ => final_posting_list.entries.push(current_entry_a.clone()),
+            };
         }
 
         for current_entry_b in &b.entries {
-            match final_posting_list.get_entry_by_key_bytes(&current_entry_b.primary_key) {
-                None => {
-                    if current_entry_b.is_present {
-                        final_posting_list.entries.push(current_entry_b.clone());
-                    }
-                },
-                _ => {}
+            match a.get_entry_by_key_bytes(&current_entry_b.primary_key) {
+                Some(entry_a) if entry_a.primary_key.txn_id() >= current_entry_b.primary_key.txn_id() => {},
+                _ => final_posting_list.entries.push(current_entry_b.clone()),
             };
         }
 
@@ -167,4 +165,22 @@ mod test {
             PostingListEntry{ primary_key: Key::create_from_str("Pedro", 1), is_present: true },
         ]});
     }
+
+    #[test]
+    fn merge_deleted_entry() {
+        let posting_list_a = PostingList::create_mock(vec![
+            ("Jaime", 2, false),
+        ]);
+        let posting_list_b = PostingList::create_mock(vec![
+            ("Jaime", 1, true),
+            ("Pedro", 1, true),
+        ]);
+
+        let merge_result = PostingList::merge(&posting_list_a, &posting_list_b);
+
+        assert_eq!(merge_result, PostingList{entries: vec![
+            PostingListEntry{ primary_key: Key::create_from_str("Jaime", 2), is_present: false },
+            PostingListEntry{ primary_key: Key::create_from_str("Pedro", 1), is_present: true },
+        ]});
+    }
 }
\ No newline at end of file
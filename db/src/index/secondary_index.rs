@@ -3,42 +3,63 @@ use crate::index::secondary_index_iterator::SecondaryIndexIterator;
 use bytes::Bytes;
 use shared::logger::logger;
 use shared::logger::SimpleDbLayer::DB;
-use shared::{KeyspaceId, SimpleDbError, SimpleDbOptions, Type};
-use std::sync::Arc;
+use shared::{KeyspaceId, SimpleDbError, SimpleDbOptions, TxnId, Type};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
 use shared::key::Key;
 use storage::transactions::transaction::Transaction;
 use storage::{SimpleDbStorageIterator, Storage};
-
-pub enum SecondaryIndexState {
-    Creating,
-    Active
-}
+use crate::index::index_type::IndexState;
 
 pub struct SecondaryIndex {
     keyspace_id: KeyspaceId,
     storage: Arc<storage::Storage>,
-    state: SecondaryIndexState,
+    state: RwLock<IndexState>,
     table_name: String,
     primary_column_type: Type,
+
+    unique: bool,
+    //Only used by unique indexes. Indexed value -> last write of the value.
+    //Concurrent transactions can't see each other writes in the index, so they are checked here
+    unique_values_written: Mutex<HashMap<Bytes, UniqueValueWrite>>,
+}
+
+struct UniqueValueWrite {
+    primary_key: Bytes,
+    txn_id: TxnId,
+    //None if the transaction is still active. Once committed, contains the greatest transaction ID active
+    //at commit time. The write is kept until all of them have finished, as they might not see it.
+    committed_with_active_txn_id: Option<Option<TxnId>>,
 }
 
 impl SecondaryIndex {
     pub fn create(
         storage: Arc<storage::Storage>,
-        state: SecondaryIndexState,
+        state: IndexState,
         keyspace_id: KeyspaceId,
         table_name: String,
         primary_column_type: Type,
+        unique: bool,
     ) -> SecondaryIndex {
-        SecondaryIndex { keyspace_id, storage, state, table_name, primary_column_type }
+        SecondaryIndex {
+            unique_values_written: Mutex::new(HashMap::new()),
+            keyspace_id,
+            storage,
+            state: RwLock::new(state),
+            table_name,
+            primary_column_type,
+            unique
+        }
     }
 
     pub fn create_mock() -> SecondaryIndex {
         SecondaryIndex {
             storage: Arc::new(Storage::create_mock(&Arc::new(SimpleDbOptions::default()))),
             table_name: String::from("table_name"),
-            state: SecondaryIndexState::Active,
+            state: RwLock::new(IndexState::Ready),
             primary_column_type: Type::I64,
+            unique_values_written: Mutex::new(HashMap::new()),
+            unique: false,
             keyspace_id: 1,
         }
     }
@@ -95,7 +116,82 @@ impl SecondaryIndex {
         )
     }
 
-    pub fn can_be_read(&self) -> bool {
-        matches!(self.state, SecondaryIndexState::Active)
+    //Returns false if the value has been written for other primary key by a transaction that
+    //hasn't finished yet, or that has committed but its writes are not visible by this transaction
+    pub fn write_unique_value(
+        &self,
+        transaction: &Transaction,
+        value: &Bytes,
+        primary_key: &Bytes,
+    ) -> bool {
+        let mut unique_values_written = self.unique_values_written.lock().unwrap();
+
+        if let Some(last_write) = unique_values_written.get(value) {
+            let can_be_read = last_write.committed_with_active_txn_id.is_some() &&
+                last_write.txn_id <= transaction.id() &&
+                !transaction.active_transactions().contains(&last_write.txn_id);
+            let is_aborted = last_write.committed_with_active_txn_id.is_none() &&
+                self.storage.is_transaction_aborted(last_write.txn_id);
+
+            if last_write.txn_id != transaction.id() && !last_write.primary_key.eq(primary_key) && !can_be_read && !is_aborted {
+                return false;
+            }
+        }
+
+        unique_values_written.insert(value.clone(), UniqueValueWrite {
+            primary_key: primary_key.clone(),
+            txn_id: transaction.id(),
+            committed_with_active_txn_id: None,
+        });
+
+        true
+    }
+
+    //Rolledback writes are discarded. Committed writes are discarded once they are visible by all active transactions
+    pub fn on_transaction_finished(
+        &self,
+        txn_id: TxnId,
+        committed: bool,
+        active_transactions: &Vec<TxnId>
+    ) {
+        if !self.unique {
+            return;
+        }
+
+        let greatest_active_txn_id = active_transactions.iter().max().cloned();
+        let smallest_active_txn_id = active_transactions.iter().min().cloned();
+        let mut unique_values_written = self.unique_values_written.lock().unwrap();
+
+        unique_values_written.retain(|_, write| {
+            if write.txn_id == txn_id {
+                if !committed {
+                    return false;
+                }
+                write.committed_with_active_txn_id = Some(greatest_active_txn_id);
+            }
+
+            match (write.committed_with_active_txn_id, smallest_active_txn_id) {
+                (Some(Some(greatest_active_at_commit)), Some(smallest_active)) => smallest_active <= greatest_active_at_commit,
+                (Some(_), _) => false,
+                (None, _) => true,
+            }
+        });
+    }
+
+    pub fn is_unique(&self) -> bool {
+        self.unique
+    }
+
+    pub fn state(&self) -> IndexState {
+        *self.state.read().unwrap()
+    }
+
+    //Called once all the rows of the table have been indexed
+    pub fn set_ready(&self) {
+        *self.state.write().unwrap() = IndexState::Ready;
+    }
+
+    pub fn keyspace_id(&self) -> KeyspaceId {
+        self.keyspace_id
     }
 }
\ No newline at end of file
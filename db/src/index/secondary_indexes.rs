@@ -1,14 +1,16 @@
-use crate::index::secondary_index::{SecondaryIndex, SecondaryIndexState};
+use crate::index::composite_key;
+use crate::index::index_type::IndexState;
+use crate::index::secondary_index::SecondaryIndex;
 use crate::index::secondary_index_iterator::SecondaryIndexIterator;
 use crate::table::record::Record;
 use crate::table::schema::Schema;
-use crate::table::table_flags::KEYSPACE_TABLE_INDEX;
+use crate::table::table_flags::{KEYSPACE_TABLE_INDEX, KEYSPACE_TABLE_UNIQUE_INDEX};
 use bytes::Bytes;
 use crossbeam_skiplist::SkipMap;
 use shared::logger::logger;
 use shared::logger::SimpleDbLayer::DB;
-use shared::SimpleDbError::IndexNotFound;
-use shared::{ColumnId, KeyspaceId, SimpleDbError, SimpleDbOptions, Type};
+use shared::SimpleDbError::{IndexNotFound, KeyspaceNotFound};
+use shared::{ColumnId, FlagMethods, KeyspaceId, SimpleDbError, SimpleDbOptions, TxnId, Type};
 use std::sync::Arc;
 use storage::transactions::transaction::Transaction;
 use storage::{SimpleDbStorageIterator, Storage};
@@ -16,15 +18,22 @@ use crate::Column;
 
 pub struct SecondaryIndexes {
     secondary_index_by_column_id: SkipMap<ColumnId, Arc<SecondaryIndex>>,
+    composite_indexes_by_keyspace_id: SkipMap<KeyspaceId, CompositeSecondaryIndex>,
     storage: Arc<Storage>,
     table_name: String,
     primary_column_type: Type,
 }
 
+struct CompositeSecondaryIndex {
+    columns: Vec<Column>, //In the order of the index key
+    secondary_index: Arc<SecondaryIndex>,
+}
+
 impl SecondaryIndexes {
     pub fn create_empty(storage: Arc<Storage>, table_name: &str, primary_column_type: Type) -> SecondaryIndexes {
         SecondaryIndexes {
             secondary_index_by_column_id: SkipMap::new(),
+            composite_indexes_by_keyspace_id: SkipMap::new(),
             table_name: table_name.to_string(),
             primary_column_type,
             storage
@@ -37,6 +46,7 @@ impl SecondaryIndexes {
 
         SecondaryIndexes {
             secondary_index_by_column_id: secondary_indexes,
+            composite_indexes_by_keyspace_id: SkipMap::new(),
             storage: Arc::new(Storage::create_mock(&options)),
             table_name: String::from(""),
             primary_column_type: Type::I64,
@@ -47,7 +57,7 @@ impl SecondaryIndexes {
         storage: Arc<Storage>,
         table_name: String,
         schema: Schema,
-    ) -> SecondaryIndexes {
+    ) -> Result<SecondaryIndexes, SimpleDbError> {
         logger().info(DB(table_name.clone()), "Loading secondary indexes");
 
         let secondary_indexes = SkipMap::new();
@@ -56,49 +66,161 @@ impl SecondaryIndexes {
 
         for column in columns {
             if let Some(secondary_index_keyspace_id) = column.secondary_index_keyspace_id {
+                let is_unique = storage.get_flags(secondary_index_keyspace_id)?.has(KEYSPACE_TABLE_UNIQUE_INDEX);
                 let secondary_index = Arc::new(SecondaryIndex::create(
                     storage.clone(),
-                    SecondaryIndexState::Active,
+                    IndexState::Ready,
                     secondary_index_keyspace_id,
                     table_name.clone(),
-                    primary_column.column_type
+                    primary_column.column_type,
+                    is_unique
                 ));
                 secondary_indexes.insert(column.column_id, secondary_index);
             }
         }
 
+        let composite_indexes = SkipMap::new();
+        for composite_index in schema.get_composite_indexes() {
+            composite_indexes.insert(composite_index.keyspace_id, CompositeSecondaryIndex {
+                columns: schema.get_composite_index_columns(&composite_index),
+                secondary_index: Arc::new(SecondaryIndex::create(
+                    storage.clone(),
+                    IndexState::Ready,
+                    composite_index.keyspace_id,
+                    table_name.clone(),
+                    primary_column.column_type,
+                    false
+                )),
+            });
+        }
+
         logger().info(DB(table_name.clone()), &format!(
-            "Loaded {} secondary indexes", secondary_indexes.len())
+            "Loaded {} secondary indexes and {} composite indexes", secondary_indexes.len(), composite_indexes.len())
         );
 
-        SecondaryIndexes {
+        Ok(SecondaryIndexes {
             secondary_index_by_column_id: secondary_indexes,
+            composite_indexes_by_keyspace_id: composite_indexes,
             primary_column_type: primary_column.column_type,
             table_name: table_name.clone(),
             storage
-        }
+        })
     }
 
     pub fn create_new_secondary_index(
         &self,
         column_to_be_indexed: Column,
+        unique: bool,
     ) -> Result<KeyspaceId, SimpleDbError> {
+        //The uniqueness is stored in the keyspace flags, so it is known when the index is loaded
         let keyspace_id = self.storage.create_keyspace(
-            KEYSPACE_TABLE_INDEX,
+            if unique { KEYSPACE_TABLE_INDEX | KEYSPACE_TABLE_UNIQUE_INDEX } else { KEYSPACE_TABLE_INDEX },
             column_to_be_indexed.column_type,
         )?;
 
         self.secondary_index_by_column_id.insert(column_to_be_indexed.column_id, Arc::new(SecondaryIndex::create(
             self.storage.clone(),
-            SecondaryIndexState::Creating,
+            IndexState::Building,
             keyspace_id,
             self.table_name.clone(),
             self.primary_column_type.clone(),
+            unique,
         )));
 
         Ok(keyspace_id)
     }
 
+    pub fn create_new_composite_index(
+        &self,
+        columns_to_be_indexed: Vec<Column>,
+    ) -> Result<KeyspaceId, SimpleDbError> {
+        //Keys are encoded with composite_key::encode(), which can be compared byte by byte
+        let keyspace_id = self.storage.create_keyspace(
+            KEYSPACE_TABLE_INDEX,
+            Type::Blob,
+        )?;
+
+        self.composite_indexes_by_keyspace_id.insert(keyspace_id, CompositeSecondaryIndex {
+            columns: columns_to_be_indexed,
+            secondary_index: Arc::new(SecondaryIndex::create(
+                self.storage.clone(),
+                IndexState::Building,
+                keyspace_id,
+                self.table_name.clone(),
+                self.primary_column_type.clone(),
+                false,
+            )),
+        });
+
+        Ok(keyspace_id)
+    }
+
+    //Once removed, new readers and writers won't see the index. Readers that already
+    //had an iterator will either finish or get a KeyspaceNotFound error
+    pub fn delete_secondary_index(
+        &self,
+        column_id: ColumnId,
+    ) -> Result<KeyspaceId, SimpleDbError> {
+        let secondary_index = match self.secondary_index_by_column_id.remove(&column_id) {
+            Some(entry) => entry.value().clone(),
+            None => return Err(IndexNotFound(column_id)),
+        };
+
+        let keyspace_id = secondary_index.keyspace_id();
+        self.storage.delete_keyspace(keyspace_id)?;
+
+        Ok(keyspace_id)
+    }
+
+    //Called when the creation of a composite index fails or gets cancelled
+    pub fn delete_composite_index(
+        &self,
+        keyspace_id: KeyspaceId,
+    ) -> Result<KeyspaceId, SimpleDbError> {
+        if self.composite_indexes_by_keyspace_id.remove(&keyspace_id).is_none() {
+            return Err(KeyspaceNotFound(keyspace_id));
+        }
+
+        self.storage.delete_keyspace(keyspace_id)?;
+
+        Ok(keyspace_id)
+    }
+
+    //The index can be used by queries once all the rows of the table have been indexed
+    pub fn set_ready(&self, keyspace_id: KeyspaceId) {
+        for entry in self.secondary_index_by_column_id.iter() {
+            if entry.value().keyspace_id() == keyspace_id {
+                entry.value().set_ready();
+            }
+        }
+        if let Some(entry) = self.composite_indexes_by_keyspace_id.get(&keyspace_id) {
+            entry.value().secondary_index.set_ready();
+        }
+    }
+
+    pub fn get_keyspaces_id(&self) -> Vec<KeyspaceId> {
+        self.secondary_index_by_column_id.iter()
+            .map(|entry| entry.value().keyspace_id())
+            .chain(self.composite_indexes_by_keyspace_id.iter().map(|entry| *entry.key()))
+            .collect()
+    }
+
+    //Columns ID of the indexes whose rows are still being indexed
+    pub fn get_building_indexes(&self) -> Vec<Vec<ColumnId>> {
+        let mut building_indexes: Vec<Vec<ColumnId>> = self.secondary_index_by_column_id.iter()
+            .filter(|entry| entry.value().state() == IndexState::Building)
+            .map(|entry| vec![*entry.key()])
+            .collect();
+
+        for entry in self.composite_indexes_by_keyspace_id.iter() {
+            if entry.value().secondary_index.state() == IndexState::Building {
+                building_indexes.push(entry.value().columns.iter().map(|column| column.column_id).collect());
+            }
+        }
+
+        building_indexes
+    }
+
     pub fn scan_all(
         &self,
         transaction: &Transaction,
@@ -110,6 +232,64 @@ impl SecondaryIndexes {
         }
     }
 
+    pub fn scan_all_composite(
+        &self,
+        transaction: &Transaction,
+        keyspace_id: KeyspaceId
+    ) -> Result<SecondaryIndexIterator<SimpleDbStorageIterator>, SimpleDbError> {
+        match self.composite_indexes_by_keyspace_id.get(&keyspace_id) {
+            Some(entry) => entry.value().secondary_index.scan_all(transaction),
+            None => Err(KeyspaceNotFound(keyspace_id)),
+        }
+    }
+
+    pub fn has_composite_indexes(&self) -> bool {
+        !self.composite_indexes_by_keyspace_id.is_empty()
+    }
+
+    //old_data should contain all the columns of the row before the update, None if the row didn't exist.
+    //Columns not present in new_data will keep its old value
+    pub fn update_all_composite(
+        &self,
+        transaction: &Transaction,
+        primary_key: Bytes,
+        new_data: &Record,
+        old_data: Option<&Record>,
+    ) -> Result<(), SimpleDbError> {
+        for entry in self.composite_indexes_by_keyspace_id.iter() {
+            let composite_index = entry.value();
+            let is_updated = composite_index.columns.iter()
+                .any(|column| new_data.get_column_bytes(column.column_id).is_some());
+            if !is_updated {
+                continue;
+            }
+
+            let mut updated_data = Record::builder();
+            for column in &composite_index.columns {
+                let column_bytes = new_data.get_column_bytes(column.column_id)
+                    .or_else(|| old_data.and_then(|old_data| old_data.get_column_bytes(column.column_id)));
+                if let Some(column_bytes) = column_bytes {
+                    updated_data.add_column(column.column_id, column_bytes.clone());
+                }
+            }
+
+            let new_key = composite_key::encode_record(&composite_index.columns, &updated_data.build())?;
+            let old_key = match old_data {
+                Some(old_data) => Some(composite_key::encode_record(&composite_index.columns, old_data)?),
+                None => None,
+            };
+
+            composite_index.secondary_index.update(
+                transaction,
+                new_key.clone(),
+                primary_key.clone(),
+                old_key.filter(|old_key| *old_key != new_key).as_ref()
+            )?;
+        }
+
+        Ok(())
+    }
+
     pub fn update_all(
         &self,
         transaction: &Transaction,
@@ -125,7 +305,7 @@ impl SecondaryIndexes {
                     transaction,
                     column_value.clone(),
                     primary_key.clone(),
-                    old_data.get_column_bytes(*column_id)
+                    old_data.get_column_bytes(*column_id).filter(|old_value| *old_value != column_value)
                 )?;
             }
         }
@@ -133,11 +313,180 @@ impl SecondaryIndexes {
         Ok(())
     }
 
-    pub fn can_be_read(&self, column_id: ColumnId) -> bool {
-        if let Some(secondary_index) = self.secondary_index_by_column_id.get(&column_id) {
-            secondary_index.value().can_be_read()
-        } else {
-            false
+    //old_data should contain all the columns of the deleted row
+    pub fn delete_all(
+        &self,
+        transaction: &Transaction,
+        primary_key: Bytes,
+        old_data: &Record,
+    ) -> Result<(), SimpleDbError> {
+        for (column_id, column_value) in &old_data.data_records {
+            if let Some(secondary_index_entry) = self.secondary_index_by_column_id.get(column_id) {
+                secondary_index_entry.value().delete(transaction, column_value.clone(), primary_key.clone())?;
+            }
+        }
+        for entry in self.composite_indexes_by_keyspace_id.iter() {
+            let composite_index = entry.value();
+            let old_key = composite_key::encode_record(&composite_index.columns, old_data)?;
+            composite_index.secondary_index.delete(transaction, old_key, primary_key.clone())?;
         }
+
+        Ok(())
+    }
+
+    pub fn has_indexes(&self) -> bool {
+        !self.secondary_index_by_column_id.is_empty() || !self.composite_indexes_by_keyspace_id.is_empty()
     }
-}
\ No newline at end of file
+
+    pub fn is_unique(&self, column_id: ColumnId) -> bool {
+        self.secondary_index_by_column_id.get(&column_id)
+            .map(|entry| entry.value().is_unique())
+            .unwrap_or(false)
+    }
+
+    //See SecondaryIndex::write_unique_value()
+    pub fn write_unique_value(
+        &self,
+        transaction: &Transaction,
+        column_id: ColumnId,
+        value: &Bytes,
+        primary_key: &Bytes,
+    ) -> Result<bool, SimpleDbError> {
+        match self.secondary_index_by_column_id.get(&column_id) {
+            Some(entry) => Ok(entry.value().write_unique_value(transaction, value, primary_key)),
+            None => Err(IndexNotFound(column_id)),
+        }
+    }
+
+    pub fn on_transaction_finished(
+        &self,
+        txn_id: TxnId,
+        committed: bool,
+        active_transactions: &Vec<TxnId>
+    ) {
+        for entry in self.secondary_index_by_column_id.iter() {
+            entry.value().on_transaction_finished(txn_id, committed, active_transactions);
+        }
+    }
+
+    //The index might still be being built
+    pub fn has_secondary_index(&self, column_id: ColumnId) -> bool {
+        self.secondary_index_by_column_id.contains_key(&column_id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::simple_db::{Context, StatementResult};
+    use crate::table::row::RowIterator;
+    use crate::test_utils::TestSimpleDb;
+    use shared::SimpleDbError;
+
+    #[test]
+    fn composite_index() {
+        let simple_db = TestSimpleDb::create("composite_index");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, apellido VARCHAR, nombre VARCHAR);");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, apellido, nombre) VALUES (1, \"Polidura\", \"Jaime\");");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, apellido, nombre) VALUES (2, \"Polidura\", \"Molon\");");
+        simple_db.execute_sql(&context, "CREATE INDEX ON personas (apellido, nombre);");
+        //Indexed once the index has been created
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, apellido, nombre) VALUES (3, \"Polidura\", \"Wili\");");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, apellido, nombre) VALUES (4, \"Poli\", \"Jaime\");");
+        simple_db.execute_in_transaction(&context, "UPDATE personas SET apellido = \"Poli\" WHERE id == 2;");
+
+        let transaction = simple_db.begin(&context).unwrap();
+        //Prefix only
+        let rows = simple_db.execute_sql(&transaction.context(), "SELECT id FROM personas WHERE apellido == \"Polidura\";")
+            .data().all().unwrap();
+        let ids: Vec<i64> = rows.iter().map(|row| row.get_column_value("id").unwrap().get_i64().unwrap()).collect();
+        assert_eq!(ids, vec![1, 3]);
+        //Full key
+        let rows = simple_db.execute_sql(&transaction.context(), "SELECT id FROM personas WHERE nombre == \"Jaime\" AND apellido == \"Poli\";")
+            .data().all().unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].get_column_value("id").unwrap().get_i64().unwrap(), 4);
+
+        let indexes = match simple_db.execute_sql(&context, "SHOW INDEX FROM personas;") {
+            StatementResult::Indexes(indexes) => indexes,
+            _ => panic!()
+        };
+        assert!(indexes.iter().any(|(columns, _, _)| columns == "apellido, nombre"));
+        assert!(matches!(simple_db.execute(&context, simple_db.parse("CREATE INDEX ON personas (apellido, nombre);").unwrap()),
+            Err(SimpleDbError::IndexAlreadyExists(_, _))));
+    }
+
+    #[test]
+    fn unique_index() {
+        let simple_db = TestSimpleDb::create("unique_index");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, email VARCHAR);");
+        simple_db.execute_sql(&context, "CREATE UNIQUE INDEX ON personas (email);");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, email) VALUES (1, \"jaime@gmail.com\");");
+
+        let transaction = simple_db.begin(&context).unwrap();
+        assert!(matches!(simple_db.execute(&transaction.context(), simple_db.parse("INSERT INTO personas (id, email) VALUES (2, \"jaime@gmail.com\");").unwrap()),
+            Err(SimpleDbError::UniqueConstraintViolation(_))));
+        //Same row
+        simple_db.execute_sql(&transaction.context(), "UPDATE personas SET email = \"jaime@gmail.com\" WHERE id == 1;");
+
+        //Concurrent transactions can't see each other writes
+        let mut transaction_a = simple_db.begin(&context).unwrap();
+        let transaction_b = simple_db.begin(&context).unwrap();
+        simple_db.execute_sql(&transaction_a.context(), "INSERT INTO personas (id, email) VALUES (3, \"molon@gmail.com\");");
+        assert!(matches!(simple_db.execute(&transaction_b.context(), simple_db.parse("INSERT INTO personas (id, email) VALUES (4, \"molon@gmail.com\");").unwrap()),
+            Err(SimpleDbError::UniqueConstraintViolation(_))));
+        transaction_a.rollback().unwrap();
+        simple_db.execute_sql(&transaction_b.context(), "INSERT INTO personas (id, email) VALUES (4, \"molon@gmail.com\");");
+    }
+
+    #[test]
+    fn unique_index_transaction_timeout() {
+        let simple_db = TestSimpleDb::create_with_options("unique_index_transaction_timeout", {
+            let mut options_builder = shared::start_simpledb_options_builder();
+            options_builder.transaction_timeout_ms(20);
+            options_builder
+        });
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, email VARCHAR);");
+        simple_db.execute_sql(&context, "CREATE UNIQUE INDEX ON personas (email);");
+
+        let mut transaction_a = simple_db.begin(&context).unwrap();
+        simple_db.execute_sql(&transaction_a.context(), "INSERT INTO personas (id, email) VALUES (1, \"jaime@gmail.com\");");
+        std::thread::sleep(std::time::Duration::from_millis(200));
+
+        //The unique value written by the aborted transaction is discarded, even if its client doesn't roll it back
+        assert!(matches!(transaction_a.commit(), Err(SimpleDbError::TransactionAborted(_))));
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, email) VALUES (2, \"jaime@gmail.com\");");
+    }
+
+    #[test]
+    fn drop_index() {
+        let simple_db = TestSimpleDb::create("drop_index");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR);");
+        simple_db.execute_sql(&context, "CREATE INDEX ON personas (nombre);");
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        simple_db.execute_sql(&transaction.context(), "INSERT INTO personas (id, nombre) VALUES (1, \"Jaime\");");
+        let mut rows = simple_db.execute_sql(&transaction.context(), "SELECT nombre FROM personas WHERE nombre == \"Jaime\";").data();
+        assert!(rows.next().unwrap().is_some());
+        transaction.commit().unwrap();
+
+        simple_db.execute_sql(&context, "DROP INDEX nombre ON personas;");
+
+        let indexes = match simple_db.execute_sql(&context, "SHOW INDEX FROM personas;") {
+            StatementResult::Indexes(indexes) => indexes,
+            _ => panic!()
+        };
+        assert_eq!(indexes.len(), 1);
+        assert_eq!(indexes[0].0, "id");
+        assert!(matches!(simple_db.execute(&context, simple_db.parse("DROP INDEX nombre ON personas;").unwrap()), Err(SimpleDbError::IndexNotFound(_))));
+
+        //Rows are still found with a full scan
+        let mut transaction = simple_db.begin(&context).unwrap();
+        let mut rows = simple_db.execute_sql(&transaction.context(), "SELECT nombre FROM personas WHERE nombre == \"Jaime\";").data();
+        assert!(rows.next().unwrap().is_some());
+        transaction.commit().unwrap();
+    }
+}
@@ -4,14 +4,26 @@ mod database;
 mod table;
 mod sql;
 mod index;
+mod transaction_handle;
+mod users;
+#[cfg(test)]
+mod test_utils;
 
-pub use sql::plan::plan_step::PlanStepDesc;
+pub use sql::plan::plan_step::{PlanStep, PlanStepDesc};
 pub use sql::query_iterator::QueryIterator;
-pub use index::index_type::IndexType;
-pub use sql::plan::RangeScan;
+pub use sql::cancellation_token::CancellationToken;
+pub use sql::query_builder::QueryBuilder;
+pub use sql::parser::expression::{BinaryOperator, Expression, UnaryOperator};
+pub use index::index_type::{IndexState, IndexType};
+pub use index::index_creation_task::{IndexCreation, IndexCreationHandle, IndexCreationProgress};
+pub use sql::plan::{DiscardedIndexScan, RangeScan, StepStats};
 pub use simple_db::SimpleDb;
 pub use simple_db::Context;
+pub use transaction_handle::{DropBehavior, TransactionHandle};
+pub use users::password::{hash_password, is_hashed, StoredPassword};
 pub use sql::parser::statement::*;
-pub use table::row::Row;
+pub use table::row::{Row, RowIterator};
+pub use table::table_stats::TableStats;
+pub use storage::transactions::transaction_manager::{IsolationLevel, TransactionDesc, TransactionState};
 pub use table::schema::*;
-pub use table::selection::Selection;
+pub use table::selection::{Aggregate, AggregateFunction, Selection};
@@ -1,20 +1,29 @@
 use crate::database::databases::Databases;
-use crate::index::index_type::IndexType;
+use crate::index::index_type::{IndexState, IndexType};
 use crate::sql::parser::parser::Parser;
 use crate::sql::query_iterator::QueryIterator;
+use crate::sql::cancellation_token::CancellationToken;
+use crate::sql::query_builder::QueryBuilder;
 use crate::sql::parser::statement::Statement;
 use shared::{SimpleDbError, SimpleDbOptions};
 use std::sync::Arc;
 use storage::transactions::transaction::Transaction;
-use crate::PlanStepDesc;
+use storage::transactions::transaction_manager::TransactionDesc;
+use crate::{CreateIndexStatement, IndexCreation, PlanStepDesc};
 use crate::sql::plan::plan_step::PlanStep;
+use crate::sql::plan::{RangeScan, ScanType};
+use crate::table::selection::Selection;
 use crate::sql::StatementExecutor;
 use crate::table::schema::Column;
+use crate::table::table_stats::TableStats;
+use crate::transaction_handle::TransactionHandle;
+use crate::users::users::Users;
 
 pub struct SimpleDb {
     statement_executor: StatementExecutor,
 
     databases: Arc<Databases>,
+    pub(crate) users: Arc<Users>,
 }
 
 pub enum StatementResult {
@@ -24,25 +33,45 @@ pub enum StatementResult {
     Databases(Vec<String>),
     Tables(Vec<String>),
     Describe(Vec<Column>),
-    Indexes(Vec<(String, IndexType)>)
+    Indexes(Vec<(String, IndexType, IndexState)>),
+    Stats(TableStats),
+    TransactionStatus(Option<Transaction>), //None if there is no active transaction
+    Transactions(Vec<TransactionDesc>), //SHOW TRANSACTION returns none if there is no active transaction
+    DatabaseSelected(String), //Returned by USE. The caller keeps the database in the context of the next statements
+    Vacuumed(usize), //Nº of bytes reclaimed by VACUUM
+    CorruptedParts(Vec<SimpleDbError>), //Returned by CHECK DATABASE. Empty if no corruption was found
 }
 
 pub fn create(
     options: Arc<SimpleDbOptions>,
 ) -> Result<SimpleDb, SimpleDbError> {
     let databases = Arc::new(Databases::create(options.clone())?);
-    
+    let users = Arc::new(Users::load(&options)?);
+    users.revoke_dropped_databases(|database_name| databases.get_database(database_name).is_some())?;
+
     Ok(SimpleDb {
-        statement_executor: StatementExecutor::create(&databases, options),
+        statement_executor: StatementExecutor::create(&databases, &users, options),
         databases,
+        users,
     })
 }
 
+//Opens a SimpleDb from a snapshot created with SimpleDb::snapshot() or BACKUP TO. The snapshot files are copied
+//into options' base path, which is expected to be empty
+pub fn restore(
+    snapshot_path: &str,
+    options: Arc<SimpleDbOptions>,
+) -> Result<SimpleDb, SimpleDbError> {
+    Databases::restore_snapshot(snapshot_path, &options)?;
+    create(options)
+}
 
 #[derive(Clone)]
 pub struct Context {
     transaction: Option<Transaction>,
     database: Option<String>,
+    cancellation_token: Option<CancellationToken>, //Applied to the queries executed with this context
+    user: Option<String>, //None for the administrator, which can access every database
 }
 
 impl SimpleDb {
@@ -55,6 +84,34 @@ impl SimpleDb {
         Ok(statement)
     }
 
+    //Parses every statement of the string. Statements are separated by ';'
+    //Errors are returned as InvalidBatchStatement, with the index of the statement that couldn't be parsed
+    pub fn parse_all(
+        &self,
+        statements: &str
+    ) -> Result<Vec<Statement>, SimpleDbError> {
+        let mut parser = Parser::create(statements.to_string());
+        let mut parsed_statements = Vec::new();
+        loop {
+            match parser.next_statement() {
+                Ok(Some(statement)) => parsed_statements.push(statement),
+                Ok(None) => return Ok(parsed_statements),
+                Err(error) => return Err(SimpleDbError::InvalidBatchStatement(parsed_statements.len(), Box::new(error))),
+            }
+        }
+    }
+
+    //Writes a point in time copy of all databases into snapshot_path, without blocking writes
+    pub fn snapshot(&self, snapshot_path: &str) -> Result<(), SimpleDbError> {
+        self.databases.snapshot(snapshot_path)
+    }
+
+    //Decodes every SSTable block and bloom filter of all databases, checking their CRCs and the order of their keys.
+    //Returns the corrupted parts found instead of failing on the first one
+    pub fn check_integrity(&self) -> Vec<SimpleDbError> {
+        self.databases.check_integrity()
+    }
+
     pub fn execute(
         &self,
         context: &Context,
@@ -63,6 +120,78 @@ impl SimpleDb {
         self.statement_executor.execute(&context, statement)
     }
 
+    //Starts building the index of a CREATE INDEX statement. The returned IndexCreation receives the progress
+    //of the build, and can cancel it, removing the half built index
+    pub fn start_index_creation(
+        &self,
+        context: &Context,
+        statement: CreateIndexStatement,
+    ) -> Result<IndexCreation, SimpleDbError> {
+        self.statement_executor.start_index_creation(context, statement)
+    }
+
+    //Builds a SELECT over table_name without parsing SQL
+    pub fn query(&self, table_name: &str) -> QueryBuilder<'_> {
+        QueryBuilder::create(self, table_name)
+    }
+
+    //Streams the rows of table_name in primary key order, without parsing SQL. The context should have a transaction
+    pub fn scan_table(
+        &self,
+        context: &Context,
+        table_name: &str,
+        selection: Selection,
+    ) -> Result<QueryIterator<PlanStep>, SimpleDbError> {
+        self.statement_executor.scan(context, table_name, selection, ScanType::Full)
+    }
+
+    //Streams the rows of table_name inside the range. The range column should be the primary column
+    //or a secondary indexed column, and the range values should be literals
+    pub fn scan_range(
+        &self,
+        context: &Context,
+        table_name: &str,
+        selection: Selection,
+        range: RangeScan,
+    ) -> Result<QueryIterator<PlanStep>, SimpleDbError> {
+        self.statement_executor.scan(context, table_name, selection, ScanType::Range(range))
+    }
+
+    //Starts a transaction in the context's database
+    pub fn begin(
+        &self,
+        context: &Context
+    ) -> Result<TransactionHandle, SimpleDbError> {
+        if !context.has_database() {
+            return Err(SimpleDbError::InvalidContext("A database should be selected before starting a transaction"));
+        }
+
+        let database = self.databases.get_database_or_err(context.database())?;
+        let transaction = database.start_transaction();
+        Ok(TransactionHandle::create(database, transaction))
+    }
+
+    //Returns false if the user doesn't exist or the password is wrong
+    pub fn authenticate_user(&self, user_name: &str, password: &str) -> bool {
+        self.users.authenticate(user_name, password)
+    }
+
+    //Returns a context with the database selected. Users can only select the databases granted to them,
+    //otherwise SimpleDbError::AccessDenied is returned. None user means the administrator
+    pub fn create_context(&self, user_name: Option<&str>, database_name: &str) -> Result<Context, SimpleDbError> {
+        self.databases.get_database_or_err(database_name)?;
+
+        let mut context = Context::create_with_database(database_name);
+        if let Some(user_name) = user_name {
+            if !self.users.has_access(user_name, database_name) {
+                return Err(SimpleDbError::AccessDenied(user_name.to_string(), database_name.to_string()));
+            }
+            context.with_user(user_name);
+        }
+
+        Ok(context)
+    }
+
     pub fn get_databases(&self) -> Arc<Databases> {
         self.databases.clone()
     }
@@ -72,7 +201,9 @@ impl Context {
     pub fn empty() -> Context {
         Context {
             transaction: None,
-            database: None
+            database: None,
+            cancellation_token: None,
+            user: None,
         }
     }
 
@@ -80,6 +211,8 @@ impl Context {
         Context {
             database: Some(name.to_string()),
             transaction: None,
+            cancellation_token: None,
+            user: None,
         }
     }
 
@@ -87,6 +220,8 @@ impl Context {
         Context {
             database: Some(name.to_string()),
             transaction: Some(transaction),
+            cancellation_token: None,
+            user: None,
         }
     }
 
@@ -102,6 +237,22 @@ impl Context {
         self.database = Some(database.to_string());
     }
 
+    pub fn with_user(&mut self, user_name: &str) {
+        self.user = Some(user_name.to_string());
+    }
+
+    pub fn get_user(&self) -> Option<&String> {
+        self.user.as_ref()
+    }
+
+    pub fn with_cancellation_token(&mut self, cancellation_token: CancellationToken) {
+        self.cancellation_token = Some(cancellation_token);
+    }
+
+    pub fn get_cancellation_token(&self) -> Option<&CancellationToken> {
+        self.cancellation_token.as_ref()
+    }
+
     pub fn has_transaction(&self) -> bool {
         self.transaction.is_some()
     }
@@ -117,6 +268,10 @@ impl Context {
     pub fn transaction(&self) -> &Transaction {
         self.transaction.as_ref().unwrap()
     }
+
+    pub fn get_transaction(&self) -> Option<&Transaction> {
+        self.transaction.as_ref()
+    }
 }
 
 impl StatementResult {
@@ -133,4 +288,165 @@ impl StatementResult {
             _ => panic!("")
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use crate::simple_db::{Context, StatementResult};
+    use crate::table::row::RowIterator;
+    use crate::test_utils::{select_dinero_sum, TestSimpleDb};
+    use crate::{Expression, RangeScan, Selection};
+    use shared::{SimpleDbError, Value};
+
+    #[test]
+    fn begin_commit() {
+        let simple_db = TestSimpleDb::create("begin_commit");
+
+        let mut transaction = simple_db.begin(&Context::create_with_database("test")).unwrap();
+        assert!(!transaction.is_finished());
+        assert!(transaction.commit().is_ok());
+        assert!(transaction.is_finished());
+    }
+
+    #[test]
+    fn begin_rollback() {
+        let simple_db = TestSimpleDb::create("begin_rollback");
+
+        let mut transaction = simple_db.begin(&Context::create_with_database("test")).unwrap();
+        assert!(transaction.rollback().is_ok());
+        assert!(matches!(transaction.commit(), Err(SimpleDbError::TransactionAlreadyFinished(_))));
+    }
+
+    #[test]
+    fn double_commit() {
+        let simple_db = TestSimpleDb::create("double_commit");
+
+        let mut transaction = simple_db.begin(&Context::create_with_database("test")).unwrap();
+        assert!(transaction.commit().is_ok());
+        assert!(matches!(transaction.commit(), Err(SimpleDbError::TransactionAlreadyFinished(_))));
+        assert!(matches!(transaction.rollback(), Err(SimpleDbError::TransactionAlreadyFinished(_))));
+        assert!(matches!(simple_db.begin(&Context::empty()), Err(SimpleDbError::InvalidContext(_))));
+    }
+
+    #[test]
+    fn prepared_statement() {
+        let simple_db = TestSimpleDb::create("prepared_statement");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR);");
+        simple_db.execute_sql(&context, "CREATE INDEX ON personas (nombre);");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, nombre) VALUES (1, \"Jaime\");");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, nombre) VALUES (2, \"Molon\");");
+
+        let prepared = simple_db.parse("SELECT * FROM personas WHERE id == ?;").unwrap();
+        let transaction = simple_db.begin(&context).unwrap();
+        for (id, nombre) in [(1, "Jaime"), (2, "Molon")] {
+            let statement = prepared.bind(&vec![Value::create_i64(id)]).unwrap();
+            let rows = simple_db.execute(&transaction.context(), statement).unwrap().data().all().unwrap();
+            assert_eq!(rows.len(), 1);
+            assert_eq!(rows[0].get_column_value("nombre").unwrap().get_string().unwrap(), nombre);
+        }
+
+        assert!(matches!(prepared.bind(&vec![]), Err(SimpleDbError::MalformedQuery(_))));
+        assert!(simple_db.execute(&transaction.context(), prepared.clone()).is_err());
+    }
+
+    #[test]
+    fn scan_table() {
+        let simple_db = TestSimpleDb::create("scan_table");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64);");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, dinero) VALUES (1, 10), (2, 20), (3, 30), (4, 40), (5, 50);");
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        let mut rows = simple_db.scan_table(&transaction.context(), "personas", Selection::Some(vec![String::from("dinero")])).unwrap();
+        let mut total_dinero = 0;
+        while let Some(row) = rows.next().unwrap() {
+            total_dinero += row.get_i64("dinero").unwrap();
+        }
+        assert_eq!(total_dinero, 150);
+
+        //2 <= id < 4
+        let range = RangeScan {
+            column_name: String::from("id"),
+            start: Some(Expression::literal(Value::create_i64(2))),
+            start_inclusive: true,
+            end: Some(Expression::literal(Value::create_i64(4))),
+            end_inclusive: false,
+        };
+        let rows = simple_db.scan_range(&transaction.context(), "personas", Selection::All, range.clone()).unwrap().all().unwrap();
+        assert_eq!(rows.iter().map(|row| row.get_i64("id").unwrap()).collect::<Vec<_>>(), vec![2, 3]);
+        //Only indexed columns can be scanned by range
+        let dinero_range = RangeScan { column_name: String::from("dinero"), ..range };
+        assert!(matches!(simple_db.scan_range(&transaction.context(), "personas", Selection::All, dinero_range), Err(SimpleDbError::MalformedQuery(_))));
+        transaction.commit().unwrap();
+
+        assert!(matches!(simple_db.scan_table(&context, "personas", Selection::All), Err(SimpleDbError::InvalidContext(_))));
+    }
+
+    #[test]
+    fn backup_restore() {
+        let simple_db = TestSimpleDb::create("backup_restore");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64);");
+        simple_db.execute_sql(&context, "CREATE INDEX ON personas (dinero);");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, dinero) VALUES (1, 10);");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, dinero) VALUES (2, 20);");
+
+        let snapshot_path = simple_db.path.join("snapshot");
+        let backup = format!("BACKUP TO \"{}\";", snapshot_path.to_str().unwrap());
+        simple_db.execute(&Context::empty(), simple_db.parse(&backup).unwrap()).unwrap();
+
+        simple_db.execute_in_transaction(&context, "UPDATE personas SET dinero = 100 WHERE id == 1;");
+        simple_db.execute_in_transaction(&context, "DELETE FROM personas WHERE id == 2;");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, dinero) VALUES (3, 30);");
+        assert_eq!(select_dinero_sum(&simple_db, &context), 130);
+
+        let restored_options = shared::start_simpledb_options_builder()
+            .base_path(simple_db.path.join("restored").to_str().unwrap())
+            .build_arc();
+        let restored = crate::simple_db::restore(snapshot_path.to_str().unwrap(), restored_options).unwrap();
+        assert_eq!(select_dinero_sum(&restored, &context), 30);
+
+        let mut transaction = restored.begin(&context).unwrap();
+        let rows = restored.execute(&transaction.context(), restored.parse("SELECT * FROM personas WHERE dinero == 20;").unwrap())
+            .unwrap().data().all().unwrap();
+        transaction.commit().unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].get_column_value("id").unwrap().get_i64().unwrap(), 2);
+    }
+
+    #[test]
+    fn check_database() {
+        let simple_db = TestSimpleDb::create_with_options("check_database", {
+            let mut options_builder = shared::start_simpledb_options_builder();
+            options_builder.memtable_max_size_bytes(1024);
+            options_builder.max_memtables_inactive(0);
+            options_builder
+        });
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64);");
+        for first_id in (0..200).step_by(25) {
+            let values: Vec<String> = (first_id..first_id + 25).map(|id| format!("({}, {})", id, id)).collect();
+            simple_db.execute_in_transaction(&context, &format!("INSERT INTO personas (id, dinero) VALUES {};", values.join(", ")));
+        }
+
+        match simple_db.execute_sql(&context, "CHECK DATABASE;") {
+            StatementResult::CorruptedParts(corrupted_parts) => assert!(corrupted_parts.is_empty()),
+            _ => panic!()
+        };
+        assert!(simple_db.check_integrity().is_empty());
+    }
+
+    #[test]
+    fn string_escapes() {
+        let simple_db = TestSimpleDb::create("string_escapes");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR);");
+        simple_db.execute_in_transaction(&context, r#"INSERT INTO personas (id, nombre) VALUES (1, "Jaime \"Polidura\"\nSegunda linea");"#);
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        let rows = simple_db.execute_sql(&transaction.context(), "SELECT * FROM personas;").data().all().unwrap();
+        assert_eq!(rows[0].get_column_value("nombre").unwrap().get_string().unwrap(), "Jaime \"Polidura\"\nSegunda linea");
+        transaction.commit().unwrap();
+    }
+}
@@ -0,0 +1,114 @@
+use shared::SimpleDbError;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+//Aborts a running query. It is polled by QueryIterator before returning each row, and by the plan steps
+//for each row read from their source, so a query will stop with SimpleDbError::QueryTimeout once it has
+//been cancelled or its timeout has expired, even if no rows are returned
+#[derive(Clone)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    started: Instant,
+    timeout: Option<Duration>, //None if it can only be cancelled explicitly
+}
+
+impl CancellationToken {
+    pub fn create() -> CancellationToken {
+        CancellationToken {
+            cancelled: Arc::new(AtomicBool::new(false)),
+            started: Instant::now(),
+            timeout: None,
+        }
+    }
+
+    pub fn create_with_timeout(timeout: Duration) -> CancellationToken {
+        CancellationToken {
+            timeout: Some(timeout),
+            ..Self::create()
+        }
+    }
+
+    //Clones of the token will also be cancelled
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Relaxed) || self.timeout
+            .map(|timeout| self.started.elapsed() >= timeout)
+            .unwrap_or(false)
+    }
+
+    pub fn check(&self) -> Result<(), SimpleDbError> {
+        if self.is_cancelled() {
+            Err(SimpleDbError::QueryTimeout(self.started.elapsed().as_millis() as u64))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+//Plan steps only hold a token if the query can be cancelled
+pub(crate) fn check_cancelled(cancellation_token: &Option<CancellationToken>) -> Result<(), SimpleDbError> {
+    match cancellation_token {
+        Some(cancellation_token) => cancellation_token.check(),
+        None => Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::CancellationToken;
+    use crate::simple_db::Context;
+    use crate::table::row::RowIterator;
+    use crate::test_utils::TestSimpleDb;
+    use shared::SimpleDbError;
+    use std::time::Duration;
+
+    #[test]
+    fn query_timeout() {
+        let simple_db = TestSimpleDb::create("query_timeout");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64);");
+        for id in 1..=10 {
+            simple_db.execute_in_transaction(&context, &format!("INSERT INTO personas (id, dinero) VALUES ({}, {});", id, id));
+        }
+
+        //Rows are returned until the timeout expires
+        let transaction = simple_db.begin(&context).unwrap();
+        let mut timeout_context = transaction.context();
+        timeout_context.with_cancellation_token(CancellationToken::create_with_timeout(Duration::from_secs(3600)));
+        let mut rows = simple_db.execute_sql(&timeout_context, "SELECT * FROM personas;")
+            .data();
+        assert_eq!(rows.next_n(2).unwrap().len(), 2);
+
+        let mut timeout_context = transaction.context();
+        timeout_context.with_cancellation_token(CancellationToken::create_with_timeout(Duration::ZERO));
+        let mut rows = simple_db.execute_sql(&timeout_context, "SELECT * FROM personas;")
+            .data();
+        assert!(matches!(rows.next_n(2), Err(SimpleDbError::QueryTimeout(_))));
+        assert!(matches!(rows.all(), Err(SimpleDbError::QueryTimeout(_))));
+
+        //Explicitly cancelled
+        let cancellation_token = CancellationToken::create();
+        let mut cancel_context = transaction.context();
+        cancel_context.with_cancellation_token(cancellation_token.clone());
+        let mut rows = simple_db.execute_sql(&cancel_context, "SELECT * FROM personas;")
+            .data();
+        assert!(rows.next().unwrap().is_some());
+        cancellation_token.cancel();
+        assert!(matches!(rows.next(), Err(SimpleDbError::QueryTimeout(_))));
+
+        //Statements that don't return rows are cancelled while they scan the table
+        for statement in ["UPDATE personas SET dinero = 0 WHERE dinero > 100;", "DELETE FROM personas WHERE dinero > 100;"] {
+            assert!(matches!(simple_db.execute(&cancel_context, simple_db.parse(statement).unwrap()), Err(SimpleDbError::QueryTimeout(_))));
+        }
+
+        //Queries without a token are not affected
+        let rows = simple_db.execute_sql(&transaction.context(), "SELECT * FROM personas;")
+            .data().all().unwrap();
+        assert_eq!(rows.len(), 10);
+    }
+}
@@ -1,5 +1,6 @@
 use crate::sql::parser::expression::Expression::Binary;
 use crate::sql::parser::expression::{BinaryOperator, Expression, UnaryOperator};
+use crate::sql::execution::scalar_functions;
 use crate::Row;
 use shared::{SimpleDbError, Type, Value};
 use SimpleDbError::MalformedQuery;
@@ -37,11 +38,18 @@ pub fn evaluate_expression(
             let unary_expr = evaluate_expression(row, &*unary_expr.clone())?;
             evaluate_constant_unary_op(unary_expr, operation.clone())
         },
-        Expression::Identifier(column_name) => {
+        Expression::Identifier(_, column_name) => {
             let value = row.get_column_value(column_name)?;
             Ok(Expression::Literal(value))
         },
         Expression::Literal(value) => Ok(Expression::Literal(value.clone())),
+        Expression::Parameter(_) => Err(MalformedQuery(String::from("Parameters should be bound before evaluating the expression"))),
+        Expression::Function(function_name, args) => {
+            let args = args.iter()
+                .map(|arg| evaluate_expression(row, arg))
+                .collect::<Result<Vec<Expression>, SimpleDbError>>()?;
+            scalar_functions::evaluate(function_name, &args)
+        },
     }
 }
 
@@ -58,7 +66,18 @@ pub fn evaluate_constant_expressions(
             let expression = evaluate_constant_expressions(*expression)?;
             evaluate_constant_unary_op(expression, operator)
         },
-        Expression::Identifier(_) => Ok(expression),
+        Expression::Function(function_name, args) => {
+            let args = args.into_iter()
+                .map(evaluate_constant_expressions)
+                .collect::<Result<Vec<Expression>, SimpleDbError>>()?;
+            if args.iter().all(|arg| arg.is_literal()) {
+                scalar_functions::evaluate(&function_name, &args)
+            } else {
+                Ok(Expression::Function(function_name, args))
+            }
+        },
+        Expression::Identifier(_, _) |
+        Expression::Parameter(_) => Ok(expression),
         Expression::Literal(value) => Ok(Expression::Literal(value)),
     }
 }
@@ -68,11 +87,23 @@ fn evaluate_constant_unary_op(
     operator: UnaryOperator,
 ) -> Result<Expression, SimpleDbError> {
     if !expression.is_literal() {
-        return Ok(expression);
+        return Ok(Expression::Unary(operator, Box::new(expression)));
     }
 
     match operator {
+        UnaryOperator::IsNull => Ok(Expression::Literal(Value::create_boolean(expression.is_null()))),
+        UnaryOperator::IsNotNull => Ok(Expression::Literal(Value::create_boolean(!expression.is_null()))),
         UnaryOperator::Plus => Ok(expression),
+        UnaryOperator::Not => {
+            let value = expression.get_value()?;
+            if value.is_boolean() {
+                Ok(Expression::Literal(Value::create_boolean(!value.get_boolean()?)))
+            } else if value.is_null() {
+                Ok(Expression::Literal(Value::create_null()))
+            } else {
+                Err(MalformedQuery(String::from("NOT can only be applied to booleans")))
+            }
+        },
         UnaryOperator::Minus => {
             let value = expression.get_value()?;
             if value.is_fp_number() {
@@ -93,6 +124,9 @@ fn evaluate_constant_binary_op(
     right: Expression,
     operator: BinaryOperator,
 ) -> Result<Expression, SimpleDbError> {
+    if matches!(operator, BinaryOperator::And | BinaryOperator::Or) && left.is_literal() != right.is_literal() {
+        return evaluate_partially_constant_logical_op(left, right, operator);
+    }
     if !left.is_constant_expression() || !right.is_constant_expression() {
         return Ok(Binary(operator, Box::new(left), Box::new(right)));
     }
@@ -102,6 +136,7 @@ fn evaluate_constant_binary_op(
         BinaryOperator::Subtract => left.subtract(&right),
         BinaryOperator::Multiply => left.multiply(&right),
         BinaryOperator::Divide => left.divide(&right),
+        BinaryOperator::Modulo => left.modulo(&right),
         BinaryOperator::And => left.and(&right),
         BinaryOperator::Or => left.or(&right),
         BinaryOperator::NotEqual => left.ne(&right),
@@ -110,6 +145,28 @@ fn evaluate_constant_binary_op(
         BinaryOperator::GreaterEqual => left.ge(&right),
         BinaryOperator::Less => left.lt(&right),
         BinaryOperator::LessEqual => left.le(&right),
+        BinaryOperator::Like => left.like(&right),
+        BinaryOperator::ILike => left.ilike(&right),
+    }
+}
+
+//AND/OR expressions where only one side is a boolean literal. For example: id > 5 AND 1 == 2 is always false
+fn evaluate_partially_constant_logical_op(
+    left: Expression,
+    right: Expression,
+    operator: BinaryOperator,
+) -> Result<Expression, SimpleDbError> {
+    let (constant, other) = if left.is_literal() { (left, right) } else { (right, left) };
+    let constant_value = constant.get_value()?;
+
+    if !constant_value.is_boolean() {
+        return Ok(Binary(operator, Box::new(constant), Box::new(other)));
+    }
+
+    match (&operator, constant_value.get_boolean()?) {
+        (BinaryOperator::And, false) => Ok(Expression::Literal(Value::create_boolean(false))),
+        (BinaryOperator::Or, true) => Ok(Expression::Literal(Value::create_boolean(true))),
+        _ => Ok(other),
     }
 }
 
@@ -119,15 +176,21 @@ mod test {
     use crate::index::secondary_indexes::SecondaryIndexes;
     use crate::sql::parser::expression::Expression::Binary;
     use crate::sql::parser::expression::{BinaryOperator, Expression};
-    use crate::sql::expression_evaluator::{evaluate_constant_expressions, evaluate_where_expression};
+    use crate::sql::execution::expression_evaluator::{evaluate_constant_expressions, evaluate_expression, evaluate_where_expression};
     use crate::sql::parser::parser::Parser;
     use crate::table::record::Record;
     use crate::table::table::Table;
+    use crate::table::row_cache::RowCache;
+    use crate::table::select_cache::SelectCache;
     use crate::table::table_descriptor::TableDescriptor;
-    use crate::{Column, Row, Schema};
+    use crate::{Column, ColumnDefinition, Row, Schema};
+    use crate::simple_db::Context;
+    use crate::test_utils::TestSimpleDb;
     use bytes::Bytes;
-    use shared::{SimpleDbOptions, Type, Value};
-    use std::sync::Arc;
+    use shared::{SimpleDbError, SimpleDbOptions, Type, Value};
+    use std::collections::HashSet;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::{Arc, Mutex, RwLock};
     use storage::Storage;
 
     //Where id == 10 OR dinero > 100
@@ -162,6 +225,36 @@ mod test {
         assert!(!result.unwrap());
     }
 
+    #[test]
+    fn where_not() {
+        let not = Parser::create(String::from("NOT dinero > 100 AND id == 10")).parse_expression().unwrap();
+
+        assert!(evaluate_where_expression(&id_dinero_nombre_row(10, Some(90), None), &not).unwrap());
+        assert!(!evaluate_where_expression(&id_dinero_nombre_row(10, Some(110), None), &not).unwrap());
+        assert!(!evaluate_where_expression(&id_dinero_nombre_row(10, None, None), &not).unwrap());
+
+        let not_number = Parser::create(String::from("NOT dinero")).parse_expression().unwrap();
+        assert!(evaluate_where_expression(&id_dinero_nombre_row(10, Some(90), None), &not_number).is_err());
+    }
+
+    #[test]
+    fn where_all_any() {
+        let all = Parser::create(String::from("dinero > ALL (10, 30, 20)")).parse_expression().unwrap();
+        let any = Parser::create(String::from("dinero > ANY (10, 30, 20)")).parse_expression().unwrap();
+
+        let row = id_dinero_nombre_row(1, Some(31), None);
+        assert!(evaluate_where_expression(&row, &all).unwrap());
+        assert!(evaluate_where_expression(&row, &any).unwrap());
+
+        let row = id_dinero_nombre_row(1, Some(30), None);
+        assert!(!evaluate_where_expression(&row, &all).unwrap());
+        assert!(evaluate_where_expression(&row, &any).unwrap());
+
+        let row = id_dinero_nombre_row(1, Some(10), None);
+        assert!(!evaluate_where_expression(&row, &all).unwrap());
+        assert!(!evaluate_where_expression(&row, &any).unwrap());
+    }
+
     #[test]
     fn constant_mixed() {
         let mut parser = Parser::create(String::from("dinero > (1 + 20) OR id > 10"));
@@ -174,12 +267,12 @@ mod test {
             BinaryOperator::Or,
             Box::new(Binary(
                 BinaryOperator::Greater,
-                Box::new(Expression::Identifier(String::from("dinero"))),
+                Box::new(Expression::Identifier(None, String::from("dinero"))),
                 Box::new(Expression::Literal(Value::create_i64(21))),
             )),
             Box::new(Binary(
                 BinaryOperator::Greater,
-                Box::new(Expression::Identifier(String::from("id"))),
+                Box::new(Expression::Identifier(None, String::from("id"))),
                 Box::new(Expression::Literal(Value::create_i64(10)))),
             )),
         );
@@ -207,6 +300,94 @@ mod test {
         assert_eq!(result.get_boolean().unwrap(), ((1 > 2) || (1 <= 2)) && (1 == 1));
     }
 
+    #[test]
+    fn constant_partially_logical_operations() {
+        let mut parser = Parser::create(String::from("id > 5 AND 1 == 2"));
+        let result = evaluate_constant_expressions(parser.parse_expression().unwrap()).unwrap();
+        assert_eq!(result, Expression::Literal(Value::create_boolean(false)));
+
+        let mut parser = Parser::create(String::from("1 == 1 OR id > 5"));
+        let result = evaluate_constant_expressions(parser.parse_expression().unwrap()).unwrap();
+        assert_eq!(result, Expression::Literal(Value::create_boolean(true)));
+
+        let mut parser = Parser::create(String::from("id > 5 AND 1 == 1"));
+        let result = evaluate_constant_expressions(parser.parse_expression().unwrap()).unwrap();
+        assert_eq!(result, Expression::Binary(
+            BinaryOperator::Greater,
+            Box::new(Expression::Identifier(None, String::from("id"))),
+            Box::new(Expression::Literal(Value::create_i64(5))),
+        ));
+    }
+
+    #[test]
+    fn functions() {
+        let row = id_dinero_nombre_row(1, Some(90), Some("Jaime"));
+
+        assert_eq!(evaluate(&row, "LENGTH(nombre)"), Value::create_i64(5));
+        assert_eq!(evaluate(&row, "UPPER(nombre)"), Value::create_string(String::from("JAIME")));
+        assert_eq!(evaluate(&row, "lower(nombre)"), Value::create_string(String::from("jaime")));
+        assert_eq!(evaluate(&row, "ABS(dinero - 100)"), Value::create_i64(10));
+        assert_eq!(evaluate(&row, "ABS(-2.5)"), Value::create_f64(2.5));
+        assert!(evaluate_where_expression(&row, &parse("LENGTH(UPPER(nombre)) == 5 AND ABS(dinero) > 10")).unwrap());
+
+        //NULL arguments produce NULL
+        let row = id_dinero_nombre_row(1, None, None);
+        assert!(evaluate(&row, "LENGTH(nombre)").is_null());
+        assert!(evaluate(&row, "UPPER(nombre)").is_null());
+        assert!(evaluate(&row, "ABS(dinero)").is_null());
+        assert!(evaluate_where_expression(&row, &parse("LOWER(nombre) IS NULL")).unwrap());
+    }
+
+    #[test]
+    fn functions_invalid_arguments() {
+        let row = id_dinero_nombre_row(1, Some(90), Some("Jaime"));
+
+        assert!(matches!(evaluate_expression(&row, &parse("UPPER(dinero)")), Err(SimpleDbError::InvalidType(_))));
+        assert!(matches!(evaluate_expression(&row, &parse("ABS(nombre)")), Err(SimpleDbError::InvalidType(_))));
+        assert!(matches!(evaluate_expression(&row, &parse("LENGTH(nombre, nombre)")), Err(SimpleDbError::MalformedQuery(_))));
+        assert!(matches!(evaluate_expression(&row, &parse("FOO(nombre)")), Err(SimpleDbError::MalformedQuery(_))));
+    }
+
+    #[test]
+    fn functions_null_handling() {
+        let row = id_dinero_nombre_row(1, None, None);
+        //First argument is null, a later one is returned
+        assert_eq!(evaluate(&row, "COALESCE(nombre, \"Anonimo\")"), Value::create_string(String::from("Anonimo")));
+        assert_eq!(evaluate(&row, "COALESCE(dinero, nombre, 10, 20)"), Value::create_i64(10));
+        assert_eq!(evaluate(&row, "IFNULL(dinero, 0)"), Value::create_i64(0));
+        //Every argument is null
+        assert!(evaluate(&row, "COALESCE(nombre, dinero)").is_null());
+        assert!(evaluate(&row, "IFNULL(nombre, dinero)").is_null());
+
+        let row = id_dinero_nombre_row(1, Some(90), Some("Jaime"));
+        assert_eq!(evaluate(&row, "COALESCE(nombre, \"Anonimo\")"), Value::create_string(String::from("Jaime")));
+        assert_eq!(evaluate(&row, "IFNULL(dinero, 0)"), Value::create_i64(90));
+
+        assert!(matches!(evaluate_expression(&row, &parse("IFNULL(dinero)")), Err(SimpleDbError::MalformedQuery(_))));
+        assert!(matches!(evaluate_expression(&row, &parse("COALESCE()")), Err(SimpleDbError::MalformedQuery(_))));
+    }
+
+    #[test]
+    fn constant_functions() {
+        let result = evaluate_constant_expressions(parse("ABS(-3) + LENGTH(\"abc\")")).unwrap();
+        assert_eq!(result, Expression::Literal(Value::create_i64(6)));
+
+        let result = evaluate_constant_expressions(parse("UPPER(nombre) == LOWER(\"A\")")).unwrap();
+        assert_eq!(result, Expression::Binary(
+            BinaryOperator::Equal,
+            Box::new(Expression::Function(String::from("UPPER"), vec![Expression::Identifier(None, String::from("nombre"))])),
+            Box::new(Expression::Literal(Value::create_string(String::from("a")))),
+        ));
+    }
+
+    fn evaluate(row: &Row, expression: &str) -> Value {
+        evaluate_expression(row, &parse(expression)).unwrap().get_value().unwrap()
+    }
+
+    fn parse(expression: &str) -> Expression {
+        Parser::create(String::from(expression)).parse_expression().unwrap()
+    }
+
     fn id_dinero_nombre_row(
         id: usize, //0
         dinero: Option<usize>, //1
@@ -228,24 +409,51 @@ mod test {
             table_name: String::from("personas"),
             storage_keyspace_id: 1,
             secondary_indexes: SecondaryIndexes::create_mock(Arc::new(SimpleDbOptions::default())),
-            database: Database::create_mock(&Arc::new(SimpleDbOptions::default()))
+            database: Database::create_mock(&Arc::new(SimpleDbOptions::default())),
+            row_cache: RowCache::create(&Arc::new(SimpleDbOptions::default())),
+            select_cache: SelectCache::create(&Arc::new(SimpleDbOptions::default())),
+            transactions: Mutex::new(HashSet::new()),
+            dropped: AtomicBool::new(false),
+            writes_lock: RwLock::new(()),
+            clock: shared::current_time_millis,
         };
 
         table.add_columns(vec![
-            (String::from("id"), Type::I64, true),
-            (String::from("dinero"), Type::I64, false),
-            (String::from("nombre"), Type::String, false),
+            ColumnDefinition::create("id", Type::I64, true),
+            ColumnDefinition::create("dinero", Type::I64, false),
+            ColumnDefinition::create("nombre", Type::String, false),
         ]);
 
         Row {
+            selected_columns_id: None,
             primary_column_value: Value::create(Bytes::copy_from_slice(id.to_le_bytes().as_slice()), Type::I64)
                 .unwrap(),
             storage_engine_record: record.build(),
             schema: Schema::create(vec![
                 Column::create_primary("id"),
                 Column::create("dinero", 1),
-                Column::create("nombre", 2),
+                Column { column_type: Type::String, ..Column::create("nombre", 2) },
             ]),
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn select_ilike() {
+        let simple_db = TestSimpleDb::create("select_ilike");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR);");
+        simple_db.execute_sql(&context, "CREATE INDEX ON personas (nombre);");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, nombre) VALUES (1, \"Jaime\");");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, nombre) VALUES (2, \"Molon\");");
+
+        let transaction = simple_db.begin(&context).unwrap();
+        let rows = simple_db.execute_sql(&transaction.context(), "SELECT * FROM personas WHERE nombre ILIKE \"jaime\";")
+            .data().all().unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].get_column_value("nombre").unwrap().get_string().unwrap(), "Jaime");
+
+        let rows = simple_db.execute_sql(&transaction.context(), "SELECT * FROM personas WHERE nombre LIKE \"jaime\";")
+            .data().all().unwrap();
+        assert!(rows.is_empty());
+    }
+}
@@ -1,3 +1,4 @@
 pub mod statement_executor;
 pub mod expression_evaluator;
+pub mod scalar_functions;
 pub mod sort;
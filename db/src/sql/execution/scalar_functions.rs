@@ -0,0 +1,217 @@
+use crate::sql::parser::expression::Expression;
+use shared::{SimpleDbError, Type, Value};
+use SimpleDbError::{InvalidType, MalformedQuery};
+
+//Functions that produce one value per row. Unlike aggregates, they can be used in any expression
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ScalarFunction {
+    Length,
+    Upper,
+    Lower,
+    Abs,
+    Coalesce,
+    IfNull,
+}
+
+impl ScalarFunction {
+    fn from_name(function_name: &str) -> Result<ScalarFunction, SimpleDbError> {
+        match function_name.to_uppercase().as_str() {
+            "LENGTH" => Ok(ScalarFunction::Length),
+            "UPPER" => Ok(ScalarFunction::Upper),
+            "LOWER" => Ok(ScalarFunction::Lower),
+            "ABS" => Ok(ScalarFunction::Abs),
+            "COALESCE" => Ok(ScalarFunction::Coalesce),
+            "IFNULL" => Ok(ScalarFunction::IfNull),
+            _ => Err(MalformedQuery(format!("Unknown function {}", function_name)))
+        }
+    }
+
+    //Min number of arguments, max number of arguments. None if there is no max
+    fn n_args(&self) -> (usize, Option<usize>) {
+        match self {
+            ScalarFunction::Length |
+            ScalarFunction::Upper |
+            ScalarFunction::Lower |
+            ScalarFunction::Abs => (1, Some(1)),
+            ScalarFunction::IfNull => (2, Some(2)),
+            ScalarFunction::Coalesce => (1, None),
+        }
+    }
+}
+
+//args_types contains the type produced by each argument
+pub(crate) fn get_result_type(
+    function_name: &str,
+    args: &[Expression],
+    args_types: &[Type],
+) -> Result<Type, SimpleDbError> {
+    let function = get_function(function_name, args)?;
+    let arg_type = args_types[0];
+
+    match function {
+        ScalarFunction::Coalesce |
+        ScalarFunction::IfNull => get_first_not_null_type(args, args_types),
+        ScalarFunction::Length if matches!(arg_type, Type::String | Type::Null) => Ok(Type::I64),
+        ScalarFunction::Upper |
+        ScalarFunction::Lower if matches!(arg_type, Type::String | Type::Null) => Ok(Type::String),
+        ScalarFunction::Abs if arg_type.is_fp_number() => Ok(Type::F64),
+        ScalarFunction::Abs if arg_type.is_unsigned_integer_number() => Ok(arg_type),
+        ScalarFunction::Abs if arg_type.is_signed_integer_number() => Ok(Type::I64),
+        ScalarFunction::Abs if matches!(arg_type, Type::Null) => Ok(Type::Null),
+        _ => Err(InvalidType(args[0].to_string())),
+    }
+}
+
+//Expect every argument to be a literal. A NULL argument produces NULL, except for COALESCE and IFNULL
+pub(crate) fn evaluate(
+    function_name: &str,
+    args: &[Expression],
+) -> Result<Expression, SimpleDbError> {
+    let function = get_function(function_name, args)?;
+    if matches!(function, ScalarFunction::Coalesce | ScalarFunction::IfNull) {
+        return get_first_not_null(args);
+    }
+
+    let value = args[0].get_value()?;
+    if value.is_null() {
+        return Ok(Expression::Literal(Value::create_null()));
+    }
+
+    let result = match function {
+        ScalarFunction::Length => Value::create_i64(get_string(&value, &args[0])?.chars().count() as i64),
+        ScalarFunction::Upper => Value::create_string(get_string(&value, &args[0])?.to_uppercase()),
+        ScalarFunction::Lower => Value::create_string(get_string(&value, &args[0])?.to_lowercase()),
+        ScalarFunction::Abs if value.is_fp_number() => Value::create_f64(value.get_f64()?.abs()),
+        ScalarFunction::Abs if value.get_type().is_unsigned_integer_number() => value,
+        ScalarFunction::Abs if value.is_integer_number() => Value::create_i64(value.get_i64()?.checked_abs()
+            .ok_or(SimpleDbError::ArithmeticOverflow)?),
+        _ => return Err(InvalidType(args[0].to_string())),
+    };
+
+    Ok(Expression::Literal(result))
+}
+
+fn get_function(function_name: &str, args: &[Expression]) -> Result<ScalarFunction, SimpleDbError> {
+    let function = ScalarFunction::from_name(function_name)?;
+    match function.n_args() {
+        (min_args, Some(max_args)) if args.len() < min_args || args.len() > max_args => {
+            Err(MalformedQuery(format!("{} expects {} arguments", function_name, max_args)))
+        },
+        (min_args, None) if args.len() < min_args => {
+            Err(MalformedQuery(format!("{} expects at least {} arguments", function_name, min_args)))
+        },
+        _ => Ok(function)
+    }
+}
+
+//The value returned will be the first not null argument, so the function produces the type of the first not null argument.
+//The rest of the arguments have to be coercible to it
+fn get_first_not_null_type(args: &[Expression], args_types: &[Type]) -> Result<Type, SimpleDbError> {
+    let result_type = args_types.iter()
+        .find(|arg_type| !arg_type.is_null())
+        .copied()
+        .unwrap_or(Type::Null);
+
+    for (arg, arg_type) in args.iter().zip(args_types.iter()) {
+        if !arg_type.can_be_coerced_to(&result_type) {
+            return Err(InvalidType(arg.to_string()));
+        }
+    }
+
+    Ok(result_type)
+}
+
+fn get_first_not_null(args: &[Expression]) -> Result<Expression, SimpleDbError> {
+    for arg in args {
+        if !arg.get_value()?.is_null() {
+            return Ok(arg.clone());
+        }
+    }
+
+    Ok(Expression::Literal(Value::create_null()))
+}
+
+fn get_string(value: &Value, arg: &Expression) -> Result<String, SimpleDbError> {
+    if !value.is_string() {
+        return Err(InvalidType(arg.to_string()));
+    }
+
+    value.get_string()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::simple_db::Context;
+    use crate::test_utils::TestSimpleDb;
+    use shared::{SimpleDbError, Type};
+
+    #[test]
+    fn select_functions() {
+        let simple_db = TestSimpleDb::create("select_functions");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR, dinero I64);");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, nombre, dinero) VALUES (1, \"Jaime\", 5);");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, nombre, dinero) VALUES (2, \"Ana\", 25);");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, nombre, dinero) VALUES (3, \"jaime\", 40);");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, dinero) VALUES (4, 40);");
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        let mut query_iterator = simple_db.execute(&transaction.context(), simple_db.parse(
+            "SELECT id, UPPER(nombre), LENGTH(nombre) AS longitud FROM personas WHERE ABS(dinero - 20) > 10 ORDER BY id DESC;"
+        ).unwrap()).unwrap().data();
+        let columns = query_iterator.get_selected_columns();
+        assert_eq!(columns.iter().map(|column| column.column_name.clone()).collect::<Vec<String>>(), vec!["id", "UPPER(nombre)", "longitud"]);
+        assert_eq!(columns.iter().map(|column| column.column_type).collect::<Vec<Type>>(), vec![Type::I64, Type::String, Type::I64]);
+
+        let rows = query_iterator.all().unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].get_column_value("id").unwrap().get_i64().unwrap(), 4);
+        assert!(rows[0].get_column_value("UPPER(nombre)").unwrap().is_null());
+        assert_eq!(rows[1].get_column_value("UPPER(nombre)").unwrap().get_string().unwrap(), "JAIME");
+        assert_eq!(rows[2].get_column_value("LENGTH(nombre)").unwrap().get_i64().unwrap(), 5);
+
+        let rows = simple_db.execute_sql(&transaction.context(), "SELECT DISTINCT LOWER(nombre) FROM personas WHERE id < 4;")
+            .data().all().unwrap();
+        assert_eq!(rows.len(), 2);
+
+        assert!(matches!(simple_db.execute(&transaction.context(), simple_db.parse("SELECT UPPER(dinero) FROM personas;").unwrap()),
+            Err(SimpleDbError::InvalidType(_))));
+        assert!(matches!(simple_db.execute(&transaction.context(), simple_db.parse("SELECT * FROM personas WHERE ABS(nombre) > 1;").unwrap()),
+            Err(SimpleDbError::InvalidType(_))));
+        transaction.commit().unwrap();
+    }
+
+    #[test]
+    fn select_coalesce() {
+        let simple_db = TestSimpleDb::create("select_coalesce");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR, apellido VARCHAR, dinero F64);");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, nombre, dinero) VALUES (1, \"Jaime\", 2.5);");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, apellido) VALUES (2, \"Polidura\");");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id) VALUES (3);");
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        let mut query_iterator = simple_db.execute(&transaction.context(), simple_db.parse(
+            "SELECT id, COALESCE(nombre, apellido), IFNULL(dinero, 0) FROM personas;"
+        ).unwrap()).unwrap().data();
+        let columns = query_iterator.get_selected_columns();
+        assert_eq!(columns.iter().map(|column| column.column_type).collect::<Vec<Type>>(), vec![Type::I64, Type::String, Type::F64]);
+
+        let rows = query_iterator.all().unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].get_column_value("COALESCE(nombre, apellido)").unwrap().get_string().unwrap(), "Jaime");
+        assert_eq!(rows[0].get_column_value("IFNULL(dinero, 0)").unwrap().get_f64().unwrap(), 2.5);
+        assert_eq!(rows[1].get_column_value("COALESCE(nombre, apellido)").unwrap().get_string().unwrap(), "Polidura");
+        assert_eq!(rows[1].get_column_value("IFNULL(dinero, 0)").unwrap().get_f64().unwrap(), 0.0);
+        assert!(rows[2].get_column_value("COALESCE(nombre, apellido)").unwrap().is_null());
+
+        let rows = simple_db.execute_sql(&transaction.context(), "SELECT * FROM personas WHERE COALESCE(nombre, apellido, \"\") == \"\";")
+            .data().all().unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].get_column_value("id").unwrap().get_i64().unwrap(), 3);
+
+        assert!(matches!(simple_db.execute(&transaction.context(), simple_db.parse("SELECT COALESCE(nombre, 1) FROM personas;").unwrap()),
+            Err(SimpleDbError::InvalidType(_))));
+        transaction.commit().unwrap();
+    }
+}
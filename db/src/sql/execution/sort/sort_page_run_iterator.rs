@@ -143,7 +143,7 @@ impl SortPageRunIterator {
         }
 
         let (row_bytes, n_rows, next_offset) = self.file.read_row_bytes(offset)?.unwrap();
-        let rows = Row::deserialize_rows(&row_bytes, n_rows, &self.table_schema);
+        let rows = Row::deserialize_rows(&row_bytes, n_rows, &self.table_schema)?;
         return Ok(Some((rows, next_offset)));
     }
 
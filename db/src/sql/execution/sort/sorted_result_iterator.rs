@@ -3,14 +3,15 @@ use crate::table::row::RowIterator;
 use crate::table::table::Table;
 use crate::Row;
 use shared::SimpleDbError;
+use std::collections::VecDeque;
 use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct SortedResultIterator {
-    sorted_file: SortFile,
+    sorted_file: Option<SortFile>, //None if the rows have been sorted in memory
     table: Arc<Table>,
 
-    rows_to_return: Vec<Row>,
+    rows_to_return: VecDeque<Row>,
     current_offset: usize,
 }
 
@@ -20,25 +21,41 @@ impl SortedResultIterator {
         table: Arc<Table>,
     ) -> SortedResultIterator {
         SortedResultIterator {
-            rows_to_return: Vec::new(),
+            sorted_file: Some(sorted_file),
+            rows_to_return: VecDeque::new(),
             current_offset: 0,
-            sorted_file,
             table
         }
     }
+
+    pub fn create_in_memory(
+        sorted_rows: Vec<Row>,
+        table: Arc<Table>,
+    ) -> SortedResultIterator {
+        SortedResultIterator {
+            rows_to_return: VecDeque::from(sorted_rows),
+            current_offset: 0,
+            sorted_file: None,
+            table
+        }
+    }
+
+    fn sorted_file_size(&self) -> usize {
+        self.sorted_file.as_ref().map(|sorted_file| sorted_file.size()).unwrap_or(0)
+    }
 }
 
 impl RowIterator for SortedResultIterator {
     fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
-        if self.current_offset < self.sorted_file.size() && self.rows_to_return.is_empty() {
-            let (row_bytes, n_rows, next_offset) = self.sorted_file.read_row_bytes(self.current_offset)?
+        if self.current_offset < self.sorted_file_size() && self.rows_to_return.is_empty() {
+            let (row_bytes, n_rows, next_offset) = self.sorted_file.as_ref().unwrap().read_row_bytes(self.current_offset)?
                 .unwrap();
 
             self.current_offset = next_offset;
-            self.rows_to_return = Row::deserialize_rows(&row_bytes, n_rows, self.table.get_schema());
-            return Ok(Some(self.rows_to_return.remove(0)));
+            self.rows_to_return = VecDeque::from(Row::deserialize_rows(&row_bytes, n_rows, self.table.get_schema())?);
+            return Ok(Some(self.rows_to_return.pop_front().unwrap()));
         } else if !self.rows_to_return.is_empty() {
-            return Ok(Some(self.rows_to_return.remove(0)));
+            return Ok(Some(self.rows_to_return.pop_front().unwrap()));
         } else {
             return Ok(None);
         }
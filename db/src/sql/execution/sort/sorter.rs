@@ -2,15 +2,17 @@ use crate::sql::execution::sort::sort_file::SortFile;
 use crate::sql::execution::sort::sort_page::SortPage;
 use crate::sql::execution::sort::sort_page_run_iterator::SortPageRunIterator;
 use crate::sql::execution::sort::sorted_result_iterator::SortedResultIterator;
+use crate::sql::cancellation_token::CancellationToken;
 use crate::sql::plan::plan_step::PlanStep;
 use crate::table::block_row_iterator::{RowBlock, RowBlockIterator};
-use crate::table::row::MockRowIterator;
+use crate::table::row::{MockRowIterator, RowIterator};
 use crate::table::selection::Selection;
 use crate::table::table::Table;
 use crate::{QueryIterator, Row, Sort};
 use bytes::Buf;
 use shared::{SimpleDbError, SimpleDbFileMode, SimpleDbOptions};
 use std::cmp::Ordering;
+use std::collections::VecDeque;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering::Relaxed;
 use std::sync::Arc;
@@ -25,12 +27,14 @@ pub struct Sorter {
     //(to write to the output file) and an immutable reference to iterate the input file.
     last_file_id: AtomicUsize,
 
-    temporary_space: Arc<TemporarySpace>,
+    //Only created if the rows don't fit in memory
+    temporary_space: Option<Arc<TemporarySpace>>,
     options: Arc<SimpleDbOptions>,
     selection: Selection,
     table: Arc<Table>,
     source: PlanStep,
     sort: Sort,
+    cancellation_token: Option<CancellationToken>,
 }
 
 impl Sorter {
@@ -42,8 +46,9 @@ impl Sorter {
         sort: Sort,
     ) -> Result<Sorter, SimpleDbError> {
         Ok(Sorter {
-            temporary_space: Arc::new(table.storage.create_temporary_space()?),
+            temporary_space: None,
             last_file_id: AtomicUsize::new(0),
+            cancellation_token: None,
             selection,
             options,
             source,
@@ -52,13 +57,56 @@ impl Sorter {
         })
     }
 
+    pub(crate) fn set_source(&mut self, source: PlanStep) {
+        self.source = source;
+    }
+
+    pub(crate) fn set_cancellation_token(&mut self, cancellation_token: CancellationToken) {
+        self.cancellation_token = Some(cancellation_token);
+    }
+
     pub fn sort(
         &mut self,
     ) -> Result<QueryIterator<SortedResultIterator>, SimpleDbError> {
+        let mut source = QueryIterator::create(
+            Selection::All, self.source.clone(), self.table.get_schema().clone()
+        );
+        if let Some(cancellation_token) = &self.cancellation_token {
+            source.with_cancellation_token(cancellation_token.clone());
+        }
+        //Rows are kept in memory until they exceed in_memory_sort_max_bytes
+        let mut rows_in_memory = Vec::new();
+        let mut rows_in_memory_size_bytes = 0;
+
+        while let Some(row) = source.next()? {
+            rows_in_memory_size_bytes += row.serialized_size();
+            rows_in_memory.push(row);
+
+            if rows_in_memory_size_bytes > self.options.in_memory_sort_max_bytes {
+                return self.external_sort(rows_in_memory, source);
+            }
+        }
+
+        self.sort_rows(&mut rows_in_memory);
+
+        Ok(QueryIterator::create(
+            self.selection.clone(),
+            SortedResultIterator::create_in_memory(rows_in_memory, self.table.clone()),
+            self.table.get_schema().clone()
+        ))
+    }
+
+    //rows_in_memory are the rows already read from source
+    fn external_sort(
+        &mut self,
+        rows_in_memory: Vec<Row>,
+        source: QueryIterator<PlanStep>,
+    ) -> Result<QueryIterator<SortedResultIterator>, SimpleDbError> {
+        self.temporary_space = Some(Arc::new(self.table.storage.create_temporary_space()?));
         let mut output = self.create_next_sort_file()?;
         let mut input = self.create_next_sort_file()?;
 
-        let n_pages_written_pass1 = self.pass_0(&mut output)?; //Writes to input
+        let n_pages_written_pass1 = self.pass_0(rows_in_memory, source, &mut output)?; //Writes to input
         let n_total_passess = self.calculate_n_total_passes(n_pages_written_pass1);
 
         let mut prev_n_pages_per_run = 1;
@@ -91,8 +139,8 @@ impl Sorter {
     ) -> Result<(), SimpleDbError> {
         let mut current_size_bytes_output_buffer = 0;
         let mut output_buffer: Vec<Row> = Vec::new();
-        let mut buffer_right: Vec<Row> = Vec::new();
-        let mut buffer_left: Vec<Row> = Vec::new();
+        let mut buffer_right: VecDeque<Row> = VecDeque::new();
+        let mut buffer_left: VecDeque<Row> = VecDeque::new();
         let mut input_iterator = SortPageRunIterator::create(
             input.clone(),
             self.options.sort_page_size_bytes,
@@ -102,10 +150,10 @@ impl Sorter {
 
         while input_iterator.has_next() || !buffer_left.is_empty() || !buffer_right.is_empty() {
             if buffer_left.is_empty() {
-                buffer_left = input_iterator.next_left()?.unwrap_or(Vec::new());
+                buffer_left = VecDeque::from(input_iterator.next_left()?.unwrap_or(Vec::new()));
             }
             if buffer_right.is_empty() {
-                buffer_right = input_iterator.next_right()?.unwrap_or(Vec::new());
+                buffer_right = VecDeque::from(input_iterator.next_right()?.unwrap_or(Vec::new()));
             }
             if buffer_right.is_empty() && buffer_left.is_empty() {
                 break;
@@ -143,17 +191,29 @@ impl Sorter {
     }
 
     //In pass one we split the rows by pages, sort them and store them in the page.
+    //The rows already read in memory are written first, followed by the rest of the source rows.
     //This function returns the number of pages written. (Overflow pages only count for 1 page written)
     fn pass_0(
         &mut self,
+        rows_in_memory: Vec<Row>,
+        source: QueryIterator<PlanStep>,
         file_input: &mut SortFile
     ) -> Result<usize, SimpleDbError> {
-        let mut query_iterator = RowBlockIterator::create(self.row_bytes_per_sort_page(), QueryIterator::create(
-            Selection::All, self.source.clone(), self.table.get_schema().clone()
-        ));
+        let mut rows_in_memory_iterator = RowBlockIterator::<MockRowIterator>::create_from_vec(
+            self.row_bytes_per_sort_page(), rows_in_memory
+        );
+        let mut query_iterator = RowBlockIterator::create(self.row_bytes_per_sort_page(), source);
         let mut n_pages_written = 0;
 
-        while let Some(block_of_rows) = query_iterator.next_block()? {
+        loop {
+            let block_of_rows = match rows_in_memory_iterator.next_block()? {
+                Some(block_of_rows) => block_of_rows,
+                None => match query_iterator.next_block()? {
+                    Some(block_of_rows) => block_of_rows,
+                    None => break,
+                },
+            };
+
             n_pages_written += 1;
             match block_of_rows {
                 RowBlock::Overflow(overflow_row) => self.write_overflow_row_pages(file_input, overflow_row)?,
@@ -265,8 +325,8 @@ impl Sorter {
 
     fn serialize_rows(rows: &mut Vec<Row>) -> Vec<u8> {
         let mut serialized: Vec<u8> = Vec::new();
-        while !rows.is_empty() {
-            serialized.extend(rows.remove(0).serialize());
+        for row in rows.drain(..) {
+            serialized.extend(row.serialize());
         }
 
         serialized
@@ -288,32 +348,32 @@ impl Sorter {
 
     fn take_min(
         &self,
-        left_vec: &mut Vec<Row>,
-        right_vec: &mut Vec<Row>,
+        left_vec: &mut VecDeque<Row>,
+        right_vec: &mut VecDeque<Row>,
     ) -> Option<Row> {
         if left_vec.is_empty() && right_vec.is_empty() {
             return None;
         }
         if left_vec.is_empty() {
-            return Some(right_vec.remove(0));
+            return right_vec.pop_front();
         }
         if right_vec.is_empty() {
-            return Some(left_vec.remove(0));
+            return left_vec.pop_front();
         }
 
-        let right_value = right_vec.get(0).unwrap();
-        let left_value = left_vec.get(0).unwrap();
+        let right_value = right_vec.front().unwrap();
+        let left_value = left_vec.front().unwrap();
 
         match self.sort.compare(left_value, right_value) {
             Ordering::Less |
-            Ordering::Equal => Some(left_vec.remove(0)),
-            Ordering::Greater => Some(right_vec.remove(0))
+            Ordering::Equal => left_vec.pop_front(),
+            Ordering::Greater => right_vec.pop_front()
         }
     }
 
     fn create_next_sort_file(&mut self) -> Result<SortFile, SimpleDbError> {
         let file_id = self.last_file_id.fetch_add(1, Relaxed);
-        let file = self.temporary_space.create_file(
+        let file = self.temporary_space.as_ref().unwrap().create_file(
             file_id.to_string().as_str(), SimpleDbFileMode::AppendOnly
         )?;
         Ok(SortFile::create(file, self.options.sort_page_size_bytes))
@@ -330,6 +390,7 @@ impl Clone for Sorter {
             table: self.table.clone(),
             source: self.source.clone(),
             sort: self.sort.clone(),
+            cancellation_token: self.cancellation_token.clone(),
         }
     }
 }
\ No newline at end of file
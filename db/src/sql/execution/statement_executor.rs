@@ -4,22 +4,31 @@ use crate::simple_db::{Context, StatementResult};
 use crate::sql::parser::expression::Expression;
 use crate::sql::plan::planner::Planner;
 use crate::sql::query_iterator::QueryIterator;
-use crate::sql::parser::statement::{CreateTableStatement, DeleteStatement, InsertStatement, SelectStatement, Statement, UpdateStatement};
+use crate::sql::parser::statement::{CreateTableStatement, DeleteStatement, InsertStatement, OnConflict, SelectStatement, Statement, UpdateStatement};
 use crate::sql::validator::StatementValidator;
 use crate::table::table::Table;
-use crate::{CreateIndexStatement, IndexType};
+use crate::users::users::Users;
+use crate::{AlterTableOperation, AlterTableStatement, CreateIndexStatement, DropIndexStatement, IndexCreation, IndexState, IndexType};
 use bytes::Bytes;
 use shared::SimpleDbError::MalformedQuery;
 use shared::{SimpleDbError, SimpleDbOptions, Value};
 use std::sync::Arc;
 use storage::transactions::transaction::Transaction;
+use storage::transactions::transaction_manager::IsolationLevel;
 use crate::sql::execution::expression_evaluator::{evaluate_constant_expressions, evaluate_expression};
 use crate::sql::optimizer::PlanOptimizer;
 use crate::table::row::RowIterator;
-use crate::table::schema::Column;
+use crate::table::schema::{Column, Schema};
+use crate::sql::plan::plan_step::PlanStep;
+use crate::sql::plan::steps::cached_result_step::CachedResultStep;
+use crate::sql::plan::ScanType;
+
+//Savepoint names written by users are identifiers, so they can't start with $
+const MULTI_ROW_INSERT_SAVEPOINT: &str = "$multi_row_insert";
 
 pub struct StatementExecutor {
     databases: Arc<Databases>,
+    users: Arc<Users>,
 
     optimizer: PlanOptimizer,
     validator: StatementValidator,
@@ -27,9 +36,10 @@ pub struct StatementExecutor {
 }
 
 impl StatementExecutor {
-    pub fn create(databases: &Arc<Databases>, options: Arc<SimpleDbOptions>) -> StatementExecutor {
+    pub fn create(databases: &Arc<Databases>, users: &Arc<Users>, options: Arc<SimpleDbOptions>) -> StatementExecutor {
         StatementExecutor {
-            validator: StatementValidator::create(databases),
+            validator: StatementValidator::create(databases, users),
+            users: users.clone(),
             optimizer: PlanOptimizer::create(),
             planner: Planner::create(options),
             databases: databases.clone(),
@@ -45,64 +55,186 @@ impl StatementExecutor {
         let statement = self.evaluate_constant_expressions(statement)?;
 
         match statement {
-            Statement::Select(select_statement) => self.select(context.database(), context.transaction(), select_statement),
-            Statement::Update(update_statement) => self.update(context.database(), context.transaction(), update_statement),
-            Statement::Delete(delete_statement) => self.delete(context.database(), context.transaction(), delete_statement),
+            Statement::Select(select_statement) => self.select(context, select_statement),
+            Statement::Update(update_statement) => self.update(context, update_statement),
+            Statement::Delete(delete_statement) => self.delete(context, delete_statement),
             Statement::Insert(insert_statement) => self.insert(context.database(), context.transaction(), insert_statement),
             Statement::CreateTable(create_table_statement) => self.create_table(context.database(), create_table_statement),
             Statement::CreateIndex(statement) => self.create_secondary_index(context.database(), statement),
+            Statement::DropTable(table_name) => self.drop_table(context.database(), &table_name),
+            Statement::TruncateTable(table_name) => self.truncate_table(context.database(), &table_name),
+            Statement::Vacuum(table_name) => self.vacuum(context.database(), table_name),
+            Statement::CheckDatabase => {
+                let database = self.databases.get_database_or_err(context.database())?;
+                Ok(StatementResult::CorruptedParts(database.check_integrity()))
+            },
+            Statement::AlterTable(statement) => self.alter_table(context.database(), statement),
+            Statement::DropIndex(statement) => self.drop_secondary_index(context.database(), statement),
             Statement::Rollback => self.rollback_transaction(context.database(), context.transaction()),
             Statement::Commit => self.commit_transaction(context.database(), context.transaction()),
+            Statement::Savepoint(name) => self.savepoint(context.database(), context.transaction(), &name),
+            Statement::RollbackToSavepoint(name) => self.rollback_to_savepoint(context.database(), context.transaction(), &name),
+            Statement::ReleaseSavepoint(name) => self.release_savepoint(context.database(), context.transaction(), &name),
             Statement::CreateDatabase(database_name) => self.create_database(database_name),
             Statement::Describe(table_name) => self.describe_table(&table_name, context),
-            Statement::StartTransaction => self.start_transaction(context.database()),
+            Statement::StartTransaction(isolation_level) => self.start_transaction(context.database(), isolation_level),
             Statement::ShowIndexes(table_name) => self.show_indexes(table_name, context),
+            Statement::ShowStats(table_name) => self.show_stats(table_name, context),
             Statement::ShowTables => self.show_tables(&context),
-            Statement::ShowDatabases => self.show_databases(),
+            Statement::ShowDatabases => self.show_databases(context),
+            Statement::ShowTransactionStatus => Ok(StatementResult::TransactionStatus(context.get_transaction().cloned())),
+            Statement::ShowTransaction => self.show_transaction(context),
+            Statement::ShowTransactions => {
+                let database = self.databases.get_database_or_err(context.database())?;
+                Ok(StatementResult::Transactions(database.describe_active_transactions()))
+            },
+            Statement::Backup(path) => {
+                self.databases.snapshot(&path)?;
+                Ok(StatementResult::Ok(0))
+            },
+            Statement::CreateUser(user_name, password) => {
+                self.users.create_user(&user_name, &password)?;
+                Ok(StatementResult::Ok(0))
+            },
+            Statement::Grant(database_name, user_name) => {
+                self.users.grant(&user_name, &database_name)?;
+                Ok(StatementResult::Ok(0))
+            },
+            Statement::Use(database_name) => Ok(StatementResult::DatabaseSelected(database_name)),
+        }
+    }
+
+    //Returns the rows of the scan without going through a SELECT statement. Rows are read lazily
+    pub fn scan(
+        &self,
+        context: &Context,
+        table_name: &str,
+        selection: Selection,
+        scan_type: ScanType,
+    ) -> Result<QueryIterator<PlanStep>, SimpleDbError> {
+        let range = match &scan_type {
+            ScanType::Range(range) => Some(range),
+            _ => None
+        };
+        self.validator.validate_scan(context, table_name, &selection, range)?;
+
+        let database = self.databases.get_database_or_err(context.database())?;
+        let table = database.get_table_for_transaction(table_name, context.transaction())?;
+        let mut scan_plan = self.planner.plan_scan(&table, scan_type, selection.clone(), context.transaction())?;
+        if let Some(cancellation_token) = context.get_cancellation_token() {
+            scan_plan.set_cancellation_token(cancellation_token);
+        }
+
+        let mut query_iterator = QueryIterator::create(selection, scan_plan, table.get_schema().clone());
+        if let Some(cancellation_token) = context.get_cancellation_token() {
+            query_iterator.with_cancellation_token(cancellation_token.clone());
         }
+
+        Ok(query_iterator)
     }
 
     fn select(
         &self,
-        database_name: &String,
-        transaction: &Transaction,
+        context: &Context,
         select_statement: SelectStatement,
     ) -> Result<StatementResult, SimpleDbError> {
+        let database_name = context.database();
+        let transaction = context.transaction();
+        let cancellation_token = context.get_cancellation_token();
         let selection = select_statement.selection.clone();
+        let aliases = select_statement.aliases.clone();
         let database = self.databases.get_database_or_err(database_name)?;
-        let table = database.get_table_or_err(&select_statement.table_name)?;
+        let table = database.get_table_for_transaction(&select_statement.table_name, transaction)?;
+        //Cached results of tables with TTL would keep returning the rows after they have expired
+        let use_select_cache = table.select_cache.is_enabled() && !select_statement.explain && table.get_ttl_seconds().is_none();
+        let analyze = select_statement.analyze;
+        let cache_key = select_statement.cache_key();
+        let cache_table_version = table.select_cache.version();
         let select_plan = self.planner.plan_select(&table, select_statement, transaction)?;
-        let select_plan = self.optimizer.optimize(select_plan, &table)?;
+        let mut select_plan = self.optimizer.optimize(select_plan, &table)?;
+        if let Some(cancellation_token) = cancellation_token {
+            select_plan.set_cancellation_token(cancellation_token);
+        }
+
+        //The query is executed, so the returned description contains the stats of each step
+        if analyze {
+            select_plan = select_plan.analyze();
+            while select_plan.next()?.is_some() {
+                if let Some(cancellation_token) = cancellation_token {
+                    cancellation_token.check()?;
+                }
+            }
+        }
 
-        Ok(StatementResult::Data(select_plan.desc(), QueryIterator::create(
+        let select_plan_desc = select_plan.desc();
+
+        //Results are materialized, so they can be cached. Only cached when they are the last committed values
+        if use_select_cache {
+            if let Some(rows) = table.select_cache.get(&cache_key, transaction) {
+                select_plan = PlanStep::CachedResult(Box::new(CachedResultStep::create(rows, select_plan)));
+            } else if table.storage.reads_last_committed_values(transaction) {
+                let mut rows = Vec::new();
+                while let Some(row) = select_plan.next()? {
+                    if let Some(cancellation_token) = cancellation_token {
+                        cancellation_token.check()?;
+                    }
+                    rows.push(row);
+                }
+
+                table.select_cache.put(cache_key, rows.clone(), transaction, cache_table_version);
+                select_plan = PlanStep::CachedResult(Box::new(CachedResultStep::create(rows, select_plan)));
+            }
+        }
+
+        //Selected expressions produce rows with their own schema
+        let schema = match &selection {
+            Selection::Expressions(_) => Schema::create(selection.get_expression_columns(&table)),
+            _ => table.get_schema().clone()
+        };
+        let mut query_iterator = QueryIterator::create(
             selection,
             select_plan,
-            table.get_schema().clone()
-        )))
+            schema
+        );
+        query_iterator.with_aliases(aliases);
+        if let Some(cancellation_token) = cancellation_token {
+            query_iterator.with_cancellation_token(cancellation_token.clone());
+        }
+
+        Ok(StatementResult::Data(select_plan_desc, query_iterator))
     }
 
     fn update(
         &self,
-        database_name: &String,
-        transaction: &Transaction,
+        context: &Context,
         update_statement: UpdateStatement,
     ) -> Result<StatementResult, SimpleDbError> {
-        let database = self.databases.get_database_or_err(database_name)?;
-        let table = database.get_table_or_err(&update_statement.table_name)?;
+        let transaction = context.transaction();
+        let cancellation_token = context.get_cancellation_token();
+        let database = self.databases.get_database_or_err(context.database())?;
+        let table = database.get_table_for_transaction(&update_statement.table_name, transaction)?;
         let mut update_plan = self.planner.plan_update(&table, &update_statement, transaction)?;
         let mut update_plan = self.optimizer.optimize(update_plan, &table)?;
+        if let Some(cancellation_token) = cancellation_token {
+            update_plan.set_cancellation_token(cancellation_token);
+        }
 
         let mut updated_rows = 0;
 
         while let Some(row_to_update) = update_plan.next()? {
+            if let Some(cancellation_token) = cancellation_token {
+                cancellation_token.check()?;
+            }
             let id = row_to_update.get_primary_column_value().clone();
             let mut new_values = Vec::new();
 
             for (updated_column_name, new_value_expr) in &update_statement.updated_values {
-                let new_value_bytes = match evaluate_expression(&row_to_update, new_value_expr)? {
+                let new_value = match evaluate_expression(&row_to_update, new_value_expr)? {
                     Expression::Literal(updated_value) => {
                         if !updated_value.is_null() {
-                            updated_value.get_bytes().clone()
+                            updated_value
+                        } else if table.is_not_null_column(updated_column_name) {
+                            return Err(SimpleDbError::NullConstraintViolation(updated_column_name.clone()));
                         } else {
                             continue
                         }
@@ -110,7 +242,7 @@ impl StatementExecutor {
                     _ => return Err(MalformedQuery(String::from("Update values should produce a literal value")))
                 };
 
-                new_values.push((updated_column_name.clone(), new_value_bytes.clone()));
+                new_values.push((updated_column_name.clone(), new_value));
             }
 
             table.update(transaction, id.get_bytes().clone(), &new_values)?;
@@ -122,16 +254,23 @@ impl StatementExecutor {
 
     fn delete(
         &self,
-        database_name: &String,
-        transaction: &Transaction,
+        context: &Context,
         delete_statement: DeleteStatement
     ) -> Result<StatementResult, SimpleDbError> {
-        let database = self.databases.get_database_or_err(database_name)?;
-        let table = database.get_table_or_err(delete_statement.table_name.as_str())?;
+        let transaction = context.transaction();
+        let cancellation_token = context.get_cancellation_token();
+        let database = self.databases.get_database_or_err(context.database())?;
+        let table = database.get_table_for_transaction(delete_statement.table_name.as_str(), transaction)?;
         let mut delete_plan = self.planner.plan_delete(&table, delete_statement, transaction)?;
+        if let Some(cancellation_token) = cancellation_token {
+            delete_plan.set_cancellation_token(cancellation_token);
+        }
         let mut deleted_rows = 0;
 
         while let Some(row_to_delete) = delete_plan.next()? {
+            if let Some(cancellation_token) = cancellation_token {
+                cancellation_token.check()?;
+            }
             let id = row_to_delete.get_primary_column_value();
             table.delete(transaction, id.get_bytes().clone())?;
             deleted_rows += 1;
@@ -147,10 +286,71 @@ impl StatementExecutor {
         insert_statement: InsertStatement,
     ) -> Result<StatementResult, SimpleDbError> {
         let database = self.databases.get_database_or_err(database_name)?;
-        let table = database.get_table_or_err(insert_statement.table_name.as_str())?;
-        let mut inserted_values = self.serialize_column_values(&insert_statement.values);
-        table.insert(transaction, &mut inserted_values)?;
-        Ok(StatementResult::Ok(1))
+        let table = database.get_table_for_transaction(insert_statement.table_name.as_str(), transaction)?;
+
+        //Rows are inserted all or nothing. If one of them fails, the ones already written are undone
+        let is_multi_row = insert_statement.values.len() > 1;
+        if is_multi_row {
+            database.create_savepoint(transaction, MULTI_ROW_INSERT_SAVEPOINT);
+        }
+        let result = self.insert_rows(&table, transaction, insert_statement);
+        if is_multi_row {
+            if result.is_err() {
+                database.rollback_to_savepoint(transaction, MULTI_ROW_INSERT_SAVEPOINT)?;
+            }
+            database.release_savepoint(transaction, MULTI_ROW_INSERT_SAVEPOINT)?;
+        }
+
+        result
+    }
+
+    fn insert_rows(
+        &self,
+        table: &Arc<Table>,
+        transaction: &Transaction,
+        insert_statement: InsertStatement,
+    ) -> Result<StatementResult, SimpleDbError> {
+        let mut inserted_rows = 0;
+
+        for values in insert_statement.values {
+            match (&insert_statement.on_conflict, self.get_existing_primary_key(table, transaction, &values)?) {
+                (OnConflict::DoNothing, Some(_)) => continue,
+                (OnConflict::Fail, Some(_)) => {
+                    return Err(SimpleDbError::PrimaryKeyAlreadyExists(table.table_name.clone()));
+                },
+                //The old row is deleted, so its secondary index entries are removed
+                (OnConflict::Replace, Some(primary_key)) => table.delete(transaction, primary_key)?,
+                _ => {}
+            }
+
+            table.clone().insert(transaction, values)?;
+            inserted_rows += 1;
+        }
+
+        Ok(StatementResult::Ok(inserted_rows))
+    }
+
+    //The key is locked before checking if it exists, so a concurrent insert of the same key waits
+    //until this transaction finishes instead of both seeing that the key doesn't exist.
+    //Rows inserted by transactions that have committed after this one started are not visible by it,
+    //so the key is read with the last committed values
+    fn get_existing_primary_key(
+        &self,
+        table: &Arc<Table>,
+        transaction: &Transaction,
+        inserted_values: &[(String, Value)]
+    ) -> Result<Option<Bytes>, SimpleDbError> {
+        let primary_column = table.get_schema().get_primary_column();
+        match inserted_values.iter().find(|(column_name, _)| *column_name == primary_column.column_name) {
+            Some((_, primary_value)) => {
+                let primary_value = primary_value.get_bytes();
+                table.lock_row(transaction, primary_value)?;
+                let last_committed_values_transaction = table.storage.last_committed_values_transaction(transaction);
+                let exists = table.get_by_primary_column(primary_value, &last_committed_values_transaction, &Selection::All)?.is_some();
+                Ok(Some(primary_value.clone()).filter(|_| exists))
+            },
+            None => Ok(None)
+        }
     }
 
     fn create_table(
@@ -159,7 +359,55 @@ impl StatementExecutor {
         create_table_statement: CreateTableStatement,
     ) -> Result<StatementResult, SimpleDbError> {
         let database = self.databases.get_database_or_err(database_name)?;
-        database.create_table(create_table_statement.table_name.as_str(), create_table_statement.columns)?;
+        database.create_table(
+            create_table_statement.table_name.as_str(),
+            create_table_statement.columns,
+            create_table_statement.checks,
+            create_table_statement.ttl_seconds
+        )?;
+        Ok(StatementResult::Ok(0))
+    }
+
+    fn drop_table(
+        &self,
+        database_name: &String,
+        table_name: &str,
+    ) -> Result<StatementResult, SimpleDbError> {
+        let database = self.databases.get_database_or_err(database_name)?;
+        database.drop_table(table_name)?;
+        Ok(StatementResult::Ok(0))
+    }
+
+    fn truncate_table(
+        &self,
+        database_name: &String,
+        table_name: &str,
+    ) -> Result<StatementResult, SimpleDbError> {
+        let database = self.databases.get_database_or_err(database_name)?;
+        database.truncate_table(table_name)?;
+        Ok(StatementResult::Ok(0))
+    }
+
+    fn vacuum(
+        &self,
+        database_name: &String,
+        table_name: Option<String>,
+    ) -> Result<StatementResult, SimpleDbError> {
+        let database = self.databases.get_database_or_err(database_name)?;
+        let bytes_reclaimed = database.vacuum(table_name.as_deref())?;
+        Ok(StatementResult::Vacuumed(bytes_reclaimed))
+    }
+
+    fn alter_table(
+        &self,
+        database_name: &String,
+        statement: AlterTableStatement,
+    ) -> Result<StatementResult, SimpleDbError> {
+        let database = self.databases.get_database_or_err(database_name)?;
+        match statement.operation {
+            AlterTableOperation::DropColumn(column_name) => database.drop_column(&statement.table_name, &column_name)?,
+            AlterTableOperation::RenameColumn(old_name, new_name) => database.rename_column(&statement.table_name, &old_name, &new_name)?,
+        };
         Ok(StatementResult::Ok(0))
     }
 
@@ -171,19 +419,48 @@ impl StatementExecutor {
         let database = self.databases.get_database_or_err(database_name)?;
         let table = database.get_table_or_err(&statement.table_name)?;
 
-        let n_affected_rows = table.create_secondary_index(
-            &statement.column_name, statement.wait
-        )?;
+        let n_affected_rows = match statement.column_names.as_slice() {
+            [column_name] => table.create_secondary_index(column_name, statement.unique, statement.wait)?,
+            column_names => table.create_composite_index(&column_names.to_vec(), statement.wait)?,
+        };
 
         Ok(StatementResult::Ok(n_affected_rows))
     }
 
+    //Like CREATE INDEX, but the caller receives the progress of the index creation and can cancel it. wait is ignored
+    pub fn start_index_creation(
+        &self,
+        context: &Context,
+        statement: CreateIndexStatement,
+    ) -> Result<IndexCreation, SimpleDbError> {
+        self.validator.validate(context, &Statement::CreateIndex(statement.clone()))?;
+        let database = self.databases.get_database_or_err(context.database())?;
+        let table = database.get_table_or_err(&statement.table_name)?;
+
+        match statement.column_names.as_slice() {
+            [column_name] => table.start_secondary_index_creation(column_name, statement.unique),
+            column_names => table.start_composite_index_creation(&column_names.to_vec()),
+        }
+    }
+
+    fn drop_secondary_index(
+        &self,
+        database_name: &String,
+        statement: DropIndexStatement,
+    ) -> Result<StatementResult, SimpleDbError> {
+        let database = self.databases.get_database_or_err(database_name)?;
+        let table = database.get_table_or_err(&statement.table_name)?;
+        table.drop_secondary_index(&statement.column_name)?;
+        Ok(StatementResult::Ok(0))
+    }
+
     fn start_transaction(
         &self,
-        database_name: &String
+        database_name: &String,
+        isolation_level: IsolationLevel
     ) -> Result<StatementResult, SimpleDbError> {
         let database = self.databases.get_database_or_err(database_name)?;
-        let transaction = database.start_transaction();
+        let transaction = database.start_transaction_with_isolation(isolation_level);
         Ok(StatementResult::TransactionStarted(transaction))
     }
 
@@ -207,6 +484,39 @@ impl StatementExecutor {
         Ok(StatementResult::Ok(0))
     }
 
+    fn savepoint(
+        &self,
+        database_name: &String,
+        transaction: &Transaction,
+        name: &str
+    ) -> Result<StatementResult, SimpleDbError> {
+        let database = self.databases.get_database_or_err(database_name)?;
+        database.create_savepoint(transaction, name);
+        Ok(StatementResult::Ok(0))
+    }
+
+    fn rollback_to_savepoint(
+        &self,
+        database_name: &String,
+        transaction: &Transaction,
+        name: &str
+    ) -> Result<StatementResult, SimpleDbError> {
+        let database = self.databases.get_database_or_err(database_name)?;
+        database.rollback_to_savepoint(transaction, name)?;
+        Ok(StatementResult::Ok(0))
+    }
+
+    fn release_savepoint(
+        &self,
+        database_name: &String,
+        transaction: &Transaction,
+        name: &str
+    ) -> Result<StatementResult, SimpleDbError> {
+        let database = self.databases.get_database_or_err(database_name)?;
+        database.release_savepoint(transaction, name)?;
+        Ok(StatementResult::Ok(0))
+    }
+
     fn create_database(
         &self,
         database_name: String
@@ -215,15 +525,28 @@ impl StatementExecutor {
         Ok(StatementResult::Ok(0))
     }
 
-    fn show_databases(&self) -> Result<StatementResult, SimpleDbError> {
+    //Users will only see the databases granted to them
+    fn show_databases(&self, context: &Context) -> Result<StatementResult, SimpleDbError> {
         let databases_name = self.databases.get_databases()
             .iter()
             .map(|database| database.name().clone())
+            .filter(|database_name| context.get_user()
+                .map_or(true, |user_name| self.users.has_access(user_name, database_name)))
             .collect();
 
         Ok(StatementResult::Databases(databases_name))
     }
 
+    fn show_transaction(&self, context: &Context) -> Result<StatementResult, SimpleDbError> {
+        match context.get_transaction() {
+            Some(transaction) => {
+                let database = self.databases.get_database_or_err(context.database())?;
+                Ok(StatementResult::Transactions(vec![database.describe_transaction(transaction)]))
+            },
+            None => Ok(StatementResult::Transactions(Vec::new())),
+        }
+    }
+
     fn show_tables(&self, context: &Context) -> Result<StatementResult, SimpleDbError> {
         let database = self.databases.get_database_or_err(context.database())?;
         let table_names = database.get_tables().iter()
@@ -241,12 +564,28 @@ impl StatementExecutor {
         let mut indexed_columns_to_return = Vec::new();
         for indexed_column in indexed_columns {
             let index_type = if indexed_column.is_primary { IndexType::Primary } else { IndexType::Secondary };
-            indexed_columns_to_return.push((indexed_column.column_name, index_type));
+            indexed_columns_to_return.push((indexed_column.column_name, index_type, IndexState::Ready));
+        }
+        for composite_index in schema.get_composite_indexes() {
+            let columns_names: Vec<String> = schema.get_composite_index_columns(&composite_index).into_iter()
+                .map(|column| column.column_name)
+                .collect();
+            indexed_columns_to_return.push((columns_names.join(", "), IndexType::Secondary, IndexState::Ready));
+        }
+        //Indexes are added to the schema once they are built
+        for building_index in table.get_building_indexes() {
+            indexed_columns_to_return.push((building_index, IndexType::Secondary, IndexState::Building));
         }
 
         Ok(StatementResult::Indexes(indexed_columns_to_return))
     }
 
+    fn show_stats(&self, table_name: String, context: &Context) -> Result<StatementResult, SimpleDbError> {
+        let database = self.databases.get_database_or_err(context.database())?;
+        let table = database.get_table_or_err(&table_name)?;
+        Ok(StatementResult::Stats(table.get_stats()?))
+    }
+
     fn describe_table(&self, table_name: &str, context: &Context) -> Result<StatementResult, SimpleDbError> {
         let databases = self.databases.get_database_or_err(context.database())?;
         let table = databases.get_table_or_err(table_name)?;
@@ -254,18 +593,6 @@ impl StatementExecutor {
         Ok(StatementResult::Describe(columns))
     }
 
-    fn serialize_column_values(
-        &self,
-        values: &Vec<(String, Value)>
-    ) -> Vec<(String, Bytes)>{
-        let mut formatted_values = Vec::new();
-        for (column_name, column_value) in values {
-            formatted_values.push((column_name.clone(), column_value.get_bytes().clone()));
-        }
-
-        formatted_values
-    }
-
     fn evaluate_constant_expressions(&self, statement: Statement) -> Result<Statement, SimpleDbError> {
         match statement {
             Statement::Select(mut select) => {
@@ -306,6 +633,12 @@ impl StatementExecutor {
                 }
                 columns_desc
             },
+            Selection::Aggregate(_, _) => {
+                selection.get_aggregate_columns(table.get_schema())
+            },
+            Selection::Expressions(_) => {
+                selection.get_expression_columns(table)
+            },
             Selection::All => {
                 table.get_schema().get_columns().iter()
                     .map(|it| it.clone())
@@ -313,4 +646,224 @@ impl StatementExecutor {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{IsolationLevel, TransactionState};
+    use crate::simple_db::{Context, StatementResult};
+    use crate::table::row::RowIterator;
+    use crate::test_utils::TestSimpleDb;
+    use shared::SimpleDbError;
+
+    #[test]
+    fn insert_on_conflict_do_nothing() {
+        let simple_db = TestSimpleDb::create("insert_on_conflict_do_nothing");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR);");
+        simple_db.execute_sql(&context, "CREATE INDEX ON personas (nombre);");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, nombre) VALUES (1, \"Jaime\");");
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        let result = simple_db.execute(&transaction.context(), simple_db.parse(
+            "INSERT INTO personas (id, nombre) VALUES (1, \"Molon\") ON CONFLICT DO NOTHING;").unwrap()).unwrap();
+        assert!(matches!(result, StatementResult::Ok(0)));
+        let result = simple_db.execute(&transaction.context(), simple_db.parse(
+            "INSERT INTO personas (id, nombre) VALUES (2, \"Molon\") ON CONFLICT DO NOTHING;").unwrap()).unwrap();
+        assert!(matches!(result, StatementResult::Ok(1)));
+        transaction.commit().unwrap();
+
+        let transaction = simple_db.begin(&context).unwrap();
+        let rows = simple_db.execute_sql(&transaction.context(), "SELECT * FROM personas;")
+            .data().all().unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].get_column_value("nombre").unwrap().get_string().unwrap(), "Jaime");
+        assert_eq!(rows[1].get_column_value("nombre").unwrap().get_string().unwrap(), "Molon");
+
+        //Keys inserted by transactions committed after this one started are not overwritten
+        let mut other_transaction = simple_db.begin(&context).unwrap();
+        simple_db.execute(&other_transaction.context(), simple_db.parse(
+            "INSERT INTO personas (id, nombre) VALUES (3, \"Wili\");").unwrap()).unwrap();
+        other_transaction.commit().unwrap();
+        let result = simple_db.execute(&transaction.context(), simple_db.parse(
+            "INSERT INTO personas (id, nombre) VALUES (3, \"Pepe\") ON CONFLICT DO NOTHING;").unwrap()).unwrap();
+        assert!(matches!(result, StatementResult::Ok(0)));
+
+        //Keys deleted by the transaction can be inserted again
+        simple_db.execute_sql(&transaction.context(), "DELETE FROM personas WHERE id == 1;");
+        let result = simple_db.execute(&transaction.context(), simple_db.parse(
+            "INSERT INTO personas (id, nombre) VALUES (1, \"Pepe\") ON CONFLICT DO NOTHING;").unwrap()).unwrap();
+        assert!(matches!(result, StatementResult::Ok(1)));
+    }
+
+    #[test]
+    fn insert_multiple_rows() {
+        let simple_db = TestSimpleDb::create("insert_multiple_rows");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64);");
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        let result = simple_db.execute(&transaction.context(), simple_db.parse(
+            "INSERT INTO personas (id, dinero) VALUES (1, 10), (2, 20), (3, 30);"
+        ).unwrap()).unwrap();
+        assert!(matches!(result, StatementResult::Ok(3)));
+        //The second tuple is not valid, so no row is inserted
+        let result = simple_db.execute(&transaction.context(), simple_db.parse(
+            "INSERT INTO personas (id, dinero) VALUES (4, 40), (5, \"Jaime\");"
+        ).unwrap());
+        assert!(result.is_err());
+        //The last tuple fails when it's inserted, so the rows written before it are undone
+        let result = simple_db.execute(&transaction.context(), simple_db.parse(
+            "INSERT INTO personas (id, dinero) VALUES (4, 40), (5, 50), (1, 10);"
+        ).unwrap());
+        assert!(matches!(result, Err(SimpleDbError::PrimaryKeyAlreadyExists(_))));
+        let result = simple_db.execute(&transaction.context(), simple_db.parse(
+            "INSERT INTO personas (id, dinero) VALUES (6, 60), (6, 61);"
+        ).unwrap());
+        assert!(matches!(result, Err(SimpleDbError::PrimaryKeyAlreadyExists(_))));
+        transaction.commit().unwrap();
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        let rows = simple_db.execute_sql(&transaction.context(), "SELECT * FROM personas;")
+            .data().all().unwrap().iter()
+            .map(|row| (row.get_primary_column_value().get_i64().unwrap(), row.get_column_value("dinero").unwrap().get_i64().unwrap()))
+            .collect::<Vec<(i64, i64)>>();
+        assert_eq!(rows, vec![(1, 10), (2, 20), (3, 30)]);
+        transaction.commit().unwrap();
+    }
+
+    #[test]
+    fn update_concat_strings() {
+        let simple_db = TestSimpleDb::create("update_concat_strings");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR);");
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        simple_db.execute_sql(&transaction.context(), "INSERT INTO personas (id, nombre) VALUES (1, \"Jaime\");");
+        simple_db.execute_sql(&transaction.context(), "UPDATE personas SET nombre = nombre + \"_suffix\" WHERE id == 1;");
+
+        let mut query_iterator = simple_db.execute_sql(&transaction.context(), "SELECT nombre FROM personas WHERE id == 1;").data();
+        let row = query_iterator.next().unwrap().unwrap();
+        assert_eq!(row.get_column_value("nombre").unwrap().get_string().unwrap(), "Jaime_suffix");
+        transaction.commit().unwrap();
+    }
+
+    #[test]
+    fn show_transaction_status() {
+        let simple_db = TestSimpleDb::create("show_transaction_status");
+        let context = Context::create_with_database("test");
+
+        let transaction = simple_db.begin(&context).unwrap();
+        match simple_db.execute_sql(&transaction.context(), "SHOW TRANSACTION STATUS;") {
+            StatementResult::TransactionStatus(Some(status)) => {
+                assert!(status.id() > 0);
+                assert_eq!(status.id(), transaction.transaction().id());
+            },
+            _ => panic!()
+        };
+
+        let status = simple_db.execute_sql(&context, "SHOW TRANSACTION STATUS;");
+        assert!(matches!(status, StatementResult::TransactionStatus(None)));
+    }
+
+    #[test]
+    fn show_transaction() {
+        let simple_db = TestSimpleDb::create("show_transaction");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY);");
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        let transaction_context = transaction.context();
+        simple_db.execute_sql(&transaction_context, "INSERT INTO personas (id) VALUES (1);");
+        match simple_db.execute_sql(&transaction_context, "SHOW TRANSACTION;") {
+            StatementResult::Transactions(transactions) => {
+                assert_eq!(transactions.len(), 1);
+                assert_eq!(transactions[0].txn_id, transaction.transaction().id());
+                assert_eq!(transactions[0].isolation_level, IsolationLevel::SnapshotIsolation);
+                assert_eq!(transactions[0].state, TransactionState::Active);
+                assert!(transactions[0].start_time.is_some());
+                assert_eq!(transactions[0].n_writes, 1);
+            },
+            _ => panic!()
+        };
+        match simple_db.execute_sql(&context, "SHOW TRANSACTIONS;") {
+            StatementResult::Transactions(transactions) => assert!(transactions.iter()
+                .any(|desc| desc.txn_id == transaction.transaction().id())),
+            _ => panic!()
+        };
+
+        transaction.commit().unwrap();
+        match simple_db.execute_sql(&context, "SHOW TRANSACTIONS;") {
+            StatementResult::Transactions(transactions) => assert!(transactions.is_empty()),
+            _ => panic!()
+        };
+        assert!(matches!(simple_db.execute_sql(&context, "SHOW TRANSACTION;"),
+            StatementResult::Transactions(transactions) if transactions.is_empty()));
+    }
+
+    #[test]
+    fn show_stats() {
+        let simple_db = TestSimpleDb::create_with_options("show_stats", {
+            let mut options_builder = shared::start_simpledb_options_builder();
+            options_builder.memtable_max_size_bytes(1024);
+            options_builder.max_memtables_inactive(1);
+            options_builder
+        });
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64);");
+        for id in 1..=200 {
+            simple_db.execute_in_transaction(&context, &format!("INSERT INTO personas (id, dinero) VALUES ({}, {});", id, id * 10));
+        }
+
+        let stats = match simple_db.execute_sql(&context, "SHOW STATS FROM personas;") {
+            StatementResult::Stats(stats) => stats,
+            _ => panic!()
+        };
+
+        let n_rows = stats.n_rows.unwrap();
+        assert!((150..=250).contains(&n_rows));
+        assert!(stats.n_sstables_per_level.iter().sum::<usize>() > 0);
+        assert!(stats.size_bytes > 0);
+        let (min, max) = stats.primary_key_range.unwrap();
+        assert_eq!(min.get_i64().unwrap(), 1);
+        assert!(max.get_i64().unwrap() >= 190);
+    }
+
+    #[test]
+    fn affected_rows() {
+        let simple_db = TestSimpleDb::create("affected_rows");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64);");
+        simple_db.execute_sql(&context, "CREATE INDEX ON personas (dinero);");
+        for id in 1..40 {
+            simple_db.execute_in_transaction(&context, &format!("INSERT INTO personas (id, dinero) VALUES ({}, {});", id, id * 10));
+        }
+        let execute = |query: &str| {
+            let mut transaction = simple_db.begin(&context).unwrap();
+            let result = simple_db.execute(&transaction.context(), simple_db.parse(query).unwrap()).unwrap();
+            transaction.commit().unwrap();
+            match result {
+                StatementResult::Ok(n_affected_rows) => n_affected_rows,
+                _ => panic!()
+            }
+        };
+
+        assert_eq!(execute("UPDATE personas SET dinero = dinero + 100 WHERE dinero > 375;"), 2);
+        assert_eq!(execute("UPDATE personas SET dinero = 0 WHERE id > 50;"), 0);
+        assert_eq!(execute("DELETE FROM personas WHERE id > 36;"), 3);
+        assert_eq!(execute("DELETE FROM personas WHERE id > 36;"), 0);
+        assert_eq!(execute("DELETE FROM personas;"), 36);
+        assert_eq!(execute("DELETE FROM personas;"), 0);
+    }
+
+    #[test]
+    fn use_database() {
+        let simple_db = TestSimpleDb::create("use_database");
+
+        match simple_db.execute_sql(&Context::empty(), "USE test;") {
+            StatementResult::DatabaseSelected(database_name) => assert_eq!(database_name, "test"),
+            _ => panic!()
+        }
+        assert!(matches!(simple_db.execute(&Context::empty(), simple_db.parse("USE noexiste;").unwrap()), Err(SimpleDbError::DatabaseNotFound(_))));
+    }
+}
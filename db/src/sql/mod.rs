@@ -1,10 +1,12 @@
 pub mod validator;
 pub mod parser;
 pub mod query_iterator;
+pub mod cancellation_token;
+pub mod query_builder;
 pub mod plan;
 
 mod token;
 mod optimizer;
-mod execution;
+pub(crate) mod execution;
 
 pub use execution::statement_executor::StatementExecutor;
\ No newline at end of file
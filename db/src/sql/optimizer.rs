@@ -2,8 +2,9 @@ use std::sync::Arc;
 use shared::SimpleDbError;
 use shared::SimpleDbError::MalformedQuery;
 use crate::sql::plan::plan_step::PlanStep;
-use crate::sql::plan::plan_step::PlanStep::{PrimaryRangeScan, SecondaryRangeScan};
+use crate::sql::plan::plan_step::PlanStep::{IndexOnlyScan, PrimaryRangeScan, SecondaryRangeScan};
 use crate::sql::plan::steps::filter_step::FilterStep;
+use crate::sql::plan::steps::index_only_scan_step::{IndexOnlyLookup, IndexOnlyScanStep};
 use crate::sql::plan::steps::limit_step::LimitStep;
 use crate::sql::plan::steps::primary_range_scan_step::PrimaryRangeScanStep;
 use crate::sql::plan::steps::project_selection_step::ProjectSelectionStep;
@@ -31,12 +32,12 @@ impl PlanOptimizer {
         match &parent_plan {
             PlanStep::ProjectSelection(projection_step) => {
                 Ok(PlanStep::ProjectSelection(Box::new(ProjectSelectionStep::create(
-                    projection_step.selection_to_project.clone(), self.merge_scans(projection_step.source.clone(), table)?
+                    projection_step.selection_to_project.clone(), table, self.merge_scans(projection_step.source.clone(), table)?
                 ))))
             },
             PlanStep::Limit(limit_step) => {
                 Ok(PlanStep::Limit(Box::new(
-                    LimitStep::create(limit_step.limit.clone(), self.merge_scans(limit_step.source.clone(), table)?)
+                    LimitStep::create(limit_step.limit.clone(), limit_step.offset, self.merge_scans(limit_step.source.clone(), table)?)
                 )))
             },
             PlanStep::Filter(filter_step) => {
@@ -44,6 +45,22 @@ impl PlanOptimizer {
                     filter_step.filter_expression.clone(), self.merge_scans(filter_step.source.clone(), table)?
                 ))))
             },
+            PlanStep::Aggregate(aggregate_step) => {
+                let mut aggregate_step = aggregate_step.clone();
+                aggregate_step.source = self.merge_scans(aggregate_step.source, table)?;
+                Ok(PlanStep::Aggregate(aggregate_step))
+            },
+            PlanStep::GroupBy(group_by_step) => {
+                let mut group_by_step = group_by_step.clone();
+                let source = self.merge_scans(group_by_step.source.clone(), table)?;
+                group_by_step.set_source(source, table.get_schema());
+                Ok(PlanStep::GroupBy(group_by_step))
+            },
+            PlanStep::Distinct(distinct_step) => {
+                let mut distinct_step = distinct_step.clone();
+                distinct_step.source = self.merge_scans(distinct_step.source, table)?;
+                Ok(PlanStep::Distinct(distinct_step))
+            },
 
             PlanStep::MergeIntersection(_) |
             PlanStep::MergeUnion(_) => {
@@ -51,14 +68,20 @@ impl PlanOptimizer {
             },
             //Shouldn't be called
             PlanStep::FullScan(_) => Ok(parent_plan),
+            PlanStep::ParallelFullScan(_) => Ok(parent_plan),
             PlanStep::PrimaryRangeScan(_) => Ok(parent_plan),
             PlanStep::SecondaryRangeScan(_) => Ok(parent_plan),
             PlanStep::PrimaryExactScan(_) => Ok(parent_plan),
             PlanStep::SecondaryExactExactScan(_) => Ok(parent_plan),
+            PlanStep::IndexOnlyScan(_) => Ok(parent_plan),
+            PlanStep::CompositeScan(_) => Ok(parent_plan),
             PlanStep::FullSort(_) => Ok(parent_plan),
             PlanStep::Mock(_) => panic!(""),
             PlanStep::TopNSort(_) => Ok(parent_plan),
             PlanStep::Reverse(_) => Ok(parent_plan),
+            PlanStep::Empty(_) => Ok(parent_plan),
+            PlanStep::CachedResult(_) => Ok(parent_plan),
+            PlanStep::Analyze(_) => Ok(parent_plan),
         }
     }
 
@@ -116,6 +139,10 @@ impl PlanOptimizer {
                     Ok(SecondaryRangeScan(SecondaryRangeScanStep::create(table.clone(), step.selection, &step.column_name,
                                                                          &step.transaction, new_range)?))
                 },
+                IndexOnlyScan(step) => {
+                    Ok(IndexOnlyScan(IndexOnlyScanStep::create(table.clone(), &step.column_name, &step.transaction,
+                                                               IndexOnlyLookup::Range(new_range))?))
+                },
                 _ => panic!("Illegal code path")
             }
 
@@ -171,6 +198,11 @@ impl PlanOptimizer {
                             PrimaryRangeScanStep::create(table.clone(), step.selection, &step.transaction, range_right)?
                         ))
                     }
+                    IndexOnlyScan(step) => {
+                        Ok(IndexOnlyScan(
+                            IndexOnlyScanStep::create(table.clone(), &step.column_name, &step.transaction, IndexOnlyLookup::Range(range_right))?
+                        ))
+                    }
                     _ => panic!("Illegal code path")
                 }
             } else {
@@ -194,6 +226,11 @@ impl PlanOptimizer {
                             PrimaryRangeScanStep::create(table.clone(), step.selection, &step.transaction, range_left)?
                         ))
                     }
+                    IndexOnlyScan(step) => {
+                        Ok(IndexOnlyScan(
+                            IndexOnlyScanStep::create(table.clone(), &step.column_name, &step.transaction, IndexOnlyLookup::Range(range_left))?
+                        ))
+                    }
                     _ => panic!("Illegal code path")
                 }
             } else {
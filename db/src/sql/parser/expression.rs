@@ -1,21 +1,28 @@
-use bytes::Bytes;
-use shared::{SimpleDbError, Value};
+use bytes::{Buf, BufMut, Bytes};
+use shared::{DecodeErrorType, SimpleDbError, Type, Value};
 use std::cmp::PartialEq;
 use std::collections::HashSet;
+use std::fmt;
+use std::fmt::Formatter;
 use SimpleDbError::MalformedQuery;
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Expression {
     Binary(BinaryOperator, Box<Expression>, Box<Expression>),
     Unary(UnaryOperator, Box<Expression>),
-    Identifier(String),
+    Identifier(Option<String>, String), //Optional table qualifier, column name
     Literal(Value),
+    Parameter(usize), //? placeholder. Replaced by its value with Statement::bind()
+    Function(String, Vec<Expression>), //Function name in upper case, arguments
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum UnaryOperator {
     Plus,
     Minus,
+    IsNull,
+    IsNotNull,
+    Not,
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -24,6 +31,7 @@ pub enum BinaryOperator {
     Subtract,
     Multiply,
     Divide,
+    Modulo,
     And,
     Or,
     NotEqual,
@@ -32,9 +40,23 @@ pub enum BinaryOperator {
     GreaterEqual,
     Less,
     LessEqual,
+    Like,
+    ILike,
 }
 
 impl Expression {
+    pub fn column(column_name: &str) -> Expression {
+        Expression::Identifier(None, column_name.to_string())
+    }
+
+    pub fn literal(value: Value) -> Expression {
+        Expression::Literal(value)
+    }
+
+    pub fn binary(operator: BinaryOperator, left: Expression, right: Expression) -> Expression {
+        Expression::Binary(operator, Box::new(left), Box::new(right))
+    }
+
     pub fn get_identifiers(&self) -> Vec<String> {
         let columns = self.get_identifiers_recursive();
         columns.into_iter().collect()
@@ -50,8 +72,14 @@ impl Expression {
                 columns.extend(right.get_identifiers());
             },
             Expression::Unary(_, expr) => columns.extend(expr.get_identifiers()),
-            Expression::Identifier(column_name) => { columns.insert(column_name.clone()); },
-            Expression::Literal(_) => {}
+            Expression::Function(_, args) => {
+                for arg in args {
+                    columns.extend(arg.get_identifiers());
+                }
+            },
+            Expression::Identifier(_, column_name) => { columns.insert(column_name.clone()); },
+            Expression::Literal(_) |
+            Expression::Parameter(_) => {}
         };
 
         columns
@@ -89,14 +117,14 @@ impl Expression {
 
     pub fn get_identifier(&self) -> Result<String, SimpleDbError> {
         match self {
-            Expression::Identifier(actual_identifier) => Ok(actual_identifier.clone()),
+            Expression::Identifier(_, actual_identifier) => Ok(actual_identifier.clone()),
             _ => Err(MalformedQuery(String::from("Cannot get identifier from expression"))),
         }
     }
 
     pub fn identifier_eq(&self, expected_identifier: &str) -> bool {
         match self {
-            Expression::Identifier(actual_identifier) => actual_identifier == expected_identifier,
+            Expression::Identifier(_, actual_identifier) => actual_identifier == expected_identifier,
             _ => false
         }
     }
@@ -107,8 +135,39 @@ impl Expression {
                 left.is_constant_expression() && right.is_constant_expression()
             },
             Expression::Unary(_, expr) => expr.is_constant_expression(),
+            Expression::Function(_, args) => args.iter().all(|arg| arg.is_constant_expression()),
             Expression::Literal(_) => true,
-            Expression::Identifier(_) => false,
+            Expression::Identifier(_, _) |
+            Expression::Parameter(_) => false,
+        }
+    }
+
+    //Replaces the parameters with the value at its index
+    pub fn bind(self, values: &Vec<Value>) -> Result<Expression, SimpleDbError> {
+        match self {
+            Expression::Binary(operator, left, right) => Ok(Expression::Binary(
+                operator, Box::new(left.bind(values)?), Box::new(right.bind(values)?)
+            )),
+            Expression::Unary(operator, expr) => Ok(Expression::Unary(operator, Box::new(expr.bind(values)?))),
+            Expression::Function(name, args) => Ok(Expression::Function(name, args.into_iter()
+                .map(|arg| arg.bind(values))
+                .collect::<Result<Vec<Expression>, SimpleDbError>>()?)),
+            Expression::Parameter(index) => values.get(index)
+                .map(|value| Expression::Literal(value.clone()))
+                .ok_or(MalformedQuery(String::from("No value bound to parameter"))),
+            Expression::Identifier(_, _) |
+            Expression::Literal(_) => Ok(self),
+        }
+    }
+
+    pub fn get_n_parameters(&self) -> usize {
+        match self {
+            Expression::Binary(_, left, right) => left.get_n_parameters() + right.get_n_parameters(),
+            Expression::Unary(_, expr) => expr.get_n_parameters(),
+            Expression::Function(_, args) => args.iter().map(|arg| arg.get_n_parameters()).sum(),
+            Expression::Parameter(_) => 1,
+            Expression::Identifier(_, _) |
+            Expression::Literal(_) => 0,
         }
     }
 
@@ -132,6 +191,10 @@ impl Expression {
         self.arithmetic_op(other, |a, b| a.divide(b))
     }
 
+    pub fn modulo(&self, other: &Expression) -> Result<Expression, SimpleDbError> {
+        self.arithmetic_op(other, |a, b| a.modulo(b))
+    }
+
     pub fn or(&self, other: &Expression) -> Result<Expression, SimpleDbError> {
         let value_self = self.get_value()?;
         let value_other = other.get_value()?;
@@ -202,6 +265,107 @@ impl Expression {
         )
     }
 
+    pub fn like(&self, pattern: &Expression) -> Result<Expression, SimpleDbError> {
+        if self.is_null() || pattern.is_null() {
+            return Ok(Expression::Literal(Value::create_null()));
+        }
+
+        let matches = self.get_value()?.like(&pattern.get_value()?)?;
+        Ok(Expression::Literal(Value::create_boolean(matches)))
+    }
+
+    pub fn ilike(&self, pattern: &Expression) -> Result<Expression, SimpleDbError> {
+        if self.is_null() || pattern.is_null() {
+            return Ok(Expression::Literal(Value::create_null()));
+        }
+
+        let matches = self.get_value()?.ilike(&pattern.get_value()?)?;
+        Ok(Expression::Literal(Value::create_boolean(matches)))
+    }
+
+    //Used to persist CHECK constraints in the table descriptor
+    pub fn serialize(&self, serialized: &mut Vec<u8>) {
+        match self {
+            Expression::Binary(operator, left, right) => {
+                serialized.put_u8(0);
+                serialized.put_u8(operator.serialize());
+                left.serialize(serialized);
+                right.serialize(serialized);
+            },
+            Expression::Unary(operator, expression) => {
+                serialized.put_u8(1);
+                serialized.put_u8(operator.serialize());
+                expression.serialize(serialized);
+            },
+            //Identifiers without qualifier are serialized with an empty one
+            Expression::Identifier(qualifier, identifier) => {
+                serialized.put_u8(2);
+                serialize_string(serialized, qualifier.as_ref().map(|qualifier| qualifier.as_str()).unwrap_or(""));
+                serialize_string(serialized, identifier);
+            },
+            Expression::Literal(value) => {
+                serialized.put_u8(3);
+                serialized.put_u8(value.get_type().serialize());
+                serialized.put_u32_le(value.get_bytes().len() as u32);
+                serialized.extend(value.get_bytes());
+            },
+            Expression::Parameter(index) => {
+                serialized.put_u8(4);
+                serialized.put_u32_le(*index as u32);
+            },
+            Expression::Function(name, args) => {
+                serialized.put_u8(5);
+                serialize_string(serialized, name);
+                serialized.put_u32_le(args.len() as u32);
+                for arg in args {
+                    arg.serialize(serialized);
+                }
+            },
+        }
+    }
+
+    pub fn deserialize(current_ptr: &mut &[u8]) -> Result<Expression, DecodeErrorType> {
+        match current_ptr.get_u8() {
+            0 => {
+                let operator = BinaryOperator::deserialize(current_ptr.get_u8())?;
+                let left = Self::deserialize(current_ptr)?;
+                let right = Self::deserialize(current_ptr)?;
+                Ok(Expression::Binary(operator, Box::new(left), Box::new(right)))
+            },
+            1 => {
+                let operator = UnaryOperator::deserialize(current_ptr.get_u8())?;
+                Ok(Expression::Unary(operator, Box::new(Self::deserialize(current_ptr)?)))
+            },
+            2 => {
+                let qualifier = deserialize_string(current_ptr)?;
+                let identifier = deserialize_string(current_ptr)?;
+                Ok(Expression::Identifier(Some(qualifier).filter(|qualifier| !qualifier.is_empty()), identifier))
+            },
+            3 => {
+                let type_id = current_ptr.get_u8();
+                let value_type = Type::deserialize(type_id)
+                    .map_err(|unknown_flag| DecodeErrorType::UnknownFlag(unknown_flag as usize))?;
+                let value_length = current_ptr.get_u32_le() as usize;
+                let value_bytes = Bytes::copy_from_slice(&current_ptr[..value_length]);
+                current_ptr.advance(value_length);
+                Value::create(value_bytes, value_type)
+                    .map(|value| Expression::Literal(value))
+                    .map_err(|_| DecodeErrorType::UnknownFlag(type_id as usize))
+            },
+            4 => Ok(Expression::Parameter(current_ptr.get_u32_le() as usize)),
+            5 => {
+                let name = deserialize_string(current_ptr)?;
+                let n_args = current_ptr.get_u32_le() as usize;
+                let mut args = Vec::with_capacity(n_args);
+                for _ in 0..n_args {
+                    args.push(Self::deserialize(current_ptr)?);
+                }
+                Ok(Expression::Function(name, args))
+            },
+            unknown_flag => Err(DecodeErrorType::UnknownFlag(unknown_flag as usize))
+        }
+    }
+
     pub fn get_value(&self) -> Result<Value, SimpleDbError> {
         match self {
             Expression::Literal(value) => Ok(value.clone()),
@@ -257,7 +421,131 @@ impl Expression {
     }
 }
 
+//Used to name the selected functions. For example: UPPER(nombre)
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Expression::Binary(operator, left, right) => write!(f, "({} {} {})", left, operator.symbol(), right),
+            Expression::Unary(UnaryOperator::IsNull, expression) => write!(f, "{} IS NULL", expression),
+            Expression::Unary(UnaryOperator::IsNotNull, expression) => write!(f, "{} IS NOT NULL", expression),
+            Expression::Unary(UnaryOperator::Not, expression) => write!(f, "NOT {}", expression),
+            Expression::Unary(UnaryOperator::Plus, expression) => write!(f, "+{}", expression),
+            Expression::Unary(UnaryOperator::Minus, expression) => write!(f, "-{}", expression),
+            Expression::Identifier(Some(qualifier), identifier) => write!(f, "{}.{}", qualifier, identifier),
+            Expression::Identifier(None, identifier) => write!(f, "{}", identifier),
+            Expression::Literal(value) if value.is_string() => write!(f, "\"{}\"", value.to_string()),
+            Expression::Literal(value) => write!(f, "{}", value.to_string()),
+            Expression::Parameter(_) => write!(f, "?"),
+            Expression::Function(name, args) => {
+                //The arguments are already delimited by the parentheses of the function
+                let args: Vec<String> = args.iter().map(|arg| match arg {
+                    Expression::Binary(operator, left, right) => format!("{} {} {}", left, operator.symbol(), right),
+                    arg => arg.to_string(),
+                }).collect();
+                write!(f, "{}({})", name, args.join(", "))
+            },
+        }
+    }
+}
+
+fn serialize_string(serialized: &mut Vec<u8>, string: &str) {
+    serialized.put_u32_le(string.len() as u32);
+    serialized.extend(string.as_bytes());
+}
+
+fn deserialize_string(current_ptr: &mut &[u8]) -> Result<String, DecodeErrorType> {
+    let string_length = current_ptr.get_u32_le() as usize;
+    let string = String::from_utf8(current_ptr[..string_length].to_vec())
+        .map_err(|e| DecodeErrorType::Utf8Decode(e))?;
+    current_ptr.advance(string_length);
+    Ok(string)
+}
+
+impl UnaryOperator {
+    pub fn serialize(&self) -> u8 {
+        match self {
+            UnaryOperator::Plus => 0,
+            UnaryOperator::Minus => 1,
+            UnaryOperator::IsNull => 2,
+            UnaryOperator::IsNotNull => 3,
+            UnaryOperator::Not => 4,
+        }
+    }
+
+    pub fn deserialize(value: u8) -> Result<UnaryOperator, DecodeErrorType> {
+        match value {
+            0 => Ok(UnaryOperator::Plus),
+            1 => Ok(UnaryOperator::Minus),
+            2 => Ok(UnaryOperator::IsNull),
+            3 => Ok(UnaryOperator::IsNotNull),
+            4 => Ok(UnaryOperator::Not),
+            _ => Err(DecodeErrorType::UnknownFlag(value as usize))
+        }
+    }
+}
+
 impl BinaryOperator {
+    pub fn serialize(&self) -> u8 {
+        match self {
+            BinaryOperator::Add => 0,
+            BinaryOperator::Subtract => 1,
+            BinaryOperator::Multiply => 2,
+            BinaryOperator::Divide => 3,
+            BinaryOperator::Modulo => 4,
+            BinaryOperator::And => 5,
+            BinaryOperator::Or => 6,
+            BinaryOperator::NotEqual => 7,
+            BinaryOperator::Equal => 8,
+            BinaryOperator::Greater => 9,
+            BinaryOperator::GreaterEqual => 10,
+            BinaryOperator::Less => 11,
+            BinaryOperator::LessEqual => 12,
+            BinaryOperator::Like => 13,
+            BinaryOperator::ILike => 14,
+        }
+    }
+
+    pub fn deserialize(value: u8) -> Result<BinaryOperator, DecodeErrorType> {
+        match value {
+            0 => Ok(BinaryOperator::Add),
+            1 => Ok(BinaryOperator::Subtract),
+            2 => Ok(BinaryOperator::Multiply),
+            3 => Ok(BinaryOperator::Divide),
+            4 => Ok(BinaryOperator::Modulo),
+            5 => Ok(BinaryOperator::And),
+            6 => Ok(BinaryOperator::Or),
+            7 => Ok(BinaryOperator::NotEqual),
+            8 => Ok(BinaryOperator::Equal),
+            9 => Ok(BinaryOperator::Greater),
+            10 => Ok(BinaryOperator::GreaterEqual),
+            11 => Ok(BinaryOperator::Less),
+            12 => Ok(BinaryOperator::LessEqual),
+            13 => Ok(BinaryOperator::Like),
+            14 => Ok(BinaryOperator::ILike),
+            _ => Err(DecodeErrorType::UnknownFlag(value as usize))
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Modulo => "%",
+            BinaryOperator::And => "AND",
+            BinaryOperator::Or => "OR",
+            BinaryOperator::NotEqual => "!=",
+            BinaryOperator::Equal => "==",
+            BinaryOperator::Greater => ">",
+            BinaryOperator::GreaterEqual => ">=",
+            BinaryOperator::Less => "<",
+            BinaryOperator::LessEqual => "<=",
+            BinaryOperator::Like => "LIKE",
+            BinaryOperator::ILike => "ILIKE",
+        }
+    }
+
     //Takes booleans, Produces boolean
     pub fn is_logical(&self) -> bool {
         match self {
@@ -273,7 +561,8 @@ impl BinaryOperator {
             BinaryOperator::Add |
             BinaryOperator::Subtract |
             BinaryOperator::Multiply |
-            BinaryOperator::Divide => true,
+            BinaryOperator::Divide |
+            BinaryOperator::Modulo => true,
             BinaryOperator::And |
             BinaryOperator::Or |
             BinaryOperator::NotEqual |
@@ -281,7 +570,9 @@ impl BinaryOperator {
             BinaryOperator::Greater |
             BinaryOperator::GreaterEqual |
             BinaryOperator::Less |
-            BinaryOperator::LessEqual => false
+            BinaryOperator::LessEqual |
+            BinaryOperator::Like |
+            BinaryOperator::ILike => false
         }
     }
 
@@ -291,7 +582,8 @@ impl BinaryOperator {
             BinaryOperator::Add |
             BinaryOperator::Subtract |
             BinaryOperator::Multiply |
-            BinaryOperator::Divide => false,
+            BinaryOperator::Divide |
+            BinaryOperator::Modulo => false,
             BinaryOperator::And |
             BinaryOperator::Or |
             BinaryOperator::NotEqual |
@@ -299,7 +591,44 @@ impl BinaryOperator {
             BinaryOperator::Greater |
             BinaryOperator::GreaterEqual |
             BinaryOperator::Less |
-            BinaryOperator::LessEqual => true
+            BinaryOperator::LessEqual => true,
+            BinaryOperator::Like |
+            BinaryOperator::ILike => false,
         }
     }
-}
\ No newline at end of file
+
+    //Takes strings, Produces boolean
+    pub fn is_pattern_matching(&self) -> bool {
+        matches!(self, BinaryOperator::Like | BinaryOperator::ILike)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::sql::parser::expression::{BinaryOperator, Expression, UnaryOperator};
+    use shared::Value;
+
+    #[test]
+    fn serialize_deserialize() {
+        let expression = Expression::Binary(
+            BinaryOperator::And,
+            Box::new(Expression::Binary(
+                BinaryOperator::GreaterEqual,
+                Box::new(Expression::Identifier(None, String::from("dinero"))),
+                Box::new(Expression::Unary(UnaryOperator::Minus, Box::new(Expression::Literal(Value::create_i64(1))))),
+            )),
+            Box::new(Expression::Binary(
+                BinaryOperator::Like,
+                Box::new(Expression::Function(String::from("UPPER"), vec![
+                    Expression::Identifier(Some(String::from("personas")), String::from("nombre"))
+                ])),
+                Box::new(Expression::Literal(Value::create_string(String::from("J%")))),
+            )),
+        );
+
+        let mut serialized = Vec::new();
+        expression.serialize(&mut serialized);
+
+        assert_eq!(Expression::deserialize(&mut serialized.as_slice()).unwrap(), expression);
+    }
+}
@@ -1,49 +1,79 @@
-use crate::table::selection::Selection;
+use crate::table::selection::{Aggregate, AggregateFunction, Selection};
 use crate::sql::parser::expression::{BinaryOperator, Expression, UnaryOperator};
-use crate::sql::parser::statement::{CreateTableStatement, DeleteStatement, InsertStatement, Limit, SelectStatement, Statement, UpdateStatement};
+use crate::sql::parser::statement::{CreateTableStatement, DeleteStatement, IndexHint, InsertStatement, Limit, OnConflict, SelectStatement, Statement, UpdateStatement};
 use shared::{SimpleDbError, Type, Value};
 use shared::SimpleDbError::IllegalToken;
-use crate::{CreateIndexStatement, Sort, SortOrder};
+use crate::{AlterTableOperation, AlterTableStatement, ColumnDefinition, CreateIndexStatement, DropIndexStatement, NullsOrder, Sort, SortOrder};
 use crate::sql::token::token::Token;
 use crate::sql::token::tokenizer::Tokenizer;
+use storage::transactions::transaction_manager::IsolationLevel;
+use std::collections::HashMap;
 
 const MAX_PRECEDENCE: u8 = u8::MAX;
+//Same as AND. NOT a > 1 AND b > 2 is parsed as (NOT a > 1) AND b > 2
+const NOT_PRECEDENCE: u8 = 2;
 
 pub struct Parser {
     tokenizer: Tokenizer,
+    n_parameters: usize, //Nº of ? found. Used to give each parameter its position
 }
 
 impl Parser {
     pub fn create(query: String) -> Parser {
         Parser {
             tokenizer: Tokenizer::create(query),
+            n_parameters: 0,
         }
     }
 
     pub fn next_statement(
         &mut self,
     ) -> Result<Option<Statement>, SimpleDbError> {
+        self.n_parameters = 0;
         let mut first_token = self.tokenizer.next_token()?;
         let is_explain = matches!(first_token, Token::Explain);
+        let mut is_analyze = false;
         if is_explain {
             first_token = self.tokenizer.next_token()?;
+            if let Token::Identifier(identifier) = &first_token {
+                is_analyze = identifier.eq_ignore_ascii_case("ANALYZE");
+            }
+            if is_analyze {
+                first_token = self.tokenizer.next_token()?;
+            }
         }
 
         let query = match first_token {
-            Token::Select => self.select(is_explain),
+            Token::Select => self.select(is_explain, is_analyze),
             Token::Update => self.update(),
             Token::Delete => self.delete(),
             Token::Insert => self.insert(),
             Token::StartTransaction => self.start_transaction(),
             Token::Create => self.create_some(),
+            Token::Drop => self.drop_some(),
+            Token::Truncate => self.truncate(),
+            Token::Alter => self.alter_table(),
             Token::Rollback => self.rollback(),
             Token::Describe => self.describe(),
             Token::Commit => self.commit(),
+            Token::Savepoint => self.savepoint(),
+            Token::Release => self.release_savepoint(),
             Token::Show => self.show(),
+            Token::Use => self.use_database(),
+            //BACKUP is not a keyword, so it can still be used as an identifier
+            Token::Identifier(ref identifier) if identifier.eq_ignore_ascii_case("BACKUP") => self.backup(),
+            Token::Identifier(ref identifier) if identifier.eq_ignore_ascii_case("GRANT") => self.grant(),
+            Token::Identifier(ref identifier) if identifier.eq_ignore_ascii_case("VACUUM") => self.vacuum(),
+            Token::Identifier(ref identifier) if identifier.eq_ignore_ascii_case("CHECK") => self.check_database(),
             Token::EOF => return Ok(None),
             _ => Err(IllegalToken(self.tokenizer.current_location(), String::from("Unknown keyword")))
         }?;
-        self.expect_token(Token::Semicolon)?;
+        //The semicolon is not consumed, so the next call starts reading the following statement
+        if !self.check_last_token(Token::Semicolon) {
+            return Err(IllegalToken(self.tokenizer.current_location(), format!(
+                "Expected token {:?} but found {:?}", Token::Semicolon, self.tokenizer.last_token()
+            )));
+        }
         Ok(Some(query))
     }
 
@@ -53,39 +83,79 @@ impl Parser {
         self.expression(0)
     }
 
-    fn select(&mut self, explain: bool) -> Result<Statement, SimpleDbError> {
+    fn select(&mut self, explain: bool, analyze: bool) -> Result<Statement, SimpleDbError> {
         self.advance()?;
-        let selection = self.selection()?;
+        let distinct = self.maybe_expect_token(Token::Distinct)?;
+        let (mut selection, aliases) = self.selection()?;
         self.expect_token(Token::From)?;
         let table_name = self.identifier()?;
+        let index_hint = self.index_hint()?;
         let mut limit = Limit::None;
+        let mut offset = 0;
         let mut expression = None;
+        let mut group_by = None;
         let mut sort = None;
 
         if self.maybe_expect_token(Token::Limit)? {
-            limit = self.limit()?;
+            (limit, offset) = self.limit_offset()?;
         }
         if self.maybe_expect_token(Token::Where)? {
             expression = Some(self.expression(0)?);
         }
+        if self.maybe_expect_token(Token::Group)? {
+            group_by = Some(self.group_by()?);
+            //Selected columns will be the group by keys
+            if let Selection::Some(column_names) = selection {
+                selection = Selection::Aggregate(column_names, Vec::new());
+            }
+        }
         if self.maybe_expect_token(Token::Order)? {
             sort = Some(self.sort()?);
         }
 
         if self.maybe_expect_token(Token::Limit)? {
-            limit = self.limit()?;
+            (limit, offset) = self.limit_offset()?;
         }
 
         Ok(Statement::Select(SelectStatement {
             where_expr: expression,
             table_name,
             selection,
+            distinct,
+            aliases,
             explain,
+            analyze,
+            offset,
             limit,
-            sort: sort
+            group_by,
+            sort: sort,
+            index_hint,
         }))
     }
 
+    fn index_hint(&mut self) -> Result<Option<IndexHint>, SimpleDbError> {
+        let hint = match self.tokenizer.last_token() {
+            Token::Hint(hint) => hint.clone(),
+            _ => return Ok(None)
+        };
+        self.advance()?;
+
+        let upper_case_hint = hint.to_uppercase();
+        if upper_case_hint == "NO_INDEX" {
+            return Ok(Some(IndexHint::NoIndex));
+        }
+        if upper_case_hint.starts_with("INDEX(") && hint.ends_with(')') {
+            let column_names = hint.get("INDEX(".len()..hint.len() - 1)
+                .map(|it| it.split(',').map(|column_name| column_name.trim().to_string()).collect::<Vec<String>>())
+                .unwrap_or_default();
+            if !column_names.is_empty() && column_names.iter().all(|column_name| !column_name.is_empty()) {
+                return Ok(Some(IndexHint::Index(column_names)));
+            }
+        }
+
+        Err(IllegalToken(self.tokenizer.current_location(), format!("Unknown hint: {}", hint)))
+    }
+
     fn expression(&mut self, precedence: u8) -> Result<Expression, SimpleDbError> {
         let mut expression = self.parse_prefix()?;
         let mut next_precedence = self.get_precedence(self.tokenizer.last_token());
@@ -100,27 +170,112 @@ impl Parser {
 
     fn parse_infix(&mut self, precedence: u8, left: Expression) -> Result<Expression, SimpleDbError> {
         let binary_operator = match self.advance()? {
+            Token::In => return self.in_list(left),
+            Token::Is => return self.is_null(left),
             Token::And => BinaryOperator::And,
             Token::Or => BinaryOperator::Or,
             Token::NotEqual => BinaryOperator::NotEqual,
+            Token::Equal |
             Token::EqualEqual => BinaryOperator::Equal,
             Token::Less => BinaryOperator::Less,
             Token::LessEqual => BinaryOperator::LessEqual,
             Token::Greater => BinaryOperator::Greater,
             Token::GreaterEqual => BinaryOperator::GreaterEqual,
+            Token::Like => BinaryOperator::Like,
+            Token::ILike => BinaryOperator::ILike,
             Token::Plus => BinaryOperator::Add,
             Token::Slash => BinaryOperator::Divide,
+            Token::Percent => BinaryOperator::Modulo,
             Token::Star => BinaryOperator::Multiply,
             Token::Minus => BinaryOperator::Subtract,
             _ => return Err(IllegalToken(
                 self.tokenizer.current_location(), String::from("Cannot use it as a binary operator")
             ))
         };
+        let is_comparation = binary_operator.is_comparation() && !binary_operator.is_logical();
+        if is_comparation && self.maybe_expect_token(Token::All)? {
+            return self.quantified_comparation(binary_operator, BinaryOperator::And, left, "ALL");
+        }
+        if is_comparation && self.maybe_expect_token(Token::Any)? {
+            return self.quantified_comparation(binary_operator, BinaryOperator::Or, left, "ANY");
+        }
+
         let right = self.expression(precedence)?;
 
         Ok(Expression::Binary(binary_operator, Box::new(left), Box::new(right)))
     }
 
+    //Expect IN to have been consumed. column IN (a, b, c) will be parsed as a balanced tree of equalities:
+    //(column == a OR column == b) OR column == c
+    fn in_list(&mut self, left: Expression) -> Result<Expression, SimpleDbError> {
+        let equalities = self.value_list("IN")?.into_iter()
+            .map(|value| Expression::Binary(BinaryOperator::Equal, Box::new(left.clone()), Box::new(value)))
+            .collect();
+
+        Ok(Self::balanced_tree(BinaryOperator::Or, equalities))
+    }
+
+    //Expect ALL or ANY to have been consumed. column > ALL (a, b) will be parsed as column > a AND column > b,
+    //column > ANY (a, b) will be parsed as column > a OR column > b
+    fn quantified_comparation(
+        &mut self,
+        comparation: BinaryOperator,
+        logical_operator: BinaryOperator,
+        left: Expression,
+        keyword: &str,
+    ) -> Result<Expression, SimpleDbError> {
+        let comparations = self.value_list(keyword)?.into_iter()
+            .map(|value| Expression::Binary(comparation.clone(), Box::new(left.clone()), Box::new(value)))
+            .collect();
+
+        Ok(Self::balanced_tree(logical_operator, comparations))
+    }
+
+    fn value_list(&mut self, keyword: &str) -> Result<Vec<Expression>, SimpleDbError> {
+        self.expect_token(Token::OpenParen)?;
+        if self.check_last_token(Token::CloseParen) {
+            return Err(SimpleDbError::MalformedQuery(format!("{} list cannot be empty", keyword)));
+        }
+
+        let mut values = Vec::new();
+        loop {
+            values.push(self.expression(0)?);
+
+            if !self.maybe_expect_token(Token::Comma)? {
+                break;
+            }
+        }
+
+        self.expect_token(Token::CloseParen)?;
+
+        Ok(values)
+    }
+
+    //Expect IS to have been consumed
+    fn is_null(&mut self, left: Expression) -> Result<Expression, SimpleDbError> {
+        let operator = if self.maybe_expect_token(Token::Not)? {
+            UnaryOperator::IsNotNull
+        } else {
+            UnaryOperator::IsNull
+        };
+        self.expect_token(Token::Null)?;
+
+        Ok(Expression::Unary(operator, Box::new(left)))
+    }
+
+    fn balanced_tree(operator: BinaryOperator, mut expressions: Vec<Expression>) -> Expression {
+        if expressions.len() == 1 {
+            return expressions.pop().unwrap();
+        }
+
+        let right = expressions.split_off(expressions.len() / 2);
+        Expression::Binary(
+            operator.clone(),
+            Box::new(Self::balanced_tree(operator.clone(), expressions)),
+            Box::new(Self::balanced_tree(operator, right))
+        )
+    }
+
     fn parse_prefix(&mut self) -> Result<Expression, SimpleDbError> {
         match self.advance()? {
             Token::False => Ok(Expression::Literal(Value::create_boolean(false))),
@@ -128,9 +283,14 @@ impl Parser {
             Token::NumberF64(num) => Ok(Expression::Literal(Value::create_f64(num))),
             Token::NumberI64(num) => Ok(Expression::Literal(Value::create_i64(num))),
             Token::String(string) => Ok(Expression::Literal(Value::create_string(string))),
-            Token::Identifier(identifier) => Ok(Expression::Identifier(identifier)),
+            //<table>.<column>
+            Token::Identifier(qualifier) if self.maybe_expect_token(Token::Dot)? => Ok(Expression::Identifier(Some(qualifier), self.identifier()?)),
+            Token::Identifier(function_name) if self.maybe_expect_token(Token::OpenParen)? => self.function_call(&function_name),
+            Token::Identifier(identifier) => Ok(Expression::Identifier(None, identifier)),
+            Token::QuestionMark => Ok(Expression::Parameter(self.next_parameter())),
             Token::Minus => Ok(Expression::Unary(UnaryOperator::Minus, Box::new(self.expression(MAX_PRECEDENCE)?))),
             Token::Plus => Ok(Expression::Unary(UnaryOperator::Plus, Box::new(self.expression(MAX_PRECEDENCE)?))),
+            Token::Not => Ok(Expression::Unary(UnaryOperator::Not, Box::new(self.expression(NOT_PRECEDENCE)?))),
             Token::OpenParen => {
                 let result = self.expression(0)?;
                 self.expect_token(Token::CloseParen)?;
@@ -140,17 +300,66 @@ impl Parser {
         }
     }
 
+    //Expect function name and ( to have been consumed. The arguments are validated against the function later
+    fn function_call(&mut self, function_name: &str) -> Result<Expression, SimpleDbError> {
+        let mut args = Vec::new();
+
+        while !self.check_last_token(Token::CloseParen) {
+            args.push(self.expression(0)?);
+
+            if !self.check_last_token(Token::CloseParen) {
+                self.expect_token(Token::Comma)?;
+            }
+        }
+        self.expect_token(Token::CloseParen)?;
+
+        Ok(Expression::Function(function_name.to_uppercase(), args))
+    }
+
     fn sort(&mut self) -> Result<Sort, SimpleDbError> {
         self.expect_token(Token::By)?;
-        let column_name = self.identifier()?;
+        let mut columns = Vec::new();
+
+        loop {
+            let column_name = self.identifier()?;
+
+            let mut order = SortOrder::Asc;
+            self.maybe_expect_token(Token::Asc)?;
+            if self.maybe_expect_token(Token::Desc)? {
+                order = SortOrder::Desc;
+            }
+
+            let mut nulls_order = order.default_nulls_order();
+            if let Token::Identifier(identifier) = self.tokenizer.last_token().clone() {
+                if identifier.eq_ignore_ascii_case("NULLS") {
+                    self.advance()?;
+                    nulls_order = match self.identifier()? {
+                        placement if placement.eq_ignore_ascii_case("FIRST") => NullsOrder::First,
+                        placement if placement.eq_ignore_ascii_case("LAST") => NullsOrder::Last,
+                        _ => return Err(IllegalToken(self.tokenizer.current_location(), String::from("Expected FIRST or LAST"))),
+                    };
+                }
+            }
+
+            columns.push((column_name, order, nulls_order));
+
+            if !self.maybe_expect_token(Token::Comma)? {
+                break;
+            }
+        }
+
+        Ok(Sort{columns})
+    }
+
+    fn group_by(&mut self) -> Result<Vec<String>, SimpleDbError> {
+        self.expect_token(Token::By)?;
+        let mut column_names = vec![self.identifier()?];
 
-        let mut order = SortOrder::Asc;
-        self.maybe_expect_token(Token::Asc)?;
-        if self.maybe_expect_token(Token::Desc)? {
-            order = SortOrder::Desc;
+        while self.maybe_expect_token(Token::Comma)? {
+            column_names.push(self.identifier()?);
         }
 
-        Ok(Sort{column_name, order})
+        Ok(column_names)
     }
 
     fn limit(&mut self) -> Result<Limit, SimpleDbError> {
@@ -158,13 +367,118 @@ impl Parser {
         Ok(Limit::Some(limit_value as usize))
     }
 
-    fn selection(&mut self) -> Result<Selection, SimpleDbError> {
-        if !self.check_last_token(Token::Star) {
-            Ok(Selection::Some(self.column_names(&Token::From)?))
-        } else {
+    fn limit_offset(&mut self) -> Result<(Limit, i64), SimpleDbError> {
+        let limit = self.limit()?;
+        let mut offset = 0;
+        if self.maybe_expect_token(Token::Offset)? {
+            //Negative offsets are parsed, so the validator can reject them
+            let is_negative = self.maybe_expect_token(Token::Minus)?;
+            offset = self.number_i64()?;
+            if is_negative {
+                offset = -offset;
+            }
+        }
+
+        Ok((limit, offset))
+    }
+
+    //Returns the selection and the aliases of the selected columns and aggregates, keyed by their position in the
+    //selected columns, so the same column can be selected more than once with different aliases
+    fn selection(&mut self) -> Result<(Selection, HashMap<usize, String>), SimpleDbError> {
+        if self.check_last_token(Token::Star) {
             self.advance()?; //Consume *
-            Ok(Selection::All)
+            return Ok((Selection::All, HashMap::new()));
+        }
+
+        let mut column_names = Vec::new();
+        let mut aggregates = Vec::new();
+        //Every selected column and function. Only used if some function is selected
+        let mut expressions = Vec::new();
+        let mut has_functions = false;
+        //(Index in column_names, Index in aggregates, Index in expressions, Alias)
+        let mut selected_aliases = Vec::new();
+
+        while !self.check_last_token(Token::From) {
+            let (column_index, aggregate_index, expression_index) = match self.advance()? {
+                Token::Identifier(identifier) if self.maybe_expect_token(Token::OpenParen)? => {
+                    if AggregateFunction::from_name(&identifier).is_some() {
+                        aggregates.push(self.aggregate(&identifier)?);
+                        (None, Some(aggregates.len() - 1), None)
+                    } else {
+                        let function = self.function_call(&identifier)?;
+                        expressions.push((function.to_string(), function));
+                        has_functions = true;
+                        (None, None, Some(expressions.len() - 1))
+                    }
+                },
+                Token::Identifier(identifier) => {
+                    column_names.push(identifier.clone());
+                    expressions.push((identifier.clone(), Expression::Identifier(None, identifier)));
+                    (Some(column_names.len() - 1), None, Some(expressions.len() - 1))
+                },
+                _ => return Err(IllegalToken(self.tokenizer.current_location(), String::from("Expected column name")))
+            };
+
+            if self.maybe_expect_token(Token::As)? {
+                selected_aliases.push((column_index, aggregate_index, expression_index, self.identifier()?));
+            }
+
+            if !self.check_last_token(Token::From) {
+                self.expect_token(Token::Comma)?;
+            }
+        }
+
+        //Non aggregated columns will be validated against the GROUP BY clause
+        if has_functions && !aggregates.is_empty() {
+            return Err(SimpleDbError::MalformedQuery(String::from("Functions cannot be selected with aggregates")));
+        }
+
+        //Aggregates are placed after the selected columns
+        let n_columns = column_names.len();
+        let aliases = selected_aliases.into_iter()
+            .map(|(column_index, aggregate_index, expression_index, alias)| {
+                let position = if has_functions {
+                    expression_index.unwrap()
+                } else {
+                    column_index.unwrap_or_else(|| n_columns + aggregate_index.unwrap())
+                };
+                (position, alias)
+            })
+            .collect();
+
+        if has_functions {
+            Ok((Selection::Expressions(expressions), aliases))
+        } else if aggregates.is_empty() {
+            Ok((Selection::Some(column_names), aliases))
+        } else {
+            Ok((Selection::Aggregate(column_names, aggregates), aliases))
+        }
+    }
+
+    //Expect function name and ( to have been consumed
+    fn aggregate(&mut self, function_name: &str) -> Result<Aggregate, SimpleDbError> {
+        let mut function = AggregateFunction::from_name(function_name)
+            .ok_or(IllegalToken(self.tokenizer.current_location(), format!("Unknown aggregate function {}", function_name)))?;
+
+        if self.maybe_expect_token(Token::Distinct)? {
+            if function != AggregateFunction::Count {
+                return Err(IllegalToken(self.tokenizer.current_location(), String::from("Only COUNT accepts DISTINCT")));
+            }
+            function = AggregateFunction::CountDistinct;
         }
+
+        let column_name = if self.maybe_expect_token(Token::Star)? {
+            if function != AggregateFunction::Count {
+                return Err(IllegalToken(self.tokenizer.current_location(), String::from("Only COUNT accepts *")));
+            }
+            None
+        } else {
+            Some(self.identifier()?)
+        };
+
+        self.expect_token(Token::CloseParen)?;
+
+        Ok(Aggregate { function, column_name })
     }
 
     fn update(&mut self) -> Result<Statement, SimpleDbError> {
@@ -232,19 +546,116 @@ impl Parser {
         Ok(Statement::Commit)
     }
 
+    //TO is not a keyword, so it can still be used as a column name
     fn rollback(&mut self) -> Result<Statement, SimpleDbError> {
         self.advance()?;
-        Ok(Statement::Rollback)
+        match self.tokenizer.last_token().clone() {
+            Token::Identifier(identifier) if identifier.eq_ignore_ascii_case("TO") => {
+                self.advance()?;
+                self.maybe_expect_token(Token::Savepoint)?;
+                Ok(Statement::RollbackToSavepoint(self.identifier()?))
+            },
+            _ => Ok(Statement::Rollback)
+        }
+    }
+
+    fn savepoint(&mut self) -> Result<Statement, SimpleDbError> {
+        self.advance()?;
+        Ok(Statement::Savepoint(self.identifier()?))
+    }
+
+    //BACKUP TO "path"
+    fn backup(&mut self) -> Result<Statement, SimpleDbError> {
+        self.advance()?;
+        self.expect_identifier_keyword("TO")?;
+        match self.advance()? {
+            Token::String(path) => Ok(Statement::Backup(path)),
+            _ => Err(IllegalToken(self.tokenizer.current_location(), String::from("Expected backup path")))
+        }
+    }
+
+    //GRANT <database> TO <user>. GRANT is not a keyword
+    fn grant(&mut self) -> Result<Statement, SimpleDbError> {
+        self.advance()?;
+        let database_name = self.identifier()?;
+        self.expect_identifier_keyword("TO")?;
+        let user_name = self.identifier()?;
+        Ok(Statement::Grant(database_name, user_name))
+    }
+
+    //VACUUM [TABLE <name>]. VACUUM is not a keyword. Without a table name, all the tables of the database are vacuumed
+    fn vacuum(&mut self) -> Result<Statement, SimpleDbError> {
+        self.advance()?;
+        if self.maybe_expect_token(Token::Table)? {
+            Ok(Statement::Vacuum(Some(self.identifier()?)))
+        } else {
+            Ok(Statement::Vacuum(None))
+        }
+    }
+
+    //CHECK DATABASE. CHECK is not a keyword
+    fn check_database(&mut self) -> Result<Statement, SimpleDbError> {
+        self.advance()?;
+        self.expect_token(Token::Database)?;
+        Ok(Statement::CheckDatabase)
+    }
+
+    //CREATE USER <name> PASSWORD "password". USER and PASSWORD are not keywords
+    fn create_user(&mut self) -> Result<Statement, SimpleDbError> {
+        let user_name = self.identifier()?;
+        self.expect_identifier_keyword("PASSWORD")?;
+        match self.advance()? {
+            Token::String(password) => Ok(Statement::CreateUser(user_name, password)),
+            _ => Err(IllegalToken(self.tokenizer.current_location(), String::from("Expected user password")))
+        }
+    }
+
+    fn release_savepoint(&mut self) -> Result<Statement, SimpleDbError> {
+        self.advance()?;
+        self.maybe_expect_token(Token::Savepoint)?;
+        Ok(Statement::ReleaseSavepoint(self.identifier()?))
     }
 
+    //START_TRANSACTION [ISOLATION LEVEL SNAPSHOT|READ UNCOMMITTED]. ISOLATION and LEVEL are not keywords
     fn start_transaction(&mut self) -> Result<Statement, SimpleDbError> {
         self.advance()?;
-        Ok(Statement::StartTransaction)
+        match self.tokenizer.last_token().clone() {
+            Token::Identifier(identifier) if identifier.eq_ignore_ascii_case("ISOLATION") => {
+                self.advance()?;
+                match self.identifier()? {
+                    level if level.eq_ignore_ascii_case("LEVEL") => {},
+                    _ => return Err(IllegalToken(self.tokenizer.current_location(), String::from("Expected LEVEL"))),
+                };
+                let mut isolation_level = self.identifier()?;
+                //Identifiers cannot contain '_', READ UNCOMMITTED is written as two words
+                if isolation_level.eq_ignore_ascii_case("READ") {
+                    isolation_level = format!("{} {}", isolation_level, self.identifier()?);
+                }
+                if isolation_level.eq_ignore_ascii_case("SNAPSHOT") {
+                    Ok(Statement::StartTransaction(IsolationLevel::SnapshotIsolation))
+                } else if isolation_level.eq_ignore_ascii_case("READ UNCOMMITTED") {
+                    Ok(Statement::StartTransaction(IsolationLevel::ReadUncommited))
+                } else {
+                    Err(SimpleDbError::MalformedQuery(format!("Unknown isolation level: {}", isolation_level)))
+                }
+            },
+            _ => Ok(Statement::StartTransaction(IsolationLevel::SnapshotIsolation))
+        }
     }
 
     fn insert(&mut self) -> Result<Statement, SimpleDbError> {
         self.advance()?;
 
+        let mut on_conflict = None;
+        if self.maybe_expect_token(Token::Or)? {
+            //REPLACE and IGNORE are not reserved, so they can still be used as identifiers
+            on_conflict = match self.advance()? {
+                Token::Identifier(ref identifier) if identifier.eq_ignore_ascii_case("REPLACE") => Some(OnConflict::Replace),
+                Token::Identifier(ref identifier) if identifier.eq_ignore_ascii_case("IGNORE") => Some(OnConflict::DoNothing),
+                _ => return Err(IllegalToken(self.tokenizer.current_location(), String::from("Expect REPLACE or IGNORE")))
+            };
+        }
+
         self.expect_token(Token::Into)?;
         match self.advance()? {
             Token::Identifier(table_name) => {
@@ -252,13 +663,31 @@ impl Parser {
                 let column_names = self.column_names(&Token::CloseParen)?;
                 self.expect_token(Token::CloseParen)?;
                 self.expect_token(Token::Values)?;
-                self.expect_token(Token::OpenParen)?;
-                let column_values = self.column_values(&Token::CloseParen)?;
-                self.expect_token(Token::CloseParen)?;
-                let column_name_values = self.create_insert_statement_values(column_names, column_values)?;
+
+                let mut column_name_values = Vec::new();
+                let mut parameters = Vec::new();
+                loop {
+                    self.expect_token(Token::OpenParen)?;
+                    let column_values = self.column_values(&Token::CloseParen)?;
+                    self.expect_token(Token::CloseParen)?;
+                    let (row_values, row_parameters) = self.create_insert_statement_values(&column_names, column_values)?;
+                    column_name_values.push(row_values);
+                    parameters.push(row_parameters);
+
+                    if !self.maybe_expect_token(Token::Comma)? {
+                        break;
+                    }
+                }
+
+                let on_conflict = match on_conflict {
+                    Some(on_conflict) => on_conflict,
+                    None => self.on_conflict()?,
+                };
 
                 Ok(Statement::Insert(InsertStatement {
                     values: column_name_values,
+                    parameters,
+                    on_conflict,
                     table_name,
                 }))
             },
@@ -266,28 +695,52 @@ impl Parser {
         }
     }
 
+    fn on_conflict(&mut self) -> Result<OnConflict, SimpleDbError> {
+        if self.maybe_expect_token(Token::On)? {
+            self.expect_token(Token::Conflict)?;
+            self.expect_token(Token::Do)?;
+            self.expect_token(Token::Nothing)?;
+            Ok(OnConflict::DoNothing)
+        } else {
+            Ok(OnConflict::Fail)
+        }
+    }
+
+    fn next_parameter(&mut self) -> usize {
+        self.n_parameters += 1;
+        self.n_parameters - 1
+    }
+
+    //Returns the column values and the columns whose value is a parameter, both in the declared order
     fn create_insert_statement_values(
         &self,
-        mut column_names: Vec<String>,
-        mut column_values_tokens: Vec<Value>
-    ) -> Result<Vec<(String, Value)>, SimpleDbError> {
-        if column_names.len() != column_values_tokens.len() {
-            return Err(SimpleDbError::MalformedQuery(String::from(
-                "Insert statements should have the same number of columns and values"
+        column_names: &Vec<String>,
+        column_values_tokens: Vec<Expression>
+    ) -> Result<(Vec<(String, Value)>, Vec<(usize, String, usize)>), SimpleDbError> {
+        if column_names.len() > column_values_tokens.len() {
+            let position = column_values_tokens.len();
+            return Err(SimpleDbError::MalformedQuery(format!(
+                "Column {} at position {} has no value", column_names[position], position + 1
+            )));
+        }
+        if column_values_tokens.len() > column_names.len() {
+            return Err(SimpleDbError::MalformedQuery(format!(
+                "Value at position {} has no column", column_names.len() + 1
             )));
         }
 
         let mut insert_values = Vec::new();
+        let mut parameters = Vec::new();
 
-        //TODO Check if value can be casted to column type
-        while !column_names.is_empty() {
-            let column_value = column_values_tokens.pop().unwrap();
-            let column_name = column_names.pop().unwrap();
-
-            insert_values.push((column_name, column_value));
+        //Values are checked against the column types by the validator, which has access to the table schema
+        for (position, (column_name, column_value)) in column_names.iter().zip(column_values_tokens).enumerate() {
+            match column_value {
+                Expression::Parameter(index) => parameters.push((position, column_name.clone(), index)),
+                column_value => insert_values.push((column_name.clone(), column_value.get_value()?)),
+            }
         }
 
-        Ok(insert_values)
+        Ok((insert_values, parameters))
     }
 
     fn column_names(&mut self, terminator_token: &Token) -> Result<Vec<String>, SimpleDbError> {
@@ -306,17 +759,21 @@ impl Parser {
         Ok(column_names)
     }
 
-    fn column_values(&mut self, terminator_token: &Token) -> Result<Vec<Value>, SimpleDbError> {
+    //Values will be returned as literals or parameters
+    fn column_values(&mut self, terminator_token: &Token) -> Result<Vec<Expression>, SimpleDbError> {
         let mut column_values = Vec::new();
         while !self.check_last_token(terminator_token.clone()) {
             let token = self.advance()?;
-            let value = token.serialize()
-                .map_err(|_| IllegalToken(
-                    self.tokenizer.current_location(),
-                    String::from("Value cannot be inserted into a row"))
-                )?;
-
-            column_values.push(value);
+            if token == Token::QuestionMark {
+                column_values.push(Expression::Parameter(self.next_parameter()));
+            } else {
+                let value = token.serialize()
+                    .map_err(|_| IllegalToken(
+                        self.tokenizer.current_location(),
+                        String::from("Value cannot be inserted into a row"))
+                    )?;
+                column_values.push(Expression::Literal(value));
+            }
 
             if !self.check_last_token(terminator_token.clone()) {
                 self.expect_token(Token::Comma)?;
@@ -331,24 +788,89 @@ impl Parser {
         match self.advance()? {
             Token::Database => self.create_database(),
             Token::Table => self.create_table(),
-            Token::Index => self.create_index(),
+            Token::Index => self.create_index(false),
+            Token::Unique => {
+                self.expect_token(Token::Index)?;
+                self.create_index(true)
+            },
+            Token::Identifier(identifier) if identifier.eq_ignore_ascii_case("USER") => self.create_user(),
             _ => Err(IllegalToken(self.tokenizer.current_location(), String::from("Invalid token after create")))
         }
     }
 
-    fn create_index(&mut self) -> Result<Statement, SimpleDbError> {
+    fn drop_some(&mut self) -> Result<Statement, SimpleDbError> {
+        self.advance()?;
+        match self.advance()? {
+            Token::Table => Ok(Statement::DropTable(self.identifier()?)),
+            Token::Index => self.drop_index(),
+            _ => Err(IllegalToken(self.tokenizer.current_location(), String::from("Invalid token after drop")))
+        }
+    }
+
+    fn truncate(&mut self) -> Result<Statement, SimpleDbError> {
+        self.advance()?;
+        match self.advance()? {
+            Token::Table => Ok(Statement::TruncateTable(self.identifier()?)),
+            _ => Err(IllegalToken(self.tokenizer.current_location(), String::from("Invalid token after truncate")))
+        }
+    }
+
+    fn alter_table(&mut self) -> Result<Statement, SimpleDbError> {
+        self.advance()?;
+        self.expect_token(Token::Table)?;
+        let table_name = self.identifier()?;
+
+        let operation = match self.advance()? {
+            Token::Drop => {
+                self.expect_identifier_keyword("COLUMN")?;
+                AlterTableOperation::DropColumn(self.identifier()?)
+            },
+            Token::Identifier(identifier) if identifier.eq_ignore_ascii_case("RENAME") => {
+                self.expect_identifier_keyword("COLUMN")?;
+                let old_column_name = self.identifier()?;
+                self.expect_identifier_keyword("TO")?;
+                AlterTableOperation::RenameColumn(old_column_name, self.identifier()?)
+            },
+            _ => return Err(IllegalToken(self.tokenizer.current_location(), String::from("Invalid token after alter table")))
+        };
+
+        Ok(Statement::AlterTable(AlterTableStatement { table_name, operation }))
+    }
+
+    //Words like COLUMN or TO are not keywords, so they can still be used as identifiers
+    fn expect_identifier_keyword(&mut self, keyword: &str) -> Result<(), SimpleDbError> {
+        match self.advance()? {
+            Token::Identifier(identifier) if identifier.eq_ignore_ascii_case(keyword) => Ok(()),
+            _ => Err(IllegalToken(self.tokenizer.current_location(), format!("Expected {}", keyword)))
+        }
+    }
+
+    fn drop_index(&mut self) -> Result<Statement, SimpleDbError> {
+        let column_name = self.identifier()?;
+        self.expect_token(Token::On)?;
+        let table_name = self.identifier()?;
+
+        Ok(Statement::DropIndex(DropIndexStatement { table_name, column_name }))
+    }
+
+    fn create_index(&mut self, unique: bool) -> Result<Statement, SimpleDbError> {
         match self.advance()? {
             Token::On => {
                 let table_name = self.identifier()?;
                 self.expect_token(Token::OpenParen)?;
-                let column_name = self.identifier()?;
+                let column_names = self.column_names(&Token::CloseParen)?;
                 self.expect_token(Token::CloseParen)?;
                 let is_async = self.maybe_expect_token(Token::Async)?;
 
+                if column_names.is_empty() {
+                    return Err(SimpleDbError::MalformedQuery(String::from("Expected at least one column to be indexed")));
+                }
+
                 Ok(Statement::CreateIndex(CreateIndexStatement {
-                    column_name,
+                    column_names,
                     table_name,
-                    wait: !is_async
+                    wait: !is_async,
+                    unique,
                 }))
             },
             _ => Err(IllegalToken(self.tokenizer.current_location(), String::from("Expect ON after CREATE INDEX")))
@@ -359,17 +881,35 @@ impl Parser {
         match self.advance()? {
             Token::Identifier(table_name) => {
                 self.expect_token(Token::OpenParen)?;
-                let columns = self.create_table_columns()?;
+                let (columns, checks) = self.create_table_columns()?;
+                let ttl_seconds = self.create_table_ttl()?;
 
                 Ok(Statement::CreateTable(CreateTableStatement {
                     table_name,
-                    columns
+                    columns,
+                    checks,
+                    ttl_seconds
                 }))
             },
             _ => Err(IllegalToken(self.tokenizer.current_location(), String::from("Expect table name")))
         }
     }
 
+    //WITH TTL <seconds>
+    fn create_table_ttl(&mut self) -> Result<Option<u64>, SimpleDbError> {
+        match self.tokenizer.last_token().clone() {
+            Token::Identifier(identifier) if identifier.eq_ignore_ascii_case("WITH") => {
+                self.advance()?;
+                self.expect_identifier_keyword("TTL")?;
+                match self.number_i64()? {
+                    ttl_seconds if ttl_seconds > 0 => Ok(Some(ttl_seconds as u64)),
+                    _ => Err(IllegalToken(self.tokenizer.current_location(), String::from("TTL should be greater than 0")))
+                }
+            },
+            _ => Ok(None)
+        }
+    }
+
     fn create_database(&mut self) -> Result<Statement, SimpleDbError> {
         match self.advance()? {
             Token::Identifier(database_name) => Ok(Statement::CreateDatabase(database_name)),
@@ -377,22 +917,35 @@ impl Parser {
         }
     }
 
-    fn create_table_columns(&mut self) -> Result<Vec<(String, Type, bool)>, SimpleDbError> {
+    //Returns the columns and the CHECK (<expression>) constraints
+    fn create_table_columns(&mut self) -> Result<(Vec<ColumnDefinition>, Vec<Expression>), SimpleDbError> {
         let mut columns = Vec::new();
+        let mut checks = Vec::new();
 
         while !self.maybe_expect_token(Token::CloseParen)? {
-            let table_name = self.identifier()?;
+            let column_name = self.identifier()?;
+            //CHECK is not a keyword, so it can still be used as a column name
+            if column_name.eq_ignore_ascii_case("CHECK") && self.check_last_token(Token::OpenParen) {
+                checks.push(self.expression(0)?);
+                if !self.check_last_token(Token::CloseParen) {
+                    self.expect_token(Token::Comma)?;
+                }
+                continue;
+            }
+
             let column_type = self.column_type()?;
             let is_primary = self.is_primary_key()?;
+            let is_not_null = self.is_not_null()?;
+            let default_value = self.column_default_value()?;
 
-            columns.push((table_name, column_type, is_primary));
+            columns.push(ColumnDefinition { column_name, column_type, is_primary, default_value, is_not_null });
 
             if !self.check_last_token(Token::CloseParen) {
                 self.expect_token(Token::Comma)?;
             }
         }
 
-        Ok(columns)
+        Ok((columns, checks))
     }
 
     fn show(&mut self) -> Result<Statement, SimpleDbError> {
@@ -402,16 +955,46 @@ impl Parser {
             Token::Databases => Ok(Statement::ShowDatabases),
             Token::Tables => Ok(Statement::ShowTables),
             Token::Index => self.show_indexes(),
+            Token::Transaction => self.show_transaction(),
+            //TRANSACTIONS and STATS are not keywords, so they can still be used as table names
+            Token::Identifier(identifier) if identifier.eq_ignore_ascii_case("TRANSACTIONS") => Ok(Statement::ShowTransactions),
+            Token::Identifier(identifier) if identifier.eq_ignore_ascii_case("STATS") => self.show_stats(),
             _ => Err(IllegalToken(self.tokenizer.current_location(), String::from("Expact Databases or tables after show")))
         }
     }
 
+    //SHOW TRANSACTION or SHOW TRANSACTION STATUS. STATUS is not a keyword, so it can still be used as a column name
+    fn show_transaction(&mut self) -> Result<Statement, SimpleDbError> {
+        match self.tokenizer.last_token() {
+            Token::Identifier(identifier) if identifier.eq_ignore_ascii_case("STATUS") => {
+                self.advance()?;
+                Ok(Statement::ShowTransactionStatus)
+            },
+            _ => Ok(Statement::ShowTransaction),
+        }
+    }
+
     fn show_indexes(&mut self) -> Result<Statement, SimpleDbError> {
         self.expect_token(Token::From)?;
         let table_name = self.identifier()?;
         Ok(Statement::ShowIndexes(table_name))
     }
 
+    fn show_stats(&mut self) -> Result<Statement, SimpleDbError> {
+        self.expect_token(Token::From)?;
+        let table_name = self.identifier()?;
+        Ok(Statement::ShowStats(table_name))
+    }
+
+    fn use_database(&mut self) -> Result<Statement, SimpleDbError> {
+        self.advance()?;
+
+        match self.advance()? {
+            Token::Identifier(database_name) => Ok(Statement::Use(database_name)),
+            _ => Err(IllegalToken(self.tokenizer.current_location(), String::from("Expect database name after use"))),
+        }
+    }
+
     fn describe(&mut self) -> Result<Statement, SimpleDbError> {
         self.advance()?;
 
@@ -432,6 +1015,34 @@ impl Parser {
         Ok(is_primary)
     }
 
+    fn is_not_null(&mut self) -> Result<bool, SimpleDbError> {
+        let mut is_not_null = false;
+
+        if self.maybe_expect_token(Token::Not)? {
+            self.expect_token(Token::Null)?;
+            is_not_null = true;
+        }
+
+        Ok(is_not_null)
+    }
+
+    //DEFAULT <literal>. DEFAULT is not a keyword, so it can still be used as an identifier
+    fn column_default_value(&mut self) -> Result<Option<Value>, SimpleDbError> {
+        match self.tokenizer.last_token().clone() {
+            Token::Identifier(identifier) if identifier.eq_ignore_ascii_case("DEFAULT") => {
+                self.advance()?;
+                let default_value = self.advance()?.serialize()
+                    .map_err(|_| IllegalToken(
+                        self.tokenizer.current_location(),
+                        String::from("Expected literal after DEFAULT"))
+                    )?;
+                //DEFAULT NULL is the same as not having a default value
+                Ok(Some(default_value).filter(|default_value| !default_value.is_null()))
+            },
+            _ => Ok(None)
+        }
+    }
+
     fn column_type(&mut self) -> Result<Type, SimpleDbError> {
         match self.advance()? {
             Token::ColumnType(column_type) => Ok(column_type),
@@ -491,9 +1102,9 @@ impl Parser {
             Token::NumberI64(_) | Token::NumberF64(_) | Token::Identifier(_) | Token::String(_) => 0,
             Token::Or => 1,
             Token::And => 2,
-            Token::Greater | Token::GreaterEqual | Token::Less | Token::LessEqual | Token::EqualEqual | Token::NotEqual => 3,
+            Token::Greater | Token::GreaterEqual | Token::Less | Token::LessEqual | Token::Equal | Token::EqualEqual | Token::NotEqual | Token::Like | Token::ILike | Token::In | Token::Is => 3,
             Token::Plus | Token::Minus => 4,
-            Token::Slash | Token::Star => 5,
+            Token::Slash | Token::Star | Token::Percent => 5,
             _ => 0
         }
     }
@@ -501,12 +1112,48 @@ impl Parser {
 
 #[cfg(test)]
 mod test {
-    use shared::{Type, Value};
-    use crate::table::selection::Selection;
-    use crate::{Sort, SortOrder};
-    use crate::sql::parser::expression::{BinaryOperator, Expression};
+    use shared::{SimpleDbError, Type, Value};
+    use crate::table::selection::{Aggregate, AggregateFunction, Selection};
+    use crate::{ColumnDefinition, NullsOrder, Sort, SortOrder};
+    use crate::sql::parser::expression::{BinaryOperator, Expression, UnaryOperator};
     use crate::sql::parser::parser::Parser;
-    use crate::sql::parser::statement::{Limit, Statement};
+    use crate::sql::parser::statement::{AlterTableOperation, IndexHint, Limit, OnConflict, Statement};
+    use storage::transactions::transaction_manager::IsolationLevel;
+
+    #[test]
+    fn multiple_statements() {
+        let mut parser = Parser::create(String::from("START_TRANSACTION; DELETE FROM personas; COMMIT;"));
+        assert!(matches!(parser.next_statement().unwrap().unwrap(), Statement::StartTransaction(_)));
+        assert!(matches!(parser.next_statement().unwrap().unwrap(), Statement::Delete(_)));
+        assert!(matches!(parser.next_statement().unwrap().unwrap(), Statement::Commit));
+        assert!(parser.next_statement().unwrap().is_none());
+    }
+
+    #[test]
+    fn select_index_hint() {
+        let mut parser = Parser::create(String::from("SELECT * FROM personas /*+ INDEX(dinero) */ WHERE dinero > 10;"));
+        match parser.next_statement().unwrap().unwrap() {
+            Statement::Select(select) => assert_eq!(select.index_hint, Some(IndexHint::Index(vec![String::from("dinero")]))),
+            _ => panic!()
+        }
+
+        let mut parser = Parser::create(String::from("SELECT * FROM personas /*+ INDEX(nombre , dinero) */ WHERE dinero > 10;"));
+        match parser.next_statement().unwrap().unwrap() {
+            Statement::Select(select) => assert_eq!(select.index_hint, Some(IndexHint::Index(vec![String::from("nombre"), String::from("dinero")]))),
+            _ => panic!()
+        }
+
+        let mut parser = Parser::create(String::from("SELECT * FROM personas /*+ no_index */ /* comment */ WHERE dinero > 10;"));
+        match parser.next_statement().unwrap().unwrap() {
+            Statement::Select(select) => assert_eq!(select.index_hint, Some(IndexHint::NoIndex)),
+            _ => panic!()
+        }
+
+        let mut parser = Parser::create(String::from("SELECT * FROM personas /*+ FULL */;"));
+        assert!(parser.next_statement().is_err());
+        let mut parser = Parser::create(String::from("SELECT * FROM personas /*+ INDEX(nombre,) */;"));
+        assert!(parser.next_statement().is_err());
+    }
 
     #[test]
     fn update_all() {
@@ -543,7 +1190,7 @@ mod test {
         assert_eq!(update_statement.updated_values[0].0, "dinero");
         assert_eq!(update_statement.updated_values[0].1, Expression::Binary(
             BinaryOperator::Add,
-            Box::new(Expression::Identifier(String::from("dinero"))),
+            Box::new(Expression::Identifier(None, String::from("dinero"))),
             Box::new(Expression::Literal(Value::create_i64(10))))
         );
         assert_eq!(update_statement.updated_values[1].0, "id");
@@ -551,7 +1198,7 @@ mod test {
 
         assert_eq!(update_statement.where_expr.unwrap(), Expression::Binary(
             BinaryOperator::Greater,
-            Box::new(Expression::Identifier(String::from("dinero"))),
+            Box::new(Expression::Identifier(None, String::from("dinero"))),
             Box::new(Expression::Literal(Value::create_i64(10))),
         ));
     }
@@ -566,65 +1213,417 @@ mod test {
             Statement::Select(s) => s, _ => panic!(),
         };
         assert!(select_statement.explain);
+        assert!(!select_statement.analyze);
     }
 
     #[test]
-    fn select_with_expression_with_limit_and_sort() {
-        let mut parser = Parser::create(String::from("SELECT dinero FROM personas WHERE dinero > 10 ORDER BY fecha LIMIT 10;"));
+    fn select_explain_analyze() {
+        let mut parser = Parser::create(String::from("EXPLAIN ANALYZE SELECT dinero FROM personas WHERE dinero > 10;"));
         let statement = parser.next_statement().unwrap().unwrap();
-        assert!(matches!(statement, Statement::Select(_)));
+        assert!(statement.is_explained());
 
         let select_statement = match statement {
             Statement::Select(s) => s, _ => panic!(),
         };
-        let expected_column_names = vec!["dinero"];
-        assert!(matches!(select_statement.selection, Selection::Some(expected_column_names)));
-        assert!(matches!(select_statement.limit, Limit::Some(10)));
-        assert_eq!(select_statement.table_name, "personas");
-        assert_eq!(select_statement.where_expr.unwrap(), Expression::Binary(
-            BinaryOperator::Greater,
-            Box::new(Expression::Identifier(String::from("dinero"))),
-            Box::new(Expression::Literal(Value::create_i64(10))),
-        ));
-        assert_eq!(select_statement.sort, Some(Sort{
-            column_name: String::from("fecha"),
-            order: SortOrder::Asc
-        }));
+        assert!(select_statement.explain);
+        assert!(select_statement.analyze);
     }
 
     #[test]
-    fn select_without_expression_with_limit() {
-        let mut parser = Parser::create(String::from("SELECT nombre, dinero FROM personas LIMIT 10;"));
+    fn select_aggregates() {
+        let mut parser = Parser::create(String::from("SELECT COUNT(*), SUM(dinero), avg(dinero) FROM personas WHERE dinero > 10;"));
         let statement = parser.next_statement().unwrap().unwrap();
 
-        assert!(matches!(statement, Statement::Select(_)));
         let select_statement = match statement {
             Statement::Select(s) => s, _ => panic!(),
         };
-        let expected_column_names = vec!["nombre", "dinero"];
-        assert!(matches!(select_statement.selection, Selection::Some(expected_column_names)));
-        assert!(matches!(select_statement.where_expr, None));
-        assert!(matches!(select_statement.limit, Limit::Some(10)));
+        let aggregates = match select_statement.selection {
+            Selection::Aggregate(_, aggregates) => aggregates, _ => panic!(),
+        };
+        assert_eq!(aggregates, vec![
+            Aggregate { function: AggregateFunction::Count, column_name: None },
+            Aggregate { function: AggregateFunction::Sum, column_name: Some(String::from("dinero")) },
+            Aggregate { function: AggregateFunction::Avg, column_name: Some(String::from("dinero")) },
+        ]);
         assert_eq!(select_statement.table_name, "personas");
     }
 
     #[test]
-    fn delete_with_expression() {
-        let mut parser = Parser::create(String::from(
-            "DELETE FROM personas WHERE id == 1;"
-        ));
+    fn select_count_distinct() {
+        let mut parser = Parser::create(String::from("SELECT COUNT(DISTINCT nombre) FROM personas;"));
         let statement = parser.next_statement().unwrap().unwrap();
-
-        assert!(matches!(statement, Statement::Delete(_)));
         let select_statement = match statement {
-            Statement::Delete(s) => s, _ => panic!(),
+            Statement::Select(s) => s, _ => panic!(),
         };
-        assert!(matches!(select_statement.limit, Limit::None));
-        assert_eq!(select_statement.table_name, "personas");
+        match select_statement.selection {
+            Selection::Aggregate(_, aggregates) => assert_eq!(aggregates, vec![
+                Aggregate { function: AggregateFunction::CountDistinct, column_name: Some(String::from("nombre")) }
+            ]),
+            _ => panic!()
+        };
+
+        let mut parser = Parser::create(String::from("SELECT COUNT(DISTINCT *) FROM personas;"));
+        assert!(parser.next_statement().is_err());
+        let mut parser = Parser::create(String::from("SELECT SUM(DISTINCT dinero) FROM personas;"));
+        assert!(parser.next_statement().is_err());
+    }
+
+    #[test]
+    fn select_group_by() {
+        let mut parser = Parser::create(String::from("SELECT nombre, COUNT(*) FROM personas WHERE dinero > 10 GROUP BY nombre, apellido LIMIT 10;"));
+        let statement = parser.next_statement().unwrap().unwrap();
+
+        let select_statement = match statement {
+            Statement::Select(s) => s, _ => panic!(),
+        };
+        let (columns, aggregates) = match select_statement.selection {
+            Selection::Aggregate(columns, aggregates) => (columns, aggregates), _ => panic!(),
+        };
+        assert_eq!(columns, vec![String::from("nombre")]);
+        assert_eq!(aggregates, vec![Aggregate { function: AggregateFunction::Count, column_name: None }]);
+        assert_eq!(select_statement.group_by, Some(vec![String::from("nombre"), String::from("apellido")]));
+        assert!(select_statement.where_expr.is_some());
+        assert!(matches!(select_statement.limit, Limit::Some(10)));
+    }
+
+    #[test]
+    fn select_aggregates_with_columns() {
+        let mut parser = Parser::create(String::from("SELECT SUM(*) FROM personas;"));
+        assert!(parser.next_statement().is_err());
+    }
+
+    #[test]
+    fn select_with_expression_with_limit_and_sort() {
+        let mut parser = Parser::create(String::from("SELECT dinero FROM personas WHERE dinero > 10 ORDER BY fecha LIMIT 10;"));
+        let statement = parser.next_statement().unwrap().unwrap();
+        assert!(matches!(statement, Statement::Select(_)));
+
+        let select_statement = match statement {
+            Statement::Select(s) => s, _ => panic!(),
+        };
+        let expected_column_names = vec!["dinero"];
+        assert!(matches!(select_statement.selection, Selection::Some(expected_column_names)));
+        assert!(matches!(select_statement.limit, Limit::Some(10)));
+        assert_eq!(select_statement.table_name, "personas");
+        assert_eq!(select_statement.where_expr.unwrap(), Expression::Binary(
+            BinaryOperator::Greater,
+            Box::new(Expression::Identifier(None, String::from("dinero"))),
+            Box::new(Expression::Literal(Value::create_i64(10))),
+        ));
+        assert_eq!(select_statement.sort, Some(Sort{
+            columns: vec![(String::from("fecha"), SortOrder::Asc, NullsOrder::Last)]
+        }));
+    }
+
+    #[test]
+    fn select_without_expression_with_limit() {
+        let mut parser = Parser::create(String::from("SELECT nombre, dinero FROM personas LIMIT 10;"));
+        let statement = parser.next_statement().unwrap().unwrap();
+
+        assert!(matches!(statement, Statement::Select(_)));
+        let select_statement = match statement {
+            Statement::Select(s) => s, _ => panic!(),
+        };
+        let expected_column_names = vec!["nombre", "dinero"];
+        assert!(matches!(select_statement.selection, Selection::Some(expected_column_names)));
+        assert!(matches!(select_statement.where_expr, None));
+        assert!(matches!(select_statement.limit, Limit::Some(10)));
+        assert_eq!(select_statement.table_name, "personas");
+    }
+
+    #[test]
+    fn delete_with_expression() {
+        let mut parser = Parser::create(String::from(
+            "DELETE FROM personas WHERE id == 1;"
+        ));
+        let statement = parser.next_statement().unwrap().unwrap();
+
+        assert!(matches!(statement, Statement::Delete(_)));
+        let select_statement = match statement {
+            Statement::Delete(s) => s, _ => panic!(),
+        };
+        assert!(matches!(select_statement.limit, Limit::None));
+        assert_eq!(select_statement.table_name, "personas");
         assert_eq!(select_statement.where_expr.unwrap(), Expression::Binary(
             BinaryOperator::Equal,
-            Box::new(Expression::Identifier(String::from("id"))),
+            Box::new(Expression::Identifier(None, String::from("id"))),
+            Box::new(Expression::Literal(Value::create_i64(1))),
+        ));
+    }
+
+    #[test]
+    fn equality_operators() {
+        let where_expr = |query: &str| match Parser::create(String::from(query)).next_statement().unwrap().unwrap() {
+            Statement::Select(select) => select.where_expr.unwrap(),
+            _ => panic!()
+        };
+        let comparison = |operator: BinaryOperator| Expression::Binary(
+            operator,
+            Box::new(Expression::column("a")),
             Box::new(Expression::Literal(Value::create_i64(1))),
+        );
+
+        assert_eq!(where_expr("SELECT * FROM t WHERE a = 1;"), comparison(BinaryOperator::Equal));
+        assert_eq!(where_expr("SELECT * FROM t WHERE a == 1;"), comparison(BinaryOperator::Equal));
+        assert_eq!(where_expr("SELECT * FROM t WHERE a != 1;"), comparison(BinaryOperator::NotEqual));
+        assert_eq!(where_expr("SELECT * FROM t WHERE a <> 1;"), comparison(BinaryOperator::NotEqual));
+        assert_eq!(where_expr("SELECT * FROM t WHERE a = 1 AND b <> 2;"), Expression::Binary(
+            BinaryOperator::And,
+            Box::new(comparison(BinaryOperator::Equal)),
+            Box::new(Expression::Binary(
+                BinaryOperator::NotEqual,
+                Box::new(Expression::column("b")),
+                Box::new(Expression::Literal(Value::create_i64(2))),
+            )),
+        ));
+    }
+
+    #[test]
+    fn unary_precedence() {
+        let where_expr = |query: &str| match Parser::create(String::from(query)).next_statement().unwrap().unwrap() {
+            Statement::Select(select) => select.where_expr.unwrap(),
+            _ => panic!()
+        };
+        let unary_minus_a = || Box::new(Expression::Unary(UnaryOperator::Minus, Box::new(Expression::column("a"))));
+
+        assert_eq!(where_expr("SELECT * FROM t WHERE -a + b;"), Expression::Binary(
+            BinaryOperator::Add, unary_minus_a(), Box::new(Expression::column("b"))
+        ));
+        assert_eq!(where_expr("SELECT * FROM t WHERE -a * b;"), Expression::Binary(
+            BinaryOperator::Multiply, unary_minus_a(), Box::new(Expression::column("b"))
+        ));
+        assert_eq!(where_expr("SELECT * FROM t WHERE -(a + b);"), Expression::Unary(
+            UnaryOperator::Minus,
+            Box::new(Expression::Binary(BinaryOperator::Add, Box::new(Expression::column("a")), Box::new(Expression::column("b"))))
+        ));
+    }
+
+    #[test]
+    fn select_with_qualified_identifiers() {
+        let mut parser = Parser::create(String::from("SELECT * FROM personas WHERE personas.id == 1 AND nombre == \"Jaime\";"));
+        let select_statement = match parser.next_statement().unwrap().unwrap() {
+            Statement::Select(s) => s, _ => panic!(),
+        };
+
+        assert_eq!(select_statement.where_expr.unwrap(), Expression::Binary(
+            BinaryOperator::And,
+            Box::new(Expression::Binary(
+                BinaryOperator::Equal,
+                Box::new(Expression::Identifier(Some(String::from("personas")), String::from("id"))),
+                Box::new(Expression::Literal(Value::create_i64(1))),
+            )),
+            Box::new(Expression::Binary(
+                BinaryOperator::Equal,
+                Box::new(Expression::Identifier(None, String::from("nombre"))),
+                Box::new(Expression::Literal(Value::create_string(String::from("Jaime")))),
+            )),
+        ));
+    }
+
+    #[test]
+    fn select_with_like() {
+        let mut parser = Parser::create(String::from("SELECT * FROM personas WHERE nombre LIKE \"Jai%\" AND id > 1;"));
+        let statement = parser.next_statement().unwrap().unwrap();
+        let select_statement = match statement {
+            Statement::Select(s) => s, _ => panic!(),
+        };
+
+        assert_eq!(select_statement.where_expr.unwrap(), Expression::Binary(
+            BinaryOperator::And,
+            Box::new(Expression::Binary(
+                BinaryOperator::Like,
+                Box::new(Expression::Identifier(None, String::from("nombre"))),
+                Box::new(Expression::Literal(Value::create_string(String::from("Jai%")))),
+            )),
+            Box::new(Expression::Binary(
+                BinaryOperator::Greater,
+                Box::new(Expression::Identifier(None, String::from("id"))),
+                Box::new(Expression::Literal(Value::create_i64(1))),
+            )),
+        ));
+    }
+
+    #[test]
+    fn select_with_functions() {
+        let mut parser = Parser::create(String::from("SELECT id, upper(nombre), ABS(dinero - 10) FROM personas WHERE LENGTH(nombre) > 3;"));
+        let statement = parser.next_statement().unwrap().unwrap();
+        let select_statement = match statement {
+            Statement::Select(s) => s, _ => panic!(),
+        };
+
+        let expressions = match select_statement.selection {
+            Selection::Expressions(expressions) => expressions, _ => panic!(),
+        };
+        assert_eq!(expressions, vec![
+            (String::from("id"), Expression::Identifier(None, String::from("id"))),
+            (String::from("UPPER(nombre)"), Expression::Function(String::from("UPPER"), vec![
+                Expression::Identifier(None, String::from("nombre"))
+            ])),
+            (String::from("ABS(dinero - 10)"), Expression::Function(String::from("ABS"), vec![
+                Expression::Binary(
+                    BinaryOperator::Subtract,
+                    Box::new(Expression::Identifier(None, String::from("dinero"))),
+                    Box::new(Expression::Literal(Value::create_i64(10))),
+                )
+            ])),
+        ]);
+        assert_eq!(select_statement.where_expr.unwrap(), Expression::Binary(
+            BinaryOperator::Greater,
+            Box::new(Expression::Function(String::from("LENGTH"), vec![
+                Expression::Identifier(None, String::from("nombre"))
+            ])),
+            Box::new(Expression::Literal(Value::create_i64(3))),
+        ));
+
+        let mut parser = Parser::create(String::from("SELECT COUNT(*), LOWER(nombre) FROM personas;"));
+        assert!(parser.next_statement().is_err());
+    }
+
+    #[test]
+    fn select_with_not() {
+        let mut parser = Parser::create(String::from("SELECT * FROM personas WHERE NOT dinero > 10 AND id > 1 OR NOT (id == 2 OR id == 3);"));
+        let statement = parser.next_statement().unwrap().unwrap();
+        let select_statement = match statement {
+            Statement::Select(s) => s, _ => panic!(),
+        };
+
+        assert_eq!(select_statement.where_expr.unwrap(), Expression::Binary(
+            BinaryOperator::Or,
+            Box::new(Expression::Binary(
+                BinaryOperator::And,
+                Box::new(Expression::Unary(UnaryOperator::Not, Box::new(Expression::Binary(
+                    BinaryOperator::Greater,
+                    Box::new(Expression::Identifier(None, String::from("dinero"))),
+                    Box::new(Expression::Literal(Value::create_i64(10))),
+                )))),
+                Box::new(Expression::Binary(
+                    BinaryOperator::Greater,
+                    Box::new(Expression::Identifier(None, String::from("id"))),
+                    Box::new(Expression::Literal(Value::create_i64(1))),
+                )),
+            )),
+            Box::new(Expression::Unary(UnaryOperator::Not, Box::new(Expression::Binary(
+                BinaryOperator::Or,
+                Box::new(Expression::Binary(
+                    BinaryOperator::Equal,
+                    Box::new(Expression::Identifier(None, String::from("id"))),
+                    Box::new(Expression::Literal(Value::create_i64(2))),
+                )),
+                Box::new(Expression::Binary(
+                    BinaryOperator::Equal,
+                    Box::new(Expression::Identifier(None, String::from("id"))),
+                    Box::new(Expression::Literal(Value::create_i64(3))),
+                )),
+            )))),
+        ));
+    }
+
+    #[test]
+    fn select_with_in() {
+        let mut parser = Parser::create(String::from("SELECT * FROM personas WHERE id IN (1, 4, 9) AND ingresos > 1;"));
+        let statement = parser.next_statement().unwrap().unwrap();
+        let select_statement = match statement {
+            Statement::Select(s) => s, _ => panic!(),
+        };
+
+        let id_equal = |value: i64| Box::new(Expression::Binary(
+            BinaryOperator::Equal,
+            Box::new(Expression::Identifier(None, String::from("id"))),
+            Box::new(Expression::Literal(Value::create_i64(value))),
+        ));
+        assert_eq!(select_statement.where_expr.unwrap(), Expression::Binary(
+            BinaryOperator::And,
+            Box::new(Expression::Binary(
+                BinaryOperator::Or,
+                id_equal(1),
+                Box::new(Expression::Binary(BinaryOperator::Or, id_equal(4), id_equal(9))),
+            )),
+            Box::new(Expression::Binary(
+                BinaryOperator::Greater,
+                Box::new(Expression::Identifier(None, String::from("ingresos"))),
+                Box::new(Expression::Literal(Value::create_i64(1))),
+            )),
+        ));
+
+        let mut parser = Parser::create(String::from("SELECT * FROM personas WHERE id IN ();"));
+        assert!(matches!(parser.next_statement(), Err(SimpleDbError::MalformedQuery(_))));
+    }
+
+    #[test]
+    fn select_with_aliases() {
+        let mut parser = Parser::create(String::from("SELECT nombre AS name, COUNT(*) AS total, dinero FROM personas GROUP BY nombre, dinero;"));
+        let select_statement = match parser.next_statement().unwrap().unwrap() {
+            Statement::Select(s) => s, _ => panic!(),
+        };
+
+        //The aggregate goes after the selected columns
+        assert_eq!(select_statement.aliases.len(), 2);
+        assert_eq!(select_statement.aliases.get(&0).unwrap(), "name");
+        assert_eq!(select_statement.aliases.get(&2).unwrap(), "total");
+        match select_statement.selection {
+            Selection::Aggregate(columns, aggregates) => {
+                assert_eq!(columns, vec![String::from("nombre"), String::from("dinero")]);
+                assert_eq!(aggregates.len(), 1);
+            },
+            _ => panic!()
+        }
+
+        let mut parser = Parser::create(String::from("SELECT id, dinero AS money FROM personas ORDER BY dinero ASC;"));
+        let select_statement = match parser.next_statement().unwrap().unwrap() {
+            Statement::Select(s) => s, _ => panic!(),
+        };
+        assert_eq!(select_statement.aliases.get(&1).unwrap(), "money");
+        assert!(matches!(select_statement.selection, Selection::Some(_)));
+
+        let mut parser = Parser::create(String::from("SELECT dinero AS a, ABS(dinero) AS b, dinero AS c FROM personas;"));
+        let select_statement = match parser.next_statement().unwrap().unwrap() {
+            Statement::Select(s) => s, _ => panic!(),
+        };
+        assert_eq!(select_statement.aliases.get(&0).unwrap(), "a");
+        assert_eq!(select_statement.aliases.get(&1).unwrap(), "b");
+        assert_eq!(select_statement.aliases.get(&2).unwrap(), "c");
+    }
+
+    #[test]
+    fn select_with_all_any() {
+        let dinero_greater = |value: i64| Box::new(Expression::Binary(
+            BinaryOperator::Greater,
+            Box::new(Expression::Identifier(None, String::from("dinero"))),
+            Box::new(Expression::Literal(Value::create_i64(value))),
+        ));
+
+        let mut parser = Parser::create(String::from("SELECT * FROM personas WHERE dinero > ALL (10, 20);"));
+        let select_statement = match parser.next_statement().unwrap().unwrap() {
+            Statement::Select(s) => s, _ => panic!(),
+        };
+        assert_eq!(select_statement.where_expr.unwrap(), Expression::Binary(
+            BinaryOperator::And, dinero_greater(10), dinero_greater(20)
+        ));
+
+        let mut parser = Parser::create(String::from("SELECT * FROM personas WHERE dinero > ANY (10, 20);"));
+        let select_statement = match parser.next_statement().unwrap().unwrap() {
+            Statement::Select(s) => s, _ => panic!(),
+        };
+        assert_eq!(select_statement.where_expr.unwrap(), Expression::Binary(
+            BinaryOperator::Or, dinero_greater(10), dinero_greater(20)
+        ));
+
+        let mut parser = Parser::create(String::from("SELECT * FROM personas WHERE dinero > ALL ();"));
+        assert!(matches!(parser.next_statement(), Err(SimpleDbError::MalformedQuery(_))));
+    }
+
+    #[test]
+    fn select_with_is_null() {
+        let mut parser = Parser::create(String::from("SELECT * FROM personas WHERE nombre IS NULL OR dinero IS NOT NULL;"));
+        let statement = parser.next_statement().unwrap().unwrap();
+        let select_statement = match statement {
+            Statement::Select(s) => s, _ => panic!(),
+        };
+
+        assert_eq!(select_statement.where_expr.unwrap(), Expression::Binary(
+            BinaryOperator::Or,
+            Box::new(Expression::Unary(UnaryOperator::IsNull, Box::new(Expression::Identifier(None, String::from("nombre"))))),
+            Box::new(Expression::Unary(UnaryOperator::IsNotNull, Box::new(Expression::Identifier(None, String::from("dinero"))))),
         ));
     }
 
@@ -640,11 +1639,70 @@ mod test {
         };
 
         assert_eq!(select_statement.sort, Some(Sort{
-            column_name: String::from("dinero"),
-            order: SortOrder::Desc
+            columns: vec![(String::from("dinero"), SortOrder::Desc, NullsOrder::First)]
+        }));
+    }
+
+    #[test]
+    fn select_with_multiple_sort_columns() {
+        let mut parser = Parser::create(String::from(
+            "SELECT * FROM personas ORDER BY nombre, dinero DESC, id ASC LIMIT 2;"
+        ));
+        let statement = parser.next_statement().unwrap().unwrap();
+        let select_statement = match statement {
+            Statement::Select(s) => s, _ => panic!(),
+        };
+
+        assert_eq!(select_statement.sort, Some(Sort{
+            columns: vec![
+                (String::from("nombre"), SortOrder::Asc, NullsOrder::Last),
+                (String::from("dinero"), SortOrder::Desc, NullsOrder::First),
+                (String::from("id"), SortOrder::Asc, NullsOrder::Last),
+            ]
+        }));
+        assert!(matches!(select_statement.limit, Limit::Some(2)));
+    }
+
+    #[test]
+    fn select_with_nulls_order() {
+        let mut parser = Parser::create(String::from(
+            "SELECT * FROM personas ORDER BY nombre ASC NULLS FIRST, dinero DESC nulls last;"
+        ));
+        let statement = parser.next_statement().unwrap().unwrap();
+        let select_statement = match statement {
+            Statement::Select(s) => s, _ => panic!(),
+        };
+
+        assert_eq!(select_statement.sort, Some(Sort{
+            columns: vec![
+                (String::from("nombre"), SortOrder::Asc, NullsOrder::First),
+                (String::from("dinero"), SortOrder::Desc, NullsOrder::Last),
+            ]
         }));
     }
 
+    #[test]
+    fn select_with_limit_offset() {
+        let mut parser = Parser::create(String::from(
+            "SELECT * FROM personas ORDER BY dinero LIMIT 10 OFFSET 20;"
+        ));
+        let select_statement = match parser.next_statement().unwrap().unwrap() {
+            Statement::Select(s) => s, _ => panic!(),
+        };
+        assert!(matches!(select_statement.limit, Limit::Some(10)));
+        assert_eq!(select_statement.offset, 20);
+
+        let mut parser = Parser::create(String::from(
+            "SELECT * FROM personas LIMIT 10 WHERE offsets > 1;"
+        ));
+        let select_statement = match parser.next_statement().unwrap().unwrap() {
+            Statement::Select(s) => s, _ => panic!(),
+        };
+        assert!(matches!(select_statement.limit, Limit::Some(10)));
+        assert_eq!(select_statement.offset, 0);
+        assert!(select_statement.where_expr.is_some());
+    }
+
     #[test]
     fn select_with_expression_without_limit() {
         let mut parser = Parser::create(String::from(
@@ -666,7 +1724,7 @@ mod test {
             BinaryOperator::And,
             Box::new(Expression::Binary(
                 BinaryOperator::GreaterEqual,
-                Box::new(Expression::Identifier(String::from("dinero"))),
+                Box::new(Expression::Identifier(None, String::from("dinero"))),
                 Box::new(Expression::Binary(
                     BinaryOperator::Add,
                     Box::new(Expression::Literal(Value::create_i64(1))),
@@ -675,19 +1733,63 @@ mod test {
             ),
             Box::new(Expression::Binary(
                 BinaryOperator::Equal,
-                Box::new(Expression::Identifier(String::from("nombre"))),
+                Box::new(Expression::Identifier(None, String::from("nombre"))),
                 Box::new(Expression:: Literal(Value::create_string(String::from("Jaime")))),
             ))
         ));
     }
 
+    #[test]
+    fn select_with_modulo() {
+        let mut parser = Parser::create(String::from(
+            "SELECT * FROM personas WHERE id % 2 == 0 + 1 * 3 % 2;"
+        ));
+        let select_statement = match parser.next_statement().unwrap().unwrap() {
+            Statement::Select(s) => s, _ => panic!(),
+        };
+
+        //Modulo has the same precedence as multiply, so it is evaluated from left to right
+        assert_eq!(select_statement.where_expr.unwrap(), Expression::Binary(
+            BinaryOperator::Equal,
+            Box::new(Expression::Binary(
+                BinaryOperator::Modulo,
+                Box::new(Expression::Identifier(None, String::from("id"))),
+                Box::new(Expression::Literal(Value::create_i64(2))),
+            )),
+            Box::new(Expression::Binary(
+                BinaryOperator::Add,
+                Box::new(Expression::Literal(Value::create_i64(0))),
+                Box::new(Expression::Binary(
+                    BinaryOperator::Modulo,
+                    Box::new(Expression::Binary(
+                        BinaryOperator::Multiply,
+                        Box::new(Expression::Literal(Value::create_i64(1))),
+                        Box::new(Expression::Literal(Value::create_i64(3))),
+                    )),
+                    Box::new(Expression::Literal(Value::create_i64(2))),
+                )),
+            ))
+        ));
+    }
+
     #[test]
     fn start_transaction() {
         let mut parser = Parser::create(String::from(
             "START_TRANSACTION;"
         ));
         let statement = parser.next_statement().unwrap().unwrap();
-        assert!(matches!(statement, Statement::StartTransaction));
+        assert!(matches!(statement, Statement::StartTransaction(IsolationLevel::SnapshotIsolation)));
+
+        let mut parser = Parser::create(String::from("START_TRANSACTION ISOLATION LEVEL read uncommitted;"));
+        let statement = parser.next_statement().unwrap().unwrap();
+        assert!(matches!(statement, Statement::StartTransaction(IsolationLevel::ReadUncommited)));
+
+        let mut parser = Parser::create(String::from("START_TRANSACTION ISOLATION LEVEL SNAPSHOT;"));
+        let statement = parser.next_statement().unwrap().unwrap();
+        assert!(matches!(statement, Statement::StartTransaction(IsolationLevel::SnapshotIsolation)));
+
+        let mut parser = Parser::create(String::from("START_TRANSACTION ISOLATION LEVEL SERIALIZABLE;"));
+        assert!(matches!(parser.next_statement(), Err(SimpleDbError::MalformedQuery(_))));
     }
 
     #[test]
@@ -699,6 +1801,18 @@ mod test {
         assert!(matches!(statement, Statement::Rollback));
     }
 
+    #[test]
+    fn savepoints() {
+        let statement = Parser::create(String::from("SAVEPOINT a;")).next_statement().unwrap().unwrap();
+        assert!(matches!(statement, Statement::Savepoint(name) if name == "a"));
+        let statement = Parser::create(String::from("ROLLBACK TO SAVEPOINT a;")).next_statement().unwrap().unwrap();
+        assert!(matches!(statement, Statement::RollbackToSavepoint(name) if name == "a"));
+        let statement = Parser::create(String::from("ROLLBACK TO a;")).next_statement().unwrap().unwrap();
+        assert!(matches!(statement, Statement::RollbackToSavepoint(name) if name == "a"));
+        let statement = Parser::create(String::from("RELEASE SAVEPOINT a;")).next_statement().unwrap().unwrap();
+        assert!(matches!(statement, Statement::ReleaseSavepoint(name) if name == "a"));
+    }
+
     #[test]
     fn commit() {
         let mut parser = Parser::create(String::from(
@@ -719,13 +1833,54 @@ mod test {
         match statement {
             Statement::Insert(insert_statement) => {
                 assert_eq!(insert_statement.table_name, String::from("personas"));
-                assert_eq!(insert_statement.values.len(), 3);
-                assert_eq!(insert_statement.values[2], (String::from("id"), Value::create_i64(1)));
-                assert_eq!(insert_statement.values[1], (String::from("nombre"), Value::create_string(String::from("Jaime"))));
-                assert_eq!(insert_statement.values[0], (String::from("dinero"), Value::create_f64(10.2)));
+                assert_eq!(insert_statement.values.len(), 1);
+                assert_eq!(insert_statement.values[0].len(), 3);
+                assert_eq!(insert_statement.values[0][0], (String::from("id"), Value::create_i64(1)));
+                assert_eq!(insert_statement.values[0][1], (String::from("nombre"), Value::create_string(String::from("Jaime"))));
+                assert_eq!(insert_statement.values[0][2], (String::from("dinero"), Value::create_f64(10.2)));
+            }
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn insert_multiple_rows() {
+        let mut parser = Parser::create(String::from(
+            "INSERT INTO personas (id, nombre) VALUES (1, \"Jaime\"), (2, \"Pedro\"), (3, \"Juan\");"
+        ));
+        let statement = parser.next_statement().unwrap().unwrap();
+
+        match statement {
+            Statement::Insert(insert_statement) => {
+                assert_eq!(insert_statement.values, vec![
+                    vec![(String::from("id"), Value::create_i64(1)), (String::from("nombre"), Value::create_string(String::from("Jaime")))],
+                    vec![(String::from("id"), Value::create_i64(2)), (String::from("nombre"), Value::create_string(String::from("Pedro")))],
+                    vec![(String::from("id"), Value::create_i64(3)), (String::from("nombre"), Value::create_string(String::from("Juan")))],
+                ]);
             }
             _ => panic!()
         }
+
+        let mut parser = Parser::create(String::from("INSERT INTO personas (id, nombre) VALUES (1, \"Jaime\"), (2);"));
+        match parser.next_statement() {
+            Err(SimpleDbError::MalformedQuery(message)) => assert_eq!(message, "Column nombre at position 2 has no value"),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn insert_arity_mismatch() {
+        let mut parser = Parser::create(String::from("INSERT INTO personas (id, nombre, dinero) VALUES (1, \"Jaime\");"));
+        match parser.next_statement() {
+            Err(SimpleDbError::MalformedQuery(message)) => assert_eq!(message, "Column dinero at position 3 has no value"),
+            _ => panic!()
+        }
+
+        let mut parser = Parser::create(String::from("INSERT INTO personas (id) VALUES (1, \"Jaime\");"));
+        match parser.next_statement() {
+            Err(SimpleDbError::MalformedQuery(message)) => assert_eq!(message, "Value at position 2 has no column"),
+            _ => panic!()
+        }
     }
 
     #[test]
@@ -753,6 +1908,77 @@ mod test {
         assert!(matches!(statement, Statement::ShowDatabases));
     }
 
+    #[test]
+    fn show_transaction_status() {
+        let mut parser = Parser::create(String::from("SHOW TRANSACTION status;"));
+        let statement = parser.next_statement().unwrap().unwrap();
+        assert!(matches!(statement, Statement::ShowTransactionStatus));
+
+        let mut parser = Parser::create(String::from("SHOW TRANSACTION;"));
+        assert!(matches!(parser.next_statement().unwrap().unwrap(), Statement::ShowTransaction));
+
+        let mut parser = Parser::create(String::from("SHOW TRANSACTIONS;"));
+        assert!(matches!(parser.next_statement().unwrap().unwrap(), Statement::ShowTransactions));
+
+        let mut parser = Parser::create(String::from("SHOW TRANSACTION otro;"));
+        assert!(parser.next_statement().is_err());
+    }
+
+    #[test]
+    fn insert_on_conflict_do_nothing() {
+        let mut parser = Parser::create(String::from("INSERT INTO personas (id) VALUES (1) ON CONFLICT DO NOTHING;"));
+        match parser.next_statement().unwrap().unwrap() {
+            Statement::Insert(insert) => assert_eq!(insert.on_conflict, OnConflict::DoNothing),
+            _ => panic!()
+        }
+
+        let mut parser = Parser::create(String::from("INSERT INTO personas (id) VALUES (1);"));
+        match parser.next_statement().unwrap().unwrap() {
+            Statement::Insert(insert) => assert_eq!(insert.on_conflict, OnConflict::Fail),
+            _ => panic!()
+        }
+
+        let mut parser = Parser::create(String::from("INSERT OR REPLACE INTO personas (id) VALUES (1);"));
+        match parser.next_statement().unwrap().unwrap() {
+            Statement::Insert(insert) => assert_eq!(insert.on_conflict, OnConflict::Replace),
+            _ => panic!()
+        }
+
+        let mut parser = Parser::create(String::from("INSERT OR IGNORE INTO personas (id) VALUES (1);"));
+        match parser.next_statement().unwrap().unwrap() {
+            Statement::Insert(insert) => assert_eq!(insert.on_conflict, OnConflict::DoNothing),
+            _ => panic!()
+        }
+
+        let mut parser = Parser::create(String::from("INSERT INTO personas (id, replace, ignore) VALUES (1, 2, 3);"));
+        match parser.next_statement().unwrap().unwrap() {
+            Statement::Insert(insert) => assert_eq!(insert.on_conflict, OnConflict::Fail),
+            _ => panic!()
+        }
+
+        let mut parser = Parser::create(String::from("INSERT INTO personas (id) VALUES (1) ON CONFLICT;"));
+        assert!(parser.next_statement().is_err());
+    }
+
+    #[test]
+    fn parameters() {
+        let mut parser = Parser::create(String::from("SELECT * FROM personas WHERE id == ? AND dinero > ?;"));
+        let statement = parser.next_statement().unwrap().unwrap();
+        assert_eq!(statement.get_n_parameters(), 2);
+
+        let mut parser = Parser::create(String::from("INSERT INTO personas (id, nombre) VALUES (?, \"Jaime\");"));
+        let statement = parser.next_statement().unwrap().unwrap();
+        assert_eq!(statement.get_n_parameters(), 1);
+        assert!(statement.bind(&vec![]).is_err());
+        match statement.bind(&vec![Value::create_i64(1)]).unwrap() {
+            Statement::Insert(insert) => assert_eq!(insert.values, vec![vec![
+                (String::from("id"), Value::create_i64(1)),
+                (String::from("nombre"), Value::create_string(String::from("Jaime"))),
+            ]]),
+            _ => panic!()
+        }
+    }
+
     #[test]
     fn create_table() {
         let mut parser = Parser::create(String::from(
@@ -769,9 +1995,113 @@ mod test {
             Statement::CreateTable(createStatement) => {
                 assert_eq!(createStatement.table_name, String::from("personas"));
                 assert_eq!(createStatement.columns.len(), 3);
-                assert_eq!(createStatement.columns[0], (String::from("id"), Type::I64, true));
-                assert_eq!(createStatement.columns[1], (String::from("nombre"), Type::String, false));
-                assert_eq!(createStatement.columns[2], (String::from("dinero"), Type::F64, false));
+                assert_eq!(createStatement.columns[0], ColumnDefinition::create("id", Type::I64, true));
+                assert_eq!(createStatement.columns[1], ColumnDefinition::create("nombre", Type::String, false));
+                assert_eq!(createStatement.columns[2], ColumnDefinition::create("dinero", Type::F64, false));
+                assert_eq!(createStatement.ttl_seconds, None);
+            },
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn create_table_with_ttl() {
+        let mut parser = Parser::create(String::from("CREATE TABLE sesiones (id i64 PRIMARY KEY) WITH TTL 3600;"));
+        match parser.next_statement().unwrap().unwrap() {
+            Statement::CreateTable(statement) => assert_eq!(statement.ttl_seconds, Some(3600)),
+            _ => panic!()
+        }
+
+        let mut parser = Parser::create(String::from("CREATE TABLE sesiones (id i64 PRIMARY KEY) WITH TTL 0;"));
+        assert!(parser.next_statement().is_err());
+    }
+
+    #[test]
+    fn create_table_with_constraints() {
+        let mut parser = Parser::create(String::from("CREATE TABLE pedidos (id i64 PRIMARY KEY, status VARCHAR NOT NULL DEFAULT \"new\", n i64, CHECK (n > 0));"));
+        match parser.next_statement().unwrap().unwrap() {
+            Statement::CreateTable(statement) => {
+                assert_eq!(statement.columns[0], ColumnDefinition::create("id", Type::I64, true));
+                assert_eq!(statement.columns[1], ColumnDefinition {
+                    default_value: Some(Value::create_string(String::from("new"))),
+                    is_not_null: true,
+                    ..ColumnDefinition::create("status", Type::String, false)
+                });
+                assert_eq!(statement.columns[2], ColumnDefinition::create("n", Type::I64, false));
+                assert_eq!(statement.checks, vec![Expression::Binary(
+                    BinaryOperator::Greater,
+                    Box::new(Expression::Identifier(None, String::from("n"))),
+                    Box::new(Expression::Literal(Value::create_i64(0)))
+                )]);
+            },
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn create_table_default_null() {
+        let mut parser = Parser::create(String::from("CREATE TABLE pedidos (id i64 PRIMARY KEY, status VARCHAR DEFAULT NULL);"));
+        match parser.next_statement().unwrap().unwrap() {
+            Statement::CreateTable(statement) => {
+                assert_eq!(statement.columns[1], ColumnDefinition::create("status", Type::String, false));
+            },
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn vacuum() {
+        let mut parser = Parser::create(String::from("VACUUM TABLE personas; VACUUM;"));
+        assert!(matches!(parser.next_statement().unwrap().unwrap(), Statement::Vacuum(Some(table_name)) if table_name == "personas"));
+        assert!(matches!(parser.next_statement().unwrap().unwrap(), Statement::Vacuum(None)));
+    }
+
+    #[test]
+    fn check_database() {
+        let mut parser = Parser::create(String::from("CHECK DATABASE;"));
+        assert!(matches!(parser.next_statement().unwrap().unwrap(), Statement::CheckDatabase));
+    }
+
+    #[test]
+    fn backup() {
+        let mut parser = Parser::create(String::from("BACKUP TO \"/tmp/backup\";"));
+        match parser.next_statement().unwrap().unwrap() {
+            Statement::Backup(path) => assert_eq!(path, String::from("/tmp/backup")),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn create_user() {
+        let mut parser = Parser::create(String::from("CREATE USER jaime PASSWORD \"secreto\";"));
+        match parser.next_statement().unwrap().unwrap() {
+            Statement::CreateUser(user_name, password) => {
+                assert_eq!(user_name, "jaime");
+                assert_eq!(password, "secreto");
+            },
+            _ => panic!()
+        }
+
+        let mut parser = Parser::create(String::from("CREATE USER jaime;"));
+        assert!(parser.next_statement().is_err());
+    }
+
+    #[test]
+    fn use_database() {
+        let mut parser = Parser::create(String::from("USE tienda;"));
+        match parser.next_statement().unwrap().unwrap() {
+            Statement::Use(database_name) => assert_eq!(database_name, "tienda"),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn grant() {
+        let mut parser = Parser::create(String::from("GRANT tienda TO jaime;"));
+        match parser.next_statement().unwrap().unwrap() {
+            Statement::Grant(database_name, user_name) => {
+                assert_eq!(database_name, "tienda");
+                assert_eq!(user_name, "jaime");
             },
             _ => panic!()
         }
@@ -790,6 +2120,89 @@ mod test {
         }
     }
 
+    #[test]
+    fn show_stats() {
+        let mut parser = Parser::create(String::from(
+            "SHOW STATS FROM personas;"
+        ));
+        let statement = parser.next_statement().unwrap().unwrap();
+
+        match statement {
+            Statement::ShowStats(table_name) => assert_eq!(table_name, String::from("personas")),
+            _ => panic!(""),
+        }
+
+        //STATS is not reserved
+        let mut parser = Parser::create(String::from("SHOW STATS FROM stats;"));
+        assert!(matches!(parser.next_statement().unwrap().unwrap(), Statement::ShowStats(table_name) if table_name == "stats"));
+        let mut parser = Parser::create(String::from("SELECT stats FROM stats;"));
+        assert!(matches!(parser.next_statement().unwrap().unwrap(), Statement::Select(select) if select.table_name == "stats"));
+    }
+
+    #[test]
+    fn drop_table() {
+        let mut parser = Parser::create(String::from("DROP TABLE personas;"));
+        let statement = parser.next_statement().unwrap().unwrap();
+
+        match statement {
+            Statement::DropTable(table_name) => assert_eq!(table_name, "personas"),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn truncate_table() {
+        let mut parser = Parser::create(String::from("TRUNCATE TABLE personas;"));
+        let statement = parser.next_statement().unwrap().unwrap();
+
+        match statement {
+            Statement::TruncateTable(table_name) => assert_eq!(table_name, "personas"),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn alter_table_drop_column() {
+        let mut parser = Parser::create(String::from("ALTER TABLE personas DROP COLUMN nombre;"));
+        let statement = parser.next_statement().unwrap().unwrap();
+
+        match statement {
+            Statement::AlterTable(statement) => {
+                assert_eq!(statement.table_name, "personas");
+                assert_eq!(statement.operation, AlterTableOperation::DropColumn(String::from("nombre")));
+            },
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn alter_table_rename_column() {
+        let mut parser = Parser::create(String::from("ALTER TABLE personas RENAME COLUMN nombre TO apellido;"));
+        let statement = parser.next_statement().unwrap().unwrap();
+
+        match statement {
+            Statement::AlterTable(statement) => {
+                assert_eq!(statement.table_name, "personas");
+                assert_eq!(statement.operation, AlterTableOperation::RenameColumn(String::from("nombre"), String::from("apellido")));
+            },
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn drop_index() {
+        let mut parser = Parser::create(String::from("DROP INDEX dinero ON personas;"));
+        let statement = parser.next_statement().unwrap().unwrap();
+
+        match statement {
+            Statement::DropIndex(statement) => {
+                assert_eq!(statement.column_name, "dinero");
+                assert_eq!(statement.table_name, "personas");
+            },
+            _ => panic!()
+        }
+    }
+
     #[test]
     fn create_index() {
         let mut parser = Parser::create(String::from(
@@ -800,8 +2213,42 @@ mod test {
         match statement {
             Statement::CreateIndex(statement) => {
                 assert_eq!(statement.table_name, String::from("personas"));
-                assert_eq!(statement.column_name, String::from("dinero"));
+                assert_eq!(statement.column_names, vec![String::from("dinero")]);
                 assert_eq!(statement.wait, false);
+                assert_eq!(statement.unique, false);
+            }
+            _ => panic!("")
+        }
+    }
+
+    #[test]
+    fn create_unique_index() {
+        let mut parser = Parser::create(String::from(
+            "CREATE UNIQUE INDEX ON personas (email);"
+        ));
+        let statement = parser.next_statement().unwrap().unwrap();
+
+        match statement {
+            Statement::CreateIndex(statement) => {
+                assert_eq!(statement.table_name, String::from("personas"));
+                assert_eq!(statement.column_names, vec![String::from("email")]);
+                assert_eq!(statement.unique, true);
+            }
+            _ => panic!("")
+        }
+    }
+
+    #[test]
+    fn create_composite_index() {
+        let mut parser = Parser::create(String::from(
+            "CREATE INDEX ON personas (apellido, nombre);"
+        ));
+        let statement = parser.next_statement().unwrap().unwrap();
+
+        match statement {
+            Statement::CreateIndex(statement) => {
+                assert_eq!(statement.column_names, vec![String::from("apellido"), String::from("nombre")]);
+                assert_eq!(statement.wait, true);
             }
             _ => panic!("")
         }
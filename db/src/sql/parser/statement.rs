@@ -1,9 +1,12 @@
 use std::cmp::Ordering;
-use shared::{Type, Value};
-use crate::{Row, Schema};
+use std::collections::HashMap;
+use shared::{SimpleDbError, Value};
+use crate::{ColumnDefinition, Row, Schema};
 use crate::table::selection::Selection;
 use crate::sql::parser::expression::Expression;
+use storage::transactions::transaction_manager::IsolationLevel;
 
+#[derive(Clone)]
 pub enum Statement {
     Select(SelectStatement),
     Update(UpdateStatement),
@@ -12,55 +15,122 @@ pub enum Statement {
     CreateTable(CreateTableStatement),
     CreateIndex(CreateIndexStatement),
     CreateDatabase(String),
+    DropTable(String), //Table name
+    DropIndex(DropIndexStatement),
+    TruncateTable(String), //Table name
+    Vacuum(Option<String>), //Table name, None to vacuum all the tables of the database
+    CheckDatabase,
+    AlterTable(AlterTableStatement),
     Describe(String),
-    StartTransaction,
+    StartTransaction(IsolationLevel),
     Rollback,
     Commit,
+    Savepoint(String), //Savepoint name
+    RollbackToSavepoint(String), //Savepoint name
+    ReleaseSavepoint(String), //Savepoint name
     ShowIndexes(String), //Table name
+    ShowStats(String), //Table name
     ShowDatabases,
     ShowTables,
+    ShowTransactionStatus,
+    ShowTransaction, //Id, isolation level and state of the current transaction
+    ShowTransactions, //Active transactions of the database
+    Backup(String), //Snapshot path
+    CreateUser(String, String), //User name, password
+    Grant(String, String), //Database name, user name
+    Use(String), //Database name
 }
 
+#[derive(Clone)]
 pub struct SelectStatement {
     pub(crate) explain: bool,
+    pub(crate) analyze: bool, //EXPLAIN ANALYZE, the query is executed to measure each plan step
     pub(crate) selection: Selection,
+    pub(crate) distinct: bool, //SELECT DISTINCT, rows with the same selected values are returned once
+    pub(crate) aliases: HashMap<usize, String>, //Position in the selected columns -> Alias
     pub(crate) table_name: String,
     pub(crate) where_expr: Option<Expression>,
+    pub(crate) group_by: Option<Vec<String>>,
     pub(crate) sort: Option<Sort>,
     pub(crate) limit: Limit,
+    pub(crate) offset: i64, //Nº of rows to skip before applying the limit. Negative values are rejected by the validator
+    pub(crate) index_hint: Option<IndexHint>,
 }
 
+#[derive(Clone)]
 pub struct UpdateStatement {
     pub(crate) table_name: String,
     pub(crate) updated_values: Vec<(String, Expression)>,
     pub(crate) where_expr: Option<Expression>
 }
 
+#[derive(Clone)]
 pub struct DeleteStatement {
     pub(crate) table_name: String,
     pub(crate) where_expr: Option<Expression>,
     pub(crate) limit: Limit
 }
 
+#[derive(Clone)]
 pub struct InsertStatement {
     pub(crate) table_name: String,
-    //Column name, Value, Value type
-    pub(crate) values: Vec<(String, Value)>,
+    //One entry per VALUES tuple. Column name, Value. In the same order as the columns were declared
+    pub(crate) values: Vec<Vec<(String, Value)>>,
+    //One entry per VALUES tuple. Declared position, column name, parameter index. Moved to values by Statement::bind()
+    pub(crate) parameters: Vec<Vec<(usize, String, usize)>>,
+    pub(crate) on_conflict: OnConflict,
+}
+
+//What to do when the inserted primary key already exists
+#[derive(Clone, Debug, PartialEq)]
+pub enum OnConflict {
+    Fail, //Plain INSERT. PrimaryKeyAlreadyExists will be returned
+    Replace, //INSERT OR REPLACE. The row will be overwritten
+    DoNothing, //INSERT OR IGNORE or ON CONFLICT DO NOTHING. The row will be skipped
 }
 
+#[derive(Clone)]
 pub struct CreateIndexStatement {
     pub(crate) table_name: String,
-    pub(crate) column_name: String,
+    pub(crate) column_names: Vec<String>, //More than one column will create a composite index
     pub(crate) wait: bool,
+    pub(crate) unique: bool, //Rows won't be allowed to have the same indexed value
 }
 
-pub struct CreateTableStatement {
+#[derive(Clone)]
+pub struct DropIndexStatement {
     pub(crate) table_name: String,
-    //Column name, Column type, is primary
-    pub(crate) columns: Vec<(String, Type, bool)>
+    pub(crate) column_name: String,
 }
 
 #[derive(Clone)]
+pub struct AlterTableStatement {
+    pub(crate) table_name: String,
+    pub(crate) operation: AlterTableOperation,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AlterTableOperation {
+    DropColumn(String), //Column name
+    RenameColumn(String, String), //Old column name, new column name
+}
+
+#[derive(Clone)]
+pub struct CreateTableStatement {
+    pub(crate) table_name: String,
+    pub(crate) columns: Vec<ColumnDefinition>,
+    pub(crate) checks: Vec<Expression>, //CHECK (<expression>) constraints
+    pub(crate) ttl_seconds: Option<u64>, //Set by WITH TTL <seconds>
+}
+
+//Query hint written after the table name: SELECT * FROM personas /*+ INDEX(dinero) */ or /*+ INDEX(nombre, dinero) */
+#[derive(Clone, Debug, PartialEq)]
+pub enum IndexHint {
+    Index(Vec<String>), //Columns of the index that must be used, in the order of the index
+    NoIndex, //Forces a full scan
+}
+
+#[derive(Clone, Debug)]
 pub enum Limit {
     None,
     Some(usize)
@@ -68,8 +138,7 @@ pub enum Limit {
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Sort {
-    pub column_name: String,
-    pub order: SortOrder
+    pub columns: Vec<(String, SortOrder, NullsOrder)> //Rows will be compared by each column in order until they are not equal
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -77,20 +146,66 @@ pub enum SortOrder {
     Asc, Desc
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum NullsOrder {
+    First, Last
+}
+
+impl SortOrder {
+    //Used when NULLS FIRST or NULLS LAST is not specified
+    pub fn default_nulls_order(&self) -> NullsOrder {
+        match self {
+            SortOrder::Asc => NullsOrder::Last,
+            SortOrder::Desc => NullsOrder::First,
+        }
+    }
+}
+
 impl Sort {
     pub fn is_indexed(&self, schema: &Schema) -> bool {
-        let column = schema.get_column(&self.column_name).unwrap();
+        let column = schema.get_column(self.get_first_column_name()).unwrap();
         column.is_secondary_indexed() || column.is_primary
     }
 
+    pub fn get_first_column_name(&self) -> &String {
+        &self.columns[0].0
+    }
+
+    pub fn get_column_names(&self) -> Vec<String> {
+        self.columns.iter()
+            .map(|(column_name, _, _)| column_name.clone())
+            .collect()
+    }
+
+    //Returns true if the rows are only sorted by column_name
+    pub fn is_only_by(&self, column_name: &str) -> bool {
+        self.columns.len() == 1 && self.get_first_column_name().eq(column_name)
+    }
+
     pub fn compare(&self, a: &Row, b: &Row) -> Ordering {
-        let value_a = a.get_column_value(&self.column_name).unwrap();
-        let value_b = b.get_column_value(&self.column_name).unwrap();
+        for (column_name, order, nulls_order) in &self.columns {
+            let value_a = a.get_column_value(column_name).unwrap();
+            let value_b = b.get_column_value(column_name).unwrap();
+
+            //Nulls are placed at the requested end regardless of the sort order
+            let ordering = match (value_a.is_null(), value_b.is_null(), nulls_order) {
+                (true, true, _) => Ordering::Equal,
+                (true, false, NullsOrder::First) |
+                (false, true, NullsOrder::Last) => Ordering::Less,
+                (true, false, NullsOrder::Last) |
+                (false, true, NullsOrder::First) => Ordering::Greater,
+                (false, false, _) => match order {
+                    SortOrder::Desc => value_b.cmp(&value_a),
+                    SortOrder::Asc => value_a.cmp(&value_b)
+                }
+            };
 
-        match self.order {
-            SortOrder::Desc => value_b.cmp(&value_a),
-            SortOrder::Asc => value_a.cmp(&value_b)
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
         }
+
+        Ordering::Equal
     }
 }
 
@@ -163,6 +278,67 @@ impl Statement {
         self.get_descriptor().requires_database()
     }
 
+    //Returns a copy of the statement with its ? parameters replaced by values, so the statement can be reused.
+    //Parameters are numbered by their position in the statement
+    pub fn bind(&self, values: &Vec<Value>) -> Result<Statement, SimpleDbError> {
+        let n_parameters = self.get_n_parameters();
+        if n_parameters != values.len() {
+            return Err(SimpleDbError::MalformedQuery(format!(
+                "Expected {} parameters to be bound but {} were given", n_parameters, values.len()
+            )));
+        }
+
+        match self.clone() {
+            Statement::Select(mut select) => {
+                select.where_expr = bind_optional(select.where_expr, values)?;
+                Ok(Statement::Select(select))
+            },
+            Statement::Update(mut update) => {
+                let mut updated_values = Vec::with_capacity(update.updated_values.len());
+                for (column_name, expression) in update.updated_values {
+                    updated_values.push((column_name, expression.bind(values)?));
+                }
+                update.updated_values = updated_values;
+                update.where_expr = bind_optional(update.where_expr, values)?;
+                Ok(Statement::Update(update))
+            },
+            Statement::Delete(mut delete) => {
+                delete.where_expr = bind_optional(delete.where_expr, values)?;
+                Ok(Statement::Delete(delete))
+            },
+            Statement::Insert(mut insert) => {
+                //Parameters are sorted by its position, so the previous positions are already in values
+                for (row_values, row_parameters) in insert.values.iter_mut().zip(insert.parameters.iter_mut()) {
+                    for (position, column_name, index) in row_parameters.drain(..) {
+                        row_values.insert(position, (column_name, values[index].clone()));
+                    }
+                }
+                Ok(Statement::Insert(insert))
+            },
+            other => Ok(other),
+        }
+    }
+
+    pub fn get_n_parameters(&self) -> usize {
+        let n_parameters_optional = |expression: &Option<Expression>| expression.as_ref()
+            .map(|expression| expression.get_n_parameters())
+            .unwrap_or(0);
+
+        match self {
+            Statement::Select(select) => n_parameters_optional(&select.where_expr),
+            Statement::Update(update) => {
+                update.updated_values.iter()
+                    .map(|(_, expression)| expression.get_n_parameters())
+                    .sum::<usize>() + n_parameters_optional(&update.where_expr)
+            },
+            Statement::Delete(delete) => n_parameters_optional(&delete.where_expr),
+            Statement::Insert(insert) => insert.parameters.iter()
+                .map(|row_parameters| row_parameters.len())
+                .sum(),
+            _ => 0
+        }
+    }
+
     pub fn is_explained(&self) -> bool {
         match self {
             Statement::Select(select) => select.explain,
@@ -202,6 +378,43 @@ impl Statement {
                 transaction_req: Requirement::Optional,
                 database_req: Requirement::ObligatoryToHave
             },
+            Statement::DropTable(_) => StatementDescriptor {
+                creates_transaction: false,
+                terminates_transaction: false,
+                transaction_req: Requirement::Optional,
+                database_req: Requirement::ObligatoryToHave
+            },
+            Statement::AlterTable(_) => StatementDescriptor {
+                creates_transaction: false,
+                terminates_transaction: false,
+                transaction_req: Requirement::Optional,
+                database_req: Requirement::ObligatoryToHave
+            },
+            Statement::TruncateTable(_) => StatementDescriptor {
+                creates_transaction: false,
+                terminates_transaction: false,
+                transaction_req: Requirement::Optional,
+                database_req: Requirement::ObligatoryToHave
+            },
+            //The transaction would keep the versions it can read from being removed
+            Statement::Vacuum(_) => StatementDescriptor {
+                creates_transaction: false,
+                terminates_transaction: false,
+                transaction_req: Requirement::ObligatoryToNotHave,
+                database_req: Requirement::ObligatoryToHave
+            },
+            Statement::CheckDatabase => StatementDescriptor {
+                creates_transaction: false,
+                terminates_transaction: false,
+                transaction_req: Requirement::Optional,
+                database_req: Requirement::ObligatoryToHave
+            },
+            Statement::DropIndex(_) => StatementDescriptor {
+                creates_transaction: false,
+                terminates_transaction: false,
+                transaction_req: Requirement::Optional,
+                database_req: Requirement::ObligatoryToHave
+            },
             Statement::CreateDatabase(_) => StatementDescriptor {
                 creates_transaction: false,
                 terminates_transaction: false,
@@ -214,7 +427,7 @@ impl Statement {
                 transaction_req: Requirement::Optional,
                 database_req: Requirement::ObligatoryToHave
             },
-            Statement::StartTransaction => StatementDescriptor {
+            Statement::StartTransaction(_) => StatementDescriptor {
                 creates_transaction: true,
                 terminates_transaction: false,
                 transaction_req: Requirement::ObligatoryToNotHave,
@@ -232,6 +445,14 @@ impl Statement {
                 transaction_req: Requirement::ObligatoryToHave,
                 database_req: Requirement::ObligatoryToHave
             },
+            Statement::Savepoint(_) |
+            Statement::RollbackToSavepoint(_) |
+            Statement::ReleaseSavepoint(_) => StatementDescriptor {
+                creates_transaction: false,
+                terminates_transaction: false,
+                transaction_req: Requirement::ObligatoryToHave,
+                database_req: Requirement::ObligatoryToHave
+            },
             Statement::ShowDatabases => StatementDescriptor {
                 creates_transaction: false,
                 terminates_transaction: false,
@@ -244,23 +465,58 @@ impl Statement {
                 transaction_req: Requirement::Optional,
                 database_req: Requirement::ObligatoryToHave
             },
+            Statement::ShowTransactionStatus |
+            Statement::ShowTransaction => StatementDescriptor {
+                creates_transaction: false,
+                terminates_transaction: false,
+                transaction_req: Requirement::Optional,
+                database_req: Requirement::Optional
+            },
+            Statement::ShowTransactions => StatementDescriptor {
+                creates_transaction: false,
+                terminates_transaction: false,
+                transaction_req: Requirement::Optional,
+                database_req: Requirement::ObligatoryToHave
+            },
             Statement::CreateIndex(_) => StatementDescriptor {
                 creates_transaction: false,
                 terminates_transaction: false,
                 transaction_req: Requirement::Optional,
                 database_req: Requirement::ObligatoryToHave
             },
-            Statement::ShowIndexes(_) => StatementDescriptor {
+            Statement::ShowIndexes(_) |
+            Statement::ShowStats(_) => StatementDescriptor {
                 creates_transaction: false,
                 terminates_transaction: false,
                 transaction_req: Requirement::Optional,
                 database_req: Requirement::ObligatoryToHave
+            },
+            //The transaction belongs to the previous database
+            Statement::Use(_) => StatementDescriptor {
+                creates_transaction: false,
+                terminates_transaction: false,
+                transaction_req: Requirement::ObligatoryToNotHave,
+                database_req: Requirement::Optional
+            },
+            Statement::Backup(_) |
+            Statement::CreateUser(_, _) |
+            Statement::Grant(_, _) => StatementDescriptor {
+                creates_transaction: false,
+                terminates_transaction: false,
+                transaction_req: Requirement::Optional,
+                database_req: Requirement::Optional
             }
         }
     }
 }
 
 impl SelectStatement {
+    //Used as the key of the SelectCache. Aliases are not included, as they don't change the returned rows
+    pub fn cache_key(&self) -> String {
+        format!("{}|{:?}|{}|{:?}|{:?}|{:?}|{:?}|{}", self.table_name, self.selection, self.distinct, self.where_expr,
+                self.group_by, self.sort, self.limit, self.offset)
+    }
+
     pub fn take_where_expression(&mut self) -> Expression {
         self.where_expr.take().unwrap()
     }
@@ -286,4 +542,16 @@ impl SelectStatement {
             Limit::None => panic!("Illegal code path"),
         }
     }
-}
\ No newline at end of file
+
+    //Expect the statement to have been validated
+    pub fn get_offset(&self) -> usize {
+        self.offset as usize
+    }
+}
+
+fn bind_optional(expression: Option<Expression>, values: &Vec<Value>) -> Result<Option<Expression>, SimpleDbError> {
+    match expression {
+        Some(expression) => Ok(Some(expression.bind(values)?)),
+        None => Ok(None),
+    }
+}
@@ -4,5 +4,8 @@ pub mod plan_step;
 mod scan_type;
 pub mod steps;
 mod scan_type_analyzer;
+mod scan_cost_estimator;
 
-pub use scan_type::RangeScan;
\ No newline at end of file
+pub use scan_type::{DiscardedIndexScan, RangeScan};
+pub(crate) use scan_type::ScanType;
+pub use steps::analyze_step::StepStats;
\ No newline at end of file
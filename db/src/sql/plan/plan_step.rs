@@ -1,5 +1,6 @@
-use crate::table::selection::Selection;
-use crate::sql::plan::scan_type::RangeScan;
+use crate::table::selection::{Aggregate, Selection};
+use crate::sql::plan::scan_type::{DiscardedIndexScan, RangeScan};
+use crate::sql::cancellation_token::CancellationToken;
 use crate::sql::plan::steps::filter_step::FilterStep;
 use crate::sql::plan::steps::full_scan_step::FullScanStep;
 use crate::sql::plan::steps::limit_step::LimitStep;
@@ -17,6 +18,15 @@ use crate::sql::plan::steps::full_sort_step::FullSortStep;
 use crate::sql::plan::steps::reverse_step::ReverseStep;
 use crate::sql::plan::steps::top_n_sort::TopNSortStep;
 use crate::table::row::RowIterator;
+use crate::sql::plan::steps::aggregate_step::AggregateStep;
+use crate::sql::plan::steps::group_by_step::GroupByStep;
+use crate::sql::plan::steps::empty_step::EmptyStep;
+use crate::sql::plan::steps::cached_result_step::CachedResultStep;
+use crate::sql::plan::steps::composite_scan_step::CompositeScanStep;
+use crate::sql::plan::steps::analyze_step::{AnalyzeStep, StepStats};
+use crate::sql::plan::steps::distinct_step::DistinctStep;
+use crate::sql::plan::steps::index_only_scan_step::{IndexOnlyLookup, IndexOnlyScanStep};
+use crate::sql::plan::steps::parallel_full_scan_step::ParallelFullScanStep;
 
 pub(crate) trait PlanStepTrait {
     fn next(&mut self) -> Result<Option<Row>, SimpleDbError>;
@@ -31,15 +41,24 @@ pub enum PlanStep {
     FullSort(Box<FullSortStep>),
     Filter(Box<FilterStep>),
     Reverse(Box<ReverseStep>),
+    Aggregate(Box<AggregateStep>),
+    GroupBy(Box<GroupByStep>),
+    Distinct(Box<DistinctStep>),
+    CachedResult(Box<CachedResultStep>),
+    Analyze(Box<AnalyzeStep>),
 
     MergeIntersection(MergeIntersectionStep),
     MergeUnion(MergeUnionStep),
 
     FullScan(FullScanStep),
+    ParallelFullScan(ParallelFullScanStep),
     PrimaryRangeScan(PrimaryRangeScanStep),
     SecondaryRangeScan(SecondaryRangeScanStep),
     PrimaryExactScan(PrimaryExactScanStep),
     SecondaryExactExactScan(SecondaryExactScanStep),
+    IndexOnlyScan(IndexOnlyScanStep),
+    CompositeScan(CompositeScanStep),
+    Empty(EmptyStep),
 
     //Only used for testing
     Mock(MockStep)
@@ -47,18 +66,27 @@ pub enum PlanStep {
 
 pub enum PlanStepDesc {
     ProjectionSelectionStep(Selection, Box<PlanStepDesc>),
-    Limit(Limit, Box<PlanStepDesc>),
+    Limit(Limit, usize, Box<PlanStepDesc>), //Limit, offset
     Filter(Box<PlanStepDesc>),
     MergeIntersection(Box<PlanStepDesc>, Box<PlanStepDesc>),
     MergeUnion(Box<PlanStepDesc>, Box<PlanStepDesc>),
     FullSort(Sort, Box<PlanStepDesc>),
     TopNSort(Sort, usize, Box<PlanStepDesc>),
-    Revserse(Box<PlanStepDesc>),
+    Reverse(Box<PlanStepDesc>),
+    Aggregate(Vec<Aggregate>, Box<PlanStepDesc>),
+    GroupBy(Vec<String>, Vec<Aggregate>, bool, Box<PlanStepDesc>), //Group by columns, aggregates, is streaming
+    Distinct(bool, Box<PlanStepDesc>), //Is source sorted
 
-    FullScan,
+    FullScan(Option<DiscardedIndexScan>, bool), //Some if a secondary index scan was discarded because of its cost, is reverse
+    ParallelFullScan(usize, usize), //Nº of partitions, nº of threads
     RangeScan(RangeScan),
     PrimaryExactScan(Bytes),
     SecondaryExactExactScan(String, Bytes),
+    IndexOnlyScan(Box<PlanStepDesc>), //Description of the secondary index lookup
+    CompositeScan(Vec<String>, Vec<Value>), //Columns names, values of the leading columns
+    Empty,
+
+    Analyzed(StepStats, Box<PlanStepDesc>), //Stats measured by EXPLAIN ANALYZE, description of the analyzed step
 }
 
 impl RowIterator for PlanStep {
@@ -69,15 +97,24 @@ impl RowIterator for PlanStep {
             PlanStep::MergeIntersection(step) => step.next(),
             PlanStep::MergeUnion(step) => step.next(),
             PlanStep::FullScan(step) => step.next(),
+            PlanStep::ParallelFullScan(step) => step.next(),
             PlanStep::PrimaryRangeScan(step) => step.next(),
             PlanStep::PrimaryExactScan(step) => step.next(),
             PlanStep::SecondaryExactExactScan(step) => step.next(),
             PlanStep::SecondaryRangeScan(step) => step.next(),
+            PlanStep::IndexOnlyScan(step) => step.next(),
+            PlanStep::CompositeScan(step) => step.next(),
             PlanStep::ProjectSelection(step) => step.next(),
             PlanStep::FullSort(step) => step.next(),
             PlanStep::Mock(step) => step.next(),
             PlanStep::TopNSort(step) => step.next(),
             PlanStep::Reverse(step) => step.next(),
+            PlanStep::Aggregate(step) => step.next(),
+            PlanStep::GroupBy(step) => step.next(),
+            PlanStep::Distinct(step) => step.next(),
+            PlanStep::Empty(step) => step.next(),
+            PlanStep::CachedResult(step) => step.next(),
+            PlanStep::Analyze(step) => step.next(),
         }
     }
 }
@@ -110,10 +147,19 @@ impl PlanStep {
         match &self {
             PlanStep::ProjectSelection(step) => step.source.get_column_sorted(schema),
             PlanStep::Limit(step) => step.source.get_column_sorted(schema),
-            PlanStep::FullSort(step) => Some(step.sort.column_name.clone()),
-            PlanStep::TopNSort(step) => Some(step.sort.column_name.clone()),
+            PlanStep::FullSort(step) => Some(step.sort.get_first_column_name().clone()),
+            PlanStep::TopNSort(step) => Some(step.sort.get_first_column_name().clone()),
             PlanStep::Filter(step) => step.source.get_column_sorted(schema),
             PlanStep::Reverse(step) => step.source.get_column_sorted(schema),
+            PlanStep::Analyze(step) => step.source.get_column_sorted(schema),
+            PlanStep::Distinct(step) => step.source.get_column_sorted(schema),
+            PlanStep::Aggregate(_) |
+            PlanStep::GroupBy(_) |
+            PlanStep::CachedResult(_) |
+            PlanStep::CompositeScan(_) |
+            //Partitions are scanned concurrently
+            PlanStep::ParallelFullScan(_) |
+            PlanStep::Empty(_) => None,
             PlanStep::MergeIntersection(_) |
             PlanStep::MergeUnion(_) => {
                 let left = self.get_merge_left();
@@ -136,6 +182,9 @@ impl PlanStep {
             PlanStep::SecondaryExactExactScan(step) => {
                 Some(step.column_name.clone())
             }
+            PlanStep::IndexOnlyScan(step) => {
+                Some(step.column_name.clone())
+            }
             PlanStep::Mock(step) => {
                 if step.sorted_by_primary {
                     Some(schema.get_primary_column().column_name)
@@ -154,14 +203,124 @@ impl PlanStep {
             PlanStep::MergeIntersection(step) => step.desc(),
             PlanStep::MergeUnion(step) => step.desc(),
             PlanStep::FullScan(step) => step.desc(),
+            PlanStep::ParallelFullScan(step) => step.desc(),
             PlanStep::PrimaryRangeScan(step) => step.desc(),
             PlanStep::PrimaryExactScan(step) => step.desc(),
             PlanStep::SecondaryExactExactScan(step) => step.desc(),
             PlanStep::SecondaryRangeScan(step) => step.desc(),
+            PlanStep::IndexOnlyScan(step) => step.desc(),
+            PlanStep::CompositeScan(step) => step.desc(),
             PlanStep::ProjectSelection(step) => step.desc(),
             PlanStep::Mock(step) => step.desc(),
             PlanStep::TopNSort(step) => step.desc(),
-            PlanStep::Reverse(step) => step.desc()
+            PlanStep::Reverse(step) => step.desc(),
+            PlanStep::Aggregate(step) => step.desc(),
+            PlanStep::GroupBy(step) => step.desc(),
+            PlanStep::Distinct(step) => step.desc(),
+            PlanStep::Empty(step) => step.desc(),
+            PlanStep::CachedResult(step) => step.desc(),
+            PlanStep::Analyze(step) => step.desc(),
+        }
+    }
+
+    //Wraps every step of the plan with an AnalyzeStep, so EXPLAIN ANALYZE can report the rows emitted
+    //and the time spent by each of them. Has to be called once the plan has been optimized
+    pub(crate) fn analyze(self) -> PlanStep {
+        let analyzed_step = match self {
+            PlanStep::ProjectSelection(mut step) => {
+                step.source = step.source.analyze();
+                PlanStep::ProjectSelection(step)
+            },
+            PlanStep::Limit(mut step) => {
+                step.source = step.source.analyze();
+                PlanStep::Limit(step)
+            },
+            PlanStep::TopNSort(mut step) => {
+                step.source = step.source.analyze();
+                PlanStep::TopNSort(step)
+            },
+            PlanStep::FullSort(mut step) => {
+                step.source = step.source.analyze();
+                step.sorter.set_source(step.source.clone());
+                PlanStep::FullSort(step)
+            },
+            PlanStep::Filter(mut step) => {
+                step.source = step.source.analyze();
+                PlanStep::Filter(step)
+            },
+            PlanStep::Reverse(mut step) => {
+                step.source = step.source.analyze();
+                PlanStep::Reverse(step)
+            },
+            PlanStep::Aggregate(mut step) => {
+                step.source = step.source.analyze();
+                PlanStep::Aggregate(step)
+            },
+            PlanStep::GroupBy(mut step) => {
+                step.source = step.source.analyze();
+                PlanStep::GroupBy(step)
+            },
+            PlanStep::Distinct(mut step) => {
+                step.source = step.source.analyze();
+                PlanStep::Distinct(step)
+            },
+            PlanStep::MergeIntersection(mut step) => {
+                step.plans = step.plans.into_iter().map(|plan| plan.analyze()).collect();
+                PlanStep::MergeIntersection(step)
+            },
+            PlanStep::MergeUnion(mut step) => {
+                step.plans = step.plans.into_iter().map(|plan| plan.analyze()).collect();
+                PlanStep::MergeUnion(step)
+            },
+            PlanStep::Analyze(_) => return self,
+            other => other,
+        };
+
+        PlanStep::Analyze(Box::new(AnalyzeStep::create(analyzed_step)))
+    }
+
+    //Sets the token in the steps that read rows from their source without returning them,
+    //so the query can be cancelled while no rows are being returned
+    pub(crate) fn set_cancellation_token(&mut self, cancellation_token: &CancellationToken) {
+        match self {
+            PlanStep::ProjectSelection(step) => step.source.set_cancellation_token(cancellation_token),
+            PlanStep::Limit(step) => step.source.set_cancellation_token(cancellation_token),
+            PlanStep::Analyze(step) => step.source.set_cancellation_token(cancellation_token),
+            PlanStep::TopNSort(step) => {
+                step.cancellation_token = Some(cancellation_token.clone());
+                step.source.set_cancellation_token(cancellation_token);
+            },
+            PlanStep::FullSort(step) => {
+                step.source.set_cancellation_token(cancellation_token);
+                step.sorter.set_source(step.source.clone());
+                step.sorter.set_cancellation_token(cancellation_token.clone());
+            },
+            PlanStep::Filter(step) => {
+                step.cancellation_token = Some(cancellation_token.clone());
+                step.source.set_cancellation_token(cancellation_token);
+            },
+            PlanStep::Reverse(step) => {
+                step.cancellation_token = Some(cancellation_token.clone());
+                step.source.set_cancellation_token(cancellation_token);
+            },
+            PlanStep::Aggregate(step) => {
+                step.cancellation_token = Some(cancellation_token.clone());
+                step.source.set_cancellation_token(cancellation_token);
+            },
+            PlanStep::GroupBy(step) => {
+                step.cancellation_token = Some(cancellation_token.clone());
+                step.source.set_cancellation_token(cancellation_token);
+            },
+            PlanStep::Distinct(step) => {
+                step.cancellation_token = Some(cancellation_token.clone());
+                step.source.set_cancellation_token(cancellation_token);
+            },
+            PlanStep::MergeIntersection(step) => step.plans.iter_mut()
+                .for_each(|plan| plan.set_cancellation_token(cancellation_token)),
+            PlanStep::MergeUnion(step) => step.plans.iter_mut()
+                .for_each(|plan| plan.set_cancellation_token(cancellation_token)),
+            //Scans return every row they read
+            _ => {}
         }
     }
 
@@ -219,6 +378,9 @@ impl PlanStep {
             (PlanStep::SecondaryExactExactScan(left), PlanStep::SecondaryExactExactScan(right)) => {
                 left.column_name.eq(&right.column_name)
             }
+            (PlanStep::IndexOnlyScan(left), PlanStep::IndexOnlyScan(right)) => {
+                left.column_name.eq(&right.column_name)
+            }
             (_, _) => false
         }
     }
@@ -231,6 +393,10 @@ impl PlanStep {
             PlanStep::SecondaryExactExactScan(step) => {
                 &step.secondary_column_value
             },
+            PlanStep::IndexOnlyScan(step) => match &step.lookup {
+                IndexOnlyLookup::Exact(value) => value,
+                IndexOnlyLookup::Range(_) => panic!("Illegal code path"),
+            },
             _ => panic!("Illegal code path")
         }
     }
@@ -239,6 +405,7 @@ impl PlanStep {
         match &self {
             PlanStep::SecondaryExactExactScan(_) |
             PlanStep::PrimaryExactScan(_) => true,
+            PlanStep::IndexOnlyScan(step) => matches!(step.lookup, IndexOnlyLookup::Exact(_)),
             _ => false
         }
     }
@@ -247,6 +414,10 @@ impl PlanStep {
         match &self {
             PlanStep::SecondaryRangeScan(step) => step.range.clone(),
             PlanStep::PrimaryRangeScan(step) => step.range.clone(),
+            PlanStep::IndexOnlyScan(step) => match &step.lookup {
+                IndexOnlyLookup::Range(range) => range.clone(),
+                IndexOnlyLookup::Exact(_) => panic!("Illegal code path"),
+            },
             _ => panic!("Illegal code path")
         }
     }
@@ -255,6 +426,7 @@ impl PlanStep {
         match &self {
             PlanStep::SecondaryRangeScan(_) |
             PlanStep::PrimaryRangeScan(_) => true,
+            PlanStep::IndexOnlyScan(step) => matches!(step.lookup, IndexOnlyLookup::Range(_)),
             _ => false
         }
     }
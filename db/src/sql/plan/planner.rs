@@ -1,10 +1,11 @@
 use std::collections::HashSet;
 use crate::table::selection::Selection;
 use crate::sql::parser::expression::Expression;
-use crate::sql::parser::statement::{DeleteStatement, Limit, SelectStatement, UpdateStatement};
+use crate::sql::parser::statement::{DeleteStatement, IndexHint, Limit, SelectStatement, UpdateStatement};
 use crate::sql::plan::plan_step::PlanStep;
 use crate::sql::plan::scan_type::ScanType;
 use crate::sql::plan::scan_type_analyzer::ScanTypeAnalyzer;
+use crate::sql::plan::scan_cost_estimator::ScanCostEstimator;
 use crate::sql::plan::steps::filter_step::FilterStep;
 use crate::sql::plan::steps::full_scan_step::FullScanStep;
 use crate::sql::plan::steps::limit_step::LimitStep;
@@ -16,23 +17,38 @@ use crate::sql::plan::steps::project_selection_step::ProjectSelectionStep;
 use crate::sql::plan::steps::secondary_exact_scan_step::SecondaryExactScanStep;
 use crate::sql::plan::steps::secondary_range_scan_step::SecondaryRangeScanStep;
 use crate::table::table::Table;
-use shared::{SimpleDbError, SimpleDbOptions};
+use shared::{SimpleDbError, SimpleDbOptions, Value};
 use std::sync::Arc;
+use threadpool::ThreadPool;
 use storage::transactions::transaction::Transaction;
-use crate::{Sort, SortOrder};
+use crate::{Schema, SortOrder};
 use crate::sql::plan::steps::full_sort_step::FullSortStep;
 use crate::sql::plan::steps::reverse_step::ReverseStep;
 use crate::sql::plan::steps::top_n_sort::TopNSortStep;
+use crate::sql::plan::steps::aggregate_step::AggregateStep;
+use crate::sql::plan::steps::group_by_step::GroupByStep;
+use crate::sql::plan::steps::empty_step::EmptyStep;
+use crate::sql::plan::steps::composite_scan_step::CompositeScanStep;
+use crate::sql::plan::steps::distinct_step::DistinctStep;
+use crate::sql::plan::steps::index_only_scan_step::{IndexOnlyLookup, IndexOnlyScanStep};
+use crate::sql::plan::steps::parallel_full_scan_step::ParallelFullScanStep;
 
 pub struct Planner {
-    options: Arc<SimpleDbOptions>
+    options: Arc<SimpleDbOptions>,
+    //Shared by the parallel full scans of every query. None if they are disabled
+    scan_thread_pool: Option<ThreadPool>,
 }
 
 impl Planner {
     pub fn create(
         options: Arc<SimpleDbOptions>
     ) -> Planner {
-        Planner { options }
+        let scan_thread_pool = match options.parallel_full_scan_n_threads {
+            0 => None,
+            n_threads => Some(ThreadPool::with_name(String::from("parallel-full-scan"), n_threads)),
+        };
+
+        Planner { options, scan_thread_pool }
     }
 
     pub fn plan_select(
@@ -42,68 +58,141 @@ impl Planner {
         transaction: &Transaction
     ) -> Result<PlanStep, SimpleDbError> {
         let query_selection = select_statement.selection.clone();
-        let (needs_projection_of_selection, storage_engine_selection) = Self::get_selection_select(&select_statement);
+        //Duplicated rows will be consecutive if the rows are sorted first by the selected columns.
+        //ORDER BY id, nombre doesn't make rows with the same nombre consecutive
+        let is_distinct_sorted = select_statement.distinct && match (&query_selection, &select_statement.sort) {
+            (Selection::Some(column_names), Some(sort)) => {
+                let selected_column_names: HashSet<&String> = column_names.iter().collect();
+                let sort_column_names = sort.get_column_names();
+                let sort_prefix_column_names: HashSet<&String> = sort_column_names.iter()
+                    .take(selected_column_names.len())
+                    .collect();
+                selected_column_names == sort_prefix_column_names
+            },
+            _ => false
+        };
+        let needs_scan = Self::remove_constant_where_expression(&mut select_statement.where_expr)?;
+        let (needs_projection_of_selection, storage_engine_selection) = Self::get_selection_select(&select_statement, table.get_schema());
 
-        let scan_type = self.get_scan_type(
-            &select_statement.where_expr,
-            table,
-        )?;
-        let mut last_step = self.build_scan_step(scan_type, transaction, storage_engine_selection.clone(), table)?;
+        let mut last_step = if needs_scan {
+            let scan_type = self.get_scan_type(&select_statement.where_expr, &select_statement.index_hint, table)?;
+
+            if Self::is_reverse_full_scan(&scan_type, &select_statement, &query_selection, table.get_schema()) {
+                //The rows will be read in the requested order, so they don't need to be sorted
+                select_statement.sort = None;
+                let discarded_index_scan = match scan_type {
+                    ScanType::FullByCost(discarded_index_scan) => Some(discarded_index_scan),
+                    _ => None
+                };
+                PlanStep::FullScan(FullScanStep::create_reverse(table.clone(), storage_engine_selection.clone(), transaction, discarded_index_scan)?)
+            } else if self.is_parallel_full_scan(&scan_type, &select_statement) {
+                let thread_pool = self.scan_thread_pool.clone().unwrap();
+                PlanStep::ParallelFullScan(ParallelFullScanStep::create(self.options.clone(), thread_pool, table.clone(), storage_engine_selection.clone(), transaction)?)
+            } else {
+                self.build_scan_step(scan_type, transaction, storage_engine_selection.clone(), table)?
+            }
+        } else {
+            //No rows will be returned, so they don't need to be sorted
+            select_statement.sort = None;
+            PlanStep::Empty(EmptyStep::create())
+        };
 
         //Where expression
         if select_statement.has_where_expression() {
             let where_expr = select_statement.take_where_expression();
             last_step = PlanStep::Filter(Box::new(FilterStep::create(where_expr.clone(), last_step)));
         }
-        //Only sorted, not with limit
-        if select_statement.is_sorted() && !select_statement.is_limit() {
+        //Aggregates without GROUP BY only produce one row, so there is no need to sort it
+        if query_selection.is_aggregate() {
+            last_step = match select_statement.group_by.take() {
+                Some(group_by) => PlanStep::GroupBy(Box::new(GroupByStep::create(&query_selection, group_by, table.get_schema(), last_step))),
+                None => PlanStep::Aggregate(Box::new(AggregateStep::create(&query_selection, table.get_schema(), last_step))),
+            };
+            //Grouped rows don't follow the table schema, so they are sorted in memory, like the buckets of GroupByStep
+            if let (PlanStep::GroupBy(_), Some(sort)) = (&last_step, select_statement.sort.take()) {
+                let n = if select_statement.is_limit() { select_statement.get_limit() + select_statement.get_offset() } else { usize::MAX };
+                last_step = PlanStep::TopNSort(Box::new(TopNSortStep::create(last_step, n, sort)));
+            }
+
+            if select_statement.is_limit() {
+                last_step = PlanStep::Limit(Box::new(LimitStep::create(select_statement.limit.clone(), select_statement.get_offset(), last_step)));
+            }
+
+            return Ok(last_step);
+        }
+        //Only sorted, not with limit. With DISTINCT, duplicated rows have to be removed before applying the limit
+        if select_statement.is_sorted() && (!select_statement.is_limit() || select_statement.distinct) {
             let sort = select_statement.sort.take().unwrap();
 
             match last_step.get_column_sorted(table.get_schema()) {
-                Some(source_produced_sorted_column) => {
-                    if sort.column_name.eq(&source_produced_sorted_column) && sort.order == SortOrder::Desc {
+                //Source produces rows sorted by the column, we only might need to reverse them
+                Some(source_produced_sorted_column) if sort.is_only_by(&source_produced_sorted_column) => {
+                    if sort.columns[0].1 == SortOrder::Desc {
                         last_step = PlanStep::Reverse(Box::new(ReverseStep::create(last_step, table.clone())?))
-                    } else if !sort.column_name.eq(&source_produced_sorted_column) {
-                        last_step = PlanStep::FullSort(Box::new(FullSortStep::create(self.options.clone(), query_selection.clone(), table.clone(), last_step, sort)?))
                     }
                 }
-                None => {
+                _ => {
                     last_step = PlanStep::FullSort(Box::new(FullSortStep::create(self.options.clone(), query_selection.clone(), table.clone(), last_step, sort)?))
                 }
             };
         }
+        if select_statement.distinct {
+            last_step = PlanStep::Distinct(Box::new(DistinctStep::create(query_selection.clone(), is_distinct_sorted, last_step)));
+        }
         //Only Limit
         if !select_statement.is_sorted() && select_statement.is_limit() {
-            last_step = PlanStep::Limit(Box::new(LimitStep::create(select_statement.limit.clone(), last_step)));
+            last_step = PlanStep::Limit(Box::new(LimitStep::create(select_statement.limit.clone(), select_statement.get_offset(), last_step)));
         }
-        //Sorted with limit
+        //Sorted with limit. The offset rows have to be sorted too, and skipped after sorting
         if select_statement.is_sorted() && select_statement.is_limit() {
             let sort = select_statement.sort.take().unwrap();
-            last_step = PlanStep::TopNSort(Box::new(TopNSortStep::create(last_step, select_statement.get_limit(), sort)));
+            let n = select_statement.get_limit() + select_statement.get_offset();
+            last_step = PlanStep::TopNSort(Box::new(TopNSortStep::create(last_step, n, sort)));
+
+            if select_statement.get_offset() > 0 {
+                last_step = PlanStep::Limit(Box::new(LimitStep::create(select_statement.limit.clone(), select_statement.get_offset(), last_step)));
+            }
         }
 
         if needs_projection_of_selection {
-            last_step = PlanStep::ProjectSelection(Box::new(ProjectSelectionStep::create(query_selection, last_step)))
+            last_step = PlanStep::ProjectSelection(Box::new(ProjectSelectionStep::create(query_selection, table, last_step)))
         }
 
         Ok(last_step)
     }
 
+    //Scan without any other step. Used by SimpleDb::scan_table() and SimpleDb::scan_range()
+    pub fn plan_scan(
+        &self,
+        table: &Arc<Table>,
+        scan_type: ScanType,
+        selection: Selection,
+        transaction: &Transaction,
+    ) -> Result<PlanStep, SimpleDbError> {
+        self.build_scan_step(scan_type, transaction, selection, table)
+    }
+
     pub fn plan_update(
         &self,
         table: &Arc<Table>,
         update_statement: &UpdateStatement,
         transaction: &Transaction,
     ) -> Result<PlanStep, SimpleDbError> {
+        let mut where_expr = update_statement.where_expr.clone();
+        if !Self::remove_constant_where_expression(&mut where_expr)? {
+            return Ok(PlanStep::Empty(EmptyStep::create()));
+        }
+
         let scan_type = self.get_scan_type(
-            &update_statement.where_expr, //No sort
+            &where_expr, //No sort
+            &None,
             table
         )?;
         let updated_values = update_statement.get_updated_values();
         let mut last_step = self.build_scan_step(scan_type, transaction, updated_values, table)?;
 
-        if let Some(where_expr) = &update_statement.where_expr {
-            last_step = PlanStep::Filter(Box::new(FilterStep::create(where_expr.clone(), last_step)));
+        if let Some(where_expr) = where_expr {
+            last_step = PlanStep::Filter(Box::new(FilterStep::create(where_expr, last_step)));
         }
 
         Ok(last_step)
@@ -112,11 +201,16 @@ impl Planner {
     pub fn plan_delete(
         &self,
         table: &Arc<Table>,
-        select_statement: DeleteStatement,
+        mut select_statement: DeleteStatement,
         transaction: &Transaction
     ) -> Result<PlanStep, SimpleDbError> {
+        if !Self::remove_constant_where_expression(&mut select_statement.where_expr)? {
+            return Ok(PlanStep::Empty(EmptyStep::create()));
+        }
+
         let scan_type = self.get_scan_type(
             &select_statement.where_expr,
+            &None,
             table,
         )?;
         let mut last_step = self.build_scan_step(scan_type, transaction, Selection::All, table)?;
@@ -125,12 +219,41 @@ impl Planner {
             last_step = PlanStep::Filter(Box::new(FilterStep::create(where_expr, last_step)));
         }
         if !matches!(select_statement.limit, Limit::None) {
-            last_step = PlanStep::Limit(Box::new(LimitStep::create(select_statement.limit, last_step)));
+            last_step = PlanStep::Limit(Box::new(LimitStep::create(select_statement.limit, 0, last_step)));
         }
 
         Ok(last_step)
     }
 
+    //ORDER BY <primary column> DESC over a full scan, the table can be read backwards
+    fn is_reverse_full_scan(
+        scan_type: &ScanType,
+        select_statement: &SelectStatement,
+        query_selection: &Selection,
+        schema: &Schema
+    ) -> bool {
+        let is_full_scan = matches!(scan_type, ScanType::Full | ScanType::FullByCost(_));
+
+        match &select_statement.sort {
+            Some(sort) => is_full_scan && !query_selection.is_aggregate() &&
+                sort.is_only_by(&schema.get_primary_column().column_name) &&
+                sort.columns[0].1 == SortOrder::Desc,
+            None => false
+        }
+    }
+
+    //Rows of parallel full scans are not returned in primary key order
+    fn is_parallel_full_scan(
+        &self,
+        scan_type: &ScanType,
+        select_statement: &SelectStatement,
+    ) -> bool {
+        self.scan_thread_pool.is_some() &&
+            matches!(scan_type, ScanType::Full) &&
+            !select_statement.is_sorted() &&
+            !select_statement.is_limit()
+    }
+
     fn build_scan_step(
         &self,
         scan_type: ScanType,
@@ -139,8 +262,15 @@ impl Planner {
         table: &Arc<Table>,
     ) -> Result<PlanStep, SimpleDbError> {
         let schema = table.get_schema();
+        //Index only scans can't tell if the rows of a table with TTL have expired
+        let can_use_index_only_scan = table.get_ttl_seconds().is_none();
 
         match scan_type {
+            ScanType::ExactSecondary(column, exact_id_expr) if can_use_index_only_scan && selection.is_covered_by_index(schema, &column) => {
+                let column_type = schema.get_column_or_err(&column)?.column_type;
+                let lookup_value = Value::create(exact_id_expr.get_literal_bytes(), column_type)?;
+                Ok(PlanStep::IndexOnlyScan(IndexOnlyScanStep::create(table.clone(), &column, transaction, IndexOnlyLookup::Exact(lookup_value))?))
+            },
             ScanType::ExactSecondary(column, exact_id_expr) => {
                 Ok(PlanStep::SecondaryExactExactScan(SecondaryExactScanStep::create(table.clone(), &column, exact_id_expr.get_literal_bytes(), transaction, selection)?))
             },
@@ -148,7 +278,11 @@ impl Planner {
                 Ok(PlanStep::PrimaryExactScan(PrimaryExactScanStep::create(table.clone(), exact_id_expr.get_literal_bytes(), selection, transaction)?))
             },
             ScanType::Range(range) => {
-                if schema.is_secondary_indexed(&range.column_name) {
+                if can_use_index_only_scan && schema.is_secondary_indexed(&range.column_name) && selection.is_covered_by_index(schema, &range.column_name) {
+                    Ok(PlanStep::IndexOnlyScan(
+                        IndexOnlyScanStep::create(table.clone(), &range.column_name.clone(), transaction, IndexOnlyLookup::Range(range))?
+                    ))
+                } else if schema.is_secondary_indexed(&range.column_name) {
                     Ok(PlanStep::SecondaryRangeScan(
                         SecondaryRangeScanStep::create(table.clone(), selection, &range.column_name, transaction, range.clone())?
                     ))
@@ -158,8 +292,14 @@ impl Planner {
                     ))
                 }
             },
+            ScanType::Composite(composite_scan) => {
+                Ok(PlanStep::CompositeScan(CompositeScanStep::create(table.clone(), composite_scan, transaction, selection)?))
+            },
             ScanType::Full => {
-                Ok(PlanStep::FullScan(FullScanStep::create(table.clone(), selection, transaction)?))
+                Ok(PlanStep::FullScan(FullScanStep::create(table.clone(), selection, transaction, None)?))
+            },
+            ScanType::FullByCost(discarded_index_scan) => {
+                Ok(PlanStep::FullScan(FullScanStep::create(table.clone(), selection, transaction, Some(discarded_index_scan))?))
             },
             ScanType::MergeUnion(left_scan_type, right_scan_type) => {
                 let left_scan_step = self.build_scan_step(*left_scan_type, transaction, selection.clone(), table)?;
@@ -174,20 +314,73 @@ impl Planner {
         }
     }
 
+    //WHERE expressions which are always true are removed.
+    //Returns false if the WHERE expression is always false, so no rows need to be scanned
+    //The expression is expected to have been passed to evaluate_constant_expressions()
+    fn remove_constant_where_expression(
+        where_expr: &mut Option<Expression>
+    ) -> Result<bool, SimpleDbError> {
+        match where_expr {
+            Some(Expression::Literal(value)) => {
+                let is_always_true = !value.is_null() && value.get_boolean()?;
+                *where_expr = None;
+                Ok(is_always_true)
+            },
+            _ => Ok(true),
+        }
+    }
+
     fn get_scan_type(
         &self,
         expression: &Option<Expression>,
+        index_hint: &Option<IndexHint>,
         table: &Arc<Table>,
     ) -> Result<ScanType, SimpleDbError> {
-        match expression {
-            Some(expression) => {
+        match (expression, index_hint) {
+            (_, Some(IndexHint::NoIndex)) => Ok(ScanType::Full),
+            (expression, Some(IndexHint::Index(column_names))) => {
+                let schema = table.get_schema();
+                let index_name = column_names.join(", ");
+                let mut columns = Vec::new();
+                for column_name in column_names {
+                    columns.push(schema.get_column_or_err(column_name)?);
+                }
+                //A single column is resolved to its own index, multiple columns to the composite index with that order
+                let composite_index = match columns.as_slice() {
+                    [column] => {
+                        if !column.is_primary && !column.is_secondary_indexed() {
+                            return Err(SimpleDbError::IndexNotFound(column.column_id));
+                        }
+                        None
+                    },
+                    _ => match schema.get_composite_index(&columns.iter().map(|column| column.column_id).collect()) {
+                        Some(composite_index) => Some(composite_index),
+                        None => return Err(SimpleDbError::MalformedQuery(format!("Index on {} not found", index_name))),
+                    },
+                };
+                let expression = expression.as_ref().ok_or_else(|| SimpleDbError::MalformedQuery(
+                    format!("Index of {} cannot be used without a WHERE expression", index_name)
+                ))?;
+
+                let scan_type_analyzer = ScanTypeAnalyzer::create(expression.clone(), schema.clone());
+                match composite_index {
+                    Some(composite_index) => scan_type_analyzer.analyze_with_composite_index(&composite_index),
+                    None => scan_type_analyzer.analyze_with_index(&index_name),
+                }
+            },
+            (Some(expression), None) => {
                 let scan_type_analyzer = ScanTypeAnalyzer::create(
                     expression.clone(),
                     table.get_schema().clone(),
                 );
-                scan_type_analyzer.analyze()
+                let scan_type = scan_type_analyzer.analyze()?;
+
+                match ScanCostEstimator::create(self.options.clone(), table.clone()).get_cheaper_full_scan(&scan_type)? {
+                    Some(discarded_index_scan) => Ok(ScanType::FullByCost(discarded_index_scan)),
+                    None => Ok(scan_type),
+                }
             },
-            None => Ok(ScanType::Full),
+            (None, None) => Ok(ScanType::Full),
         }
     }
 
@@ -197,17 +390,33 @@ impl Planner {
     //For example: SELECT nombre WHERE dinero > 100. We will need nombre and dinero to be scanned from the stoage engine
     //But we will only return dinero to the final user.
     fn get_selection_select(
-        select: &SelectStatement
+        select: &SelectStatement,
+        schema: &Schema
     ) -> (bool, Selection) {
         match &select.selection {
             Selection::All => (false, Selection::All),
+            Selection::Aggregate(_, _) => {
+                let mut storage_engine_selection = HashSet::new();
+
+                storage_engine_selection.extend(select.selection.get_some_selected_columns());
+                if let Some(group_by) = &select.group_by {
+                    storage_engine_selection.extend(group_by.iter().cloned());
+                }
+                if let Some(where_expr) = &select.where_expr {
+                    storage_engine_selection.extend(where_expr.get_identifiers());
+                }
+
+                (false, Selection::Some(storage_engine_selection.into_iter().collect()))
+            },
             Selection::Some(query_selection) => {
                 let mut storage_engine_selection = HashSet::new();
 
                 storage_engine_selection.extend(query_selection.iter().map(|it| it.clone()));
 
                 if let Some(sort) = &select.sort {
-                    storage_engine_selection.insert(sort.column_name.clone());
+                    storage_engine_selection.extend(sort.get_column_names());
+                    //Rows might be written to disk while sorting, which are read back by its primary column
+                    storage_engine_selection.insert(schema.get_primary_column().column_name);
                 }
                 if let Some(where_expr) = &select.where_expr {
                     storage_engine_selection.extend(where_expr.get_identifiers());
@@ -216,7 +425,204 @@ impl Planner {
                 let mut projection_needed = query_selection.len() != storage_engine_selection.len();
 
                 (projection_needed, Selection::Some(storage_engine_selection.into_iter().collect()))
+            },
+            //Expressions are evaluated after reading the rows, so they always need a projection
+            Selection::Expressions(_) => {
+                let mut storage_engine_selection = HashSet::new();
+
+                storage_engine_selection.extend(select.selection.get_some_selected_columns());
+
+                if let Some(sort) = &select.sort {
+                    storage_engine_selection.extend(sort.get_column_names());
+                    //Rows might be written to disk while sorting, which are read back by its primary column
+                    storage_engine_selection.insert(schema.get_primary_column().column_name);
+                }
+                if let Some(where_expr) = &select.where_expr {
+                    storage_engine_selection.extend(where_expr.get_identifiers());
+                }
+
+                (true, Selection::Some(storage_engine_selection.into_iter().collect()))
             }
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod test {
+    use crate::sql::execution::expression_evaluator::evaluate_constant_expressions;
+    use crate::sql::parser::parser::Parser;
+    use crate::sql::parser::statement::{SelectStatement, Statement};
+    use crate::sql::plan::plan_step::PlanStep;
+    use crate::sql::plan::planner::Planner;
+    use crate::table::row::RowIterator;
+    use crate::table::table::Table;
+    use crate::{Column, PlanStepDesc};
+    use crate::simple_db::{Context, StatementResult};
+    use crate::test_utils::TestSimpleDb;
+    use shared::{SimpleDbError, SimpleDbOptions};
+    use std::sync::Arc;
+    use storage::transactions::transaction::Transaction;
+
+    #[test]
+    fn plan_select_constant_false_where() {
+        let planner = Planner::create(Arc::new(SimpleDbOptions::default()));
+        let table = Table::create_mock(vec![Column::create_primary("id"), Column::create("dinero", 1)]);
+
+        let mut plan = planner.plan_select(&table, select("SELECT * FROM personas WHERE id > 5 AND 1 == 2;"), &Transaction::none())
+            .unwrap();
+
+        //No scan step is created, so nothing will be read from storage
+        assert!(matches!(plan, PlanStep::Empty(_)));
+        assert!(matches!(plan.desc(), PlanStepDesc::Empty));
+        assert!(plan.next().unwrap().is_none());
+    }
+
+    #[test]
+    fn remove_constant_where_expression() {
+        let mut where_expr = select("SELECT * FROM personas WHERE 1 == 1;").where_expr;
+        assert!(Planner::remove_constant_where_expression(&mut where_expr).unwrap());
+        assert!(where_expr.is_none());
+
+        let mut where_expr = select("SELECT * FROM personas WHERE 1 == 2;").where_expr;
+        assert!(!Planner::remove_constant_where_expression(&mut where_expr).unwrap());
+        assert!(where_expr.is_none());
+
+        let mut where_expr = select("SELECT * FROM personas WHERE id > 1;").where_expr;
+        assert!(Planner::remove_constant_where_expression(&mut where_expr).unwrap());
+        assert!(where_expr.is_some());
+    }
+
+    fn select(query: &str) -> SelectStatement {
+        let mut parser = Parser::create(String::from(query));
+        match parser.next_statement().unwrap().unwrap() {
+            Statement::Select(mut select) => {
+                select.where_expr = select.where_expr.map(|it| evaluate_constant_expressions(it).unwrap());
+                select
+            },
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn select_less_range() {
+        let simple_db = TestSimpleDb::create("select_less_range");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY);");
+        for id in 1..=5 {
+            simple_db.execute_in_transaction(&context, &format!("INSERT INTO personas (id) VALUES ({});", id));
+        }
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        let explain = simple_db.execute_sql(&transaction.context(), "EXPLAIN SELECT * FROM personas WHERE id < 3;");
+        match explain {
+            StatementResult::Data(PlanStepDesc::Filter(source), _) => match *source {
+                PlanStepDesc::RangeScan(range_scan) => assert!(!range_scan.end_inclusive),
+                _ => panic!("Expected range scan")
+            },
+            _ => panic!("Expected filter")
+        }
+
+        let rows = simple_db.execute_sql(&transaction.context(), "SELECT * FROM personas WHERE id < 3;").data().all().unwrap();
+        assert_eq!(rows.len(), 2);
+        let rows = simple_db.execute_sql(&transaction.context(), "SELECT * FROM personas WHERE id <= 3;").data().all().unwrap();
+        assert_eq!(rows.len(), 3);
+        transaction.commit().unwrap();
+    }
+
+    #[test]
+    fn cost_based_full_scan() {
+        let simple_db = TestSimpleDb::create("cost_based_full_scan");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, estado I64, dinero I64);");
+        simple_db.execute_sql(&context, "CREATE INDEX ON personas (estado);");
+        simple_db.execute_sql(&context, "CREATE INDEX ON personas (dinero);");
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        for id in 1..=20 {
+            let estado = if id <= 18 { 1 } else { 2 };
+            let insert = format!("INSERT INTO personas (id, estado, dinero) VALUES ({}, {}, {});", id, estado, id * 10);
+            simple_db.execute(&transaction.context(), simple_db.parse(&insert).unwrap()).unwrap();
+        }
+        let explain = |query: &str| match simple_db.execute(&transaction.context(), simple_db.parse(query).unwrap()) {
+            Ok(StatementResult::Data(desc, _)) => desc,
+            _ => panic!(),
+        };
+
+        //Half of the table is estimated to have estado == 1
+        let desc = explain("EXPLAIN SELECT * FROM personas WHERE estado == 1;");
+        assert!(matches!(desc, PlanStepDesc::Filter(scan) if matches!(&*scan, PlanStepDesc::FullScan(Some(discarded), false)
+            if discarded.column_name == "estado" && discarded.estimated_selectivity == 0.5 && discarded.estimated_n_rows == 20)));
+
+        let desc = explain("EXPLAIN SELECT * FROM personas WHERE dinero > 20;");
+        assert!(matches!(desc, PlanStepDesc::Filter(scan) if matches!(&*scan, PlanStepDesc::FullScan(Some(discarded), false) if discarded.column_name == "dinero")));
+        let desc = explain("EXPLAIN SELECT * FROM personas WHERE dinero > 180;");
+        assert!(matches!(desc, PlanStepDesc::Filter(scan) if matches!(&*scan, PlanStepDesc::RangeScan(range) if range.column_name == "dinero")));
+
+        //Index hints are not overridden
+        let desc = explain("EXPLAIN SELECT * FROM personas /*+ INDEX(estado) */ WHERE estado == 1;");
+        assert!(matches!(desc, PlanStepDesc::Filter(scan) if matches!(*scan, PlanStepDesc::SecondaryExactExactScan(_, _))));
+
+        let mut rows = simple_db.execute_sql(&transaction.context(), "SELECT * FROM personas WHERE estado == 1;").data();
+        let mut n_rows = 0;
+        while rows.next().unwrap().is_some() {
+            n_rows += 1;
+        }
+        assert_eq!(n_rows, 18);
+        transaction.commit().unwrap();
+    }
+
+    #[test]
+    fn index_hints() {
+        let simple_db = TestSimpleDb::create("index_hints");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64, nombre VARCHAR);");
+        simple_db.execute_sql(&context, "CREATE INDEX ON personas (dinero);");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, dinero, nombre) VALUES (1, 20, \"Jaime\");");
+
+        let transaction = simple_db.begin(&context).unwrap();
+        let explain = |query: &str| match simple_db.execute(&transaction.context(), simple_db.parse(query).unwrap()) {
+            Ok(StatementResult::Data(desc, _)) => Ok(desc),
+            Ok(_) => panic!(),
+            Err(error) => Err(error),
+        };
+
+        let desc = explain("EXPLAIN SELECT * FROM personas /*+ NO_INDEX */ WHERE dinero > 10;").unwrap();
+        assert!(matches!(desc, PlanStepDesc::Filter(scan) if matches!(*scan, PlanStepDesc::FullScan(None, false))));
+
+        let desc = explain("EXPLAIN SELECT * FROM personas /*+ INDEX(dinero) */ WHERE dinero > 10 AND id > 0;").unwrap();
+        assert!(matches!(desc, PlanStepDesc::Filter(scan) if matches!(&*scan, PlanStepDesc::RangeScan(range) if range.column_name == "dinero")));
+
+        assert!(matches!(explain("EXPLAIN SELECT * FROM personas /*+ INDEX(nombre) */ WHERE nombre == \"Jaime\";"), Err(SimpleDbError::IndexNotFound(_))));
+        assert!(matches!(explain("EXPLAIN SELECT * FROM personas /*+ INDEX(apellido) */ WHERE dinero > 10;"), Err(SimpleDbError::ColumnNotFound(_))));
+        assert!(matches!(explain("EXPLAIN SELECT * FROM personas /*+ INDEX(dinero) */ WHERE id > 10;"), Err(SimpleDbError::MalformedQuery(_))));
+    }
+
+    #[test]
+    fn composite_index_hint() {
+        let simple_db = TestSimpleDb::create("composite_index_hint");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64, nombre VARCHAR);");
+        simple_db.execute_sql(&context, "CREATE INDEX ON personas (nombre, dinero);");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, dinero, nombre) VALUES (1, 20, \"Jaime\");");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, dinero, nombre) VALUES (2, 30, \"Jaime\");");
+
+        let transaction = simple_db.begin(&context).unwrap();
+        let explain = |query: &str| match simple_db.execute(&transaction.context(), simple_db.parse(query).unwrap()) {
+            Ok(StatementResult::Data(desc, _)) => Ok(desc),
+            Ok(_) => panic!(),
+            Err(error) => Err(error),
+        };
+
+        //Without the hint the primary key would be used
+        let desc = explain("EXPLAIN SELECT * FROM personas /*+ INDEX(nombre, dinero) */ WHERE id == 1 AND nombre == \"Jaime\" AND dinero == 20;").unwrap();
+        assert!(matches!(desc, PlanStepDesc::Filter(scan) if matches!(&*scan, PlanStepDesc::CompositeScan(column_names, _) if column_names.len() == 2)));
+
+        let rows = simple_db.execute_sql(&transaction.context(), "SELECT * FROM personas /*+ INDEX(nombre, dinero) */ WHERE nombre == \"Jaime\" AND dinero == 30;")
+            .data().all().unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].get_column_value("id").unwrap().get_i64().unwrap(), 2);
+
+        //The columns must be in the order of the index, and its first column must be constrained
+        assert!(matches!(explain("EXPLAIN SELECT * FROM personas /*+ INDEX(dinero, nombre) */ WHERE nombre == \"Jaime\";"), Err(SimpleDbError::MalformedQuery(_))));
+        assert!(matches!(explain("EXPLAIN SELECT * FROM personas /*+ INDEX(nombre, dinero) */ WHERE dinero == 20;"), Err(SimpleDbError::MalformedQuery(_))));
+    }
+}
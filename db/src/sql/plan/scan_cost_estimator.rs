@@ -0,0 +1,100 @@
+use crate::sql::parser::expression::Expression;
+use crate::sql::plan::scan_type::{DiscardedIndexScan, RangeScan, ScanType};
+use crate::table::schema::Column;
+use crate::table::table::Table;
+use shared::{SimpleDbError, SimpleDbOptions};
+use std::sync::Arc;
+
+//Secondary index scans read the primary row of every indexed entry found, which is more expensive
+//than reading the rows one after the other in a full scan. If the scan is estimated to read a big
+//fraction of the table (see SimpleDbOptions::index_scan_max_selectivity), a full scan is preferred
+pub struct ScanCostEstimator {
+    options: Arc<SimpleDbOptions>,
+    table: Arc<Table>,
+}
+
+impl ScanCostEstimator {
+    pub fn create(
+        options: Arc<SimpleDbOptions>,
+        table: Arc<Table>
+    ) -> ScanCostEstimator {
+        ScanCostEstimator { options, table }
+    }
+
+    //Returns Some if a full scan is expected to be cheaper than the secondary index scan
+    //Only secondary exact and range scans are considered
+    pub fn get_cheaper_full_scan(&self, scan_type: &ScanType) -> Result<Option<DiscardedIndexScan>, SimpleDbError> {
+        let (column_name, estimated_selectivity) = match scan_type {
+            ScanType::ExactSecondary(column_name, _) => {
+                let column = self.table.get_schema().get_column_or_err(column_name)?;
+                (column_name, self.estimate_exact_selectivity(&column)?)
+            },
+            ScanType::Range(range) if self.table.get_schema().is_secondary_indexed(&range.column_name) => {
+                let column = self.table.get_schema().get_column_or_err(&range.column_name)?;
+                (&range.column_name, self.estimate_range_selectivity(&column, range)?)
+            },
+            _ => return Ok(None),
+        };
+
+        match (estimated_selectivity, self.table.estimate_n_rows()?) {
+            (Some(estimated_selectivity), Some(estimated_n_rows))
+                if estimated_n_rows > 0 && estimated_selectivity > self.options.index_scan_max_selectivity => {
+                Ok(Some(DiscardedIndexScan {
+                    column_name: column_name.clone(),
+                    estimated_selectivity,
+                    estimated_n_rows,
+                }))
+            },
+            _ => Ok(None),
+        }
+    }
+
+    //Assumes values are uniformly distributed
+    fn estimate_exact_selectivity(&self, column: &Column) -> Result<Option<f64>, SimpleDbError> {
+        if self.table.is_unique_index(column) {
+            return Ok(None);
+        }
+
+        match self.table.estimate_n_distinct_values(column)? {
+            Some(n_distinct_values) if n_distinct_values > 0 => Ok(Some(1.0 / n_distinct_values as f64)),
+            _ => Ok(None),
+        }
+    }
+
+    //The range is compared against the smallest and biggest indexed values, assuming they are uniformly distributed
+    //Only numbers can be estimated
+    fn estimate_range_selectivity(&self, column: &Column, range: &RangeScan) -> Result<Option<f64>, SimpleDbError> {
+        if !column.column_type.is_number() {
+            return Ok(None);
+        }
+        let (min, max) = match self.table.get_indexed_values_range(column)? {
+            Some((min, max)) => (min.get_f64()?, max.get_f64()?),
+            None => return Ok(None),
+        };
+        let start = match range.start.as_ref().map(|start| Self::get_number(start)) {
+            Some(Some(start)) => start.max(min),
+            Some(None) => return Ok(None),
+            None => min,
+        };
+        let end = match range.end.as_ref().map(|end| Self::get_number(end)) {
+            Some(Some(end)) => end.min(max),
+            Some(None) => return Ok(None),
+            None => max,
+        };
+
+        if start > end {
+            Ok(Some(0.0))
+        } else if max <= min {
+            Ok(Some(1.0))
+        } else {
+            Ok(Some((end - start) / (max - min)))
+        }
+    }
+
+    fn get_number(expression: &Expression) -> Option<f64> {
+        match expression {
+            Expression::Literal(value) if value.is_number() => value.get_f64().ok(),
+            _ => None,
+        }
+    }
+}
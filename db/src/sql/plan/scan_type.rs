@@ -1,5 +1,5 @@
 use crate::sql::parser::expression::Expression;
-use shared::{SimpleDbError, Value};
+use shared::{KeyspaceId, SimpleDbError, Value};
 use SimpleDbError::MalformedQuery;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -17,6 +17,29 @@ pub enum ScanType {
 
     // min < values < expression
     Range(RangeScan),
+
+    //Only produced for the whole WHERE expression, it won't be merged with other scan types
+    Composite(CompositeScan),
+
+    //Produced by the planner instead of a secondary index scan that is estimated to read too many rows
+    //Only produced for the whole WHERE expression, it won't be merged with other scan types
+    FullByCost(DiscardedIndexScan),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscardedIndexScan {
+    pub column_name: String,
+    pub estimated_selectivity: f64, //Fraction of the table rows that the index scan would read
+    pub estimated_n_rows: usize,
+}
+
+//Lookup of the rows whose leading columns of a composite index are equal to values.
+//values might be shorter than the columns of the index
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompositeScan {
+    pub keyspace_id: KeyspaceId,
+    pub column_names: Vec<String>,
+    pub values: Vec<Value>, //Coerced to the column types
 }
 
 #[derive(Debug, Clone, PartialEq)]
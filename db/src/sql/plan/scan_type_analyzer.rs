@@ -1,7 +1,7 @@
-use crate::sql::parser::expression::{BinaryOperator, Expression};
-use crate::sql::plan::scan_type::{RangeScan, ScanType};
-use crate::table::schema::Schema;
-use shared::SimpleDbError;
+use crate::sql::parser::expression::{BinaryOperator, Expression, UnaryOperator};
+use crate::sql::plan::scan_type::{CompositeScan, RangeScan, ScanType};
+use crate::table::schema::{CompositeIndex, Schema};
+use shared::{SimpleDbError, Value};
 use shared::SimpleDbError::MalformedQuery;
 
 pub struct ScanTypeAnalyzer {
@@ -22,11 +22,151 @@ impl ScanTypeAnalyzer {
     }
 
     pub fn analyze(&self) -> Result<ScanType, SimpleDbError> {
+        let composite_scan = self.get_composite_scan();
+        let is_primary_constrained = self.is_primary_constrained();
+
+        match composite_scan {
+            //Exact primary lookups return at most one row
+            Some(composite_scan) if composite_scan.values.len() > 1 && !is_primary_constrained => {
+                Ok(ScanType::Composite(composite_scan))
+            },
+            Some(composite_scan) => {
+                match self.analyze_expression()? {
+                    ScanType::Full => Ok(ScanType::Composite(composite_scan)),
+                    scan_type => Ok(scan_type),
+                }
+            },
+            None => self.analyze_expression(),
+        }
+    }
+
+    //Used by the INDEX(column) query hint. Composite indexes are ignored and the scan is restricted
+    //to the index of the column. Returns an error if the expression cannot be resolved by that index
+    pub fn analyze_with_index(&self, column_name: &str) -> Result<ScanType, SimpleDbError> {
+        let scan_type = self.analyze_expression()?;
+        match self.restrict_to_index(scan_type, column_name) {
+            Some(scan_type) => Ok(scan_type),
+            None => Err(MalformedQuery(format!("Index of {} cannot be used by the WHERE expression", column_name))),
+        }
+    }
+
+    //Used by the INDEX(column, ...) query hint when it names a composite index. Returns an error if the
+    //first column of the index is not constrained by an equality joined with AND
+    pub fn analyze_with_composite_index(&self, composite_index: &CompositeIndex) -> Result<ScanType, SimpleDbError> {
+        let mut equalities = Vec::new();
+        Self::get_and_equalities(&self.expression, &mut equalities);
+        let composite_scan = self.get_composite_index_scan(composite_index, &equalities);
+
+        if composite_scan.values.is_empty() {
+            let column_names = self.schema.get_composite_index_columns(composite_index).into_iter()
+                .map(|column| column.column_name)
+                .collect::<Vec<String>>();
+            return Err(MalformedQuery(format!("Index of {} cannot be used by the WHERE expression", column_names.join(", "))));
+        }
+
+        Ok(ScanType::Composite(composite_scan))
+    }
+
+    fn restrict_to_index(&self, scan_type: ScanType, column_name: &str) -> Option<ScanType> {
+        match scan_type {
+            ScanType::ExactPrimary(_) if self.schema.get_primary_column().column_name.eq(column_name) => Some(scan_type),
+            ScanType::ExactSecondary(ref scan_column_name, _) if scan_column_name.eq(column_name) => Some(scan_type),
+            ScanType::Range(ref range) if range.column_name.eq(column_name) => Some(scan_type),
+            //The rows of the other side will be discarded by the filter step
+            ScanType::MergeIntersection(left, right) => {
+                match (self.restrict_to_index(*left, column_name), self.restrict_to_index(*right, column_name)) {
+                    (Some(left), Some(right)) => Some(ScanType::MergeIntersection(Box::new(left), Box::new(right))),
+                    (Some(scan_type), None) | (None, Some(scan_type)) => Some(scan_type),
+                    (None, None) => None,
+                }
+            },
+            ScanType::MergeUnion(left, right) => {
+                let left = self.restrict_to_index(*left, column_name)?;
+                let right = self.restrict_to_index(*right, column_name)?;
+                Some(ScanType::MergeUnion(Box::new(left), Box::new(right)))
+            },
+            _ => None,
+        }
+    }
+
+    fn is_primary_constrained(&self) -> bool {
+        let mut equalities = Vec::new();
+        Self::get_and_equalities(&self.expression, &mut equalities);
+        let primary_column_name = self.schema.get_primary_column().column_name;
+        equalities.iter().any(|(column_name, _)| column_name.eq(&primary_column_name))
+    }
+
+    //Returns the composite index with the most leading columns constrained by equalities joined with AND
+    fn get_composite_scan(&self) -> Option<CompositeScan> {
+        let mut equalities = Vec::new();
+        Self::get_and_equalities(&self.expression, &mut equalities);
+        let mut best_composite_scan: Option<CompositeScan> = None;
+
+        for composite_index in self.schema.get_composite_indexes() {
+            let composite_scan = self.get_composite_index_scan(&composite_index, &equalities);
+            let n_best_values = best_composite_scan.as_ref().map_or(0, |scan| scan.values.len());
+            if composite_scan.values.len() > n_best_values {
+                best_composite_scan = Some(composite_scan);
+            }
+        }
+
+        best_composite_scan
+    }
+
+    //Uses the leading columns of the composite index that are constrained by the equalities
+    fn get_composite_index_scan(&self, composite_index: &CompositeIndex, equalities: &[(String, Value)]) -> CompositeScan {
+        let mut composite_scan = CompositeScan {
+            keyspace_id: composite_index.keyspace_id,
+            column_names: Vec::new(),
+            values: Vec::new(),
+        };
+
+        for column in self.schema.get_composite_index_columns(composite_index) {
+            let value = equalities.iter()
+                .find(|(column_name, _)| column_name.eq(&column.column_name))
+                .and_then(|(_, value)| value.coerce(&column.column_type));
+
+            match value {
+                Some(value) => {
+                    composite_scan.column_names.push(column.column_name);
+                    composite_scan.values.push(value);
+                },
+                None => break,
+            }
+        }
+
+        composite_scan
+    }
+
+    //Collects the column == literal expressions that must be true for the expression to be true
+    fn get_and_equalities(expression: &Expression, equalities: &mut Vec<(String, Value)>) {
+        match expression {
+            Expression::Binary(BinaryOperator::And, left, right) => {
+                Self::get_and_equalities(left, equalities);
+                Self::get_and_equalities(right, equalities);
+            },
+            Expression::Binary(BinaryOperator::Equal, left, right) => {
+                if let (Expression::Identifier(_, column_name), Expression::Literal(value)) = (left.as_ref(), right.as_ref()) {
+                    if !value.is_null() {
+                        equalities.push((column_name.clone(), value.clone()));
+                    }
+                }
+            },
+            _ => {}
+        }
+    }
+
+    fn analyze_expression(&self) -> Result<ScanType, SimpleDbError> {
         match &self.expression {
             Expression::Binary(operator, left, right) => {
                 self.get_scan_type_binary_expr(*operator, &left, &right)
             },
+            Expression::Unary(UnaryOperator::IsNull, _) |
+            Expression::Unary(UnaryOperator::IsNotNull, _) => Ok(ScanType::Full),
+            //The scan of the negated expression is not inverted
+            Expression::Unary(UnaryOperator::Not, _) => Ok(ScanType::Full),
             Expression::Unary(_, _) => Err(MalformedQuery(String::from("Illegal unary expression"))),
+            Expression::Function(_, _) => Ok(ScanType::Full),
             _ => Err(MalformedQuery(String::from("Illegal literal expression"))),
         }
     }
@@ -37,6 +177,11 @@ impl ScanTypeAnalyzer {
         left: &Box<Expression>,
         right: &Box<Expression>,
     ) -> Result<ScanType, SimpleDbError> {
+        //Indexes store the column values, not the values produced by a function
+        if matches!(left.as_ref(), Expression::Function(_, _)) && !operator.is_logical() {
+            return Ok(ScanType::Full);
+        }
+
         match operator {
             BinaryOperator::And => {
                 self.get_scan_type_logical_expr(operator, left, right)
@@ -47,7 +192,8 @@ impl ScanTypeAnalyzer {
             BinaryOperator::Add |
             BinaryOperator::Subtract |
             BinaryOperator::Multiply |
-            BinaryOperator::Divide => {
+            BinaryOperator::Divide |
+            BinaryOperator::Modulo => {
                 Ok(ScanType::Full)
             },
             BinaryOperator::Equal => {
@@ -92,8 +238,66 @@ impl ScanTypeAnalyzer {
                 } else {
                     Ok(ScanType::Full)
                 }
+            },
+            BinaryOperator::Like => {
+                self.get_scan_type_like_expr(left, right)
+            },
+            //Indexes are sorted by the case sensitive value
+            BinaryOperator::ILike => Ok(ScanType::Full),
+        }
+    }
+
+    //LIKE "prefix%" on an indexed column can be scanned with the range [prefix, prefix++)
+    //Patterns starting with a wildcard will need a full scan
+    fn get_scan_type_like_expr(
+        &self,
+        left: &Box<Expression>,
+        right: &Box<Expression>,
+    ) -> Result<ScanType, SimpleDbError> {
+        let column_name = match left.get_identifier() {
+            Ok(column_name) => column_name,
+            Err(_) => return Ok(ScanType::Full),
+        };
+        let is_indexed = column_name.eq(&self.schema.get_primary_column().column_name) ||
+            self.schema.is_secondary_indexed(&column_name);
+        if !is_indexed || !right.is_literal() || right.is_null() {
+            return Ok(ScanType::Full);
+        }
+
+        let prefix = right.get_value()?.get_like_pattern_prefix()?;
+        if prefix.is_empty() {
+            return Ok(ScanType::Full);
+        }
+
+        Ok(ScanType::Range(RangeScan {
+            end: Self::next_string_prefix(&prefix)
+                .map(|end| Expression::Literal(Value::create_string(end))),
+            start: Some(Expression::Literal(Value::create_string(prefix))),
+            start_inclusive: true,
+            end_inclusive: false,
+            column_name,
+        }))
+    }
+
+    //Returns the smallest string bigger than all the strings starting with prefix.
+    //Returns None if there is no such string
+    fn next_string_prefix(prefix: &str) -> Option<String> {
+        let mut chars: Vec<char> = prefix.chars().collect();
+
+        while let Some(last_char) = chars.pop() {
+            let next_char = match last_char as u32 + 1 {
+                //Skip surrogates, they cannot be represented as chars
+                0xD800 => Some('\u{E000}'),
+                next_char => char::from_u32(next_char),
+            };
+
+            if let Some(next_char) = next_char {
+                chars.push(next_char);
+                return Some(chars.into_iter().collect());
             }
         }
+
+        None
     }
 
     fn get_scan_type_logical_expr(
@@ -303,6 +507,10 @@ impl ScanTypeAnalyzer {
                     Ok(ScanType::MergeUnion(Box::new(a.clone()), Box::new(b.clone())))
                 }
             },
+            (ScanType::Composite(_), _) |
+            (_, ScanType::Composite(_)) |
+            (ScanType::FullByCost(_), _) |
+            (_, ScanType::FullByCost(_)) => panic!("Illegal code path"),
             //Range rules
             (ScanType::Range(_), ScanType::Range(_)) => {
                 if matches!(binary_operator, BinaryOperator::And) {
@@ -322,7 +530,7 @@ impl ScanTypeAnalyzer {
             self.schema.clone(),
         );
 
-        analyzer.analyze()
+        analyzer.analyze_expression()
     }
 }
 
@@ -331,10 +539,10 @@ mod test {
     use shared::Value;
     use crate::sql::parser::expression::Expression;
     use crate::sql::parser::parser::Parser;
-    use crate::sql::plan::scan_type::ScanType;
+    use crate::sql::plan::scan_type::{CompositeScan, RangeScan, ScanType};
     use crate::sql::plan::scan_type::ScanType::{ExactPrimary, ExactSecondary, MergeUnion};
     use crate::sql::plan::scan_type_analyzer::ScanTypeAnalyzer;
-    use crate::table::schema::{Column, Schema};
+    use crate::table::schema::{Column, CompositeIndex, Schema};
 
     #[test]
     fn compound_2() {
@@ -380,6 +588,21 @@ mod test {
         assert_eq!(result, ScanType::Full);
     }
 
+    #[test]
+    fn functions() {
+        let schema = Schema::create(vec![
+            Column::create_primary("id"),
+            Column::create_secondary("nombre", 1)
+        ]);
+
+        let result = ScanTypeAnalyzer::create(parse("UPPER(nombre) == \"A\""), schema.clone()).analyze().unwrap();
+        assert_eq!(result, ScanType::Full);
+        let result = ScanTypeAnalyzer::create(parse("ABS(id) > 1"), schema.clone()).analyze().unwrap();
+        assert_eq!(result, ScanType::Full);
+        let result = ScanTypeAnalyzer::create(parse("LENGTH(nombre) == 1 AND id == 2"), schema.clone()).analyze().unwrap();
+        assert_eq!(result, ScanType::ExactPrimary(Expression::Literal(Value::create_i64(2))));
+    }
+
     //Expect merge union
     #[test]
     fn primary_or_secondary() {
@@ -514,6 +737,135 @@ mod test {
         assert_eq!(result, ScanType::Full);
     }
 
+    #[test]
+    fn like_prefix() {
+        let schema = Schema::create(vec![
+            Column::create_primary("id"),
+            Column::create_secondary("nombre", 1),
+            Column::create("apellido", 2),
+        ]);
+
+        let result = ScanTypeAnalyzer::create(parse("nombre LIKE \"Jai%\""), schema.clone()).analyze().unwrap();
+        assert_eq!(result, ScanType::Range(RangeScan {
+            column_name: String::from("nombre"),
+            start: Some(Expression::Literal(Value::create_string(String::from("Jai")))),
+            start_inclusive: true,
+            end: Some(Expression::Literal(Value::create_string(String::from("Jaj")))),
+            end_inclusive: false,
+        }));
+
+        //Escaped wildcards are part of the prefix
+        let result = ScanTypeAnalyzer::create(parse("nombre LIKE \"J\\_a%\""), schema.clone()).analyze().unwrap();
+        assert_eq!(result, ScanType::Range(RangeScan {
+            column_name: String::from("nombre"),
+            start: Some(Expression::Literal(Value::create_string(String::from("J_a")))),
+            start_inclusive: true,
+            end: Some(Expression::Literal(Value::create_string(String::from("J_b")))),
+            end_inclusive: false,
+        }));
+
+        let result = ScanTypeAnalyzer::create(parse("nombre LIKE \"%Jai\""), schema.clone()).analyze().unwrap();
+        assert_eq!(result, ScanType::Full);
+        let result = ScanTypeAnalyzer::create(parse("nombre LIKE \"_ai%\""), schema.clone()).analyze().unwrap();
+        assert_eq!(result, ScanType::Full);
+        let result = ScanTypeAnalyzer::create(parse("apellido LIKE \"Pol%\""), schema.clone()).analyze().unwrap();
+        assert_eq!(result, ScanType::Full);
+    }
+
+    #[test]
+    fn in_list() {
+        let schema = Schema::create(vec![
+            Column::create_primary("id"),
+            Column::create_secondary("dinero", 1),
+            Column::create("credito", 2),
+        ]);
+
+        let result = ScanTypeAnalyzer::create(parse("id IN (1, 2, 3)"), schema.clone()).analyze().unwrap();
+        assert_eq!(result, MergeUnion(
+            Box::new(ExactPrimary(Expression::Literal(Value::create_i64(1)))),
+            Box::new(MergeUnion(
+                Box::new(ExactPrimary(Expression::Literal(Value::create_i64(2)))),
+                Box::new(ExactPrimary(Expression::Literal(Value::create_i64(3)))),
+            )),
+        ));
+
+        let result = ScanTypeAnalyzer::create(parse("dinero IN (1, 2)"), schema.clone()).analyze().unwrap();
+        assert_eq!(result, MergeUnion(
+            Box::new(ExactSecondary(String::from("dinero"), Expression::Literal(Value::create_i64(1)))),
+            Box::new(ExactSecondary(String::from("dinero"), Expression::Literal(Value::create_i64(2)))),
+        ));
+
+        let result = ScanTypeAnalyzer::create(parse("credito IN (1, 2)"), schema.clone()).analyze().unwrap();
+        assert_eq!(result, ScanType::Full);
+    }
+
+    #[test]
+    fn is_null() {
+        let schema = Schema::create(vec![
+            Column::create_primary("id"),
+            Column::create_secondary("dinero", 1),
+        ]);
+
+        let result = ScanTypeAnalyzer::create(parse("dinero IS NULL"), schema.clone()).analyze().unwrap();
+        assert_eq!(result, ScanType::Full);
+        let result = ScanTypeAnalyzer::create(parse("dinero IS NOT NULL AND id == 1"), schema.clone()).analyze().unwrap();
+        assert_eq!(result, ExactPrimary(Expression::Literal(Value::create_i64(1))));
+    }
+
+    #[test]
+    fn not() {
+        let schema = Schema::create(vec![
+            Column::create_primary("id"),
+            Column::create_secondary("nombre", 1),
+        ]);
+
+        let result = ScanTypeAnalyzer::create(parse("NOT id == 1"), schema.clone()).analyze().unwrap();
+        assert_eq!(result, ScanType::Full);
+        let result = ScanTypeAnalyzer::create(parse("NOT nombre > \"J\" AND id > 1"), schema.clone()).analyze().unwrap();
+        assert_eq!(result, ScanType::Range(RangeScan {
+            column_name: String::from("id"),
+            start: Some(Expression::Literal(Value::create_i64(1))),
+            start_inclusive: false,
+            end: None,
+            end_inclusive: false,
+        }));
+    }
+
+    #[test]
+    fn composite_index() {
+        let schema = Schema::create(vec![
+            Column::create_primary("id"),
+            Column::create("apellido", 1),
+            Column::create("nombre", 2),
+            Column::create_secondary("dinero", 3),
+        ]);
+        schema.add_composite_index(CompositeIndex { keyspace_id: 5, columns_id: vec![1, 2] });
+
+        //Prefix only
+        let result = ScanTypeAnalyzer::create(parse("apellido == 1 AND dinero > 10"), schema.clone()).analyze().unwrap();
+        assert!(matches!(result, ScanType::Range(_)));
+        let result = ScanTypeAnalyzer::create(parse("apellido == 1 AND nombre > 10"), schema.clone()).analyze().unwrap();
+        assert_eq!(result, ScanType::Composite(CompositeScan {
+            keyspace_id: 5,
+            column_names: vec![String::from("apellido")],
+            values: vec![Value::create_i64(1)],
+        }));
+        //Full key
+        let result = ScanTypeAnalyzer::create(parse("nombre == 2 AND dinero == 3 AND apellido == 1"), schema.clone()).analyze().unwrap();
+        assert_eq!(result, ScanType::Composite(CompositeScan {
+            keyspace_id: 5,
+            column_names: vec![String::from("apellido"), String::from("nombre")],
+            values: vec![Value::create_i64(1), Value::create_i64(2)],
+        }));
+        //Leading column not constrained
+        let result = ScanTypeAnalyzer::create(parse("nombre == 2"), schema.clone()).analyze().unwrap();
+        assert_eq!(result, ScanType::Full);
+        let result = ScanTypeAnalyzer::create(parse("apellido == 1 OR nombre == 2"), schema.clone()).analyze().unwrap();
+        assert_eq!(result, ScanType::Full);
+        let result = ScanTypeAnalyzer::create(parse("apellido == 1 AND nombre == 2 AND id == 3"), schema.clone()).analyze().unwrap();
+        assert_eq!(result, ScanType::ExactPrimary(Expression::Literal(Value::create_i64(3))));
+    }
+
     fn parse(query: &str) -> Expression {
         let mut parser = Parser::create(query.to_string());
         parser.parse_expression().unwrap()
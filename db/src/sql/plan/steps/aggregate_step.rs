@@ -0,0 +1,270 @@
+use crate::sql::plan::plan_step::{PlanStep, PlanStepDesc, PlanStepTrait};
+use crate::table::record::Record;
+use crate::table::row::RowIterator;
+use crate::sql::cancellation_token::{check_cancelled, CancellationToken};
+use crate::table::selection::{Aggregate, AggregateFunction, Selection};
+use crate::{Row, Schema};
+use bytes::Bytes;
+use shared::{ColumnId, SimpleDbError, Value};
+use std::collections::HashSet;
+
+//Consumes all the rows of the source and returns only one row, which will contain the result of the aggregates.
+//The returned row columns ID will be the index of the aggregate in the selection.
+#[derive(Clone)]
+pub struct AggregateStep {
+    pub(crate) aggregates: Vec<Aggregate>,
+    pub(crate) source: PlanStep,
+    pub(crate) cancellation_token: Option<CancellationToken>,
+
+    output_schema: Schema,
+    aggregated: bool,
+}
+
+//Holds the partial results of a list of aggregates
+#[derive(Clone)]
+pub(crate) struct AggregatesAccumulator {
+    results: Vec<Value>,
+    n_values: Vec<i64>, //Nº of non-null values aggregated
+    distinct_values: Vec<HashSet<Bytes>>, //Only used by COUNT(DISTINCT)
+}
+
+impl AggregateStep {
+    pub(crate) fn create(
+        selection: &Selection,
+        table_schema: &Schema,
+        source: PlanStep,
+    ) -> AggregateStep {
+        let aggregates = match selection {
+            Selection::Aggregate(_, aggregates) => aggregates.clone(),
+            _ => panic!("Illegal code path")
+        };
+
+        AggregateStep {
+            output_schema: Schema::create(selection.get_aggregate_columns(table_schema)),
+            aggregated: false,
+            cancellation_token: None,
+            aggregates,
+            source,
+        }
+    }
+}
+
+impl PlanStepTrait for AggregateStep {
+    fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
+        if self.aggregated {
+            return Ok(None);
+        }
+
+        let mut accumulator = AggregatesAccumulator::create(&self.aggregates);
+        while let Some(row) = self.source.next()? {
+            check_cancelled(&self.cancellation_token)?;
+            accumulator.add_row(&self.aggregates, &row)?;
+        }
+
+        self.aggregated = true;
+
+        let values = accumulator.build(&self.aggregates)?;
+        Ok(Some(build_row(values, &self.output_schema)))
+    }
+
+    fn desc(&self) -> PlanStepDesc {
+        PlanStepDesc::Aggregate(
+            self.aggregates.clone(),
+            Box::new(self.source.desc())
+        )
+    }
+}
+
+impl AggregatesAccumulator {
+    pub(crate) fn create(aggregates: &Vec<Aggregate>) -> AggregatesAccumulator {
+        AggregatesAccumulator {
+            results: vec![Value::create_null(); aggregates.len()],
+            n_values: vec![0; aggregates.len()],
+            distinct_values: vec![HashSet::new(); aggregates.len()],
+        }
+    }
+
+    pub(crate) fn add_row(
+        &mut self,
+        aggregates: &Vec<Aggregate>,
+        row: &Row,
+    ) -> Result<(), SimpleDbError> {
+        for (index, aggregate) in aggregates.iter().enumerate() {
+            let value = match &aggregate.column_name {
+                Some(column_name) => row.get_column_value(column_name)?,
+                None => {
+                    //COUNT(*)
+                    self.n_values[index] += 1;
+                    continue;
+                }
+            };
+
+            if value.is_null() {
+                continue;
+            }
+
+            self.n_values[index] += 1;
+            let current_result = &self.results[index];
+
+            self.results[index] = match aggregate.function {
+                AggregateFunction::Count => continue,
+                AggregateFunction::CountDistinct => {
+                    self.distinct_values[index].insert(value.get_bytes().clone());
+                    self.n_values[index] = self.distinct_values[index].len() as i64;
+                    continue
+                },
+                AggregateFunction::Sum |
+                AggregateFunction::Avg => {
+                    if current_result.is_null() {
+                        //Adding it to 0 will convert the value to I64 or F64
+                        Value::create_i64(0).add(&value)?
+                    } else {
+                        current_result.add(&value)?
+                    }
+                },
+                AggregateFunction::Min => {
+                    if current_result.is_null() || value.lt(current_result) { value } else { continue }
+                },
+                AggregateFunction::Max => {
+                    if current_result.is_null() || value.gt(current_result) { value } else { continue }
+                }
+            };
+        }
+
+        Ok(())
+    }
+
+    //Returns the aggregates results in the same order as aggregates
+    pub(crate) fn build(self, aggregates: &Vec<Aggregate>) -> Result<Vec<Value>, SimpleDbError> {
+        let mut values = Vec::with_capacity(aggregates.len());
+
+        for (index, (aggregate, result)) in aggregates.iter().zip(self.results).enumerate() {
+            values.push(match aggregate.function {
+                AggregateFunction::Count |
+                AggregateFunction::CountDistinct => Value::create_i64(self.n_values[index]),
+                AggregateFunction::Avg => {
+                    if self.n_values[index] > 0 {
+                        Value::create_f64(result.get_f64()? / self.n_values[index] as f64)
+                    } else {
+                        Value::create_null()
+                    }
+                },
+                AggregateFunction::Sum |
+                AggregateFunction::Min |
+                AggregateFunction::Max => result,
+            });
+        }
+
+        Ok(values)
+    }
+}
+
+//Values will be stored with its index as the column ID. Null values won't be stored.
+pub(crate) fn build_row(values: Vec<Value>, output_schema: &Schema) -> Row {
+    let mut record_builder = Record::builder();
+
+    for (index, value) in values.into_iter().enumerate() {
+        if !value.is_null() {
+            record_builder.add_column(index as ColumnId, value.get_bytes().clone());
+        }
+    }
+
+    Row::create(record_builder.build(), Value::create_null(), output_schema.clone())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::sql::plan::plan_step::{MockStep, PlanStep, PlanStepTrait};
+    use crate::sql::plan::steps::aggregate_step::AggregateStep;
+    use crate::table::record::Record;
+    use crate::table::selection::{Aggregate, AggregateFunction, Selection};
+    use crate::{Column, Row, Schema};
+    use bytes::Bytes;
+    use shared::Value;
+
+    #[test]
+    fn aggregate() {
+        let schema = Schema::create(vec![
+            Column::create_primary("id"),
+            Column::create("dinero", 1),
+        ]);
+        let mut step = AggregateStep::create(&all_aggregates("dinero"), &schema, PlanStep::Mock(MockStep::create(false, vec![
+            row(&schema, 1, Some(10)),
+            row(&schema, 2, None),
+            row(&schema, 3, Some(30)),
+            row(&schema, 4, Some(5)),
+        ])));
+
+        let row = step.next().unwrap().unwrap();
+        assert_eq!(row.get_column_value("COUNT(*)").unwrap().get_i64().unwrap(), 4);
+        assert_eq!(row.get_column_value("COUNT(dinero)").unwrap().get_i64().unwrap(), 3);
+        assert_eq!(row.get_column_value("SUM(dinero)").unwrap().get_i64().unwrap(), 45);
+        assert_eq!(row.get_column_value("AVG(dinero)").unwrap().get_f64().unwrap(), 15.0);
+        assert_eq!(row.get_column_value("MIN(dinero)").unwrap().get_i64().unwrap(), 5);
+        assert_eq!(row.get_column_value("MAX(dinero)").unwrap().get_i64().unwrap(), 30);
+        assert!(step.next().unwrap().is_none());
+    }
+
+    #[test]
+    fn count_distinct() {
+        let schema = Schema::create(vec![
+            Column::create_primary("id"),
+            Column::create("dinero", 1),
+        ]);
+        let selection = Selection::Aggregate(vec![], vec![
+            Aggregate { function: AggregateFunction::CountDistinct, column_name: Some(String::from("dinero")) },
+            Aggregate { function: AggregateFunction::Count, column_name: Some(String::from("dinero")) },
+        ]);
+        let mut step = AggregateStep::create(&selection, &schema, PlanStep::Mock(MockStep::create(false, vec![
+            row(&schema, 1, Some(10)),
+            row(&schema, 2, None),
+            row(&schema, 3, Some(30)),
+            row(&schema, 4, Some(10)),
+            row(&schema, 5, None),
+            row(&schema, 6, Some(10)),
+        ])));
+
+        let row = step.next().unwrap().unwrap();
+        assert_eq!(row.get_column_value("COUNT(DISTINCT dinero)").unwrap().get_i64().unwrap(), 2);
+        assert_eq!(row.get_column_value("COUNT(dinero)").unwrap().get_i64().unwrap(), 4);
+        assert!(step.next().unwrap().is_none());
+    }
+
+    #[test]
+    fn aggregate_empty() {
+        let schema = Schema::create(vec![
+            Column::create_primary("id"),
+            Column::create("dinero", 1),
+        ]);
+        let mut step = AggregateStep::create(&all_aggregates("dinero"), &schema, PlanStep::Mock(MockStep::create(false, vec![])));
+
+        let row = step.next().unwrap().unwrap();
+        assert_eq!(row.get_column_value("COUNT(*)").unwrap().get_i64().unwrap(), 0);
+        assert_eq!(row.get_column_value("COUNT(dinero)").unwrap().get_i64().unwrap(), 0);
+        assert!(row.get_column_value("SUM(dinero)").unwrap().is_null());
+        assert!(row.get_column_value("AVG(dinero)").unwrap().is_null());
+        assert!(row.get_column_value("MIN(dinero)").unwrap().is_null());
+        assert!(row.get_column_value("MAX(dinero)").unwrap().is_null());
+        assert!(step.next().unwrap().is_none());
+    }
+
+    fn all_aggregates(column_name: &str) -> Selection {
+        Selection::Aggregate(vec![], vec![
+            Aggregate { function: AggregateFunction::Count, column_name: None },
+            Aggregate { function: AggregateFunction::Count, column_name: Some(column_name.to_string()) },
+            Aggregate { function: AggregateFunction::Sum, column_name: Some(column_name.to_string()) },
+            Aggregate { function: AggregateFunction::Avg, column_name: Some(column_name.to_string()) },
+            Aggregate { function: AggregateFunction::Min, column_name: Some(column_name.to_string()) },
+            Aggregate { function: AggregateFunction::Max, column_name: Some(column_name.to_string()) },
+        ])
+    }
+
+    fn row(schema: &Schema, id: i64, dinero: Option<i64>) -> Row {
+        let mut record_builder = Record::builder();
+        record_builder.add_column(0, Bytes::from(id.to_le_bytes().to_vec()));
+        if let Some(dinero) = dinero {
+            record_builder.add_column(1, Bytes::from(dinero.to_le_bytes().to_vec()));
+        }
+        Row::create(record_builder.build(), Value::create_i64(id), schema.clone())
+    }
+}
@@ -0,0 +1,98 @@
+use crate::sql::plan::plan_step::{PlanStep, PlanStepDesc, PlanStepTrait};
+use crate::table::row::RowIterator;
+use crate::Row;
+use shared::SimpleDbError;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+//Used by EXPLAIN ANALYZE. Wraps a step to measure the nº of rows it emits and the time spent in its next() calls,
+//which includes the time spent by its sources.
+//Stats are shared between clones, as some steps like FullSortStep iterate a clone of its source.
+#[derive(Clone)]
+pub struct AnalyzeStep {
+    pub(crate) source: PlanStep,
+    stats: Arc<AnalyzeStepStats>,
+}
+
+struct AnalyzeStepStats {
+    n_rows: AtomicUsize,
+    elapsed_nanos: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StepStats {
+    pub n_rows: usize, //Nº of rows emitted by the step
+    pub elapsed: Duration,
+}
+
+impl AnalyzeStep {
+    pub(crate) fn create(source: PlanStep) -> AnalyzeStep {
+        AnalyzeStep {
+            stats: Arc::new(AnalyzeStepStats {
+                n_rows: AtomicUsize::new(0),
+                elapsed_nanos: AtomicU64::new(0),
+            }),
+            source,
+        }
+    }
+
+    pub(crate) fn get_stats(&self) -> StepStats {
+        StepStats {
+            n_rows: self.stats.n_rows.load(Ordering::Relaxed),
+            elapsed: Duration::from_nanos(self.stats.elapsed_nanos.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl PlanStepTrait for AnalyzeStep {
+    fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
+        let start = Instant::now();
+        let next_row = self.source.next();
+        self.stats.elapsed_nanos.fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+
+        if let Ok(Some(_)) = &next_row {
+            self.stats.n_rows.fetch_add(1, Ordering::Relaxed);
+        }
+
+        next_row
+    }
+
+    fn desc(&self) -> PlanStepDesc {
+        PlanStepDesc::Analyzed(self.get_stats(), Box::new(self.source.desc()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::PlanStepDesc;
+    use crate::simple_db::{Context, StatementResult};
+    use crate::test_utils::TestSimpleDb;
+
+    #[test]
+    fn explain_analyze() {
+        let simple_db = TestSimpleDb::create("explain_analyze");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64);");
+        for id in 1..=10 {
+            simple_db.execute_in_transaction(&context, &format!("INSERT INTO personas (id, dinero) VALUES ({}, {});", id, id * 10));
+        }
+
+        let transaction = simple_db.begin(&context).unwrap();
+        let desc = match simple_db.execute(&transaction.context(), simple_db.parse("EXPLAIN ANALYZE SELECT * FROM personas WHERE dinero > 70;").unwrap()) {
+            Ok(StatementResult::Data(desc, _)) => desc,
+            _ => panic!(),
+        };
+
+        let (filter_stats, filter_source) = match desc {
+            PlanStepDesc::Analyzed(stats, filter) => match *filter {
+                PlanStepDesc::Filter(source) => (stats, source),
+                _ => panic!(),
+            },
+            _ => panic!(),
+        };
+        assert_eq!(filter_stats.n_rows, 3);
+        assert!(matches!(*filter_source, PlanStepDesc::Analyzed(scan_stats, scan)
+            if scan_stats.n_rows == 10 && matches!(*scan, PlanStepDesc::FullScan(None, false))));
+    }
+}
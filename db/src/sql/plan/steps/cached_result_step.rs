@@ -0,0 +1,31 @@
+use crate::sql::plan::plan_step::{PlanStep, PlanStepDesc, PlanStepTrait};
+use crate::Row;
+use shared::SimpleDbError;
+use std::collections::VecDeque;
+
+//Returns the rows of a query served from the SelectCache, so nothing is read from storage.
+//desc() returns the description of the plan whose result was cached, source won't be iterated
+#[derive(Clone)]
+pub struct CachedResultStep {
+    rows: VecDeque<Row>,
+    source: PlanStep,
+}
+
+impl CachedResultStep {
+    pub(crate) fn create(rows: Vec<Row>, source: PlanStep) -> CachedResultStep {
+        CachedResultStep {
+            rows: VecDeque::from(rows),
+            source,
+        }
+    }
+}
+
+impl PlanStepTrait for CachedResultStep {
+    fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
+        Ok(self.rows.pop_front())
+    }
+
+    fn desc(&self) -> PlanStepDesc {
+        self.source.desc()
+    }
+}
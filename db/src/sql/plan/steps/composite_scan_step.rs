@@ -0,0 +1,70 @@
+use crate::index::composite_key;
+use crate::index::secondary_index_iterator::SecondaryIndexIterator;
+use crate::sql::plan::plan_step::{PlanStepDesc, PlanStepTrait};
+use crate::sql::plan::scan_type::CompositeScan;
+use crate::table::selection::Selection;
+use crate::table::table::Table;
+use crate::Row;
+use bytes::Bytes;
+use shared::SimpleDbError;
+use std::sync::Arc;
+use storage::transactions::transaction::Transaction;
+use storage::SimpleDbStorageIterator;
+
+//Returns the rows whose composite index key starts with the encoded values of the scan.
+//Rows are always read from the table, as the index key might not contain all the selected columns
+#[derive(Clone)]
+pub struct CompositeScanStep {
+    pub(crate) secondary_index_iterator: SecondaryIndexIterator<SimpleDbStorageIterator>,
+    pub(crate) transaction: Transaction,
+    pub(crate) selection: Selection,
+    pub(crate) table: Arc<Table>,
+    pub(crate) scan: CompositeScan,
+    pub(crate) key_prefix: Bytes,
+}
+
+impl CompositeScanStep {
+    pub(crate) fn create(
+        table: Arc<Table>,
+        scan: CompositeScan,
+        transaction: &Transaction,
+        selection: Selection
+    ) -> Result<CompositeScanStep, SimpleDbError> {
+        let key_prefix = composite_key::encode(&scan.values)?;
+        let secondary_index_iterator = table.scan_from_key_composite_index(
+            &key_prefix,
+            transaction,
+            scan.keyspace_id
+        )?;
+
+        Ok(CompositeScanStep {
+            transaction: transaction.clone(),
+            secondary_index_iterator,
+            key_prefix,
+            selection,
+            table,
+            scan,
+        })
+    }
+}
+
+impl PlanStepTrait for CompositeScanStep {
+    fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
+        while let Some((secondary_indexed_value, primary_key)) = self.secondary_index_iterator.next() {
+            if !secondary_indexed_value.as_bytes().starts_with(&self.key_prefix) {
+                return Ok(None);
+            }
+
+            //The row might have been deleted
+            if let Some(row) = self.table.get_by_primary_column(primary_key.as_bytes(), &self.transaction, &self.selection)? {
+                return Ok(Some(row));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn desc(&self) -> PlanStepDesc {
+        PlanStepDesc::CompositeScan(self.scan.column_names.clone(), self.scan.values.clone())
+    }
+}
@@ -0,0 +1,158 @@
+use crate::sql::plan::plan_step::{PlanStep, PlanStepDesc, PlanStepTrait};
+use crate::table::row::RowIterator;
+use crate::sql::cancellation_token::{check_cancelled, CancellationToken};
+use crate::table::selection::Selection;
+use crate::Row;
+use bytes::BufMut;
+use crate::sql::execution::expression_evaluator::evaluate_expression;
+use shared::{SimpleDbError, Value};
+use std::collections::HashSet;
+
+//Removes the rows whose selected columns have the same values as a previous row.
+//If the source is sorted by the selected columns, duplicated rows will be consecutive, so only the
+//last row needs to be kept. Otherwise, the values of every returned row are kept in memory
+#[derive(Clone)]
+pub struct DistinctStep {
+    pub(crate) source: PlanStep,
+    pub(crate) selection: Selection,
+    pub(crate) is_source_sorted: bool,
+    pub(crate) cancellation_token: Option<CancellationToken>,
+
+    pub(crate) seen: HashSet<Vec<u8>>,
+    pub(crate) last: Option<Vec<u8>>,
+}
+
+impl DistinctStep {
+    pub(crate) fn create(
+        selection: Selection,
+        is_source_sorted: bool,
+        source: PlanStep,
+    ) -> DistinctStep {
+        DistinctStep {
+            seen: HashSet::new(),
+            last: None,
+            cancellation_token: None,
+            is_source_sorted,
+            selection,
+            source,
+        }
+    }
+
+    fn selected_values(&self, row: &Row) -> Result<Vec<u8>, SimpleDbError> {
+        let selected_values = match &self.selection {
+            Selection::Some(column_names) => column_names.iter()
+                .map(|column_name| row.get_column_value(column_name))
+                .collect::<Result<Vec<Value>, SimpleDbError>>()?,
+            //Rows are projected after removing duplicates, so the expressions have to be evaluated
+            Selection::Expressions(expressions) => expressions.iter()
+                .map(|(_, expression)| evaluate_expression(row, expression)?.get_value())
+                .collect::<Result<Vec<Value>, SimpleDbError>>()?,
+            _ => row.schema.get_columns().into_iter()
+                .map(|column| row.get_column_value(&column.column_name))
+                .collect::<Result<Vec<Value>, SimpleDbError>>()?
+        };
+
+        let mut values = Vec::new();
+        for value in selected_values {
+            values.put_u8(value.is_null() as u8);
+            values.put_u32_le(value.get_bytes().len() as u32);
+            values.extend(value.get_bytes());
+        }
+
+        Ok(values)
+    }
+}
+
+impl PlanStepTrait for DistinctStep {
+    fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
+        while let Some(next_row) = self.source.next()? {
+            check_cancelled(&self.cancellation_token)?;
+            let values = self.selected_values(&next_row)?;
+
+            let is_duplicated = if self.is_source_sorted {
+                let is_duplicated = self.last.as_ref() == Some(&values);
+                self.last = Some(values);
+                is_duplicated
+            } else {
+                !self.seen.insert(values)
+            };
+
+            if !is_duplicated {
+                return Ok(Some(next_row));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn desc(&self) -> PlanStepDesc {
+        PlanStepDesc::Distinct(self.is_source_sorted, Box::new(self.source.desc()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::sql::plan::plan_step::{MockStep, PlanStep, PlanStepTrait};
+    use crate::sql::plan::steps::distinct_step::DistinctStep;
+    use crate::table::record::Record;
+    use crate::table::selection::Selection;
+    use crate::{Column, Row, Schema};
+    use crate::simple_db::Context;
+    use crate::test_utils::TestSimpleDb;
+    use bytes::Bytes;
+    use shared::Value;
+
+    #[test]
+    fn distinct() {
+        let schema = Schema::create(vec![
+            Column::create_primary("id"),
+            Column::create("dinero", 1),
+        ]);
+        let rows = vec![row(&schema, 1, Some(10)), row(&schema, 2, None), row(&schema, 3, Some(10)), row(&schema, 4, None), row(&schema, 5, Some(0))];
+        let selection = Selection::Some(vec![String::from("dinero")]);
+
+        let mut step = DistinctStep::create(selection, false, PlanStep::Mock(MockStep::create(false, rows)));
+        assert_eq!(step.next().unwrap().unwrap().get_primary_column_value().get_i64().unwrap(), 1);
+        assert_eq!(step.next().unwrap().unwrap().get_primary_column_value().get_i64().unwrap(), 2);
+        assert_eq!(step.next().unwrap().unwrap().get_primary_column_value().get_i64().unwrap(), 5);
+        assert!(step.next().unwrap().is_none());
+    }
+
+    fn row(schema: &Schema, id: i64, dinero: Option<i64>) -> Row {
+        let mut record_builder = Record::builder();
+        record_builder.add_column(0, Bytes::from(id.to_le_bytes().to_vec()));
+        if let Some(dinero) = dinero {
+            record_builder.add_column(1, Bytes::from(dinero.to_le_bytes().to_vec()));
+        }
+        Row::create(record_builder.build(), Value::create_i64(id), schema.clone())
+    }
+
+    #[test]
+    fn select_distinct() {
+        let simple_db = TestSimpleDb::create("select_distinct");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR);");
+        simple_db.execute_sql(&context, "CREATE INDEX ON personas (nombre);");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, nombre) VALUES (1, \"Jaime\");");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, nombre) VALUES (2, \"Pedro\");");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, nombre) VALUES (3, \"Jaime\");");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, nombre) VALUES (4, \"Ana\");");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, nombre) VALUES (5, \"Pedro\");");
+        let select = |query: &str| {
+            let mut transaction = simple_db.begin(&context).unwrap();
+            let rows = simple_db.execute(&transaction.context(), simple_db.parse(query).unwrap()).unwrap().data().all().unwrap();
+            transaction.commit().unwrap();
+            rows.iter()
+                .map(|row| row.get_column_value("nombre").unwrap().get_string().unwrap())
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(select("SELECT DISTINCT nombre FROM personas;"), vec!["Jaime", "Pedro", "Ana"]);
+        assert_eq!(select("SELECT DISTINCT nombre FROM personas ORDER BY nombre ASC;"), vec!["Ana", "Jaime", "Pedro"]);
+        assert_eq!(select("SELECT DISTINCT nombre FROM personas ORDER BY nombre DESC LIMIT 2;"), vec!["Pedro", "Jaime"]);
+        //Rows with the same nombre are not consecutive
+        assert_eq!(select("SELECT DISTINCT nombre FROM personas ORDER BY id ASC, nombre ASC;"), vec!["Jaime", "Pedro", "Ana"]);
+        assert_eq!(select("SELECT DISTINCT nombre FROM personas ORDER BY nombre ASC, id ASC;"), vec!["Ana", "Jaime", "Pedro"]);
+        assert_eq!(select("SELECT nombre FROM personas WHERE id > 2;").len(), 3);
+    }
+}
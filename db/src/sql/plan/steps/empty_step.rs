@@ -0,0 +1,23 @@
+use crate::sql::plan::plan_step::{PlanStepDesc, PlanStepTrait};
+use crate::Row;
+use shared::SimpleDbError;
+
+//Used instead of a scan when the WHERE expression is always false, so nothing is read from storage
+#[derive(Clone)]
+pub struct EmptyStep {}
+
+impl EmptyStep {
+    pub(crate) fn create() -> EmptyStep {
+        EmptyStep {}
+    }
+}
+
+impl PlanStepTrait for EmptyStep {
+    fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
+        Ok(None)
+    }
+
+    fn desc(&self) -> PlanStepDesc {
+        PlanStepDesc::Empty
+    }
+}
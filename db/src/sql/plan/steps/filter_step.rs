@@ -4,11 +4,13 @@ use crate::Row;
 use shared::SimpleDbError;
 use crate::sql::execution::expression_evaluator::evaluate_where_expression;
 use crate::table::row::RowIterator;
+use crate::sql::cancellation_token::{check_cancelled, CancellationToken};
 
 #[derive(Clone)]
 pub struct FilterStep {
     pub(crate) filter_expression: Expression,
     pub(crate) source: PlanStep,
+    pub(crate) cancellation_token: Option<CancellationToken>,
 }
 
 impl FilterStep {
@@ -17,6 +19,7 @@ impl FilterStep {
         source: PlanStep,
     ) -> FilterStep {
         FilterStep {
+            cancellation_token: None,
             filter_expression,
             source
         }
@@ -26,6 +29,7 @@ impl FilterStep {
 impl PlanStepTrait for FilterStep {
     fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
         while let Some(next_row) = self.source.next()? {
+            check_cancelled(&self.cancellation_token)?;
             if evaluate_where_expression(&next_row, &self.filter_expression)? {
                 return Ok(Some(next_row));
             }
@@ -37,4 +41,64 @@ impl PlanStepTrait for FilterStep {
     fn desc(&self) -> PlanStepDesc {
         PlanStepDesc::Filter(Box::new(self.source.desc()))
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod test {
+    use crate::sql::parser::expression::Expression;
+    use crate::sql::parser::parser::Parser;
+    use crate::sql::plan::plan_step::{MockStep, PlanStep, PlanStepTrait};
+    use crate::sql::plan::steps::filter_step::FilterStep;
+    use crate::table::record::Record;
+    use crate::{Column, Row, Schema};
+    use crate::CancellationToken;
+    use bytes::Bytes;
+    use shared::{SimpleDbError, Value};
+
+    #[test]
+    fn is_null() {
+        let schema = Schema::create(vec![
+            Column::create_primary("id"),
+            Column::create("dinero", 1),
+        ]);
+        let rows = vec![row(&schema, 1, Some(10)), row(&schema, 2, None), row(&schema, 3, Some(0)), row(&schema, 4, None)];
+
+        let mut step = FilterStep::create(parse("dinero IS NULL"), PlanStep::Mock(MockStep::create(false, rows.clone())));
+        assert_eq!(step.next().unwrap().unwrap().get_primary_column_value().get_i64().unwrap(), 2);
+        assert_eq!(step.next().unwrap().unwrap().get_primary_column_value().get_i64().unwrap(), 4);
+        assert!(step.next().unwrap().is_none());
+
+        let mut step = FilterStep::create(parse("dinero IS NOT NULL"), PlanStep::Mock(MockStep::create(false, rows.clone())));
+        assert_eq!(step.next().unwrap().unwrap().get_primary_column_value().get_i64().unwrap(), 1);
+        assert_eq!(step.next().unwrap().unwrap().get_primary_column_value().get_i64().unwrap(), 3);
+        assert!(step.next().unwrap().is_none());
+    }
+
+    #[test]
+    fn cancelled() {
+        let schema = Schema::create(vec![
+            Column::create_primary("id"),
+            Column::create("dinero", 1),
+        ]);
+        let rows = vec![row(&schema, 1, Some(10)), row(&schema, 2, None), row(&schema, 3, Some(0))];
+        let cancellation_token = CancellationToken::create();
+
+        //No row matches, but the filter stops once the token is cancelled
+        let mut step = FilterStep::create(parse("dinero > 100"), PlanStep::Mock(MockStep::create(false, rows.clone())));
+        step.cancellation_token = Some(cancellation_token.clone());
+        cancellation_token.cancel();
+        assert!(matches!(step.next(), Err(SimpleDbError::QueryTimeout(_))));
+    }
+
+    fn parse(expression: &str) -> Expression {
+        Parser::create(expression.to_string()).parse_expression().unwrap()
+    }
+
+    fn row(schema: &Schema, id: i64, dinero: Option<i64>) -> Row {
+        let mut record_builder = Record::builder();
+        record_builder.add_column(0, Bytes::from(id.to_le_bytes().to_vec()));
+        if let Some(dinero) = dinero {
+            record_builder.add_column(1, Bytes::from(dinero.to_le_bytes().to_vec()));
+        }
+        Row::create(record_builder.build(), Value::create_i64(id), schema.clone())
+    }
+}
@@ -7,20 +7,40 @@ use std::sync::Arc;
 use storage::transactions::transaction::Transaction;
 use storage::SimpleDbStorageIterator;
 use crate::sql::plan::plan_step::{PlanStepDesc, PlanStepTrait};
+use crate::sql::plan::scan_type::DiscardedIndexScan;
 
 #[derive(Clone)]
 pub struct FullScanStep {
     pub(crate) iterator: TableIterator<SimpleDbStorageIterator>,
+    pub(crate) discarded_index_scan: Option<DiscardedIndexScan>,
+    pub(crate) is_reverse: bool,
 }
 
 impl FullScanStep {
     pub(crate) fn create(
         table: Arc<Table>,
         selection: Selection,
-        transaction: &Transaction
+        transaction: &Transaction,
+        discarded_index_scan: Option<DiscardedIndexScan>,
     ) -> Result<FullScanStep, SimpleDbError> {
         Ok(FullScanStep {
-            iterator: table.scan_all(transaction, &selection)?
+            iterator: table.scan_all(transaction, &selection)?,
+            is_reverse: false,
+            discarded_index_scan,
+        })
+    }
+
+    //Rows are read from the end of the table, so they will be returned in descending primary key order
+    pub(crate) fn create_reverse(
+        table: Arc<Table>,
+        selection: Selection,
+        transaction: &Transaction,
+        discarded_index_scan: Option<DiscardedIndexScan>,
+    ) -> Result<FullScanStep, SimpleDbError> {
+        Ok(FullScanStep {
+            iterator: table.scan_all_backwards(transaction, &selection)?,
+            is_reverse: true,
+            discarded_index_scan,
         })
     }
 }
@@ -35,6 +55,6 @@ impl PlanStepTrait for FullScanStep {
     }
 
     fn desc(&self) -> PlanStepDesc {
-        PlanStepDesc::FullScan
+        PlanStepDesc::FullScan(self.discarded_index_scan.clone(), self.is_reverse)
     }
 }
\ No newline at end of file
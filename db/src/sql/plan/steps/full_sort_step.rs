@@ -68,4 +68,110 @@ impl PlanStepTrait for FullSortStep {
     fn desc(&self) -> PlanStepDesc {
         PlanStepDesc::FullSort(self.sort.clone(), Box::new(self.source.desc()))
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use crate::simple_db::Context;
+    use crate::table::row::RowIterator;
+    use crate::test_utils::TestSimpleDb;
+
+    #[test]
+    fn select_order_by_nulls() {
+        let simple_db = TestSimpleDb::create("select_order_by_nulls");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64);");
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        simple_db.execute_sql(&transaction.context(), "INSERT INTO personas (id, dinero) VALUES (1, 30);");
+        simple_db.execute_sql(&transaction.context(), "INSERT INTO personas (id) VALUES (2);");
+        simple_db.execute_sql(&transaction.context(), "INSERT INTO personas (id, dinero) VALUES (3, 10);");
+        simple_db.execute_sql(&transaction.context(), "INSERT INTO personas (id) VALUES (4);");
+        simple_db.execute_sql(&transaction.context(), "INSERT INTO personas (id, dinero) VALUES (5, 20);");
+
+        let query_ids = |query: &str| -> Vec<i64> {
+            let mut rows = simple_db.execute(&transaction.context(), simple_db.parse(query).unwrap()).unwrap().data();
+            let mut ids = Vec::new();
+            while let Some(row) = rows.next().unwrap() {
+                ids.push(row.get_column_value("id").unwrap().get_i64().unwrap());
+            }
+            ids
+        };
+
+        assert_eq!(query_ids("SELECT * FROM personas ORDER BY dinero ASC;"), vec![3, 5, 1, 2, 4]);
+        assert_eq!(query_ids("SELECT * FROM personas ORDER BY dinero DESC;"), vec![2, 4, 1, 5, 3]);
+        assert_eq!(query_ids("SELECT * FROM personas ORDER BY dinero ASC NULLS FIRST;"), vec![2, 4, 3, 5, 1]);
+        assert_eq!(query_ids("SELECT * FROM personas ORDER BY dinero DESC NULLS LAST;"), vec![1, 5, 3, 2, 4]);
+        assert_eq!(query_ids("SELECT * FROM personas ORDER BY dinero NULLS FIRST LIMIT 3;"), vec![2, 4, 3]);
+        transaction.commit().unwrap();
+    }
+
+    #[test]
+    fn select_order_by_multiple_columns() {
+        let simple_db = TestSimpleDb::create("select_order_by_multiple_columns");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, estado I64, dinero I64);");
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        for (id, estado, dinero) in [(1, 2, 10), (2, 1, 30), (3, 2, 30), (4, 1, 10), (5, 2, 20), (6, 1, 30)] {
+            let insert = format!("INSERT INTO personas (id, estado, dinero) VALUES ({}, {}, {});", id, estado, dinero);
+            simple_db.execute(&transaction.context(), simple_db.parse(&insert).unwrap()).unwrap();
+        }
+
+        let query_ids = |query: &str| -> Vec<i64> {
+            let mut rows = simple_db.execute(&transaction.context(), simple_db.parse(query).unwrap()).unwrap().data();
+            let mut ids = Vec::new();
+            while let Some(row) = rows.next().unwrap() {
+                ids.push(row.get_column_value("id").unwrap().get_i64().unwrap());
+            }
+            ids
+        };
+
+        //Ties of the first column are sorted by the next ones
+        assert_eq!(query_ids("SELECT * FROM personas ORDER BY estado ASC, dinero DESC, id ASC;"), vec![2, 6, 4, 3, 5, 1]);
+        assert_eq!(query_ids("SELECT * FROM personas ORDER BY dinero DESC, estado ASC, id DESC;"), vec![6, 2, 3, 5, 4, 1]);
+        //Sorted with limit
+        assert_eq!(query_ids("SELECT * FROM personas ORDER BY estado DESC, dinero ASC LIMIT 2;"), vec![1, 5]);
+        assert_eq!(query_ids("SELECT * FROM personas ORDER BY dinero ASC, id DESC LIMIT 3 OFFSET 1;"), vec![1, 5, 6]);
+        transaction.commit().unwrap();
+    }
+
+    #[test]
+    fn in_memory_sort() {
+        for (in_memory_sort_max_bytes, uses_temporary_files) in [(1048576, false), (0, true)] {
+            let simple_db = TestSimpleDb::create_with_options("in_memory_sort", {
+                let mut options_builder = shared::start_simpledb_options_builder();
+                options_builder.in_memory_sort_max_bytes(in_memory_sort_max_bytes);
+                options_builder
+            });
+            let context = Context::create_with_database("test");
+            simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64);");
+            for id in 1..=10 {
+                simple_db.execute_in_transaction(&context, &format!("INSERT INTO personas (id, dinero) VALUES ({}, {});", id, (id * 7) % 11));
+            }
+
+            let mut transaction = simple_db.begin(&context).unwrap();
+            let mut rows = simple_db.execute_sql(&transaction.context(), "SELECT * FROM personas ORDER BY dinero DESC;")
+                .data();
+            let mut dinero_values = Vec::new();
+            while let Some(row) = rows.next().unwrap() {
+                dinero_values.push(row.get_column_value("dinero").unwrap().get_i64().unwrap());
+            }
+            assert_eq!(dinero_values, vec![10, 9, 8, 7, 6, 5, 4, 3, 2, 1]);
+
+            //The sorted rows are still being iterated, so temporary files haven't been deleted yet
+            let n_temporary_spaces = std::fs::read_dir(simple_db.path.join("test").join("tmp")).unwrap().count();
+            assert_eq!(n_temporary_spaces > 0, uses_temporary_files);
+            drop(rows);
+
+            //The primary column is not selected, but it is needed to read back the rows written to temporary files
+            let rows = simple_db.execute_sql(&transaction.context(), "SELECT dinero FROM personas ORDER BY dinero ASC;")
+                .data().all().unwrap();
+            let dinero_values: Vec<i64> = rows.iter()
+                .map(|row| row.get_column_value("dinero").unwrap().get_i64().unwrap())
+                .collect();
+            assert_eq!(dinero_values, vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+            transaction.commit().unwrap();
+        }
+    }
+}
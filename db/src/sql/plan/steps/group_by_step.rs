@@ -0,0 +1,344 @@
+use crate::sql::plan::plan_step::{PlanStep, PlanStepDesc, PlanStepTrait};
+use crate::sql::plan::steps::aggregate_step::{build_row, AggregatesAccumulator};
+use crate::table::row::RowIterator;
+use crate::sql::cancellation_token::{check_cancelled, CancellationToken};
+use crate::table::selection::{Aggregate, Selection};
+use crate::{Row, Schema};
+use bytes::{BufMut, Bytes};
+use shared::{SimpleDbError, Value};
+use std::collections::HashMap;
+use std::collections::VecDeque;
+
+//Buckets the rows of the source by the values of the group by columns.
+//Returns one row per bucket, with the selected group by columns followed by the aggregates results.
+//Buckets are returned in the same order as they were first found in the source.
+//If the source is sorted by one of the group by columns, rows with the same value of that column are contiguous,
+//so the buckets are emitted as soon as its value changes, instead of holding all of them until the source is consumed.
+#[derive(Clone)]
+pub struct GroupByStep {
+    pub(crate) group_by: Vec<String>,
+    pub(crate) aggregates: Vec<Aggregate>,
+    pub(crate) source: PlanStep,
+    pub(crate) cancellation_token: Option<CancellationToken>,
+
+    selected_columns: Vec<String>,
+    output_schema: Schema,
+    sorted_column: Option<String>, //Group by column by which the source is sorted
+
+    grouped_rows: VecDeque<Row>,
+    groups: Vec<Group>,
+    group_index_by_key: HashMap<Bytes, usize>,
+    current_sorted_value: Option<Bytes>,
+    source_finished: bool,
+    max_groups_held: usize, //Max nº of buckets that have been held in memory at the same time
+}
+
+#[derive(Clone)]
+struct Group {
+    key_values: Vec<Value>, //Values of the selected group by columns
+    accumulator: AggregatesAccumulator,
+}
+
+impl GroupByStep {
+    pub(crate) fn create(
+        selection: &Selection,
+        group_by: Vec<String>,
+        table_schema: &Schema,
+        source: PlanStep,
+    ) -> GroupByStep {
+        let (selected_columns, aggregates) = match selection {
+            Selection::Aggregate(columns, aggregates) => (columns.clone(), aggregates.clone()),
+            _ => panic!("Illegal code path")
+        };
+
+        let mut group_by_step = GroupByStep {
+            output_schema: Schema::create(selection.get_aggregate_columns(table_schema)),
+            grouped_rows: VecDeque::new(),
+            groups: Vec::new(),
+            group_index_by_key: HashMap::new(),
+            current_sorted_value: None,
+            source_finished: false,
+            max_groups_held: 0,
+            sorted_column: None,
+            cancellation_token: None,
+            selected_columns,
+            aggregates,
+            group_by,
+            source,
+        };
+        group_by_step.sorted_column = group_by_step.get_sorted_column(table_schema);
+        group_by_step
+    }
+
+    //Has to be called when the source is replaced, as it might produce rows in a different order
+    pub(crate) fn set_source(&mut self, source: PlanStep, table_schema: &Schema) {
+        self.source = source;
+        self.sorted_column = self.get_sorted_column(table_schema);
+    }
+
+    pub(crate) fn is_streaming(&self) -> bool {
+        self.sorted_column.is_some()
+    }
+
+    fn get_sorted_column(&self, table_schema: &Schema) -> Option<String> {
+        self.source.get_column_sorted(table_schema)
+            .filter(|sorted_column| self.group_by.contains(sorted_column))
+    }
+
+    //Reads rows from the source until some buckets can be emitted or the source is consumed
+    fn group_next_rows(&mut self) -> Result<(), SimpleDbError> {
+        while let Some(row) = self.source.next()? {
+            check_cancelled(&self.cancellation_token)?;
+            let sorted_value_changed = self.update_sorted_value(&row)?;
+            if sorted_value_changed {
+                self.flush_groups()?;
+            }
+
+            self.add_row(&row)?;
+
+            if sorted_value_changed && !self.grouped_rows.is_empty() {
+                return Ok(());
+            }
+        }
+
+        self.source_finished = true;
+        self.flush_groups()
+    }
+
+    //Returns true if the value of the sorted column is different from the one of the previous row
+    fn update_sorted_value(&mut self, row: &Row) -> Result<bool, SimpleDbError> {
+        let sorted_column = match &self.sorted_column {
+            Some(sorted_column) => sorted_column,
+            None => return Ok(false)
+        };
+        let sorted_value = row.get_column_value(sorted_column)?.get_bytes().clone();
+        let changed = self.current_sorted_value.as_ref().map_or(false, |current| current.ne(&sorted_value));
+        self.current_sorted_value = Some(sorted_value);
+        Ok(changed)
+    }
+
+    fn add_row(&mut self, row: &Row) -> Result<(), SimpleDbError> {
+        let group_key = self.group_key(row)?;
+
+        let group_index = match self.group_index_by_key.get(&group_key) {
+            Some(group_index) => *group_index,
+            None => {
+                self.groups.push(Group {
+                    key_values: self.selected_values(row)?,
+                    accumulator: AggregatesAccumulator::create(&self.aggregates),
+                });
+                self.group_index_by_key.insert(group_key, self.groups.len() - 1);
+                self.max_groups_held = self.max_groups_held.max(self.groups.len());
+                self.groups.len() - 1
+            }
+        };
+
+        self.groups[group_index].accumulator.add_row(&self.aggregates, row)
+    }
+
+    fn flush_groups(&mut self) -> Result<(), SimpleDbError> {
+        self.group_index_by_key.clear();
+
+        for group in std::mem::take(&mut self.groups) {
+            let mut values = group.key_values;
+            values.extend(group.accumulator.build(&self.aggregates)?);
+            self.grouped_rows.push_back(build_row(values, &self.output_schema));
+        }
+
+        Ok(())
+    }
+
+    //Every value is prefixed with its length, so different values cannot produce the same key.
+    //Null values are encoded with a length of u32::MAX
+    fn group_key(&self, row: &Row) -> Result<Bytes, SimpleDbError> {
+        let mut group_key = Vec::new();
+
+        for column_name in &self.group_by {
+            let value = row.get_column_value(column_name)?;
+            if value.is_null() {
+                group_key.put_u32_le(u32::MAX);
+            } else {
+                group_key.put_u32_le(value.get_bytes().len() as u32);
+                group_key.extend_from_slice(value.get_bytes());
+            }
+        }
+
+        Ok(Bytes::from(group_key))
+    }
+
+    fn selected_values(&self, row: &Row) -> Result<Vec<Value>, SimpleDbError> {
+        let mut values = Vec::with_capacity(self.selected_columns.len());
+        for column_name in &self.selected_columns {
+            values.push(row.get_column_value(column_name)?);
+        }
+        Ok(values)
+    }
+}
+
+impl PlanStepTrait for GroupByStep {
+    fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
+        loop {
+            if let Some(row) = self.grouped_rows.pop_front() {
+                return Ok(Some(row));
+            }
+            if self.source_finished {
+                return Ok(None);
+            }
+
+            self.group_next_rows()?;
+        }
+    }
+
+    fn desc(&self) -> PlanStepDesc {
+        PlanStepDesc::GroupBy(
+            self.group_by.clone(),
+            self.aggregates.clone(),
+            self.is_streaming(),
+            Box::new(self.source.desc())
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::sql::plan::plan_step::{MockStep, PlanStep, PlanStepTrait};
+    use crate::sql::plan::steps::group_by_step::GroupByStep;
+    use crate::table::record::Record;
+    use crate::table::selection::{Aggregate, AggregateFunction, Selection};
+    use crate::{Column, Row, Schema};
+    use crate::simple_db::Context;
+    use crate::table::row::RowIterator;
+    use crate::test_utils::TestSimpleDb;
+    use bytes::Bytes;
+    use shared::{SimpleDbError, Type, Value};
+
+    #[test]
+    fn group_by() {
+        let schema = Schema::create(vec![
+            Column::create_primary("id"),
+            Column { column_id: 1, column_type: Type::String, column_name: String::from("nombre"), is_primary: false, secondary_index_keyspace_id: None },
+            Column::create("dinero", 2),
+        ]);
+        let selection = Selection::Aggregate(vec![String::from("nombre")], vec![
+            Aggregate { function: AggregateFunction::Count, column_name: None },
+            Aggregate { function: AggregateFunction::Sum, column_name: Some(String::from("dinero")) },
+        ]);
+        let mut step = GroupByStep::create(&selection, vec![String::from("nombre")], &schema, PlanStep::Mock(MockStep::create(false, vec![
+            row(&schema, 1, Some("jaime"), 10),
+            row(&schema, 2, Some("molon"), 5),
+            row(&schema, 3, Some("jaime"), 20),
+            row(&schema, 4, None, 1),
+            row(&schema, 5, None, 2),
+        ])));
+
+        let row = step.next().unwrap().unwrap();
+        assert_eq!(row.get_column_value("nombre").unwrap().get_string().unwrap(), "jaime");
+        assert_eq!(row.get_column_value("COUNT(*)").unwrap().get_i64().unwrap(), 2);
+        assert_eq!(row.get_column_value("SUM(dinero)").unwrap().get_i64().unwrap(), 30);
+
+        let row = step.next().unwrap().unwrap();
+        assert_eq!(row.get_column_value("nombre").unwrap().get_string().unwrap(), "molon");
+        assert_eq!(row.get_column_value("COUNT(*)").unwrap().get_i64().unwrap(), 1);
+        assert_eq!(row.get_column_value("SUM(dinero)").unwrap().get_i64().unwrap(), 5);
+
+        let row = step.next().unwrap().unwrap();
+        assert!(row.get_column_value("nombre").unwrap().is_null());
+        assert_eq!(row.get_column_value("COUNT(*)").unwrap().get_i64().unwrap(), 2);
+        assert_eq!(row.get_column_value("SUM(dinero)").unwrap().get_i64().unwrap(), 3);
+
+        assert!(step.next().unwrap().is_none());
+    }
+
+    #[test]
+    fn group_by_empty() {
+        let schema = Schema::create(vec![
+            Column::create_primary("id"),
+            Column::create("dinero", 1),
+        ]);
+        let selection = Selection::Aggregate(vec![], vec![
+            Aggregate { function: AggregateFunction::Count, column_name: None },
+        ]);
+        let mut step = GroupByStep::create(&selection, vec![String::from("dinero")], &schema, PlanStep::Mock(MockStep::create(false, vec![])));
+
+        assert!(step.next().unwrap().is_none());
+    }
+
+    #[test]
+    fn group_by_sorted_source() {
+        let schema = Schema::create(vec![
+            Column::create_primary("id"),
+            Column::create("dinero", 2),
+        ]);
+        let selection = Selection::Aggregate(vec![String::from("id")], vec![
+            Aggregate { function: AggregateFunction::Sum, column_name: Some(String::from("dinero")) },
+        ]);
+        let rows = vec![
+            row(&schema, 1, None, 10),
+            row(&schema, 1, None, 20),
+            row(&schema, 2, None, 5),
+            row(&schema, 3, None, 1),
+            row(&schema, 3, None, 2),
+        ];
+        let mut streaming_step = GroupByStep::create(&selection, vec![String::from("id")], &schema, PlanStep::Mock(MockStep::create(true, rows.clone())));
+        let mut hash_step = GroupByStep::create(&selection, vec![String::from("id")], &schema, PlanStep::Mock(MockStep::create(false, rows)));
+        assert!(streaming_step.is_streaming());
+        assert!(!hash_step.is_streaming());
+
+        for (expected_id, expected_sum) in [(1, 30), (2, 5), (3, 3)] {
+            let streaming_row = streaming_step.next().unwrap().unwrap();
+            let hash_row = hash_step.next().unwrap().unwrap();
+            assert_eq!(streaming_row.get_column_value("id").unwrap().get_i64().unwrap(), expected_id);
+            assert_eq!(hash_row.get_column_value("id").unwrap().get_i64().unwrap(), expected_id);
+            assert_eq!(streaming_row.get_column_value("SUM(dinero)").unwrap().get_i64().unwrap(), expected_sum);
+            assert_eq!(hash_row.get_column_value("SUM(dinero)").unwrap().get_i64().unwrap(), expected_sum);
+        }
+        assert!(streaming_step.next().unwrap().is_none());
+        assert!(hash_step.next().unwrap().is_none());
+
+        //Only one bucket is held at the same time
+        assert_eq!(streaming_step.max_groups_held, 1);
+        assert_eq!(hash_step.max_groups_held, 3);
+    }
+
+    fn row(schema: &Schema, id: i64, nombre: Option<&str>, dinero: i64) -> Row {
+        let mut record_builder = Record::builder();
+        record_builder.add_column(0, Bytes::from(id.to_le_bytes().to_vec()));
+        if let Some(nombre) = nombre {
+            record_builder.add_column(1, Bytes::from(nombre.as_bytes().to_vec()));
+        }
+        record_builder.add_column(2, Bytes::from(dinero.to_le_bytes().to_vec()));
+        Row::create(record_builder.build(), Value::create_i64(id), schema.clone())
+    }
+
+    #[test]
+    fn select_group_by_order_by() {
+        let simple_db = TestSimpleDb::create("select_group_by_order_by");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, estado I64, dinero I64);");
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        for (id, estado, dinero) in [(1, 2, 10), (2, 1, 30), (3, 3, 30), (4, 1, 10), (5, 2, 20), (6, 3, 5)] {
+            let insert = format!("INSERT INTO personas (id, estado, dinero) VALUES ({}, {}, {});", id, estado, dinero);
+            simple_db.execute(&transaction.context(), simple_db.parse(&insert).unwrap()).unwrap();
+        }
+
+        let query = |query: &str| -> Vec<(i64, i64)> {
+            let mut rows = simple_db.execute(&transaction.context(), simple_db.parse(query).unwrap()).unwrap().data();
+            let mut groups = Vec::new();
+            while let Some(row) = rows.next().unwrap() {
+                groups.push((row.get_column_value("estado").unwrap().get_i64().unwrap(),
+                             row.get_column_value("SUM(dinero)").unwrap().get_i64().unwrap()));
+            }
+            groups
+        };
+
+        assert_eq!(query("SELECT estado, SUM(dinero) FROM personas GROUP BY estado ORDER BY estado DESC;"), vec![(3, 35), (2, 30), (1, 40)]);
+        assert_eq!(query("SELECT estado, SUM(dinero) FROM personas GROUP BY estado ORDER BY estado ASC LIMIT 2;"), vec![(1, 40), (2, 30)]);
+        assert_eq!(query("SELECT estado, SUM(dinero) FROM personas GROUP BY estado ORDER BY estado ASC LIMIT 1 OFFSET 1;"), vec![(2, 30)]);
+
+        //Only the selected group by columns can be sorted
+        let result = simple_db.execute(&transaction.context(), simple_db.parse("SELECT estado, SUM(dinero) FROM personas GROUP BY estado ORDER BY dinero;").unwrap());
+        assert!(matches!(result, Err(SimpleDbError::MalformedQuery(_))));
+        transaction.commit().unwrap();
+    }
+}
@@ -0,0 +1,144 @@
+use crate::index::secondary_index_iterator::SecondaryIndexIterator;
+use crate::sql::plan::plan_step::{PlanStepDesc, PlanStepTrait};
+use crate::sql::plan::scan_type::{RangeKeyPosition, RangeScan};
+use crate::table::row::RowBuilder;
+use crate::table::table::Table;
+use crate::Row;
+use shared::{SimpleDbError, Value};
+use std::sync::Arc;
+use storage::transactions::transaction::Transaction;
+use storage::SimpleDbStorageIterator;
+
+//Used when the selected columns are only the primary column and the scanned secondary indexed column.
+//Both values are stored in the secondary index, so the rows are built without reading the primary keyspace
+#[derive(Clone)]
+pub struct IndexOnlyScanStep {
+    pub(crate) secondary_iterator: SecondaryIndexIterator<SimpleDbStorageIterator>,
+    pub(crate) table: Arc<Table>,
+    pub(crate) transaction: Transaction,
+    pub(crate) column_name: String,
+    pub(crate) lookup: IndexOnlyLookup,
+}
+
+#[derive(Clone)]
+pub enum IndexOnlyLookup {
+    Exact(Value),
+    Range(RangeScan),
+}
+
+impl IndexOnlyScanStep {
+    pub(crate) fn create(
+        table: Arc<Table>,
+        column_name: &str,
+        transaction: &Transaction,
+        lookup: IndexOnlyLookup,
+    ) -> Result<IndexOnlyScanStep, SimpleDbError> {
+        let secondary_iterator = match &lookup {
+            IndexOnlyLookup::Exact(value) => {
+                table.scan_from_key_secondary_index(value.get_bytes(), true, transaction, column_name)?
+            },
+            IndexOnlyLookup::Range(range) => match range.start() {
+                Some(start_range_key_expr) => {
+                    let start_range_key_bytes = start_range_key_expr.get_literal_bytes();
+                    table.scan_from_key_secondary_index(&start_range_key_bytes, range.is_start_inclusive(), transaction, column_name)?
+                },
+                None => table.scan_all_secondary_index(transaction, column_name)?,
+            },
+        };
+
+        Ok(IndexOnlyScanStep {
+            column_name: column_name.to_string(),
+            transaction: transaction.clone(),
+            secondary_iterator,
+            lookup,
+            table,
+        })
+    }
+
+    fn is_inside_lookup(&self, indexed_value: &Value) -> bool {
+        match &self.lookup {
+            IndexOnlyLookup::Exact(value) => value.get_bytes().eq(indexed_value.get_bytes()),
+            IndexOnlyLookup::Range(range) => matches!(range.get_position(indexed_value), RangeKeyPosition::Inside),
+        }
+    }
+}
+
+impl PlanStepTrait for IndexOnlyScanStep {
+    fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
+        match self.secondary_iterator.next() {
+            //The iterator has been seeked to the start of the lookup, so the first value outside it ends the scan
+            Some((indexed_value, primary_key)) if self.is_inside_lookup(indexed_value.get_value()) => {
+                let mut row_builder = RowBuilder::create(self.table.get_schema().clone());
+                row_builder.add_primary_value(primary_key.get_value().clone());
+                row_builder.add_by_column_name(indexed_value.get_value().get_bytes().clone(), &self.column_name);
+                Ok(Some(row_builder.build()))
+            },
+            _ => Ok(None),
+        }
+    }
+
+    fn desc(&self) -> PlanStepDesc {
+        let index_scan_desc = match &self.lookup {
+            IndexOnlyLookup::Exact(value) => PlanStepDesc::SecondaryExactExactScan(self.column_name.clone(), value.get_bytes().clone()),
+            IndexOnlyLookup::Range(range) => PlanStepDesc::RangeScan(range.clone()),
+        };
+
+        PlanStepDesc::IndexOnlyScan(Box::new(index_scan_desc))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::PlanStepDesc;
+    use crate::simple_db::{Context, StatementResult};
+    use crate::test_utils::TestSimpleDb;
+
+    #[test]
+    fn index_only_scan() {
+        let simple_db = TestSimpleDb::create("index_only_scan");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64, edad I64);");
+        simple_db.execute_sql(&context, "CREATE INDEX ON personas (dinero);");
+        for id in 1..=20 {
+            simple_db.execute_in_transaction(&context, &format!("INSERT INTO personas (id, dinero, edad) VALUES ({}, {}, {});", id, id * 10, id));
+        }
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        let explain = |query: &str| match simple_db.execute(&transaction.context(), simple_db.parse(query).unwrap()) {
+            Ok(StatementResult::Data(desc, _)) => desc,
+            _ => panic!(),
+        };
+        let desc = explain("EXPLAIN SELECT id, dinero FROM personas WHERE dinero == 50;");
+        assert!(matches!(desc, PlanStepDesc::Filter(scan) if matches!(&*scan, PlanStepDesc::IndexOnlyScan(index_scan)
+            if matches!(&**index_scan, PlanStepDesc::SecondaryExactExactScan(column_name, _) if column_name == "dinero"))));
+        let desc = explain("EXPLAIN SELECT dinero FROM personas WHERE dinero > 180;");
+        assert!(matches!(desc, PlanStepDesc::Filter(scan) if matches!(&*scan, PlanStepDesc::IndexOnlyScan(index_scan)
+            if matches!(&**index_scan, PlanStepDesc::RangeScan(range) if range.column_name == "dinero"))));
+        //edad is not stored in the secondary index
+        let desc = explain("EXPLAIN SELECT id, edad FROM personas WHERE dinero > 180;");
+        assert!(matches!(desc, PlanStepDesc::ProjectionSelectionStep(_, filter) if matches!(&*filter, PlanStepDesc::Filter(scan)
+            if matches!(&**scan, PlanStepDesc::RangeScan(_)))));
+        transaction.commit().unwrap();
+
+        //Deleted rows and old values of updated rows are removed from the secondary index
+        simple_db.execute_in_transaction(&context, "DELETE FROM personas WHERE id == 20;");
+        simple_db.execute_in_transaction(&context, "UPDATE personas SET dinero = 5 WHERE id == 19;");
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        let rows = simple_db.execute_sql(&transaction.context(), "SELECT id, dinero FROM personas WHERE dinero > 170;")
+            .data().all().unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].get_column_value("id").unwrap().get_i64().unwrap(), 18);
+        assert_eq!(rows[0].get_column_value("dinero").unwrap().get_i64().unwrap(), 180);
+
+        let rows = simple_db.execute_sql(&transaction.context(), "SELECT id, dinero FROM personas WHERE dinero == 190;")
+            .data().all().unwrap();
+        assert!(rows.is_empty());
+
+        let rows = simple_db.execute_sql(&transaction.context(), "SELECT id, dinero FROM personas WHERE dinero == 5;")
+            .data().all().unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].get_column_value("id").unwrap().get_i64().unwrap(), 19);
+        transaction.commit().unwrap();
+    }
+}
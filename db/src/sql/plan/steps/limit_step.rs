@@ -7,25 +7,45 @@ use crate::table::row::RowIterator;
 #[derive(Clone)]
 pub struct LimitStep {
     pub(crate) limit: Limit,
+    pub(crate) offset: usize,
     pub(crate) source: PlanStep,
-    pub(crate) count: usize
+    pub(crate) count: usize,
+    pub(crate) skipped: bool,
 }
 
 impl LimitStep {
     pub(crate) fn create(
         limit: Limit,
+        offset: usize,
         source: PlanStep
     ) -> LimitStep {
         LimitStep {
+            skipped: offset == 0,
             count: 0,
             source,
+            offset,
             limit,
         }
     }
+
+    fn skip_offset(&mut self) -> Result<(), SimpleDbError> {
+        for _ in 0..self.offset {
+            if self.source.next()?.is_none() {
+                break;
+            }
+        }
+
+        self.skipped = true;
+        Ok(())
+    }
 }
 
 impl PlanStepTrait for LimitStep {
     fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
+        if !self.skipped {
+            self.skip_offset()?;
+        }
+
         match self.limit {
             Limit::Some(limit) => {
                 if (self.count + 1) > limit {
@@ -44,7 +64,70 @@ impl PlanStepTrait for LimitStep {
     fn desc(&self) -> PlanStepDesc {
         PlanStepDesc::Limit(
             self.limit.clone(),
+            self.offset,
             Box::new(self.source.desc())
         )
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use crate::sql::parser::statement::Limit;
+    use crate::sql::plan::plan_step::{MockStep, PlanStep, PlanStepTrait};
+    use crate::sql::plan::steps::limit_step::LimitStep;
+    use crate::table::record::Record;
+    use crate::{Column, Row, Schema};
+    use crate::simple_db::Context;
+    use crate::table::row::RowIterator;
+    use crate::test_utils::TestSimpleDb;
+    use bytes::Bytes;
+    use shared::{SimpleDbError, Value};
+
+    #[test]
+    fn limit_offset() {
+        let schema = Schema::create(vec![Column::create_primary("id")]);
+        let rows = (1..=5).map(|id| row(&schema, id)).collect();
+        let mut step = LimitStep::create(Limit::Some(2), 2, PlanStep::Mock(MockStep::create(false, rows)));
+
+        assert_eq!(step.next().unwrap().unwrap().get_primary_column_value().get_i64().unwrap(), 3);
+        assert_eq!(step.next().unwrap().unwrap().get_primary_column_value().get_i64().unwrap(), 4);
+        assert!(step.next().unwrap().is_none());
+
+        let rows = (1..=2).map(|id| row(&schema, id)).collect();
+        let mut step = LimitStep::create(Limit::Some(2), 3, PlanStep::Mock(MockStep::create(false, rows)));
+        assert!(step.next().unwrap().is_none());
+    }
+
+    fn row(schema: &Schema, id: i64) -> Row {
+        let mut record_builder = Record::builder();
+        record_builder.add_column(0, Bytes::from(id.to_le_bytes().to_vec()));
+        Row::create(record_builder.build(), Value::create_i64(id), schema.clone())
+    }
+
+    #[test]
+    fn select_limit_offset() {
+        let simple_db = TestSimpleDb::create("select_limit_offset");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64);");
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        for (id, dinero) in [(1, 50), (2, 10), (3, 40), (4, 20), (5, 30)] {
+            let insert = format!("INSERT INTO personas (id, dinero) VALUES ({}, {});", id, dinero);
+            simple_db.execute(&transaction.context(), simple_db.parse(&insert).unwrap()).unwrap();
+        }
+
+        //Offset is applied after sorting
+        let mut rows = simple_db.execute_sql(&transaction.context(), "SELECT * FROM personas ORDER BY dinero LIMIT 2 OFFSET 1;").data();
+        assert_eq!(rows.next().unwrap().unwrap().get_column_value("dinero").unwrap().get_i64().unwrap(), 20);
+        assert_eq!(rows.next().unwrap().unwrap().get_column_value("dinero").unwrap().get_i64().unwrap(), 30);
+        assert!(rows.next().unwrap().is_none());
+
+        let mut rows = simple_db.execute_sql(&transaction.context(), "SELECT * FROM personas LIMIT 10 OFFSET 4;").data();
+        assert!(rows.next().unwrap().is_some());
+        assert!(rows.next().unwrap().is_none());
+
+        let result = simple_db.execute(&transaction.context(), simple_db.parse("SELECT * FROM personas LIMIT 10 OFFSET -1;").unwrap());
+        assert!(matches!(result, Err(SimpleDbError::MalformedQuery(_))));
+        transaction.commit().unwrap();
+    }
+}
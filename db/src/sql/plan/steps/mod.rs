@@ -10,4 +10,13 @@ pub mod secondary_range_scan_step;
 pub mod project_selection_step;
 pub mod full_sort_step;
 pub mod top_n_sort;
-pub mod reverse_step;
\ No newline at end of file
+pub mod reverse_step;
+pub mod aggregate_step;
+pub mod group_by_step;
+pub mod empty_step;
+pub mod cached_result_step;
+pub mod composite_scan_step;
+pub mod analyze_step;
+pub mod distinct_step;
+pub mod index_only_scan_step;
+pub mod parallel_full_scan_step;
@@ -0,0 +1,204 @@
+use crate::sql::plan::plan_step::{PlanStepDesc, PlanStepTrait};
+use crate::table::selection::Selection;
+use crate::table::table::Table;
+use crate::Row;
+use bytes::Bytes;
+use shared::{SimpleDbError, SimpleDbOptions};
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::mpsc::{Receiver, SyncSender};
+use std::sync::{mpsc, Arc};
+use storage::transactions::transaction::Transaction;
+use threadpool::ThreadPool;
+
+//Max nº of rows produced by each scan thread that haven't been returned yet. Once reached, the threads
+//wait until the rows are consumed, so the table is not buffered in memory when the consumer is slow
+const N_BUFFERED_ROWS_PER_THREAD: usize = 256;
+
+//Full scan used when the rows don't need to be returned sorted by primary key.
+//The table is split in partitions by the key ranges of its SSTables, which are scanned by a pool of threads
+//shared by every query. Rows are returned as soon as they are produced by any thread, so they are not ordered
+pub struct ParallelFullScanStep {
+    pub(crate) table: Arc<Table>,
+    pub(crate) selection: Selection,
+    pub(crate) transaction: Transaction,
+    pub(crate) partition_keys: Vec<Bytes>,
+    pub(crate) n_threads: usize,
+    pub(crate) thread_pool: ThreadPool,
+
+    //None until the first row is requested
+    pub(crate) receiver: Option<Receiver<Result<Row, SimpleDbError>>>,
+}
+
+impl ParallelFullScanStep {
+    pub(crate) fn create(
+        options: Arc<SimpleDbOptions>,
+        thread_pool: ThreadPool,
+        table: Arc<Table>,
+        selection: Selection,
+        transaction: &Transaction,
+    ) -> Result<ParallelFullScanStep, SimpleDbError> {
+        Ok(ParallelFullScanStep {
+            partition_keys: table.get_scan_partition_keys()?,
+            n_threads: options.parallel_full_scan_n_threads,
+            transaction: transaction.clone(),
+            receiver: None,
+            thread_pool,
+            selection,
+            table,
+        })
+    }
+
+    fn start_scan_threads(&mut self) -> Receiver<Result<Row, SimpleDbError>> {
+        let (sender, receiver) = mpsc::sync_channel(self.n_threads * N_BUFFERED_ROWS_PER_THREAD);
+        //Partition i goes from partition_keys[i - 1] (inclusive) to partition_keys[i] (exclusive)
+        let n_partitions = self.partition_keys.len() + 1;
+        let next_partition = Arc::new(AtomicUsize::new(0));
+        let partition_keys = Arc::new(self.partition_keys.clone());
+
+        for _ in 0..self.n_threads.min(n_partitions) {
+            let next_partition = next_partition.clone();
+            let partition_keys = partition_keys.clone();
+            let transaction = self.transaction.clone();
+            let selection = self.selection.clone();
+            let table = self.table.clone();
+            let sender = sender.clone();
+
+            self.thread_pool.execute(move || {
+                loop {
+                    let partition = next_partition.fetch_add(1, Relaxed);
+                    if partition >= n_partitions {
+                        return;
+                    }
+                    let start_key = if partition > 0 { partition_keys.get(partition - 1) } else { None };
+                    let end_key = partition_keys.get(partition);
+
+                    if let Err(error) = Self::scan_partition(&table, &transaction, &selection, start_key, end_key, &sender) {
+                        let _ = sender.send(Err(error));
+                        return;
+                    }
+                }
+            });
+        }
+
+        receiver
+    }
+
+    //Returns Ok even if the receiver has been dropped, the remaining rows are not needed
+    fn scan_partition(
+        table: &Arc<Table>,
+        transaction: &Transaction,
+        selection: &Selection,
+        start_key: Option<&Bytes>,
+        end_key: Option<&Bytes>,
+        sender: &SyncSender<Result<Row, SimpleDbError>>,
+    ) -> Result<(), SimpleDbError> {
+        let mut iterator = match start_key {
+            Some(start_key) => table.scan_from_key(start_key, true, transaction, selection)?,
+            None => table.scan_all(transaction, selection)?,
+        };
+
+        while iterator.next() {
+            let row = iterator.row();
+            if let Some(end_key) = end_key {
+                if row.get_primary_column_value().ge_bytes(end_key) {
+                    return Ok(());
+                }
+            }
+            if sender.send(Ok(row.clone())).is_err() {
+                return Ok(());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl PlanStepTrait for ParallelFullScanStep {
+    fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
+        if self.receiver.is_none() {
+            self.receiver = Some(self.start_scan_threads());
+        }
+
+        //All the threads have finished once every sender has been dropped
+        match self.receiver.as_ref().unwrap().recv() {
+            Ok(row) => row.map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+
+    fn desc(&self) -> PlanStepDesc {
+        PlanStepDesc::ParallelFullScan(self.partition_keys.len() + 1, self.n_threads)
+    }
+}
+
+//The rows being scanned can't be shared, the cloned step will scan the table again from the beginning
+impl Clone for ParallelFullScanStep {
+    fn clone(&self) -> Self {
+        ParallelFullScanStep {
+            partition_keys: self.partition_keys.clone(),
+            transaction: self.transaction.clone(),
+            selection: self.selection.clone(),
+            table: self.table.clone(),
+            n_threads: self.n_threads,
+            thread_pool: self.thread_pool.clone(),
+            receiver: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::PlanStepDesc;
+    use crate::simple_db::{Context, StatementResult};
+    use crate::table::row::RowIterator;
+    use crate::test_utils::TestSimpleDb;
+
+    #[test]
+    fn parallel_full_scan() {
+        let simple_db = TestSimpleDb::create_with_options("parallel_full_scan", {
+            let mut options_builder = shared::start_simpledb_options_builder();
+            options_builder.memtable_max_size_bytes(1024);
+            options_builder.max_memtables_inactive(1);
+            options_builder.compaction_task_frequency_ms(3600000);
+            options_builder.parallel_full_scan_n_threads(4);
+            options_builder
+        });
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64);");
+        for id in 1..=300 {
+            simple_db.execute_in_transaction(&context, &format!("INSERT INTO personas (id, dinero) VALUES ({}, {});", id, id * 10));
+        }
+        simple_db.execute_in_transaction(&context, "UPDATE personas SET dinero = 0 WHERE id > 250;");
+        simple_db.execute_in_transaction(&context, "DELETE FROM personas WHERE id <= 20;");
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        let desc = match simple_db.execute(&transaction.context(), simple_db.parse("EXPLAIN SELECT * FROM personas;").unwrap()) {
+            Ok(StatementResult::Data(desc, _)) => desc,
+            _ => panic!(),
+        };
+        assert!(matches!(desc, PlanStepDesc::ParallelFullScan(n_partitions, 4) if n_partitions > 1));
+
+        let query = |query: &str| {
+            let rows = simple_db.execute(&transaction.context(), simple_db.parse(query).unwrap()).unwrap().data().all().unwrap();
+            let mut rows: Vec<(i64, i64)> = rows.iter()
+                .map(|row| (row.get_column_value("id").unwrap().get_i64().unwrap(), row.get_column_value("dinero").unwrap().get_i64().unwrap()))
+                .collect();
+            rows.sort();
+            rows
+        };
+        //Sorted by primary key, so it is scanned serially
+        let serial_rows = query("SELECT * FROM personas ORDER BY id ASC;");
+        let parallel_rows = query("SELECT * FROM personas;");
+        assert!(!serial_rows.is_empty());
+        assert_eq!(serial_rows, parallel_rows);
+
+        //The threads are shared by every query. Threads of queries whose rows are not consumed are released once the query is dropped
+        for _ in 0..10 {
+            let mut rows = simple_db.execute_sql(&transaction.context(), "SELECT * FROM personas;").data();
+            assert!(rows.next().unwrap().is_some());
+        }
+        assert_eq!(query("SELECT * FROM personas;"), serial_rows);
+        transaction.commit().unwrap();
+    }
+}
@@ -1,30 +1,57 @@
 use shared::SimpleDbError;
 use crate::{PlanStepDesc, Row};
 use crate::table::selection::Selection;
+use crate::sql::execution::expression_evaluator::evaluate_expression;
 use crate::sql::plan::plan_step::{PlanStep, PlanStepTrait};
+use crate::sql::plan::steps::aggregate_step::build_row;
 use crate::table::row::RowIterator;
+use crate::table::schema::Schema;
+use crate::table::table::Table;
 
 #[derive(Clone)]
 pub struct ProjectSelectionStep {
     pub(crate) source: PlanStep,
     pub(crate) selection_to_project: Selection,
+    output_schema: Option<Schema>, //Only present when expressions are selected
 }
 
 impl ProjectSelectionStep {
     pub fn create(
         required_selection: Selection,
+        table: &Table,
         source: PlanStep,
     ) -> ProjectSelectionStep {
+        let output_schema = match &required_selection {
+            Selection::Expressions(_) => Some(Schema::create(required_selection.get_expression_columns(table))),
+            _ => None
+        };
+
         ProjectSelectionStep {
             selection_to_project: required_selection,
+            output_schema,
             source
         }
     }
+
+    fn evaluate_expressions(&self, row: &Row) -> Result<Row, SimpleDbError> {
+        let output_schema = self.output_schema.as_ref().unwrap();
+        let mut values = Vec::new();
+        if let Selection::Expressions(expressions) = &self.selection_to_project {
+            for ((_, expression), column) in expressions.iter().zip(output_schema.get_columns()) {
+                let value = evaluate_expression(row, expression)?.get_value()?;
+                //Functions like COALESCE might return values of different types
+                values.push(value.coerce(&column.column_type).unwrap_or(value));
+            }
+        }
+
+        Ok(build_row(values, output_schema))
+    }
 }
 
 impl PlanStepTrait for ProjectSelectionStep {
     fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
         match self.source.next()? {
+            Some(row) if self.output_schema.is_some() => Ok(Some(self.evaluate_expressions(&row)?)),
             Some(mut row) => {
                 row.project_selection(&self.selection_to_project);
                 Ok(Some(row))
@@ -39,4 +66,39 @@ impl PlanStepTrait for ProjectSelectionStep {
             Box::new(self.source.desc())
         )
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use crate::simple_db::Context;
+    use crate::table::row::RowIterator;
+    use crate::test_utils::TestSimpleDb;
+
+    #[test]
+    fn select_with_aliases() {
+        let simple_db = TestSimpleDb::create("select_with_aliases");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR);");
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        simple_db.execute_sql(&transaction.context(), "INSERT INTO personas (id, nombre) VALUES (1, \"Jaime\");");
+
+        let mut query_iterator = simple_db.execute_sql(&transaction.context(), "SELECT id, nombre AS name FROM personas;").data();
+        let selected_columns = query_iterator.get_selected_columns();
+        assert_eq!(selected_columns[0].column_name, "id");
+        assert_eq!(selected_columns[1].column_name, "name");
+        assert_eq!(selected_columns[1].column_id, 1);
+        let row = query_iterator.next().unwrap().unwrap();
+        assert_eq!(row.get_column_value("nombre").unwrap().get_string().unwrap(), "Jaime");
+
+        let query_iterator = simple_db.execute_sql(&transaction.context(), "SELECT COUNT(*) AS total FROM personas;").data();
+        assert_eq!(query_iterator.get_selected_columns()[0].column_name, "total");
+
+        //The same column selected twice keeps both aliases
+        let query_iterator = simple_db.execute_sql(&transaction.context(), "SELECT nombre AS a, nombre AS b FROM personas;").data();
+        let selected_columns = query_iterator.get_selected_columns();
+        assert_eq!(selected_columns[0].column_name, "a");
+        assert_eq!(selected_columns[1].column_name, "b");
+        transaction.commit().unwrap();
+    }
+}
@@ -6,19 +6,21 @@ use storage::TemporarySpace;
 use crate::{PlanStepDesc, Row};
 use crate::sql::plan::plan_step::{PlanStep, PlanStepTrait};
 use crate::table::row::RowIterator;
+use crate::sql::cancellation_token::{check_cancelled, CancellationToken};
 use crate::table::table::Table;
 
 #[derive(Clone)]
 pub struct ReverseStep {
     pub(crate) source: PlanStep,
     pub(crate) table: Arc<Table>,
-    pub(crate) state: RevserseStepState,
+    pub(crate) state: ReverseStepState,
     pub(crate) temporary_space: TemporarySpace,
     pub(crate) reserved_file: SimpleDbFile,
+    pub(crate) cancellation_token: Option<CancellationToken>,
 }
 
 #[derive(Clone)]
-enum RevserseStepState {
+pub(crate) enum ReverseStepState {
     Reversing,
     //Last offset read. It starts from the top of the file and grows to 0
     Reversed(usize)
@@ -30,9 +32,10 @@ impl ReverseStep {
         let reversed_file = temporary_space.create_file("reversed", SimpleDbFileMode::AppendOnly)?;
 
         Ok(ReverseStep {
-            state: RevserseStepState::Reversing,
+            state: ReverseStepState::Reversing,
             temporary_space,
             reserved_file: reversed_file,
+            cancellation_token: None,
             source,
             table
         })
@@ -51,9 +54,9 @@ impl ReverseStep {
 
         let row_bytes = self.reserved_file.read(row_bytes_offset, row_size_bytes)
             .map_err(|e| CannotReadReverseFile(e))?;
-        let row = Row::deserialize(&mut row_bytes.as_slice(), self.table.get_schema());
+        let row = Row::deserialize(&mut row_bytes.as_slice(), self.table.get_schema())?;
 
-        self.state = RevserseStepState::Reversed(row_bytes_offset);
+        self.state = ReverseStepState::Reversed(row_bytes_offset);
 
         return Ok(Some(row));
     }
@@ -62,8 +65,9 @@ impl ReverseStep {
 impl PlanStepTrait for ReverseStep {
     fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
         match self.state.clone() {
-            RevserseStepState::Reversing => {
+            ReverseStepState::Reversing => {
                 while let Some(row) = self.source.next()? {
+                    check_cancelled(&self.cancellation_token)?;
                     let row_serialized = row.serialize();
                     let row_serialized_size = row_serialized.len() as u32;
                     let mut serialized: Vec<u8> = Vec::new();
@@ -74,17 +78,54 @@ impl PlanStepTrait for ReverseStep {
                         .map_err(|e| SimpleDbError::CannotWriteReverseFile(e))?;
                 }
 
-                self.state = RevserseStepState::Reversed(self.reserved_file.size());
+                self.state = ReverseStepState::Reversed(self.reserved_file.size());
 
                 self.next_row_from_reserved_file(self.reserved_file.size())
             }
-            RevserseStepState::Reversed(last_offset_read) => {
+            ReverseStepState::Reversed(last_offset_read) => {
                 self.next_row_from_reserved_file(last_offset_read)
             }
         }
     }
 
     fn desc(&self) -> PlanStepDesc {
-        PlanStepDesc::Revserse(Box::new(self.source.desc()))
+        PlanStepDesc::Reverse(Box::new(self.source.desc()))
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use crate::PlanStepDesc;
+    use crate::simple_db::{Context, StatementResult};
+    use crate::test_utils::TestSimpleDb;
+
+    #[test]
+    fn reverse_full_scan() {
+        let simple_db = TestSimpleDb::create("reverse_full_scan");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64);");
+        for id in 1..6 {
+            simple_db.execute_in_transaction(&context, &format!("INSERT INTO personas (id, dinero) VALUES ({}, {});", id, id * 10));
+        }
+        simple_db.execute_in_transaction(&context, "UPDATE personas SET dinero = 100 WHERE id == 2;");
+        simple_db.execute_in_transaction(&context, "DELETE FROM personas WHERE id == 4;");
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        let select = |query: &str| {
+            simple_db.execute(&transaction.context(), simple_db.parse(query).unwrap()).unwrap().data().all().unwrap().iter()
+                .map(|row| (row.get_primary_column_value().get_i64().unwrap(), row.get_column_value("dinero").unwrap().get_i64().unwrap()))
+                .collect::<Vec<(i64, i64)>>()
+        };
+
+        assert_eq!(select("SELECT * FROM personas ORDER BY id DESC;"), vec![(5, 50), (3, 30), (2, 100), (1, 10)]);
+        assert_eq!(select("SELECT * FROM personas ORDER BY id DESC LIMIT 2;"), vec![(5, 50), (3, 30)]);
+        assert_eq!(select("SELECT * FROM personas WHERE dinero > 20 ORDER BY id DESC;"), vec![(5, 50), (3, 30), (2, 100)]);
+
+        let desc = match simple_db.execute(&transaction.context(), simple_db.parse("EXPLAIN SELECT * FROM personas ORDER BY id DESC LIMIT 2;").unwrap()) {
+            Ok(StatementResult::Data(desc, _)) => desc,
+            _ => panic!(),
+        };
+        assert!(matches!(desc, PlanStepDesc::Limit(_, _, scan) if matches!(*scan, PlanStepDesc::FullScan(None, true))));
+        transaction.commit().unwrap();
+    }
+}
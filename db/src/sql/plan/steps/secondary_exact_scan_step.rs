@@ -41,7 +41,7 @@ impl SecondaryExactScanStep {
         let secondary_column_value = Value::create(secondary_index_value_lookup, column_type)?;
 
         Ok(SecondaryExactScanStep {
-            index_selection_type: selection.get_index_selection_type(table.get_schema()),
+            index_selection_type: table.get_index_selection_type(&selection),
             column_name: secondary_column_name.to_string(),
             transaction: transaction.clone(),
             secondary_index_iterator,
@@ -69,16 +69,14 @@ impl PlanStepTrait for SecondaryExactScanStep {
                     return Ok(Some(row_builder.build()));
                 }
                 IndexSelectionType::All => {
-                    let mut primary_key_iterator = self.table.scan_from_key(
-                        &primary_key.as_bytes(),
-                        true,
+                    //The row might have been expired by the table TTL
+                    if let Some(row) = self.table.get_by_primary_column(
+                        primary_key.as_bytes(),
                         &self.transaction,
                         &self.selection,
-                    )?;
-
-                    primary_key_iterator.next();
-
-                    return Ok(Some(primary_key_iterator.row().clone()));
+                    )? {
+                        return Ok(Some(row));
+                    }
                 }
             }
         }
@@ -38,7 +38,7 @@ impl SecondaryRangeScanStep {
         }?;
 
         Ok(SecondaryRangeScanStep {
-            index_selection_type: selection.get_index_selection_type(table.get_schema()),
+            index_selection_type: table.get_index_selection_type(&selection),
             column_name: column_name.to_string(),
             transaction: transaction.clone(),
             secondary_iterator: iterator,
@@ -51,7 +51,7 @@ impl SecondaryRangeScanStep {
 
 impl PlanStepTrait for SecondaryRangeScanStep {
     fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
-        if let Some((indexed_value, primary_key)) = self.secondary_iterator.next() {
+        while let Some((indexed_value, primary_key)) = self.secondary_iterator.next() {
             match self.range.get_position(indexed_value.get_value()) {
                 RangeKeyPosition::Inside => {
                     match self.index_selection_type {
@@ -61,24 +61,27 @@ impl PlanStepTrait for SecondaryRangeScanStep {
                             let mut row_builder = RowBuilder::create(self.table.get_schema().clone());
                             row_builder.add_primary_value(primary_key.get_value().clone());
                             row_builder.add_by_column_name(indexed_value.get_value().get_bytes().clone(), &self.column_name);
-                            Ok(Some(row_builder.build()))
+                            return Ok(Some(row_builder.build()));
                         },
                         IndexSelectionType::All => {
-                            self.table.get_by_primary_column(
+                            //The row might have been expired by the table TTL
+                            if let Some(row) = self.table.get_by_primary_column(
                                 primary_key.as_bytes(),
                                 &self.transaction,
                                 &self.selection,
-                            )
+                            )? {
+                                return Ok(Some(row));
+                            }
                         },
                     }
                 },
-                RangeKeyPosition::Above => Ok(None),
+                RangeKeyPosition::Above => return Ok(None),
                 //Not possible because, the iterator have been seeked in construction time
                 RangeKeyPosition::Bellow => panic!(""),
             }
-        } else {
-            Ok(None)
         }
+
+        Ok(None)
     }
 
     fn desc(&self) -> PlanStepDesc {
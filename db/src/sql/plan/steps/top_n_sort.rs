@@ -1,7 +1,8 @@
 use crate::sql::plan::plan_step::{PlanStep, PlanStepTrait};
 use crate::table::row::RowIterator;
+use crate::sql::cancellation_token::{check_cancelled, CancellationToken};
 use crate::{PlanStepDesc, Row, Sort};
-use shared::{SimpleDbError, Value};
+use shared::SimpleDbError;
 use std::cmp::Ordering;
 use std::collections::{BTreeMap, BinaryHeap};
 use std::sync::Arc;
@@ -14,6 +15,8 @@ pub struct TopNSortStep {
     pub(crate) n: usize, //N elements to maintain in the heap,
     pub(crate) sort: Arc<Sort>,
     pub(crate) state: TopNSortStepState,
+    pub(crate) n_inserted_rows: usize,
+    pub(crate) cancellation_token: Option<CancellationToken>,
 }
 
 #[derive(Clone)]
@@ -26,6 +29,8 @@ enum TopNSortStepState {
 struct TopNSortHeapEntry {
     row: Row,
     sort: Arc<Sort>,
+    //Breaks ties between rows with equal sort values, so they are not collapsed into one heap entry
+    insertion_order: usize,
 }
 
 impl TopNSortStep {
@@ -38,6 +43,8 @@ impl TopNSortStep {
             state: TopNSortStepState::Sorting,
             heap: BTreeMap::new(),
             sort: Arc::new(sort),
+            n_inserted_rows: 0,
+            cancellation_token: None,
             source,
             n,
         }
@@ -45,10 +52,13 @@ impl TopNSortStep {
 
     fn top_n_sort(&mut self) -> Result<(), SimpleDbError> {
         while let Some(row) = self.source.next()? {
+            check_cancelled(&self.cancellation_token)?;
             self.heap.insert(TopNSortHeapEntry {
+                insertion_order: self.n_inserted_rows,
                 sort: self.sort.clone(),
                 row
             }, ());
+            self.n_inserted_rows += 1;
 
             if self.heap.len() > self.n {
                 self.remove_largest();
@@ -96,12 +106,6 @@ impl PlanStepTrait for TopNSortStep {
     }
 }
 
-impl TopNSortHeapEntry {
-    pub fn get_sorted_value(&self) -> Value {
-        self.row.get_column_value(&self.sort.column_name).unwrap()
-    }
-}
-
 impl Eq for TopNSortHeapEntry {}
 
 impl Ord for TopNSortHeapEntry {
@@ -112,12 +116,13 @@ impl Ord for TopNSortHeapEntry {
 
 impl PartialEq<Self> for TopNSortHeapEntry {
     fn eq(&self, other: &Self) -> bool {
-        self.get_sorted_value().eq(&other.get_sorted_value())
+        self.cmp(other) == Ordering::Equal
     }
 }
 
 impl PartialOrd for TopNSortHeapEntry {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.sort.compare(&self.row, &other.row))
+        Some(self.sort.compare(&self.row, &other.row)
+            .then(self.insertion_order.cmp(&other.insertion_order)))
     }
 }
\ No newline at end of file
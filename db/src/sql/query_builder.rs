@@ -0,0 +1,119 @@
+use crate::sql::parser::expression::{BinaryOperator, Expression};
+use crate::sql::parser::statement::{Limit, SelectStatement, Sort, SortOrder, Statement};
+use crate::sql::plan::plan_step::PlanStep;
+use crate::sql::query_iterator::QueryIterator;
+use crate::simple_db::{Context, SimpleDb};
+use crate::table::selection::Selection;
+use shared::SimpleDbError;
+use std::collections::HashMap;
+
+/// Builds a SELECT from Rust code, without going through the SQL tokenizer and parser. The query is
+/// validated, planned and executed in the same way as a SQL SELECT.
+///
+/// ```
+/// use db::{Context, Expression, BinaryOperator, Statement};
+/// use shared::Value;
+///
+/// let path = std::env::temp_dir().join(format!("simpledb-query-builder-{}", std::process::id()));
+/// std::fs::create_dir_all(&path).unwrap();
+/// let options = shared::start_simpledb_options_builder()
+///     .base_path(path.to_str().unwrap())
+///     .build_arc();
+/// shared::logger::Logger::init(options.clone());
+/// let simple_db = db::simple_db::create(options).unwrap();
+/// simple_db.execute(&Context::empty(), Statement::CreateDatabase(String::from("test"))).unwrap();
+///
+/// let context = Context::create_with_database("test");
+/// for statement in simple_db.parse_all("CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR, dinero I64);").unwrap() {
+///     simple_db.execute(&context, statement).unwrap();
+/// }
+/// let mut transaction = simple_db.begin(&context).unwrap();
+/// for statement in simple_db.parse_all("INSERT INTO personas (id, nombre, dinero) VALUES (1, \"Jaime\", 10);\
+///     INSERT INTO personas (id, nombre, dinero) VALUES (2, \"Pedro\", 100);").unwrap() {
+///     simple_db.execute(&transaction.context(), statement).unwrap();
+/// }
+///
+/// let mut result = simple_db.query("personas")
+///     .filter(Expression::binary(BinaryOperator::Greater, Expression::column("dinero"), Expression::literal(Value::create_i64(50))))
+///     .select(&["nombre"])
+///     .limit(10)
+///     .run(&transaction.context())
+///     .unwrap();
+/// let rows = result.all().unwrap();
+/// transaction.commit().unwrap();
+///
+/// assert_eq!(result.get_selected_columns().len(), 1);
+/// assert_eq!(rows.len(), 1);
+/// assert_eq!(rows[0].get_column_value("nombre").unwrap().get_string().unwrap(), "Pedro");
+/// # let _ = std::fs::remove_dir_all(path);
+/// ```
+pub struct QueryBuilder<'a> {
+    simple_db: &'a SimpleDb,
+    select_statement: SelectStatement,
+}
+
+impl<'a> QueryBuilder<'a> {
+    pub(crate) fn create(simple_db: &'a SimpleDb, table_name: &str) -> QueryBuilder<'a> {
+        QueryBuilder {
+            select_statement: SelectStatement {
+                explain: false,
+                analyze: false,
+                selection: Selection::All,
+                distinct: false,
+                aliases: HashMap::new(),
+                table_name: table_name.to_string(),
+                where_expr: None,
+                group_by: None,
+                sort: None,
+                limit: Limit::None,
+                offset: 0,
+                index_hint: None,
+            },
+            simple_db,
+        }
+    }
+
+    //Multiple filters are joined with AND
+    pub fn filter(mut self, expression: Expression) -> QueryBuilder<'a> {
+        self.select_statement.where_expr = match self.select_statement.where_expr.take() {
+            Some(where_expr) => Some(Expression::binary(BinaryOperator::And, where_expr, expression)),
+            None => Some(expression),
+        };
+        self
+    }
+
+    pub fn select(mut self, column_names: &[&str]) -> QueryBuilder<'a> {
+        self.select_statement.selection = Selection::Some(column_names.iter()
+            .map(|column_name| column_name.to_string())
+            .collect());
+        self
+    }
+
+    pub fn distinct(mut self) -> QueryBuilder<'a> {
+        self.select_statement.distinct = true;
+        self
+    }
+
+    //Multiple calls will sort the rows by each column in order
+    pub fn order_by(mut self, column_name: &str, order: SortOrder) -> QueryBuilder<'a> {
+        let nulls_order = order.default_nulls_order();
+        let sort = self.select_statement.sort.get_or_insert(Sort { columns: Vec::new() });
+        sort.columns.push((column_name.to_string(), order, nulls_order));
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> QueryBuilder<'a> {
+        self.select_statement.limit = Limit::Some(limit);
+        self
+    }
+
+    pub fn offset(mut self, offset: usize) -> QueryBuilder<'a> {
+        self.select_statement.offset = offset as i64;
+        self
+    }
+
+    pub fn run(self, context: &Context) -> Result<QueryIterator<PlanStep>, SimpleDbError> {
+        let result = self.simple_db.execute(context, Statement::Select(self.select_statement))?;
+        Ok(result.data())
+    }
+}
@@ -1,9 +1,11 @@
+use crate::sql::cancellation_token::CancellationToken;
 use crate::sql::plan::plan_step::PlanStepDesc;
 use crate::table::row::RowIterator;
 use crate::table::schema::Schema;
 use crate::table::selection::Selection;
 use crate::{Column, Row};
 use shared::SimpleDbError;
+use std::collections::HashMap;
 
 //This will be returned to the user of SimpleDb when it queries data
 //This is simple wrapper around a Plan
@@ -12,6 +14,8 @@ pub struct QueryIterator<I: RowIterator> {
     source: I,
     schema: Schema,
     selection: Selection,
+    aliases: HashMap<usize, String>, //Position in the selected columns -> Alias
+    cancellation_token: Option<CancellationToken>,
 }
 
 impl<I: RowIterator> QueryIterator<I> {
@@ -20,10 +24,31 @@ impl<I: RowIterator> QueryIterator<I> {
         plan: I,
         schema: Schema
     ) -> QueryIterator<I> {
-        QueryIterator { source: plan, schema, selection }
+        QueryIterator { source: plan, schema, selection, aliases: HashMap::new(), cancellation_token: None }
     }
 
+    //The token will be checked before reading every row
+    pub fn with_cancellation_token(&mut self, cancellation_token: CancellationToken) {
+        self.cancellation_token = Some(cancellation_token);
+    }
+
+    pub(crate) fn with_aliases(&mut self, aliases: HashMap<usize, String>) {
+        self.aliases = aliases;
+    }
+
+    //Aliased columns will keep their column ID, only their names are replaced by the alias
     pub fn get_selected_columns(&self) -> Vec<Column> {
+        let mut selected_columns = self.get_selected_columns_without_aliases();
+        for (position, selected_column) in selected_columns.iter_mut().enumerate() {
+            if let Some(alias) = self.aliases.get(&position) {
+                selected_column.column_name = alias.clone();
+            }
+        }
+
+        selected_columns
+    }
+
+    fn get_selected_columns_without_aliases(&self) -> Vec<Column> {
         match &self.selection {
             Selection::All => {
                 self.schema.get_columns()
@@ -38,6 +63,12 @@ impl<I: RowIterator> QueryIterator<I> {
                 }
 
                 selected_columns
+            },
+            Selection::Aggregate(_, _) => {
+                self.selection.get_aggregate_columns(&self.schema)
+            },
+            Selection::Expressions(_) => {
+                self.schema.get_columns()
             }
         }
     }
@@ -45,8 +76,8 @@ impl<I: RowIterator> QueryIterator<I> {
     pub fn next_n(&mut self, n: usize) -> Result<Vec<Row>, SimpleDbError> {
         let mut results = Vec::new();
 
-        while results.len() <= n {
-            match self.source.next()? {
+        while results.len() < n {
+            match self.next()? {
                 Some(row) => results.push(row),
                 None => break
             };
@@ -58,7 +89,7 @@ impl<I: RowIterator> QueryIterator<I> {
     pub fn all(&mut self) -> Result<Vec<Row>, SimpleDbError> {
         let mut results = Vec::new();
 
-        while let Some(row) = self.source.next()? {
+        while let Some(row) = self.next()? {
             results.push(row);
         }
 
@@ -72,6 +103,10 @@ impl<I: RowIterator> QueryIterator<I> {
 
 impl<I: RowIterator> RowIterator for QueryIterator<I> {
     fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
+        if let Some(cancellation_token) = &self.cancellation_token {
+            cancellation_token.check()?;
+        }
+
         self.source.next()
     }
 }
\ No newline at end of file
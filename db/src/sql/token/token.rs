@@ -5,20 +5,23 @@ pub enum Token {
     OpenParen, // "("
     CloseParen, // ")"
     Comma, // ","
+    Dot, // "."
     Plus, // "+"
     Star, // "*"
     Minus, // "-"
     Slash, // "/"
+    Percent, // "%"
+    QuestionMark, // "?"
     Less, // "<"
     Equal, // "="
     EqualEqual, // "=="
     Greater, // ">"
     GreaterEqual, // ">="
     LessEqual, // "<="
-    NotEqual, // "!="
+    NotEqual, // "!=" or "<>"
     Semicolon,
 
-    True, //15
+    True, //17
     False,
     Null,
     And,
@@ -29,6 +32,7 @@ pub enum Token {
     Create,
     Table,
     Limit,
+    Offset,
     Update,
     Delete,
     Insert,
@@ -38,6 +42,7 @@ pub enum Token {
     Primary,
     Key,
     StartTransaction, // "START_TRANSACTION"
+    Transaction,
     Rollback, // "ROLLBACK"
     Commit, // "COMMIT"
     Database,
@@ -50,13 +55,34 @@ pub enum Token {
     On,
     Explain,
     Asc,
+    As,
     Desc,
     Order,
     By,
+    Group,
+    Like,
+    ILike,
+    In,
+    All,
+    Any,
+    Is,
+    Not,
+    Drop,
+    Alter,
+    Truncate,
+    Distinct,
+    Conflict,
+    Do,
+    Nothing,
+    Unique,
+    Savepoint,
+    Release,
+    Use,
 
     Identifier(String), //Ohter identifier, like table or column names
     ColumnType(Type),
     String(String), // "some text"
+    Hint(String), // /*+ INDEX(dinero) */
     NumberI64(i64), // any number
     NumberF64(f64), // any number
 
@@ -1,5 +1,5 @@
 use std::str::FromStr;
-use shared::SimpleDbError::{IllegalToken};
+use shared::SimpleDbError::{IllegalToken, MalformedNumber, MalformedString};
 use shared::{TokenLocation, Type};
 use crate::sql::token::token::Token;
 
@@ -48,13 +48,18 @@ impl Tokenizer {
             '(' => Ok(Token::OpenParen),
             ')' => Ok(Token::CloseParen),
             ',' => Ok(Token::Comma),
+            '.' => Ok(Token::Dot),
             '+' => Ok(Token::Plus),
             '-' => Ok(Token::Minus),
             '*' => Ok(Token::Star),
+            '/' if !self.end_reached() && self.current() == '*' => self.comment(),
             '/' => Ok(Token::Slash),
+            '%' => Ok(Token::Percent),
+            '?' => Ok(Token::QuestionMark),
             ';' => Ok(Token::Semicolon),
             '"' => self.string(),
             '>' => self.match_char_or('=', Token::GreaterEqual, Token::Greater),
+            '<' if self.advance_if_next_char_eq('>') => Ok(Token::NotEqual),
             '<' => self.match_char_or('=', Token::LessEqual, Token::Less),
             '=' => self.match_char_or('=', Token::EqualEqual, Token::Equal),
             '!' => self.match_char_or_error('=', Token::NotEqual),
@@ -62,11 +67,40 @@ impl Tokenizer {
         }
     }
 
+    //Comments starting with /*+ are query hints, like /*+ INDEX(dinero) */. Other comments are skipped
+    fn comment(&mut self) -> Result<Token, shared::SimpleDbError> {
+        //Get rid of *
+        self.advance();
+        let is_hint = !self.end_reached() && self.advance_if_next_char_eq('+');
+        let mut content = String::new();
+
+        loop {
+            if self.end_reached() {
+                return Err(IllegalToken(self.current_location(), String::from("Comments should end with '*/'")));
+            }
+            let current = self.advance();
+            if current == '*' && !self.end_reached() && self.advance_if_next_char_eq('/') {
+                break;
+            }
+            content.push(current);
+        }
+
+        if is_hint {
+            Ok(Token::Hint(content.trim().to_string()))
+        } else {
+            self.get_token()
+        }
+    }
+
     fn string(&mut self) -> Result<Token, shared::SimpleDbError> {
         let mut string = String::new();
 
         while !self.end_reached() && self.current() != '"' {
-            string.push(self.advance());
+            let character_location = self.current_location();
+            match self.advance() {
+                '\\' => self.escape_sequence(&mut string, character_location)?,
+                character => string.push(character),
+            }
         }
 
         if self.end_reached() || self.current() != '"' {
@@ -79,10 +113,41 @@ impl Tokenizer {
         Ok(Token::String(string))
     }
 
+    //Supported escape sequences: \" \\ \n \t \r
+    //\% and \_ are kept as they are, so LIKE patterns can still match those characters literally
+    fn escape_sequence(&mut self, string: &mut String, escape_location: TokenLocation) -> Result<(), shared::SimpleDbError> {
+        if self.end_reached() {
+            return Err(MalformedString(self.current_location(), String::from("Strings cannot end with '\\'")));
+        }
+
+        match self.advance() {
+            '"' => string.push('"'),
+            '\\' => string.push('\\'),
+            'n' => string.push('\n'),
+            't' => string.push('\t'),
+            'r' => string.push('\r'),
+            like_wildcard @ ('%' | '_') => {
+                string.push('\\');
+                string.push(like_wildcard);
+            },
+            other => return Err(IllegalToken(escape_location, format!("Unknown escape sequence \\{}", other))),
+        };
+
+        Ok(())
+    }
+
     fn identifier(&mut self) -> Result<Token, shared::SimpleDbError> {
         match self.advance().to_uppercase().next().unwrap() {
             'A' => {
-                if self.advance_if_next_string_eq("ND") {
+                if self.advance_if_next_keyword_eq("LL") {
+                    Ok(Token::All)
+                } else if self.advance_if_next_keyword_eq("LTER") {
+                    Ok(Token::Alter)
+                } else if self.advance_if_next_keyword_eq("NY") {
+                    Ok(Token::Any)
+                } else if self.advance_if_next_keyword_eq("S") {
+                    Ok(Token::As)
+                } else if self.advance_if_next_string_eq("ND") {
                     Ok(Token::And)
                 } else if self.advance_if_next_string_eq("SC") {
                     Ok(Token::Asc)
@@ -107,10 +172,13 @@ impl Tokenizer {
                 }
             },
             'K' => self.match_string_or_other_identifier("EY", Token::Key, 1),
+            'G' => self.match_string_or_other_identifier("ROUP", Token::Group, 1),
             'P' => self.match_string_or_other_identifier("RIMARY", Token::Primary, 1),
             'E' => self.match_string_or_other_identifier("XPLAIN", Token::Explain, 1),
             'O' => {
-                if self.advance_if_next_string_eq("RDER") {
+                if self.advance_if_next_keyword_eq("FFSET") {
+                    Ok(Token::Offset)
+                } else if self.advance_if_next_string_eq("RDER") {
                     Ok(Token::Order)
                 } else if self.advance_if_next_string_eq("R") {
                     Ok(Token::Or)
@@ -121,10 +189,23 @@ impl Tokenizer {
                     Ok(self.other_identifier())
                 }
             },
-            'N' => self.match_string_or_other_identifier("ULL", Token::Null, 1),
+            'N' => {
+                if self.advance_if_next_keyword_eq("ULL") {
+                    Ok(Token::Null)
+                } else if self.advance_if_next_keyword_eq("OT") {
+                    Ok(Token::Not)
+                } else if self.advance_if_next_keyword_eq("OTHING") {
+                    Ok(Token::Nothing)
+                } else {
+                    self.next -= 1;
+                    Ok(self.other_identifier())
+                }
+            },
             'R' => {
                 if self.advance_if_next_string_eq("OLLBACK") {
                     Ok(Token::Rollback)
+                } else if self.advance_if_next_keyword_eq("ELEASE") {
+                    Ok(Token::Release)
                 } else {
                     self.next -= 1;
                     Ok(self.other_identifier())
@@ -137,6 +218,8 @@ impl Tokenizer {
                     Ok(Token::Show)
                 } else if self.advance_if_next_string_eq("TART_TRANSACTION") {
                     Ok(Token::StartTransaction)
+                } else if self.advance_if_next_keyword_eq("AVEPOINT") {
+                    Ok(Token::Savepoint)
                 } else if self.advance_if_next_string_eq("ET") {
                     Ok(Token::Set)
                 } else {
@@ -164,6 +247,8 @@ impl Tokenizer {
                     Ok(Token::Create)
                 } else if self.advance_if_next_string_eq("OMMIT") {
                     Ok(Token::Commit)
+                } else if self.advance_if_next_keyword_eq("ONFLICT") {
+                    Ok(Token::Conflict)
                 } else {
                     self.next -= 1;
                     Ok(self.other_identifier())
@@ -174,6 +259,10 @@ impl Tokenizer {
                     Ok(Token::Tables)
                 } else if self.advance_if_next_string_eq("ABLE") {
                     Ok(Token::Table)
+                } else if self.advance_if_next_keyword_eq("RANSACTION") {
+                    Ok(Token::Transaction)
+                } else if self.advance_if_next_keyword_eq("RUNCATE") {
+                    Ok(Token::Truncate)
                 } else if self.advance_if_next_string_eq("RUE") {
                     Ok(Token::True)
                 } else {
@@ -181,10 +270,23 @@ impl Tokenizer {
                     Ok(self.other_identifier())
                 }
             },
-            'L' => self.match_string_or_other_identifier("IMIT", Token::Limit, 1),
+            'L' => {
+                if self.advance_if_next_string_eq("IMIT") {
+                    Ok(Token::Limit)
+                } else if self.advance_if_next_string_eq("IKE") {
+                    Ok(Token::Like)
+                } else {
+                    self.next -= 1;
+                    Ok(self.other_identifier())
+                }
+            },
             'U' => {
                 if self.advance_if_next_string_eq("PDATE") {
                     Ok(Token::Update)
+                } else if self.advance_if_next_keyword_eq("NIQUE") {
+                    Ok(Token::Unique)
+                } else if self.advance_if_next_keyword_eq("SE") {
+                    Ok(Token::Use)
                 } else if self.advance_if_next_string_eq("8") {
                     Ok(Token::ColumnType(Type::U8))
                 } else if self.advance_if_next_string_eq("16") {
@@ -211,6 +313,12 @@ impl Tokenizer {
                     Ok(Token::Delete)
                 } else if self.advance_if_next_string_eq("ATABASE"){
                     Ok(Token::Database)
+                } else if self.advance_if_next_string_eq("ROP"){
+                    Ok(Token::Drop)
+                } else if self.advance_if_next_string_eq("ISTINCT"){
+                    Ok(Token::Distinct)
+                } else if self.advance_if_next_keyword_eq("O"){
+                    Ok(Token::Do)
                 } else {
                     self.next -= 1;
                     Ok(self.other_identifier())
@@ -223,6 +331,12 @@ impl Tokenizer {
                     Ok(Token::Index)
                 } else if self.advance_if_next_string_eq("NTO") {
                     Ok(Token::Into)
+                } else if self.advance_if_next_keyword_eq("LIKE") {
+                    Ok(Token::ILike)
+                } else if self.advance_if_next_keyword_eq("N") {
+                    Ok(Token::In)
+                } else if self.advance_if_next_keyword_eq("S") {
+                    Ok(Token::Is)
                 } else if self.advance_if_next_string_eq("8") {
                     Ok(Token::ColumnType(Type::I8))
                 } else if self.advance_if_next_string_eq("16") {
@@ -242,6 +356,7 @@ impl Tokenizer {
                 } else if self.advance_if_next_string_eq("ALUES") {
                     Ok(Token::Values)
                 } else {
+                    self.next -= 1;
                     Ok(self.other_identifier())
                 }
             },
@@ -266,9 +381,10 @@ impl Tokenizer {
     fn number(&mut self) -> Result<Token, shared::SimpleDbError> {
         let start_number_index = self.next;
         let mut has_decimals = false;
+        let mut has_exponent = false;
 
         //Not decimal number part
-        while !self.end_reached() && self.is_number() {
+        while !self.end_reached() && self.is_number_or_separator() {
             self.advance();
         }
 
@@ -278,15 +394,34 @@ impl Tokenizer {
 
             //Decimal part
             self.advance();
-            while !self.end_reached() && self.is_number() {
+            while !self.end_reached() && self.is_number_or_separator() {
                 self.advance();
             }
         }
 
-        let number_string = &self.string[start_number_index..self.next];
+        //Has exponent? (1e6, 1.5e-3)
+        if !self.end_reached() && (self.current() == 'e' || self.current() == 'E') {
+            has_exponent = true;
 
-        if has_decimals {
+            self.advance();
+            if !self.end_reached() && (self.current() == '+' || self.current() == '-') {
+                self.advance();
+            }
+            let start_exponent_index = self.next;
+            while !self.end_reached() && self.is_number_or_separator() {
+                self.advance();
+            }
+            if start_exponent_index == self.next {
+                return Err(MalformedNumber(self.current_location(), String::from("Exponent should contain at least one digit")));
+            }
+        }
+
+        let number_string = self.remove_number_separators(&self.string[start_number_index..self.next])?;
+        let number_string = number_string.as_str();
+
+        if has_decimals || has_exponent {
             match f64::from_str(number_string) {
+                Ok(f64_value) if f64_value.is_infinite() => Err(MalformedNumber(self.current_location(), String::from("Number is out of range"))),
                 Ok(f64_value) => Ok(Token::NumberF64(f64_value)),
                 Err(_) => Err(IllegalToken(self.current_location(), String::from("Illegal number format"))),
             }
@@ -298,6 +433,17 @@ impl Tokenizer {
         }
     }
 
+    //Numbers can contain _ as digit separators (1_000_000). They should be placed between two digits
+    fn remove_number_separators(&self, number_string: &str) -> Result<String, shared::SimpleDbError> {
+        for digits in number_string.split(|c| matches!(c, '.' | 'e' | 'E' | '+' | '-')) {
+            if digits.starts_with('_') || digits.ends_with('_') || digits.contains("__") {
+                return Err(MalformedNumber(self.current_location(), String::from("_ should be placed between two digits")));
+            }
+        }
+
+        Ok(number_string.replace('_', ""))
+    }
+
     fn skip_whitespaces(&mut self) {
         loop {
             if self.end_reached() {
@@ -329,6 +475,10 @@ impl Tokenizer {
         char >= '0' && char <= '9'
     }
 
+    fn is_number_or_separator(&self) -> bool {
+        self.is_number() || self.current() == '_'
+    }
+
     fn match_char_or_error(
         &mut self,
         next: char,
@@ -377,7 +527,7 @@ impl Tokenizer {
         let start_string_index = self.next;
         let end_string_index = start_string_index + string_to_match.len();
 
-        if end_string_index >= self.string.len() {
+        if end_string_index > self.string.len() {
             return false;
         }
 
@@ -398,6 +548,21 @@ impl Tokenizer {
         }
     }
 
+    //Unlike advance_if_next_string_eq(), it won't match if the string is followed by more identifier characters
+    //Used for short keywords, which are likely to be the start of other identifiers (IN -> INGRESOS)
+    fn advance_if_next_keyword_eq(
+        &mut self,
+        string_to_match: &str,
+    ) -> bool {
+        let end_string_index = self.next + string_to_match.len();
+        if self.is_next_string_eq(string_to_match) &&
+            !self.string[end_string_index..].starts_with(|char: char| char.is_ascii_alphanumeric() || char == '_') {
+            self.advance_if_next_string_eq(string_to_match)
+        } else {
+            false
+        }
+    }
+
     fn advance_if_next_char_eq(&mut self, expected: char) -> bool {
         if self.current() == expected {
             self.advance();
@@ -432,7 +597,7 @@ impl Tokenizer {
 
 #[cfg(test)]
 mod test {
-    use shared::Type;
+    use shared::{SimpleDbError, Type};
     use crate::sql::token::token::Token;
     use crate::sql::token::tokenizer::Tokenizer;
 
@@ -463,6 +628,21 @@ mod test {
         assert!(matches!(tokenizer.get_token().unwrap(), Token::EOF));
     }
 
+    #[test]
+    fn identifier_starting_with_v() {
+        let mut tokenizer = Tokenizer::create(String::from("SELECT Valor, VALUES, Vip FROM VARCHAR"));
+
+        assert!(matches!(tokenizer.get_token().unwrap(), Token::Select));
+        assert!(matches!(tokenizer.get_token().unwrap(), Token::Identifier(identifier) if identifier == "Valor"));
+        assert!(matches!(tokenizer.get_token().unwrap(), Token::Comma));
+        assert!(matches!(tokenizer.get_token().unwrap(), Token::Values));
+        assert!(matches!(tokenizer.get_token().unwrap(), Token::Comma));
+        assert!(matches!(tokenizer.get_token().unwrap(), Token::Identifier(identifier) if identifier == "Vip"));
+        assert!(matches!(tokenizer.get_token().unwrap(), Token::From));
+        assert!(matches!(tokenizer.get_token().unwrap(), Token::ColumnType(Type::String)));
+        assert!(matches!(tokenizer.get_token().unwrap(), Token::EOF));
+    }
+
     #[test]
     fn insert() {
         let mut tokenizer = Tokenizer::create(String::from(
@@ -574,4 +754,112 @@ mod test {
         assert!(matches!(tokenizer.get_token().unwrap(), Token::Semicolon));
         assert!(matches!(tokenizer.get_token().unwrap(), Token::EOF));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn less() {
+        let mut tokenizer = Tokenizer::create(String::from("id < 5 <= 6"));
+
+        assert_eq!(tokenizer.get_token().unwrap(), Token::Identifier(String::from("id")));
+        assert_eq!(tokenizer.get_token().unwrap(), Token::Less);
+        assert_eq!(tokenizer.get_token().unwrap(), Token::NumberI64(5));
+        assert_eq!(tokenizer.get_token().unwrap(), Token::LessEqual);
+        assert_eq!(tokenizer.get_token().unwrap(), Token::NumberI64(6));
+        assert_eq!(tokenizer.get_token().unwrap(), Token::EOF);
+    }
+
+    #[test]
+    fn not_equal() {
+        let mut tokenizer = Tokenizer::create(String::from("id != 5 <> 6"));
+
+        assert_eq!(tokenizer.get_token().unwrap(), Token::Identifier(String::from("id")));
+        assert_eq!(tokenizer.get_token().unwrap(), Token::NotEqual);
+        assert_eq!(tokenizer.get_token().unwrap(), Token::NumberI64(5));
+        assert_eq!(tokenizer.get_token().unwrap(), Token::NotEqual);
+        assert_eq!(tokenizer.get_token().unwrap(), Token::NumberI64(6));
+        assert_eq!(tokenizer.get_token().unwrap(), Token::EOF);
+    }
+
+    #[test]
+    fn modulo() {
+        let mut tokenizer = Tokenizer::create(String::from("id % 2 == 0"));
+
+        assert_eq!(tokenizer.get_token().unwrap(), Token::Identifier(String::from("id")));
+        assert_eq!(tokenizer.get_token().unwrap(), Token::Percent);
+        assert_eq!(tokenizer.get_token().unwrap(), Token::NumberI64(2));
+        assert_eq!(tokenizer.get_token().unwrap(), Token::EqualEqual);
+        assert_eq!(tokenizer.get_token().unwrap(), Token::NumberI64(0));
+        assert_eq!(tokenizer.get_token().unwrap(), Token::EOF);
+    }
+
+    #[test]
+    fn number_separators() {
+        let mut tokenizer = Tokenizer::create(String::from("1_000_000 1_000.000_5"));
+
+        assert_eq!(tokenizer.get_token().unwrap(), Token::NumberI64(1_000_000));
+        assert_eq!(tokenizer.get_token().unwrap(), Token::NumberF64(1_000.000_5));
+        assert_eq!(tokenizer.get_token().unwrap(), Token::EOF);
+    }
+
+    #[test]
+    fn string_escapes() {
+        let mut tokenizer = Tokenizer::create(String::from(r#""Say \"hi\"\n\tC:\\ \r""#));
+        assert_eq!(tokenizer.get_token().unwrap(), Token::String(String::from("Say \"hi\"\n\tC:\\ \r")));
+        assert_eq!(tokenizer.get_token().unwrap(), Token::EOF);
+
+        //LIKE wildcards escapes are left for the LIKE pattern
+        let mut tokenizer = Tokenizer::create(String::from(r#""100\%""#));
+        assert_eq!(tokenizer.get_token().unwrap(), Token::String(String::from("100\\%")));
+
+        let mut tokenizer = Tokenizer::create(String::from(r#""ab\q""#));
+        match tokenizer.get_token() {
+            Err(SimpleDbError::IllegalToken(location, _)) => assert_eq!(location.column_index, 3),
+            _ => panic!("Expected illegal token")
+        }
+
+        let mut tokenizer = Tokenizer::create(String::from(r#""ab\"#));
+        assert!(matches!(tokenizer.get_token(), Err(SimpleDbError::MalformedString(_, _))));
+    }
+
+    #[test]
+    fn number_exponent() {
+        let mut tokenizer = Tokenizer::create(String::from("1e6 1.5e-3 2E+2 1_000e1_0"));
+
+        assert_eq!(tokenizer.get_token().unwrap(), Token::NumberF64(1e6));
+        assert_eq!(tokenizer.get_token().unwrap(), Token::NumberF64(1.5e-3));
+        assert_eq!(tokenizer.get_token().unwrap(), Token::NumberF64(2e2));
+        assert_eq!(tokenizer.get_token().unwrap(), Token::NumberF64(1_000e10));
+        assert_eq!(tokenizer.get_token().unwrap(), Token::EOF);
+    }
+
+    #[test]
+    fn number_out_of_range() {
+        let mut tokenizer = Tokenizer::create(String::from("1e999"));
+        assert!(matches!(tokenizer.get_token(), Err(SimpleDbError::MalformedNumber(_, _))));
+    }
+
+    #[test]
+    fn number_exponent_malformed() {
+        let mut tokenizer = Tokenizer::create(String::from("10 > 1e"));
+        assert_eq!(tokenizer.get_token().unwrap(), Token::NumberI64(10));
+        assert_eq!(tokenizer.get_token().unwrap(), Token::Greater);
+
+        //Location after the exponent character
+        match tokenizer.get_token() {
+            Err(SimpleDbError::MalformedNumber(location, _)) => assert_eq!(location.column_index, 7),
+            _ => panic!("Expected malformed number")
+        }
+
+        for number in ["1e+", "1.5e-", "1e_5"] {
+            let mut tokenizer = Tokenizer::create(String::from(number));
+            assert!(matches!(tokenizer.get_token(), Err(SimpleDbError::MalformedNumber(_, _))));
+        }
+    }
+
+    #[test]
+    fn number_separators_invalid_position() {
+        for number in ["1__000", "1000_", "1_.5", "1._5", "1.5_"] {
+            let mut tokenizer = Tokenizer::create(String::from(number));
+            assert!(matches!(tokenizer.get_token(), Err(SimpleDbError::MalformedNumber(_, _))));
+        }
+    }
+}
@@ -1,23 +1,30 @@
 use crate::database::databases::Databases;
 use crate::simple_db::Context;
-use crate::sql::parser::expression::Expression;
+use crate::sql::parser::expression::{BinaryOperator, Expression, UnaryOperator};
 use crate::sql::parser::statement::{CreateTableStatement, DeleteStatement, InsertStatement, SelectStatement, Statement, UpdateStatement};
 use crate::table::table::Table;
-use crate::{CreateIndexStatement, Sort};
+use crate::users::users::Users;
+use crate::table::selection::Selection;
+use crate::sql::plan::RangeScan;
+use crate::sql::execution::scalar_functions;
+use crate::{CreateIndexStatement, DropIndexStatement, Sort};
 use shared::SimpleDbError::UnknownColumn;
 use shared::{SimpleDbError, Type};
 use std::sync::Arc;
 
 pub struct StatementValidator {
     databases: Arc<Databases>,
+    users: Arc<Users>,
 }
 
 impl StatementValidator {
     pub fn create(
         databases: &Arc<Databases>,
+        users: &Arc<Users>,
     ) -> StatementValidator {
         StatementValidator {
             databases: databases.clone(),
+            users: users.clone(),
         }
     }
 
@@ -36,16 +43,88 @@ impl StatementValidator {
             Statement::Delete(statement) => self.validate_delete(context.database(), statement),
             Statement::Insert(statement) => self.validate_insert(context.database(), statement),
             Statement::CreateDatabase(database_name) => self.validate_create_database(database_name),
-            Statement::ShowIndexes(table_name) => self.validate_show_indexes(context.database(), table_name),
+            Statement::ShowIndexes(table_name) |
+            Statement::ShowStats(table_name) => self.validate_show_indexes(context.database(), table_name),
             Statement::Describe(table) => self.validate_describe(context, table),
-            Statement::StartTransaction |
+            Statement::DropTable(table) => self.validate_drop_table(context, table),
+            Statement::TruncateTable(table) => self.validate_drop_table(context, table),
+            Statement::Vacuum(Some(table)) => self.validate_drop_table(context, table),
+            Statement::AlterTable(statement) => self.validate_drop_table(context, &statement.table_name),
+            Statement::DropIndex(statement) => self.validate_drop_index(context, statement),
+            Statement::CreateUser(user_name, _) => self.validate_create_user(user_name),
+            Statement::Grant(database_name, user_name) => self.validate_grant(database_name, user_name),
+            Statement::Use(database_name) => self.databases.get_database_or_err(database_name).map(|_| ()),
+            Statement::Vacuum(None) |
+            Statement::CheckDatabase |
+            Statement::StartTransaction(_) |
             Statement::ShowDatabases |
             Statement::ShowTables |
+            Statement::ShowTransactionStatus |
+            Statement::ShowTransaction |
+            Statement::ShowTransactions |
+            Statement::Backup(_) |
+            Statement::Savepoint(_) |
+            Statement::RollbackToSavepoint(_) |
+            Statement::ReleaseSavepoint(_) |
             Statement::Rollback |
             Statement::Commit => Ok(()),
         }
     }
 
+    //Used by SimpleDb::scan_table() and SimpleDb::scan_range(), which don't go through a SELECT statement
+    pub fn validate_scan(
+        &self,
+        context: &Context,
+        table_name: &str,
+        selection: &Selection,
+        range: Option<&RangeScan>,
+    ) -> Result<(), SimpleDbError> {
+        if !context.has_transaction() {
+            return Err(SimpleDbError::InvalidContext("A Transaction should be supplied"));
+        }
+        if !context.has_database() {
+            return Err(SimpleDbError::InvalidContext("A Database should be supplied"));
+        }
+        if let Some(user_name) = context.get_user() {
+            if !self.users.has_access(user_name, context.database()) {
+                return Err(SimpleDbError::AccessDenied(user_name.to_string(), context.database().to_string()));
+            }
+        }
+        if selection.is_aggregate() {
+            return Err(SimpleDbError::MalformedQuery(String::from("Aggregates cannot be scanned")));
+        }
+        if matches!(selection, Selection::Expressions(_)) {
+            return Err(SimpleDbError::MalformedQuery(String::from("Functions cannot be scanned")));
+        }
+
+        let database = self.databases.get_database_or_err(context.database())?;
+        let table = database.get_table_or_err(table_name)?;
+        table.validate_selection(selection)?;
+
+        match range {
+            Some(range) => self.validate_range_scan(&table, range),
+            None => Ok(()),
+        }
+    }
+
+    //The range has to be done on an indexed column, with literal values that can be compared with the column
+    fn validate_range_scan(&self, table: &Arc<Table>, range: &RangeScan) -> Result<(), SimpleDbError> {
+        let schema = table.get_schema();
+        let column = schema.get_column_or_err(&range.column_name)?;
+        if !column.is_primary && !schema.is_secondary_indexed(&range.column_name) {
+            return Err(SimpleDbError::MalformedQuery(format!("Column {} should be indexed to be scanned by range", range.column_name)));
+        }
+
+        for range_value in [&range.start, &range.end].into_iter().flatten() {
+            match range_value {
+                Expression::Literal(value) if value.get_type().can_be_coerced_to(&column.column_type) => {},
+                _ => return Err(SimpleDbError::MalformedQuery(format!("Range values should be literals of the type of {}", range.column_name))),
+            }
+        }
+
+        Ok(())
+    }
+
     fn validate_create_secondary_index(
         &self,
         statement: &CreateIndexStatement,
@@ -53,7 +132,24 @@ impl StatementValidator {
     ) -> Result<(), SimpleDbError> {
         let database = self.databases.get_database_or_err(database_name)?;
         let table = database.get_table_or_err(&statement.table_name)?;
-        table.validate_create_index(&statement.column_name)
+        table.validate_create_index(&statement.column_names, statement.unique, statement.wait)
+    }
+
+    fn validate_create_user(&self, user_name: &str) -> Result<(), SimpleDbError> {
+        if self.users.exists(user_name) {
+            Err(SimpleDbError::UserAlreadyExists(user_name.to_string()))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn validate_grant(&self, database_name: &str, user_name: &str) -> Result<(), SimpleDbError> {
+        self.databases.get_database_or_err(database_name)?;
+        if !self.users.exists(user_name) {
+            return Err(SimpleDbError::UserNotFound(user_name.to_string()));
+        }
+
+        Ok(())
     }
 
     fn validate_create_database(
@@ -75,10 +171,57 @@ impl StatementValidator {
         let table = database.get_table_or_err(&statement.table_name)?;
         self.validate_where_expression(&statement.where_expr, &table)?;
         self.validate_sort(&table, &statement.sort)?;
+        self.validate_group_by(&table, statement)?;
         table.validate_selection(&statement.selection)?;
+        if statement.offset < 0 {
+            return Err(SimpleDbError::MalformedQuery(String::from("OFFSET cannot be negative")));
+        }
         Ok(())
     }
 
+    fn validate_group_by(
+        &self,
+        table: &Arc<Table>,
+        statement: &SelectStatement
+    ) -> Result<(), SimpleDbError> {
+        let group_by = statement.group_by.as_ref();
+        let schema = table.get_schema();
+
+        for column_name in group_by.into_iter().flatten() {
+            schema.get_column_or_err(column_name)?;
+        }
+        //Grouped rows only contain the selected group by columns and the aggregates
+        if let (Some(_), Some(sort), Selection::Aggregate(columns, _)) = (group_by, &statement.sort, &statement.selection) {
+            for column_name in sort.get_column_names() {
+                if !columns.contains(&column_name) {
+                    return Err(SimpleDbError::MalformedQuery(format!(
+                        "Column {} must be selected to be used in ORDER BY with GROUP BY", column_name
+                    )));
+                }
+            }
+        }
+
+        match &statement.selection {
+            Selection::Aggregate(columns, _) => {
+                for column_name in columns {
+                    if !group_by.map_or(false, |group_by| group_by.contains(column_name)) {
+                        return Err(SimpleDbError::MalformedQuery(format!(
+                            "Column {} must appear in GROUP BY or be used in an aggregate function", column_name
+                        )));
+                    }
+                }
+                Ok(())
+            },
+            Selection::All if group_by.is_some() => Err(SimpleDbError::MalformedQuery(String::from(
+                "Cannot select * with GROUP BY"
+            ))),
+            Selection::Expressions(_) if group_by.is_some() => Err(SimpleDbError::MalformedQuery(String::from(
+                "Functions cannot be selected with GROUP BY"
+            ))),
+            _ => Ok(())
+        }
+    }
+
     fn validate_sort(
         &self,
         table: &Arc<Table>,
@@ -87,7 +230,9 @@ impl StatementValidator {
         match sort {
             Some(sort) => {
                 let schema = table.get_schema();
-                schema.get_column_or_err(&sort.column_name)?;
+                for column_name in sort.get_column_names() {
+                    schema.get_column_or_err(&column_name)?;
+                }
                 Ok(())
             },
             None => {
@@ -108,10 +253,13 @@ impl StatementValidator {
         for (updated_column_name, updated_column_value_expr) in &statement.updated_values {
             let column_data = table.get_column(updated_column_name)
                 .ok_or(SimpleDbError::ColumnNotFound(updated_column_name.clone()))?;
-            let expression_type_result = self.validate_expression(updated_column_value_expr, &table)?;
+            let expression_type_result = get_expression_type(updated_column_value_expr, &table)?;
 
-            if !expression_type_result.can_be_casted(&column_data.column_type) {
-                return Err(SimpleDbError::MalformedQuery(String::from("SET expression should produce a column value")))
+            if !expression_type_result.can_be_coerced_to(&column_data.column_type) {
+                return Err(SimpleDbError::InvalidType(updated_column_name.clone()))
+            }
+            if expression_type_result.is_null() && table.is_not_null_column(updated_column_name) {
+                return Err(SimpleDbError::NullConstraintViolation(updated_column_name.clone()))
             }
         }
 
@@ -135,7 +283,11 @@ impl StatementValidator {
     ) -> Result<(), SimpleDbError> {
         let database = self.databases.get_database_or_err(database_name)?;
         let table = database.get_table_or_err(statement.table_name.as_str())?;
-        table.validate_insert_column_values(&statement.values)
+        //Every row is validated before inserting any of them
+        for row_values in &statement.values {
+            table.validate_insert_column_values(row_values)?;
+        }
+        Ok(())
     }
 
     fn validate_create_table(
@@ -169,7 +321,7 @@ impl StatementValidator {
                     return Err(SimpleDbError::MalformedQuery(String::from("Expression shouldn't produce a constant value")));
                 }
 
-                let type_produced = self.validate_expression(expression, &table)?;
+                let type_produced = get_expression_type(expression, &table)?;
                 if !matches!(type_produced, Type::Boolean) {
                     Err(SimpleDbError::MalformedQuery(String::from("Expression should produce a boolean")))
                 } else {
@@ -186,62 +338,160 @@ impl StatementValidator {
         Ok(())
     }
 
-    fn validate_expression(
-        &self,
-        expression: &Expression,
-        table: &Arc<Table>
-    ) -> Result<Type, SimpleDbError> {
-        match expression {
-            Expression::Binary(operator, left, right) => {
-                let type_left = self.validate_expression(left, table)?;
-                let type_right = self.validate_expression(right, table)?;
-
-                if operator.is_logical() &&
-                    matches!(type_left, Type::Boolean) &&
-                    matches!(type_right, Type::Boolean) {
-                    Ok(Type::Boolean)
-                } else if operator.is_arithmetic() &&
-                    type_left.is_number() &&
-                    type_right.is_number() {
-
-                    if type_left.is_fp_number() || type_right.is_fp_number() {
-                        Ok(Type::F64)
-                    } else if type_left.is_signed_integer_number() || type_right.is_signed_integer_number() {
-                        Ok(Type::I64)
-                    } else {
-                        Ok(Type::U64)
-                    }
-                } else if operator.is_comparation() && type_left.is_comparable(&type_right) {
-                    Ok(Type::Boolean)
-                } else {
-                    Err(SimpleDbError::MalformedQuery(String::from("Expression produces wrong type")))
-                }
-            },
-            Expression::Unary(_, expr) => {
-                let produced_type = self.validate_expression(expr, table)?;
-                if !produced_type.is_number() {
-                    Err(SimpleDbError::MalformedQuery(String::from("Expression should produce a number")))
-                } else {
-                    Ok(produced_type)
-                }
-            }
-            Expression::Identifier(table_name) => {
-                table.get_column(table_name)
-                    .ok_or(UnknownColumn(table_name.clone()))
-                    .map(|it| it.column_type)
-            },
-            Expression::Literal(value) => Ok(value.get_type()),
-        }
+    fn validate_drop_table(&self, context: &Context, table_name: &str) -> Result<(), SimpleDbError> {
+        let database = self.databases.get_database_or_err(context.database())?;
+        database.get_table_or_err(table_name)?;
+        Ok(())
+    }
+
+    fn validate_drop_index(&self, context: &Context, statement: &DropIndexStatement) -> Result<(), SimpleDbError> {
+        let database = self.databases.get_database_or_err(context.database())?;
+        let table = database.get_table_or_err(&statement.table_name)?;
+        table.validate_drop_index(&statement.column_name)
     }
 
     fn validate_context(&self, context: &Context, statement: &Statement) -> Result<(), SimpleDbError> {
+        if statement.get_n_parameters() > 0 {
+            return Err(unbound_parameters_error());
+        }
         if statement.requires_transaction() && !context.has_transaction() {
             return Err(SimpleDbError::InvalidContext("A Transaction should be supplied"));
         }
         if statement.requires_database() && !context.has_database() {
             return Err(SimpleDbError::InvalidContext("A Database should be supplied"));
         }
+        if let Some(user_name) = context.get_user() {
+            self.validate_user_access(user_name, context, statement)?;
+        }
+
+        Ok(())
+    }
+
+    //Contexts without a user belong to the administrator, which can access every database
+    fn validate_user_access(&self, user_name: &str, context: &Context, statement: &Statement) -> Result<(), SimpleDbError> {
+        let access_denied = |database_name: &str| SimpleDbError::AccessDenied(user_name.to_string(), database_name.to_string());
+
+        match statement {
+            Statement::CreateUser(_, _) |
+            Statement::ShowTransactions |
+            Statement::Backup(_) => return Err(access_denied("*")),
+            Statement::Grant(database_name, _) |
+            Statement::CreateDatabase(database_name) => return Err(access_denied(database_name)),
+            Statement::Use(database_name) if !self.users.has_access(user_name, database_name) => return Err(access_denied(database_name)),
+            _ => {}
+        };
+
+        if context.has_database() && !self.users.has_access(user_name, context.database()) {
+            return Err(access_denied(context.database()));
+        }
 
         Ok(())
     }
 }
+
+//Returns the type produced by the expression. Fails if the expression is not valid for the table
+pub(crate) fn get_expression_type(
+    expression: &Expression,
+    table: &Table
+) -> Result<Type, SimpleDbError> {
+    match expression {
+        Expression::Binary(operator, left, right) => {
+            let type_left = get_expression_type(left, table)?;
+            let type_right = get_expression_type(right, table)?;
+
+            if operator.is_logical() &&
+                matches!(type_left, Type::Boolean) &&
+                matches!(type_right, Type::Boolean) {
+                Ok(Type::Boolean)
+            } else if matches!(operator, BinaryOperator::Add) &&
+                (matches!(type_left, Type::String) || matches!(type_right, Type::String)) &&
+                (matches!(type_left, Type::String) || type_left.is_number()) &&
+                (matches!(type_right, Type::String) || type_right.is_number()) {
+                //String concatenation
+                Ok(Type::String)
+            } else if operator.is_arithmetic() &&
+                type_left.is_number() &&
+                type_right.is_number() {
+
+                if type_left.is_fp_number() || type_right.is_fp_number() {
+                    Ok(Type::F64)
+                } else if type_left.is_signed_integer_number() || type_right.is_signed_integer_number() {
+                    Ok(Type::I64)
+                } else {
+                    Ok(Type::U64)
+                }
+            } else if operator.is_comparation() && type_left.is_comparable(&type_right) {
+                Ok(Type::Boolean)
+            } else if operator.is_pattern_matching() &&
+                matches!(type_left, Type::String | Type::Null) &&
+                matches!(type_right, Type::String | Type::Null) {
+                Ok(Type::Boolean)
+            } else {
+                Err(SimpleDbError::MalformedQuery(String::from("Expression produces wrong type")))
+            }
+        },
+        Expression::Unary(UnaryOperator::IsNull, expr) |
+        Expression::Unary(UnaryOperator::IsNotNull, expr) => {
+            get_expression_type(expr, table)?;
+            Ok(Type::Boolean)
+        },
+        Expression::Unary(UnaryOperator::Not, expr) => {
+            let produced_type = get_expression_type(expr, table)?;
+            if !matches!(produced_type, Type::Boolean | Type::Null) {
+                Err(SimpleDbError::MalformedQuery(String::from("Expression should produce a boolean")))
+            } else {
+                Ok(Type::Boolean)
+            }
+        },
+        Expression::Unary(_, expr) => {
+            let produced_type = get_expression_type(expr, table)?;
+            if !produced_type.is_number() {
+                Err(SimpleDbError::MalformedQuery(String::from("Expression should produce a number")))
+            } else {
+                Ok(produced_type)
+            }
+        }
+        Expression::Identifier(qualifier, column_name) => {
+            table.get_schema().get_qualified_column(&table.table_name, qualifier, column_name)
+                .map(|it| it.column_type)
+        },
+        Expression::Literal(value) => Ok(value.get_type()),
+        Expression::Parameter(_) => Err(unbound_parameters_error()),
+        Expression::Function(function_name, args) => {
+            let args_types = args.iter()
+                .map(|arg| get_expression_type(arg, table))
+                .collect::<Result<Vec<Type>, SimpleDbError>>()?;
+            scalar_functions::get_result_type(function_name, args, &args_types)
+        },
+    }
+}
+
+fn unbound_parameters_error() -> SimpleDbError {
+    SimpleDbError::MalformedQuery(String::from("Parameters should be bound with Statement::bind() before executing the statement"))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::simple_db::Context;
+    use crate::test_utils::TestSimpleDb;
+    use shared::SimpleDbError;
+
+    #[test]
+    fn qualified_identifiers() {
+        let simple_db = TestSimpleDb::create("qualified_identifiers");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64);");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, dinero) VALUES (1, 10);");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, dinero) VALUES (2, 20);");
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        let rows = simple_db.execute_sql(&transaction.context(), "SELECT * FROM personas WHERE personas.id == 1;")
+            .data().all().unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].get_column_value("dinero").unwrap().get_i64().unwrap(), 10);
+
+        let result = simple_db.execute(&transaction.context(), simple_db.parse("SELECT * FROM personas WHERE coches.id == 1;").unwrap());
+        assert!(matches!(result, Err(SimpleDbError::UnknownColumn(column_name)) if column_name == "coches.id"));
+        transaction.commit().unwrap();
+    }
+}
@@ -1,10 +1,13 @@
 pub mod table;
 pub mod row;
+pub mod table_stats;
 
 pub(crate) mod table_descriptor;
 pub(crate) mod record;
 pub(crate) mod table_iterator;
 pub(crate) mod table_flags;
+pub(crate) mod row_cache;
+pub(crate) mod select_cache;
 pub mod block_row_iterator;
 pub mod schema;
 pub mod selection;
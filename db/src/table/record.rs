@@ -2,22 +2,38 @@ use std::collections::HashSet;
 use bytes::{Buf, BufMut, Bytes};
 use shared::ColumnId;
 
+//If set in the nº of columns, the insert time (u64) is serialized after it
+const RECORD_HAS_INSERT_TIME: u32 = 0x80000000;
+//If set in the nº of columns, the record contains the whole row, so it replaces the previous versions of the row
+const RECORD_IS_FULL_ROW: u32 = 0x40000000;
+
 //Represents the row data stored in the storage engine,
 //This might represent an incomplete set of data
-// Column ID (u16) | Column value length (u32) | Column value bytes |
+// Nº Columns (u32) | [Insert time (u64)] | Column ID (u16) | Column value length (u32) | Column value bytes |
 #[derive(Clone)]
 pub struct Record {
-    pub(crate) data_records: Vec<(ColumnId, Bytes)>
+    pub(crate) data_records: Vec<(ColumnId, Bytes)>,
+    pub(crate) insert_time: Option<u64>, //Millis since epoch. Only stored in tables with TTL
+    //Written by inserts. Columns missing in a full row are NULL instead of being taken from previous versions
+    pub(crate) is_full_row: bool,
 }
 
 impl Record {
     pub fn create(data_records: Vec<(ColumnId, Bytes)>) -> Record {
-        Record { data_records }
+        Record { data_records, insert_time: None, is_full_row: false }
     }
 
     //Missing records from other will be added
     //Repeated records will be replaced by other
+    //If other is a full row, it replaces self
     pub fn merge(&mut self, mut other: Record) {
+        if other.is_full_row {
+            *self = other;
+            return;
+        }
+        if other.insert_time.is_some() {
+            self.insert_time = other.insert_time;
+        }
         while let Some((other_column_id, other_column_value)) = other.data_records.pop() {
             match self.get_column_id_index(other_column_id) {
                 Some(self_column_id_index) => {
@@ -87,7 +103,7 @@ impl Record {
     }
 
     pub fn serialize_size(&self) -> usize {
-        let mut size_bytes = 0;
+        let mut size_bytes = if self.insert_time.is_some() { 8 } else { 0 };
         for (_, column_value) in &self.data_records {
             size_bytes += 2;
             size_bytes += 4;
@@ -99,7 +115,14 @@ impl Record {
 
     pub fn serialize(&self) -> Vec<u8> {
         let mut result = Vec::new();
-        result.put_u32_le(self.data_records.len() as u32);
+        let full_row_flag = if self.is_full_row { RECORD_IS_FULL_ROW } else { 0 };
+        match self.insert_time {
+            Some(insert_time) => {
+                result.put_u32_le(self.data_records.len() as u32 | RECORD_HAS_INSERT_TIME | full_row_flag);
+                result.put_u64_le(insert_time);
+            },
+            None => result.put_u32_le(self.data_records.len() as u32 | full_row_flag),
+        }
         for (column_id, column_value) in &self.data_records {
             result.put_u16_le(*column_id);
             result.put_u32_le(column_value.len() as u32);
@@ -114,6 +137,13 @@ impl Record {
     ) -> Record {
         let mut data_records: Vec<(ColumnId, Bytes)> = Vec::new();
         let mut n_columns = current_ptr.get_u32_le();
+        let mut insert_time = None;
+        let is_full_row = n_columns & RECORD_IS_FULL_ROW != 0;
+        n_columns &= !RECORD_IS_FULL_ROW;
+        if n_columns & RECORD_HAS_INSERT_TIME != 0 {
+            n_columns &= !RECORD_HAS_INSERT_TIME;
+            insert_time = Some(current_ptr.get_u64_le());
+        }
 
         for _ in 0..n_columns {
             let column_id = current_ptr.get_u16_le() as ColumnId;
@@ -124,21 +154,66 @@ impl Record {
             data_records.push((column_id, Bytes::from(column_value_bytes.to_vec())));
         }
 
-        Record { data_records }
+        Record { data_records, insert_time, is_full_row }
+    }
+
+    //Returns the nº of bytes taken by the record serialized at the start of bytes.
+    //If they are truncated, returns the nº of bytes needed to decode the part of the record that is missing
+    pub fn serialized_size_at(bytes: &[u8]) -> Result<usize, usize> {
+        let mut current_ptr = bytes;
+        let needed = |current_ptr: &[u8], n_bytes: usize| -> Result<(), usize> {
+            if current_ptr.remaining() < n_bytes {
+                Err(bytes.len() - current_ptr.remaining() + n_bytes)
+            } else {
+                Ok(())
+            }
+        };
+
+        needed(current_ptr, 4)?;
+        let mut n_columns = current_ptr.get_u32_le() & !RECORD_IS_FULL_ROW;
+        if n_columns & RECORD_HAS_INSERT_TIME != 0 {
+            n_columns &= !RECORD_HAS_INSERT_TIME;
+            needed(current_ptr, 8)?;
+            current_ptr.advance(8);
+        }
+
+        for _ in 0..n_columns {
+            needed(current_ptr, 6)?;
+            current_ptr.advance(2);
+            let column_value_length = current_ptr.get_u32_le() as usize;
+            needed(current_ptr, column_value_length)?;
+            current_ptr.advance(column_value_length);
+        }
+
+        Ok(bytes.len() - current_ptr.remaining())
+    }
+
+    //Reads the insert time without decoding the columns
+    pub fn deserialize_insert_time(bytes: &[u8]) -> Option<u64> {
+        let mut current_ptr = bytes;
+        if current_ptr.remaining() >= 12 && current_ptr.get_u32_le() & RECORD_HAS_INSERT_TIME != 0 {
+            Some(current_ptr.get_u64_le())
+        } else {
+            None
+        }
     }
 
     pub fn builder() -> RecordBuilder {
-        RecordBuilder { data_records: Vec::new() }
+        RecordBuilder { data_records: Vec::new(), insert_time: None }
     }
 }
 
  #[derive(Clone)]
 pub struct RecordBuilder {
-    data_records: Vec<(ColumnId, Bytes)>
+    data_records: Vec<(ColumnId, Bytes)>,
+    insert_time: Option<u64>,
 }
 
 impl RecordBuilder {
     pub fn add_record(&mut self, mut other: Record) {
+        if self.insert_time.is_none() {
+            self.insert_time = other.insert_time;
+        }
         while let Some((column_id, other_value)) = other.data_records.pop() {
             self.add_column(column_id, other_value);
         }
@@ -176,6 +251,6 @@ impl RecordBuilder {
     }
 
     pub fn build(self) -> Record {
-        Record { data_records: self.data_records }
+        Record { data_records: self.data_records, insert_time: self.insert_time, is_full_row: false }
     }
 }
\ No newline at end of file
@@ -1,12 +1,25 @@
 use crate::table::selection::Selection;
 use crate::table::record::{Record, RecordBuilder};
 use crate::table::schema::Schema;
-use bytes::{BufMut, Bytes};
-use shared::{SimpleDbError, Value};
+use bytes::{Buf, BufMut, Bytes};
+use shared::{ColumnId, DecodeError, DecodeErrorType, SimpleDbError, Type, Value};
 pub use std::collections::HashSet;
 use std::fmt;
 use std::fmt::Formatter;
 
+//Format of the bytes produced by Row::serialize()
+//V0 only contains the record. It was used before the format version was added, so it has no version prefix
+//V1 contains the format version byte followed by the record
+//V2 contains the format version byte, the columns kept after projecting the row and the record
+// Nº selected columns (u16) | Column ID (u16) | Record
+pub const ROW_FORMAT_V0: u8 = 0;
+pub const ROW_FORMAT_V1: u8 = 1;
+pub const ROW_FORMAT_V2: u8 = 2;
+pub const CURRENT_ROW_FORMAT: u8 = ROW_FORMAT_V2;
+
+//Nº selected columns of rows that haven't been projected
+const EVERY_COLUMN_SELECTED: u16 = u16::MAX;
+
 pub trait RowIterator {
     fn next(&mut self) -> Result<Option<Row>, SimpleDbError>;
 }
@@ -15,6 +28,8 @@ pub trait RowIterator {
 pub struct Row {
     pub(crate) storage_engine_record: Record,
     pub(crate) primary_column_value: Value,
+    //Columns kept after projecting the row. None if every column of the schema can be read
+    pub(crate) selected_columns_id: Option<HashSet<ColumnId>>,
 
     pub(crate) schema: Schema
 }
@@ -26,6 +41,7 @@ impl Row {
         schema: Schema,
     ) -> Row {
         Row {
+            selected_columns_id: None,
             storage_engine_record,
             primary_column_value,
             schema,
@@ -34,10 +50,12 @@ impl Row {
 
     pub fn project_selection(&mut self, selection: &Selection) {
         match selection {
+            Selection::Aggregate(_, _) |
+            Selection::Expressions(_) |
             Selection::Some(_) => {
-                self.storage_engine_record.project_selection(&selection.to_columns_id(&self.schema)
-                    .unwrap().into_iter()
-                    .collect());
+                let selected_columns_id = selection.to_columns_id(&self.schema).unwrap();
+                self.storage_engine_record.project_selection(&selected_columns_id);
+                self.selected_columns_id = Some(selected_columns_id.into_iter().collect());
             }
             Selection::All => {}
         }
@@ -59,45 +77,186 @@ impl Row {
         }
     }
 
-    pub fn deserialize(bytes: &mut &[u8], schema: &Schema) -> Row {
-        let primary_column = schema.get_primary_column();
+    //Unlike get_column_value(), columns not selected by the query return ColumnNotFound instead of NULL.
+    //The value shares the bytes of the row, they are not copied
+    pub fn get(&self, column_name: &str) -> Result<Value, SimpleDbError> {
+        match self.get_selected_column_bytes(column_name)? {
+            (Some(column_bytes), column_type) => Value::create(column_bytes.clone(), column_type),
+            (None, _) => Ok(Value::create_null()),
+        }
+    }
+
+    //NULL values or values that cannot be converted to the type return InvalidType
+    pub fn get_i64(&self, column_name: &str) -> Result<i64, SimpleDbError> {
+        self.get(column_name)?.get_i64()
+            .map_err(|_| SimpleDbError::InvalidType(column_name.to_string()))
+    }
+
+    pub fn get_f64(&self, column_name: &str) -> Result<f64, SimpleDbError> {
+        self.get(column_name)?.get_f64()
+            .map_err(|_| SimpleDbError::InvalidType(column_name.to_string()))
+    }
+
+    //Borrows the string from the bytes of the row
+    pub fn get_str(&self, column_name: &str) -> Result<&str, SimpleDbError> {
+        match self.get_selected_column_bytes(column_name)? {
+            (Some(column_bytes), Type::String) => std::str::from_utf8(column_bytes)
+                .map_err(|_| SimpleDbError::InvalidType(column_name.to_string())),
+            _ => Err(SimpleDbError::InvalidType(column_name.to_string())),
+        }
+    }
+
+    //Returns None if the column is NULL
+    fn get_selected_column_bytes(&self, column_name: &str) -> Result<(Option<&Bytes>, Type), SimpleDbError> {
+        let column = self.schema.get_column(column_name)
+            .ok_or(SimpleDbError::ColumnNotFound(column_name.to_string()))?;
+
+        match &self.selected_columns_id {
+            Some(selected_columns_id) if !selected_columns_id.contains(&column.column_id) => {
+                Err(SimpleDbError::ColumnNotFound(column_name.to_string()))
+            },
+            _ => Ok((self.storage_engine_record.get_column_bytes(column.column_id), column.column_type)),
+        }
+    }
+
+    //Reads the format version prefix and decodes the rest of the row with it. V0 rows start with the record,
+    //so they are detected when the bytes can't be decoded as a V1 row, but they can be decoded as a record
+    pub fn deserialize(bytes: &mut &[u8], schema: &Schema) -> Result<Row, SimpleDbError> {
+        let format_version = match bytes.first() {
+            Some(&ROW_FORMAT_V2) if Self::selection_serialized_size_at(&bytes[1..])
+                .is_ok_and(|selection_size| Record::serialized_size_at(&bytes[1 + selection_size..]).is_ok()) => ROW_FORMAT_V2,
+            Some(&ROW_FORMAT_V1) if Record::serialized_size_at(&bytes[1..]).is_ok() => ROW_FORMAT_V1,
+            Some(_) if Record::serialized_size_at(bytes).is_ok() => ROW_FORMAT_V0,
+            Some(&ROW_FORMAT_V1) => ROW_FORMAT_V1,
+            Some(&ROW_FORMAT_V2) => ROW_FORMAT_V2,
+            Some(format_version) => return Err(SimpleDbError::UnknownRowFormatVersion(*format_version)),
+            None => return Err(Self::truncated_row_error(1, 0)),
+        };
+        if format_version != ROW_FORMAT_V0 {
+            bytes.advance(1);
+        }
+
+        Self::deserialize_with_format(bytes, schema, format_version)
+    }
+
+    //Used to decode rows whose bytes don't contain the format version prefix, like V0 rows
+    pub fn deserialize_with_format(
+        bytes: &mut &[u8],
+        schema: &Schema,
+        format_version: u8
+    ) -> Result<Row, SimpleDbError> {
+        let selected_columns_id = match format_version {
+            ROW_FORMAT_V0 |
+            ROW_FORMAT_V1 => None,
+            ROW_FORMAT_V2 => Self::deserialize_selection(bytes)?,
+            _ => return Err(SimpleDbError::UnknownRowFormatVersion(format_version)),
+        };
+        Record::serialized_size_at(bytes)
+            .map_err(|expected_size| Self::truncated_row_error(expected_size, bytes.len()))?;
         let record = Record::deserialize(bytes);
+
+        let primary_column = schema.get_primary_column();
         let primary_column_value = Value::create(
             record.get_column_bytes(primary_column.column_id).unwrap().clone(),
             primary_column.column_type
-        ).unwrap();
+        )?;
 
-        Row {
+        Ok(Row {
             storage_engine_record: record,
+            selected_columns_id,
             schema: schema.clone(),
             primary_column_value
-        }
+        })
+    }
+
+    //Reencodes a row serialized with an older format to the current one
+    pub fn migrate(
+        bytes: &[u8],
+        schema: &Schema,
+        format_version: u8
+    ) -> Result<Vec<u8>, SimpleDbError> {
+        let row = Self::deserialize_with_format(&mut &bytes[..], schema, format_version)?;
+        Ok(row.serialize())
     }
 
-    pub fn serialize(self) -> Vec<u8> {
+    //The insert time is only used to expire rows stored in the storage engine, so it is not included.
+    //Serialized rows are sent to the clients, which don't expect it
+    pub fn serialize(mut self) -> Vec<u8> {
         let mut serialized: Vec<u8> = Vec::new();
+        self.storage_engine_record.insert_time = None;
+        serialized.put_u8(CURRENT_ROW_FORMAT);
+        match &self.selected_columns_id {
+            Some(selected_columns_id) => {
+                serialized.put_u16_le(selected_columns_id.len() as u16);
+                for column_id in selected_columns_id {
+                    serialized.put_u16_le(*column_id);
+                }
+            },
+            None => serialized.put_u16_le(EVERY_COLUMN_SELECTED),
+        }
         serialized.extend(self.storage_engine_record.serialize());
         serialized
     }
 
     pub fn serialized_size(&self) -> usize {
-        self.storage_engine_record.serialize_size() + 4
+        let insert_time_size = if self.storage_engine_record.insert_time.is_some() { 8 } else { 0 };
+        let selection_size = 2 + self.selected_columns_id.as_ref()
+            .map(|selected_columns_id| selected_columns_id.len() * 2)
+            .unwrap_or(0);
+        self.storage_engine_record.serialize_size() - insert_time_size + 4 + 1 + selection_size
+    }
+
+    fn deserialize_selection(bytes: &mut &[u8]) -> Result<Option<HashSet<ColumnId>>, SimpleDbError> {
+        Self::selection_serialized_size_at(bytes)
+            .map_err(|expected_size| Self::truncated_row_error(expected_size, bytes.len()))?;
+        let n_selected_columns = bytes.get_u16_le();
+        if n_selected_columns == EVERY_COLUMN_SELECTED {
+            return Ok(None);
+        }
+
+        Ok(Some((0..n_selected_columns)
+            .map(|_| bytes.get_u16_le() as ColumnId)
+            .collect()))
+    }
+
+    //Returns the expected size as an error if the bytes are truncated
+    fn selection_serialized_size_at(bytes: &[u8]) -> Result<usize, usize> {
+        if bytes.len() < 2 {
+            return Err(2);
+        }
+        let selection_size = match u16::from_le_bytes([bytes[0], bytes[1]]) {
+            EVERY_COLUMN_SELECTED => 2,
+            n_selected_columns => 2 + n_selected_columns as usize * 2,
+        };
+        if bytes.len() < selection_size {
+            return Err(selection_size);
+        }
+
+        Ok(selection_size)
+    }
+
+    fn truncated_row_error(expected_size: usize, actual_size: usize) -> SimpleDbError {
+        SimpleDbError::CannotDecodeRow(DecodeError {
+            error_type: DecodeErrorType::IllegalSize(expected_size, actual_size),
+            offset: 0,
+            index: 0,
+        })
     }
 
     pub fn deserialize_rows(
         row_bytes: &Vec<u8>,
         n_rows: usize,
         schema: &Schema
-    ) -> Vec<Row> {
+    ) -> Result<Vec<Row>, SimpleDbError> {
         let mut rows = Vec::new();
         let current_ptr = &mut row_bytes.as_slice();
 
         for _ in 0..n_rows {
-            let row = Row::deserialize(current_ptr, schema);
+            let row = Row::deserialize(current_ptr, schema)?;
             rows.push(row);
         }
 
-        rows
+        Ok(rows)
     }
 }
 
@@ -165,6 +324,7 @@ impl RowBuilder {
 
     pub fn build(self) -> Row {
         Row {
+            selected_columns_id: None,
             storage_engine_record: self.storage_record_builder.build(),
             primary_column_value: self.primary_value.unwrap(),
             schema: self.schema
@@ -192,4 +352,151 @@ impl RowIterator for MockRowIterator {
             Ok(None)
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use crate::table::record::Record;
+    use crate::table::row::{Row, CURRENT_ROW_FORMAT, ROW_FORMAT_V0, ROW_FORMAT_V1};
+    use crate::table::selection::Selection;
+    use crate::{Column, Schema};
+    use crate::simple_db::Context;
+    use crate::test_utils::TestSimpleDb;
+    use bytes::Bytes;
+    use shared::{SimpleDbError, Value};
+
+    #[test]
+    fn serialize_deserialize() {
+        let schema = schema();
+        let serialized = row(&schema, 1, 10).serialize();
+        assert_eq!(serialized[0], CURRENT_ROW_FORMAT);
+
+        let row = Row::deserialize(&mut serialized.as_slice(), &schema).unwrap();
+        assert_eq!(row.get_primary_column_value().get_i64().unwrap(), 1);
+        assert_eq!(row.get_column_value("dinero").unwrap().get_i64().unwrap(), 10);
+    }
+
+    #[test]
+    fn serialize_deserialize_projected() {
+        let schema = schema();
+        let mut row = row(&schema, 1, 10);
+        row.project_selection(&Selection::Some(vec![String::from("id")]));
+        let serialized_size = row.serialized_size();
+        let serialized = row.serialize();
+        assert_eq!(serialized.len(), serialized_size);
+
+        //Columns not selected are still not found once deserialized
+        let row = Row::deserialize(&mut serialized.as_slice(), &schema).unwrap();
+        assert_eq!(row.get_i64("id").unwrap(), 1);
+        assert!(matches!(row.get("dinero"), Err(SimpleDbError::ColumnNotFound(_))));
+    }
+
+    #[test]
+    fn serialize_without_insert_time() {
+        let schema = schema();
+        let mut row = row(&schema, 1, 10);
+        row.storage_engine_record.insert_time = Some(1000);
+        let serialized_size = row.serialized_size();
+        let serialized = row.serialize();
+        assert_eq!(serialized.len(), serialized_size);
+
+        let row = Row::deserialize(&mut serialized.as_slice(), &schema).unwrap();
+        assert_eq!(row.storage_engine_record.insert_time, None);
+        assert_eq!(row.get_column_value("dinero").unwrap().get_i64().unwrap(), 10);
+    }
+
+    #[test]
+    fn deserialize_v0() {
+        let schema = schema();
+        //V0 rows only contained the record
+        let serialized = row(&schema, 1, 10).storage_engine_record.serialize();
+
+        let row = Row::deserialize_with_format(&mut serialized.as_slice(), &schema, ROW_FORMAT_V0).unwrap();
+        assert_eq!(row.get_primary_column_value().get_i64().unwrap(), 1);
+        assert_eq!(row.get_column_value("dinero").unwrap().get_i64().unwrap(), 10);
+
+        let migrated = Row::migrate(&serialized, &schema, ROW_FORMAT_V0).unwrap();
+        assert_eq!(migrated[0], CURRENT_ROW_FORMAT);
+        let row = Row::deserialize(&mut migrated.as_slice(), &schema).unwrap();
+        assert_eq!(row.get_column_value("dinero").unwrap().get_i64().unwrap(), 10);
+    }
+
+    #[test]
+    fn deserialize_v1() {
+        let schema = schema();
+        let mut serialized = vec![ROW_FORMAT_V1];
+        serialized.extend(row(&schema, 1, 10).storage_engine_record.serialize());
+
+        let row = Row::deserialize(&mut serialized.as_slice(), &schema).unwrap();
+        assert_eq!(row.get_primary_column_value().get_i64().unwrap(), 1);
+        assert_eq!(row.get_i64("dinero").unwrap(), 10);
+    }
+
+    #[test]
+    fn deserialize_unknown_format() {
+        let schema = schema();
+        let mut serialized = row(&schema, 1, 10).serialize();
+        serialized[0] = 0xFF;
+
+        assert!(matches!(Row::deserialize(&mut serialized.as_slice(), &schema), Err(SimpleDbError::UnknownRowFormatVersion(0xFF))));
+    }
+
+    #[test]
+    fn deserialize_v0_without_format() {
+        let schema = schema();
+        let serialized = row(&schema, 1, 10).storage_engine_record.serialize();
+
+        let row = Row::deserialize(&mut serialized.as_slice(), &schema).unwrap();
+        assert_eq!(row.get_primary_column_value().get_i64().unwrap(), 1);
+        assert_eq!(row.get_column_value("dinero").unwrap().get_i64().unwrap(), 10);
+    }
+
+    #[test]
+    fn deserialize_truncated() {
+        let schema = schema();
+        assert!(matches!(Row::deserialize(&mut [].as_slice(), &schema), Err(SimpleDbError::CannotDecodeRow(_))));
+
+        let serialized = row(&schema, 1, 10).serialize();
+        let truncated = &serialized[..serialized.len() - 1];
+        assert!(matches!(Row::deserialize(&mut &truncated[..], &schema), Err(SimpleDbError::CannotDecodeRow(_))));
+    }
+
+    fn schema() -> Schema {
+        Schema::create(vec![
+            Column::create_primary("id"),
+            Column::create("dinero", 1),
+        ])
+    }
+
+    fn row(schema: &Schema, id: i64, dinero: i64) -> Row {
+        let mut record_builder = Record::builder();
+        record_builder.add_column(0, Bytes::from(id.to_le_bytes().to_vec()));
+        record_builder.add_column(1, Bytes::from(dinero.to_le_bytes().to_vec()));
+        Row::create(record_builder.build(), Value::create_i64(id), schema.clone())
+    }
+
+    #[test]
+    fn row_typed_accessors() {
+        let simple_db = TestSimpleDb::create("row_typed_accessors");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR, dinero F64, edad I64);");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, nombre, dinero) VALUES (1, \"Jaime\", 10.5);");
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        let rows = simple_db.execute_sql(&transaction.context(), "SELECT id, nombre, dinero, edad FROM personas;")
+            .data().all().unwrap();
+        assert_eq!(rows[0].get_i64("id").unwrap(), 1);
+        assert_eq!(rows[0].get_str("nombre").unwrap(), "Jaime");
+        assert_eq!(rows[0].get_f64("dinero").unwrap(), 10.5);
+        assert!(rows[0].get("edad").unwrap().is_null());
+        assert!(matches!(rows[0].get_i64("edad"), Err(SimpleDbError::InvalidType(_))));
+        assert!(matches!(rows[0].get_str("id"), Err(SimpleDbError::InvalidType(_))));
+        assert!(matches!(rows[0].get("apellido"), Err(SimpleDbError::ColumnNotFound(_))));
+
+        let rows = simple_db.execute_sql(&transaction.context(), "SELECT nombre FROM personas WHERE dinero > 1;")
+            .data().all().unwrap();
+        assert_eq!(rows[0].get_str("nombre").unwrap(), "Jaime");
+        assert!(matches!(rows[0].get("dinero"), Err(SimpleDbError::ColumnNotFound(_))));
+        transaction.commit().unwrap();
+    }
+}
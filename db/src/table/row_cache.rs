@@ -0,0 +1,278 @@
+use crate::table::record::Record;
+use bytes::Bytes;
+use shared::{ColumnId, SimpleDbOptions, TxnId};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::atomic::{AtomicU64, AtomicUsize};
+use std::sync::{Arc, Mutex};
+use storage::transactions::transaction::Transaction;
+
+//Caches rows by their primary key. Used by Table::get_by_primary_column()
+//Rows are only cached when they are the last committed values (see Storage::reads_last_committed_values()).
+//Any write to a primary key will invalidate its entry. The least recently used entry is evicted first
+pub struct RowCache {
+    entries: Mutex<RowCacheEntries>,
+    max_entries: usize,
+
+    //Incremented on every invalidation. Used to detect writes that happened while a row was being read from storage
+    generation: AtomicU64,
+
+    n_hits: AtomicUsize,
+    n_misses: AtomicUsize,
+}
+
+struct RowCacheEntries {
+    by_key: HashMap<Bytes, RowCacheEntry>,
+    by_last_access: BTreeMap<u64, Bytes>, //The first one is the least recently used
+    n_accesses: u64,
+}
+
+struct RowCacheEntry {
+    record: Record,
+    columns_id: Vec<ColumnId>, //Columns read from storage
+    txn_id: TxnId, //Transaction that read the row
+    last_access: u64,
+}
+
+impl RowCacheEntries {
+    fn touch(&mut self, primary_key: &Bytes) {
+        self.n_accesses += 1;
+        let last_access = self.n_accesses;
+        if let Some(entry) = self.by_key.get_mut(primary_key) {
+            self.by_last_access.remove(&entry.last_access);
+            self.by_last_access.insert(last_access, primary_key.clone());
+            entry.last_access = last_access;
+        }
+    }
+
+    fn remove(&mut self, primary_key: &Bytes) {
+        if let Some(entry) = self.by_key.remove(primary_key) {
+            self.by_last_access.remove(&entry.last_access);
+        }
+    }
+}
+
+impl RowCache {
+    pub fn create(options: &Arc<SimpleDbOptions>) -> RowCache {
+        RowCache {
+            max_entries: options.primary_row_cache_n_entries,
+            generation: AtomicU64::new(0),
+            n_misses: AtomicUsize::new(0),
+            n_hits: AtomicUsize::new(0),
+            entries: Mutex::new(RowCacheEntries {
+                by_last_access: BTreeMap::new(),
+                by_key: HashMap::new(),
+                n_accesses: 0,
+            }),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.max_entries > 0
+    }
+
+    //Returns the cached record projected to columns_id
+    pub fn get(
+        &self,
+        primary_key: &Bytes,
+        transaction: &Transaction,
+        columns_id: &Vec<ColumnId>
+    ) -> Option<Record> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.by_key.get(primary_key) {
+            Some(entry) => {
+                //Transactions started before the row was cached might not be able to see its value
+                if transaction.id() < entry.txn_id || !columns_id.iter().all(|it| entry.columns_id.contains(it)) {
+                    self.n_misses.fetch_add(1, Relaxed);
+                    return None;
+                }
+
+                let mut record = entry.record.clone();
+                record.project_selection(columns_id);
+                entries.touch(primary_key);
+                self.n_hits.fetch_add(1, Relaxed);
+                Some(record)
+            },
+            None => {
+                self.n_misses.fetch_add(1, Relaxed);
+                None
+            }
+        }
+    }
+
+    //generation should be taken before reading the row from storage.
+    //If there has been a write since then, the row won't be cached
+    pub fn put(
+        &self,
+        primary_key: Bytes,
+        record: Record,
+        columns_id: Vec<ColumnId>,
+        transaction: &Transaction,
+        generation: u64,
+    ) {
+        //Invalidations increment the generation before taking the lock
+        let mut entries = self.entries.lock().unwrap();
+        if self.generation.load(Relaxed) != generation {
+            return;
+        }
+
+        entries.remove(&primary_key);
+        entries.by_key.insert(primary_key.clone(), RowCacheEntry {
+            txn_id: transaction.id(),
+            last_access: 0,
+            columns_id,
+            record,
+        });
+        entries.touch(&primary_key);
+
+        while entries.by_key.len() > self.max_entries {
+            let (_, least_recently_used_key) = entries.by_last_access.pop_first().unwrap();
+            entries.by_key.remove(&least_recently_used_key);
+        }
+    }
+
+    pub fn invalidate(&self, primary_key: &Bytes) {
+        self.generation.fetch_add(1, Relaxed);
+        self.entries.lock().unwrap().remove(primary_key);
+    }
+
+    pub fn invalidate_all(&self) {
+        self.generation.fetch_add(1, Relaxed);
+        let mut entries = self.entries.lock().unwrap();
+        entries.by_key.clear();
+        entries.by_last_access.clear();
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Relaxed)
+    }
+
+    #[cfg(test)]
+    pub fn n_hits(&self) -> usize {
+        self.n_hits.load(Relaxed)
+    }
+
+    #[cfg(test)]
+    pub fn n_misses(&self) -> usize {
+        self.n_misses.load(Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::table::record::Record;
+    use crate::table::row_cache::RowCache;
+    use crate::simple_db::Context;
+    use crate::test_utils::TestSimpleDb;
+    use bytes::Bytes;
+    use shared::start_simpledb_options_builder;
+    use storage::transactions::transaction::Transaction;
+
+    #[test]
+    fn get_put_invalidate() {
+        let cache = RowCache::create(&start_simpledb_options_builder()
+            .primary_row_cache_n_entries(8)
+            .build_arc());
+        let key = Bytes::from(vec![1]);
+
+        assert!(cache.get(&key, &Transaction::create(1), &vec![0, 1]).is_none());
+        cache.put(key.clone(), record(), vec![0, 1], &Transaction::create(1), cache.generation());
+
+        assert!(cache.get(&key, &Transaction::create(1), &vec![0, 1]).is_some());
+        assert!(cache.get(&key, &Transaction::create(2), &vec![1]).is_some());
+        assert_eq!(cache.n_hits(), 2);
+        //Older transaction
+        assert!(cache.get(&key, &Transaction::create(0), &vec![0, 1]).is_none());
+        //Column not read
+        assert!(cache.get(&key, &Transaction::create(2), &vec![0, 2]).is_none());
+
+        cache.invalidate(&key);
+        assert!(cache.get(&key, &Transaction::create(2), &vec![0, 1]).is_none());
+        assert_eq!(cache.n_hits(), 2);
+        assert_eq!(cache.n_misses(), 4);
+    }
+
+    #[test]
+    fn put_after_write() {
+        let cache = RowCache::create(&start_simpledb_options_builder()
+            .primary_row_cache_n_entries(8)
+            .build_arc());
+        let key = Bytes::from(vec![1]);
+
+        let generation = cache.generation();
+        cache.invalidate(&key);
+        cache.put(key.clone(), record(), vec![0, 1], &Transaction::create(1), generation);
+
+        assert!(cache.get(&key, &Transaction::create(1), &vec![0, 1]).is_none());
+    }
+
+    #[test]
+    fn evict_least_recently_used() {
+        let cache = RowCache::create(&start_simpledb_options_builder()
+            .primary_row_cache_n_entries(2)
+            .build_arc());
+        let (key_a, key_b, key_c) = (Bytes::from(vec![1]), Bytes::from(vec![2]), Bytes::from(vec![3]));
+
+        cache.put(key_a.clone(), record(), vec![0, 1], &Transaction::create(1), cache.generation());
+        cache.put(key_b.clone(), record(), vec![0, 1], &Transaction::create(1), cache.generation());
+        assert!(cache.get(&key_a, &Transaction::create(1), &vec![0, 1]).is_some());
+        cache.put(key_c.clone(), record(), vec![0, 1], &Transaction::create(1), cache.generation());
+
+        assert!(cache.get(&key_b, &Transaction::create(1), &vec![0, 1]).is_none());
+        assert!(cache.get(&key_a, &Transaction::create(1), &vec![0, 1]).is_some());
+        assert!(cache.get(&key_c, &Transaction::create(1), &vec![0, 1]).is_some());
+    }
+
+    fn record() -> Record {
+        let mut record_builder = Record::builder();
+        record_builder.add_column(0, Bytes::from(vec![1]));
+        record_builder.add_column(1, Bytes::from(vec![2]));
+        record_builder.build()
+    }
+
+    #[test]
+    fn row_cache() {
+        let simple_db = TestSimpleDb::create_with_options("row_cache", {
+            let mut options_builder = shared::start_simpledb_options_builder();
+            options_builder.primary_row_cache_n_entries(2);
+            options_builder
+        });
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64);");
+        for id in 1..=3 {
+            simple_db.execute_in_transaction(&context, &format!("INSERT INTO personas (id, dinero) VALUES ({}, {});", id, id * 10));
+        }
+        let table = simple_db.get_databases().get_database_or_err("test").unwrap()
+            .get_table_or_err("personas").unwrap();
+        let select_dinero = |id: i64| {
+            let mut transaction = simple_db.begin(&context).unwrap();
+            let query = format!("SELECT dinero FROM personas WHERE id = {};", id);
+            let rows = simple_db.execute(&transaction.context(), simple_db.parse(&query).unwrap())
+                .unwrap().data().all().unwrap();
+            transaction.commit().unwrap();
+            rows[0].get_column_value("dinero").unwrap().get_i64().unwrap()
+        };
+
+        assert_eq!(select_dinero(1), 10);
+        assert_eq!(select_dinero(1), 10);
+        assert_eq!(table.row_cache.n_hits(), 1);
+
+        //Writes invalidate the cached row
+        simple_db.execute_in_transaction(&context, "UPDATE personas SET dinero = 11 WHERE id = 1;");
+        let n_misses = table.row_cache.n_misses();
+        assert_eq!(select_dinero(1), 11);
+        assert_eq!(select_dinero(1), 11);
+        assert_eq!(table.row_cache.n_misses(), n_misses + 1);
+
+        //Row 1 was used more recently than row 2, so row 2 is evicted when row 3 is cached
+        assert_eq!(select_dinero(2), 20);
+        assert_eq!(select_dinero(1), 11);
+        assert_eq!(select_dinero(3), 30);
+        let n_hits = table.row_cache.n_hits();
+        assert_eq!(select_dinero(1), 11);
+        assert_eq!(table.row_cache.n_hits(), n_hits + 1);
+        let n_misses = table.row_cache.n_misses();
+        assert_eq!(select_dinero(2), 20);
+        assert_eq!(table.row_cache.n_misses(), n_misses + 1);
+    }
+}
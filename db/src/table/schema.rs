@@ -1,7 +1,7 @@
 use bytes::{Buf, BufMut};
 use crossbeam_skiplist::SkipMap;
-use shared::SimpleDbError::ColumnNotFound;
-use shared::{utils, ColumnId, KeyspaceId, SimpleDbError, Type};
+use shared::SimpleDbError::{ColumnNotFound, UnknownColumn};
+use shared::{utils, ColumnId, KeyspaceId, SimpleDbError, Type, Value};
 use std::cmp::max;
 use std::hash::Hash;
 use std::sync::atomic::AtomicUsize;
@@ -13,6 +13,14 @@ pub struct Schema {
     primary_column_id: AtomicUsize, //We use atomic, so we can modify it when using non mutable references
     columns_by_id: SkipMap<ColumnId, Column>,
     columns_id_by_name: SkipMap<String, ColumnId>,
+    composite_indexes: SkipMap<KeyspaceId, CompositeIndex>,
+}
+
+//Secondary index over multiple columns. The index keys are built with composite_key::encode()
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompositeIndex {
+    pub keyspace_id: KeyspaceId,
+    pub columns_id: Vec<ColumnId>, //In the order of the index key
 }
 
 #[derive(Clone, Debug, PartialOrd, PartialEq)]
@@ -30,6 +38,7 @@ impl Schema {
             primary_column_id: AtomicUsize::new(0),
             columns_id_by_name: SkipMap::new(),
             columns_by_id: SkipMap::new(),
+            composite_indexes: SkipMap::new(),
         }
     }
 
@@ -48,6 +57,7 @@ impl Schema {
 
         Schema {
             primary_column_id: AtomicUsize::new(primary_column_id as usize),
+            composite_indexes: SkipMap::new(),
             columns_id_by_name,
             columns_by_id,
         }
@@ -65,21 +75,61 @@ impl Schema {
         self.columns_id_by_name.insert(column.column_name, column.column_id);
     }
 
+    pub(crate) fn remove_column(&self, column_id: ColumnId) {
+        if let Some(entry) = self.columns_by_id.remove(&column_id) {
+            self.columns_id_by_name.remove(&entry.value().column_name);
+        }
+    }
+
+    //The new name is added before removing the old one, so the column can always be found by one of them
+    pub(crate) fn rename_column(&self, column_id: ColumnId, new_name: &str) {
+        let mut renamed_column = self.columns_by_id.get(&column_id).unwrap().value().clone();
+        let old_name = renamed_column.column_name.clone();
+        renamed_column.column_name = new_name.to_string();
+
+        self.columns_by_id.insert(column_id, renamed_column);
+        self.columns_id_by_name.insert(new_name.to_string(), column_id);
+        self.columns_id_by_name.remove(&old_name);
+    }
+
     pub(crate) fn update_column_secondary_index(
         &self,
         column_id: ColumnId,
-        secondary_index_keyspace_id: KeyspaceId
+        secondary_index_keyspace_id: Option<KeyspaceId>
     ) {
         let mut column_to_update = self.columns_by_id.remove(&column_id)
             .unwrap()
             .value()
             .clone();
 
-        column_to_update.secondary_index_keyspace_id = Some(secondary_index_keyspace_id);
+        column_to_update.secondary_index_keyspace_id = secondary_index_keyspace_id;
 
         self.columns_by_id.insert(column_id, column_to_update);
     }
 
+    pub(crate) fn add_composite_index(&self, composite_index: CompositeIndex) {
+        self.composite_indexes.insert(composite_index.keyspace_id, composite_index);
+    }
+
+    pub fn get_composite_indexes(&self) -> Vec<CompositeIndex> {
+        self.composite_indexes.iter()
+            .map(|entry| entry.value().clone())
+            .collect()
+    }
+
+    pub fn get_composite_index(&self, columns_id: &Vec<ColumnId>) -> Option<CompositeIndex> {
+        self.composite_indexes.iter()
+            .find(|entry| entry.value().columns_id.eq(columns_id))
+            .map(|entry| entry.value().clone())
+    }
+
+    //Returns the columns indexed by the composite index, in the order of the index key
+    pub fn get_composite_index_columns(&self, composite_index: &CompositeIndex) -> Vec<Column> {
+        composite_index.columns_id.iter()
+            .map(|column_id| self.columns_by_id.get(column_id).unwrap().value().clone())
+            .collect()
+    }
+
     pub fn get_columns(&self) -> Vec<Column> {
         let mut columns = Vec::new();
         for column in self.columns_by_id.iter() {
@@ -106,6 +156,19 @@ impl Schema {
         }
     }
 
+    //Qualified columns (<table>.<column>) should be qualified with the name of the table of the schema
+    pub fn get_qualified_column(
+        &self,
+        table_name: &str,
+        qualifier: &Option<String>,
+        column_name: &str
+    ) -> Result<Column, SimpleDbError> {
+        match qualifier {
+            Some(qualifier) if qualifier != table_name => Err(UnknownColumn(format!("{}.{}", qualifier, column_name))),
+            _ => self.get_column(column_name).ok_or(UnknownColumn(column_name.to_string()))
+        }
+    }
+
     pub fn get_indexed_columns(&self) -> Vec<Column> {
         let mut columns = Vec::new();
         for column in self.columns_by_id.iter() {
@@ -139,6 +202,11 @@ impl Schema {
             serialized.extend(column.serialize());
         }
 
+        serialized.put_u32_le(self.composite_indexes.len() as u32);
+        for entry in self.composite_indexes.iter() {
+            serialized.extend(entry.value().serialize());
+        }
+
         serialized
     }
 
@@ -163,7 +231,17 @@ impl Schema {
             columns.push(Column::deserialize(keyspace_id, columns.len(), ptr)?);
         }
 
-        Ok(Schema::create(columns))
+        let schema = Schema::create(columns);
+
+        //Table descriptors written before composite indexes were added won't have them
+        if ptr.has_remaining() {
+            let n_composite_indexes = ptr.get_u32_le();
+            for _ in 0..n_composite_indexes {
+                schema.add_composite_index(CompositeIndex::deserialize(ptr));
+            }
+        }
+
+        Ok(schema)
     }
 }
 
@@ -173,6 +251,53 @@ impl Clone for Schema {
             primary_column_id: AtomicUsize::new(self.primary_column_id.load(Relaxed)),
             columns_id_by_name: utils::clone_skipmap(&self.columns_id_by_name),
             columns_by_id: utils::clone_skipmap(&self.columns_by_id),
+            composite_indexes: utils::clone_skipmap(&self.composite_indexes),
+        }
+    }
+}
+
+impl CompositeIndex {
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut serialized = Vec::new();
+        serialized.put_u64_le(self.keyspace_id as u64);
+        serialized.put_u16_le(self.columns_id.len() as u16);
+        for column_id in &self.columns_id {
+            serialized.put_u16_le(*column_id);
+        }
+        serialized
+    }
+
+    pub fn deserialize(ptr: &mut &[u8]) -> CompositeIndex {
+        let keyspace_id = ptr.get_u64_le() as KeyspaceId;
+        let n_columns = ptr.get_u16_le();
+        let mut columns_id = Vec::with_capacity(n_columns as usize);
+        for _ in 0..n_columns {
+            columns_id.push(ptr.get_u16_le() as ColumnId);
+        }
+
+        CompositeIndex { keyspace_id, columns_id }
+    }
+}
+
+//Column of a CREATE TABLE statement, or added to an existing table
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColumnDefinition {
+    pub column_name: String,
+    pub column_type: Type,
+    pub is_primary: bool,
+    pub default_value: Option<Value>,
+    pub is_not_null: bool,
+}
+
+impl ColumnDefinition {
+    //Nullable column without default value
+    pub fn create(column_name: &str, column_type: Type, is_primary: bool) -> ColumnDefinition {
+        ColumnDefinition {
+            column_name: column_name.to_string(),
+            default_value: None,
+            is_not_null: false,
+            column_type,
+            is_primary,
         }
     }
 }
@@ -277,7 +402,22 @@ impl Column {
 mod test {
     use std::sync::atomic::Ordering::Relaxed;
     use shared::Type;
-    use crate::{Column, Schema};
+    use crate::{Column, CompositeIndex, Schema};
+
+    #[test]
+    fn serialize_deserialize_composite_indexes() {
+        let schema_to_be_serialized = Schema::create(vec![
+            Column{ column_id: 1, column_type: Type::I64, column_name: String::from("a"), is_primary: true, secondary_index_keyspace_id: None },
+            Column{ column_id: 2, column_type: Type::String, column_name: String::from("b"), is_primary: false, secondary_index_keyspace_id: None },
+        ]);
+        schema_to_be_serialized.add_composite_index(CompositeIndex { keyspace_id: 3, columns_id: vec![2, 1] });
+
+        let serialized = schema_to_be_serialized.serialize();
+        let schema_deserialized = Schema::deserialize(&mut serialized.as_slice(), 1)
+            .unwrap();
+
+        assert_eq!(schema_deserialized.get_composite_indexes(), vec![CompositeIndex { keyspace_id: 3, columns_id: vec![2, 1] }]);
+    }
 
     #[test]
     fn serialize_deserialize() {
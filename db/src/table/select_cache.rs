@@ -0,0 +1,281 @@
+use crate::Row;
+use crossbeam_skiplist::SkipSet;
+use shared::{SimpleDbOptions, TxnId};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::atomic::{AtomicU64, AtomicUsize};
+use std::sync::{Arc, Mutex};
+use storage::transactions::transaction::Transaction;
+use storage::transactions::transaction_manager::IsolationLevel;
+
+//Caches the rows returned by SELECT statements of a table. Keyed by SelectStatement::cache_key() and the version
+//of the table. The version is incremented when a transaction that wrote to the table commits, or when the table is
+//altered, so uncommitted writes never change the cached results.
+//Results are only cached when they are the last committed values (see Storage::reads_last_committed_values()).
+//They are only returned to transactions whose snapshot includes them and that haven't written to the table.
+pub struct SelectCache {
+    entries: Mutex<SelectCacheEntries>,
+    max_entries: usize,
+
+    version: AtomicU64,
+    //Transactions with uncommitted writes to the table
+    writers: SkipSet<TxnId>,
+
+    n_hits: AtomicUsize,
+    n_misses: AtomicUsize,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct SelectCacheKey {
+    statement: String,
+    table_version: u64,
+}
+
+struct SelectCacheEntries {
+    by_key: HashMap<SelectCacheKey, SelectCacheEntry>,
+    insertion_order: VecDeque<SelectCacheKey>, //The oldest entry is evicted first
+}
+
+struct SelectCacheEntry {
+    rows: Vec<Row>,
+    txn_id: TxnId, //Transaction that executed the query
+}
+
+impl SelectCache {
+    pub fn create(options: &Arc<SimpleDbOptions>) -> SelectCache {
+        SelectCache {
+            entries: Mutex::new(SelectCacheEntries {
+                insertion_order: VecDeque::new(),
+                by_key: HashMap::new(),
+            }),
+            max_entries: options.select_cache_n_entries,
+            version: AtomicU64::new(0),
+            n_misses: AtomicUsize::new(0),
+            n_hits: AtomicUsize::new(0),
+            writers: SkipSet::new(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.max_entries > 0
+    }
+
+    pub fn get(&self, statement: &str, transaction: &Transaction) -> Option<Vec<Row>> {
+        let key = SelectCacheKey { statement: statement.to_string(), table_version: self.version() };
+        let entries = self.entries.lock().unwrap();
+
+        match entries.by_key.get(&key) {
+            //Transactions started before the result was cached might not be able to see it, transactions that have
+            //written to the table have to see their own writes, and read uncommitted ones see the other writes too
+            Some(entry) if transaction.id() >= entry.txn_id && !self.writers.contains(&transaction.id()) &&
+                matches!(transaction.isolation_level(), IsolationLevel::SnapshotIsolation) => {
+                self.n_hits.fetch_add(1, Relaxed);
+                Some(entry.rows.clone())
+            },
+            _ => {
+                self.n_misses.fetch_add(1, Relaxed);
+                None
+            }
+        }
+    }
+
+    //table_version should be taken before executing the query.
+    //If a transaction that wrote to the table has committed since then, the result won't be cached
+    pub fn put(
+        &self,
+        statement: String,
+        rows: Vec<Row>,
+        transaction: &Transaction,
+        table_version: u64,
+    ) {
+        if self.writers.contains(&transaction.id()) {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        if self.version() != table_version {
+            return;
+        }
+
+        let key = SelectCacheKey { statement, table_version };
+        let entry = SelectCacheEntry { txn_id: transaction.id(), rows };
+        if entries.by_key.insert(key.clone(), entry).is_none() {
+            entries.insertion_order.push_back(key);
+        }
+
+        while entries.by_key.len() > self.max_entries {
+            let oldest_key = entries.insertion_order.pop_front().unwrap();
+            entries.by_key.remove(&oldest_key);
+        }
+    }
+
+    //Called when the transaction writes to the table
+    pub fn on_write(&self, transaction: &Transaction) {
+        self.writers.insert(transaction.id());
+    }
+
+    //Called before the transaction gets committed, so the results cached before its writes stop being returned
+    //as soon as they are visible
+    pub fn on_commit(&self, txn_id: TxnId) {
+        if self.writers.contains(&txn_id) {
+            self.invalidate();
+        }
+    }
+
+    //Returns true if an active transaction has written the table
+    pub fn has_writers(&self) -> bool {
+        !self.writers.is_empty()
+    }
+
+    //Called when the transaction is committed or rolledback
+    pub fn on_transaction_finished(&self, txn_id: TxnId) {
+        self.writers.remove(&txn_id);
+    }
+
+    //Called when the table is altered or truncated, as those changes are visible right away
+    pub fn invalidate(&self) {
+        self.version.fetch_add(1, Relaxed);
+        let mut entries = self.entries.lock().unwrap();
+        entries.by_key.clear();
+        entries.insertion_order.clear();
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version.load(Relaxed)
+    }
+
+    #[cfg(test)]
+    pub fn n_hits(&self) -> usize {
+        self.n_hits.load(Relaxed)
+    }
+
+    #[cfg(test)]
+    pub fn n_misses(&self) -> usize {
+        self.n_misses.load(Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::table::select_cache::SelectCache;
+    use crate::simple_db::Context;
+    use crate::test_utils::{select_dinero_sum, TestSimpleDb};
+    use crate::transaction_handle::TransactionHandle;
+    use shared::start_simpledb_options_builder;
+    use storage::transactions::transaction::Transaction;
+
+    #[test]
+    fn get_put_commit() {
+        let cache = SelectCache::create(&start_simpledb_options_builder()
+            .select_cache_n_entries(8)
+            .build_arc());
+
+        cache.put(String::from("a"), Vec::new(), &Transaction::create(2), cache.version());
+        assert!(cache.get("a", &Transaction::create(2)).is_some());
+        assert!(cache.get("a", &Transaction::create(3)).is_some());
+        //Older snapshot
+        assert!(cache.get("a", &Transaction::create(1)).is_none());
+
+        //Uncommitted writes are only seen by its transaction
+        cache.on_write(&Transaction::create(3));
+        assert!(cache.get("a", &Transaction::create(3)).is_none());
+        assert!(cache.get("a", &Transaction::create(4)).is_some());
+
+        cache.on_commit(3);
+        cache.on_transaction_finished(3);
+        assert!(cache.get("a", &Transaction::create(4)).is_none());
+        assert_eq!(cache.n_hits(), 3);
+        assert_eq!(cache.n_misses(), 3);
+    }
+
+    #[test]
+    fn put_after_commit() {
+        let cache = SelectCache::create(&start_simpledb_options_builder()
+            .select_cache_n_entries(8)
+            .build_arc());
+
+        let table_version = cache.version();
+        cache.on_write(&Transaction::create(1));
+        cache.on_commit(1);
+        cache.on_transaction_finished(1);
+        cache.put(String::from("a"), Vec::new(), &Transaction::create(2), table_version);
+        assert!(cache.get("a", &Transaction::create(2)).is_none());
+
+        //Rolledback transactions don't change the version
+        let table_version = cache.version();
+        cache.on_write(&Transaction::create(3));
+        cache.on_transaction_finished(3);
+        cache.put(String::from("a"), Vec::new(), &Transaction::create(4), table_version);
+        assert!(cache.get("a", &Transaction::create(4)).is_some());
+    }
+
+    #[test]
+    fn evict_oldest_entry() {
+        let cache = SelectCache::create(&start_simpledb_options_builder()
+            .select_cache_n_entries(2)
+            .build_arc());
+
+        cache.put(String::from("c"), Vec::new(), &Transaction::create(1), cache.version());
+        cache.put(String::from("b"), Vec::new(), &Transaction::create(1), cache.version());
+        cache.put(String::from("a"), Vec::new(), &Transaction::create(1), cache.version());
+
+        assert!(cache.get("c", &Transaction::create(1)).is_none());
+        assert!(cache.get("b", &Transaction::create(1)).is_some());
+        assert!(cache.get("a", &Transaction::create(1)).is_some());
+    }
+
+    #[test]
+    fn select_cache() {
+        let simple_db = TestSimpleDb::create_with_options("select_cache", {
+            let mut options_builder = shared::start_simpledb_options_builder();
+            options_builder.select_cache_n_entries(8);
+            options_builder
+        });
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64);");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, dinero) VALUES (1, 10);");
+        let table = simple_db.get_databases().get_database_or_err("test").unwrap()
+            .get_table_or_err("personas").unwrap();
+
+        assert_eq!(select_dinero_sum(&simple_db, &context), 10);
+        assert_eq!(table.select_cache.n_hits(), 0);
+        assert_eq!(select_dinero_sum(&simple_db, &context), 10);
+        assert_eq!(table.select_cache.n_hits(), 1);
+
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, dinero) VALUES (2, 20);");
+        assert_eq!(select_dinero_sum(&simple_db, &context), 30);
+        assert_eq!(table.select_cache.n_hits(), 1);
+        assert_eq!(table.select_cache.n_misses(), 2);
+    }
+
+    #[test]
+    fn select_cache_uncommitted_writes() {
+        let simple_db = TestSimpleDb::create_with_options("select_cache_uncommitted_writes", {
+            let mut options_builder = shared::start_simpledb_options_builder();
+            options_builder.select_cache_n_entries(8);
+            options_builder
+        });
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64);");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, dinero) VALUES (1, 10);");
+        assert_eq!(select_dinero_sum(&simple_db, &context), 10);
+        let select_sum = |transaction: &TransactionHandle| {
+            let rows = simple_db.execute_sql(&transaction.context(), "SELECT SUM(dinero) FROM personas;")
+                .data().all().unwrap();
+            rows[0].get_column_value("SUM(dinero)").unwrap().get_i64().unwrap()
+        };
+
+        let mut writer = simple_db.begin(&context).unwrap();
+        simple_db.execute_sql(&writer.context(), "INSERT INTO personas (id, dinero) VALUES (2, 20);");
+        let mut reader = simple_db.begin(&context).unwrap();
+        //The cached result doesn't include uncommitted writes, but the writer sees its own writes
+        assert_eq!(select_sum(&reader), 10);
+        assert_eq!(select_sum(&writer), 30);
+
+        writer.commit().unwrap();
+        //The reader started before the commit
+        assert_eq!(select_sum(&reader), 10);
+        reader.commit().unwrap();
+        assert_eq!(select_dinero_sum(&simple_db, &context), 30);
+    }
+}
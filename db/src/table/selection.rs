@@ -1,10 +1,31 @@
-use crate::table::schema::Schema;
-use shared::{ColumnId, SimpleDbError};
+use crate::sql::parser::expression::Expression;
+use crate::sql::validator::get_expression_type;
+use crate::table::table::Table;
+use crate::table::schema::{Column, Schema};
+use shared::{ColumnId, SimpleDbError, Type};
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub enum Selection {
     All,
-    Some(Vec<String>)
+    Some(Vec<String>),
+    Aggregate(Vec<String>, Vec<Aggregate>), //Group by columns selected, aggregates
+    Expressions(Vec<(String, Expression)>), //Selected name, expression. Used when some function is selected
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AggregateFunction {
+    Count,
+    CountDistinct,
+    Sum,
+    Avg,
+    Min,
+    Max
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Aggregate {
+    pub function: AggregateFunction,
+    pub column_name: Option<String>, //None if COUNT(*)
 }
 
 //Describes the type of columns present in the selection.
@@ -20,6 +41,8 @@ impl Selection {
     pub fn is_empty(&self) -> bool {
         match self {
             Selection::Some(list) => list.is_empty(),
+            Selection::Aggregate(columns, aggregates) => columns.is_empty() && aggregates.is_empty(),
+            Selection::Expressions(expressions) => expressions.is_empty(),
             Selection::All => false,
         }
     }
@@ -27,10 +50,74 @@ impl Selection {
     pub fn get_some_selected_columns(&self) -> Vec<String> {
         match &self {
             Selection::Some(values) => values.clone(),
+            Selection::Aggregate(columns, aggregates) => {
+                let mut selected_columns = columns.clone();
+                for column_name in aggregates.iter().filter_map(|aggregate| aggregate.column_name.as_ref()) {
+                    if !selected_columns.contains(column_name) {
+                        selected_columns.push(column_name.clone());
+                    }
+                }
+                selected_columns
+            },
+            Selection::Expressions(expressions) => {
+                let mut selected_columns: Vec<String> = Vec::new();
+                for column_name in expressions.iter().flat_map(|(_, expression)| expression.get_identifiers()) {
+                    if !selected_columns.contains(&column_name) {
+                        selected_columns.push(column_name);
+                    }
+                }
+                selected_columns
+            },
             Selection::All => Vec::new(),
         }
     }
 
+    pub fn is_aggregate(&self) -> bool {
+        matches!(self, Selection::Aggregate(_, _))
+    }
+
+    //Returns the selected group by columns followed by the columns produced by the aggregate functions
+    //Column IDs will be the index of the column in the returned list
+    pub fn get_aggregate_columns(&self, schema: &Schema) -> Vec<Column> {
+        match &self {
+            Selection::Aggregate(columns, aggregates) => {
+                let mut aggregate_columns = Vec::new();
+
+                for column_name in columns {
+                    let mut column = schema.get_column(column_name).unwrap();
+                    column.column_id = aggregate_columns.len() as ColumnId;
+                    column.secondary_index_keyspace_id = None;
+                    column.is_primary = false;
+                    aggregate_columns.push(column);
+                }
+                for aggregate in aggregates {
+                    aggregate_columns.push(aggregate.to_column(aggregate_columns.len() as ColumnId, schema));
+                }
+
+                aggregate_columns
+            },
+            _ => Vec::new()
+        }
+    }
+
+    //Returns the columns produced by the selected expressions. Column IDs will be the index of the expression in the selection
+    //Expect the selection to have been validated
+    pub(crate) fn get_expression_columns(&self, table: &Table) -> Vec<Column> {
+        match &self {
+            Selection::Expressions(expressions) => expressions.iter()
+                .enumerate()
+                .map(|(index, (selected_name, expression))| Column {
+                    column_type: get_expression_type(expression, table).unwrap_or(Type::Null),
+                    column_name: selected_name.clone(),
+                    secondary_index_keyspace_id: None,
+                    is_primary: false,
+                    column_id: index as ColumnId,
+                })
+                .collect(),
+            _ => Vec::new()
+        }
+    }
+
     pub fn to_columns_id(
         &self,
         schema: &Schema,
@@ -46,6 +133,10 @@ impl Selection {
 
                 Ok(column_ids)
             },
+            Selection::Aggregate(_, _) |
+            Selection::Expressions(_) => {
+                Selection::Some(self.get_some_selected_columns()).to_columns_id(schema)
+            },
             Selection::All => {
                 Ok(schema.get_columns().iter()
                     .map(|column| column.column_id)
@@ -81,7 +172,86 @@ impl Selection {
                     return IndexSelectionType::All;
                 }
             }
+            Selection::Aggregate(_, _) |
+            Selection::Expressions(_) |
             Selection::All => IndexSelectionType::All,
         }
     }
+
+    //Returns true if every selected column is stored in the secondary index of indexed_column_name,
+    //which are the secondary indexed column itself and the primary column
+    pub fn is_covered_by_index(&self, schema: &Schema, indexed_column_name: &str) -> bool {
+        match &self {
+            Selection::Some(selected_columns) => !selected_columns.is_empty() && selected_columns.iter()
+                .all(|selected_column_name| selected_column_name == indexed_column_name ||
+                    selected_column_name == &schema.get_primary_column().column_name),
+            Selection::Aggregate(_, _) |
+            Selection::Expressions(_) |
+            Selection::All => false,
+        }
+    }
+}
+
+impl AggregateFunction {
+    pub fn from_name(name: &str) -> Option<AggregateFunction> {
+        match name.to_uppercase().as_str() {
+            "COUNT" => Some(AggregateFunction::Count),
+            "SUM" => Some(AggregateFunction::Sum),
+            "AVG" => Some(AggregateFunction::Avg),
+            "MIN" => Some(AggregateFunction::Min),
+            "MAX" => Some(AggregateFunction::Max),
+            _ => None
+        }
+    }
+
+    pub fn to_string(&self) -> &str {
+        match self {
+            AggregateFunction::Count |
+            AggregateFunction::CountDistinct => "COUNT",
+            AggregateFunction::Sum => "SUM",
+            AggregateFunction::Avg => "AVG",
+            AggregateFunction::Min => "MIN",
+            AggregateFunction::Max => "MAX",
+        }
+    }
+}
+
+impl Aggregate {
+    pub fn get_result_type(&self, schema: &Schema) -> Type {
+        let column_type = self.column_name.as_ref()
+            .and_then(|column_name| schema.get_column(column_name))
+            .map(|column| column.column_type);
+
+        match (&self.function, column_type) {
+            (AggregateFunction::Count, _) |
+            (AggregateFunction::CountDistinct, _) => Type::I64,
+            (AggregateFunction::Avg, _) => Type::F64,
+            (AggregateFunction::Sum, Some(column_type)) if column_type.is_fp_number() => Type::F64,
+            (AggregateFunction::Sum, _) => Type::I64,
+            (AggregateFunction::Min, Some(column_type)) |
+            (AggregateFunction::Max, Some(column_type)) => column_type,
+            (AggregateFunction::Min, None) |
+            (AggregateFunction::Max, None) => Type::Null,
+        }
+    }
+
+    pub fn to_column(&self, column_id: ColumnId, schema: &Schema) -> Column {
+        Column {
+            column_type: self.get_result_type(schema),
+            column_name: self.to_string(),
+            secondary_index_keyspace_id: None,
+            is_primary: false,
+            column_id,
+        }
+    }
+
+    pub fn to_string(&self) -> String {
+        match &self.column_name {
+            Some(column_name) if self.function == AggregateFunction::CountDistinct => {
+                format!("{}(DISTINCT {})", self.function.to_string(), column_name)
+            },
+            Some(column_name) => format!("{}({})", self.function.to_string(), column_name),
+            None => format!("{}(*)", self.function.to_string()),
+        }
+    }
 }
\ No newline at end of file
@@ -1,20 +1,26 @@
 use crate::database::database::Database;
-use crate::index::index_creation_task::IndexCreationTask;
+use crate::index::index_creation_task::{IndexCreation, IndexCreationTask};
 use crate::index::secondary_index_iterator::SecondaryIndexIterator;
 use crate::index::secondary_indexes::SecondaryIndexes;
-use crate::table::selection::Selection;
+use crate::sql::execution::expression_evaluator::evaluate_where_expression;
+use crate::sql::parser::expression::Expression;
+use crate::sql::validator::get_expression_type;
+use crate::table::selection::{AggregateFunction, IndexSelectionType, Selection};
 use crate::table::record::Record;
 use crate::table::row::Row;
-use crate::table::schema::{Column, Schema};
+use crate::table::schema::{Column, ColumnDefinition, CompositeIndex, Schema};
 use crate::table::table_descriptor::TableDescriptor;
 use crate::table::table_flags::KEYSPACE_TABLE_USER;
 use crate::table::table_iterator::TableIterator;
+use crate::table::table_stats::TableStats;
+use crate::table::row_cache::RowCache;
+use crate::table::select_cache::SelectCache;
 use bytes::Bytes;
-use shared::SimpleDbError::{ColumnNameAlreadyDefined, ColumnNotFound, IndexAlreadyExists, InvalidType, OnlyOnePrimaryColumnAllowed, PrimaryColumnNotIncluded, UnknownColumn};
-use shared::{ColumnId, FlagMethods, KeyspaceId, SimpleDbError, SimpleDbOptions, Type, Value};
+use shared::SimpleDbError::{CheckConstraintViolation, ColumnNameAlreadyDefined, ColumnNotFound, IndexAlreadyExists, IndexNotFound, InvalidIdentifier, InvalidType, NullConstraintViolation, OnlyOnePrimaryColumnAllowed, PrimaryColumnNotIncluded, TableInUse, TableNotFound, UniqueConstraintViolation, UnknownColumn};
+use shared::{ClockFn, ColumnId, FlagMethods, KeyspaceId, SimpleDbError, SimpleDbOptions, TxnId, Value};
 use std::collections::HashSet;
-use std::sync::atomic::{fence, Ordering};
-use std::sync::Arc;
+use std::sync::atomic::{fence, AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use storage::transactions::transaction::Transaction;
 use storage::{SimpleDbStorageIterator, Storage};
 
@@ -28,37 +34,58 @@ pub struct Table {
 
     pub(crate) database: Arc<Database>,
 
-    pub(crate) table_descriptor: TableDescriptor
+    pub(crate) table_descriptor: TableDescriptor,
+
+    pub(crate) row_cache: RowCache,
+    pub(crate) select_cache: SelectCache,
+
+    //Active transactions that have read or written the table. It can't be dropped until all of them have finished
+    pub(crate) transactions: Mutex<HashSet<TxnId>>,
+    pub(crate) dropped: AtomicBool,
+    //Held by writes while they write the table, and by TRUNCATE while it checks for uncommitted writes and clears it
+    pub(crate) writes_lock: RwLock<()>,
+    pub(crate) clock: ClockFn, //Sets and checks the insert time of the rows of tables with TTL
 }
 
 impl Table {
     pub(crate) fn create(
         table_name: &str,
-        columns: Vec<(String, Type, bool)>,
+        columns: Vec<ColumnDefinition>,
+        checks: Vec<Expression>,
+        ttl_seconds: Option<u64>,
         options: &Arc<shared::SimpleDbOptions>,
         storage: &Arc<storage::Storage>,
         database: Arc<Database>
     ) -> Result<Arc<Table>, SimpleDbError> {
         let primary_column_type = columns.iter()
-            .find(|(_, _, is_primary)| *is_primary)
-            .map(|(_, column_type, _)| column_type.clone())
+            .find(|column| column.is_primary)
+            .map(|column| column.column_type)
             .ok_or(PrimaryColumnNotIncluded())?;
 
-        let table_keyspace_id = storage.create_keyspace(
+        let table_keyspace_id = storage.create_keyspace_with_ttl(
             KEYSPACE_TABLE_USER,
-            primary_column_type
+            primary_column_type,
+            ttl_seconds.unwrap_or(0)
         )?;
         let table_descriptor = TableDescriptor::create(
             table_keyspace_id,
             options,
             table_name,
-            columns
+            columns,
+            checks,
+            ttl_seconds
         )?;
 
         Ok(Arc::new(Table {
             secondary_indexes: SecondaryIndexes::create_empty(storage.clone(), table_name, primary_column_type),
             table_name: table_descriptor.table_name.clone(),
             storage_keyspace_id: table_keyspace_id,
+            row_cache: RowCache::create(options),
+            select_cache: SelectCache::create(options),
+            transactions: Mutex::new(HashSet::new()),
+            dropped: AtomicBool::new(false),
+            writes_lock: RwLock::new(()),
+            clock: options.clock.unwrap_or(shared::current_time_millis),
             storage: storage.clone(),
             table_descriptor,
             database
@@ -82,9 +109,15 @@ impl Table {
                         storage.clone(),
                         table_descriptor.table_name.clone(),
                         table_descriptor.schema.clone()
-                    ),
+                    )?,
                     table_name: table_descriptor.table_name.clone(),
                     storage_keyspace_id: keyspace_id,
+                    row_cache: RowCache::create(options),
+                    select_cache: SelectCache::create(options),
+                    transactions: Mutex::new(HashSet::new()),
+                    dropped: AtomicBool::new(false),
+                    writes_lock: RwLock::new(()),
+                    clock: options.clock.unwrap_or(shared::current_time_millis),
                     database: database.clone(),
                     storage: storage.clone(),
                     table_descriptor
@@ -100,6 +133,12 @@ impl Table {
         Arc::new(Table {
             secondary_indexes: SecondaryIndexes::create_mock(options.clone()),
             table_descriptor: TableDescriptor::create_mock(columns),
+            row_cache: RowCache::create(&options),
+            select_cache: SelectCache::create(&options),
+            transactions: Mutex::new(HashSet::new()),
+            dropped: AtomicBool::new(false),
+            writes_lock: RwLock::new(()),
+            clock: shared::current_time_millis,
             storage: Arc::new(Storage::create_mock(&options)),
             database: Database::create_mock(&options),
             table_name: String::from("Mock"),
@@ -109,11 +148,32 @@ impl Table {
 
     pub fn add_columns(
         &self,
-        columns_to_add: Vec<(String, Type, bool)>,
+        columns_to_add: Vec<ColumnDefinition>,
     ) -> Result<(), SimpleDbError> {
-        for (column_name, column_type, is_primary) in columns_to_add {
-            self.table_descriptor.add_column(&column_name, column_type, is_primary)?;
+        for column in columns_to_add {
+            self.table_descriptor.add_column(column)?;
         }
+        self.select_cache.invalidate();
+        Ok(())
+    }
+
+    pub fn drop_column(
+        &self,
+        column_name: &str,
+    ) -> Result<(), SimpleDbError> {
+        self.table_descriptor.drop_column(column_name)?;
+        self.row_cache.invalidate_all();
+        self.select_cache.invalidate();
+        Ok(())
+    }
+
+    pub fn rename_column(
+        &self,
+        old_column_name: &str,
+        new_column_name: &str,
+    ) -> Result<(), SimpleDbError> {
+        self.table_descriptor.rename_column(old_column_name, new_column_name)?;
+        self.select_cache.invalidate();
         Ok(())
     }
 
@@ -127,6 +187,23 @@ impl Table {
             return Ok(None);
         }
 
+        let columns_id = selection.to_columns_id(self.get_schema())?;
+
+        if self.row_cache.is_enabled() {
+            if let Some(cached_record) = self.row_cache.get(key, transaction, &columns_id) {
+                if self.is_expired(&cached_record) {
+                    return Ok(None);
+                }
+                let primary_column_type = self.get_schema().get_primary_column().column_type;
+                return Ok(Some(Row::create(
+                    cached_record,
+                    Value::create(key.clone(), primary_column_type)?,
+                    self.get_schema().clone()
+                )));
+            }
+        }
+
+        let row_cache_generation = self.row_cache.generation();
         let mut table_iterator = TableIterator::create(
             self.storage.scan_from_key_with_transaction(
                 transaction,
@@ -134,7 +211,7 @@ impl Table {
                 key,
                 true
             )?,
-            selection.to_columns_id(self.get_schema())?,
+            columns_id.clone(),
             self.clone()
         );
         if !table_iterator.next() {
@@ -144,6 +221,16 @@ impl Table {
         let row = table_iterator.row();
 
         if row.get_primary_column_value().eq_bytes(key) {
+            if self.row_cache.is_enabled() && self.storage.reads_last_committed_values(transaction) {
+                self.row_cache.put(
+                    key.clone(),
+                    row.storage_engine_record.clone(),
+                    columns_id,
+                    transaction,
+                    row_cache_generation
+                );
+            }
+
             Ok(Some(row.clone()))
         } else {
             Ok(None)
@@ -187,6 +274,22 @@ impl Table {
         ))
     }
 
+    //Rows are returned in descending primary key order
+    pub fn scan_all_backwards(
+        self: &Arc<Self>,
+        transaction: &Transaction,
+        selection: &Selection
+    ) -> Result<TableIterator<SimpleDbStorageIterator>, SimpleDbError> {
+        let selection = selection.to_columns_id(self.get_schema())?;
+        let storage_iterator = self.storage.scan_all_backwards_with_transaction(transaction, self.storage_keyspace_id)?;
+
+        Ok(TableIterator::create_backwards(
+            storage_iterator,
+            selection,
+            self.clone()
+        ))
+    }
+
     pub fn scan_from_key_secondary_index(
         self: &Arc<Self>,
         key: &Bytes,
@@ -217,20 +320,37 @@ impl Table {
     pub fn create_secondary_index(
         self: &Arc<Self>,
         column_name_to_be_indexed: &str,
+        unique: bool,
         wait: bool
     ) -> Result<usize, SimpleDbError> {
-        let column_to_be_indexed = self.get_column(column_name_to_be_indexed).unwrap();
+        let index_creation = self.start_secondary_index_creation(column_name_to_be_indexed, unique)?;
+
+        if wait {
+            index_creation.wait(&mut |_| {})
+        } else {
+            Ok(0)
+        }
+    }
+
+    //The index won't be used by queries until all the rows have been indexed
+    pub fn start_secondary_index_creation(
+        self: &Arc<Self>,
+        column_name_to_be_indexed: &str,
+        unique: bool,
+    ) -> Result<IndexCreation, SimpleDbError> {
+        let column_to_be_indexed = self.get_schema().get_column_or_err(column_name_to_be_indexed)?;
 
-        if self.secondary_indexes.can_be_read(column_to_be_indexed.column_id) {
+        if self.secondary_indexes.has_secondary_index(column_to_be_indexed.column_id) {
             return Err(IndexAlreadyExists(self.storage_keyspace_id, column_name_to_be_indexed.to_string()));
         }
 
-        let index_keyspace_id = self.secondary_indexes.create_new_secondary_index(column_to_be_indexed.clone())?;
+        let index_keyspace_id = self.secondary_indexes.create_new_secondary_index(column_to_be_indexed.clone(), unique)?;
         //Before we start reading all the SSTables and Memtables, make sure the new secondary index is visible for writers
         fence(Ordering::Release);
 
-        let (task, receiver) = IndexCreationTask::create(
-            column_to_be_indexed.clone(),
+        let (task, index_creation) = IndexCreationTask::create(
+            vec![column_to_be_indexed.clone()],
+            unique,
             index_keyspace_id,
             self.storage_keyspace_id,
             self.database.clone(),
@@ -239,67 +359,241 @@ impl Table {
         );
 
         let _ = std::thread::spawn(move || task.start());
-        let mut n_affected_rows = 0;
+
+        Ok(index_creation)
+    }
+
+    pub fn create_composite_index(
+        self: &Arc<Self>,
+        column_names_to_be_indexed: &Vec<String>,
+        wait: bool
+    ) -> Result<usize, SimpleDbError> {
+        let index_creation = self.start_composite_index_creation(column_names_to_be_indexed)?;
 
         if wait {
-            match receiver.recv().unwrap() {
-                Ok(n) => n_affected_rows = n,
-                Err(err) => {
-                    //TODO Delete keyspace & secondary index
-                    return Err(err);
-                }
-            }
+            index_creation.wait(&mut |_| {})
+        } else {
+            Ok(0)
         }
+    }
 
-        self.table_descriptor.update_column_secondary_index(
-            column_to_be_indexed.column_id,
-            index_keyspace_id
-        )?;
+    pub fn start_composite_index_creation(
+        self: &Arc<Self>,
+        column_names_to_be_indexed: &Vec<String>,
+    ) -> Result<IndexCreation, SimpleDbError> {
+        let columns_to_be_indexed: Vec<Column> = column_names_to_be_indexed.iter()
+            .map(|column_name| self.get_schema().get_column_or_err(column_name))
+            .collect::<Result<Vec<Column>, SimpleDbError>>()?;
+
+        let index_keyspace_id = self.secondary_indexes.create_new_composite_index(columns_to_be_indexed.clone())?;
+        //Before we start reading all the SSTables and Memtables, make sure the new secondary index is visible for writers
+        fence(Ordering::Release);
+
+        let (task, index_creation) = IndexCreationTask::create(
+            columns_to_be_indexed,
+            false,
+            index_keyspace_id,
+            self.storage_keyspace_id,
+            self.database.clone(),
+            self.storage.clone(),
+            self.clone(),
+        );
+
+        let _ = std::thread::spawn(move || task.start());
+
+        Ok(index_creation)
+    }
+
+    //Called by IndexCreationTask once all the rows have been indexed. The index is added to the table descriptor,
+    //so it will be used by queries
+    pub(crate) fn on_index_created(&self, indexed_columns: &Vec<Column>, index_keyspace_id: KeyspaceId) -> Result<(), SimpleDbError> {
+        match indexed_columns.as_slice() {
+            [indexed_column] => self.table_descriptor.update_column_secondary_index(indexed_column.column_id, Some(index_keyspace_id))?,
+            _ => self.table_descriptor.add_composite_index(CompositeIndex {
+                columns_id: indexed_columns.iter().map(|column| column.column_id).collect(),
+                keyspace_id: index_keyspace_id,
+            })?,
+        };
+
+        self.secondary_indexes.set_ready(index_keyspace_id);
+
+        Ok(())
+    }
+
+    //Called by IndexCreationTask if the index creation fails or gets cancelled. The half built index is removed
+    pub(crate) fn on_index_creation_failed(&self, indexed_columns: &Vec<Column>, index_keyspace_id: KeyspaceId) -> Result<(), SimpleDbError> {
+        match indexed_columns.as_slice() {
+            [indexed_column] => self.secondary_indexes.delete_secondary_index(indexed_column.column_id)?,
+            _ => self.secondary_indexes.delete_composite_index(index_keyspace_id)?,
+        };
+
+        Ok(())
+    }
+
+    //Names of the indexes whose rows are still being indexed. The name of a composite index is the name of its columns
+    pub fn get_building_indexes(&self) -> Vec<String> {
+        let columns = self.get_schema().get_columns();
+
+        self.secondary_indexes.get_building_indexes().into_iter()
+            .map(|columns_id| columns_id.iter()
+                .filter_map(|column_id| columns.iter().find(|column| column.column_id == *column_id))
+                .map(|column| column.column_name.clone())
+                .collect::<Vec<String>>()
+                .join(", "))
+            .collect()
+    }
+
+    //Seeks the composite index to the first key greater or equal than key
+    pub fn scan_from_key_composite_index(
+        self: &Arc<Self>,
+        key: &Bytes,
+        transaction: &Transaction,
+        keyspace_id: KeyspaceId,
+    ) -> Result<SecondaryIndexIterator<SimpleDbStorageIterator>, SimpleDbError> {
+        let mut iterator = self.secondary_indexes.scan_all_composite(transaction, keyspace_id)?;
+        iterator.seek(key, true);
+        Ok(iterator)
+    }
 
-        Ok(n_affected_rows)
+    //The column is removed from the table descriptor first, so the index won't be loaded again
+    //if a crash happens before its keyspace is deleted
+    pub fn drop_secondary_index(
+        &self,
+        column_name_indexed: &str,
+    ) -> Result<(), SimpleDbError> {
+        let column = self.get_schema().get_column_or_err(column_name_indexed)?;
+
+        self.table_descriptor.update_column_secondary_index(column.column_id, None)?;
+        self.secondary_indexes.delete_secondary_index(column.column_id)?;
+
+        Ok(())
     }
 
     //Expect call to validate_insert before calling this function
+    //Omitted columns take their default value. NULL values are not stored. The inserted record replaces the previous
+    //versions of the row, so its missing columns are read as NULL
     pub fn insert(
         self: Arc<Self>,
         transaction: &Transaction,
-        to_insert_data: &mut Vec<(String, Bytes)>
+        mut values: Vec<(String, Value)>
     ) -> Result<(), SimpleDbError> {
-        let id_value = self.extract_primary_value(to_insert_data).unwrap();
-        self.upsert(transaction, id_value, true, to_insert_data)
+        self.fill_missing_columns(&mut values);
+        let mut to_insert_data: Vec<(String, Bytes)> = values.into_iter()
+            .filter(|(_, value)| !value.is_null())
+            .map(|(column_name, value)| (column_name, value.get_bytes().clone()))
+            .collect();
+
+        let id_value = self.extract_primary_value(&mut to_insert_data).unwrap();
+        self.upsert(transaction, id_value, true, true, &to_insert_data)
+    }
+
+    //Concurrent writes to the same row wait until the transaction that locked it finishes
+    pub fn lock_row(&self, transaction: &Transaction, id: &Bytes) -> Result<(), SimpleDbError> {
+        self.storage.lock_key(transaction, self.storage_keyspace_id, id)
     }
 
     pub fn delete(
-        &self,
+        self: &Arc<Self>,
         transaction: &Transaction,
         id: Bytes
     ) -> Result<(), SimpleDbError> {
+        let _writes_lock = self.writes_lock.read().unwrap();
+        //Registered before writing, so TRUNCATE sees the transaction even if the write fails halfway
+        self.select_cache.on_write(transaction);
+        self.storage.lock_key(transaction, self.storage_keyspace_id, &id)?;
+        //Index entries of the deleted row are removed, so index only scans won't return it
+        let old_row = if self.secondary_indexes.has_indexes() {
+            self.get_by_primary_column(&id, transaction, &Selection::All)?
+        } else {
+            None
+        };
+
         self.storage.delete_with_transaction(
             self.storage_keyspace_id,
             transaction,
-            id
-        )
+            id.clone()
+        )?;
+        self.row_cache.invalidate(&id);
+
+        if let Some(old_row) = old_row {
+            self.secondary_indexes.delete_all(transaction, id, &old_row.storage_engine_record)?;
+        }
+
+        Ok(())
     }
 
     pub fn update(
         self: &Arc<Self>,
         transaction: &Transaction,
         id: Bytes,
-        to_update_data: &Vec<(String, Bytes)>
+        to_update_data: &Vec<(String, Value)>
     ) -> Result<(), SimpleDbError> {
-        self.upsert(transaction, id, true, to_update_data)
+        let to_update_data = self.coerce_column_values(to_update_data)?;
+        self.upsert(transaction, id, false, false, &to_update_data)
+    }
+
+    //Converts the values to the format of its column type, so that they can be read back with it
+    fn coerce_column_values(
+        &self,
+        data: &Vec<(String, Value)>
+    ) -> Result<Vec<(String, Bytes)>, SimpleDbError> {
+        let schema = self.table_descriptor.get_schema();
+        let mut coerced_data = Vec::with_capacity(data.len());
+
+        for (column_name, column_value) in data {
+            let column = schema.get_column(column_name)
+                .ok_or(UnknownColumn(column_name.clone()))?;
+            let coerced_value = column_value.coerce(&column.column_type)
+                .filter(|coerced_value| !coerced_value.is_non_finite_fp_number())
+                .ok_or(InvalidType(column_name.clone()))?;
+
+            coerced_data.push((column_name.clone(), coerced_value.get_bytes().clone()));
+        }
+
+        Ok(coerced_data)
     }
 
     fn upsert(
         self: &Arc<Self>,
         transaction: &Transaction,
         id: Bytes,
+        is_insert: bool,
         is_new_values: bool,
         to_update_data: &Vec<(String, Bytes)>
     ) -> Result<(), SimpleDbError> {
-        let new_record = self.build_record(to_update_data)?;
+        let _writes_lock = self.writes_lock.read().unwrap();
+        //Registered before writing, so TRUNCATE sees the transaction even if the write fails halfway
+        self.select_cache.on_write(transaction);
+        //Concurrent writes to the same row wait until the transaction that wrote it first finishes
+        self.storage.lock_key(transaction, self.storage_keyspace_id, &id)?;
+
+        let has_ttl = self.table_descriptor.ttl_seconds.is_some();
+        //Composite index keys are built with all its columns, which might not be present in the updated data
+        let old_row = if self.secondary_indexes.has_composite_indexes() || (has_ttl && !is_insert) {
+            self.get_by_primary_column(&id, transaction, &Selection::All)?
+        } else {
+            None
+        };
+
+        let mut new_record = self.build_record(to_update_data)?;
+        //Inserted rows replace the previous versions of the row, like the ones deleted in the same transaction
+        new_record.is_full_row = is_insert;
+        if has_ttl && is_insert {
+            new_record.insert_time = Some((self.clock)());
+        } else if has_ttl {
+            //Every version of the row keeps the insert time of the row, so all of them expire at the same time,
+            //even if they are stored in different SSTables. If the row has expired, there is nothing to update
+            match old_row.as_ref().and_then(|old_row| old_row.storage_engine_record.insert_time) {
+                Some(insert_time) => new_record.insert_time = Some(insert_time),
+                None => return Ok(()),
+            }
+        }
         let new_value = new_record.serialize();
 
+        self.validate_checks_values(transaction, &id, is_insert, &new_record)?;
+
+        self.validate_unique_values(transaction, &id, to_update_data)?;
+
         let old_record = Record::create(if !is_new_values {
             self.get_old_data_to_invalidate_secondary_index(&id, transaction, to_update_data)?
         } else {
@@ -312,17 +606,249 @@ impl Table {
             id.clone(),
             new_value.as_slice()
         )?;
+        self.row_cache.invalidate(&id);
 
         self.secondary_indexes.update_all(
             transaction,
-            id,
+            id.clone(),
             &new_record,
             &old_record
         )?;
+        self.secondary_indexes.update_all_composite(
+            transaction,
+            id,
+            &new_record,
+            old_row.as_ref().map(|old_row| &old_row.storage_engine_record)
+        )?;
+
+        Ok(())
+    }
+
+    //Checks are evaluated with the same semantics as WHERE, against the row resulting from the insert or update
+    fn validate_checks_values(
+        self: &Arc<Self>,
+        transaction: &Transaction,
+        id: &Bytes,
+        is_insert: bool,
+        new_record: &Record
+    ) -> Result<(), SimpleDbError> {
+        if self.table_descriptor.checks.is_empty() {
+            return Ok(());
+        }
+
+        let mut record = new_record.clone();
+        if !is_insert {
+            if let Some(old_row) = self.get_by_primary_column(id, transaction, &Selection::All)? {
+                record = old_row.storage_engine_record;
+                record.merge(new_record.clone());
+            }
+        }
+
+        let primary_column_type = self.get_schema().get_primary_column().column_type;
+        let row = Row::create(record, Value::create(id.clone(), primary_column_type)?, self.get_schema().clone());
+        for check in &self.table_descriptor.checks {
+            if !evaluate_where_expression(&row, check)? {
+                return Err(CheckConstraintViolation(self.table_name.clone()));
+            }
+        }
+
+        Ok(())
+    }
+
+    //Returns UniqueConstraintViolation if other row, visible by the transaction or written by a concurrent
+    //transaction, has the same value in a unique indexed column
+    fn validate_unique_values(
+        self: &Arc<Self>,
+        transaction: &Transaction,
+        id: &Bytes,
+        to_update_data: &Vec<(String, Bytes)>
+    ) -> Result<(), SimpleDbError> {
+        for (column_name, column_value) in to_update_data {
+            let column = self.get_schema().get_column_or_err(column_name)?;
+            if !self.secondary_indexes.is_unique(column.column_id) {
+                continue;
+            }
+
+            let mut iterator = self.secondary_indexes.scan_all(transaction, column.column_id)?;
+            iterator.seek(column_value, true);
+
+            while let Some((indexed_value, primary_key)) = iterator.next() {
+                if !indexed_value.as_bytes().eq(column_value) {
+                    break;
+                }
+                if primary_key.as_bytes().eq(id) {
+                    continue;
+                }
+
+                //Index entries are not removed when the row is deleted or its value is updated
+                let row = self.get_by_primary_column(primary_key.as_bytes(), transaction, &Selection::All)?;
+                let has_same_value = row.map(|row| row.storage_engine_record.get_column_bytes(column.column_id)
+                        .map(|row_value| row_value.eq(column_value))
+                        .unwrap_or(false))
+                    .unwrap_or(false);
+                if has_same_value {
+                    return Err(UniqueConstraintViolation(column_name.clone()));
+                }
+            }
+
+            if !self.secondary_indexes.write_unique_value(transaction, column.column_id, column_value, id)? {
+                return Err(UniqueConstraintViolation(column_name.clone()));
+            }
+        }
+
+        Ok(())
+    }
 
+    pub(crate) fn on_transaction_finished(
+        &self,
+        txn_id: TxnId,
+        committed: bool,
+        active_transactions: &Vec<TxnId>
+    ) {
+        self.secondary_indexes.on_transaction_finished(txn_id, committed, active_transactions);
+        self.select_cache.on_transaction_finished(txn_id);
+        self.transactions.lock().unwrap().remove(&txn_id);
+    }
+
+    //Registers the transaction as a user of the table. Returns TableNotFound if the table has been dropped
+    pub(crate) fn use_in_transaction(&self, transaction: &Transaction) -> Result<(), SimpleDbError> {
+        let mut transactions = self.transactions.lock().unwrap();
+        if self.dropped.load(Ordering::Relaxed) {
+            return Err(TableNotFound(self.table_name.clone()));
+        }
+
+        transactions.insert(transaction.id());
+        Ok(())
+    }
+
+    //Returns TableInUse if an active transaction has used the table. Otherwise, it can't be used by new transactions
+    pub(crate) fn mark_as_dropped(&self, active_transactions: &[TxnId]) -> Result<(), SimpleDbError> {
+        let mut transactions = self.transactions.lock().unwrap();
+        transactions.retain(|txn_id| active_transactions.contains(txn_id));
+        if !transactions.is_empty() {
+            return Err(TableInUse(self.table_name.clone()));
+        }
+
+        self.dropped.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    //Removes all rows from the table and its indexes, without writing a tombstone per row.
+    //The keyspaces are truncated together, so a crash won't leave indexes pointing to truncated rows.
+    //Returns TableInUse if an active transaction has written the table, as its writes would be lost
+    pub(crate) fn truncate(&self) -> Result<(), SimpleDbError> {
+        let _writes_lock = self.writes_lock.write().unwrap();
+        if self.select_cache.has_writers() {
+            return Err(TableInUse(self.table_name.clone()));
+        }
+
+        let mut keyspaces_id = vec![self.storage_keyspace_id];
+        for column in self.get_schema().get_columns() {
+            if let Some(secondary_index_keyspace_id) = column.secondary_index_keyspace_id {
+                keyspaces_id.push(secondary_index_keyspace_id);
+            }
+        }
+        for composite_index in self.get_schema().get_composite_indexes() {
+            keyspaces_id.push(composite_index.keyspace_id);
+        }
+
+        self.storage.truncate_keyspaces(&keyspaces_id)?;
+
+        self.row_cache.invalidate_all();
+        self.select_cache.invalidate();
         Ok(())
     }
 
+    //Compacts the table keyspace and its secondary indexes keyspaces, removing deleted rows and overwritten versions
+    //that can't be read by any active transaction. Returns the nº of bytes reclaimed
+    pub(crate) fn vacuum(&self) -> Result<usize, SimpleDbError> {
+        let mut bytes_reclaimed = self.storage.vacuum_keyspace(self.storage_keyspace_id)?;
+
+        for column in self.get_schema().get_columns() {
+            if let Some(secondary_index_keyspace_id) = column.secondary_index_keyspace_id {
+                bytes_reclaimed += self.storage.vacuum_keyspace(secondary_index_keyspace_id)?;
+            }
+        }
+        for composite_index in self.get_schema().get_composite_indexes() {
+            bytes_reclaimed += self.storage.vacuum_keyspace(composite_index.keyspace_id)?;
+        }
+
+        Ok(bytes_reclaimed)
+    }
+
+    //Deletes the table keyspace, its secondary indexes keyspaces and the table descriptor
+    //The table keyspace is deleted first, so the table won't be loaded again if a crash happens in the middle
+    pub(crate) fn delete_files(&self) -> Result<(), SimpleDbError> {
+        self.storage.delete_keyspace(self.storage_keyspace_id)?;
+
+        for column in self.get_schema().get_columns() {
+            if let Some(secondary_index_keyspace_id) = column.secondary_index_keyspace_id {
+                self.storage.delete_keyspace(secondary_index_keyspace_id)?;
+            }
+        }
+        for composite_index in self.get_schema().get_composite_indexes() {
+            self.storage.delete_keyspace(composite_index.keyspace_id)?;
+        }
+
+        self.table_descriptor.delete()
+    }
+
+    //Statistics used by the planner. They are approximate and return None if they cannot be estimated
+    pub fn estimate_n_rows(&self) -> Result<Option<usize>, SimpleDbError> {
+        self.storage.estimate_n_keys(self.storage_keyspace_id)
+    }
+
+    //Returns the primary keys in which the table can be split to be scanned in parallel, in ascending order
+    pub fn get_scan_partition_keys(&self) -> Result<Vec<Bytes>, SimpleDbError> {
+        self.storage.get_partition_keys(self.storage_keyspace_id)
+    }
+
+    pub fn get_stats(&self) -> Result<TableStats, SimpleDbError> {
+        let primary_column = self.get_schema().get_primary_column();
+        let primary_key_range = match self.storage.get_key_range(self.storage_keyspace_id)? {
+            Some((min, max)) => Some((
+                Value::create(min, primary_column.column_type)?,
+                Value::create(max, primary_column.column_type)?
+            )),
+            None => None,
+        };
+
+        Ok(TableStats {
+            n_rows: self.estimate_n_rows()?,
+            n_sstables_per_level: self.storage.get_n_sstables_per_level(self.storage_keyspace_id)?,
+            size_bytes: self.storage.get_size_bytes(self.storage_keyspace_id)?,
+            primary_key_range,
+        })
+    }
+
+    //Every indexed value is stored as one key in the secondary index keyspace
+    pub fn estimate_n_distinct_values(&self, column: &Column) -> Result<Option<usize>, SimpleDbError> {
+        match column.secondary_index_keyspace_id {
+            Some(secondary_index_keyspace_id) => self.storage.estimate_n_keys(secondary_index_keyspace_id),
+            None => Ok(None),
+        }
+    }
+
+    //Returns the smallest and biggest values stored in the secondary index of the column
+    pub fn get_indexed_values_range(&self, column: &Column) -> Result<Option<(Value, Value)>, SimpleDbError> {
+        let secondary_index_keyspace_id = match column.secondary_index_keyspace_id {
+            Some(secondary_index_keyspace_id) => secondary_index_keyspace_id,
+            None => return Ok(None),
+        };
+
+        match self.storage.get_key_range(secondary_index_keyspace_id)? {
+            Some((min, max)) => Ok(Some((
+                Value::create(min, column.column_type)?,
+                Value::create(max, column.column_type)?
+            ))),
+            None => Ok(None),
+        }
+    }
+
+    pub fn is_unique_index(&self, column: &Column) -> bool {
+        self.secondary_indexes.is_unique(column.column_id)
+    }
+
     pub fn get_column(
         &self,
         column_name: &str
@@ -336,13 +862,21 @@ impl Table {
     }
 
     pub fn validate_new_columns(
-        columns: &Vec<(String, Type, bool)>,
+        columns: &Vec<ColumnDefinition>,
     ) -> Result<(), SimpleDbError> {
         let mut primary_already_added = false;
         let mut column_names_added = HashSet::new();
 
-        for (new_column_name, _, is_primary) in columns {
-            let is_primary = *is_primary;
+        for column in columns {
+            let new_column_name = &column.column_name;
+            let is_primary = column.is_primary;
+            Self::validate_identifier(new_column_name)?;
+
+            if let Some(default_value) = &column.default_value {
+                if !column.column_type.can_be_casted(&default_value.get_type()) {
+                    return Err(InvalidType(new_column_name.to_string()));
+                }
+            }
 
             if primary_already_added && is_primary {
                 return Err(OnlyOnePrimaryColumnAllowed());
@@ -365,6 +899,38 @@ impl Table {
         Ok(())
     }
 
+    //Checks can only reference the columns of the table
+    pub fn validate_checks(
+        columns: &[ColumnDefinition],
+        checks: &Vec<Expression>,
+    ) -> Result<(), SimpleDbError> {
+        for check in checks {
+            for identifier in check.get_identifiers() {
+                if !columns.iter().any(|column| column.column_name == identifier) {
+                    return Err(UnknownColumn(identifier));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    //Identifiers should start with a letter, so they can't be confused with numbers by the tokenizer
+    pub fn validate_identifier(identifier: &str) -> Result<(), SimpleDbError> {
+        let starts_with_letter = identifier.chars()
+            .next()
+            .map(|first_char| first_char.is_ascii_alphabetic())
+            .unwrap_or(false);
+        let has_valid_chars = identifier.chars()
+            .all(|char| char.is_ascii_alphanumeric() || char == '_');
+
+        if !starts_with_letter || !has_valid_chars {
+            return Err(InvalidIdentifier(identifier.to_string()));
+        }
+
+        Ok(())
+    }
+
     pub fn validate_selection(
         &self,
         selection: &Selection
@@ -379,20 +945,86 @@ impl Table {
                     }
                 }
 
+                Ok(())
+            },
+            Selection::Aggregate(columns, aggregates) => {
+                let schema = self.table_descriptor.get_schema();
+                for column_name in columns {
+                    schema.get_column_or_err(column_name)?;
+                }
+                for aggregate in aggregates {
+                    if let Some(column_name) = &aggregate.column_name {
+                        let column = schema.get_column_or_err(column_name)?;
+
+                        if matches!(aggregate.function, AggregateFunction::Sum | AggregateFunction::Avg) &&
+                            !column.column_type.is_number() {
+                            return Err(InvalidType(column_name.clone()));
+                        }
+                    }
+                }
+
+                Ok(())
+            },
+            Selection::Expressions(expressions) => {
+                for (_, expression) in expressions {
+                    get_expression_type(expression, self)?;
+                }
+
                 Ok(())
             }
         }
     }
 
-    pub fn validate_create_index(
+    pub fn validate_drop_index(
         &self,
         column_name: &str
     ) -> Result<(), SimpleDbError> {
         let schema = self.get_schema();
         let column = schema.get_column_or_err(column_name)?;
 
-        if self.secondary_indexes.can_be_read(column.column_id) || column.is_primary{
-            return Err(IndexAlreadyExists(self.storage_keyspace_id, column_name.to_string()));
+        if !column.is_secondary_indexed() {
+            return Err(IndexNotFound(column.column_id));
+        }
+
+        Ok(())
+    }
+
+    pub fn validate_create_index(
+        &self,
+        column_names: &Vec<String>,
+        unique: bool,
+        wait: bool,
+    ) -> Result<(), SimpleDbError> {
+        let schema = self.get_schema();
+
+        if unique && column_names.len() > 1 {
+            return Err(SimpleDbError::MalformedQuery(String::from("Unique indexes can only have one column")));
+        }
+        if unique && !wait {
+            return Err(SimpleDbError::MalformedQuery(String::from("Unique indexes can't be created with ASYNC")));
+        }
+
+        if let [column_name] = column_names.as_slice() {
+            let column = schema.get_column_or_err(column_name)?;
+
+            if self.secondary_indexes.has_secondary_index(column.column_id) || column.is_primary{
+                return Err(IndexAlreadyExists(self.storage_keyspace_id, column_name.to_string()));
+            }
+
+            return Ok(());
+        }
+
+        let mut columns_id = Vec::new();
+        for column_name in column_names {
+            let column = schema.get_column_or_err(column_name)?;
+            if columns_id.contains(&column.column_id) {
+                return Err(ColumnNameAlreadyDefined(column_name.clone()));
+            }
+            columns_id.push(column.column_id);
+        }
+
+        if schema.get_composite_index(&columns_id).is_some() {
+            return Err(IndexAlreadyExists(self.storage_keyspace_id, column_names.join(", ")));
         }
 
         Ok(())
@@ -410,17 +1042,44 @@ impl Table {
         for (column_name, column_value) in to_insert_data {
             match schema.get_column(column_name) {
                 Some(column) => {
-                    if !column.column_type.can_be_casted(&column_value.get_type()) {
+                    if !column.column_type.can_be_casted(&column_value.get_type()) || column_value.is_non_finite_fp_number() {
                         return Err(InvalidType(column_name.clone()));
                     }
+                    if column_value.is_null() && self.table_descriptor.is_not_null(&column) {
+                        return Err(NullConstraintViolation(column_name.clone()));
+                    }
                 },
                 None => return Err(UnknownColumn(column_name.clone())),
             }
         }
+        //Omitted columns without default value will be NULL
+        for column in schema.get_columns() {
+            let is_omitted = !to_insert_data.iter().any(|(column_name, _)| *column_name == column.column_name);
+            if is_omitted && self.table_descriptor.is_not_null(&column) && self.table_descriptor.get_default_value(column.column_id).is_none() {
+                return Err(NullConstraintViolation(column.column_name));
+            }
+        }
 
         Ok(())
     }
 
+    pub fn is_not_null_column(&self, column_name: &str) -> bool {
+        self.get_schema().get_column(column_name)
+            .map(|column| self.table_descriptor.is_not_null(&column))
+            .unwrap_or(false)
+    }
+
+    //Omitted columns take their default value, or NULL if they don't have one
+    fn fill_missing_columns(&self, values: &mut Vec<(String, Value)>) {
+        for column in self.get_schema().get_columns() {
+            if !column.is_primary && !values.iter().any(|(column_name, _)| *column_name == column.column_name) {
+                let value = self.table_descriptor.get_default_value(column.column_id)
+                    .unwrap_or(Value::create_null());
+                values.push((column.column_name, value));
+            }
+        }
+    }
+
     fn validate_has_primary_value(&self, data: &Vec<(String, Value)>) -> bool {
         let schema = self.table_descriptor.get_schema();
         let primary_column_name = schema.get_primary_column();
@@ -459,7 +1118,28 @@ impl Table {
             data_records_to_return.push((column.column_id, column_value.clone()));
         }
 
-        Ok(Record { data_records: data_records_to_return, })
+        Ok(Record::create(data_records_to_return))
+    }
+
+    //Expired rows are not returned, even if they haven't been removed by a compaction yet
+    pub(crate) fn is_expired(&self, record: &Record) -> bool {
+        match (self.table_descriptor.ttl_seconds, record.insert_time) {
+            (Some(ttl_seconds), Some(insert_time)) => insert_time + ttl_seconds * 1000 <= (self.clock)(),
+            _ => false,
+        }
+    }
+
+    //Index entries are not expired, so rows of tables with TTL are always read to check if they have expired
+    pub(crate) fn get_index_selection_type(&self, selection: &Selection) -> IndexSelectionType {
+        if self.table_descriptor.ttl_seconds.is_some() {
+            IndexSelectionType::All
+        } else {
+            selection.get_index_selection_type(self.get_schema())
+        }
+    }
+
+    pub fn get_ttl_seconds(&self) -> Option<u64> {
+        self.table_descriptor.ttl_seconds
     }
 
     pub fn name(&self) -> &String {
@@ -493,14 +1173,359 @@ impl Table {
                         .unwrap()
                         .column_id;
 
-                    match old_row_value.get_column_value(&column_secondary_indexed_column_name)? {
-                        _ => continue,
-                        value => old_data.push((column_id, value.get_bytes().clone())),
-                    };
+                    let value = old_row_value.get_column_value(&column_secondary_indexed_column_name)?;
+                    if !value.is_null() {
+                        old_data.push((column_id, value.get_bytes().clone()));
+                    }
                 }
             }
         }
 
         Ok(old_data)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod test {
+    use crate::table::table::Table;
+    use crate::{Column, ColumnDefinition};
+    use crate::simple_db::{Context, StatementResult};
+    use crate::test_utils::{select_dinero_sum, TestSimpleDb};
+    use shared::{utils, SimpleDbError, Type, Value};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    #[test]
+    fn coerce_column_values() {
+        let table = Table::create_mock(vec![
+            Column::create_primary("id"),
+            Column { column_id: 1, column_type: Type::F64, column_name: String::from("dinero"), is_primary: false, secondary_index_keyspace_id: None },
+        ]);
+
+        let coerced = table.coerce_column_values(&vec![(String::from("dinero"), Value::create_i64(5))]).unwrap();
+        assert_eq!(utils::bytes_to_f64_le(&coerced[0].1), 5.0);
+
+        let result = table.coerce_column_values(&vec![(String::from("dinero"), Value::create_string(String::from("a")))]);
+        assert!(matches!(result, Err(SimpleDbError::InvalidType(_))));
+
+        let result = table.coerce_column_values(&vec![(String::from("dinero"), Value::create_f64(f64::NAN))]);
+        assert!(matches!(result, Err(SimpleDbError::InvalidType(_))));
+    }
+
+    #[test]
+    fn validate_new_columns_identifiers() {
+        let empty_column_name = vec![ColumnDefinition::create("id", Type::I64, true), ColumnDefinition::create("", Type::String, false)];
+        assert!(matches!(Table::validate_new_columns(&empty_column_name), Err(SimpleDbError::InvalidIdentifier(_))));
+
+        let numeric_leading_name = vec![ColumnDefinition::create("id", Type::I64, true), ColumnDefinition::create("1col", Type::String, false)];
+        assert!(matches!(Table::validate_new_columns(&numeric_leading_name), Err(SimpleDbError::InvalidIdentifier(_))));
+
+        let valid_names = vec![ColumnDefinition::create("id", Type::I64, true), ColumnDefinition::create("col1", Type::String, false)];
+        assert!(Table::validate_new_columns(&valid_names).is_ok());
+    }
+
+    #[test]
+    fn too_many_columns() {
+        let mut options_builder = shared::start_simpledb_options_builder();
+        options_builder.max_columns_per_table(3);
+        let simple_db = TestSimpleDb::create_with_options("too_many_columns", options_builder);
+        let context = Context::create_with_database("test");
+
+        assert!(matches!(
+            simple_db.execute(&context, simple_db.parse("CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR, dinero I64, edad I64);").unwrap()),
+            Err(SimpleDbError::TooManyColumns(_, 3))
+        ));
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR, dinero I64);");
+
+        let database = simple_db.get_databases().get_database("test").unwrap();
+        assert!(matches!(
+            database.add_column("personas", vec![ColumnDefinition::create("edad", Type::I64, false)]),
+            Err(SimpleDbError::TooManyColumns(_, 3))
+        ));
+        assert_eq!(database.get_table_or_err("personas").unwrap().get_schema().get_columns().len(), 3);
+    }
+
+    #[test]
+    fn drop_column() {
+        let simple_db = TestSimpleDb::create("drop_column");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR, dinero I64);");
+        simple_db.execute_sql(&context, "CREATE INDEX ON personas (dinero);");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, nombre, dinero) VALUES (1, \"Jaime\", 10);");
+
+        assert!(matches!(simple_db.execute(&context, simple_db.parse("ALTER TABLE personas DROP COLUMN id;").unwrap()),
+            Err(SimpleDbError::CannotDropPrimaryColumn(_))));
+        assert!(matches!(simple_db.execute(&context, simple_db.parse("ALTER TABLE personas DROP COLUMN dinero;").unwrap()),
+            Err(SimpleDbError::CannotDropIndexedColumn(_))));
+        assert!(matches!(simple_db.execute(&context, simple_db.parse("ALTER TABLE personas DROP COLUMN edad;").unwrap()),
+            Err(SimpleDbError::ColumnNotFound(_))));
+
+        simple_db.execute_sql(&context, "ALTER TABLE personas DROP COLUMN nombre;");
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        let rows = simple_db.execute_sql(&transaction.context(), "SELECT * FROM personas;").data().all().unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].get_column_value("dinero").unwrap().get_i64().unwrap(), 10);
+        assert!(matches!(rows[0].get_column_value("nombre"), Err(SimpleDbError::ColumnNotFound(_))));
+        transaction.commit().unwrap();
+
+        //The stored bytes of the dropped column are not read by a new column with the same name
+        let database = simple_db.get_databases().get_database("test").unwrap();
+        database.add_column("personas", vec![ColumnDefinition::create("nombre", Type::String, false)]).unwrap();
+        let mut transaction = simple_db.begin(&context).unwrap();
+        let rows = simple_db.execute_sql(&transaction.context(), "SELECT * FROM personas;").data().all().unwrap();
+        assert!(rows[0].get_column_value("nombre").unwrap().is_null());
+        transaction.commit().unwrap();
+    }
+
+    #[test]
+    fn rename_column() {
+        let simple_db = TestSimpleDb::create("rename_column");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR, dinero I64);");
+        simple_db.execute_sql(&context, "CREATE INDEX ON personas (dinero);");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, nombre, dinero) VALUES (1, \"Jaime\", 10);");
+
+        assert!(matches!(simple_db.execute(&context, simple_db.parse("ALTER TABLE personas RENAME COLUMN nombre TO dinero;").unwrap()),
+            Err(SimpleDbError::ColumnNameAlreadyDefined(_))));
+        assert!(matches!(simple_db.execute(&context, simple_db.parse("ALTER TABLE personas RENAME COLUMN edad TO anios;").unwrap()),
+            Err(SimpleDbError::UnknownColumn(_))));
+
+        simple_db.execute_sql(&context, "ALTER TABLE personas RENAME COLUMN nombre TO apellido;");
+        simple_db.execute_sql(&context, "ALTER TABLE personas RENAME COLUMN dinero TO saldo;");
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        let rows = simple_db.execute_sql(&transaction.context(), "SELECT apellido FROM personas WHERE saldo > 5;").data().all().unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].get_column_value("apellido").unwrap().get_string().unwrap(), "Jaime");
+        assert!(simple_db.execute(&transaction.context(), simple_db.parse("SELECT nombre FROM personas;").unwrap()).is_err());
+        transaction.commit().unwrap();
+
+        let table = simple_db.get_databases().get_database("test").unwrap().get_table_or_err("personas").unwrap();
+        assert!(table.get_schema().is_secondary_indexed("saldo"));
+    }
+
+    #[test]
+    fn insert_existing_primary_key() {
+        let simple_db = TestSimpleDb::create("insert_existing_primary_key");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR);");
+        simple_db.execute_sql(&context, "CREATE INDEX ON personas (nombre);");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, nombre) VALUES (1, \"Jaime\");");
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        let insert = |query: &str| simple_db.execute(&transaction.context(), simple_db.parse(query).unwrap());
+        let select_nombre = || simple_db.execute_sql(&transaction.context(), "SELECT * FROM personas;")
+            .data().all().unwrap().iter()
+            .map(|row| row.get_column_value("nombre").unwrap().get_string().unwrap())
+            .collect::<Vec<String>>();
+
+        assert!(matches!(insert("INSERT INTO personas (id, nombre) VALUES (1, \"Molon\");"),
+            Err(SimpleDbError::PrimaryKeyAlreadyExists(_))));
+        assert_eq!(select_nombre(), vec![String::from("Jaime")]);
+        assert!(matches!(insert("INSERT OR IGNORE INTO personas (id, nombre) VALUES (1, \"Molon\");"), Ok(StatementResult::Ok(0))));
+        assert_eq!(select_nombre(), vec![String::from("Jaime")]);
+        assert!(matches!(insert("INSERT OR REPLACE INTO personas (id, nombre) VALUES (1, \"Molon\");"), Ok(StatementResult::Ok(1))));
+        assert_eq!(select_nombre(), vec![String::from("Molon")]);
+        //The index entry of the replaced row is removed
+        let rows = simple_db.execute_sql(&transaction.context(), "SELECT nombre FROM personas /*+ INDEX(nombre) */ WHERE nombre == \"Jaime\";")
+            .data().all().unwrap();
+        assert!(rows.is_empty());
+        transaction.commit().unwrap();
+    }
+
+    #[test]
+    fn insert_values_validation() {
+        let simple_db = TestSimpleDb::create("insert_values_validation");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64, nombre VARCHAR);");
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        let result = simple_db.execute(&transaction.context(), simple_db.parse("INSERT INTO personas (id, dinero) VALUES (1, \"Jaime\");").unwrap());
+        assert!(matches!(result, Err(SimpleDbError::InvalidType(column_name)) if column_name == "dinero"));
+
+        simple_db.execute_sql(&transaction.context(), "INSERT INTO personas (id, dinero) VALUES (2, 10);");
+        simple_db.execute_sql(&transaction.context(), "INSERT INTO personas (id, dinero, nombre) VALUES (3, 20, NULL);");
+        let rows = simple_db.execute_sql(&transaction.context(), "SELECT * FROM personas;").data().all().unwrap();
+        transaction.commit().unwrap();
+
+        assert_eq!(rows.len(), 2);
+        for row in rows {
+            assert!(row.get_column_value("nombre").unwrap().is_null());
+        }
+
+        //The inserted row doesn't keep the columns of the deleted one
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, dinero, nombre) VALUES (4, 30, \"Jaime\");");
+        let mut transaction = simple_db.begin(&context).unwrap();
+        simple_db.execute_sql(&transaction.context(), "DELETE FROM personas WHERE id = 4;");
+        simple_db.execute_sql(&transaction.context(), "INSERT INTO personas (id, dinero) VALUES (4, 40);");
+        let rows = simple_db.execute_sql(&transaction.context(), "SELECT * FROM personas WHERE id = 4;").data().all().unwrap();
+        transaction.commit().unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].get_column_value("dinero").unwrap(), Value::create_i64(40));
+        assert!(rows[0].get_column_value("nombre").unwrap().is_null());
+    }
+
+    #[test]
+    fn non_finite_fp_values() {
+        let simple_db = TestSimpleDb::create("non_finite_fp_values");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero F64);");
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        let result = simple_db.parse("INSERT INTO personas (id, dinero) VALUES (1, 1e999);");
+        assert!(matches!(result, Err(SimpleDbError::MalformedNumber(_, _))));
+        for non_finite_value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let insert = simple_db.parse("INSERT INTO personas (id, dinero) VALUES (1, ?);").unwrap()
+                .bind(&vec![Value::create_f64(non_finite_value)]).unwrap();
+            let result = simple_db.execute(&transaction.context(), insert);
+            assert!(matches!(result, Err(SimpleDbError::InvalidType(column_name)) if column_name == "dinero"));
+        }
+
+        simple_db.execute_sql(&transaction.context(), "INSERT INTO personas (id, dinero) VALUES (1, 1.5);");
+        let result = simple_db.execute(&transaction.context(), simple_db.parse("UPDATE personas SET dinero = dinero / 0.0 WHERE id == 1;").unwrap());
+        assert!(matches!(result, Err(SimpleDbError::InvalidType(column_name)) if column_name == "dinero"));
+        transaction.rollback().unwrap();
+    }
+
+    #[test]
+    fn default_values() {
+        let simple_db = TestSimpleDb::create("default_values");
+        let context = Context::create_with_database("test");
+        let result = simple_db.execute(&context, simple_db.parse("CREATE TABLE pedidos (id I64 PRIMARY KEY, cantidad I64 DEFAULT \"uno\");").unwrap());
+        assert!(matches!(result, Err(SimpleDbError::InvalidType(column_name)) if column_name == "cantidad"));
+
+        simple_db.execute_sql(&context, "CREATE TABLE pedidos (id I64 PRIMARY KEY, status VARCHAR DEFAULT \"new\");");
+        simple_db.execute_in_transaction(&context, "INSERT INTO pedidos (id) VALUES (1);");
+        simple_db.execute_in_transaction(&context, "INSERT INTO pedidos (id, status) VALUES (2, \"sent\");");
+
+        //Explicit NULL values don't take the default value
+        simple_db.execute_in_transaction(&context, "INSERT INTO pedidos (id, status) VALUES (3, NULL);");
+        //Defaults are also applied to rows inserted without SQL
+        let table = simple_db.get_databases().get_database("test").unwrap().get_table_or_err("pedidos").unwrap();
+        let mut transaction = simple_db.begin(&context).unwrap();
+        table.insert(transaction.transaction(), vec![(String::from("id"), Value::create_i64(4))]).unwrap();
+        transaction.commit().unwrap();
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        let rows = simple_db.execute_sql(&transaction.context(), "SELECT * FROM pedidos;").data().all().unwrap();
+        transaction.commit().unwrap();
+
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[0].get_column_value("status").unwrap().get_string().unwrap(), "new");
+        assert_eq!(rows[1].get_column_value("status").unwrap().get_string().unwrap(), "sent");
+        assert!(rows[2].get_column_value("status").unwrap().is_null());
+        assert_eq!(rows[3].get_column_value("status").unwrap().get_string().unwrap(), "new");
+    }
+
+    #[test]
+    fn not_null_columns() {
+        let simple_db = TestSimpleDb::create("not_null_columns");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR NOT NULL, apellido VARCHAR, dinero I64 NOT NULL DEFAULT 0);");
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        let result = simple_db.execute(&transaction.context(), simple_db.parse("INSERT INTO personas (id, nombre) VALUES (1, NULL);").unwrap());
+        assert!(matches!(result, Err(SimpleDbError::NullConstraintViolation(column_name)) if column_name == "nombre"));
+        let result = simple_db.execute(&transaction.context(), simple_db.parse("INSERT INTO personas (id, dinero) VALUES (1, 10);").unwrap());
+        assert!(matches!(result, Err(SimpleDbError::NullConstraintViolation(column_name)) if column_name == "nombre"));
+
+        simple_db.execute_sql(&transaction.context(), "INSERT INTO personas (id, nombre) VALUES (1, \"Jaime\");");
+        let result = simple_db.execute(&transaction.context(), simple_db.parse("UPDATE personas SET nombre = apellido WHERE id == 1;").unwrap());
+        assert!(matches!(result, Err(SimpleDbError::NullConstraintViolation(column_name)) if column_name == "nombre"));
+        transaction.commit().unwrap();
+
+        assert_eq!(select_dinero_sum(&simple_db, &context), 0);
+    }
+
+    #[test]
+    fn check_constraints() {
+        let simple_db = TestSimpleDb::create("check_constraints");
+        let context = Context::create_with_database("test");
+        let result = simple_db.execute(&context, simple_db.parse("CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64, CHECK (saldo >= 0));").unwrap());
+        assert!(matches!(result, Err(SimpleDbError::UnknownColumn(column_name)) if column_name == "saldo"));
+
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64, CHECK (dinero >= 0 AND dinero < 100));");
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        let result = simple_db.execute(&transaction.context(), simple_db.parse("INSERT INTO personas (id, dinero) VALUES (1, 150);").unwrap());
+        assert!(matches!(result, Err(SimpleDbError::CheckConstraintViolation(_))));
+
+        simple_db.execute_sql(&transaction.context(), "INSERT INTO personas (id, dinero) VALUES (1, 10);");
+        let result = simple_db.execute(&transaction.context(), simple_db.parse("UPDATE personas SET dinero = dinero - 20 WHERE id == 1;").unwrap());
+        assert!(matches!(result, Err(SimpleDbError::CheckConstraintViolation(_))));
+        transaction.commit().unwrap();
+
+        assert_eq!(select_dinero_sum(&simple_db, &context), 10);
+
+        assert!(matches!(simple_db.execute(&context, simple_db.parse("ALTER TABLE personas DROP COLUMN dinero;").unwrap()),
+            Err(SimpleDbError::ColumnUsedByCheck(_))));
+        assert!(matches!(simple_db.execute(&context, simple_db.parse("ALTER TABLE personas RENAME COLUMN dinero TO saldo;").unwrap()),
+            Err(SimpleDbError::ColumnUsedByCheck(_))));
+
+        //Updates are checked against the whole row, not only the updated columns
+        simple_db.execute_sql(&context, "CREATE TABLE cuentas (id I64 PRIMARY KEY, dinero I64, deuda I64, CHECK (dinero >= deuda));");
+        let mut transaction = simple_db.begin(&context).unwrap();
+        simple_db.execute_sql(&transaction.context(), "INSERT INTO cuentas (id, dinero, deuda) VALUES (1, 10, 5);");
+        let result = simple_db.execute(&transaction.context(), simple_db.parse("UPDATE cuentas SET dinero = 1 WHERE id == 1;").unwrap());
+        assert!(matches!(result, Err(SimpleDbError::CheckConstraintViolation(_))));
+        simple_db.execute_sql(&transaction.context(), "UPDATE cuentas SET deuda = 10 WHERE id == 1;");
+        transaction.commit().unwrap();
+    }
+
+    #[test]
+    fn table_ttl() {
+        static NOW_MILLIS: AtomicU64 = AtomicU64::new(0);
+        let simple_db = TestSimpleDb::create_with_options("table_ttl", {
+            let mut options_builder = shared::start_simpledb_options_builder();
+            options_builder.clock(|| NOW_MILLIS.load(Ordering::Relaxed));
+            options_builder
+        });
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64) WITH TTL 1;");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, dinero) VALUES (1, 10);");
+        let select = || {
+            let mut transaction = simple_db.begin(&context).unwrap();
+            let rows = simple_db.execute_sql(&transaction.context(), "SELECT * FROM personas;").data().all().unwrap();
+            transaction.commit().unwrap();
+            rows
+        };
+
+        NOW_MILLIS.store(999, Ordering::Relaxed);
+        assert_eq!(select().len(), 1);
+        NOW_MILLIS.store(1000, Ordering::Relaxed);
+        assert!(select().is_empty());
+    }
+
+    #[test]
+    fn table_ttl_updates_and_indexes() {
+        static NOW_MILLIS: AtomicU64 = AtomicU64::new(0);
+        let simple_db = TestSimpleDb::create_with_options("table_ttl_updates_and_indexes", {
+            let mut options_builder = shared::start_simpledb_options_builder();
+            options_builder.select_cache_n_entries(8);
+            options_builder.clock(|| NOW_MILLIS.load(Ordering::Relaxed));
+            options_builder
+        });
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64) WITH TTL 1;");
+        simple_db.execute_sql(&context, "CREATE INDEX ON personas (dinero);");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, dinero) VALUES (1, 10);");
+        let select = |query: &str| {
+            let mut transaction = simple_db.begin(&context).unwrap();
+            let rows = simple_db.execute(&transaction.context(), simple_db.parse(query).unwrap()).unwrap().data().all().unwrap();
+            transaction.commit().unwrap();
+            rows
+        };
+
+        NOW_MILLIS.store(600, Ordering::Relaxed);
+        //Updates don't extend the life of the row
+        simple_db.execute_in_transaction(&context, "UPDATE personas SET dinero = 20 WHERE id == 1;");
+        assert_eq!(select("SELECT * FROM personas;").len(), 1);
+        assert_eq!(select("SELECT * FROM personas WHERE dinero == 20;").len(), 1);
+        assert_eq!(select("SELECT id, dinero FROM personas WHERE dinero > 5;").len(), 1);
+
+        NOW_MILLIS.store(1200, Ordering::Relaxed);
+        assert!(select("SELECT * FROM personas;").is_empty());
+        assert!(select("SELECT * FROM personas WHERE dinero == 20;").is_empty());
+        assert!(select("SELECT id, dinero FROM personas WHERE dinero == 20;").is_empty());
+        assert!(select("SELECT id, dinero FROM personas WHERE dinero > 5;").is_empty());
+    }
+}
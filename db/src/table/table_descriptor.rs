@@ -1,7 +1,9 @@
-use crate::table::schema::{Column, Schema};
-use bytes::{Buf, BufMut};
+use crate::sql::parser::expression::Expression;
+use crate::table::schema::{Column, ColumnDefinition, CompositeIndex, Schema};
+use bytes::{Buf, BufMut, Bytes};
+use crossbeam_skiplist::{SkipMap, SkipSet};
 use shared::SimpleDbError::CannotWriteTableDescriptor;
-use shared::{ColumnId, KeyspaceId, SimpleDbError, SimpleDbFile, Type};
+use shared::{ColumnId, KeyspaceId, SimpleDbError, SimpleDbFile, Type, Value};
 use std::path::PathBuf;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering::Relaxed;
@@ -15,7 +17,11 @@ pub struct TableDescriptor {
     pub(crate) table_name: String,
     pub(crate) schema: Schema,
     pub(crate) next_column_id: AtomicUsize,
-    pub(crate) storage_keyspace_id: KeyspaceId
+    pub(crate) storage_keyspace_id: KeyspaceId,
+    pub(crate) ttl_seconds: Option<u64>, //Rows older than this are expired
+    pub(crate) default_values: SkipMap<ColumnId, Value>, //Used for the columns omitted in an insert
+    pub(crate) not_null_columns: SkipSet<ColumnId>,
+    pub(crate) checks: Vec<Expression>, //Every inserted or updated row should satisfy them
 }
 
 impl TableDescriptor {
@@ -23,26 +29,41 @@ impl TableDescriptor {
         keyspace_id: KeyspaceId,
         options: &Arc<shared::SimpleDbOptions>,
         table_name: &str,
-        columns: Vec<(String, Type, bool)>,
+        columns: Vec<ColumnDefinition>,
+        checks: Vec<Expression>,
+        ttl_seconds: Option<u64>,
     ) -> Result<TableDescriptor, SimpleDbError> {
-        let mut next_column_id = AtomicUsize::new(0);
+        let next_column_id = AtomicUsize::new(0);
+        let default_values = SkipMap::new();
+        let not_null_columns = SkipSet::new();
 
         let mut table_descriptor = TableDescriptor {
             file: Mutex::new(SimpleDbFile::create_mock()),
-            next_column_id: AtomicUsize::new(0),
+            next_column_id: AtomicUsize::new(columns.len()),
             table_name: table_name.to_string(),
             storage_keyspace_id: keyspace_id,
+            ttl_seconds,
             schema: Schema::create(columns.iter()
-                .map(|(column_name, column_type, is_primary)| {
+                .map(|column| {
+                    let column_id = next_column_id.fetch_add(1, Relaxed) as ColumnId;
+                    if let Some(default_value) = &column.default_value {
+                        default_values.insert(column_id, default_value.clone());
+                    }
+                    if column.is_not_null {
+                        not_null_columns.insert(column_id);
+                    }
                     Column {
-                        column_id: next_column_id.fetch_add(1, Relaxed) as ColumnId,
-                        column_type: column_type.clone(),
-                        column_name: column_name.clone(),
-                        is_primary: *is_primary,
+                        column_id,
+                        column_type: column.column_type,
+                        column_name: column.column_name.clone(),
+                        is_primary: column.is_primary,
                         secondary_index_keyspace_id: None,
                     }
                 })
                 .collect()),
+            default_values,
+            not_null_columns,
+            checks,
         };
 
         let table_descriptor_file = SimpleDbFile::create(
@@ -52,7 +73,6 @@ impl TableDescriptor {
         ).map_err(|e| SimpleDbError::CannotCreateTableDescriptor(keyspace_id, e))?;
 
         table_descriptor.file = Mutex::new(table_descriptor_file);
-        table_descriptor.next_column_id = next_column_id;
 
         Ok(table_descriptor)
     }
@@ -64,6 +84,10 @@ impl TableDescriptor {
             schema: Schema::create(columns),
             next_column_id: AtomicUsize::new(10),
             storage_keyspace_id: 0,
+            ttl_seconds: None,
+            default_values: SkipMap::new(),
+            not_null_columns: SkipSet::new(),
+            checks: Vec::new(),
         }
     }
 
@@ -89,22 +113,39 @@ impl TableDescriptor {
         Ok(table_descriptor)
     }
 
+    pub fn delete(&self) -> Result<(), SimpleDbError> {
+        self.file.lock().unwrap().delete()
+            .map_err(|e| SimpleDbError::CannotDeleteTableDescriptor(self.storage_keyspace_id, e))
+    }
+
     pub fn get_schema(&self) -> &Schema {
         &self.schema
     }
 
-    pub fn add_column(
-        &self,
-        name: &str,
-        column_type: Type,
-        is_primary: bool
-    ) -> Result<(), SimpleDbError> {
+    pub fn get_default_value(&self, column_id: ColumnId) -> Option<Value> {
+        self.default_values.get(&column_id)
+            .map(|entry| entry.value().clone())
+    }
+
+    //Primary columns are always not null
+    pub fn is_not_null(&self, column: &Column) -> bool {
+        column.is_primary || self.not_null_columns.contains(&column.column_id)
+    }
+
+    pub fn add_column(&self, column: ColumnDefinition) -> Result<(), SimpleDbError> {
+        let column_id = self.next_column_id.fetch_add(1, Relaxed) as ColumnId;
+        if let Some(default_value) = column.default_value {
+            self.default_values.insert(column_id, default_value);
+        }
+        if column.is_not_null {
+            self.not_null_columns.insert(column_id);
+        }
         self.schema.add_column(Column {
-            column_id: self.next_column_id.fetch_add(1, Relaxed) as ColumnId,
+            column_id,
             secondary_index_keyspace_id: None,
-            column_name: name.to_string(),
-            column_type,
-            is_primary,
+            column_name: column.column_name,
+            column_type: column.column_type,
+            is_primary: column.is_primary,
         });
 
         let mut file = self.file.lock().unwrap();
@@ -114,10 +155,74 @@ impl TableDescriptor {
         Ok(())
     }
 
+    //Rows keep the bytes of the dropped column. Column IDs are never reused, so they will be ignored when read
+    pub fn drop_column(&self, name: &str) -> Result<(), SimpleDbError> {
+        let mut file_lock = self.file.lock().unwrap();
+        let column = self.schema.get_column_or_err(name)?;
+        if column.is_primary {
+            return Err(SimpleDbError::CannotDropPrimaryColumn(name.to_string()));
+        }
+        let is_in_composite_index = self.schema.get_composite_indexes().iter()
+            .any(|composite_index| composite_index.columns_id.contains(&column.column_id));
+        if column.is_secondary_indexed() || is_in_composite_index {
+            return Err(SimpleDbError::CannotDropIndexedColumn(name.to_string()));
+        }
+        if self.is_used_by_checks(&column.column_name) {
+            return Err(SimpleDbError::ColumnUsedByCheck(name.to_string()));
+        }
+
+        self.schema.remove_column(column.column_id);
+
+        file_lock.safe_replace(&self.serialize())
+            .map_err(|io_error| CannotWriteTableDescriptor(self.storage_keyspace_id, io_error))?;
+
+        Ok(())
+    }
+
+    //The column keeps its ID, so stored rows and its indexes are still valid
+    pub fn rename_column(&self, old_name: &str, new_name: &str) -> Result<(), SimpleDbError> {
+        let mut file_lock = self.file.lock().unwrap();
+        if self.schema.get_column(new_name).is_some() {
+            return Err(SimpleDbError::ColumnNameAlreadyDefined(new_name.to_string()));
+        }
+        let column = self.schema.get_column(old_name)
+            .ok_or(SimpleDbError::UnknownColumn(old_name.to_string()))?;
+        //Checks reference columns by name
+        if self.is_used_by_checks(&column.column_name) {
+            return Err(SimpleDbError::ColumnUsedByCheck(old_name.to_string()));
+        }
+
+        self.schema.rename_column(column.column_id, new_name);
+
+        file_lock.safe_replace(&self.serialize())
+            .map_err(|io_error| CannotWriteTableDescriptor(self.storage_keyspace_id, io_error))?;
+
+        Ok(())
+    }
+
+    fn is_used_by_checks(&self, column_name: &str) -> bool {
+        self.checks.iter()
+            .any(|check| check.get_identifiers_recursive().contains(column_name))
+    }
+
+    pub fn add_composite_index(
+        &self,
+        composite_index: CompositeIndex
+    ) -> Result<(), SimpleDbError> {
+        let mut file_lock = self.file.lock().unwrap();
+        self.schema.add_composite_index(composite_index);
+
+        file_lock.safe_replace(&self.serialize())
+            .map_err(|io_error| CannotWriteTableDescriptor(self.storage_keyspace_id, io_error))?;
+
+        Ok(())
+    }
+
+    //None will remove the secondary index from the column
     pub fn update_column_secondary_index(
         &self,
         column_id_indexed: ColumnId,
-        keyspace_id: KeyspaceId
+        keyspace_id: Option<KeyspaceId>
     ) -> Result<(), SimpleDbError> {
         let mut file_lock = self.file.lock().unwrap();
         let mut new_columns = Vec::new();
@@ -125,7 +230,7 @@ impl TableDescriptor {
         for current in columns {
             if current.column_id == column_id_indexed {
                 let mut updated_column = current.clone();
-                updated_column.secondary_index_keyspace_id = Some(keyspace_id);
+                updated_column.secondary_index_keyspace_id = keyspace_id;
                 new_columns.push(updated_column);
             } else {
                 new_columns.push(current);
@@ -153,6 +258,24 @@ impl TableDescriptor {
         serialized.put_u32_le(table_name_bytes.len() as u32);
         serialized.extend(table_name_bytes);
         serialized.extend(self.schema.serialize());
+        serialized.put_u64_le(self.next_column_id.load(Relaxed) as u64);
+        serialized.put_u64_le(self.ttl_seconds.unwrap_or(0));
+        serialized.put_u32_le(self.default_values.len() as u32);
+        for entry in self.default_values.iter() {
+            let default_value = entry.value();
+            serialized.put_u16_le(*entry.key());
+            serialized.put_u8(default_value.get_type().serialize());
+            serialized.put_u32_le(default_value.get_bytes().len() as u32);
+            serialized.extend(default_value.get_bytes());
+        }
+        serialized.put_u32_le(self.not_null_columns.len() as u32);
+        for entry in self.not_null_columns.iter() {
+            serialized.put_u16_le(*entry.value());
+        }
+        serialized.put_u32_le(self.checks.len() as u32);
+        for check in &self.checks {
+            check.serialize(&mut serialized);
+        }
 
         serialized
     }
@@ -170,11 +293,62 @@ impl TableDescriptor {
         let table_name = decode_string(name_bytes, storage_keyspace_id, 0)?;
 
         let schema = Schema::deserialize(&mut current_ptr, storage_keyspace_id)?;
+        //Fields added in later versions are appended at the end, so descriptors written by older versions end before
+        //them. Each one is only read if there are bytes left
+        let mut next_column_id = schema.get_max_column_id() as usize + 1;
+        if current_ptr.has_remaining() {
+            next_column_id = next_column_id.max(current_ptr.get_u64_le() as usize);
+        }
+        //0 if the table doesn't have TTL
+        let mut ttl_seconds = None;
+        if current_ptr.has_remaining() {
+            ttl_seconds = Some(current_ptr.get_u64_le()).filter(|ttl_seconds| *ttl_seconds > 0);
+        }
+        let default_values = SkipMap::new();
+        if current_ptr.has_remaining() {
+            let n_default_values = current_ptr.get_u32_le();
+            for index in 0..n_default_values as usize {
+                let column_id = current_ptr.get_u16_le() as ColumnId;
+                let value_type = Type::deserialize(current_ptr.get_u8())
+                    .map_err(|unknown_flag| SimpleDbError::CannotDecodeTableDescriptor(storage_keyspace_id, shared::DecodeError {
+                        error_type: shared::DecodeErrorType::UnknownFlag(unknown_flag as usize),
+                        offset: 0,
+                        index,
+                    }))?;
+                let value_length = current_ptr.get_u32_le() as usize;
+                let value_bytes = Bytes::copy_from_slice(&current_ptr[..value_length]);
+                current_ptr.advance(value_length);
+                default_values.insert(column_id, Value::create(value_bytes, value_type)?);
+            }
+        }
+        let not_null_columns = SkipSet::new();
+        if current_ptr.has_remaining() {
+            let n_not_null_columns = current_ptr.get_u32_le();
+            for _ in 0..n_not_null_columns {
+                not_null_columns.insert(current_ptr.get_u16_le() as ColumnId);
+            }
+        }
+        let mut checks = Vec::new();
+        if current_ptr.has_remaining() {
+            let n_checks = current_ptr.get_u32_le() as usize;
+            for index in 0..n_checks {
+                checks.push(Expression::deserialize(&mut current_ptr)
+                    .map_err(|error_type| SimpleDbError::CannotDecodeTableDescriptor(storage_keyspace_id, shared::DecodeError {
+                        error_type,
+                        offset: 0,
+                        index,
+                    }))?);
+            }
+        }
 
         Ok(TableDescriptor {
-            next_column_id: AtomicUsize::new(schema.get_max_column_id() as usize + 1),
+            next_column_id: AtomicUsize::new(next_column_id),
             file: Mutex::new(SimpleDbFile::create_mock()), //Temporal
             storage_keyspace_id,
+            ttl_seconds,
+            default_values,
+            not_null_columns,
+            checks,
             table_name,
             schema
         })
@@ -27,6 +27,8 @@ pub struct TableIterator<I: StorageIterator> {
 
     rows_reassembling: Vec<RowReassemble>,
     current_row: Option<Row>,
+    //The storage iterator is expected to have been placed at the end with seek_to_end()
+    is_backwards: bool,
 
     table: Arc<Table>
 }
@@ -41,46 +43,73 @@ impl<I: StorageIterator> TableIterator<I> {
             rows_reassembling: Vec::new(),
             simple_db_storage_iterator,
             current_row: None,
+            is_backwards: false,
             selection,
             table,
         }
     }
 
+    pub(crate) fn create_backwards(
+        simple_db_storage_iterator: I,
+        selection: Vec<ColumnId>, //Columns ID to select
+        table: Arc<Table>
+    ) -> TableIterator<I> {
+        let mut iterator = Self::create(simple_db_storage_iterator, selection, table);
+        iterator.is_backwards = true;
+        iterator
+    }
+
     pub fn next(&mut self) -> bool {
-        while self.n_reassembled_rows_that_can_be_returned() == 0 {
-            if !self.simple_db_storage_iterator.next() {
-                break;
+        loop {
+            while self.n_reassembled_rows_that_can_be_returned() == 0 {
+                if !self.advance_storage_iterator() {
+                    break;
+                }
+
+                let record = Record::deserialize(&mut self.simple_db_storage_iterator.value().as_ref());
+                let key = Bytes::copy_from_slice(self.simple_db_storage_iterator.key().as_bytes());
+                self.reassemble_row(key, record);
             }
 
-            let record = Record::deserialize(&mut self.simple_db_storage_iterator.value().as_ref());
-            let key = Bytes::copy_from_slice(self.simple_db_storage_iterator.key().as_bytes());
-            self.reassemble_row(key, record);
-        }
-
-        if self.rows_reassembling.is_empty() {
-            return false;
-        }
+            if self.rows_reassembling.is_empty() {
+                return false;
+            }
 
-        let row_in_reassembling = self.rows_reassembling.remove(0);
-        let key_bytes = row_in_reassembling.key.clone();
-        let mut row_record_reassembled = row_in_reassembling.build();
-        row_record_reassembled.project_selection(&self.selection);
+            let row_in_reassembling = self.rows_reassembling.remove(0);
+            let key_bytes = row_in_reassembling.key.clone();
+            let mut row_record_reassembled = row_in_reassembling.build();
+            //Expired rows might not have been removed yet by compaction
+            if self.table.is_expired(&row_record_reassembled) {
+                continue;
+            }
+            row_record_reassembled.project_selection(&self.selection);
 
-        let schema = self.table.get_schema();
+            let schema = self.table.get_schema();
 
-        self.current_row = Some(Row::create(
-            row_record_reassembled,
-            Value::create(key_bytes, schema.get_primary_column().column_type).unwrap(),
-            self.table.get_schema().clone())
-        );
+            let mut row = Row::create(
+                row_record_reassembled,
+                Value::create(key_bytes, schema.get_primary_column().column_type).unwrap(),
+                self.table.get_schema().clone()
+            );
+            row.selected_columns_id = Some(self.selection.iter().cloned().collect());
+            self.current_row = Some(row);
 
-        true
+            return true;
+        }
     }
 
     pub fn row(&self) -> &Row {
         self.current_row.as_ref().unwrap()
     }
 
+    fn advance_storage_iterator(&mut self) -> bool {
+        if self.is_backwards {
+            self.simple_db_storage_iterator.prev()
+        } else {
+            self.simple_db_storage_iterator.next()
+        }
+    }
+
     fn reassemble_row(&mut self, key: Bytes, record: Record) {
         let schema = self.table.get_schema();
 
@@ -0,0 +1,9 @@
+use shared::Value;
+
+//Returned by SHOW STATS FROM <table>. Every value is approximate, as it is read from the storage metadata
+pub struct TableStats {
+    pub n_rows: Option<usize>, //None if it cannot be estimated
+    pub n_sstables_per_level: Vec<usize>,
+    pub size_bytes: usize, //Bytes stored on disk by the SSTables
+    pub primary_key_range: Option<(Value, Value)>, //Deleted rows might be included. None if the table is empty
+}
@@ -0,0 +1,80 @@
+use crate::simple_db::{Context, SimpleDb, StatementResult};
+use crate::Statement;
+use shared::logger::Logger;
+use shared::{SimpleDbOptions, SimpleDbOptionsBuilder};
+use std::ops::Deref;
+use std::path::PathBuf;
+use std::sync::{Arc, Once};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static INIT_LOGGER: Once = Once::new();
+
+//SimpleDb with an empty database named "test", stored in a temporary directory that is removed when it is dropped
+pub(crate) struct TestSimpleDb {
+    simple_db: Option<SimpleDb>, //None while it is stopped
+    options: Arc<SimpleDbOptions>,
+    pub(crate) path: PathBuf,
+}
+
+impl TestSimpleDb {
+    pub(crate) fn create(name: &str) -> TestSimpleDb {
+        Self::create_with_options(name, shared::start_simpledb_options_builder())
+    }
+
+    pub(crate) fn create_with_options(name: &str, mut options_builder: SimpleDbOptionsBuilder) -> TestSimpleDb {
+        let mut path = std::env::temp_dir();
+        path.push(format!("simpledb-{}-{}", name, SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()));
+        std::fs::create_dir_all(&path).unwrap();
+        let options = options_builder
+            .base_path(path.to_str().unwrap())
+            .build_arc();
+        INIT_LOGGER.call_once(|| Logger::init(options.clone()));
+        let simple_db = crate::simple_db::create(options.clone()).unwrap();
+        simple_db.execute(&Context::empty(), Statement::CreateDatabase(String::from("test"))).unwrap();
+
+        TestSimpleDb { simple_db: Some(simple_db), options, path }
+    }
+
+    //Simulates a shutdown. The files are kept until it is dropped
+    pub(crate) fn stop(&mut self) {
+        self.simple_db = None;
+    }
+
+    //Loads again the files left by stop()
+    pub(crate) fn start(&mut self) {
+        self.simple_db = Some(crate::simple_db::create(self.options.clone()).unwrap());
+    }
+
+    pub(crate) fn execute_sql(&self, context: &Context, statement: &str) -> StatementResult {
+        self.execute(context, self.parse(statement).unwrap()).unwrap()
+    }
+
+    pub(crate) fn execute_in_transaction(&self, context: &Context, statement: &str) {
+        let mut transaction = self.begin(context).unwrap();
+        self.execute_sql(&transaction.context(), statement);
+        transaction.commit().unwrap();
+    }
+}
+
+impl Deref for TestSimpleDb {
+    type Target = SimpleDb;
+
+    fn deref(&self) -> &SimpleDb {
+        self.simple_db.as_ref().unwrap()
+    }
+}
+
+impl Drop for TestSimpleDb {
+    fn drop(&mut self) {
+        self.simple_db = None;
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+pub(crate) fn select_dinero_sum(simple_db: &SimpleDb, context: &Context) -> i64 {
+    let mut transaction = simple_db.begin(context).unwrap();
+    let rows = simple_db.execute(&transaction.context(), simple_db.parse("SELECT SUM(dinero) FROM personas;").unwrap())
+        .unwrap().data().all().unwrap();
+    transaction.commit().unwrap();
+    rows[0].get_column_value("SUM(dinero)").unwrap().get_i64().unwrap()
+}
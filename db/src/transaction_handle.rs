@@ -0,0 +1,200 @@
+use crate::database::database::Database;
+use crate::simple_db::Context;
+use shared::SimpleDbError;
+use std::sync::Arc;
+use storage::transactions::transaction::Transaction;
+
+//Returned by SimpleDb::begin(). A transaction can only be committed or rolledback once,
+//any later attempt will return SimpleDbError::TransactionAlreadyFinished
+pub struct TransactionHandle {
+    database: Arc<Database>,
+    transaction: Transaction,
+    finished: bool,
+    drop_behavior: DropBehavior,
+}
+
+//What happens when a handle is dropped without having been committed or rolledback
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DropBehavior {
+    Rollback,
+    //The transaction will be kept active. It should be committed or rolledback with SimpleDb::execute()
+    RequireExplicit,
+}
+
+impl TransactionHandle {
+    pub(crate) fn create(
+        database: Arc<Database>,
+        transaction: Transaction,
+    ) -> TransactionHandle {
+        TransactionHandle {
+            drop_behavior: DropBehavior::Rollback,
+            finished: false,
+            transaction,
+            database,
+        }
+    }
+
+    pub fn commit(&mut self) -> Result<(), SimpleDbError> {
+        self.finish()?;
+        self.database.commit_transaction(&self.transaction)
+    }
+
+    pub fn rollback(&mut self) -> Result<(), SimpleDbError> {
+        self.finish()?;
+        self.database.rollback_transaction(&self.transaction)
+    }
+
+    //Context used to run statements inside the transaction with SimpleDb::execute()
+    pub fn context(&self) -> Context {
+        Context::create(self.database.name(), self.transaction.clone())
+    }
+
+    pub fn with_drop_behavior(&mut self, drop_behavior: DropBehavior) {
+        self.drop_behavior = drop_behavior;
+    }
+
+    pub fn transaction(&self) -> &Transaction {
+        &self.transaction
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    fn finish(&mut self) -> Result<(), SimpleDbError> {
+        if self.finished {
+            return Err(SimpleDbError::TransactionAlreadyFinished(self.transaction.id()));
+        }
+
+        self.finished = true;
+        Ok(())
+    }
+}
+
+impl Drop for TransactionHandle {
+    fn drop(&mut self) {
+        if !self.finished && self.drop_behavior == DropBehavior::Rollback {
+            let _ = self.rollback();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{DropBehavior, Statement};
+    use crate::simple_db::Context;
+    use crate::table::row::RowIterator;
+    use crate::test_utils::TestSimpleDb;
+    use shared::SimpleDbError;
+    use std::time::Duration;
+
+    #[test]
+    fn drop_rollbacks() {
+        let simple_db = TestSimpleDb::create("drop_rollbacks");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR);");
+
+        let transaction = simple_db.begin(&context).unwrap();
+        let txn_id = transaction.transaction().id();
+        simple_db.execute_sql(&transaction.context(), "INSERT INTO personas (id, nombre) VALUES (1, \"Jaime\");");
+        drop(transaction);
+
+        let database = simple_db.get_databases().get_database("test").unwrap();
+        assert!(!database.get_active_transactions().contains(&txn_id));
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        let mut rows = simple_db.execute_sql(&transaction.context(), "SELECT * FROM personas;").data();
+        assert!(rows.next().unwrap().is_none());
+        transaction.commit().unwrap();
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        transaction.with_drop_behavior(DropBehavior::RequireExplicit);
+        let txn_id = transaction.transaction().id();
+        drop(transaction);
+        assert!(database.get_active_transactions().contains(&txn_id));
+    }
+
+    #[test]
+    fn savepoints() {
+        let simple_db = TestSimpleDb::create("savepoints");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR);");
+
+        let mut transaction = simple_db.begin(&context).unwrap();
+        simple_db.execute_sql(&transaction.context(), "INSERT INTO personas (id, nombre) VALUES (1, \"Jaime\");");
+        simple_db.execute_sql(&transaction.context(), "INSERT INTO personas (id, nombre) VALUES (2, \"Molon\");");
+        simple_db.execute_sql(&transaction.context(), "SAVEPOINT antes;");
+        simple_db.execute_sql(&transaction.context(), "INSERT INTO personas (id, nombre) VALUES (3, \"Wili\");");
+        simple_db.execute_sql(&transaction.context(), "UPDATE personas SET nombre = \"Juan\" WHERE id == 1;");
+        simple_db.execute_sql(&transaction.context(), "ROLLBACK TO SAVEPOINT antes;");
+        transaction.commit().unwrap();
+
+        let transaction = simple_db.begin(&context).unwrap();
+        let rows = simple_db.execute_sql(&transaction.context(), "SELECT * FROM personas;")
+            .data().all().unwrap();
+        let names: Vec<String> = rows.iter().map(|row| row.get_column_value("nombre").unwrap().to_string()).collect();
+        assert_eq!(names, vec![String::from("Jaime"), String::from("Molon")]);
+        assert!(matches!(simple_db.execute(&transaction.context(), simple_db.parse("RELEASE SAVEPOINT antes;").unwrap()),
+            Err(SimpleDbError::SavepointNotFound(_))));
+    }
+
+    #[test]
+    fn deadlock() {
+        let simple_db = TestSimpleDb::create("deadlock");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64);");
+        simple_db.execute_in_transaction(&context, "INSERT INTO personas (id, dinero) VALUES (1, 0), (2, 0);");
+
+        let mut transaction_a = simple_db.begin(&context).unwrap();
+        let mut transaction_b = simple_db.begin(&context).unwrap();
+        simple_db.execute_sql(&transaction_a.context(), "UPDATE personas SET dinero = 10 WHERE id == 1;");
+        simple_db.execute_sql(&transaction_b.context(), "UPDATE personas SET dinero = 20 WHERE id == 2;");
+
+        let transaction_a_context = transaction_a.context();
+        std::thread::scope(|scope| {
+            //Waits until transaction b finishes
+            let waiting_update = scope.spawn(|| simple_db.execute(&transaction_a_context,
+                simple_db.parse("UPDATE personas SET dinero = 10 WHERE id == 2;").unwrap()).is_ok());
+            std::thread::sleep(Duration::from_millis(100));
+
+            //Transaction b is the youngest one, so it is aborted
+            assert!(matches!(simple_db.execute(&transaction_b.context(), simple_db.parse("UPDATE personas SET dinero = 20 WHERE id == 1;").unwrap()),
+                Err(SimpleDbError::DeadlockDetected(_))));
+            transaction_b.rollback().unwrap();
+            assert!(waiting_update.join().unwrap());
+        });
+        transaction_a.commit().unwrap();
+
+        let transaction = simple_db.begin(&context).unwrap();
+        let rows = simple_db.execute_sql(&transaction.context(), "SELECT * FROM personas;")
+            .data().all().unwrap();
+        let dinero: Vec<String> = rows.iter().map(|row| row.get_column_value("dinero").unwrap().to_string()).collect();
+        assert_eq!(dinero, vec![String::from("10"), String::from("10")]);
+    }
+
+    #[test]
+    fn isolation_levels() {
+        let simple_db = TestSimpleDb::create("isolation_levels");
+        let context = Context::create_with_database("test");
+        simple_db.execute_sql(&context, "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR);");
+
+        let mut writer = simple_db.begin(&context).unwrap();
+        simple_db.execute_sql(&writer.context(), "INSERT INTO personas (id, nombre) VALUES (1, \"Jaime\");");
+
+        let read_uncommitted = simple_db.execute_sql(&context, "START_TRANSACTION ISOLATION LEVEL READ UNCOMMITTED;")
+            .get_transaction();
+        let read_uncommitted_context = Context::create("test", read_uncommitted);
+        let rows = simple_db.execute_sql(&read_uncommitted_context, "SELECT * FROM personas;").data().all().unwrap();
+        assert_eq!(rows.len(), 1);
+        simple_db.execute(&read_uncommitted_context, Statement::Commit).unwrap();
+
+        let snapshot = simple_db.execute_sql(&context, "START_TRANSACTION ISOLATION LEVEL SNAPSHOT;")
+            .get_transaction();
+        let snapshot_context = Context::create("test", snapshot);
+        let rows = simple_db.execute_sql(&snapshot_context, "SELECT * FROM personas;").data().all().unwrap();
+        assert!(rows.is_empty());
+        simple_db.execute(&snapshot_context, Statement::Commit).unwrap();
+
+        writer.commit().unwrap();
+    }
+}
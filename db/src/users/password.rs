@@ -0,0 +1,133 @@
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use shared::SimpleDbError;
+use shared::SimpleDbError::InvalidPasswordHash;
+use std::num::NonZeroU32;
+
+//Stored hashes have the format: pbkdf2-sha256$<nº iterations>$<salt hex>$<hash hex>
+const HASH_PREFIX: &str = "pbkdf2-sha256";
+const N_ITERATIONS: u32 = 100_000;
+const SALT_LENGTH: usize = 16;
+const HASH_LENGTH: usize = 32;
+
+static ALGORITHM: pbkdf2::Algorithm = pbkdf2::PBKDF2_HMAC_SHA256;
+
+//Password of the server, configured with SimpleDbOptions::server_password, or of a user created with CREATE USER
+#[derive(Clone)]
+pub struct StoredPassword {
+    n_iterations: NonZeroU32,
+    salt: Vec<u8>,
+    hash: Vec<u8>,
+}
+
+impl StoredPassword {
+    //Legacy plaintext passwords are hashed when loaded, so the plaintext is never compared.
+    //A plaintext password might start with the hash prefix, so it is only used as a hash if it can be decoded
+    pub fn create(stored_password: &str) -> StoredPassword {
+        match Self::decode(stored_password) {
+            Ok(decoded_password) => decoded_password,
+            Err(_) => Self::hash(stored_password),
+        }
+    }
+
+    pub fn verify(&self, password: &str) -> bool {
+        pbkdf2::verify(ALGORITHM, self.n_iterations, &self.salt, password.as_bytes(), &self.hash).is_ok()
+    }
+
+    pub fn encode(&self) -> String {
+        format!("{}${}${}${}", HASH_PREFIX, self.n_iterations, to_hex(&self.salt), to_hex(&self.hash))
+    }
+
+    pub fn hash(password: &str) -> StoredPassword {
+        let mut salt = vec![0u8; SALT_LENGTH];
+        SystemRandom::new().fill(&mut salt)
+            .expect("Cannot generate password salt");
+        let n_iterations = NonZeroU32::new(N_ITERATIONS).unwrap();
+        let mut hash = vec![0u8; HASH_LENGTH];
+        pbkdf2::derive(ALGORITHM, n_iterations, &salt, password.as_bytes(), &mut hash);
+
+        StoredPassword { n_iterations, salt, hash }
+    }
+
+    pub fn decode(encoded: &str) -> Result<StoredPassword, SimpleDbError> {
+        let parts: Vec<&str> = encoded.split('$').collect();
+        if parts.len() != 4 || parts[0] != HASH_PREFIX {
+            return Err(InvalidPasswordHash(String::from("Expected pbkdf2-sha256$<iterations>$<salt>$<hash>")));
+        }
+
+        let n_iterations = parts[1].parse::<u32>().ok()
+            .and_then(NonZeroU32::new)
+            .ok_or_else(|| InvalidPasswordHash(format!("Invalid nº of iterations {}", parts[1])))?;
+        let salt = from_hex(parts[2])
+            .ok_or_else(|| InvalidPasswordHash(String::from("Invalid salt")))?;
+        let hash = from_hex(parts[3])
+            .filter(|hash| hash.len() == HASH_LENGTH)
+            .ok_or_else(|| InvalidPasswordHash(String::from("Invalid hash")))?;
+
+        Ok(StoredPassword { n_iterations, salt, hash })
+    }
+}
+
+//Returns the value to store in the options file for the given password
+pub fn hash_password(password: &str) -> String {
+    StoredPassword::hash(password).encode()
+}
+
+pub fn is_hashed(stored_password: &str) -> bool {
+    StoredPassword::decode(stored_password).is_ok()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+
+    (0..hex.len()).step_by(2)
+        .map(|index| hex.get(index..index + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::users::password::{hash_password, is_hashed, StoredPassword};
+
+    #[test]
+    fn verify_hashed_password() {
+        let hashed = hash_password("secreto");
+        assert!(is_hashed(&hashed));
+        assert_ne!(hash_password("secreto"), hashed);
+
+        let stored_password = StoredPassword::create(&hashed);
+        assert!(stored_password.verify("secreto"));
+        assert!(!stored_password.verify("Secreto"));
+        assert!(!stored_password.verify(""));
+        assert_eq!(stored_password.encode(), hashed);
+    }
+
+    #[test]
+    fn verify_legacy_plaintext_password() {
+        let stored_password = StoredPassword::create("123456");
+        assert!(stored_password.verify("123456"));
+        assert!(!stored_password.verify("1234567"));
+
+        //Plaintext passwords can start with the hash prefix
+        for plaintext_password in ["pbkdf2-sha256", "pbkdf2-sha256$1$2$3"] {
+            assert!(!is_hashed(plaintext_password));
+            assert!(StoredPassword::create(plaintext_password).verify(plaintext_password));
+        }
+    }
+
+    #[test]
+    fn invalid_hash() {
+        assert!(StoredPassword::decode("pbkdf2-sha256$0$00$00").is_err());
+        assert!(StoredPassword::decode("pbkdf2-sha256$1000$zz$00").is_err());
+        assert!(StoredPassword::decode("pbkdf2-sha256$1000$00").is_err());
+        assert!(StoredPassword::decode("123456").is_err());
+    }
+}
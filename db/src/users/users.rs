@@ -0,0 +1,276 @@
+use crate::users::password::StoredPassword;
+use bytes::{Buf, BufMut};
+use crossbeam_skiplist::SkipMap;
+use shared::SimpleDbError::{CannotDecodeUsersFile, CannotReadUsersFile, CannotWriteUsersFile, UserAlreadyExists, UserNotFound};
+use shared::{DecodeError, DecodeErrorType, SimpleDbError, SimpleDbFile, SimpleDbFileMode, SimpleDbOptions};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+//Users created with CREATE USER. A user can only access the databases granted to it with GRANT.
+//Users are stored in the users file of the base path. This file is stored in binary format and it is rewritten on every change
+
+// [ User name length (u32) | User name bytes... | Password hash length (u32) | Password hash bytes... |
+//   Nº databases (u32) | Database name length (u32) | Database name bytes... ]
+pub struct Users {
+    users: SkipMap<String, User>,
+    file: Mutex<SimpleDbFile>,
+}
+
+#[derive(Clone)]
+struct User {
+    password: StoredPassword,
+    databases: Vec<String>, //Granted databases
+}
+
+impl Users {
+    pub fn load(options: &Arc<SimpleDbOptions>) -> Result<Users, SimpleDbError> {
+        let file = SimpleDbFile::open(Self::users_file_path(options).as_path(), SimpleDbFileMode::RandomWrites)
+            .map_err(|e| CannotReadUsersFile(e))?;
+        let bytes = file.read_all()
+            .map_err(|e| CannotReadUsersFile(e))?;
+
+        Ok(Users {
+            users: Self::deserialize_users(&bytes)?,
+            file: Mutex::new(file),
+        })
+    }
+
+    pub fn create_user(&self, user_name: &str, password: &str) -> Result<(), SimpleDbError> {
+        let mut file = self.file.lock().unwrap();
+        if self.users.contains_key(user_name) {
+            return Err(UserAlreadyExists(user_name.to_string()));
+        }
+
+        self.users.insert(user_name.to_string(), User { password: StoredPassword::hash(password), databases: Vec::new() });
+        self.write_users(&mut file)
+    }
+
+    pub fn grant(&self, user_name: &str, database_name: &str) -> Result<(), SimpleDbError> {
+        let mut file = self.file.lock().unwrap();
+        let mut user = self.users.get(user_name)
+            .map(|entry| entry.value().clone())
+            .ok_or(UserNotFound(user_name.to_string()))?;
+
+        if !user.databases.iter().any(|database| database == database_name) {
+            user.databases.push(database_name.to_string());
+            self.users.insert(user_name.to_string(), user);
+            self.write_users(&mut file)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn authenticate(&self, user_name: &str, password: &str) -> bool {
+        self.users.get(user_name)
+            .map(|entry| entry.value().password.verify(password))
+            .unwrap_or(false)
+    }
+
+    pub fn has_access(&self, user_name: &str, database_name: &str) -> bool {
+        self.users.get(user_name)
+            .map(|entry| entry.value().databases.iter().any(|database| database == database_name))
+            .unwrap_or(false)
+    }
+
+    //There is no DROP DATABASE, a database is dropped by removing its directory. Its grants are removed when
+    //the users are loaded, so a database created later with the same name is not accessible by the old users
+    pub fn revoke_dropped_databases(&self, database_exists: impl Fn(&str) -> bool) -> Result<(), SimpleDbError> {
+        let mut file = self.file.lock().unwrap();
+        let mut some_revoked = false;
+
+        for entry in self.users.iter() {
+            let mut user = entry.value().clone();
+            let n_granted_databases = user.databases.len();
+            user.databases.retain(|database_name| database_exists(database_name));
+
+            if user.databases.len() != n_granted_databases {
+                self.users.insert(entry.key().clone(), user);
+                some_revoked = true;
+            }
+        }
+
+        if some_revoked {
+            self.write_users(&mut file)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn exists(&self, user_name: &str) -> bool {
+        self.users.contains_key(user_name)
+    }
+
+    fn write_users(&self, file: &mut SimpleDbFile) -> Result<(), SimpleDbError> {
+        let mut serialized = Vec::new();
+        for entry in self.users.iter() {
+            let user = entry.value();
+            Self::serialize_string(&mut serialized, entry.key());
+            Self::serialize_string(&mut serialized, &user.password.encode());
+            serialized.put_u32_le(user.databases.len() as u32);
+            for database_name in &user.databases {
+                Self::serialize_string(&mut serialized, database_name);
+            }
+        }
+
+        file.safe_replace(&serialized)
+            .map_err(|e| CannotWriteUsersFile(e))
+    }
+
+    fn serialize_string(serialized: &mut Vec<u8>, string: &str) {
+        serialized.put_u32_le(string.len() as u32);
+        serialized.extend(string.as_bytes());
+    }
+
+    fn deserialize_users(bytes: &[u8]) -> Result<SkipMap<String, User>, SimpleDbError> {
+        let mut current_ptr = bytes;
+        let users = SkipMap::new();
+
+        while current_ptr.has_remaining() {
+            let user_name = Self::deserialize_string(&mut current_ptr, users.len())?;
+            let password = StoredPassword::decode(&Self::deserialize_string(&mut current_ptr, users.len())?)?;
+            let n_databases = Self::deserialize_u32(&mut current_ptr, users.len())? as usize;
+            let mut databases = Vec::with_capacity(n_databases);
+            for _ in 0..n_databases {
+                databases.push(Self::deserialize_string(&mut current_ptr, users.len())?);
+            }
+
+            users.insert(user_name, User { password, databases });
+        }
+
+        Ok(users)
+    }
+
+    fn deserialize_string(current_ptr: &mut &[u8], index: usize) -> Result<String, SimpleDbError> {
+        let length = Self::deserialize_u32(current_ptr, index)? as usize;
+        if current_ptr.remaining() < length {
+            return Err(Self::truncated_file_error(length, current_ptr.remaining(), index));
+        }
+
+        let string_bytes = current_ptr[..length].to_vec();
+        current_ptr.advance(length);
+
+        String::from_utf8(string_bytes).map_err(|e| CannotDecodeUsersFile(DecodeError {
+            error_type: DecodeErrorType::Utf8Decode(e),
+            offset: 0,
+            index,
+        }))
+    }
+
+    fn deserialize_u32(current_ptr: &mut &[u8], index: usize) -> Result<u32, SimpleDbError> {
+        if current_ptr.remaining() < 4 {
+            return Err(Self::truncated_file_error(4, current_ptr.remaining(), index));
+        }
+
+        Ok(current_ptr.get_u32_le())
+    }
+
+    fn truncated_file_error(expected_size: usize, actual_size: usize, index: usize) -> SimpleDbError {
+        CannotDecodeUsersFile(DecodeError {
+            error_type: DecodeErrorType::IllegalSize(expected_size, actual_size),
+            offset: 0,
+            index,
+        })
+    }
+
+    fn users_file_path(options: &Arc<SimpleDbOptions>) -> PathBuf {
+        let mut path = PathBuf::from(&options.base_path);
+        path.push("users");
+        path
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::users::password::hash_password;
+    use crate::users::users::Users;
+    use crate::Statement;
+    use crate::simple_db::{Context, StatementResult};
+    use crate::test_utils::TestSimpleDb;
+    use bytes::BufMut;
+    use shared::SimpleDbError;
+
+    #[test]
+    fn deserialize_truncated_users() {
+        let mut bytes = Vec::new();
+        Users::serialize_string(&mut bytes, "jaime");
+        Users::serialize_string(&mut bytes, &hash_password("secreto"));
+        bytes.put_u32_le(1);
+        Users::serialize_string(&mut bytes, "test");
+        assert!(Users::deserialize_users(&bytes).is_ok());
+
+        for length in 1..bytes.len() {
+            assert!(matches!(Users::deserialize_users(&bytes[..length]), Err(SimpleDbError::CannotDecodeUsersFile(_))));
+        }
+    }
+
+    #[test]
+    fn users_access() {
+        let simple_db = TestSimpleDb::create("users_access");
+        let admin_context = Context::empty();
+        simple_db.execute(&admin_context, Statement::CreateDatabase(String::from("otro"))).unwrap();
+        simple_db.execute_sql(&admin_context, "CREATE USER jaime PASSWORD \"secreto\";");
+        simple_db.execute_sql(&admin_context, "GRANT test TO jaime;");
+        simple_db.execute(&Context::create_with_database("test"), simple_db.parse("CREATE TABLE personas (id I64 PRIMARY KEY);").unwrap()).unwrap();
+        simple_db.execute_in_transaction(&Context::create_with_database("test"), "INSERT INTO personas (id) VALUES (1);");
+
+        assert!(simple_db.authenticate_user("jaime", "secreto"));
+        assert!(!simple_db.authenticate_user("jaime", "123456"));
+        assert!(!simple_db.authenticate_user("noexiste", "secreto"));
+        assert!(matches!(simple_db.execute(&admin_context, simple_db.parse("GRANT noexiste TO jaime;").unwrap()), Err(SimpleDbError::DatabaseNotFound(_))));
+        assert!(matches!(simple_db.execute(&admin_context, simple_db.parse("GRANT test TO noexiste;").unwrap()), Err(SimpleDbError::UserNotFound(_))));
+        assert!(matches!(simple_db.execute(&admin_context, simple_db.parse("CREATE USER jaime PASSWORD \"otro\";").unwrap()), Err(SimpleDbError::UserAlreadyExists(_))));
+
+        //The granted database can be read
+        let user_context = simple_db.create_context(Some("jaime"), "test").unwrap();
+        let mut transaction = simple_db.begin(&user_context).unwrap();
+        let mut transaction_context = transaction.context();
+        transaction_context.with_user("jaime");
+        let rows = simple_db.execute_sql(&transaction_context, "SELECT * FROM personas;")
+            .data().all().unwrap();
+        assert_eq!(rows.len(), 1);
+        transaction.commit().unwrap();
+        match simple_db.execute(&user_context, Statement::ShowDatabases).unwrap() {
+            StatementResult::Databases(databases) => assert_eq!(databases, vec![String::from("test")]),
+            _ => panic!()
+        }
+
+        //Other databases and administration statements are denied
+        assert!(matches!(simple_db.create_context(Some("jaime"), "otro"), Err(SimpleDbError::AccessDenied(_, _))));
+        let mut denied_context = Context::create_with_database("otro");
+        denied_context.with_user("jaime");
+        assert!(matches!(simple_db.execute(&denied_context, Statement::ShowTables), Err(SimpleDbError::AccessDenied(_, _))));
+        assert!(matches!(simple_db.execute(&user_context, simple_db.parse("GRANT otro TO jaime;").unwrap()), Err(SimpleDbError::AccessDenied(_, _))));
+        assert!(matches!(simple_db.execute(&user_context, simple_db.parse("CREATE USER wili PASSWORD \"wili\";").unwrap()), Err(SimpleDbError::AccessDenied(_, _))));
+        assert!(matches!(simple_db.execute(&user_context, simple_db.parse("USE otro;").unwrap()), Err(SimpleDbError::AccessDenied(_, _))));
+
+        //Users are persisted
+        let options = shared::start_simpledb_options_builder()
+            .base_path(simple_db.path.to_str().unwrap())
+            .build_arc();
+        let users = Users::load(&options).unwrap();
+        assert!(users.authenticate("jaime", "secreto"));
+        assert!(users.has_access("jaime", "test"));
+        assert!(!users.has_access("jaime", "otro"));
+    }
+
+    #[test]
+    fn grants_of_dropped_databases_revoked_on_load() {
+        let mut simple_db = TestSimpleDb::create("grants_of_dropped_databases_revoked_on_load");
+        let admin_context = Context::empty();
+        simple_db.execute(&admin_context, Statement::CreateDatabase(String::from("otro"))).unwrap();
+        simple_db.execute_sql(&admin_context, "CREATE USER jaime PASSWORD \"secreto\";");
+        simple_db.execute_sql(&admin_context, "GRANT test TO jaime;");
+        simple_db.execute_sql(&admin_context, "GRANT otro TO jaime;");
+        simple_db.stop();
+
+        //Databases are dropped by removing their directory
+        std::fs::remove_dir_all(simple_db.path.join("otro")).unwrap();
+        simple_db.start();
+        assert!(simple_db.users.has_access("jaime", "test"));
+        assert!(!simple_db.users.has_access("jaime", "otro"));
+
+        //A new database with the same name is not accessible by the old grants
+        simple_db.execute(&admin_context, Statement::CreateDatabase(String::from("otro"))).unwrap();
+        assert!(matches!(simple_db.create_context(Some("jaime"), "otro"), Err(SimpleDbError::AccessDenied(_, _))));
+    }
+}
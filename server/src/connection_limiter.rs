@@ -0,0 +1,110 @@
+use std::collections::VecDeque;
+use std::net::TcpStream;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+pub enum Admission {
+    Accepted(TcpStream), //A slot has been taken, the connection can be served
+    Queued, //It will be served by a connection thread once another connection gets closed
+    Rejected(TcpStream), //Both the slots and the accept queue are full
+}
+
+//Limits the nº of connections served at the same time, so every connection has its own thread.
+//When all slots are taken, new connections wait in a bounded queue. The thread of a closed connection
+//will serve the next queued one, so no threads are spawned for queued connections
+pub struct ConnectionLimiter {
+    max_connections: usize, //0 means no limit
+    max_queued_connections: usize,
+    queued_connection_timeout: Duration, //Zero means no timeout
+
+    state: Mutex<ConnectionLimiterState>,
+}
+
+struct ConnectionLimiterState {
+    n_active_connections: usize,
+    queued_connections: VecDeque<(TcpStream, Instant)>, //Connections with the time they were queued at
+}
+
+impl ConnectionLimiter {
+    pub fn create(
+        max_connections: usize,
+        max_queued_connections: usize,
+        queued_connection_timeout: Duration,
+    ) -> ConnectionLimiter {
+        ConnectionLimiter {
+            state: Mutex::new(ConnectionLimiterState {
+                queued_connections: VecDeque::new(),
+                n_active_connections: 0,
+            }),
+            queued_connection_timeout,
+            max_queued_connections,
+            max_connections,
+        }
+    }
+
+    pub fn admit(&self, socket: TcpStream) -> Admission {
+        let mut state = self.state.lock().unwrap();
+
+        if self.max_connections == 0 || state.n_active_connections < self.max_connections {
+            state.n_active_connections += 1;
+            Admission::Accepted(socket)
+        } else if state.queued_connections.len() < self.max_queued_connections {
+            state.queued_connections.push_back((socket, Instant::now()));
+            Admission::Queued
+        } else {
+            Admission::Rejected(socket)
+        }
+    }
+
+    //Called when a connection is closed. If there is a queued connection, it takes the released slot
+    //and it is returned, so it can be served by the calling thread
+    pub fn release(&self) -> Option<TcpStream> {
+        let mut state = self.state.lock().unwrap();
+
+        match state.queued_connections.pop_front() {
+            Some((queued_connection, _)) => Some(queued_connection),
+            None => {
+                state.n_active_connections -= 1;
+                None
+            }
+        }
+    }
+
+    //Removes the connections that have been queued for longer than the timeout, so they can be rejected.
+    //Connections are queued in order, so the expired ones are at the front of the queue
+    pub fn take_expired_queued_connections(&self) -> Vec<TcpStream> {
+        let mut state = self.state.lock().unwrap();
+        let mut expired_connections = Vec::new();
+
+        if self.queued_connection_timeout.is_zero() {
+            return expired_connections;
+        }
+
+        while let Some((_, queued_at)) = state.queued_connections.front() {
+            if queued_at.elapsed() < self.queued_connection_timeout {
+                break;
+            }
+
+            let (expired_connection, _) = state.queued_connections.pop_front().unwrap();
+            expired_connections.push(expired_connection);
+        }
+
+        expired_connections
+    }
+
+    pub fn queued_connection_timeout(&self) -> Duration {
+        self.queued_connection_timeout
+    }
+
+    pub fn get_n_active_connections(&self) -> usize {
+        self.state.lock().unwrap().n_active_connections
+    }
+
+    pub fn get_n_queued_connections(&self) -> usize {
+        self.state.lock().unwrap().queued_connections.len()
+    }
+
+    pub fn max_connections(&self) -> usize {
+        self.max_connections
+    }
+}
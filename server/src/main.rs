@@ -1,16 +1,23 @@
 use crate::options_file::load_options;
 use crate::server::Server;
-use std::{env, fs};
+use std::{env, io};
 use std::fs::OpenOptions;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 mod server;
+mod connection_limiter;
 mod options_file;
 mod request;
 mod response;
 
 fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() > 1 && args[1] == "hash-password" {
+        hash_password_command(&args);
+        return;
+    }
+
     let options_path = get_simpledb_options_path()
         .expect("Provide the simple db program path");
     let options = load_options(options_path)
@@ -21,6 +28,21 @@ fn main() {
     server.start()
 }
 
+//Prints the hash to store as server_password in the options file.
+//The password is read from stdin if it is not passed as an argument
+fn hash_password_command(args: &[String]) {
+    let password = match args.get(2) {
+        Some(password) => password.clone(),
+        None => {
+            let mut password = String::new();
+            io::stdin().read_line(&mut password).expect("Cannot read password from stdin");
+            password.trim_end_matches(['\r', '\n']).to_string()
+        }
+    };
+
+    println!("{}", db::hash_password(&password));
+}
+
 fn get_simpledb_options_path() -> Result<String, ()> {
     let args: Vec<String> = env::args().collect();
     if args.len() > 1 {
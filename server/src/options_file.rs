@@ -62,4 +62,48 @@ fn config_file_path(base_path: &Path) -> PathBuf {
     let mut path_buff = PathBuf::from(base_path);
     path_buff.push("config.json");
     path_buff
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod test {
+    use crate::options_file::{config_file_path, load_options};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    //Config file written by a version without the options added later
+    const OLD_CONFIG_FILE: &str = r#"{
+        "use_debug_logging": false,
+        "server_password": "my_password",
+        "server_port": 1234,
+        "sort_page_size_bytes": 4096,
+        "simple_leveled_compaction_options": { "level0_file_num_compaction_trigger": 2, "size_ratio_percent": 2, "max_levels": 4 },
+        "tiered_compaction_options": { "min_levels_trigger_size_ratio": 3, "max_size_amplification": 2, "size_ratio": 2 },
+        "compaction_strategy": "SimpleLeveled",
+        "compaction_task_frequency_ms": 100,
+        "n_cached_blocks_per_sstable": 8,
+        "durability_level": "Strong",
+        "memtable_max_size_bytes": 1048576,
+        "max_memtables_inactive": 8,
+        "bloom_filter_n_entries": 32768,
+        "block_size_bytes": 4096,
+        "sst_size_bytes": 268435456
+    }"#;
+
+    #[test]
+    fn load_old_config_file() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("simpledb-load_old_config_file-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()));
+        std::fs::create_dir_all(&path).unwrap();
+        std::fs::write(config_file_path(&path), OLD_CONFIG_FILE).unwrap();
+
+        let options = load_options(path.to_str().unwrap().to_string()).unwrap();
+
+        assert_eq!(options.server_password, "my_password");
+        assert_eq!(options.server_port, 1234);
+        assert_eq!(options.memtable_max_size_bytes, 1048576);
+        assert_eq!(options.select_cache_n_entries, 0);
+        //The config file is not overwritten with the default values
+        let config_file = std::fs::read_to_string(config_file_path(&path)).unwrap();
+        assert_eq!(config_file, OLD_CONFIG_FILE);
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+}
@@ -1,16 +1,31 @@
 use shared::connection::Connection;
 use shared::logger::{logger, SimpleDbLayer};
-use shared::SimpleDbError;
+use shared::{SimpleDbError, Type, Value};
 use shared::SimpleDbError::{InvalidRequestBinaryFormat};
+use bytes::Bytes;
+
+pub type PreparedStatementId = u32;
+pub type QueryTimeoutMs = u64; //0 if the query has no timeout
+
+//Flags byte of the statement, execute prepared and batch requests. The query timeout is only sent when
+//REQUEST_FLAG_TIMEOUT is set, so requests without it keep the layout of previous versions
+pub const REQUEST_FLAG_STANDALONE: u8 = 0x01;
+pub const REQUEST_FLAG_TIMEOUT: u8 = 0x02; //Followed by the query timeout in ms as u64
 
 pub enum Request {
-    //Authentication, standalone, statement
-    Statement(Authentication, bool, String), //Request Type ID: 1
+    //Authentication, standalone, query timeout, statement
+    Statement(Authentication, bool, QueryTimeoutMs, String), //Request Type ID: 1
     Close(Authentication), //Request Type ID: 2
     UseDatabase(Authentication, String), //Request Type ID: 3
+    Prepare(Authentication, String), //Request Type ID: 4
+    //Authentication, standalone, query timeout, prepared statement ID, parameter values
+    ExecutePrepared(Authentication, bool, QueryTimeoutMs, PreparedStatementId, Vec<Value>), //Request Type ID: 5
+    //Authentication, standalone, query timeout, statements separated by ;
+    Batch(Authentication, bool, QueryTimeoutMs, String), //Request Type ID: 6
 }
 
 pub struct Authentication {
+    pub user: String, //Empty for the administrator, authenticated with the server password
     pub password: String
 }
 
@@ -20,18 +35,18 @@ impl Request {
 
         match connection.read_u8()? {
             1 => {
-                let is_standalone = connection.read_u8()? != 0x00;
+                let (is_standalone, timeout_ms) = Self::deserialize_flags(connection)?;
                 let statement_length = connection.read_u32()?;
                 let statement_bytes = connection.read_n(statement_length as usize)?;
                 let statement = String::from_utf8(statement_bytes)
                     .map_err(|_| InvalidRequestBinaryFormat)?;
 
                 logger().debug(SimpleDbLayer::Server, &format!(
-                    "Received statement request. ConnectionID: {} Password: {} Statement: {}",
-                    connection.connection_id(), authentication.password, statement
+                    "Received statement request. ConnectionID: {} Timeout: {} ms Statement: {}",
+                    connection.connection_id(), timeout_ms, statement
                 ));
 
-                Ok(Request::Statement(authentication, is_standalone, statement))
+                Ok(Request::Statement(authentication, is_standalone, timeout_ms, statement))
             },
             2 => {
                 logger().debug(SimpleDbLayer::Server, &format!("Received close request. ConnectionID: {}", connection.connection_id()));
@@ -44,32 +59,110 @@ impl Request {
                     .map_err(|_| InvalidRequestBinaryFormat)?;
 
                 logger().debug(SimpleDbLayer::Server, &format!(
-                    "Received use database request. Password: {} Database: {}",
-                    authentication.password, database_name_string
+                    "Received use database request. ConnectionID: {} Database: {}",
+                    connection.connection_id(), database_name_string
                 ));
 
                 Ok(Request::UseDatabase(authentication, database_name_string))
             },
+            4 => {
+                let statement_length = connection.read_u32()?;
+                let statement_bytes = connection.read_n(statement_length as usize)?;
+                let statement = String::from_utf8(statement_bytes)
+                    .map_err(|_| InvalidRequestBinaryFormat)?;
+
+                logger().debug(SimpleDbLayer::Server, &format!(
+                    "Received prepare request. ConnectionID: {} Statement: {}", connection.connection_id(), statement
+                ));
+
+                Ok(Request::Prepare(authentication, statement))
+            },
+            5 => {
+                let (is_standalone, timeout_ms) = Self::deserialize_flags(connection)?;
+                let prepared_statement_id = connection.read_u32()?;
+                let n_values = connection.read_u32()?;
+                //Not preallocated, as n_values is read before the request is authenticated
+                let mut values = Vec::new();
+                for _ in 0..n_values {
+                    values.push(Self::deserialize_value(connection)?);
+                }
+
+                logger().debug(SimpleDbLayer::Server, &format!(
+                    "Received execute prepared statement request. ConnectionID: {} Prepared statement ID: {}",
+                    connection.connection_id(), prepared_statement_id
+                ));
+
+                Ok(Request::ExecutePrepared(authentication, is_standalone, timeout_ms, prepared_statement_id, values))
+            },
+            6 => {
+                let (is_standalone, timeout_ms) = Self::deserialize_flags(connection)?;
+                let statements_length = connection.read_u32()?;
+                let statements_bytes = connection.read_n(statements_length as usize)?;
+                let statements = String::from_utf8(statements_bytes)
+                    .map_err(|_| InvalidRequestBinaryFormat)?;
+
+                logger().debug(SimpleDbLayer::Server, &format!(
+                    "Received batch request. ConnectionID: {} Statements: {}", connection.connection_id(), statements
+                ));
+
+                Ok(Request::Batch(authentication, is_standalone, timeout_ms, statements))
+            },
             _ => Err(InvalidRequestBinaryFormat)
         }
     }
 
     pub fn get_authentication(&self) -> &Authentication {
         match self {
-            Request::Statement(authentication, _, _) => authentication,
+            Request::Statement(authentication, _, _, _) => authentication,
             Request::Close(authentication) => authentication,
-            Request::UseDatabase(authentication, _) => authentication
+            Request::UseDatabase(authentication, _) => authentication,
+            Request::Prepare(authentication, _) => authentication,
+            Request::ExecutePrepared(authentication, _, _, _, _) => authentication,
+            Request::Batch(authentication, _, _, _) => authentication,
+        }
+    }
+
+    //Flags byte, followed by the query timeout if REQUEST_FLAG_TIMEOUT is set
+    fn deserialize_flags(connection: &mut Connection) -> Result<(bool, QueryTimeoutMs), SimpleDbError> {
+        let flags = connection.read_u8()?;
+        let timeout_ms = if flags & REQUEST_FLAG_TIMEOUT != 0 {
+            connection.read_u64()?
+        } else {
+            0
+        };
+
+        Ok((flags & REQUEST_FLAG_STANDALONE != 0, timeout_ms))
+    }
+
+    //Type ID (0 if null), value length, value bytes
+    fn deserialize_value(connection: &mut Connection) -> Result<Value, SimpleDbError> {
+        let type_id = connection.read_u8()?;
+        if type_id == 0 {
+            return Ok(Value::create_null());
         }
+
+        let value_type = Type::deserialize(type_id)
+            .map_err(|_| InvalidRequestBinaryFormat)?;
+        let value_length = connection.read_u32()?;
+        let value_bytes = connection.read_n(value_length as usize)?;
+
+        Value::create(Bytes::from(value_bytes), value_type)
+            .map_err(|_| InvalidRequestBinaryFormat)
     }
 }
 
 impl Authentication {
+    //User length, user, password length, password
     pub fn deserialize_from_connection(connection: &mut Connection) -> Result<Authentication, SimpleDbError> {
+        let user_length = connection.read_u32()?;
+        let user_bytes = connection.read_n(user_length as usize)?;
+        let user_string = String::from_utf8(user_bytes)
+            .map_err(|_| InvalidRequestBinaryFormat)?;
         let password_length = connection.read_u32()?;
         let password_bytes = connection.read_n(password_length as usize)?;
         let password_string = String::from_utf8(password_bytes)
             .map_err(|_| InvalidRequestBinaryFormat)?;
 
-        Ok(Authentication { password: password_string })
+        Ok(Authentication { user: user_string, password: password_string })
     }
 }
\ No newline at end of file
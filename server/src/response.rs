@@ -1,13 +1,20 @@
+use std::collections::VecDeque;
 use std::fmt::Debug;
+use std::time::UNIX_EPOCH;
 use bytes::BufMut;
 use serde::Serialize;
-use db::{Column, IndexType, Limit, PlanStepDesc, RangeScan, Row, Schema, Selection, Sort, SortOrder};
-use shared::{ErrorTypeId, SimpleDbError, Type, Value};
+use db::{Aggregate, Column, DiscardedIndexScan, IndexState, IndexType, IsolationLevel, Limit, NullsOrder, PlanStep, PlanStepDesc, QueryIterator, RangeScan, Row, Schema, Selection, Sort, SortOrder, StepStats, TransactionDesc, TransactionState};
+use shared::{ErrorTypeId, SimpleDbError, TxnId, Type, Value};
+use crate::request::PreparedStatementId;
 
 pub enum Response {
     Statement(StatementResponse),
     Error(ErrorTypeId, String), //Error number, error message
     Ok,
+    Prepared(PreparedStatementId, usize), //Prepared statement ID, Nº parameters
+    //One response per executed statement. If a statement fails, the execution stops and its error is the last response,
+    //so the index of the failed statement is the nº of responses - 1
+    Batch(Vec<Response>),
 }
 
 pub enum StatementResponse {
@@ -15,41 +22,89 @@ pub enum StatementResponse {
     Rows(RowsResponse),
     Databases(Vec<String>),
     Tables(Vec<String>),
-    Indexes(Vec<(String, IndexType)>),
+    Indexes(Vec<(String, IndexType, IndexState)>),
     Describe(Vec<Column>),
-    Explain(PlanStepDesc, Schema)
+    Explain(PlanStepDesc, Schema),
+    TransactionStatus(Option<(TxnId, Vec<TxnId>)>), //Transaction ID, Active transactions when it started
+    Stats(Vec<(String, String)>), //Stat name, Value
+    Transactions(Vec<TransactionDesc>),
+    Vacuumed(usize), //Nº of bytes reclaimed
+    CorruptedParts(Vec<String>), //Error message of each corrupted part
 }
 
+//Rows are read lazily from the query while they are being serialized, so they are never all held in memory.
+//Rows of batch statements are read before executing the next statement, see RowsResponse::materialize()
 pub struct RowsResponse {
     columns_desc: Vec<Column>,
-    rows: Vec<Row>,
+    rows: QueryIterator<PlanStep>,
+    materialized_rows: Option<VecDeque<Row>>,
 }
 
+//Chunk of rows headers. A chunk with 0 rows marks the end of the rows
+const END_OF_ROWS_MARKER: u32 = 0;
+//If the query fails while the rows are being sent, this header is followed by the error
+const ROWS_ERROR_MARKER: u32 = u32::MAX;
+
 impl Response {
     pub fn from_simpledb_error(error: SimpleDbError) -> Response {
         Response::Error(error.serialize(), format!("{:?}", error))
     }
 
-    pub fn serialize(&self) -> Vec<u8> {
+    pub fn serialize(&mut self, rows_per_chunk: usize) -> Vec<u8> {
         let mut serialized: Vec<u8> = Vec::new();
+        let _ = self.serialize_in_chunks(rows_per_chunk, &mut |chunk| {
+            serialized.extend(chunk);
+            Ok(())
+        });
+        serialized
+    }
 
+    //The response is passed to write_chunk in pieces as it gets serialized.
+    //Query rows are sent in chunks of rows_per_chunk rows
+    pub fn serialize_in_chunks(
+        &mut self,
+        rows_per_chunk: usize,
+        write_chunk: &mut dyn FnMut(Vec<u8>) -> Result<(), SimpleDbError>
+    ) -> Result<(), SimpleDbError> {
+        let mut serialized: Vec<u8> = Vec::new();
         serialized.put_u8(self.message_type_id());
-        serialized.extend(self.serialize_message_content());
 
-        serialized
+        match self {
+            Response::Statement(result) => {
+                write_chunk(serialized)?;
+                result.serialize_in_chunks(rows_per_chunk, write_chunk)
+            },
+            Response::Batch(responses) => {
+                serialized.put_u32_le(responses.len() as u32);
+                write_chunk(serialized)?;
+                for response in responses {
+                    response.serialize_in_chunks(rows_per_chunk, write_chunk)?;
+                }
+                Ok(())
+            },
+            _ => {
+                serialized.extend(self.serialize_message_content());
+                write_chunk(serialized)
+            }
+        }
     }
 
     fn serialize_message_content(&self) -> Vec<u8> {
         let mut serialized = Vec::new();
 
         match self {
-            Response::Statement(result) => serialized.extend(result.serialize()),
+            Response::Statement(_) |
+            Response::Batch(_) => panic!("Illegal code path"),
             Response::Error(error_type_id, error_message) => {
                 serialized.put_u8(*error_type_id as u8);
                 serialized.put_u32_le(error_message.len() as u32);
                 serialized.extend(error_message.bytes());
             },
             Response::Ok => {},
+            Response::Prepared(prepared_statement_id, n_parameters) => {
+                serialized.put_u32_le(*prepared_statement_id);
+                serialized.put_u32_le(*n_parameters as u32);
+            },
         };
 
         serialized
@@ -59,7 +114,9 @@ impl Response {
         match self {
             Response::Statement(_) => 1,
             Response::Error(_, _) => 2,
-            Response::Ok => 3
+            Response::Ok => 3,
+            Response::Prepared(_, _) => 4,
+            Response::Batch(_) => 5,
         }
     }
 }
@@ -67,9 +124,22 @@ impl Response {
 impl RowsResponse {
     pub fn create(
         columns_desc: Vec<Column>,
-        rows: Vec<Row>
+        rows: QueryIterator<PlanStep>
     ) -> RowsResponse {
-        RowsResponse { columns_desc, rows }
+        RowsResponse { columns_desc, rows, materialized_rows: None }
+    }
+
+    //Reads every row of the query, so next statements that modify them won't change the returned rows
+    pub fn materialize(&mut self) -> Result<(), SimpleDbError> {
+        self.materialized_rows = Some(VecDeque::from(self.rows.all()?));
+        Ok(())
+    }
+
+    pub fn next_rows(&mut self, n: usize) -> Result<Vec<Row>, SimpleDbError> {
+        match &mut self.materialized_rows {
+            Some(materialized_rows) => Ok(materialized_rows.drain(..n.min(materialized_rows.len())).collect()),
+            None => self.rows.next_n(n),
+        }
     }
 
     pub fn get_primary_column_name(&self) -> &str {
@@ -94,53 +164,90 @@ impl RowsResponse {
 }
 
 impl StatementResponse {
-    pub fn serialize(&self) -> Vec<u8> {
+    pub fn serialize_in_chunks(
+        &mut self,
+        rows_per_chunk: usize,
+        write_chunk: &mut dyn FnMut(Vec<u8>) -> Result<(), SimpleDbError>
+    ) -> Result<(), SimpleDbError> {
         let mut serialized = Vec::new();
 
         serialized.put_u8(self.statement_response_type_id());
 
         match self {
+            StatementResponse::Rows(data) => {
+                serialized.extend(Self::serialize_columns_desc(&data.columns_desc));
+                write_chunk(serialized)?;
+                return Self::serialize_rows_in_chunks(data, rows_per_chunk, write_chunk);
+            },
             StatementResponse::Explain(explanation, schema) => serialized.extend(Self::serialize_explanation(explanation, schema.clone())),
             StatementResponse::Describe(columns_desc) => serialized.extend(Self::serialize_columns_desc(columns_desc)),
             StatementResponse::Databases(databases) => serialized.extend(Self::serialize_string_vec(databases)),
             StatementResponse::Indexes(indexes) => serialized.extend(Self::serialize_show_indexes(indexes)),
-            StatementResponse::Rows(data) => serialized.extend(Self::serialize_query_data(data)),
             StatementResponse::Tables(tables) => serialized.extend(Self::serialize_string_vec(tables)),
             StatementResponse::Ok(n_affected_rows) => serialized.put_u64_le(*n_affected_rows as u64),
+            StatementResponse::TransactionStatus(status) => serialized.extend(Self::serialize_transaction_status(status)),
+            StatementResponse::Stats(stats) => serialized.extend(Self::serialize_stats(stats)),
+            StatementResponse::Transactions(transactions) => serialized.extend(Self::serialize_transactions(transactions)),
+            StatementResponse::Vacuumed(bytes_reclaimed) => serialized.put_u64_le(*bytes_reclaimed as u64),
+            StatementResponse::CorruptedParts(corrupted_parts) => serialized.extend(Self::serialize_string_vec(corrupted_parts)),
         };
 
-        serialized
+        write_chunk(serialized)
     }
 
     fn serialize_show_indexes(
-        indexes: &Vec<(String, IndexType)>
+        indexes: &Vec<(String, IndexType, IndexState)>
     ) -> Vec<u8> {
         let mut serialized: Vec<u8> = Vec::new();
 
         serialized.put_u32_le(indexes.len() as u32);
-        for (indexed_column_name, index_type) in indexes {
+        for (indexed_column_name, index_type, index_state) in indexes {
             serialized.put_u32_le(indexed_column_name.len() as u32);
             serialized.extend(indexed_column_name.as_bytes());
             serialized.put_u8(index_type.serialize());
+            serialized.put_u8(index_state.serialize());
         }
 
         serialized
     }
 
-    fn serialize_query_data(
-        query_data_response: &RowsResponse,
-    ) -> Vec<u8> {
-        let mut serialized = Vec::new();
+    //Every chunk starts with its nº of rows. The rows end with a chunk of 0 rows
+    fn serialize_rows_in_chunks(
+        rows: &mut RowsResponse,
+        rows_per_chunk: usize,
+        write_chunk: &mut dyn FnMut(Vec<u8>) -> Result<(), SimpleDbError>
+    ) -> Result<(), SimpleDbError> {
+        let rows_per_chunk = rows_per_chunk.max(1);
+
+        loop {
+            let chunk = match rows.next_rows(rows_per_chunk) {
+                Ok(chunk) => chunk,
+                Err(error) => {
+                    let mut serialized = Vec::new();
+                    serialized.put_u32_le(ROWS_ERROR_MARKER);
+                    serialized.put_u8(error.serialize() as u8);
+                    let error_message = format!("{:?}", error);
+                    serialized.put_u32_le(error_message.len() as u32);
+                    serialized.extend(error_message.bytes());
+                    return write_chunk(serialized);
+                }
+            };
+            let n_rows = chunk.len();
 
-        //Columns desc
-        serialized.extend(Self::serialize_columns_desc(&query_data_response.columns_desc));
-        //Rows
-        serialized.put_u32_le(query_data_response.rows.len() as u32);
-        for row in &query_data_response.rows {
-            serialized.extend(row.clone().serialize());
+            if n_rows > 0 {
+                let mut serialized = Vec::new();
+                serialized.put_u32_le(n_rows as u32);
+                for row in chunk {
+                    serialized.extend(row.serialize());
+                }
+                write_chunk(serialized)?;
+            }
+            if n_rows < rows_per_chunk {
+                let mut serialized = Vec::new();
+                serialized.put_u32_le(END_OF_ROWS_MARKER);
+                return write_chunk(serialized);
+            }
         }
-
-        serialized
     }
 
     fn serialize_columns_desc(
@@ -149,8 +256,77 @@ impl StatementResponse {
         let mut serialized = Vec::new();
 
         serialized.put_u32_le(columns_desc.len() as u32);
-        for columns_desc in columns_desc {
-            serialized.extend(columns_desc.serialize());
+        for column_desc in columns_desc {
+            serialized.extend(Self::serialize_column_desc(column_desc));
+        }
+
+        serialized
+    }
+
+    //The index keyspace ID is only present if the column is secondary indexed
+    fn serialize_column_desc(column_desc: &Column) -> Vec<u8> {
+        let mut serialized = Vec::new();
+        serialized.put_u16_le(column_desc.column_id);
+        serialized.put_u8(column_desc.column_type.serialize());
+        serialized.put_u8(column_desc.is_primary as u8);
+        serialized.put_u8(column_desc.is_secondary_indexed() as u8);
+        if let Some(index_keyspace_id) = column_desc.secondary_index_keyspace_id {
+            serialized.put_u64_le(index_keyspace_id as u64);
+        }
+        serialized.put_u32_le(column_desc.column_name.len() as u32);
+        serialized.extend(column_desc.column_name.as_bytes());
+        serialized
+    }
+
+    fn serialize_transaction_status(status: &Option<(TxnId, Vec<TxnId>)>) -> Vec<u8> {
+        let mut serialized = Vec::new();
+        match status {
+            Some((txn_id, active_transactions)) => {
+                serialized.put_u8(1);
+                serialized.put_u64_le(*txn_id as u64);
+                serialized.put_u32_le(active_transactions.len() as u32);
+                for active_txn_id in active_transactions {
+                    serialized.put_u64_le(*active_txn_id as u64);
+                }
+            },
+            None => serialized.put_u8(0),
+        }
+
+        serialized
+    }
+
+    //Start time is serialized as milliseconds since UNIX epoch, 0 if the transaction is no longer active
+    fn serialize_transactions(transactions: &Vec<TransactionDesc>) -> Vec<u8> {
+        let mut serialized = Vec::new();
+        serialized.put_u32_le(transactions.len() as u32);
+        for transaction in transactions {
+            serialized.put_u64_le(transaction.txn_id as u64);
+            serialized.put_u8(match transaction.isolation_level {
+                IsolationLevel::ReadUncommited => 1,
+                IsolationLevel::SnapshotIsolation => 2,
+            });
+            serialized.put_u8(match transaction.state {
+                TransactionState::Active => 1,
+                TransactionState::Aborted => 2,
+                TransactionState::Finished => 3,
+            });
+            serialized.put_u64_le(transaction.start_time
+                .and_then(|start_time| start_time.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |start_time| start_time.as_millis() as u64));
+            serialized.put_u64_le(transaction.n_writes as u64);
+        }
+
+        serialized
+    }
+
+    fn serialize_stats(stats: &Vec<(String, String)>) -> Vec<u8> {
+        let mut serialized = Vec::new();
+        serialized.put_u32_le(stats.len() as u32);
+        for (stat_name, stat_value) in stats {
+            serialized.put_u32_le(stat_name.len() as u32);
+            serialized.extend(stat_name.bytes());
+            serialized.put_u32_le(stat_value.len() as u32);
+            serialized.extend(stat_value.bytes());
         }
 
         serialized
@@ -175,7 +351,12 @@ impl StatementResponse {
             StatementResponse::Tables(_) => 4,
             StatementResponse::Describe(_) => 5,
             StatementResponse::Indexes(_) => 6,
-            StatementResponse::Explain(_, _) => 7
+            StatementResponse::Explain(_, _) => 7,
+            StatementResponse::TransactionStatus(_) => 8,
+            StatementResponse::Stats(_) => 9,
+            StatementResponse::Transactions(_) => 10,
+            StatementResponse::Vacuumed(_) => 11,
+            StatementResponse::CorruptedParts(_) => 12,
         }
     }
 
@@ -187,9 +368,18 @@ impl StatementResponse {
 
         let mut serialized: Vec<u8> = Vec::new();
         serialized.put_u32_le(lines.len() as u32);
-        for line in lines {
+        for (line, stats) in lines {
             serialized.put_u32_le(line.len() as u32);
             serialized.extend(line.as_bytes());
+            //Stats are only present in EXPLAIN ANALYZE
+            match stats {
+                Some(stats) => {
+                    serialized.put_u8(1);
+                    serialized.put_u64_le(stats.n_rows as u64);
+                    serialized.put_u64_le(stats.elapsed.as_nanos() as u64);
+                },
+                None => serialized.put_u8(0),
+            }
         }
 
         serialized
@@ -198,50 +388,55 @@ impl StatementResponse {
     fn plan_to_explain_lines(
         explanation: &PlanStepDesc,
         schema: Schema
-    ) -> Vec<String> {
+    ) -> Vec<(String, Option<StepStats>)> {
         //Since the plan steps desc follows a tre structure, it wil be very complicated to create,
         //a binary format for it. Instead, we will convert it to a string which will be displayed by the cli.
         let mut pending = Vec::new();
         let mut strings = Vec::new();
-        pending.push((0, explanation));
+        pending.push((0, explanation, None));
 
-        while let Some((depth, current_step)) = pending.pop() {
+        while let Some((depth, current_step, stats)) = pending.pop() {
             match current_step {
-                PlanStepDesc::Limit(limit, source) => {
-                    pending.push((depth, source.clone()));
-                    strings.push(Self::limit_plan_desc_to_string(depth, limit));
+                PlanStepDesc::Limit(limit, offset, source) => {
+                    pending.push((depth, source, None));
+                    strings.push((Self::limit_plan_desc_to_string(depth, limit, *offset), stats));
                 },
                 PlanStepDesc::FullSort(sort, source) => {
-                    pending.push((depth, source.clone()));
-                    strings.push(Self::full_sort_plan_desc_to_string(depth, sort));
+                    pending.push((depth, source, None));
+                    strings.push((Self::full_sort_plan_desc_to_string(depth, sort), stats));
                 }
                 PlanStepDesc::TopNSort(sort, n, source) => {
-                    pending.push((depth, source.clone()));
-                    strings.push(Self::top_n_sort_plan_desc_to_string(depth, sort, *n));
+                    pending.push((depth, source, None));
+                    strings.push((Self::top_n_sort_plan_desc_to_string(depth, sort, *n), stats));
                 }
                 PlanStepDesc::Filter(source) => {
-                    pending.push((depth, source.clone()));
-                    strings.push(Self::filter_plan_desc_to_string(depth));
+                    pending.push((depth, source, None));
+                    strings.push((Self::filter_plan_desc_to_string(depth), stats));
                 },
                 PlanStepDesc::ProjectionSelectionStep(selection, source) => {
-                    pending.push((depth, source.clone()));
-                    strings.push(Self::projection_to_string(depth, selection));
+                    pending.push((depth, source, None));
+                    strings.push((Self::projection_to_string(depth, selection), stats));
                 },
                 PlanStepDesc::MergeIntersection(left, right) => {
-                    pending.push((depth + 1, left.clone()));
-                    pending.push((depth + 1, right.clone()));
-                    strings.push(Self::intersection_plan_desc_to_string(depth));
+                    pending.push((depth + 1, left, None));
+                    pending.push((depth + 1, right, None));
+                    strings.push((Self::intersection_plan_desc_to_string(depth), stats));
                 }
                 PlanStepDesc::MergeUnion(left, right) => {
-                    pending.push((depth + 1, left.clone()));
-                    pending.push((depth + 1, right.clone()));
-                    strings.push(Self::union_plan_desc_to_string(depth));
+                    pending.push((depth + 1, left, None));
+                    pending.push((depth + 1, right, None));
+                    strings.push((Self::union_plan_desc_to_string(depth), stats));
+                }
+                PlanStepDesc::FullScan(discarded_index_scan, is_reverse) => {
+                    strings.push((Self::full_scan_to_string(depth, discarded_index_scan, *is_reverse), stats));
                 }
-                PlanStepDesc::FullScan => {
-                    strings.push(Self::full_scan_to_string(depth));
+                PlanStepDesc::ParallelFullScan(n_partitions, n_threads) => {
+                    let mut string = Self::explain_plan_new_line(depth);
+                    string.push_str(&format!("ParallelFullScan ({} partitions, {} threads)", n_partitions, n_threads));
+                    strings.push((string, stats));
                 }
                 PlanStepDesc::RangeScan(range) => {
-                    strings.push(Self::range_scan_plan_desc_to_string(depth, range));
+                    strings.push((Self::range_scan_plan_desc_to_string(depth, range), stats));
                 }
                 PlanStepDesc::PrimaryExactScan(primary_column_value_bytes) => {
                     let primary_column = schema.get_primary_column();
@@ -249,20 +444,52 @@ impl StatementResponse {
                     let primary_column_value = Value::create(primary_column_value_bytes.clone(), primary_column_type)
                         .unwrap();
 
-                    strings.push(Self::exact_primary_scan_plan_desc_to_string(depth, primary_column_value));
+                    strings.push((Self::exact_primary_scan_plan_desc_to_string(depth, primary_column_value), stats));
                 }
                 PlanStepDesc::SecondaryExactExactScan(secondary_column_name, secondary_column_value) => {
                     let secondary_column = schema.get_column_or_err(&secondary_column_name).unwrap();
                     let secondary_column_type = secondary_column.column_type;
                     let secondary_column_value = Value::create(secondary_column_value.clone(), secondary_column_type)
                         .unwrap();
-                    strings.push(Self::exact_secondary_scan_plan_desc_to_string(depth, secondary_column_name, secondary_column_value));
+                    strings.push((Self::exact_secondary_scan_plan_desc_to_string(depth, secondary_column_name, secondary_column_value), stats));
                 }
-                PlanStepDesc::Revserse(source) => {
-                    pending.push((depth, source));
+                PlanStepDesc::IndexOnlyScan(index_scan) => {
+                    pending.push((depth, index_scan, None));
+                    let mut string = Self::explain_plan_new_line(depth);
+                    string.push_str("IndexOnlyScan");
+                    strings.push((string, stats));
+                }
+                PlanStepDesc::CompositeScan(column_names, values) => {
+                    strings.push((Self::composite_scan_plan_desc_to_string(depth, column_names, values), stats));
+                }
+                PlanStepDesc::Aggregate(aggregates, source) => {
+                    pending.push((depth, source, None));
+                    strings.push((Self::aggregate_plan_desc_to_string(depth, aggregates), stats));
+                }
+                PlanStepDesc::GroupBy(group_by, aggregates, is_streaming, source) => {
+                    pending.push((depth, source, None));
+                    strings.push((Self::group_by_plan_desc_to_string(depth, group_by, aggregates, *is_streaming), stats));
+                }
+                PlanStepDesc::Distinct(is_source_sorted, source) => {
+                    pending.push((depth, source, None));
+                    let mut string = Self::explain_plan_new_line(depth);
+                    string.push_str(if *is_source_sorted { "Distinct (Sorted)" } else { "Distinct" });
+                    strings.push((string, stats));
+                }
+                PlanStepDesc::Empty => {
+                    let mut string = Self::explain_plan_new_line(depth);
+                    string.push_str("Empty");
+                    strings.push((string, stats));
+                }
+                PlanStepDesc::Analyzed(step_stats, source) => {
+                    //The stats are attached to the line of the analyzed step
+                    pending.push((depth, source, Some(*step_stats)));
+                }
+                PlanStepDesc::Reverse(source) => {
+                    pending.push((depth, source, None));
                     let mut string = Self::explain_plan_new_line(depth);
                     string.push_str("Reverse");
-                    strings.push(string);
+                    strings.push((string, stats));
                 }
             };
         }
@@ -270,15 +497,47 @@ impl StatementResponse {
         strings
     }
 
+    fn aggregate_plan_desc_to_string(depth: usize, aggregates: &Vec<Aggregate>) -> String {
+        let mut string = Self::explain_plan_new_line(depth);
+        string.push_str("Aggregate (");
+        string.push_str(&aggregates.iter()
+            .map(|aggregate| aggregate.to_string())
+            .collect::<Vec<String>>()
+            .join(", "));
+        string.push_str(")");
+        string
+    }
+
+    fn group_by_plan_desc_to_string(depth: usize, group_by: &Vec<String>, aggregates: &Vec<Aggregate>, is_streaming: bool) -> String {
+        let mut string = Self::explain_plan_new_line(depth);
+        if is_streaming {
+            string.push_str("StreamingGroupBy (");
+        } else {
+            string.push_str("GroupBy (");
+        }
+        string.push_str(&group_by.join(", "));
+        string.push_str(") Aggregate (");
+        string.push_str(&aggregates.iter()
+            .map(|aggregate| aggregate.to_string())
+            .collect::<Vec<String>>()
+            .join(", "));
+        string.push_str(")");
+        string
+    }
+
     fn limit_plan_desc_to_string(
         depth: usize,
-        limit: &Limit
+        limit: &Limit,
+        offset: usize,
     ) -> String {
         let mut string = Self::explain_plan_new_line(depth);
         match limit {
             Limit::None => {
                 string.push_str("Limit (None)");
             }
+            Limit::Some(limit_n) if offset > 0 => {
+                string.push_str(&format!("Limit ({} OFFSET {})", limit_n, offset));
+            }
             Limit::Some(limit_n) => {
                 string.push_str(&format!("Limit ({})", limit_n));
             }
@@ -289,12 +548,7 @@ impl StatementResponse {
     fn top_n_sort_plan_desc_to_string(depth: usize, sort: &Sort, n: usize) -> String {
         let mut string = Self::explain_plan_new_line(depth);
         string.push_str("Top N Sort (");
-        string.push_str(&sort.column_name);
-        string.push_str(" ");
-        match sort.order {
-            SortOrder::Asc => string.push_str("ASC"),
-            SortOrder::Desc => string.push_str("DESC"),
-        }
+        string.push_str(&Self::sort_columns_to_string(sort));
         string.push_str(" LIMIT ");
         string.push_str(n.to_string().as_str());
         string.push_str(")");
@@ -305,20 +559,40 @@ impl StatementResponse {
     fn full_sort_plan_desc_to_string(depth: usize, sort: &Sort) -> String {
         let mut string = Self::explain_plan_new_line(depth);
         string.push_str("Full Sort (");
-        string.push_str(&sort.column_name);
-        string.push_str(" ");
-        match sort.order {
-            SortOrder::Asc => string.push_str("ASC"),
-            SortOrder::Desc => string.push_str("DESC"),
-        }
+        string.push_str(&Self::sort_columns_to_string(sort));
         string.push_str(")");
 
         string
     }
 
-    fn full_scan_to_string(depth: usize) -> String {
+    fn sort_columns_to_string(sort: &Sort) -> String {
+        sort.columns.iter()
+            .map(|(column_name, order, nulls_order)| {
+                let order = match order {
+                    SortOrder::Asc => "ASC",
+                    SortOrder::Desc => "DESC",
+                };
+                let nulls_order = match nulls_order {
+                    NullsOrder::First => "NULLS FIRST",
+                    NullsOrder::Last => "NULLS LAST",
+                };
+                format!("{} {} {}", column_name, order, nulls_order)
+            })
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    fn full_scan_to_string(depth: usize, discarded_index_scan: &Option<DiscardedIndexScan>, is_reverse: bool) -> String {
         let mut string = Self::explain_plan_new_line(depth);
-        string.push_str("Full Scan");
+        string.push_str(if is_reverse { "Reverse Full Scan" } else { "Full Scan" });
+        if let Some(discarded_index_scan) = discarded_index_scan {
+            string.push_str(&format!(
+                " (Index of {} not used: estimated to read {:.0}% of {} rows)",
+                discarded_index_scan.column_name,
+                discarded_index_scan.estimated_selectivity * 100.0,
+                discarded_index_scan.estimated_n_rows
+            ));
+        }
         string
     }
 
@@ -396,6 +670,24 @@ impl StatementResponse {
         string
     }
 
+    fn composite_scan_plan_desc_to_string(
+        depth: usize,
+        column_names: &Vec<String>,
+        values: &Vec<Value>
+    ) -> String {
+        let values: Vec<String> = values.iter()
+            .map(|value| value.to_string())
+            .collect();
+
+        let mut string = Self::explain_plan_new_line(depth);
+        string.push_str("Composite index (");
+        string.push_str(&column_names.join(", "));
+        string.push_str(") (");
+        string.push_str(&values.join(", "));
+        string.push_str(")");
+        string
+    }
+
     fn projection_to_string(depth: usize, selection: &Selection) -> String {
         let mut string = Self::explain_plan_new_line(depth);
         string.push_str("Projection (");
@@ -412,6 +704,22 @@ impl StatementResponse {
                 string.push_str(&columns.join(", "));
                 string.push_str(")");
             }
+            Selection::Aggregate(columns, aggregates) => {
+                let mut selected: Vec<String> = columns.clone();
+                selected.extend(aggregates.iter()
+                    .map(|aggregate| aggregate.to_string()));
+
+                string.push_str(&selected.join(", "));
+                string.push_str(")");
+            }
+            Selection::Expressions(expressions) => {
+                let selected: Vec<String> = expressions.iter()
+                    .map(|(selected_name, _)| selected_name.clone())
+                    .collect();
+
+                string.push_str(&selected.join(", "));
+                string.push(')');
+            }
         }
 
         string
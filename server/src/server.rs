@@ -1,15 +1,24 @@
-use crate::request::Request;
+use crate::connection_limiter::{Admission, ConnectionLimiter};
+use crate::request::{PreparedStatementId, QueryTimeoutMs, Request};
 use crate::response::{Response, RowsResponse, StatementResponse};
 use crossbeam_skiplist::SkipMap;
 use db::simple_db::StatementResult;
-use db::{Context, SimpleDb, Statement};
+use db::{CancellationToken, Context, IsolationLevel, SimpleDb, Statement, StoredPassword, TableStats};
 use shared::connection::Connection;
 use shared::logger::{logger, Logger, SimpleDbLayer};
-use shared::SimpleDbError::InvalidPassword;
-use shared::{SimpleDbError, SimpleDbOptions};
-use std::net::TcpListener;
+use shared::SimpleDbError::{InvalidPassword, InvalidTlsConfiguration, TooManyConnections};
+use ring::digest;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::{ServerConfig, ServerConnection, StreamOwned};
+use shared::{SimpleDbError, SimpleDbOptions, Value};
+use std::io::ErrorKind;
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering::Relaxed;
 use std::sync::Arc;
 use std::thread;
+use std::time::{Duration, Instant};
 
 pub type ConnectionId = usize;
 
@@ -18,12 +27,57 @@ pub struct Server {
     options: Arc<SimpleDbOptions>,
 
     context_by_connection_id: SkipMap<ConnectionId, Context>,
+
+    //Statements are parsed once and bound with different values on every execution
+    prepared_statements: SkipMap<(ConnectionId, PreparedStatementId), (String, Statement)>,
+    next_prepared_statement_id: AtomicU32,
+
+    tls_config: Option<Arc<ServerConfig>>, //None if TLS is disabled
+
+    connection_limiter: ConnectionLimiter,
+
+    password: StoredPassword, //Password of the administrator
+    //User name (empty for the administrator) and SHA-256 digest of the credentials that authenticated the connection
+    authenticated_connections: SkipMap<ConnectionId, (String, Vec<u8>)>,
+}
+
+//Slot taken by a connection being served. If the thread panics, the slot is released when the guard gets dropped,
+//and the queued connection that takes it is served by a new thread
+struct ConnectionSlot {
+    server: Arc<Server>,
+    released: bool,
+}
+
+impl ConnectionSlot {
+    //Returns the queued connection that takes the slot, which will be served by the calling thread
+    fn release(mut self) -> Option<TcpStream> {
+        self.released = true;
+        self.server.connection_limiter.release()
+    }
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        if !self.released {
+            if let Some(queued_socket) = self.server.connection_limiter.release() {
+                let server = self.server.clone();
+                thread::spawn(move || server.serve_connections(queued_socket));
+            }
+        }
+    }
 }
 
 impl Server {
     pub fn create(
         options: Arc<SimpleDbOptions>
     ) -> Result<Server, SimpleDbError> {
+        //Only the hash is kept. The configured password, which might be plaintext, is removed from the options
+        let password = StoredPassword::create(&options.server_password);
+        let is_password_hashed = db::is_hashed(&options.server_password);
+        let options = shared::start_simpledb_options_builder_from(&options)
+            .server_password("")
+            .build_arc();
+
         Logger::init(options.clone());
 
         logger().info(
@@ -31,9 +85,23 @@ impl Server {
             &format!("Initializing server at address 127.0.0:{}", options.server_port)
         );
 
+        let tls_config = Self::create_tls_config(&options)?;
+        if !is_password_hashed {
+            logger().info(SimpleDbLayer::Server, "The server password is stored in plaintext. Replace it with the output of: server hash-password <password>");
+        }
         let simple_db = db::simple_db::create(options.clone())?;
         Ok(Server {
+            tls_config,
             context_by_connection_id: SkipMap::new(),
+            prepared_statements: SkipMap::new(),
+            next_prepared_statement_id: AtomicU32::new(0),
+            authenticated_connections: SkipMap::new(),
+            password,
+            connection_limiter: ConnectionLimiter::create(
+                options.server_max_connections,
+                options.server_max_queued_connections,
+                Duration::from_millis(options.server_queued_connection_timeout_ms)
+            ),
             simple_db: Arc::new(simple_db),
             options
         })
@@ -43,16 +111,148 @@ impl Server {
         let listener = TcpListener::bind(self.server_address_to_str())
             .unwrap();
 
+        self.accept_connections(listener)
+    }
+
+    fn accept_connections(self: Arc<Self>, listener: TcpListener) -> ! {
+        if !self.connection_limiter.queued_connection_timeout().is_zero() {
+            let server = self.clone();
+            thread::spawn(move || server.reject_expired_queued_connections());
+        }
+
         loop {
             let (socket, _) = listener.accept().unwrap();
-            let server = self.clone();
-            logger().debug(SimpleDbLayer::Server, &format!("Accepted new connection {}", socket.peer_addr().unwrap()));
-            let connection = Connection::create(socket);
+            let peer_address = socket.peer_addr().unwrap();
 
-            thread::spawn(|| {
-                Self::handle_connection(connection, server);
-            });
+            match self.connection_limiter.admit(socket) {
+                Admission::Accepted(socket) => {
+                    logger().debug(SimpleDbLayer::Server, &format!("Accepted new connection {}. Active connections: {}",
+                        peer_address, self.get_n_active_connections()));
+                    let server = self.clone();
+                    thread::spawn(move || server.serve_connections(socket));
+                },
+                Admission::Queued => {
+                    logger().debug(SimpleDbLayer::Server, &format!("Queued new connection {}. Queued connections: {}",
+                        peer_address, self.get_n_queued_connections()));
+                },
+                Admission::Rejected(socket) => {
+                    logger().info(SimpleDbLayer::Server, &format!("Rejected connection {}. Too many connections", peer_address));
+                    self.reject_connection(socket);
+                },
+            }
+        }
+    }
+
+    //Queued connections are only taken when a slot is released, so the expired ones are periodically rejected
+    fn reject_expired_queued_connections(self: Arc<Self>) -> ! {
+        let check_interval = self.connection_limiter.queued_connection_timeout()
+            .min(Duration::from_secs(1));
+
+        loop {
+            thread::sleep(check_interval);
+
+            for socket in self.connection_limiter.take_expired_queued_connections() {
+                logger().info(SimpleDbLayer::Server, &format!("Rejected queued connection {:?}. Timeout waiting for a free slot",
+                    socket.peer_addr()));
+                self.reject_connection(socket);
+            }
+        }
+    }
+
+    //Once the connection is closed, the thread will serve the next queued connection if there is any
+    fn serve_connections(self: Arc<Self>, socket: TcpStream) {
+        let mut next_socket = Some(socket);
+
+        while let Some(socket) = next_socket {
+            let connection_slot = ConnectionSlot { server: self.clone(), released: false };
+
+            match self.accept_connection(socket) {
+                Ok(connection) => Self::handle_connection(connection, self.clone()),
+                Err(error) => logger().info(SimpleDbLayer::Server, &format!("Cannot accept connection: {:?}", error)),
+            }
+
+            next_socket = connection_slot.release();
+        }
+    }
+
+    //The error is sent before reading any request. If TLS is enabled, the socket is just closed,
+    //as establishing the TLS session would block the accept thread
+    fn reject_connection(&self, socket: TcpStream) {
+        if self.tls_config.is_none() {
+            let max_connections = self.connection_limiter.max_connections();
+            let mut response = Response::from_simpledb_error(TooManyConnections(max_connections));
+            let _ = Connection::create(socket).write(response.serialize(self.options.server_rows_per_chunk));
+        }
+    }
+
+    //None if the connection belongs to the administrator
+    fn get_connection_user(&self, connection_id: ConnectionId) -> Option<String> {
+        self.authenticated_connections.get(&connection_id)
+            .map(|entry| entry.value().0.clone())
+            .filter(|user| !user.is_empty())
+    }
+
+    pub fn get_n_active_connections(&self) -> usize {
+        self.connection_limiter.get_n_active_connections()
+    }
+
+    pub fn get_n_queued_connections(&self) -> usize {
+        self.connection_limiter.get_n_queued_connections()
+    }
+
+    //If TLS is enabled, the TLS session is established before reading any request, so passwords are never sent in plain text.
+    //Clients that don't complete the handshake before server_tls_handshake_timeout_ms get disconnected, so they don't hold the slot
+    fn accept_connection(&self, socket: TcpStream) -> Result<Connection, SimpleDbError> {
+        let tls_config = match &self.tls_config {
+            Some(tls_config) => tls_config.clone(),
+            None => return Ok(Connection::create(socket)),
+        };
+
+        let peer_address = socket.peer_addr().map_err(|e| SimpleDbError::NetworkError(e))?;
+        let tls_connection = ServerConnection::new(tls_config)
+            .map_err(|e| InvalidTlsConfiguration(e.to_string()))?;
+        let mut tls_stream = StreamOwned::new(tls_connection, socket);
+        let handshake_timeout = Duration::from_millis(self.options.server_tls_handshake_timeout_ms);
+        let handshake_deadline = Instant::now() + handshake_timeout;
+        while tls_stream.conn.is_handshaking() {
+            //Each read or write can only wait the remaining time, so clients sending the handshake slowly also time out
+            if !handshake_timeout.is_zero() {
+                let remaining = handshake_deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    return Err(SimpleDbError::NetworkError(std::io::Error::new(ErrorKind::TimedOut, "TLS handshake timeout")));
+                }
+                tls_stream.sock.set_read_timeout(Some(remaining)).map_err(SimpleDbError::NetworkError)?;
+                tls_stream.sock.set_write_timeout(Some(remaining)).map_err(SimpleDbError::NetworkError)?;
+            }
+
+            tls_stream.conn.complete_io(&mut tls_stream.sock)
+                .map_err(|e| SimpleDbError::NetworkError(e))?;
         }
+        tls_stream.sock.set_read_timeout(None).map_err(SimpleDbError::NetworkError)?;
+        tls_stream.sock.set_write_timeout(None).map_err(SimpleDbError::NetworkError)?;
+
+        Ok(Connection::create_with_stream(Box::new(tls_stream), peer_address))
+    }
+
+    fn create_tls_config(options: &Arc<SimpleDbOptions>) -> Result<Option<Arc<ServerConfig>>, SimpleDbError> {
+        if options.server_tls_cert_path.is_empty() {
+            return Ok(None);
+        }
+
+        let cert_chain = CertificateDer::pem_file_iter(&options.server_tls_cert_path)
+            .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| InvalidTlsConfiguration(format!("Cannot read certificate {}: {:?}", options.server_tls_cert_path, e)))?;
+        let private_key = PrivateKeyDer::from_pem_file(&options.server_tls_key_path)
+            .map_err(|e| InvalidTlsConfiguration(format!("Cannot read private key {}: {:?}", options.server_tls_key_path, e)))?;
+
+        let tls_config = ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|e| InvalidTlsConfiguration(e.to_string()))?
+            .with_no_client_auth()
+            .with_single_cert(cert_chain, private_key)
+            .map_err(|e| InvalidTlsConfiguration(e.to_string()))?;
+
+        Ok(Some(Arc::new(tls_config)))
     }
 
     fn handle_connection(mut connection: Connection, server: Arc<Server>) {
@@ -60,19 +260,19 @@ impl Server {
         server.context_by_connection_id.insert(connection_id, Context::empty());
 
         loop {
-            let response = Self::handle_request(&mut connection, server.clone())
+            let mut response = Self::handle_request(&mut connection, server.clone())
                 .unwrap_or_else(|error| Response::from_simpledb_error(error));
 
-            match connection.write(response.serialize()) {
-                //The connection was closed
-                Ok(n) => if n == 0 {
-                    server.context_by_connection_id.remove(&connection_id);
-                    break;
-                }
-                Err(_) => {
-                    break;
-                }
-            };
+            let write_result = response.serialize_in_chunks(server.options.server_rows_per_chunk, &mut |chunk| {
+                connection.write(chunk).map(|_| ())
+            });
+
+            //The connection was closed. Its transaction is rolledback, so it won't be kept active.
+            //Connections that failed to authenticate are also closed, so each one verifies at most one password
+            if write_result.is_err() || !server.authenticated_connections.contains_key(&connection_id) {
+                Self::handle_close_request(server.clone(), connection_id);
+                break;
+            }
         }
     }
 
@@ -83,7 +283,7 @@ impl Server {
         let request = Request::deserialize_from_connection(connection)?;
         let connection_id = connection.connection_id();
 
-        Self::authenticate(&server, &request)?;
+        Self::authenticate(&server, connection_id, &request)?;
 
         match request {
             Request::UseDatabase(_, database) => {
@@ -92,10 +292,31 @@ impl Server {
                     connection.connection_id(), database));
                 Ok(Response::Ok)
             },
-            Request::Statement(_, is_stand_alone, statement) => {
-                let statement_result = Self::handle_statement_request(connection_id, server, is_stand_alone, statement)?;
+            Request::Statement(_, is_stand_alone, timeout_ms, statement) => {
+                let cancellation_token = Self::create_cancellation_token(timeout_ms);
+                let statement_result = Self::handle_statement_request(connection_id, server, is_stand_alone, cancellation_token, statement)?;
+                Ok(Response::Statement(statement_result))
+            },
+            Request::Prepare(_, statement) => {
+                let (prepared_statement_id, n_parameters) = Self::handle_prepare_request(connection_id, server, statement)?;
+                logger().debug(SimpleDbLayer::Server, &format!("Executed prepare request. Connection ID: {} Prepared statement ID: {}",
+                    connection_id, prepared_statement_id));
+                Ok(Response::Prepared(prepared_statement_id, n_parameters))
+            },
+            Request::ExecutePrepared(_, is_stand_alone, timeout_ms, prepared_statement_id, values) => {
+                let cancellation_token = Self::create_cancellation_token(timeout_ms);
+                let statement_result = Self::handle_execute_prepared_request(
+                    connection_id, server, is_stand_alone, cancellation_token, prepared_statement_id, values
+                )?;
                 Ok(Response::Statement(statement_result))
             },
+            Request::Batch(_, is_stand_alone, timeout_ms, statements) => {
+                let cancellation_token = Self::create_cancellation_token(timeout_ms);
+                let responses = Self::handle_batch_request(connection_id, server, is_stand_alone, cancellation_token, statements)?;
+                logger().debug(SimpleDbLayer::Server, &format!("Executed batch request. Connection ID: {} Responses: {}",
+                    connection_id, responses.len()));
+                Ok(Response::Batch(responses))
+            },
             Request::Close(_) => {
                 Self::handle_close_request(server, connection_id);
                 logger().debug(SimpleDbLayer::Server, &format!("Executed close request with connection ID: {}", connection_id));
@@ -104,15 +325,52 @@ impl Server {
         }
     }
 
+    //The timeout also covers the time spent sending the rows of the response, as they are read while being sent
+    fn create_cancellation_token(timeout_ms: QueryTimeoutMs) -> Option<CancellationToken> {
+        if timeout_ms > 0 {
+            Some(CancellationToken::create_with_timeout(Duration::from_millis(timeout_ms)))
+        } else {
+            None
+        }
+    }
+
+    //Every request carries the credentials. Verifying the hash is slow, so it is only done by the first request
+    //of the connection, and the digest of its credentials is kept to authenticate the next ones.
+    //Requests without user are authenticated with the server password and have access to every database
     fn authenticate(
         server: &Arc<Server>,
+        connection_id: ConnectionId,
         request: &Request
     ) -> Result<(), SimpleDbError> {
         let authentication = request.get_authentication();
-        if authentication.password != server.options.server_password {
-            Err(InvalidPassword)
+        let mut digest_context = digest::Context::new(&digest::SHA256);
+        digest_context.update(authentication.user.as_bytes());
+        digest_context.update(&[0]);
+        digest_context.update(authentication.password.as_bytes());
+        let credentials_digest = digest_context.finish();
+
+        //Authenticated connections cannot change their credentials
+        if let Some(authenticated) = server.authenticated_connections.get(&connection_id) {
+            let (authenticated_user, authenticated_digest) = authenticated.value();
+            if *authenticated_user == authentication.user && authenticated_digest.as_slice() == credentials_digest.as_ref() {
+                return Ok(());
+            }
+
+            server.authenticated_connections.remove(&connection_id);
+            return Err(InvalidPassword);
+        }
+
+        let is_valid = if authentication.user.is_empty() {
+            server.password.verify(&authentication.password)
         } else {
+            server.simple_db.authenticate_user(&authentication.user, &authentication.password)
+        };
+
+        if is_valid {
+            server.authenticated_connections.insert(connection_id, (authentication.user.clone(), credentials_digest.as_ref().to_vec()));
             Ok(())
+        } else {
+            Err(InvalidPassword)
         }
     }
 
@@ -120,6 +378,119 @@ impl Server {
         connection_id: ConnectionId,
         server: Arc<Server>,
         is_stand_alone: bool,
+        cancellation_token: Option<CancellationToken>,
+        statement_string: String
+    ) -> Result<StatementResponse, SimpleDbError> {
+        let statement = server.simple_db.parse(&statement_string)?;
+        Self::handle_statement(connection_id, server, is_stand_alone, cancellation_token, statement, statement_string)
+    }
+
+    //Statements are parsed before executing any of them. They are executed one by one with the connection's context,
+    //so they will run inside the active transaction if there is one. In standalone mode, if there isn't, they run in a
+    //transaction started for the batch, so if one of them fails, the writes of the previous ones are rolledback too.
+    //Batches that start or finish transactions themselves, and statements that are not transactional, like
+    //CREATE TABLE, are not rolledback
+    fn handle_batch_request(
+        connection_id: ConnectionId,
+        server: Arc<Server>,
+        is_stand_alone: bool,
+        cancellation_token: Option<CancellationToken>,
+        statements_string: String
+    ) -> Result<Vec<Response>, SimpleDbError> {
+        let statements = server.simple_db.parse_all(&statements_string)?;
+        let mut responses = Vec::with_capacity(statements.len());
+        let statements_string = if statements.iter().any(|statement| matches!(statement, Statement::CreateUser(_, _))) {
+            String::from("<batch with a CREATE USER statement, redacted>")
+        } else {
+            statements_string
+        };
+        let has_transaction = server.context_by_connection_id.get(&connection_id)
+            .map(|context| context.value().has_transaction())
+            .unwrap_or(false);
+        let run_in_batch_transaction = is_stand_alone && !has_transaction && statements.iter().any(|statement| {
+            statement.get_descriptor().requires_transaction()
+        }) && !statements.iter().any(|statement| {
+            let statement_desc = statement.get_descriptor();
+            statement_desc.creates_transaction() || statement_desc.terminates_transaction()
+        });
+        if run_in_batch_transaction {
+            Self::handle_statement(connection_id, server.clone(), false, None,
+                Statement::StartTransaction(IsolationLevel::SnapshotIsolation), statements_string.clone())?;
+        }
+        let is_stand_alone = is_stand_alone && !run_in_batch_transaction;
+
+        let mut failed = false;
+        for statement in statements {
+            let statement_response = Self::handle_statement(connection_id, server.clone(), is_stand_alone, cancellation_token.clone(), statement, statements_string.clone())
+                .and_then(|mut statement_response| {
+                    //Rows are read before executing the next statements, which might modify them
+                    if let StatementResponse::Rows(rows) = &mut statement_response {
+                        rows.materialize()?;
+                    }
+                    Ok(statement_response)
+                });
+
+            match statement_response {
+                Ok(statement_response) => responses.push(Response::Statement(statement_response)),
+                Err(error) => {
+                    responses.push(Response::from_simpledb_error(error));
+                    failed = true;
+                    break;
+                }
+            }
+        }
+
+        if run_in_batch_transaction {
+            let end_transaction = if failed { Statement::Rollback } else { Statement::Commit };
+            if let Err(error) = Self::handle_statement(connection_id, server.clone(), false, None, end_transaction, statements_string) {
+                responses.push(Response::from_simpledb_error(error));
+            }
+        }
+
+        Ok(responses)
+    }
+
+    fn handle_prepare_request(
+        connection_id: ConnectionId,
+        server: Arc<Server>,
+        statement_string: String
+    ) -> Result<(PreparedStatementId, usize), SimpleDbError> {
+        let statement = server.simple_db.parse(&statement_string)?;
+        let n_parameters = statement.get_n_parameters();
+        let prepared_statement_id = server.next_prepared_statement_id.fetch_add(1, Relaxed);
+
+        server.prepared_statements.insert((connection_id, prepared_statement_id), (statement_string, statement));
+
+        Ok((prepared_statement_id, n_parameters))
+    }
+
+    fn handle_execute_prepared_request(
+        connection_id: ConnectionId,
+        server: Arc<Server>,
+        is_stand_alone: bool,
+        cancellation_token: Option<CancellationToken>,
+        prepared_statement_id: PreparedStatementId,
+        values: Vec<Value>
+    ) -> Result<StatementResponse, SimpleDbError> {
+        let (statement_string, statement) = match server.prepared_statements.get(&(connection_id, prepared_statement_id)) {
+            Some(entry) => {
+                let (statement_string, statement) = entry.value();
+                (statement_string.clone(), statement.bind(&values)?)
+            },
+            None => return Err(SimpleDbError::MalformedQuery(format!(
+                "Unknown prepared statement {}", prepared_statement_id
+            ))),
+        };
+
+        Self::handle_statement(connection_id, server, is_stand_alone, cancellation_token, statement, statement_string)
+    }
+
+    fn handle_statement(
+        connection_id: ConnectionId,
+        server: Arc<Server>,
+        is_stand_alone: bool,
+        cancellation_token: Option<CancellationToken>,
+        statement: Statement,
         statement_string: String
     ) -> Result<StatementResponse, SimpleDbError> {
         let mut context = match server.context_by_connection_id.get(&connection_id) {
@@ -127,17 +498,26 @@ impl Server {
             None => Context::empty()
         };
 
-        let statement = server.simple_db.parse(&statement_string)?;
         let statement_desc = statement.get_descriptor();
         let is_explained = statement.is_explained();
+        let statement_string = Self::redact_password(&statement, statement_string);
 
         if statement_desc.requires_transaction() && !context.has_transaction() && is_stand_alone {
-            let transaction = server.simple_db.execute(&context, Statement::StartTransaction)?
+            let transaction = server.simple_db.execute(&context, Statement::StartTransaction(IsolationLevel::SnapshotIsolation))?
                 .get_transaction();
             context.with_transaction(transaction);
         }
 
-        match server.simple_db.execute(&context, statement) {
+        //The token is only applied to this statement, the context stored for the connection won't keep it
+        let mut statement_context = context.clone();
+        if let Some(cancellation_token) = cancellation_token {
+            statement_context.with_cancellation_token(cancellation_token);
+        }
+        if let Some(user) = server.get_connection_user(connection_id) {
+            statement_context.with_user(&user);
+        }
+
+        match server.simple_db.execute(&statement_context, statement) {
             Ok(statement_result) => {
                 if statement_desc.requires_transaction() && is_stand_alone {
                     server.simple_db.execute(&context, Statement::Commit)?;
@@ -148,6 +528,9 @@ impl Server {
                 } else if statement_desc.terminates_transaction() {
                     context.clear_transaction();
                     server.context_by_connection_id.insert(connection_id, context);
+                } else if let StatementResult::DatabaseSelected(database_name) = &statement_result {
+                    //Next statements of the connection will run in the selected database
+                    Self::handle_use_database_connection_request(server.clone(), database_name, connection_id)?;
                 }
 
                 Self::create_response(statement_result, connection_id, statement_string, is_explained)
@@ -162,22 +545,31 @@ impl Server {
         }
     }
 
+    //Statements are written to the debug log. CREATE USER carries a plaintext password, so it is logged without it
+    fn redact_password(statement: &Statement, statement_string: String) -> String {
+        match statement {
+            Statement::CreateUser(user_name, _) => format!("CREATE USER {} PASSWORD <redacted>;", user_name),
+            _ => statement_string,
+        }
+    }
+
     fn handle_use_database_connection_request(
         server: Arc<Server>,
         database_name: &String,
         connection_id: ConnectionId
     )  -> Result<(), SimpleDbError> {
-        server.simple_db.get_databases().get_database_or_err(database_name)?;
+        let user = server.get_connection_user(connection_id);
+        let new_context = server.simple_db.create_context(user.as_deref(), database_name)?;
 
         match server.context_by_connection_id.get(&connection_id) {
             Some(context) => {
                 let context = context.value();
                 //Rollback previous transaction
                 let _ = server.simple_db.execute(context, Statement::Rollback);
-                server.context_by_connection_id.insert(connection_id, Context::create_with_database(&database_name));
+                server.context_by_connection_id.insert(connection_id, new_context);
             }
             None => {
-                server.context_by_connection_id.insert(connection_id, Context::create_with_database(&database_name));
+                server.context_by_connection_id.insert(connection_id, new_context);
             }
         };
 
@@ -212,6 +604,12 @@ impl Server {
                 );
                 Ok(StatementResponse::Indexes(indexes))
             }
+            StatementResult::Stats(stats) => {
+                logger().debug(SimpleDbLayer::Server, &format!(
+                    "Executed show stats request Connection ID: {}", connection_id
+                ));
+                Ok(StatementResponse::Stats(Self::table_stats_to_entries(stats)))
+            }
             StatementResult::Tables(tables) => {
                 logger().debug(SimpleDbLayer::Server, &format!(
                     "Executed show tables request Connection ID: {} Entries to return {}",
@@ -219,6 +617,16 @@ impl Server {
                 );
                 Ok(StatementResponse::Tables(tables))
             },
+            StatementResult::TransactionStatus(transaction) => {
+                logger().debug(SimpleDbLayer::Server, &format!(
+                    "Executed show transaction status request Connection ID: {}", connection_id
+                ));
+                Ok(StatementResponse::TransactionStatus(transaction.map(|transaction| {
+                    let mut active_transactions: Vec<_> = transaction.active_transactions().iter().cloned().collect();
+                    active_transactions.sort();
+                    (transaction.id(), active_transactions)
+                })))
+            },
             StatementResult::Ok(n) => {
                 logger().debug(SimpleDbLayer::Server, &format!(
                     "Executed statement request Connection ID: {} Rows affected {}. Statement: {}",
@@ -226,6 +634,33 @@ impl Server {
                 ));
                 Ok(StatementResponse::Ok(n))
             },
+            StatementResult::Transactions(transactions) => {
+                logger().debug(SimpleDbLayer::Server, &format!(
+                    "Executed show transactions request Connection ID: {} Entries to return {}",
+                    connection_id, transactions.len())
+                );
+                Ok(StatementResponse::Transactions(transactions))
+            },
+            StatementResult::Vacuumed(bytes_reclaimed) => {
+                logger().debug(SimpleDbLayer::Server, &format!(
+                    "Executed vacuum request Connection ID: {} Bytes reclaimed {}", connection_id, bytes_reclaimed
+                ));
+                Ok(StatementResponse::Vacuumed(bytes_reclaimed))
+            },
+            StatementResult::CorruptedParts(corrupted_parts) => {
+                logger().debug(SimpleDbLayer::Server, &format!(
+                    "Executed check database request Connection ID: {} Corrupted parts {}", connection_id, corrupted_parts.len()
+                ));
+                Ok(StatementResponse::CorruptedParts(corrupted_parts.iter()
+                    .map(|corrupted_part| format!("{:?}", corrupted_part))
+                    .collect()))
+            },
+            StatementResult::DatabaseSelected(database_name) => {
+                logger().debug(SimpleDbLayer::Server, &format!(
+                    "Executed use statement Connection ID: {} Database: {}", connection_id, database_name
+                ));
+                Ok(StatementResponse::Ok(0))
+            },
             StatementResult::TransactionStarted(transaction) => {
                 logger().debug(SimpleDbLayer::Server, &format!(
                     "Executed start transaction request Connection ID: {} Transaction ID: {}",
@@ -233,16 +668,15 @@ impl Server {
                 ));
                 Ok(StatementResponse::Ok(0))
             },
-            StatementResult::Data(plan_desc, mut query_iterator) => {
+            StatementResult::Data(plan_desc, query_iterator) => {
                 if !is_explained {
-                    let rows = query_iterator.all()?;
                     logger().debug(SimpleDbLayer::Server, &format!(
-                        "Executed query request request Connection ID: {} Rows returned: {} Statement: {}",
-                        connection_id, rows.len(), statement
+                        "Executed query request request Connection ID: {} Statement: {}",
+                        connection_id, statement
                     ));
                     Ok(StatementResponse::Rows(RowsResponse::create(
                         query_iterator.get_selected_columns(),
-                        rows
+                        query_iterator
                     )))
                 } else {
                     logger().debug(SimpleDbLayer::Server, &format!(
@@ -255,6 +689,24 @@ impl Server {
         }
     }
 
+    fn table_stats_to_entries(stats: TableStats) -> Vec<(String, String)> {
+        let n_sstables_per_level: Vec<String> = stats.n_sstables_per_level.iter()
+            .map(|n_sstables| n_sstables.to_string())
+            .collect();
+        let (min_primary_key, max_primary_key) = match stats.primary_key_range {
+            Some((min, max)) => (min.to_string(), max.to_string()),
+            None => (String::from("NULL"), String::from("NULL")),
+        };
+
+        vec![
+            (String::from("Rows (approximate)"), stats.n_rows.map_or(String::from("Unknown"), |n_rows| n_rows.to_string())),
+            (String::from("SSTables per level"), format!("[{}]", n_sstables_per_level.join(", "))),
+            (String::from("Size on disk (bytes)"), stats.size_bytes.to_string()),
+            (String::from("Min primary key"), min_primary_key),
+            (String::from("Max primary key"), max_primary_key),
+        ]
+    }
+
     fn handle_close_request(server: Arc<Server>, connection_id: ConnectionId) {
         if let Some(context_entry) = server.context_by_connection_id.get(&connection_id) {
             let context = context_entry.value();
@@ -264,6 +716,17 @@ impl Server {
 
             server.context_by_connection_id.remove(&connection_id);
         }
+
+        server.authenticated_connections.remove(&connection_id);
+        server.remove_prepared_statements(connection_id);
+    }
+
+    fn remove_prepared_statements(&self, connection_id: ConnectionId) {
+        let prepared_statements = self.prepared_statements
+            .range((connection_id, 0)..=(connection_id, PreparedStatementId::MAX));
+        for entry in prepared_statements {
+            entry.remove();
+        }
     }
 
     fn server_address_to_str(&self) -> String {
@@ -271,4 +734,575 @@ impl Server {
         address.push_str(self.options.server_port.to_string().as_str());
         address
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod test {
+    use crate::request::{Request, REQUEST_FLAG_STANDALONE, REQUEST_FLAG_TIMEOUT};
+    use crate::response::{Response, StatementResponse};
+    use crate::connection_limiter::Admission;
+    use crate::server::{ConnectionSlot, Server};
+    use bytes::{Buf, BufMut};
+    use db::{CancellationToken, Context, Statement};
+    use shared::connection::Connection;
+    use rustls::pki_types::ServerName;
+    use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+    use std::io::{Read, Write};
+    use std::net::{Shutdown, TcpListener, TcpStream};
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use shared::{SimpleDbError, SimpleDbOptionsBuilder};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    const ADMIN: (&str, &str) = ("", "123456"); //User, password
+
+    #[test]
+    fn tls_connection() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("simpledb-server-tls-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()));
+        std::fs::create_dir_all(&path).unwrap();
+        let certified_key = rcgen::generate_simple_self_signed(vec![String::from("localhost")]).unwrap();
+        let cert_path = path.join("cert.pem");
+        let key_path = path.join("key.pem");
+        std::fs::write(&cert_path, certified_key.cert.pem()).unwrap();
+        std::fs::write(&key_path, certified_key.signing_key.serialize_pem()).unwrap();
+        let options = shared::start_simpledb_options_builder()
+            .base_path(path.to_str().unwrap())
+            .server_tls(cert_path.to_str().unwrap(), key_path.to_str().unwrap())
+            .build_arc();
+        let server = Arc::new(Server::create(options.clone()).unwrap());
+        server.simple_db.execute(&Context::empty(), Statement::CreateDatabase(String::from("test"))).unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server_thread = {
+            let server = server.clone();
+            std::thread::spawn(move || {
+                let mut connection = server.accept_connection(listener.accept().unwrap().0).unwrap();
+                let mut response = Server::handle_request(&mut connection, server.clone()).unwrap();
+                connection.write(response.serialize(1024)).unwrap();
+            })
+        };
+
+        let mut root_store = RootCertStore::empty();
+        root_store.add(certified_key.cert.der().clone()).unwrap();
+        let client_config = ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(root_store)
+            .with_no_client_auth();
+        let client_connection = ClientConnection::new(Arc::new(client_config), ServerName::try_from("localhost").unwrap()).unwrap();
+        let mut client = StreamOwned::new(client_connection, TcpStream::connect(address).unwrap());
+
+        client.write_all(&statement_request(ADMIN, 0, "SHOW DATABASES;")).unwrap();
+        client.flush().unwrap();
+        let mut response = [0u8; 2];
+        client.read_exact(&mut response).unwrap();
+        //Statement response with the databases
+        assert_eq!(response, [1, 3]);
+
+        server_thread.join().unwrap();
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn tls_handshake_timeout() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("simpledb-server-tls-timeout-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()));
+        std::fs::create_dir_all(&path).unwrap();
+        let certified_key = rcgen::generate_simple_self_signed(vec![String::from("localhost")]).unwrap();
+        let cert_path = path.join("cert.pem");
+        let key_path = path.join("key.pem");
+        std::fs::write(&cert_path, certified_key.cert.pem()).unwrap();
+        std::fs::write(&key_path, certified_key.signing_key.serialize_pem()).unwrap();
+        let options = shared::start_simpledb_options_builder()
+            .base_path(path.to_str().unwrap())
+            .server_tls(cert_path.to_str().unwrap(), key_path.to_str().unwrap())
+            .server_tls_handshake_timeout_ms(100)
+            .build_arc();
+        let server = Arc::new(Server::create(options.clone()).unwrap());
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        //The client connects but never starts the handshake
+        let _client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let started = std::time::Instant::now();
+        assert!(matches!(server.accept_connection(listener.accept().unwrap().0), Err(SimpleDbError::NetworkError(_))));
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn use_statement() {
+        let (server, path) = create_server("use_statement");
+        let connection_id = 1;
+
+        assert!(matches!(Server::handle_statement_request(connection_id, server.clone(), true, None, String::from("USE noexiste;")),
+            Err(SimpleDbError::DatabaseNotFound(_))));
+        Server::handle_statement_request(connection_id, server.clone(), true, None, String::from("USE test;")).unwrap();
+        assert_eq!(server.context_by_connection_id.get(&connection_id).unwrap().value().database(), "test");
+
+        //Next statements don't need to specify the database
+        Server::handle_statement_request(connection_id, server.clone(), true, None, String::from("CREATE TABLE personas (id I64 PRIMARY KEY);")).unwrap();
+        Server::handle_statement_request(connection_id, server.clone(), true, None, String::from("INSERT INTO personas (id) VALUES (1);")).unwrap();
+        assert!(matches!(Server::handle_statement_request(connection_id, server.clone(), true, None, String::from("SELECT * FROM personas;")),
+            Ok(StatementResponse::Rows(_))));
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn rows_in_chunks() {
+        let (server, path) = create_server("rows_in_chunks");
+        let connection_id = 1;
+        Server::handle_use_database_connection_request(server.clone(), &String::from("test"), connection_id).unwrap();
+        Server::handle_statement_request(connection_id, server.clone(), true, None, String::from("CREATE TABLE personas (id I64 PRIMARY KEY);")).unwrap();
+        for id in 0..5 {
+            Server::handle_statement_request(connection_id, server.clone(), true, None, format!("INSERT INTO personas (id) VALUES ({});", id)).unwrap();
+        }
+
+        let mut response = Response::Statement(Server::handle_statement_request(
+            connection_id, server.clone(), true, None, String::from("SELECT * FROM personas;")
+        ).unwrap());
+        let mut chunks = Vec::new();
+        response.serialize_in_chunks(2, &mut |chunk| {
+            chunks.push(chunk);
+            Ok(())
+        }).unwrap();
+
+        //Response type, statement response type with the columns desc, 3 chunks of rows and the end marker
+        assert_eq!(chunks.len(), 6);
+        let n_rows_per_chunk: Vec<u32> = chunks[2..].iter()
+            .map(|chunk| u32::from_le_bytes(chunk[0..4].try_into().unwrap()))
+            .collect();
+        assert_eq!(n_rows_per_chunk, vec![2, 2, 1, 0]);
+        assert_eq!(chunks[5].len(), 4);
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn describe_indexed_columns() {
+        let (server, path) = create_server("describe_indexed_columns");
+        let connection_id = 1;
+        Server::handle_use_database_connection_request(server.clone(), &String::from("test"), connection_id).unwrap();
+        Server::handle_statement_request(connection_id, server.clone(), true, None, String::from("CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR, dinero I64);")).unwrap();
+        Server::handle_statement_request(connection_id, server.clone(), true, None, String::from("CREATE INDEX ON personas (nombre);")).unwrap();
+
+        let mut response = Response::Statement(Server::handle_statement_request(
+            connection_id, server.clone(), true, None, String::from("DESCRIBE personas;")
+        ).unwrap());
+        let serialized = response.serialize(1024);
+        let mut ptr = &serialized[2..];
+
+        //Column name, is primary, index keyspace ID
+        let mut columns_desc = Vec::new();
+        for _ in 0..ptr.get_u32_le() {
+            ptr.get_u16_le();
+            ptr.get_u8();
+            let is_primary = ptr.get_u8() != 0;
+            let index_keyspace_id = if ptr.get_u8() != 0 { Some(ptr.get_u64_le()) } else { None };
+            let column_name_length = ptr.get_u32_le() as usize;
+            let column_name = String::from_utf8(ptr[..column_name_length].to_vec()).unwrap();
+            ptr.advance(column_name_length);
+            columns_desc.push((column_name, is_primary, index_keyspace_id));
+        }
+
+        assert_eq!(serialized[0..2], [1, 5]);
+        assert_eq!(columns_desc.len(), 3);
+        assert_eq!(columns_desc[0], (String::from("id"), true, None));
+        assert_eq!(columns_desc[1].0, "nombre");
+        assert!(!columns_desc[1].1 && columns_desc[1].2.is_some());
+        assert_eq!(columns_desc[2], (String::from("dinero"), false, None));
+        assert!(ptr.is_empty());
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn batch() {
+        let (server, path) = create_server("batch");
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let mut connection = Connection::create(listener.accept().unwrap().0);
+        Server::handle_use_database_connection_request(server.clone(), &String::from("test"), connection.connection_id()).unwrap();
+
+        client.write_all(&batch_request(ADMIN,
+            "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR); INSERT INTO personas (id, nombre) VALUES (1, \"Jaime\"); SELECT * FROM personas;"
+        )).unwrap();
+        let responses = match Server::handle_request(&mut connection, server.clone()).unwrap() {
+            Response::Batch(responses) => responses,
+            _ => panic!()
+        };
+        assert_eq!(responses.len(), 3);
+        assert!(matches!(responses[0], Response::Statement(StatementResponse::Ok(0))));
+        assert!(matches!(responses[1], Response::Statement(StatementResponse::Ok(1))));
+        assert!(matches!(&responses[2], Response::Statement(StatementResponse::Rows(_))));
+        let serialized = Response::Batch(responses).serialize(1024);
+        assert_eq!(serialized[0], 5);
+        assert_eq!(u32::from_le_bytes(serialized[1..5].try_into().unwrap()), 3);
+
+        //The execution stops at the failed statement
+        client.write_all(&batch_request(ADMIN,
+            "INSERT INTO personas (id, nombre) VALUES (2, \"Molon\"); SELECT * FROM noexiste; INSERT INTO personas (id, nombre) VALUES (3, \"Wili\");"
+        )).unwrap();
+        let responses = match Server::handle_request(&mut connection, server.clone()).unwrap() {
+            Response::Batch(responses) => responses,
+            _ => panic!()
+        };
+        assert_eq!(responses.len(), 2);
+        assert!(matches!(responses[0], Response::Statement(StatementResponse::Ok(1))));
+        assert!(matches!(responses[1], Response::Error(_, _)));
+
+        //Statements that can't be parsed are reported with its index in the batch
+        client.write_all(&batch_request(ADMIN,
+            "INSERT INTO personas (id, nombre) VALUES (4, \"Pepe\"); SELEC * FROM personas;"
+        )).unwrap();
+        match Server::handle_request(&mut connection, server.clone()) {
+            Err(error) => assert!(format!("{:?}", error).starts_with("Statement 1 of the batch")),
+            _ => panic!()
+        };
+
+        //Rows are read before the next statements of the batch are executed
+        client.write_all(&batch_request(ADMIN,
+            "START_TRANSACTION; SELECT * FROM personas; DELETE FROM personas; COMMIT;"
+        )).unwrap();
+        let mut responses = match Server::handle_request(&mut connection, server.clone()).unwrap() {
+            Response::Batch(responses) => responses,
+            _ => panic!()
+        };
+        assert_eq!(responses.len(), 4);
+        match &mut responses[1] {
+            //The insert of the failed batch has been rolledback
+            Response::Statement(StatementResponse::Rows(rows)) => assert_eq!(rows.next_rows(10).unwrap().len(), 1),
+            _ => panic!()
+        };
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn execute_prepared_request_huge_n_values() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let mut connection = Connection::create(listener.accept().unwrap().0);
+
+        let mut request = auth_block(("", ""));
+        request.put_u8(5);
+        request.put_u8(REQUEST_FLAG_STANDALONE);
+        request.put_u32_le(1); //Prepared statement ID
+        request.put_u32_le(u32::MAX); //Nº of values
+        client.write_all(&request).unwrap();
+        client.shutdown(Shutdown::Both).unwrap();
+
+        //The values are not preallocated, so the request fails when the connection is closed
+        assert!(Request::deserialize_from_connection(&mut connection).is_err());
+    }
+
+    #[test]
+    fn statement_timeout() {
+        let (server, path) = create_server("statement_timeout");
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let mut connection = Connection::create(listener.accept().unwrap().0);
+        let connection_id = connection.connection_id();
+        Server::handle_use_database_connection_request(server.clone(), &String::from("test"), connection_id).unwrap();
+        Server::handle_statement_request(connection_id, server.clone(), true, None, String::from("CREATE TABLE personas (id I64 PRIMARY KEY);")).unwrap();
+        for id in 0..5 {
+            Server::handle_statement_request(connection_id, server.clone(), true, None, format!("INSERT INTO personas (id) VALUES ({});", id)).unwrap();
+        }
+
+        //The timeout is read from the request
+        client.write_all(&statement_request(ADMIN, 100, "SELECT * FROM personas;")).unwrap();
+        assert!(matches!(Request::deserialize_from_connection(&mut connection).unwrap(), Request::Statement(_, true, 100, _)));
+
+        //The timeout expires before the rows are read
+        let mut response = Response::Statement(Server::handle_statement_request(
+            connection_id, server.clone(), true, Some(CancellationToken::create_with_timeout(Duration::ZERO)), String::from("SELECT * FROM personas;")
+        ).unwrap());
+        let mut chunks = Vec::new();
+        response.serialize_in_chunks(2, &mut |chunk| {
+            chunks.push(chunk);
+            Ok(())
+        }).unwrap();
+
+        //Response type, statement response type with the columns desc and the rows error
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(u32::from_le_bytes(chunks[2][0..4].try_into().unwrap()), u32::MAX);
+        assert_eq!(chunks[2][4], SimpleDbError::QueryTimeout(0).serialize() as u8);
+
+        //Later statements of the connection are not affected by the timeout
+        let mut response = Response::Statement(Server::handle_statement_request(
+            connection_id, server.clone(), true, None, String::from("SELECT * FROM personas;")
+        ).unwrap());
+        let mut chunks = Vec::new();
+        response.serialize_in_chunks(5, &mut |chunk| {
+            chunks.push(chunk);
+            Ok(())
+        }).unwrap();
+        assert_eq!(u32::from_le_bytes(chunks[2][0..4].try_into().unwrap()), 5);
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn max_connections() {
+        let (server, path) = create_server_with_options("max_connections", |options| {
+            options.server_max_connections(2).server_max_queued_connections(1);
+        });
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        {
+            let server = server.clone();
+            std::thread::spawn(move || server.accept_connections(listener));
+        }
+        let send_request = |client: &mut TcpStream| {
+            client.write_all(&use_database_request(ADMIN, "test")).unwrap();
+            let mut response = [0u8; 1];
+            client.read_exact(&mut response).unwrap();
+            response
+        };
+        let read_rejection = |client: &mut TcpStream| {
+            let mut response = [0u8; 2];
+            client.read_exact(&mut response).unwrap();
+            response
+        };
+
+        let mut first_client = TcpStream::connect(address).unwrap();
+        let mut second_client = TcpStream::connect(address).unwrap();
+        assert_eq!(send_request(&mut first_client), [3]);
+        assert_eq!(send_request(&mut second_client), [3]);
+        assert_eq!(server.get_n_active_connections(), 2);
+
+        //All slots are taken, the third one waits in the queue and the fourth one gets rejected
+        let mut queued_client = TcpStream::connect(address).unwrap();
+        let mut rejected_client = TcpStream::connect(address).unwrap();
+        let too_many_connections_error_id = SimpleDbError::TooManyConnections(0).serialize() as u8;
+        assert_eq!(read_rejection(&mut rejected_client), [2, too_many_connections_error_id]);
+        assert_eq!(server.get_n_queued_connections(), 1);
+
+        //Existing connections keep working
+        first_client.shutdown(Shutdown::Both).unwrap();
+        assert_eq!(send_request(&mut second_client), [3]);
+
+        //The queued connection takes the slot of the closed one
+        assert_eq!(send_request(&mut queued_client), [3]);
+        assert_eq!(server.get_n_active_connections(), 2);
+        assert_eq!(server.get_n_queued_connections(), 0);
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn queued_connection_timeout() {
+        let (server, path) = create_server_with_options("queued_connection_timeout", |options| {
+            options.server_max_connections(1).server_max_queued_connections(1).server_queued_connection_timeout_ms(100);
+        });
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        {
+            let server = server.clone();
+            std::thread::spawn(move || server.accept_connections(listener));
+        }
+
+        let mut active_client = TcpStream::connect(address).unwrap();
+        active_client.write_all(&use_database_request(ADMIN, "test")).unwrap();
+        let mut response = [0u8; 1];
+        active_client.read_exact(&mut response).unwrap();
+        assert_eq!(response, [3]);
+
+        //The slot is never released, so the queued connection gets rejected once the timeout expires
+        let mut queued_client = TcpStream::connect(address).unwrap();
+        let mut rejection = [0u8; 2];
+        queued_client.read_exact(&mut rejection).unwrap();
+        let too_many_connections_error_id = SimpleDbError::TooManyConnections(0).serialize();
+        assert_eq!(rejection, [2, too_many_connections_error_id]);
+        assert_eq!(server.get_n_queued_connections(), 0);
+        assert_eq!(server.get_n_active_connections(), 1);
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn connection_slot_released_on_panic() {
+        let (server, path) = create_server_with_options("connection_slot_released_on_panic", |options| {
+            options.server_max_connections(1).server_max_queued_connections(1);
+        });
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let _client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let socket = listener.accept().unwrap().0;
+        assert!(matches!(server.connection_limiter.admit(socket), Admission::Accepted(_)));
+        assert_eq!(server.get_n_active_connections(), 1);
+
+        let panic_result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _connection_slot = ConnectionSlot { server: server.clone(), released: false };
+            panic!("Connection handler panicked");
+        }));
+
+        assert!(panic_result.is_err());
+        assert_eq!(server.get_n_active_connections(), 0);
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn create_user_password_redacted() {
+        let statement_string = String::from("CREATE USER jaime PASSWORD \"secreto\";");
+        let statement = Statement::CreateUser(String::from("jaime"), String::from("secreto"));
+        let redacted = Server::redact_password(&statement, statement_string);
+        assert!(!redacted.contains("secreto"));
+
+        let statement_string = String::from("SELECT * FROM personas;");
+        assert_eq!(Server::redact_password(&Statement::ShowTables, statement_string.clone()), statement_string);
+    }
+
+    #[test]
+    fn authentication() {
+        let (server, path) = create_server("authentication");
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let mut connection = Connection::create(listener.accept().unwrap().0);
+
+        client.write_all(&use_database_request(("", "1234567"), "test")).unwrap();
+        assert!(matches!(Server::handle_request(&mut connection, server.clone()), Err(SimpleDbError::InvalidPassword)));
+        client.write_all(&use_database_request(ADMIN, "test")).unwrap();
+        assert!(matches!(Server::handle_request(&mut connection, server.clone()), Ok(Response::Ok)));
+        //Authenticated connections cannot change their credentials
+        client.write_all(&use_database_request(("", ""), "test")).unwrap();
+        assert!(matches!(Server::handle_request(&mut connection, server.clone()), Err(SimpleDbError::InvalidPassword)));
+        assert!(!server.authenticated_connections.contains_key(&connection.connection_id()));
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn failed_authentication_closes_connection() {
+        let (server, path) = create_server("failed_authentication_closes_connection");
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let connection = Connection::create(listener.accept().unwrap().0);
+        let handler = {
+            let server = server.clone();
+            std::thread::spawn(move || Server::handle_connection(connection, server))
+        };
+
+        client.write_all(&use_database_request(("", "1234567"), "test")).unwrap();
+        let mut response = [0u8; 2];
+        client.read_exact(&mut response).unwrap();
+        assert_eq!(response, [2, SimpleDbError::InvalidPassword.serialize()]);
+
+        //No more passwords are verified on this connection
+        handler.join().unwrap();
+        let mut remaining_response = Vec::new();
+        client.read_to_end(&mut remaining_response).unwrap();
+        assert_eq!(client.read(&mut [0u8; 1]).unwrap(), 0);
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn plaintext_password_removed_from_options() {
+        let (server, path) = create_server_with_options("plaintext_password_removed_from_options", |options| {
+            options.server_password("pbkdf2-sha256");
+        });
+
+        assert!(server.options.server_password.is_empty());
+        assert!(server.password.verify("pbkdf2-sha256"));
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn restricted_user() {
+        let (server, path) = create_server("restricted_user");
+        let admin_connection_id = 1;
+        server.simple_db.execute(&Context::empty(), Statement::CreateDatabase(String::from("otro"))).unwrap();
+        Server::handle_use_database_connection_request(server.clone(), &String::from("test"), admin_connection_id).unwrap();
+        for statement in ["CREATE TABLE personas (id I64 PRIMARY KEY);", "INSERT INTO personas (id) VALUES (1);",
+            "CREATE USER jaime PASSWORD \"secreto\";", "GRANT test TO jaime;"] {
+            Server::handle_statement_request(admin_connection_id, server.clone(), true, None, String::from(statement)).unwrap();
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let mut connection = Connection::create(listener.accept().unwrap().0);
+        let user = ("jaime", "secreto");
+
+        client.write_all(&use_database_request(("jaime", "123456"), "test")).unwrap();
+        assert!(matches!(Server::handle_request(&mut connection, server.clone()), Err(SimpleDbError::InvalidPassword)));
+
+        //The granted database can be read
+        client.write_all(&use_database_request(user, "test")).unwrap();
+        assert!(matches!(Server::handle_request(&mut connection, server.clone()), Ok(Response::Ok)));
+        client.write_all(&statement_request(user, 0, "SELECT * FROM personas;")).unwrap();
+        let serialized = Server::handle_request(&mut connection, server.clone()).unwrap().serialize(1024);
+        assert_eq!(serialized[0..2], [1, 2]); //Statement, rows
+
+        //Other databases are denied
+        client.write_all(&use_database_request(user, "otro")).unwrap();
+        assert!(matches!(Server::handle_request(&mut connection, server.clone()), Err(SimpleDbError::AccessDenied(_, _))));
+        client.write_all(&statement_request(user, 0, "CREATE DATABASE nueva;")).unwrap();
+        assert!(matches!(Server::handle_request(&mut connection, server.clone()), Err(SimpleDbError::AccessDenied(_, _))));
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    fn create_server(name: &str) -> (Arc<Server>, PathBuf) {
+        create_server_with_options(name, |_| {})
+    }
+
+    fn create_server_with_options(name: &str, configure: impl Fn(&mut SimpleDbOptionsBuilder)) -> (Arc<Server>, PathBuf) {
+        let mut path = std::env::temp_dir();
+        path.push(format!("simpledb-server-{}-{}", name, SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()));
+        std::fs::create_dir_all(&path).unwrap();
+        let mut options_builder = shared::start_simpledb_options_builder();
+        configure(&mut options_builder);
+        let options = options_builder
+            .base_path(path.to_str().unwrap())
+            .build_arc();
+        let server = Arc::new(Server::create(options).unwrap());
+        server.simple_db.execute(&Context::empty(), Statement::CreateDatabase(String::from("test"))).unwrap();
+        (server, path)
+    }
+
+    fn auth_block((user, password): (&str, &str)) -> Vec<u8> {
+        let mut authentication = Vec::new();
+        authentication.put_u32_le(user.len() as u32);
+        authentication.extend(user.bytes());
+        authentication.put_u32_le(password.len() as u32);
+        authentication.extend(password.bytes());
+        authentication
+    }
+
+    fn statement_request(credentials: (&str, &str), timeout_ms: u64, statement: &str) -> Vec<u8> {
+        let mut request = auth_block(credentials);
+        request.put_u8(1);
+        if timeout_ms > 0 {
+            request.put_u8(REQUEST_FLAG_STANDALONE | REQUEST_FLAG_TIMEOUT);
+            request.put_u64_le(timeout_ms);
+        } else {
+            request.put_u8(REQUEST_FLAG_STANDALONE);
+        }
+        request.put_u32_le(statement.len() as u32);
+        request.extend(statement.bytes());
+        request
+    }
+
+    fn use_database_request(credentials: (&str, &str), database: &str) -> Vec<u8> {
+        let mut request = auth_block(credentials);
+        request.put_u8(3);
+        request.put_u32_le(database.len() as u32);
+        request.extend(database.bytes());
+        request
+    }
+
+    fn batch_request(credentials: (&str, &str), statements: &str) -> Vec<u8> {
+        let mut request = auth_block(credentials);
+        request.put_u8(6);
+        request.put_u8(REQUEST_FLAG_STANDALONE);
+        request.put_u32_le(statements.len() as u32);
+        request.extend(statements.bytes());
+        request
+    }
+}
@@ -57,4 +57,26 @@ where
         panic!("{}", format!("Iterator only have {} elements. Expected number of elements: {}",
                              current_index + 1, initial_expected_n_elements))
     }
-}
\ No newline at end of file
+}
+//Calls seek_to_end() and expects the keys to be returned by prev() in the same order as expected_strs
+pub fn assert_reversed_iterator_str_seq<I>(
+    mut iterator: I,
+    expected_strs: Vec<&str>
+)
+where
+    I: StorageIterator
+{
+    iterator.seek_to_end();
+
+    let mut actual_keys = Vec::new();
+    while iterator.prev() {
+        actual_keys.push(iterator.key().clone());
+    }
+
+    let expected_keys: Vec<Key> = expected_strs.iter()
+        .map(|expected_str| Key::create_from_str(expected_str, 0))
+        .collect();
+
+    assert_eq!(actual_keys, expected_keys);
+    assert!(!iterator.has_prev());
+}
@@ -2,19 +2,30 @@ use crate::SimpleDbError::NetworkError;
 use crate::{ConnectionId, SimpleDbError};
 use std::hash::{DefaultHasher, Hasher};
 use std::io::{Read, Write};
-use std::net::{IpAddr, TcpStream};
+use std::net::{IpAddr, SocketAddr, TcpStream};
+
+//Byte stream over which requests and responses are sent. It might be a plain TCP socket or a TLS session over it
+pub trait ConnectionStream: Read + Write + Send {}
+
+impl<T: Read + Write + Send> ConnectionStream for T {}
 
 pub struct Connection {
-    tcp_stream: TcpStream
+    tcp_stream: Box<dyn ConnectionStream>,
+    peer_address: SocketAddr,
 }
 
 impl Connection {
     pub fn create(tcp_stream: TcpStream) -> Connection {
-        Connection { tcp_stream }
+        let peer_address = tcp_stream.peer_addr().unwrap();
+        Connection { tcp_stream: Box::new(tcp_stream), peer_address }
+    }
+
+    pub fn create_with_stream(stream: Box<dyn ConnectionStream>, peer_address: SocketAddr) -> Connection {
+        Connection { tcp_stream: stream, peer_address }
     }
 
     pub fn connection_id(&self) -> ConnectionId {
-        let address = self.tcp_stream.peer_addr().unwrap();
+        let address = self.peer_address;
         let port = address.port();
 
         match address.ip() {
@@ -29,7 +40,7 @@ impl Connection {
     }
 
     pub fn address(&self) -> String {
-        self.tcp_stream.peer_addr().unwrap().to_string()
+        self.peer_address.to_string()
     }
 
     pub fn read_u8(&mut self) -> Result<u8, SimpleDbError> {
@@ -68,7 +79,10 @@ impl Connection {
     }
 
     pub fn write(&mut self, bytes: Vec<u8>) -> Result<usize, SimpleDbError> {
-        self.tcp_stream.write(bytes.as_slice())
-            .map_err(|e| NetworkError(e))
+        self.tcp_stream.write_all(bytes.as_slice())
+            .map_err(|e| NetworkError(e))?;
+        self.tcp_stream.flush()
+            .map_err(|e| NetworkError(e))?;
+        Ok(bytes.len())
     }
 }
\ No newline at end of file
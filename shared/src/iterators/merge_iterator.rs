@@ -80,6 +80,36 @@ impl<I: StorageIterator> MergeIterator<I> {
             current_index += 1;
         }
     }
+
+    fn call_prev_on_every_iterator(&mut self) {
+        for (current_index, iterator) in self.iterators.iter_mut().enumerate() {
+            let iterator = iterator.as_mut().unwrap();
+            if !iterator.prev() {
+                self.finished_iterators_indexes.insert(current_index);
+            }
+        }
+    }
+
+    //Moves back every iterator pointing to a key greater or equal than max_key_seen
+    fn move_back_iterators(&mut self, max_key_seen: &Key) {
+        let mut finished_iterators = Vec::new();
+
+        for (current_index, iterator) in self.iterators.iter_mut().enumerate() {
+            if self.finished_iterators_indexes.contains(&current_index) {
+                continue;
+            }
+
+            let iterator = iterator.as_mut().unwrap();
+            while iterator.key() >= max_key_seen {
+                if !iterator.prev() {
+                    finished_iterators.push(current_index);
+                    break;
+                }
+            }
+        }
+
+        self.remove_finished_iterators(finished_iterators);
+    }
 }
 
 impl<I: StorageIterator> StorageIterator for MergeIterator<I> {
@@ -160,6 +190,62 @@ impl<I: StorageIterator> StorageIterator for MergeIterator<I> {
 
         self.first_iteration = true;
     }
+
+    fn seek_to_end(&mut self) {
+        for iterator in &mut self.iterators {
+            if let Some(iterator) = iterator.as_mut() {
+                iterator.seek_to_end();
+            }
+        }
+
+        self.first_iteration = true;
+    }
+
+    fn prev(&mut self) -> bool {
+        if self.first_iteration {
+            self.call_prev_on_every_iterator();
+            self.first_iteration = false;
+        }
+
+        let mut max_key_seen: Option<Key> = None;
+        let mut max_iterator_index = 0;
+
+        for (current_index, current_iterator) in self.iterators.iter().enumerate() {
+            if self.finished_iterators_indexes.contains(&current_index) {
+                continue;
+            }
+
+            if let Some(current_iterator) = current_iterator {
+                let current_key = current_iterator.key();
+                if max_key_seen.is_none() || current_key > max_key_seen.as_ref().unwrap() {
+                    max_key_seen = Some(current_key.clone());
+                    max_iterator_index = current_index;
+                }
+            }
+        }
+
+        match max_key_seen {
+            Some(max_key_seen) => {
+                self.last_value_iterated = Some(Bytes::copy_from_slice(self.iterators[max_iterator_index]
+                    .as_ref()
+                    .unwrap()
+                    .value()));
+                self.move_back_iterators(&max_key_seen);
+                self.last_key_iterated = Some(max_key_seen);
+                true
+            },
+            None => false
+        }
+    }
+
+    fn has_prev(&self) -> bool {
+        if self.first_iteration {
+            return self.iterators.iter()
+                .any(|iterator| iterator.as_ref().unwrap().has_prev());
+        }
+
+        self.finished_iterators_indexes.len() < self.iterators.len()
+    }
 }
 
 fn is_iterator_up_to_date<I: StorageIterator>(it: &Box<I>, last_key: &Key) -> bool {
@@ -266,6 +352,19 @@ mod test {
         );
     }
 
+    /**
+    A -> B -> D
+    B -> E
+    C -> D -> E
+    */
+    #[test]
+    fn reversed_iterator() {
+        assertions::assert_reversed_iterator_str_seq(
+            create_merge_iterator(),
+            vec!["e", "d", "c", "b", "a"]
+        );
+    }
+
     fn create_merge_iterator() -> MergeIterator<MockIterator> {
         let mut iterator1 = MockIterator::create();
         iterator1.add_entry("a", 0, Bytes::from("a"));
@@ -113,6 +113,23 @@ impl StorageIterator for MockIterator {
             };
         }
     }
+
+    fn seek_to_end(&mut self) {
+        self.next_index = self.entries.len() + 1;
+    }
+
+    fn prev(&mut self) -> bool {
+        if self.has_prev() {
+            self.next_index -= 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn has_prev(&self) -> bool {
+        self.next_index > 1
+    }
 }
 
 #[cfg(test)]
@@ -179,6 +196,14 @@ mod test {
         );
     }
 
+    #[test]
+    fn reversed_iterator() {
+        assertions::assert_reversed_iterator_str_seq(
+            MockIterator::create_from_strs_values(vec!["a", "c", "d"]),
+            vec!["d", "c", "a"]
+        );
+    }
+
     #[test]
     fn iterator() {
         assertions::assert_iterator_str_seq(
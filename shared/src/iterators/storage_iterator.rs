@@ -26,4 +26,14 @@ pub trait StorageIterator {
     //[1, 3, 5] Seek = 0, inclusive = true or false. The iterator will point to 1
     //[1, 3, 5] Seek = 2, inclusive = true or false. The iterator will point to 3
     fn seek(&mut self, key: &Bytes, inclusive: bool);
-}
\ No newline at end of file
+
+    //Expect call after creation of the iterator. Places the iterator after the last key, so that
+    //calls to prev() will return the keys in descending order. next() is not expected to be called after it
+    //[1, 3, 5] prev() -> 5, prev() -> 3, prev() -> 1
+    fn seek_to_end(&mut self);
+
+    //Returns true if it has moved to the previous key. Expect call after seek_to_end()
+    fn prev(&mut self) -> bool;
+
+    fn has_prev(&self) -> bool;
+}
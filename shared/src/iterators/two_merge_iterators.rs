@@ -10,22 +10,26 @@ pub struct TwoMergeIterator<A: StorageIterator, B: StorageIterator> {
     choose_a: bool,
 
     first_iteration: bool,
+
+    //If the iterators are pointing to an entry. The last entry of an iterator is still valid, although has_next() returns false
+    is_a_valid: bool,
+    is_b_valid: bool,
 }
 
 impl<A: StorageIterator, B: StorageIterator> TwoMergeIterator<A, B> {
     pub fn create(a: A, b: B) -> TwoMergeIterator<A, B> {
-        TwoMergeIterator { a, b, choose_a: false, first_iteration: true }
+        TwoMergeIterator { a, b, choose_a: false, first_iteration: true, is_a_valid: false, is_b_valid: false }
     }
 
     fn choose_a(&self) -> bool {
-        if !self.a.has_next() && !self.b.has_next() {
+        if !self.is_a_valid && !self.is_b_valid {
             //Return previous choice
             return self.choose_a;
         }
-        if !self.a.has_next() && self.b.has_next() {
+        if !self.is_a_valid && self.is_b_valid {
             return false;
         }
-        if !self.b.has_next() && self.a.has_next() {
+        if !self.is_b_valid && self.is_a_valid {
             return true;
         }
 
@@ -33,32 +37,31 @@ impl<A: StorageIterator, B: StorageIterator> TwoMergeIterator<A, B> {
     }
 
     fn skip_b_duplicates(&mut self) {
-        while self.a.has_next() && self.b.has_next() && self.a.key() == self.b.key() {
-            self.b.next();
+        while self.is_a_valid && self.is_b_valid && self.a.key() == self.b.key() {
+            self.is_b_valid = self.b.next();
         }
     }
 
     fn first_iteration(&mut self) -> bool {
         self.first_iteration = false;
-        let a_advanced = self.a.next();
-        let b_advanced = self.b.next();
+        self.is_a_valid = self.a.next();
+        self.is_b_valid = self.b.next();
 
-        if !a_advanced && !b_advanced {
+        if !self.is_a_valid && !self.is_b_valid {
             return false;
         }
-        if a_advanced && !b_advanced {
-            self.choose_a = true;
-        }
-        if !a_advanced && b_advanced {
-            self.choose_a = false;
-        }
-        if a_advanced && b_advanced {
-            self.skip_b_duplicates();
-            self.choose_a = self.choose_a();
-        }
+
+        self.skip_b_duplicates();
+        self.choose_a = self.choose_a();
 
         true
     }
+
+    fn skip_b_duplicates_backwards(&mut self) {
+        while self.is_a_valid && self.is_b_valid && self.a.key() == self.b.key() {
+            self.is_b_valid = self.b.prev();
+        }
+    }
 }
 
 impl<A: StorageIterator, B: StorageIterator> StorageIterator for TwoMergeIterator<A, B> {
@@ -70,20 +73,29 @@ impl<A: StorageIterator, B: StorageIterator> StorageIterator for TwoMergeIterato
             return self.first_iteration();
         }
 
-        let advanced= if self.choose_a {
-            self.a.next()
+        if self.choose_a {
+            self.is_a_valid = self.a.next();
         } else { //Choose b
-            self.b.next()
-        };
+            self.is_b_valid = self.b.next();
+        }
 
         self.skip_b_duplicates();
         self.choose_a = self.choose_a();
 
-        advanced
+        self.is_a_valid || self.is_b_valid
     }
 
     fn has_next(&self) -> bool {
-        self.a.has_next() || self.b.has_next()
+        if self.first_iteration {
+            return self.a.has_next() || self.b.has_next();
+        }
+
+        //The entry of the iterator not chosen has not been returned yet
+        if self.choose_a {
+            self.a.has_next() || self.is_b_valid
+        } else {
+            self.b.has_next() || self.is_a_valid
+        }
     }
 
     fn key(&self) -> &Key {
@@ -108,6 +120,45 @@ impl<A: StorageIterator, B: StorageIterator> StorageIterator for TwoMergeIterato
         self.b.seek(key, inclusive);
         self.first_iteration = true;
     }
+
+    fn seek_to_end(&mut self) {
+        self.a.seek_to_end();
+        self.b.seek_to_end();
+        self.first_iteration = true;
+    }
+
+    fn prev(&mut self) -> bool {
+        if self.first_iteration {
+            self.first_iteration = false;
+            self.is_a_valid = self.a.prev();
+            self.is_b_valid = self.b.prev();
+        } else if self.choose_a {
+            self.is_a_valid = self.a.prev();
+        } else {
+            self.is_b_valid = self.b.prev();
+        }
+
+        self.skip_b_duplicates_backwards();
+
+        if !self.is_a_valid && !self.is_b_valid {
+            return false;
+        }
+
+        self.choose_a = self.is_a_valid && (!self.is_b_valid || self.a.key() > self.b.key());
+        true
+    }
+
+    fn has_prev(&self) -> bool {
+        if self.first_iteration {
+            return self.a.has_prev() || self.b.has_prev();
+        }
+
+        if self.choose_a {
+            self.is_b_valid || self.a.has_prev()
+        } else {
+            self.is_a_valid || self.b.has_prev()
+        }
+    }
 }
 
 #[cfg(test)]
@@ -218,6 +269,65 @@ mod test {
         );
     }
 
+    // A -> B
+    // C -> D
+    #[test]
+    fn last_entry_of_one_iterator() {
+        let mut iterator1 = MockIterator::create();
+        iterator1.add_entry("c", 0, Bytes::from(vec![1]));
+        iterator1.add_entry("d", 0, Bytes::from(vec![1]));
+
+        let mut iterator2 = MockIterator::create();
+        iterator2.add_entry("a", 0, Bytes::from(vec![1]));
+        iterator2.add_entry("b", 0, Bytes::from(vec![1]));
+
+        assertions::assert_iterator_str_seq(
+            TwoMergeIterator::create(iterator1, iterator2),
+            vec![
+                "a",
+                "b",
+                "c",
+                "d"
+            ]
+        );
+    }
+
+    // C
+    // A
+    #[test]
+    fn has_next_with_pending_entry() {
+        let mut iterator = TwoMergeIterator::create(
+            MockIterator::create_from_strs_values(vec!["c"]),
+            MockIterator::create_from_strs_values(vec!["a"]),
+        );
+
+        assert!(iterator.has_next());
+        assert!(iterator.next());
+        assert_eq!(iterator.key().as_bytes(), &Bytes::from("a"));
+        //Both iterators have already been advanced, but the entry of the first one has not been returned
+        assert!(iterator.has_next());
+        assert!(iterator.next());
+        assert_eq!(iterator.key().as_bytes(), &Bytes::from("c"));
+        assert!(!iterator.has_next());
+        assert!(!iterator.next());
+    }
+
+    // A -> B -> D
+    // A -> C -> D -> F
+    #[test]
+    fn reversed_iterator() {
+        assertions::assert_reversed_iterator_str_seq(
+            create_iterator(),
+            vec![
+                "f",
+                "d",
+                "c",
+                "b",
+                "a"
+            ]
+        );
+    }
+
     fn create_iterator() -> TwoMergeIterator<MockIterator, MockIterator> {
         let mut iterator1 = MockIterator::create();
         iterator1.add_entry("a", 0, Bytes::from(vec![1]));
@@ -1,7 +1,7 @@
 use crate::{KeyspaceId, SimpleDbOptions};
 use std::sync::{Arc, OnceLock};
 use env_logger::Builder;
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 
 pub enum SimpleDbLayer {
     Server,
@@ -26,7 +26,8 @@ impl Logger {
             let mut builder = Builder::new();
             builder.filter_level(log::LevelFilter::Info);
             builder.filter_level(log::LevelFilter::Debug);
-            builder.init();
+            //Might be called concurrently by tests
+            let _ = builder.try_init();
 
             let logger = Arc::new(Logger{ options });
             let _ = LOGGER.set(logger);
@@ -37,6 +38,10 @@ impl Logger {
         info!("[{}] {}", layer.to_string(), message);
     }
 
+    pub fn warn(&self, layer: SimpleDbLayer, message: &str) {
+        warn!("[{}] {}", layer.to_string(), message);
+    }
+
     pub fn error(&self, layer: SimpleDbLayer, message: &str) {
         error!("[{}] {}", layer.to_string(), message);
     }
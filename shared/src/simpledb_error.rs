@@ -1,4 +1,4 @@
-use crate::{types, ColumnId, KeyspaceId, Type};
+use crate::{types, ColumnId, KeyspaceId, TxnId, Type};
 use bytes::Bytes;
 use std::fmt::{Debug, Formatter};
 use std::string::FromUtf8Error;
@@ -9,6 +9,8 @@ pub enum DecodeErrorType {
     JsonSerdeDeserialization(serde_json::Error),
     IllegalSize(usize, usize), //Expected size, actual size
     UnknownFlag(usize), //Current flag value
+    Decompression(String), //Error message of the codec
+    UnsortedKeys(usize), //Index of the first key lower than the previous one
 }
 
 pub struct DecodeError {
@@ -22,6 +24,7 @@ pub enum SSTableCorruptedPart {
     BlockMetadata,
     BloomFilter,
     Block(usize), //Block ID
+    Footer,
 }
 
 pub struct TokenLocation {
@@ -36,16 +39,29 @@ pub enum SimpleDbError {
     InvalidPassword,
     InvalidRequestBinaryFormat,
     NetworkError(std::io::Error),
+    InvalidTlsConfiguration(String),
+    TooManyConnections(usize), //Max nº of connections
+    InvalidPasswordHash(String),
+    AccessDenied(String, String), //User name, database name. "*" if the statement affects every database
 
     //Shared error types
     IllegalTypeCastFromBytes(Type),
     IllegalTypeOperation(&'static str),
+    ArithmeticOverflow,
+    DivisionByZero,
 
     //DB Layer errors
     IndexAlreadyExists(KeyspaceId, String),
+    IndexCreationCancelled(String), //Indexed columns names
+    IndexCreationInterrupted(String), //Indexed columns names
     IndexNotFound(ColumnId),
     IllegalToken(TokenLocation, String),
+    MalformedNumber(TokenLocation, String),
+    MalformedString(TokenLocation, String),
+    UniqueConstraintViolation(String),
+    InvalidIdentifier(String),
     MalformedQuery(String),
+    InvalidBatchStatement(usize, Box<SimpleDbError>), //Index of the statement in the batch, error
     FullScanNotAllowed(),
     RangeScanNotAllowed(),
     InvalidContext(&'static str),
@@ -76,6 +92,19 @@ pub enum SimpleDbError {
     CannotReadSortFile(std::io::Error),
     CannotWriteReverseFile(std::io::Error),
     CannotReadReverseFile(std::io::Error),
+    TransactionAlreadyFinished(TxnId),
+    TableInUse(String),
+    CannotDeleteTableDescriptor(types::KeyspaceId, std::io::Error),
+    TooManyColumns(String, usize), //Table name, max nº of columns
+    UnknownRowFormatVersion(u8),
+    CannotDecodeRow(DecodeError),
+    CannotDropPrimaryColumn(String),
+    CannotDropIndexedColumn(String),
+    UserAlreadyExists(String),
+    UserNotFound(String),
+    CannotReadUsersFile(std::io::Error),
+    CannotWriteUsersFile(std::io::Error),
+    CannotDecodeUsersFile(DecodeError),
 
     //Storage layer errors
     CannotCreateKeyspaceDescriptorFile(types::KeyspaceId, std::io::Error),
@@ -86,6 +115,8 @@ pub enum SimpleDbError {
     CannotReadKeyspacesDirectories(std::io::Error),
     CannotReadKeyspaceFile(types::KeyspaceId, std::io::Error),
     CannotCreateKeyspaceDirectory(types::KeyspaceId, std::io::Error),
+    CannotDeleteKeyspace(types::KeyspaceId, std::io::Error),
+    CannotWriteTruncateFile(std::io::Error),
     CannotCreateWal(types::KeyspaceId, types::MemtableId, std::io::Error),
     CannotWriteWalEntry(types::KeyspaceId, types::MemtableId, std::io::Error),
     CannotReadWalEntries(types::KeyspaceId, types::MemtableId, std::io::Error),
@@ -102,15 +133,28 @@ pub enum SimpleDbError {
     CannotDecodeSSTable(types::KeyspaceId, types::SSTableId, SSTableCorruptedPart, DecodeError),
     CannotDeleteSSTable(types::KeyspaceId, types::SSTableId, std::io::Error),
     CannotCreateSSTableFile(types::KeyspaceId, types::SSTableId, std::io::Error),
+    CorruptedFiles(Vec<SimpleDbError>), //Found when verifying the storage files
     CannotCreateTransactionLog(std::io::Error),
     CannotWriteTransactionLogEntry(std::io::Error),
     CannotReadTransactionLogEntries(std::io::Error),
     CannotDecodeTransactionLogEntry(DecodeError),
     CannotResetTransactionLog(std::io::Error),
+    SavepointNotFound(String),
+    CannotRollbackToSavepoint(String),
+    TransactionAborted(TxnId), //Rolledback because it exceeded the transaction timeout
+    DeadlockDetected(TxnId), //Rolledback transaction, chosen to break a cycle of transactions waiting on each other's keys
     CannotInitTemporarySpaces(std::io::Error),
     CannotCreateTemporarySpace(std::io::Error),
     CannotCreateTemporaryFile(std::io::Error),
     CannotGetTemporaryFile(std::io::Error),
+    CannotCreateSnapshot(String, std::io::Error), //Snapshot path
+    CannotRestoreSnapshot(String, std::io::Error), //Snapshot path
+    NullConstraintViolation(String), //Column name
+    CheckConstraintViolation(String), //Table name
+    ColumnUsedByCheck(String), //Column name
+    InvalidOption(String), //Option name
+    PrimaryKeyAlreadyExists(String), //Table name
+    QueryTimeout(u64), //Elapsed milliseconds when the query was aborted
 
     //This error cannot be returned to the final user,
     //It will only be used internally in the storage engine code
@@ -186,6 +230,13 @@ impl Debug for SimpleDbError {
             SimpleDbError::CannotCreateSSTableFile(keyspace_id, sstable_id, io_error) => {
                 write!(f, "Cannot create SSTable file. SSTable ID: {} Error: {}. Keyspace ID: {}", sstable_id, io_error, keyspace_id)
             }
+            SimpleDbError::CorruptedFiles(corrupted_parts) => {
+                write!(f, "Found {} corrupted parts:", corrupted_parts.len())?;
+                for corrupted_part in corrupted_parts {
+                    write!(f, "\n{:?}", corrupted_part)?;
+                }
+                Ok(())
+            }
             SimpleDbError::CannotCreateTransactionLog(io_error) => {
                 write!(f, "Cannot create transaction log file. Error: {}", io_error)
             }
@@ -270,6 +321,33 @@ impl Debug for SimpleDbError {
             SimpleDbError::MalformedQuery(message) => {
                 write!(f, "Malformed query: {}", message)
             }
+            SimpleDbError::InvalidBatchStatement(statement_index, error) => {
+                write!(f, "Statement {} of the batch: {:?}", statement_index, error)
+            }
+            SimpleDbError::MalformedNumber(location, message) => {
+                write!(f, "Malformed number at line {} and index {} Message: {}", location.line, location.column_index, message)
+            }
+            SimpleDbError::MalformedString(location, message) => {
+                write!(f, "Malformed string at line {} and index {} Message: {}", location.line, location.column_index, message)
+            }
+            SimpleDbError::UniqueConstraintViolation(column_name) => {
+                write!(f, "Unique constraint violated. Another row has the same value in column: {}", column_name)
+            }
+            SimpleDbError::SavepointNotFound(name) => {
+                write!(f, "Savepoint not found: {}", name)
+            }
+            SimpleDbError::CannotRollbackToSavepoint(name) => {
+                write!(f, "Cannot rollback to savepoint {}. Some of its writes have already been flushed to disk, the transaction should be rolledback", name)
+            }
+            SimpleDbError::TransactionAborted(txn_id) => {
+                write!(f, "Transaction {} has been aborted because it exceeded the transaction timeout", txn_id)
+            }
+            SimpleDbError::DeadlockDetected(txn_id) => {
+                write!(f, "Transaction {} has been aborted to resolve a deadlock with other transactions", txn_id)
+            }
+            SimpleDbError::InvalidIdentifier(identifier) => {
+                write!(f, "Invalid identifier: \"{}\". It should start with a letter and only contain letters, numbers or _", identifier)
+            }
             SimpleDbError::DatabaseNotFound(database) => {
                 write!(f, "Database not found: {}", database)
             }
@@ -282,6 +360,54 @@ impl Debug for SimpleDbError {
             SimpleDbError::FullScanNotAllowed() => {
                 write!(f, "Full scan is not allowed")
             }
+            SimpleDbError::CannotCreateSnapshot(path, io_error) => {
+                write!(f, "Cannot create snapshot in {}. IO Error: {}", path, io_error)
+            }
+            SimpleDbError::CannotRestoreSnapshot(path, io_error) => {
+                write!(f, "Cannot restore snapshot from {}. IO Error: {}", path, io_error)
+            }
+            SimpleDbError::NullConstraintViolation(column_name) => {
+                write!(f, "Not null constraint violated. Column {} cannot be NULL", column_name)
+            }
+            SimpleDbError::ColumnUsedByCheck(column_name) => {
+                write!(f, "Column {} cannot be dropped or renamed because it is used by a CHECK constraint", column_name)
+            }
+            SimpleDbError::InvalidOption(option_name) => {
+                write!(f, "Invalid value for option {}", option_name)
+            }
+            SimpleDbError::CheckConstraintViolation(table_name) => {
+                write!(f, "Check constraint violated. The row doesn't satisfy a check of table {}", table_name)
+            }
+            SimpleDbError::PrimaryKeyAlreadyExists(table_name) => {
+                write!(f, "Primary key already exists in table {}", table_name)
+            }
+            SimpleDbError::AccessDenied(user_name, database_name) => {
+                write!(f, "Access denied. User {} is not allowed to access database {}", user_name, database_name)
+            }
+            SimpleDbError::UserAlreadyExists(user_name) => {
+                write!(f, "User {} already exists", user_name)
+            }
+            SimpleDbError::UserNotFound(user_name) => {
+                write!(f, "User {} not found", user_name)
+            }
+            SimpleDbError::CannotReadUsersFile(io_error) => {
+                write!(f, "Cannot read users file. IO Error: {}", io_error)
+            }
+            SimpleDbError::CannotWriteUsersFile(io_error) => {
+                write!(f, "Cannot write users file. IO Error: {}", io_error)
+            }
+            SimpleDbError::CannotDecodeUsersFile(decode_error) => {
+                write!(f, "Cannot decode users file. Error: {}", decode_error_to_message(&decode_error))
+            }
+            SimpleDbError::InvalidPasswordHash(message) => {
+                write!(f, "Invalid server password hash: {}", message)
+            }
+            SimpleDbError::TooManyConnections(max_connections) => {
+                write!(f, "Too many connections. The server accepts at most {} connections", max_connections)
+            }
+            SimpleDbError::QueryTimeout(elapsed_ms) => {
+                write!(f, "Query was cancelled after {} ms", elapsed_ms)
+            }
             SimpleDbError::CannotCreateDatabaseFolder(database_name, io_error) => {
                 write!(f, "Cannot create database {} folder. IO Error: {}", database_name, io_error)
             }
@@ -300,12 +426,21 @@ impl Debug for SimpleDbError {
             SimpleDbError::NetworkError(e) => {
                 write!(f, "Network error: {}", e)
             }
+            SimpleDbError::InvalidTlsConfiguration(message) => {
+                write!(f, "Invalid TLS configuration: {}", message)
+            }
             SimpleDbError::RangeScanNotAllowed() => {
                 write!(f, "Range scan not allowed")
             }
             SimpleDbError::IndexAlreadyExists(keyspace_id, column_name) => {
                 write!(f, "Index with name: {} already exists. Keyspace ID: {}", column_name, keyspace_id)
             }
+            SimpleDbError::IndexCreationCancelled(columns_names) => {
+                write!(f, "Creation of index on {} has been cancelled", columns_names)
+            }
+            SimpleDbError::IndexCreationInterrupted(columns_names) => {
+                write!(f, "Creation of index on {} stopped without finishing", columns_names)
+            }
             SimpleDbError::IndexNotFound(column_id) => {
                 write!(f, "Index not found on column ID: {}", column_id)
             }
@@ -315,6 +450,12 @@ impl Debug for SimpleDbError {
             SimpleDbError::IllegalTypeOperation(message) => {
                 write!(f, "{}", message)
             }
+            SimpleDbError::ArithmeticOverflow => {
+                write!(f, "Arithmetic operation overflowed")
+            }
+            SimpleDbError::DivisionByZero => {
+                write!(f, "Division by zero")
+            }
             SimpleDbError::CannotInitTemporarySpaces(io_error) => {
                 write!(f, "Cannot innit temporary space: {}", io_error)
             }
@@ -339,6 +480,36 @@ impl Debug for SimpleDbError {
             SimpleDbError::CannotReadReverseFile(io_error) => {
                 write!(f, "Cannot read temporary file: {}", io_error)
             }
+            SimpleDbError::TransactionAlreadyFinished(txn_id) => {
+                write!(f, "Transaction {} has already been committed or rolledback", txn_id)
+            }
+            SimpleDbError::TableInUse(table_name) => {
+                write!(f, "Table {} cannot be dropped while there are active transactions", table_name)
+            }
+            SimpleDbError::CannotDeleteTableDescriptor(keyspace_id, io_error) => {
+                write!(f, "Cannot delete table descriptor. IO Error: {}. Keyspace ID: {}", io_error, keyspace_id)
+            }
+            SimpleDbError::CannotDropPrimaryColumn(column_name) => {
+                write!(f, "Column {} cannot be dropped because it is the primary column", column_name)
+            }
+            SimpleDbError::CannotDropIndexedColumn(column_name) => {
+                write!(f, "Column {} cannot be dropped because it is indexed. Drop its index first", column_name)
+            }
+            SimpleDbError::TooManyColumns(table_name, max_columns) => {
+                write!(f, "Table {} cannot have more than {} columns", table_name, max_columns)
+            }
+            SimpleDbError::UnknownRowFormatVersion(format_version) => {
+                write!(f, "Unknown row format version: {}", format_version)
+            }
+            SimpleDbError::CannotDecodeRow(decode_error) => {
+                write!(f, "Cannot decode row. Error: {}", decode_error_to_message(decode_error))
+            }
+            SimpleDbError::CannotDeleteKeyspace(keyspace_id, io_error) => {
+                write!(f, "Cannot delete keyspace files. IO Error: {}. Keyspace ID: {}", io_error, keyspace_id)
+            }
+            SimpleDbError::CannotWriteTruncateFile(io_error) => {
+                write!(f, "Cannot write truncate file. IO Error: {}", io_error)
+            }
         }
     }
 }
@@ -349,6 +520,7 @@ impl SimpleDbError {
             SimpleDbError::RangeScanNotAllowed() => 2,
             SimpleDbError::IllegalToken(_, _) => 3,
             SimpleDbError::MalformedQuery(_) => 4,
+            SimpleDbError::InvalidBatchStatement(_, error) => error.serialize(),
             SimpleDbError::FullScanNotAllowed() => 5,
             SimpleDbError::InvalidContext(_) => 6,
             SimpleDbError::ColumnNotFound(_) => 7,
@@ -419,6 +591,46 @@ impl SimpleDbError {
             SimpleDbError::CannotGetTemporaryFile(_) => 72,
             SimpleDbError::CannotWriteReverseFile(_) => 73,
             SimpleDbError::CannotReadReverseFile(_) => 74,
+            SimpleDbError::TransactionAlreadyFinished(_) => 75,
+            SimpleDbError::TableInUse(_) => 76,
+            SimpleDbError::CannotDeleteTableDescriptor(_, _) => 77,
+            SimpleDbError::CannotDeleteKeyspace(_, _) => 78,
+            SimpleDbError::TooManyColumns(_, _) => 79,
+            SimpleDbError::ArithmeticOverflow => 80,
+            SimpleDbError::DivisionByZero => 81,
+            SimpleDbError::CorruptedFiles(_) => 82,
+            SimpleDbError::UnknownRowFormatVersion(_) => 83,
+            SimpleDbError::MalformedNumber(_, _) => 84,
+            SimpleDbError::UniqueConstraintViolation(_) => 85,
+            SimpleDbError::InvalidIdentifier(_) => 86,
+            SimpleDbError::SavepointNotFound(_) => 87,
+            SimpleDbError::CannotRollbackToSavepoint(_) => 88,
+            SimpleDbError::TransactionAborted(_) => 89,
+            SimpleDbError::InvalidTlsConfiguration(_) => 90,
+            SimpleDbError::CannotDropPrimaryColumn(_) => 91,
+            SimpleDbError::CannotDropIndexedColumn(_) => 92,
+            SimpleDbError::MalformedString(_, _) => 93,
+            SimpleDbError::CannotCreateSnapshot(_, _) => 94,
+            SimpleDbError::CannotRestoreSnapshot(_, _) => 95,
+            SimpleDbError::NullConstraintViolation(_) => 96,
+            SimpleDbError::CheckConstraintViolation(_) => 97,
+            SimpleDbError::PrimaryKeyAlreadyExists(_) => 98,
+            SimpleDbError::QueryTimeout(_) => 99,
+            SimpleDbError::TooManyConnections(_) => 100,
+            SimpleDbError::InvalidPasswordHash(_) => 101,
+            SimpleDbError::AccessDenied(_, _) => 102,
+            SimpleDbError::UserAlreadyExists(_) => 103,
+            SimpleDbError::UserNotFound(_) => 104,
+            SimpleDbError::CannotReadUsersFile(_) => 105,
+            SimpleDbError::CannotWriteUsersFile(_) => 106,
+            SimpleDbError::CannotDecodeUsersFile(_) => 107,
+            SimpleDbError::DeadlockDetected(_) => 108,
+            SimpleDbError::IndexCreationCancelled(_) => 109,
+            SimpleDbError::ColumnUsedByCheck(_) => 110,
+            SimpleDbError::InvalidOption(_) => 111,
+            SimpleDbError::CannotDecodeRow(_) => 112,
+            SimpleDbError::CannotWriteTruncateFile(_) => 113,
+            SimpleDbError::IndexCreationInterrupted(_) => 114,
         }
     }
 }
@@ -448,6 +660,7 @@ fn sstable_decode_error_to_message(
         SSTableCorruptedPart::BlockMetadata => "block metadata".to_string(),
         SSTableCorruptedPart::BloomFilter => "bloom filter".to_string(),
         SSTableCorruptedPart::Block(block_id) => format!("block ID {}", block_id),
+        SSTableCorruptedPart::Footer => "footer".to_string(),
     };
 
     message.push_str(format!("Cannot decode SSTable {}. SSTable ID: {}. Error: {}", corrupted_part,
@@ -483,5 +696,11 @@ fn decode_error_type_to_message(decode_error_type: &DecodeErrorType) -> String {
         DecodeErrorType::UnknownFlag(unknown_flgag) => {
             format!("Unknown flag {}", unknown_flgag)
         },
+        DecodeErrorType::Decompression(message) => {
+            format!("Cannot decompress: {}", message)
+        },
+        DecodeErrorType::UnsortedKeys(index) => {
+            format!("Key at index {} is lower than the previous one", index)
+        },
     }
 }
\ No newline at end of file
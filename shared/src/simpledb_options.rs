@@ -1,20 +1,40 @@
 use std::sync::Arc;
 use bytes::Bytes;
 use serde::{Deserialize, Serialize};
-use crate::{Flag, Type};
+use crate::{Flag, SimpleDbError, Type};
 
 #[derive(Clone, Copy, Serialize, Deserialize)]
 pub enum CompactionStrategy {
     SimpleLeveled,
     Tiered,
+    SizeTiered, //Compacts together SSTables of similar size. Better suited for write heavy workloads
 }
 
 #[derive(Clone, Copy, Serialize, Deserialize)]
 pub enum DurabilityLevel {
     Strong, //Writes to memtable after WAL entry has been written to disk (using fsync)
+    //Same guarantee as Strong, but WAL entries are fsynced in groups of SimpleDbOptions::wal_group_commit_n_entries
+    //entries, or after waiting SimpleDbOptions::wal_group_commit_interval_ms. Writers wait until their group is fsynced.
+    //Higher write throughput than Strong when there are many concurrent writers
+    Group,
     Weak, //Writes to memtable without waiting for WAL write to complete
 }
 
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Debug)]
+pub enum SSTableCompression {
+    None,
+    Lz4, //Faster than other codecs, with a lower compression ratio
+}
+
+//Returns the time in which the value was inserted (millis since epoch). None if it is unknown
+pub type StorageValueInsertTimeFn = fn(
+    value: &Bytes,
+    keyspace_flags: Flag,
+) -> Option<u64>;
+
+//Returns the current time (millis since epoch). Used to expire the rows of tables with TTL
+pub type ClockFn = fn() -> u64;
+
 //a is before b, (example b has greater timestamp (txn_id))
 pub type StorageValueMergerFn = fn(
     a: &Bytes,
@@ -24,6 +44,8 @@ pub type StorageValueMergerFn = fn(
 ) -> StorageValueMergeResult;
 
 #[derive(Clone, Serialize, Deserialize)]
+//Options missing in the config file, like the ones added in newer versions, take their default value
+#[serde(default)]
 pub struct SimpleDbOptions {
     //Common/Shared option
     #[serde(skip)]
@@ -31,29 +53,59 @@ pub struct SimpleDbOptions {
     pub use_debug_logging: bool,
 
     //Server layer options
-    pub server_password: String,
+    pub server_password: String, //Hash generated with the server hash-password command, or a legacy plaintext password
     pub server_port: u16,
+    pub server_rows_per_chunk: usize, //Query results are sent to the client in chunks of this nº of rows
+    pub server_tls_cert_path: String, //PEM certificate chain. Empty disables TLS, connections will be plain TCP
+    pub server_tls_key_path: String, //PEM private key of the certificate
+    pub server_tls_handshake_timeout_ms: u64, //Connections that don't complete the TLS handshake in this time get closed. 0 means no timeout
+    pub server_max_connections: usize, //Connections being served at the same time. 0 means no limit
+    pub server_max_queued_connections: usize, //Connections waiting for a free slot. Further connections will be rejected
+    pub server_queued_connection_timeout_ms: u64, //Queued connections waiting longer than this get rejected. 0 means no timeout
 
     //DB Layer options
     pub sort_page_size_bytes: usize,
+    pub in_memory_sort_max_bytes: usize, //Sorts of fewer rows than this nº of serialized bytes are done in memory, without temporary files
+    pub primary_row_cache_n_entries: usize, //0 disables the cache
+    pub select_cache_n_entries: usize, //0 disables the cache
+    pub max_columns_per_table: usize,
+    pub index_scan_max_selectivity: f64, //Secondary index scans estimated to read a bigger fraction of the table rows are replaced by full scans
+    pub parallel_full_scan_n_threads: usize, //Nº of threads used by full scans of queries that don't need the rows in order. 0 disables it
 
     //Storage engine layer options
     pub simple_leveled_compaction_options: SimpleLeveledCompactionOptions,
     #[serde(skip)]
     pub storage_value_merger: Option<StorageValueMergerFn>,
+    #[serde(skip)]
+    pub storage_value_insert_time: Option<StorageValueInsertTimeFn>,
+    #[serde(skip)]
+    pub clock: Option<ClockFn>, //None uses the system time
     pub tiered_compaction_options: TieredCompactionOptions,
+    pub size_tiered_compaction_options: SizeTieredCompactionOptions,
     pub compaction_strategy: CompactionStrategy,
     pub compaction_task_frequency_ms: usize,
+    pub max_concurrent_compactions: usize, //Compactions of non overlapping levels run in parallel, up to this nº
+    pub compaction_max_bytes_per_second: usize, //Write throughput shared by all running compactions. 0 means no limit
+    pub transaction_timeout_ms: usize, //Active transactions older than this will be rolledback. 0 disables it
     pub n_cached_blocks_per_sstable: usize,
     pub durability_level: DurabilityLevel,
+    pub wal_group_commit_n_entries: usize, //Only used with DurabilityLevel::Group
+    pub wal_group_commit_interval_ms: usize, //Only used with DurabilityLevel::Group. Max time a writer waits for its group
     pub memtable_max_size_bytes: usize,
     pub max_memtables_inactive: usize,
     pub bloom_filter_n_entries: usize,
-    pub block_size_bytes: usize,
+    pub bloom_filter_bits_per_key: usize, //Bloom filters are sized from the nº of keys of the SSTable. 0 uses bloom_filter_n_entries
+    pub block_size_bytes: usize, //At most 65535 bytes, as offsets inside a block are stored in 2 bytes. Checked when the storage is opened
+    pub block_restart_interval: usize, //Nº of prefix compressed keys between keys stored in full inside a block
+    pub sstable_compression: SSTableCompression, //Codec of the blocks of new SSTables. Every SSTable stores its codec
     pub sst_size_bytes: usize,
+    pub manifest_checkpoint_n_operations: usize, //Completed operations are removed from the manifest every this nº of operations. 0 disables it
+    pub verify_on_open: bool, //Decodes every file at startup, failing if any of them is corrupted
+    pub quarantine_corrupted_sstables: bool, //Corrupted blocks are skipped by scans, and its SSTable file is moved aside, instead of failing the scan
 }
 
 #[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
 pub struct TieredCompactionOptions {
     pub min_levels_trigger_size_ratio: usize,
     pub max_size_amplification: usize,
@@ -61,6 +113,17 @@ pub struct TieredCompactionOptions {
 }
 
 #[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SizeTieredCompactionOptions {
+    pub min_threshold: usize, //Min nº of SSTables in a bucket to compact it
+    pub max_threshold: usize, //Max nº of SSTables compacted at once
+    pub bucket_low: f64, //SSTables smaller than bucket_low * bucket average size won't be added to the bucket
+    pub bucket_high: f64, //SSTables bigger than bucket_high * bucket average size won't be added to the bucket
+    pub min_sstable_size_bytes: usize, //SSTables smaller than this are added to the same bucket
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SimpleLeveledCompactionOptions {
     pub level0_file_num_compaction_trigger: usize,
     pub size_ratio_percent: usize,
@@ -78,21 +141,68 @@ impl Default for SimpleDbOptions {
         SimpleDbOptions {
             simple_leveled_compaction_options: SimpleLeveledCompactionOptions::default(),
             tiered_compaction_options: TieredCompactionOptions::default(),
+            size_tiered_compaction_options: SizeTieredCompactionOptions::default(),
             compaction_strategy: CompactionStrategy::SimpleLeveled,
             durability_level: DurabilityLevel::Strong,
+            wal_group_commit_n_entries: 64,
+            wal_group_commit_interval_ms: 10,
             base_path: String::from("ignored"),
             compaction_task_frequency_ms: 100, //100ms
+            max_concurrent_compactions: 1,
+            compaction_max_bytes_per_second: 0,
+            transaction_timeout_ms: 0,
             memtable_max_size_bytes: 1048576, //1Mb
             n_cached_blocks_per_sstable: 8, //Expect power of two
             bloom_filter_n_entries: 32768, //4kb of bloom filter so it fits in a page
+            bloom_filter_bits_per_key: 10,
             storage_value_merger: None,
+            storage_value_insert_time: None,
+            clock: None,
             sst_size_bytes: 268435456, //256 MB ~ 64 blocks
+            verify_on_open: false,
+            quarantine_corrupted_sstables: false,
+            manifest_checkpoint_n_operations: 1024,
             max_memtables_inactive: 8,
             sort_page_size_bytes: 4096, //Kb
+            in_memory_sort_max_bytes: 1048576, //1Mb
+            primary_row_cache_n_entries: 0,
+            select_cache_n_entries: 0,
+            max_columns_per_table: 1024,
+            index_scan_max_selectivity: 0.3,
+            parallel_full_scan_n_threads: 0,
             block_size_bytes: 4096, //4kb
+            block_restart_interval: 16,
+            sstable_compression: SSTableCompression::None,
             use_debug_logging: true,
             server_port: 8888,
-            server_password: String::from("123456"),
+            server_rows_per_chunk: 1024,
+            server_tls_cert_path: String::from(""),
+            server_tls_key_path: String::from(""),
+            server_tls_handshake_timeout_ms: 10000, //10s
+            server_max_connections: 1024,
+            server_max_queued_connections: 64,
+            server_queued_connection_timeout_ms: 30000, //30s
+            //Hash of 123456. Legacy plaintext passwords are also accepted
+            server_password: String::from("pbkdf2-sha256$100000$d3709082a0f8d5e81772b25c2e778e9b$c19866a4af4c0380d2d2523110ba1f050b45fe529cc33a2452270d42bae0df17"),
+        }
+    }
+}
+
+impl SimpleDbOptions {
+    //Rejects the options that would write files that cannot be read back
+    pub fn validate(&self) -> Result<(), SimpleDbError> {
+        //Offsets inside a block are stored in 2 bytes
+        if self.block_size_bytes == 0 || self.block_size_bytes > u16::MAX as usize {
+            return Err(SimpleDbError::InvalidOption(String::from("block_size_bytes")));
+        }
+
+        Ok(())
+    }
+
+    pub fn current_time_millis(&self) -> u64 {
+        match self.clock {
+            Some(clock_fn) => clock_fn(),
+            None => crate::utils::current_time_millis(),
         }
     }
 }
@@ -124,16 +234,37 @@ impl SimpleDbOptionsBuilder {
         self
     }
 
+    pub fn size_tiered_compaction_options(&mut self, value: SizeTieredCompactionOptions) -> &mut SimpleDbOptionsBuilder {
+        self.options.size_tiered_compaction_options = value;
+        self
+    }
+
     pub fn storage_value_merger(&mut self, storage_value_merger_fn: StorageValueMergerFn) -> &mut SimpleDbOptionsBuilder {
         self.options.storage_value_merger = Some(storage_value_merger_fn);
         self
     }
 
+    pub fn storage_value_insert_time(&mut self, storage_value_insert_time_fn: StorageValueInsertTimeFn) -> &mut SimpleDbOptionsBuilder {
+        self.options.storage_value_insert_time = Some(storage_value_insert_time_fn);
+        self
+    }
+
+    pub fn clock(&mut self, clock_fn: ClockFn) -> &mut SimpleDbOptionsBuilder {
+        self.options.clock = Some(clock_fn);
+        self
+    }
+
     pub fn durability_level(&mut self, level: DurabilityLevel) -> &mut SimpleDbOptionsBuilder {
         self.options.durability_level = level;
         self
     }
 
+    pub fn wal_group_commit(&mut self, n_entries: usize, interval_ms: usize) -> &mut SimpleDbOptionsBuilder {
+        self.options.wal_group_commit_n_entries = n_entries;
+        self.options.wal_group_commit_interval_ms = interval_ms;
+        self
+    }
+
     pub fn compaction_strategy(&mut self, value: CompactionStrategy) -> &mut SimpleDbOptionsBuilder {
         self.options.compaction_strategy = value;
         self
@@ -144,6 +275,21 @@ impl SimpleDbOptionsBuilder {
         self
     }
 
+    pub fn max_concurrent_compactions(&mut self, value: usize) -> &mut SimpleDbOptionsBuilder {
+        self.options.max_concurrent_compactions = value;
+        self
+    }
+
+    pub fn compaction_max_bytes_per_second(&mut self, value: usize) -> &mut SimpleDbOptionsBuilder {
+        self.options.compaction_max_bytes_per_second = value;
+        self
+    }
+
+    pub fn transaction_timeout_ms(&mut self, value: usize) -> &mut SimpleDbOptionsBuilder {
+        self.options.transaction_timeout_ms = value;
+        self
+    }
+
     pub fn n_cached_blocks_per_sstable(&mut self, value: usize) -> &mut SimpleDbOptionsBuilder {
         self.options.n_cached_blocks_per_sstable = value;
         self
@@ -164,16 +310,112 @@ impl SimpleDbOptionsBuilder {
         self
     }
 
+    pub fn bloom_filter_bits_per_key(&mut self, value: usize) -> &mut SimpleDbOptionsBuilder {
+        self.options.bloom_filter_bits_per_key = value;
+        self
+    }
+
     pub fn block_size_bytes(&mut self, value: usize) -> &mut SimpleDbOptionsBuilder {
         self.options.block_size_bytes = value;
         self
     }
 
+    pub fn block_restart_interval(&mut self, value: usize) -> &mut SimpleDbOptionsBuilder {
+        self.options.block_restart_interval = value;
+        self
+    }
+
+    pub fn sstable_compression(&mut self, value: SSTableCompression) -> &mut SimpleDbOptionsBuilder {
+        self.options.sstable_compression = value;
+        self
+    }
+
     pub fn sst_size_bytes(&mut self, value: usize) -> &mut SimpleDbOptionsBuilder {
         self.options.sst_size_bytes = value;
         self
     }
 
+    pub fn in_memory_sort_max_bytes(&mut self, value: usize) -> &mut SimpleDbOptionsBuilder {
+        self.options.in_memory_sort_max_bytes = value;
+        self
+    }
+
+    pub fn primary_row_cache_n_entries(&mut self, value: usize) -> &mut SimpleDbOptionsBuilder {
+        self.options.primary_row_cache_n_entries = value;
+        self
+    }
+
+    pub fn select_cache_n_entries(&mut self, value: usize) -> &mut SimpleDbOptionsBuilder {
+        self.options.select_cache_n_entries = value;
+        self
+    }
+
+    pub fn max_columns_per_table(&mut self, value: usize) -> &mut SimpleDbOptionsBuilder {
+        self.options.max_columns_per_table = value;
+        self
+    }
+
+    pub fn index_scan_max_selectivity(&mut self, value: f64) -> &mut SimpleDbOptionsBuilder {
+        self.options.index_scan_max_selectivity = value;
+        self
+    }
+
+    pub fn parallel_full_scan_n_threads(&mut self, value: usize) -> &mut SimpleDbOptionsBuilder {
+        self.options.parallel_full_scan_n_threads = value;
+        self
+    }
+
+    pub fn manifest_checkpoint_n_operations(&mut self, value: usize) -> &mut SimpleDbOptionsBuilder {
+        self.options.manifest_checkpoint_n_operations = value;
+        self
+    }
+
+    pub fn verify_on_open(&mut self, value: bool) -> &mut SimpleDbOptionsBuilder {
+        self.options.verify_on_open = value;
+        self
+    }
+
+    pub fn quarantine_corrupted_sstables(&mut self, value: bool) -> &mut SimpleDbOptionsBuilder {
+        self.options.quarantine_corrupted_sstables = value;
+        self
+    }
+
+    pub fn server_rows_per_chunk(&mut self, value: usize) -> &mut SimpleDbOptionsBuilder {
+        self.options.server_rows_per_chunk = value;
+        self
+    }
+
+    pub fn server_password(&mut self, value: &str) -> &mut SimpleDbOptionsBuilder {
+        self.options.server_password = value.to_string();
+        self
+    }
+
+    pub fn server_tls(&mut self, cert_path: &str, key_path: &str) -> &mut SimpleDbOptionsBuilder {
+        self.options.server_tls_cert_path = cert_path.to_string();
+        self.options.server_tls_key_path = key_path.to_string();
+        self
+    }
+
+    pub fn server_tls_handshake_timeout_ms(&mut self, value: u64) -> &mut SimpleDbOptionsBuilder {
+        self.options.server_tls_handshake_timeout_ms = value;
+        self
+    }
+
+    pub fn server_max_connections(&mut self, value: usize) -> &mut SimpleDbOptionsBuilder {
+        self.options.server_max_connections = value;
+        self
+    }
+
+    pub fn server_max_queued_connections(&mut self, value: usize) -> &mut SimpleDbOptionsBuilder {
+        self.options.server_max_queued_connections = value;
+        self
+    }
+
+    pub fn server_queued_connection_timeout_ms(&mut self, value: u64) -> &mut SimpleDbOptionsBuilder {
+        self.options.server_queued_connection_timeout_ms = value;
+        self
+    }
+
     pub fn base_path(&mut self, value: &str) -> &mut SimpleDbOptionsBuilder {
         self.options.base_path = value.to_string();
         self
@@ -198,6 +440,18 @@ impl Default for TieredCompactionOptions {
     }
 }
 
+impl Default for SizeTieredCompactionOptions {
+    fn default() -> Self {
+        SizeTieredCompactionOptions {
+            min_threshold: 4,
+            max_threshold: 32,
+            bucket_low: 0.5,
+            bucket_high: 1.5,
+            min_sstable_size_bytes: 1048576, //1Mb
+        }
+    }
+}
+
 impl Default for SimpleLeveledCompactionOptions {
     fn default() -> Self {
         SimpleLeveledCompactionOptions {
@@ -1,7 +1,8 @@
 use bytes::Bytes;
 use std::fs;
 use std::fs::DirEntry;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 use crossbeam_skiplist::{SkipMap, SkipSet};
 
 pub fn bytes_to_u8_array_string(bytes: &Bytes) -> String {
@@ -18,6 +19,24 @@ pub fn bytes_to_f64_le(bytes: &Bytes) -> f64 {
     f64::from_le_bytes(byte_array)
 }
 
+//Shortest decimal representation that parses back to the same value. Integral values keep the ".0" suffix,
+//so they are rendered the same way whether the number comes from a query result or from a literal
+pub fn f64_to_string(value: f64) -> String {
+    fp_number_to_string(value.to_string(), value.is_finite())
+}
+
+pub fn f32_to_string(value: f32) -> String {
+    fp_number_to_string(value.to_string(), value.is_finite())
+}
+
+fn fp_number_to_string(mut formatted: String, is_finite: bool) -> String {
+    if is_finite && !formatted.contains('.') {
+        formatted.push_str(".0");
+    }
+
+    formatted
+}
+
 pub fn bytes_to_u64_le(bytes: &Bytes) -> u64 {
     u64::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7]])
 }
@@ -71,6 +90,15 @@ pub fn u16_to_u8_le(value: u16, start_index: usize, vector: &mut Vec<u8>) {
     vector[start_index + 1] = (value >> 8 & 0xff) as u8;
 }
 
+pub fn u32_to_u8_le(value: u32, start_index: usize, vector: &mut Vec<u8>) {
+    fill_u8_vec_if_empty(vector, start_index + 3, 0);
+
+    vector[start_index] =     (value & 0xff) as u8;
+    vector[start_index + 1] = (value >> 8 & 0xff) as u8;
+    vector[start_index + 2] = (value >> 16 & 0xff) as u8;
+    vector[start_index + 3] = (value >> 24 & 0xff) as u8;
+}
+
 pub fn overflows_bytes_64(bytes: &Bytes, target_size_bytes: u8) -> bool {
     let byte_array: [u8; 8] = bytes[..8].try_into().expect("slice with incorrect length");
     let u64 = u64::from_le_bytes(byte_array);
@@ -189,6 +217,27 @@ pub fn create_paths(path: &String) -> Result<(), std::io::Error> {
     fs::create_dir_all(path)
 }
 
+//Renames are only durable once the directory containing the file has been fsynced
+pub fn fsync_directory(path: &Path) -> Result<(), std::io::Error> {
+    fs::File::open(path)?.sync_all()
+}
+
+//Copies recursively the contents of from directory into to directory
+pub fn copy_dir_all(from: &Path, to: &Path) -> Result<(), std::io::Error> {
+    fs::create_dir_all(to)?;
+
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            copy_dir_all(&entry.path(), &to.join(entry.file_name()))?;
+        } else {
+            fs::copy(entry.path(), to.join(entry.file_name()))?;
+        }
+    }
+
+    Ok(())
+}
+
 pub fn clone_skipset<V>(source: &SkipSet<V>) -> SkipSet<V>
 where
     V: Clone + Ord + Send + 'static
@@ -217,4 +266,8 @@ where
     }
 
     result
-}
\ No newline at end of file
+}
+
+pub fn current_time_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64
+}
@@ -75,6 +75,15 @@ impl Type {
         }
     }
 
+    //Integer values can be coerced to floating point types, but not the other way around
+    pub fn can_be_coerced_to(&self, target: &Type) -> bool {
+        if self.is_number() && target.is_fp_number() {
+            true
+        } else {
+            self.can_be_casted(target)
+        }
+    }
+
     pub fn is_null(&self) -> bool {
         matches!(self, Type::Null)
     }
@@ -141,6 +150,12 @@ pub struct Value {
     value_bytes: Bytes,
 }
 
+enum LikePatternElement {
+    AnySequence, //%
+    AnyChar, //_
+    Char(char),
+}
+
 impl Value {
     pub fn create(value_bytes: Bytes, value_type: Type) -> Result<Value, SimpleDbError> {
         if !Self::bytes_has_type_format(&value_bytes, &value_type) {
@@ -194,6 +209,19 @@ impl Value {
         }
     }
 
+    //Returns the value with the format of the target type. Returns None if it cannot be coerced
+    pub fn coerce(&self, target: &Type) -> Option<Value> {
+        if !self.value_type.can_be_coerced_to(target) {
+            None
+        } else if self.is_null() {
+            Some(self.clone())
+        } else if target.is_fp_number() {
+            Some(Value::create_f64(self.get_f64().ok()?))
+        } else {
+            Some(Value { value_bytes: self.value_bytes.clone(), value_type: *target })
+        }
+    }
+
     pub fn get_bytes(&self) -> &Bytes {
         &self.value_bytes
     }
@@ -242,6 +270,26 @@ impl Value {
         }
     }
 
+    pub fn get_u64(&self) -> Result<u64, SimpleDbError> {
+        match self.value_type {
+            Type::U64 => Ok(utils::bytes_to_u64_le(&self.value_bytes)),
+            Type::U32 => Ok(utils::bytes_to_u32_le(&self.value_bytes) as u64),
+            Type::U16 => Ok(utils::bytes_to_u16_le(&self.value_bytes) as u64),
+            Type::U8 => Ok(self.value_bytes[0] as u64),
+            Type::F64 |
+            Type::F32 |
+            Type::I64 |
+            Type::I32 |
+            Type::I16 |
+            Type::I8 |
+            Type::Boolean => Ok(self.get_i64()? as u64),
+            Type::String |
+            Type::Date |
+            Type::Blob |
+            Type::Null => Err(SimpleDbError::IllegalTypeOperation("Expected number type from value")),
+        }
+    }
+
     pub fn get_string(&self) -> Result<String, SimpleDbError> {
         match self.value_type {
             Type::String => Ok(String::from_utf8(self.value_bytes.to_vec()).unwrap()),
@@ -275,9 +323,9 @@ impl Value {
             Type::I32 => utils::bytes_to_i32_le(&self.value_bytes).to_string(),
             Type::U64 => utils::bytes_to_u64_le(&self.value_bytes).to_string(),
             Type::I64 => utils::bytes_to_i64_le(&self.value_bytes).to_string(),
-            Type::F32 => utils::bytes_to_f32_le(&self.value_bytes).to_string(),
-            Type::F64 => utils::bytes_to_f64_le(&self.value_bytes).to_string(),
-            Type::Boolean => if self.value_bytes[0] == 0x01 { String::from("true") } else { String::from("false") },
+            Type::F32 => utils::f32_to_string(utils::bytes_to_f32_le(&self.value_bytes)),
+            Type::F64 => utils::f64_to_string(utils::bytes_to_f64_le(&self.value_bytes)),
+            Type::Boolean => if self.value_bytes[0] != 0x00 { String::from("true") } else { String::from("false") },
             Type::String => String::from_utf8(self.value_bytes.to_vec()).unwrap(),
             Type::Date => todo!(),
             Type::Blob => format!("{:02X?}", self.value_bytes),
@@ -327,6 +375,11 @@ impl Value {
         }
     }
 
+    //NaN and infinite values are not allowed to be stored, as they don't have a total order
+    pub fn is_non_finite_fp_number(&self) -> bool {
+        self.is_fp_number() && !self.get_f64().map(|value| value.is_finite()).unwrap_or(true)
+    }
+
     pub fn is_comparable(&self, other: &Value) -> bool {
         if self.is_number() && other.is_number() {
             true
@@ -352,61 +405,177 @@ impl Value {
         }
     }
 
+    //% matches any sequence of characters, _ matches any single character and \ escapes the next character
+    pub fn like(&self, pattern: &Value) -> Result<bool, SimpleDbError> {
+        if !self.is_string() || !pattern.is_string() {
+            return Err(SimpleDbError::IllegalTypeOperation("LIKE can only be applied to strings"));
+        }
+
+        let pattern = Self::parse_like_pattern(&pattern.get_string()?);
+        let value: Vec<char> = self.get_string()?.chars().collect();
+        let mut value_index = 0;
+        let mut pattern_index = 0;
+        //Last % found (pattern index, value index), used to backtrack
+        let mut last_any_sequence: Option<(usize, usize)> = None;
+
+        while value_index < value.len() {
+            let current_matches = match pattern.get(pattern_index) {
+                Some(LikePatternElement::AnyChar) => true,
+                Some(LikePatternElement::Char(char)) => *char == value[value_index],
+                _ => false
+            };
+
+            if current_matches {
+                value_index += 1;
+                pattern_index += 1;
+            } else if matches!(pattern.get(pattern_index), Some(LikePatternElement::AnySequence)) {
+                last_any_sequence = Some((pattern_index, value_index));
+                pattern_index += 1;
+            } else if let Some((any_sequence_pattern_index, any_sequence_value_index)) = last_any_sequence {
+                //Make the last % consume one more character
+                last_any_sequence = Some((any_sequence_pattern_index, any_sequence_value_index + 1));
+                pattern_index = any_sequence_pattern_index + 1;
+                value_index = any_sequence_value_index + 1;
+            } else {
+                return Ok(false);
+            }
+        }
+
+        Ok(pattern[pattern_index..].iter().all(|it| matches!(it, LikePatternElement::AnySequence)))
+    }
+
+    //Same as like(), but ignoring case. Only ASCII characters are folded, other characters must match exactly
+    pub fn ilike(&self, pattern: &Value) -> Result<bool, SimpleDbError> {
+        if !self.is_string() || !pattern.is_string() {
+            return Err(SimpleDbError::IllegalTypeOperation("ILIKE can only be applied to strings"));
+        }
+
+        Value::create_string(self.get_string()?.to_ascii_lowercase())
+            .like(&Value::create_string(pattern.get_string()?.to_ascii_lowercase()))
+    }
+
+    //Only ASCII characters are folded, other characters must match exactly
+    pub fn eq_ignore_ascii_case(&self, other: &Value) -> Result<bool, SimpleDbError> {
+        if !self.is_string() || !other.is_string() {
+            return Err(SimpleDbError::IllegalTypeOperation("Case insensitive comparison can only be applied to strings"));
+        }
+
+        Ok(self.get_string()?.eq_ignore_ascii_case(&other.get_string()?))
+    }
+
+    //Returns the literal characters of a LIKE pattern before the first wildcard
+    pub fn get_like_pattern_prefix(&self) -> Result<String, SimpleDbError> {
+        Ok(Self::parse_like_pattern(&self.get_string()?).iter()
+            .map_while(|it| match it {
+                LikePatternElement::Char(char) => Some(*char),
+                _ => None
+            })
+            .collect())
+    }
+
+    fn parse_like_pattern(pattern: &str) -> Vec<LikePatternElement> {
+        let mut elements = Vec::new();
+        let mut chars = pattern.chars();
+
+        while let Some(char) = chars.next() {
+            elements.push(match char {
+                '%' => LikePatternElement::AnySequence,
+                '_' => LikePatternElement::AnyChar,
+                '\\' => LikePatternElement::Char(chars.next().unwrap_or('\\')),
+                other => LikePatternElement::Char(other),
+            });
+        }
+
+        elements
+    }
+
     pub fn eq_bytes(&self, other: &Bytes) -> bool {
         self.get_bytes().eq(other)
     }
 
     pub fn gt_bytes(&self, other: &Bytes) -> bool {
         let other_value = Value::create(other.clone(), self.value_type.clone()).unwrap();
-        self.comparation_op(&other_value, |a, b| a > b, |a, b| a > b, |a, b| a > b)
+        self.comparation_op(&other_value, |a, b| a > b, |a, b| a > b, |a, b| a > b, |a, b| a > b)
             .unwrap()
     }
 
     pub fn ge_bytes(&self, other: &Bytes) -> bool {
         let other_value = Value::create(other.clone(), self.value_type.clone()).unwrap();
-        self.comparation_op(&other_value, |a, b| a >= b, |a, b| a >= b, |a, b| a >= b)
+        self.comparation_op(&other_value, |a, b| a >= b, |a, b| a >= b, |a, b| a >= b, |a, b| a >= b)
             .unwrap()
     }
 
     pub fn lt_bytes(&self, other: &Bytes) -> bool {
         let other_value = Value::create(other.clone(), self.value_type.clone()).unwrap();
-        self.comparation_op(&other_value, |a, b| a < b, |a, b| a < b, |a, b| a < b)
+        self.comparation_op(&other_value, |a, b| a < b, |a, b| a < b, |a, b| a < b, |a, b| a < b)
             .unwrap()
     }
 
     pub fn le_bytes(&self, other: &Bytes) -> bool {
         let other_value = Value::create(other.clone(), self.value_type.clone()).unwrap();
-        self.comparation_op(&other_value, |a, b| a <= b, |a, b| a <= b, |a, b| a <= b)
+        self.comparation_op(&other_value, |a, b| a <= b, |a, b| a <= b, |a, b| a <= b, |a, b| a <= b)
             .unwrap()
     }
 
+    //Strings are concatenated. If only one of the values is a string, the other one has to be a number,
+    //which will be converted to string. For example: "a" + 1 produces "a1"
     pub fn add(&self, other: &Value) -> Result<Value, SimpleDbError> {
-        self.arithmetic_op(other, |a, b| a + b, |a, b| a + b)
+        self.arithmetic_op(other, |a, b| a + b, |a, b| a.checked_add(b), |a, b| Some(format!("{}{}", a, b)))
     }
 
     pub fn subtract(&self, other: &Value) -> Result<Value, SimpleDbError> {
-        self.arithmetic_op(other, |a, b| a - b, |a, b| a - b)
+        self.arithmetic_op(other, |a, b| a - b, |a, b| a.checked_sub(b), |_, _| None)
     }
 
     pub fn multiply(&self, other: &Value) -> Result<Value, SimpleDbError> {
-        self.arithmetic_op(other, |a, b| a * b, |a, b| a * b)
+        self.arithmetic_op(other, |a, b| a * b, |a, b| a.checked_mul(b), |_, _| None)
     }
 
+    //Floating point division by zero follows IEEE 754, only integer division by zero is rejected
     pub fn divide(&self, other: &Value) -> Result<Value, SimpleDbError> {
-        self.arithmetic_op(other, |a, b| a / b, |a, b| a / b)
+        if self.is_integer_number() && other.is_integer_number() && other.get_i64()? == 0 {
+            return Err(SimpleDbError::DivisionByZero);
+        }
+
+        self.arithmetic_op(other, |a, b| a / b, |a, b| a.checked_div(b), |_, _| None)
     }
 
-    fn arithmetic_op<FpOp, IntOp>(&self, other: &Value, fp_op: FpOp, int_op: IntOp) -> Result<Value, SimpleDbError>
+    pub fn modulo(&self, other: &Value) -> Result<Value, SimpleDbError> {
+        if self.is_integer_number() && other.is_integer_number() && other.get_i64()? == 0 {
+            return Err(SimpleDbError::DivisionByZero);
+        }
+
+        self.arithmetic_op(other, |a, b| a % b, |a, b| a.checked_rem(b), |_, _| None)
+    }
+
+    //str_op returns None if the operation is not supported for strings
+    fn arithmetic_op<FpOp, IntOp, StrOp>(
+        &self,
+        other: &Value,
+        fp_op: FpOp,
+        int_op: IntOp,
+        str_op: StrOp
+    ) -> Result<Value, SimpleDbError>
     where
-        IntOp: Fn(i64, i64) -> i64,
+        StrOp: Fn(&String, &String) -> Option<String>,
+        IntOp: Fn(i64, i64) -> Option<i64>,
         FpOp: Fn(f64, f64) -> f64,
     {
+        if (self.is_string() || other.is_string()) &&
+            (self.is_string() || self.is_number()) &&
+            (other.is_string() || other.is_number()) {
+            return str_op(&self.to_string(), &other.to_string())
+                .map(|result| Value::create_string(result))
+                .ok_or(IllegalTypeOperation("Operation not supported for strings"));
+        }
+
         if !self.is_number() && !other.is_number() {
             return Err(IllegalTypeOperation("Only numbers can be added"));
         }
 
         if !self.is_fp_number() && !other.is_fp_number() {
-            let result = int_op(self.get_i64()?, other.get_i64()?);
+            let result = int_op(self.get_i64()?, other.get_i64()?)
+                .ok_or(SimpleDbError::ArithmeticOverflow)?;
             Value::create(Bytes::from(result.to_le_bytes().to_vec()), Type::I64)
         } else {
             let result = fp_op(self.get_f64()?, other.get_f64()?);
@@ -414,16 +583,18 @@ impl Value {
         }
     }
 
-    fn comparation_op<FpOp, IntOp, StrOp>(
+    fn comparation_op<FpOp, IntOp, UintOp, StrOp>(
         &self,
         other: &Value,
         fp_op: FpOp,
         int_op: IntOp,
+        uint_op: UintOp,
         str_op: StrOp
     ) -> Result<bool, SimpleDbError>
     where
         StrOp: Fn(&String, &String) -> bool,
         IntOp: Fn(i64, i64) -> bool,
+        UintOp: Fn(u64, u64) -> bool,
         FpOp: Fn(f64, f64) -> bool,
     {
         if !self.is_comparable(other) {
@@ -436,6 +607,9 @@ impl Value {
             Ok(fp_op(self.get_i64()? as f64, other.get_f64()?))
         } else if self.is_fp_number() && other.is_fp_number() {
             Ok(fp_op(self.get_f64()?, other.get_f64()?))
+        } else if self.value_type.is_unsigned_integer_number() && other.value_type.is_unsigned_integer_number() {
+            //Values above i64::MAX would wrap to negative numbers in the signed path
+            Ok(uint_op(self.get_u64()?, other.get_u64()?))
         } else if self.is_integer_number() && other.is_integer_number() {
             Ok(int_op(self.get_i64()?, other.get_i64()? ))
         } else if self.is_string() && other.is_string() {
@@ -471,11 +645,16 @@ impl Ord for Value {
         if self.get_bytes().eq(other.get_bytes()) {
             return Ordering::Equal;
         }
+        //Blobs are compared byte by byte
+        if matches!(self.value_type, Type::Blob) && matches!(other.value_type, Type::Blob) {
+            return self.get_bytes().cmp(other.get_bytes());
+        }
 
         let is_less = self.comparation_op(
             &other,
             |a, b| a < b,
             |a, b| a < b,
+            |a, b| a < b,
             |a, b| a < b
         ).unwrap();
 
@@ -502,7 +681,8 @@ impl PartialOrd for Value {
 
 #[cfg(test)]
 mod test {
-    use crate::Value;
+    use bytes::Bytes;
+    use crate::{SimpleDbError, Type, Value};
 
     #[test]
     fn compare_strings() {
@@ -523,6 +703,96 @@ mod test {
         assert!(!a.lt(&b));
     }
 
+    #[test]
+    fn compare_unsigned() {
+        let max = Value::create(Bytes::from(u64::MAX.to_le_bytes().to_vec()), Type::U64).unwrap();
+        let one = Value::create(Bytes::from(1u64.to_le_bytes().to_vec()), Type::U64).unwrap();
+        assert_eq!(max.get_u64().unwrap(), u64::MAX);
+        assert!(max.gt_bytes(one.get_bytes()));
+        assert!(one.lt_bytes(max.get_bytes()));
+        assert!(max > one);
+    }
+
+    #[test]
+    fn boolean_to_string() {
+        assert_eq!(Value::create_boolean(true).to_string(), "true");
+        assert_eq!(Value::create_boolean(false).to_string(), "false");
+        //Any non zero byte is true, like in get_boolean()
+        let value = Value::create(Bytes::from(vec![0x02]), Type::Boolean).unwrap();
+        assert_eq!(value.to_string(), "true");
+        assert!(value.get_boolean().unwrap());
+    }
+
+    #[test]
+    fn fp_number_to_string() {
+        assert_eq!(Value::create_f64(1.0).to_string(), "1.0");
+        assert_eq!(Value::create_f64(0.1).to_string(), "0.1");
+        assert_eq!(Value::create_f64(0.1 + 0.2).to_string(), "0.30000000000000004");
+        assert_eq!(Value::create_f64(-2.5).to_string(), "-2.5");
+        assert_eq!(Value::create_f64(1e21).to_string(), "1000000000000000000000.0");
+        assert_eq!(Value::create_f64(f64::INFINITY).to_string(), "inf");
+        let f32_value = Value::create(Bytes::from(0.1f32.to_le_bytes().to_vec()), Type::F32).unwrap();
+        assert_eq!(f32_value.to_string(), "0.1");
+        //Parses back to the same value
+        let value = 1.0 / 3.0;
+        assert_eq!(Value::create_f64(value).to_string().parse::<f64>().unwrap(), value);
+    }
+
+    #[test]
+    fn like() {
+        let value = Value::create_string(String::from("Jaime_Polidura%"));
+        assert!(value.like(&string("Jaime%")).unwrap());
+        assert!(value.like(&string("%Polidura%")).unwrap());
+        assert!(value.like(&string("J_ime%")).unwrap());
+        assert!(value.like(&string("%")).unwrap());
+        assert!(!value.like(&string("Polidura%")).unwrap());
+        assert!(!value.like(&string("Jaime")).unwrap());
+        assert!(!value.like(&string("J_me%")).unwrap());
+        //Escaped wildcards
+        assert!(value.like(&string("Jaime\\_Polidura\\%")).unwrap());
+        assert!(!Value::create_string(String::from("JaimeXPolidura%")).like(&string("Jaime\\_%")).unwrap());
+        assert!(!Value::create_string(String::from("Jaime_Polidura")).like(&string("%\\%")).unwrap());
+
+        assert!(Value::create_i64(1).like(&string("1")).is_err());
+    }
+
+    #[test]
+    fn ilike() {
+        let value = Value::create_string(String::from("Jaime"));
+        assert!(value.ilike(&string("jaime")).unwrap());
+        assert!(value.ilike(&string("JAI%")).unwrap());
+        assert!(!value.ilike(&string("jaim")).unwrap());
+        assert!(value.eq_ignore_ascii_case(&string("JAIME")).unwrap());
+        assert!(!value.eq_ignore_ascii_case(&string("Jaime ")).unwrap());
+        //Only ASCII characters are folded
+        assert!(!Value::create_string(String::from("Ñ")).eq_ignore_ascii_case(&string("ñ")).unwrap());
+    }
+
+    #[test]
+    fn like_pattern_prefix() {
+        assert_eq!(string("Jaime%").get_like_pattern_prefix().unwrap(), "Jaime");
+        assert_eq!(string("Ja\\_ime_%").get_like_pattern_prefix().unwrap(), "Ja_ime");
+        assert_eq!(string("%Jaime").get_like_pattern_prefix().unwrap(), "");
+    }
+
+    fn string(string: &str) -> Value {
+        Value::create_string(String::from(string))
+    }
+
+    #[test]
+    fn coerce() {
+        let coerced = Value::create_i64(5).coerce(&Type::F64).unwrap();
+        assert_eq!(coerced.get_type(), Type::F64);
+        assert_eq!(coerced.get_f64().unwrap(), 5.0);
+
+        let coerced = Value::create_i64(5).coerce(&Type::I32).unwrap();
+        assert_eq!(coerced.get_i64().unwrap(), 5);
+
+        assert!(Value::create_f64(5.5).coerce(&Type::I64).is_none());
+        assert!(Value::create_string(String::from("5")).coerce(&Type::F64).is_none());
+        assert!(Value::create_null().coerce(&Type::F64).unwrap().is_null());
+    }
+
     #[test]
     fn compare_numbers() {
         let a = Value::create_i64(143);
@@ -533,4 +803,37 @@ mod test {
         assert!(!a.le(&b));
         assert!(!a.lt(&b));
     }
+
+    #[test]
+    fn arithmetic_overflow() {
+        assert!(matches!(Value::create_i64(i64::MAX).add(&Value::create_i64(1)), Err(SimpleDbError::ArithmeticOverflow)));
+        assert!(matches!(Value::create_i64(i64::MIN).subtract(&Value::create_i64(1)), Err(SimpleDbError::ArithmeticOverflow)));
+        assert!(matches!(Value::create_i64(i64::MAX).multiply(&Value::create_i64(2)), Err(SimpleDbError::ArithmeticOverflow)));
+        assert_eq!(Value::create_i64(i64::MAX - 1).add(&Value::create_i64(1)).unwrap().get_i64().unwrap(), i64::MAX);
+    }
+
+    #[test]
+    fn divide_by_zero() {
+        assert!(matches!(Value::create_i64(10).divide(&Value::create_i64(0)), Err(SimpleDbError::DivisionByZero)));
+        assert!(matches!(Value::create_i64(i64::MIN).divide(&Value::create_i64(-1)), Err(SimpleDbError::ArithmeticOverflow)));
+        assert_eq!(Value::create_i64(10).divide(&Value::create_i64(2)).unwrap().get_i64().unwrap(), 5);
+    }
+
+    #[test]
+    fn concat_strings() {
+        let concatenated = string("Jaime").add(&string("_suffix")).unwrap();
+        assert_eq!(concatenated.get_string().unwrap(), "Jaime_suffix");
+        assert_eq!(string("id_").add(&Value::create_i64(1)).unwrap().get_string().unwrap(), "id_1");
+        assert_eq!(Value::create_i64(1).add(&string("_id")).unwrap().get_string().unwrap(), "1_id");
+        assert!(string("a").subtract(&string("a")).is_err());
+        assert!(string("a").add(&Value::create_boolean(true)).is_err());
+    }
+
+    #[test]
+    fn modulo() {
+        assert_eq!(Value::create_i64(7).modulo(&Value::create_i64(2)).unwrap().get_i64().unwrap(), 1);
+        assert_eq!(Value::create_f64(7.5).modulo(&Value::create_i64(2)).unwrap().get_f64().unwrap(), 1.5);
+        assert!(matches!(Value::create_i64(7).modulo(&Value::create_i64(0)), Err(SimpleDbError::DivisionByZero)));
+        assert!(matches!(Value::create_i64(i64::MIN).modulo(&Value::create_i64(-1)), Err(SimpleDbError::ArithmeticOverflow)));
+    }
 }
\ No newline at end of file
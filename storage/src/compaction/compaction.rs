@@ -1,36 +1,100 @@
+use crate::compaction::compaction_throttle::CompactionThrottle;
 use crate::compaction::simple_leveled::{create_simple_level_compaction_task, start_simple_leveled_compaction, SimpleLeveledCompactionTask};
 use crate::compaction::tiered::{create_tiered_compaction_task, start_tiered_compaction, TieredCompactionTask};
+use crate::compaction::size_tiered::{create_size_tiered_compaction_task, start_size_tiered_compaction, SizeTieredCompactionTask};
+use crate::compaction::vacuum::{create_vacuum_task, start_vacuum, VacuumCompactionTask};
 use serde::{Deserialize, Serialize};
 use crate::sst::sstables::SSTables;
+use std::collections::HashSet;
 use std::time::Duration;
-use std::sync::Arc;
+use bytes::Bytes;
+use std::sync::{Arc, Mutex, RwLock, RwLockWriteGuard};
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::Relaxed;
 use shared::{Flag, Type};
 use shared::logger::logger;
 use shared::logger::SimpleDbLayer::StorageKeyspace;
 use crate::keyspace::keyspace_descriptor::KeyspaceDescriptor;
 use crate::manifest::manifest::{Manifest, ManifestOperationContent};
+use crate::memtables::memtables::Memtables;
 use crate::transactions::transaction_manager::TransactionManager;
 
 pub struct Compaction {
     transaction_manager: Arc<TransactionManager>,
     options: Arc<shared::SimpleDbOptions>,
     sstables: Arc<SSTables>,
+    memtables: Arc<Memtables>,
     manifest: Arc<Manifest>,
     keyspace_desc: KeyspaceDescriptor,
+    stopped: Arc<AtomicBool>,
+    //Held for reading while a compaction is running
+    compaction_lock: Arc<RwLock<()>>,
+    running_compactions: Arc<Mutex<RunningCompactions>>,
+    throttle: Arc<CompactionThrottle>,
 }
 
+//Up to SimpleDbOptions::max_concurrent_compactions compactions can run at the same time, each one in its own thread.
+//Compactions won't be started if they use a level of an already running compaction
+#[derive(Clone)]
 struct CompactionThread {
     transaction_manager: Arc<TransactionManager>,
     options: Arc<shared::SimpleDbOptions>,
     sstables: Arc<SSTables>,
+    memtables: Arc<Memtables>,
     manifest: Arc<Manifest>,
-    keyspace_desc: KeyspaceDescriptor
+    keyspace_desc: KeyspaceDescriptor,
+    stopped: Arc<AtomicBool>,
+    compaction_lock: Arc<RwLock<()>>,
+    running_compactions: Arc<Mutex<RunningCompactions>>,
+    throttle: Arc<CompactionThrottle>,
+}
+
+struct RunningCompactions {
+    n_running: usize,
+    levels: HashSet<usize>, //Levels read or written by the running compactions
+}
+
+impl RunningCompactions {
+    //None if the compaction uses a level of a running compaction
+    fn reserve(&mut self, compaction_task: CompactionTask, sstables: &Arc<SSTables>) -> Option<(CompactionTask, Vec<usize>)> {
+        let levels = compaction_task.get_levels(sstables);
+        if levels.iter().any(|level| self.levels.contains(level)) {
+            return None;
+        }
+
+        self.n_running += 1;
+        self.levels.extend(levels.iter());
+
+        Some((compaction_task, levels))
+    }
+
+    fn release(&mut self, levels: Vec<usize>) {
+        self.n_running -= 1;
+        for level in levels {
+            self.levels.remove(&level);
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Clone, Copy)]
+#[derive(Serialize, Deserialize, Clone)]
 pub enum CompactionTask {
     SimpleLeveled(SimpleLeveledCompactionTask),
     Tiered(TieredCompactionTask),
+    SizeTiered(SizeTieredCompactionTask),
+    Vacuum(VacuumCompactionTask),
+}
+
+impl CompactionTask {
+    //Levels read or written by the compaction
+    fn get_levels(&self, sstables: &Arc<SSTables>) -> Vec<usize> {
+        match self {
+            CompactionTask::SimpleLeveled(simple_leveled_task) => simple_leveled_task.get_levels(),
+            CompactionTask::Tiered(TieredCompactionTask::SizeRatioTrigger(level_id)) => (0..=*level_id + 1).collect(),
+            CompactionTask::Tiered(TieredCompactionTask::AmplificationRatioTrigger) => (0..=sstables.get_n_levels()).collect(),
+            CompactionTask::SizeTiered(_) => vec![0],
+            CompactionTask::Vacuum(_) => (0..=sstables.get_n_levels()).collect(),
+        }
+    }
 }
 
 impl Compaction {
@@ -38,6 +102,7 @@ impl Compaction {
         transaction_manager: Arc<TransactionManager>,
         options: Arc<shared::SimpleDbOptions>,
         sstables: Arc<SSTables>,
+        memtables: Arc<Memtables>,
         manifest: Arc<Manifest>,
         keyspace_desc: KeyspaceDescriptor,
     ) -> Arc<Compaction> {
@@ -45,7 +110,12 @@ impl Compaction {
             transaction_manager: transaction_manager.clone(),
             options: options.clone(),
             sstables: sstables.clone(),
+            memtables,
             manifest: manifest.clone(),
+            stopped: Arc::new(AtomicBool::new(false)),
+            compaction_lock: Arc::new(RwLock::new(())),
+            running_compactions: Arc::new(Mutex::new(RunningCompactions { n_running: 0, levels: HashSet::new() })),
+            throttle: Arc::new(CompactionThrottle::create(options.compaction_max_bytes_per_second)),
             keyspace_desc,
         })
     }
@@ -53,55 +123,127 @@ impl Compaction {
     pub fn start_compaction_thread(&self) {
         logger().info(StorageKeyspace(self.keyspace_desc.keyspace_id), "Starting compaction thread");
 
-        let compaction_thread = CompactionThread {
+        let compaction_thread = self.create_compaction_thread();
+
+        std::thread::spawn(move || {
+            compaction_thread.start_compactions();
+        });
+    }
+
+    fn create_compaction_thread(&self) -> CompactionThread {
+        CompactionThread {
             transaction_manager: self.transaction_manager.clone(),
             sstables: self.sstables.clone(),
+            memtables: self.memtables.clone(),
             manifest: self.manifest.clone(),
             keyspace_desc: self.keyspace_desc,
             options: self.options.clone(),
-        };
+            stopped: self.stopped.clone(),
+            compaction_lock: self.compaction_lock.clone(),
+            running_compactions: self.running_compactions.clone(),
+            throttle: self.throttle.clone(),
+        }
+    }
 
-        std::thread::spawn(move || {
-            compaction_thread.start_compactions();
-        });
+    //No compaction will run until the returned guard is dropped. Waits for the running compactions to finish
+    pub fn lock_compactions(&self) -> RwLockWriteGuard<'_, ()> {
+        self.compaction_lock.write().unwrap()
+    }
+
+    //Compacts all the SSTables, removing the versions of the keys that can't be read by any active transaction.
+    //Waits for the running compactions to finish. Returns the nº of bytes reclaimed
+    pub fn vacuum(&self) -> Result<usize, shared::SimpleDbError> {
+        let _compaction_lock = self.lock_compactions();
+        let vacuum_task = create_vacuum_task(&self.transaction_manager, &self.sstables);
+        let operation_id = self.manifest.append_operation(ManifestOperationContent::Compaction(
+            CompactionTask::Vacuum(vacuum_task.clone())
+        ))?;
+
+        let bytes_reclaimed = start_vacuum(
+            vacuum_task, &self.transaction_manager, &self.options, &self.sstables, &self.memtables, self.keyspace_desc, &self.throttle
+        )?;
+
+        self.manifest.mark_as_completed(operation_id)?;
+
+        Ok(bytes_reclaimed)
+    }
+
+    //The compaction thread will exit before starting its next compaction
+    pub fn stop_compaction_thread(&self) {
+        self.stopped.store(true, Relaxed);
     }
 
     pub fn compact(&self, compaction_task: CompactionTask, key_type: Type) -> Result<(), shared::SimpleDbError> {
         match compaction_task {
             CompactionTask::SimpleLeveled(simple_leveled_task) => start_simple_leveled_compaction(
-                simple_leveled_task, &self.transaction_manager, &self.options, &self.sstables, self.keyspace_desc
+                simple_leveled_task, &self.transaction_manager, &self.options, &self.sstables, self.keyspace_desc, &self.throttle
             ),
             CompactionTask::Tiered(tiered_task) => start_tiered_compaction(
-                tiered_task, &self.transaction_manager, &self.options, &self.sstables, self.keyspace_desc
+                tiered_task, &self.transaction_manager, &self.options, &self.sstables, self.keyspace_desc, &self.throttle
             ),
+            CompactionTask::SizeTiered(size_tiered_task) => start_size_tiered_compaction(
+                size_tiered_task, &self.transaction_manager, &self.options, &self.sstables, self.keyspace_desc, &self.throttle
+            ),
+            CompactionTask::Vacuum(vacuum_task) => start_vacuum(
+                vacuum_task, &self.transaction_manager, &self.options, &self.sstables, &self.memtables, self.keyspace_desc, &self.throttle
+            ).map(|_| ()),
         }
     }
 }
 
 impl CompactionThread {
-    fn start_compactions(&self) -> ! {
+    fn start_compactions(&self) {
         loop {
             std::thread::sleep(Duration::from_millis(self.options.compaction_task_frequency_ms as u64));
 
-            if let Some(compaction_task) = self.create_compaction_task() {
-                let operation_id = self.manifest.append_operation(ManifestOperationContent::Compaction(compaction_task));
+            if self.stopped.load(Relaxed) {
+                logger().info(StorageKeyspace(self.keyspace_desc.keyspace_id), "Stopping compaction thread");
+                return;
+            }
 
-                if let Err(compaction_error) = self.compact(compaction_task) {
-                    logger().error(StorageKeyspace(self.keyspace_desc.keyspace_id), &format!("Error while compacting: {:?}", compaction_error));
-                }
+            while let Some((compaction_task, levels)) = self.reserve_compaction_task() {
+                let compaction_thread = self.clone();
+                std::thread::spawn(move || {
+                    compaction_thread.run_compaction(compaction_task, levels);
+                });
+            }
+        }
+    }
 
-                if let Ok(operation_id) = operation_id {
-                    let _ = self.manifest.mark_as_completed(operation_id);
-                }
+    //Returns the next compaction task to run and its levels, which are marked as used until the compaction finishes.
+    //None if there is nothing to compact, or max_concurrent_compactions compactions are already running
+    fn reserve_compaction_task(&self) -> Option<(CompactionTask, Vec<usize>)> {
+        let mut running_compactions = self.running_compactions.lock().unwrap();
+        if running_compactions.n_running >= self.options.max_concurrent_compactions.max(1) {
+            return None;
+        }
+
+        let compaction_task = self.create_compaction_task(&running_compactions.levels)?;
+        running_compactions.reserve(compaction_task, &self.sstables)
+    }
+
+    fn run_compaction(&self, compaction_task: CompactionTask, levels: Vec<usize>) {
+        {
+            let _compaction_lock = self.compaction_lock.read().unwrap();
+            let operation_id = self.manifest.append_operation(ManifestOperationContent::Compaction(compaction_task.clone()));
+
+            if let Err(compaction_error) = self.compact(compaction_task) {
+                logger().error(StorageKeyspace(self.keyspace_desc.keyspace_id), &format!("Error while compacting: {:?}", compaction_error));
+            }
+
+            if let Ok(operation_id) = operation_id {
+                let _ = self.manifest.mark_as_completed(operation_id);
             }
         }
+
+        self.running_compactions.lock().unwrap().release(levels);
     }
 
-    pub fn create_compaction_task(&self) -> Option<CompactionTask> {
+    fn create_compaction_task(&self, running_levels: &HashSet<usize>) -> Option<CompactionTask> {
         match self.options.compaction_strategy {
             shared::CompactionStrategy::SimpleLeveled => {
                 if let Some(compaction_task) = create_simple_level_compaction_task(
-                    self.options.simple_leveled_compaction_options, &self.sstables
+                    self.options.simple_leveled_compaction_options, &self.sstables, running_levels
                 ) {
                     return Some(CompactionTask::SimpleLeveled(compaction_task));
                 }
@@ -113,6 +255,13 @@ impl CompactionThread {
                     return Some(CompactionTask::Tiered(compaction_task));
                 }
             },
+            shared::CompactionStrategy::SizeTiered => {
+                if let Some(compaction_task) = create_size_tiered_compaction_task(
+                    self.options.size_tiered_compaction_options, &self.sstables
+                ) {
+                    return Some(CompactionTask::SizeTiered(compaction_task));
+                }
+            },
         }
 
         None
@@ -121,11 +270,128 @@ impl CompactionThread {
     fn compact(&self, compaction_task: CompactionTask) -> Result<(), shared::SimpleDbError> {
         match compaction_task {
             CompactionTask::SimpleLeveled(simple_leveled_task) => start_simple_leveled_compaction(
-                simple_leveled_task, &self.transaction_manager, &self.options, &self.sstables, self.keyspace_desc
+                simple_leveled_task, &self.transaction_manager, &self.options, &self.sstables, self.keyspace_desc, &self.throttle
             ),
             CompactionTask::Tiered(tiered_task) => start_tiered_compaction(
-                tiered_task, &self.transaction_manager, &self.options, &self.sstables, self.keyspace_desc,
+                tiered_task, &self.transaction_manager, &self.options, &self.sstables, self.keyspace_desc, &self.throttle
             ),
+            CompactionTask::SizeTiered(size_tiered_task) => start_size_tiered_compaction(
+                size_tiered_task, &self.transaction_manager, &self.options, &self.sstables, self.keyspace_desc, &self.throttle
+            ),
+            CompactionTask::Vacuum(vacuum_task) => start_vacuum(
+                vacuum_task, &self.transaction_manager, &self.options, &self.sstables, &self.memtables, self.keyspace_desc, &self.throttle
+            ).map(|_| ()),
         }
     }
-}
\ No newline at end of file
+}
+
+//Expired keys are not written to the new SSTables. Every version of a key stores the insert time of the key,
+//so versions stored in SSTables that don't take part in the compaction expire at the same time
+pub(crate) fn is_expired(
+    options: &Arc<shared::SimpleDbOptions>,
+    keyspace_desc: KeyspaceDescriptor,
+    value: &[u8]
+) -> bool {
+    if keyspace_desc.ttl_seconds == 0 {
+        return false;
+    }
+
+    let insert_time = options.storage_value_insert_time
+        .and_then(|insert_time_fn| insert_time_fn(&Bytes::copy_from_slice(value), keyspace_desc.flags));
+
+    match insert_time {
+        Some(insert_time) => insert_time + keyspace_desc.ttl_seconds * 1000 <= options.current_time_millis(),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::compaction::compaction::{Compaction, CompactionTask};
+    use crate::compaction::simple_leveled::SimpleLeveledCompactionTask;
+    use crate::keyspace::keyspace_descriptor::KeyspaceDescriptor;
+    use crate::manifest::manifest::Manifest;
+    use crate::memtables::memtables::Memtables;
+    use crate::sst::sstable_builder::SSTableBuilder;
+    use crate::sst::sstables::SSTables;
+    use crate::transactions::transaction_manager::TransactionManager;
+    use crate::utils::storage_engine_iterator::StorageEngineIterator;
+    use bytes::Bytes;
+    use shared::iterators::storage_iterator::StorageIterator;
+    use shared::key::Key;
+    use shared::logger::Logger;
+    use shared::Type;
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn concurrent_compactions() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("simpledb-compaction-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()));
+        let options = Arc::new(shared::start_simpledb_options_builder_from(&shared::SimpleDbOptions::default())
+            .base_path(path.to_str().unwrap())
+            .max_concurrent_compactions(2)
+            .compaction_max_bytes_per_second(1048576)
+            .build());
+        Logger::init(options.clone());
+        let keyspace_desc = KeyspaceDescriptor::create_mock(Type::String);
+        std::fs::create_dir_all(shared::get_file_usize(&options.base_path, keyspace_desc.keyspace_id, "")).unwrap();
+        let manifest = Arc::new(Manifest::create(options.clone(), keyspace_desc).unwrap());
+        let sstables = Arc::new(SSTables::open(options.clone(), keyspace_desc, manifest.clone()).unwrap());
+        let transaction_manager = Arc::new(TransactionManager::create_mock(options.clone()));
+
+        //Levels 0 and 1 contain keys 0..30, levels 2 and 3 keys 100..130. The value of each key is its txn id
+        let flush = |level: u32, keys: std::ops::Range<usize>, txn_id: shared::TxnId| {
+            let mut builder = SSTableBuilder::create(options.clone(), keyspace_desc, level);
+            for key in keys {
+                builder.add_entry(Key::create_from_str(&format!("{:03}", key), txn_id), Bytes::from(vec![txn_id as u8]));
+            }
+            sstables.flush_to_disk(builder).unwrap();
+        };
+        flush(1, 0..10, 1);
+        flush(0, 0..20, 2);
+        flush(0, 10..30, 3);
+        flush(3, 100..110, 1);
+        flush(2, 100..120, 2);
+        flush(2, 110..130, 3);
+
+        let memtables = Arc::new(Memtables::create_and_recover_from_wal(options.clone(), keyspace_desc).unwrap());
+        let compaction = Compaction::create(transaction_manager, options.clone(), sstables.clone(), memtables, manifest, keyspace_desc);
+        let compaction_thread = compaction.create_compaction_thread();
+        let (level0_task, level0_task_levels) = compaction_thread.reserve_compaction_task().unwrap();
+        let (level2_task, level2_task_levels) = {
+            let mut running_compactions = compaction_thread.running_compactions.lock().unwrap();
+            //Level 1 is being compacted
+            assert!(running_compactions.reserve(CompactionTask::SimpleLeveled(SimpleLeveledCompactionTask { level: 1 }), &sstables).is_none());
+            running_compactions.reserve(CompactionTask::SimpleLeveled(SimpleLeveledCompactionTask { level: 2 }), &sstables).unwrap()
+        };
+
+        std::thread::scope(|scope| {
+            scope.spawn(|| compaction_thread.run_compaction(level0_task, level0_task_levels));
+            scope.spawn(|| compaction_thread.run_compaction(level2_task, level2_task_levels));
+        });
+
+        assert_eq!(sstables.get_n_sstables_per_level(), vec![0, 1, 0, 1]);
+        assert_eq!(compaction_thread.running_compactions.lock().unwrap().n_running, 0);
+        assert!(compaction_thread.running_compactions.lock().unwrap().levels.is_empty());
+
+        for (level, first_key) in [(1, 0), (3, 100)] {
+            let mut iterator = StorageEngineIterator::create(keyspace_desc, &options, sstables.scan_from_level(&vec![level]));
+            let mut last_values: BTreeMap<Bytes, u8> = BTreeMap::new();
+            let mut n_entries = 0;
+            while iterator.next() {
+                last_values.insert(iterator.key().as_bytes().clone(), iterator.value()[0]);
+                n_entries += 1;
+            }
+            assert_eq!(n_entries, 50);
+            assert_eq!(last_values.len(), 30);
+            for (key, last_value) in last_values {
+                let key: usize = String::from_utf8(key.to_vec()).unwrap().parse().unwrap();
+                assert_eq!(last_value, if key - first_key < 10 { 2 } else { 3 });
+            }
+        }
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+}
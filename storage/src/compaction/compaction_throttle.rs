@@ -0,0 +1,37 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+//Limits the nº of bytes per second written by compactions. The limit is shared by all the running compactions.
+//Every write reserves a time slot proportional to its size, and waits until the slot starts
+pub struct CompactionThrottle {
+    max_bytes_per_second: usize, //0 means no limit
+    next_write_time: Mutex<Instant>,
+}
+
+impl CompactionThrottle {
+    pub fn create(max_bytes_per_second: usize) -> CompactionThrottle {
+        CompactionThrottle {
+            next_write_time: Mutex::new(Instant::now()),
+            max_bytes_per_second,
+        }
+    }
+
+    //Blocks until n_bytes can be written
+    pub fn throttle(&self, n_bytes: usize) {
+        if self.max_bytes_per_second == 0 {
+            return;
+        }
+
+        let write_time = {
+            let mut next_write_time = self.next_write_time.lock().unwrap();
+            let write_time = std::cmp::max(*next_write_time, Instant::now());
+            *next_write_time = write_time + Duration::from_secs_f64(n_bytes as f64 / self.max_bytes_per_second as f64);
+            write_time
+        };
+
+        let now = Instant::now();
+        if write_time > now {
+            std::thread::sleep(write_time - now);
+        }
+    }
+}
@@ -1,3 +1,6 @@
 pub mod compaction;
+pub mod compaction_throttle;
 pub mod simple_leveled;
 pub mod tiered;
+pub mod size_tiered;
+pub mod vacuum;
\ No newline at end of file
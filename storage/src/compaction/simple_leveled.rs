@@ -1,7 +1,10 @@
+use std::collections::HashSet;
 use std::sync::Arc;
 use bytes::Bytes;
 use serde::{Deserialize, Serialize};
 use shared::{Flag, Type};
+use crate::compaction::compaction::is_expired;
+use crate::compaction::compaction_throttle::CompactionThrottle;
 use crate::sst::sstable_builder::SSTableBuilder;
 use crate::sst::sstables::SSTables;
 use crate::transactions::transaction_manager::TransactionManager;
@@ -14,7 +17,14 @@ use crate::utils::tombstone::TOMBSTONE;
 
 #[derive(Serialize, Deserialize, Copy, Clone)]
 pub struct SimpleLeveledCompactionTask {
-    level: usize,
+    pub(crate) level: usize,
+}
+
+impl SimpleLeveledCompactionTask {
+    //Compacts level into level + 1
+    pub(crate) fn get_levels(&self) -> Vec<usize> {
+        vec![self.level, self.level + 1]
+    }
 }
 
 pub(crate) fn start_simple_leveled_compaction(
@@ -22,7 +32,8 @@ pub(crate) fn start_simple_leveled_compaction(
     transaction_manager: &Arc<TransactionManager>,
     options: &Arc<shared::SimpleDbOptions>,
     sstables: &Arc<SSTables>,
-    keyspace_desc: KeyspaceDescriptor
+    keyspace_desc: KeyspaceDescriptor,
+    throttle: &CompactionThrottle,
 ) -> Result<(), shared::SimpleDbError> {
     let level_to_compact = compaction_task.level;
 
@@ -58,12 +69,16 @@ pub(crate) fn start_simple_leveled_compaction(
                     //We remove tombstones in the last levels compactions
                     continue;
                 }
+                if is_expired(options, keyspace_desc, value) {
+                    continue;
+                }
 
                 new_sstable_builder.as_mut().unwrap().add_entry(
                     key, Bytes::copy_from_slice(iterator.value())
                 );
 
                 if new_sstable_builder.as_ref().unwrap().estimated_size_bytes() > options.sst_size_bytes {
+                    throttle.throttle(new_sstable_builder.as_ref().unwrap().estimated_size_bytes());
                     let new_sstable_id: usize = sstables.flush_to_disk(new_sstable_builder.take().unwrap())?;
                     new_sstables_id.push(new_sstable_id);
 
@@ -77,6 +92,7 @@ pub(crate) fn start_simple_leveled_compaction(
     }
 
     if new_sstable_builder.as_ref().unwrap().n_entries() > 0 {
+        throttle.throttle(new_sstable_builder.as_ref().unwrap().estimated_size_bytes());
         new_sstables_id.push(sstables.flush_to_disk(new_sstable_builder.take().unwrap())?);
     }
 
@@ -94,15 +110,21 @@ pub(crate) fn start_simple_leveled_compaction(
 
 pub(crate) fn create_simple_level_compaction_task(
     options: shared::SimpleLeveledCompactionOptions,
-    sstables: &Arc<SSTables>
+    sstables: &Arc<SSTables>,
+    running_levels: &HashSet<usize>, //Levels used by running compactions, they won't be compacted
 ) -> Option<SimpleLeveledCompactionTask> {
+    let is_running = |level: usize| running_levels.contains(&level) || running_levels.contains(&(level + 1));
+
     //Trigger l0 to l1 compaction
-    if sstables.get_n_sstables(0) > options.level0_file_num_compaction_trigger {
+    if !is_running(0) && sstables.get_n_sstables(0) > options.level0_file_num_compaction_trigger {
         return Some(SimpleLeveledCompactionTask{level: 0});
     }
 
     for current_level in 1..sstables.get_n_levels() {
         let prev_level = current_level - 1;
+        if is_running(prev_level) {
+            continue;
+        }
         let n_sstables_current_level = sstables.get_n_sstables(current_level);
         let n_sstables_prev_level = sstables.get_n_sstables(prev_level);
 
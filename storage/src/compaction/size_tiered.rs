@@ -0,0 +1,190 @@
+use std::sync::Arc;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use crate::compaction::compaction::is_expired;
+use crate::compaction::compaction_throttle::CompactionThrottle;
+use crate::sst::sstable::SSTable;
+use crate::sst::sstable_builder::SSTableBuilder;
+use crate::sst::sstables::SSTables;
+use crate::transactions::transaction_manager::TransactionManager;
+use crate::utils::storage_engine_iterator::StorageEngineIterator;
+use shared::iterators::storage_iterator::StorageIterator;
+use shared::logger::logger;
+use shared::logger::SimpleDbLayer::StorageKeyspace;
+use crate::keyspace::keyspace_descriptor::KeyspaceDescriptor;
+use crate::utils::tombstone::TOMBSTONE;
+
+//Every SSTable is stored in level 0. SSTables of similar size are grouped in buckets,
+//once a bucket has enough SSTables, they are merged into a single bigger SSTable.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SizeTieredCompactionTask {
+    sstables_id: Vec<shared::SSTableId>,
+}
+
+pub(crate) fn start_size_tiered_compaction(
+    compaction_task: SizeTieredCompactionTask,
+    transaction_manager: &Arc<TransactionManager>,
+    options: &Arc<shared::SimpleDbOptions>,
+    sstables: &Arc<SSTables>,
+    keyspace_desc: KeyspaceDescriptor,
+    throttle: &CompactionThrottle,
+) -> Result<(), shared::SimpleDbError> {
+    //When restarting the compaction from the manifest, some SSTables might have been already deleted
+    let sstables_id: Vec<shared::SSTableId> = compaction_task.sstables_id.into_iter()
+        .filter(|sstable_id| sstables.contains_sstable_id(*sstable_id))
+        .collect();
+    if sstables_id.is_empty() {
+        return Ok(());
+    }
+
+    //Tombstones can only be removed if there are no other SSTables containing older versions of the key
+    let compacts_all_sstables = sstables_id.len() == sstables.get_n_sstables_all_levels();
+    let mut iterator = StorageEngineIterator::create(
+        keyspace_desc,
+        options,
+        sstables.scan_sstables(0, &sstables_id),
+    );
+    //The result is not split by sst_size_bytes, otherwise, the resulting SSTables would end up in the same bucket
+    //and would be compacted again
+    let mut new_sstable_builder = SSTableBuilder::create(options.clone(), keyspace_desc, 0);
+
+    while iterator.has_next() {
+        iterator.next();
+
+        let key = iterator.key().clone();
+
+        if transaction_manager.on_write_key(&key).is_ok() {
+            let value = iterator.value();
+            let is_tombstone = value.eq(TOMBSTONE.as_ref());
+
+            if (compacts_all_sstables && is_tombstone) || is_expired(options, keyspace_desc, value) {
+                continue;
+            }
+
+            new_sstable_builder.add_entry(key, Bytes::copy_from_slice(value));
+        }
+    }
+
+    let mut new_sstable_id = None;
+    if new_sstable_builder.n_entries() > 0 {
+        throttle.throttle(new_sstable_builder.estimated_size_bytes());
+        new_sstable_id = Some(sstables.flush_to_disk(new_sstable_builder)?);
+    }
+
+    logger().info(StorageKeyspace(keyspace_desc.keyspace_id), &format!(
+        "Compacted SSTables: {:?} of similar size. Created SSTable {:?}", sstables_id, new_sstable_id,
+    ));
+
+    sstables.delete_sstables(0, sstables_id)?;
+
+    Ok(())
+}
+
+pub(crate) fn create_size_tiered_compaction_task(
+    options: shared::SizeTieredCompactionOptions,
+    sstables: &Arc<SSTables>
+) -> Option<SizeTieredCompactionTask> {
+    let mut sstables_by_size = sstables.get_sstables(0);
+    sstables_by_size.sort_by_key(|sstable| sstable.size());
+
+    let mut buckets: Vec<Vec<Arc<SSTable>>> = Vec::new();
+    for sstable in sstables_by_size {
+        match buckets.last_mut() {
+            Some(bucket) if fits_in_bucket(&options, bucket, &sstable) => bucket.push(sstable),
+            _ => buckets.push(vec![sstable]),
+        }
+    }
+
+    //Buckets are sorted by size, so the cheapest bucket to compact is picked first
+    buckets.into_iter()
+        .find(|bucket| bucket.len() >= options.min_threshold.max(2))
+        .map(|bucket| SizeTieredCompactionTask {
+            sstables_id: bucket.iter()
+                .take(options.max_threshold)
+                .map(|sstable| sstable.sstable_id)
+                .collect()
+        })
+}
+
+fn fits_in_bucket(
+    options: &shared::SizeTieredCompactionOptions,
+    bucket: &Vec<Arc<SSTable>>,
+    sstable: &Arc<SSTable>
+) -> bool {
+    let bucket_avg_size = bucket.iter().map(|sstable| sstable.size()).sum::<usize>() / bucket.len();
+    let sstable_size = sstable.size();
+
+    if bucket_avg_size < options.min_sstable_size_bytes && sstable_size < options.min_sstable_size_bytes {
+        return true;
+    }
+
+    sstable_size as f64 >= bucket_avg_size as f64 * options.bucket_low &&
+        sstable_size as f64 <= bucket_avg_size as f64 * options.bucket_high
+}
+
+#[cfg(test)]
+mod test {
+    use crate::compaction::compaction_throttle::CompactionThrottle;
+    use crate::compaction::size_tiered::{create_size_tiered_compaction_task, start_size_tiered_compaction};
+    use crate::keyspace::keyspace_descriptor::KeyspaceDescriptor;
+    use crate::manifest::manifest::Manifest;
+    use crate::sst::sstable_builder::SSTableBuilder;
+    use crate::sst::sstables::SSTables;
+    use crate::transactions::transaction_manager::TransactionManager;
+    use crate::utils::storage_engine_iterator::StorageEngineIterator;
+    use bytes::Bytes;
+    use shared::iterators::storage_iterator::StorageIterator;
+    use shared::key::Key;
+    use shared::logger::Logger;
+    use shared::Type;
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn compact_bucket() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("simpledb-size-tiered-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()));
+        let options = Arc::new(shared::start_simpledb_options_builder_from(&shared::SimpleDbOptions::default())
+            .base_path(path.to_str().unwrap())
+            .compaction_strategy(shared::CompactionStrategy::SizeTiered)
+            .build());
+        Logger::init(options.clone());
+        let keyspace_desc = KeyspaceDescriptor::create_mock(Type::String);
+        std::fs::create_dir_all(shared::get_file_usize(&options.base_path, keyspace_desc.keyspace_id, "")).unwrap();
+        let manifest = Arc::new(Manifest::create(options.clone(), keyspace_desc).unwrap());
+        let sstables = Arc::new(SSTables::open(options.clone(), keyspace_desc, manifest).unwrap());
+
+        //Every SSTable overlaps with the next one, the newest versions are written by the last SSTables
+        for sstable_index in 0..4 {
+            let mut builder = SSTableBuilder::create(options.clone(), keyspace_desc, 0);
+            for key in (sstable_index * 10)..(sstable_index * 10 + 20) {
+                builder.add_entry(Key::create_from_str(&format!("{:03}", key), sstable_index + 1), Bytes::from(vec![sstable_index as u8]));
+            }
+            sstables.flush_to_disk(builder).unwrap();
+        }
+        let task = create_size_tiered_compaction_task(options.size_tiered_compaction_options, &sstables).unwrap();
+        let transaction_manager = Arc::new(TransactionManager::create_mock(options.clone()));
+        start_size_tiered_compaction(task, &transaction_manager, &options, &sstables, keyspace_desc, &CompactionThrottle::create(0)).unwrap();
+
+        assert_eq!(sstables.get_n_sstables(0), 1);
+        assert!(create_size_tiered_compaction_task(options.size_tiered_compaction_options, &sstables).is_none());
+
+        //Every version of the keys is kept
+        let mut iterator = StorageEngineIterator::create(keyspace_desc, &options, sstables.scan_from_level(&vec![0]));
+        let mut last_values: BTreeMap<Bytes, u8> = BTreeMap::new();
+        let mut n_entries = 0;
+        while iterator.next() {
+            last_values.insert(iterator.key().as_bytes().clone(), iterator.value()[0]);
+            n_entries += 1;
+        }
+        assert_eq!(n_entries, 80);
+        assert_eq!(last_values.len(), 50);
+        for (key, last_value) in last_values {
+            let key: usize = String::from_utf8(key.to_vec()).unwrap().parse().unwrap();
+            assert_eq!(last_value as usize, (key / 10).min(3));
+        }
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+}
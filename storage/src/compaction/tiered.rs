@@ -2,6 +2,8 @@ use std::sync::Arc;
 use bytes::Bytes;
 use serde::{Deserialize, Serialize};
 use shared::{Flag, Type};
+use crate::compaction::compaction::is_expired;
+use crate::compaction::compaction_throttle::CompactionThrottle;
 use crate::sst::sstable_builder::SSTableBuilder;
 use crate::sst::sstables::SSTables;
 use crate::transactions::transaction_manager::TransactionManager;
@@ -21,14 +23,15 @@ pub(crate) fn start_tiered_compaction(
     transaction_manager: &Arc<TransactionManager>,
     options: &Arc<shared::SimpleDbOptions>,
     sstables: &Arc<SSTables>,
-    keyspace_desc: KeyspaceDescriptor
+    keyspace_desc: KeyspaceDescriptor,
+    throttle: &CompactionThrottle,
 ) -> Result<(), shared::SimpleDbError> {
     match task {
         TieredCompactionTask::AmplificationRatioTrigger => {
-            do_tiered_compaction(options, sstables, sstables.get_n_levels() - 1, transaction_manager, keyspace_desc)
+            do_tiered_compaction(options, sstables, sstables.get_n_levels() - 1, transaction_manager, keyspace_desc, throttle)
         },
         TieredCompactionTask::SizeRatioTrigger(level_id) => {
-            do_tiered_compaction(options, sstables, level_id, transaction_manager, keyspace_desc)
+            do_tiered_compaction(options, sstables, level_id, transaction_manager, keyspace_desc, throttle)
         },
     }
 }
@@ -38,7 +41,8 @@ fn do_tiered_compaction(
     sstables: &Arc<SSTables>,
     max_level_id_to_compact: usize, //Compact from level 0 to max_level_id_to_compact (inclusive, inclusive)
     transaction_manager: &Arc<TransactionManager>,
-    keyspace_desc: KeyspaceDescriptor
+    keyspace_desc: KeyspaceDescriptor,
+    throttle: &CompactionThrottle,
 ) -> Result<(), shared::SimpleDbError> {
     let new_level = max_level_id_to_compact + 1;
     let is_new_level_last_level = sstables.is_last_level(new_level);
@@ -65,12 +69,16 @@ fn do_tiered_compaction(
                     //We remove tombstones in the last levels compactions
                     continue;
                 }
+                if is_expired(options, keyspace_desc, value) {
+                    continue;
+                }
 
                 new_sstable_builder.as_mut().unwrap().add_entry(
                     key, Bytes::copy_from_slice(iterator.value())
                 );
 
                 if new_sstable_builder.as_ref().unwrap().estimated_size_bytes() > options.sst_size_bytes {
+                    throttle.throttle(new_sstable_builder.as_ref().unwrap().estimated_size_bytes());
                     sstables.flush_to_disk(new_sstable_builder.take().unwrap())?;
 
                     new_sstable_builder = Some(
@@ -83,6 +91,7 @@ fn do_tiered_compaction(
     }
 
     if new_sstable_builder.as_ref().unwrap().n_entries() > 0 {
+        throttle.throttle(new_sstable_builder.as_ref().unwrap().estimated_size_bytes());
         sstables.flush_to_disk(new_sstable_builder.take().unwrap())?;
     }
 
@@ -0,0 +1,219 @@
+use std::sync::Arc;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use shared::key::Key;
+use shared::iterators::storage_iterator::StorageIterator;
+use shared::logger::logger;
+use shared::logger::SimpleDbLayer::StorageKeyspace;
+use shared::StorageValueMergeResult;
+use crate::compaction::compaction::is_expired;
+use crate::compaction::compaction_throttle::CompactionThrottle;
+use crate::keyspace::keyspace_descriptor::KeyspaceDescriptor;
+use crate::memtables::memtables::Memtables;
+use crate::sst::sstable_builder::SSTableBuilder;
+use crate::sst::sstables::SSTables;
+use crate::transactions::transaction_manager::TransactionManager;
+use crate::utils::tombstone::TOMBSTONE;
+
+//Compacts all the SSTables of the keyspace into the deepest level. Every active transaction can see all the versions
+//written by transactions older than min_visible_txn_id, so these versions are merged into a single one.
+//Newer versions are kept, as some transactions might still read the older ones.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct VacuumCompactionTask {
+    min_visible_txn_id: shared::TxnId,
+    sstables_id: Vec<Vec<shared::SSTableId>>, //Indexed by level
+}
+
+pub(crate) fn create_vacuum_task(
+    transaction_manager: &Arc<TransactionManager>,
+    sstables: &Arc<SSTables>,
+) -> VacuumCompactionTask {
+    let n_levels = sstables.get_n_sstables_per_level().len();
+
+    VacuumCompactionTask {
+        min_visible_txn_id: transaction_manager.get_min_visible_txn_id(),
+        sstables_id: (0..n_levels).map(|level| sstables.get_sstables_id(level)).collect(),
+    }
+}
+
+//Returns the nº of bytes reclaimed
+pub(crate) fn start_vacuum(
+    task: VacuumCompactionTask,
+    transaction_manager: &Arc<TransactionManager>,
+    options: &Arc<shared::SimpleDbOptions>,
+    sstables: &Arc<SSTables>,
+    memtables: &Arc<Memtables>,
+    keyspace_desc: KeyspaceDescriptor,
+    throttle: &CompactionThrottle,
+) -> Result<usize, shared::SimpleDbError> {
+    //When restarting the vacuum from the manifest, if some SSTables have been deleted, the new SSTables
+    //have already been written
+    let n_sstables = task.sstables_id.iter().map(|sstables_id| sstables_id.len()).sum::<usize>();
+    let sstables_id: Vec<Vec<shared::SSTableId>> = task.sstables_id.into_iter()
+        .map(|sstables_id| sstables_id.into_iter()
+            .filter(|sstable_id| sstables.contains_sstable_id(*sstable_id))
+            .collect())
+        .collect();
+    let n_existing_sstables = sstables_id.iter().map(|sstables_id| sstables_id.len()).sum::<usize>();
+    if n_existing_sstables == 0 {
+        return Ok(0);
+    }
+    if n_existing_sstables < n_sstables {
+        delete_sstables(sstables, sstables_id)?;
+        return Ok(0);
+    }
+
+    let size_before: usize = sstables_id.iter().enumerate()
+        .map(|(level, sstables_id)| get_size_bytes(sstables, level, sstables_id))
+        .sum();
+    let new_level = sstables_id.len() - 1;
+    let mut iterator = sstables.scan_from_sstables(&sstables_id);
+    let mut new_sstables = NewSSTables {
+        builder: SSTableBuilder::create(options.clone(), keyspace_desc, new_level as u32),
+        sstables_id: Vec::new(),
+        new_level,
+        keyspace_desc,
+        sstables,
+        throttle,
+        options,
+    };
+    let mut versions: Vec<(Key, Bytes)> = Vec::new();
+
+    while iterator.has_next() {
+        iterator.next();
+
+        let key = iterator.key().clone();
+        if !versions.is_empty() && !versions[0].0.bytes_eq(&key) {
+            vacuum_key(&mut versions, task.min_visible_txn_id, options, memtables, &mut new_sstables)?;
+        }
+        //Rolledback writes are discarded
+        if transaction_manager.on_write_key(&key).is_ok() {
+            versions.push((key, Bytes::copy_from_slice(iterator.value())));
+        }
+    }
+    vacuum_key(&mut versions, task.min_visible_txn_id, options, memtables, &mut new_sstables)?;
+
+    let new_sstables_id = new_sstables.finish()?;
+    let size_after = get_size_bytes(sstables, new_level, &new_sstables_id);
+
+    logger().info(StorageKeyspace(keyspace_desc.keyspace_id), &format!(
+        "Vacuumed SSTables: {:?}. Created SSTables {:?} in level {}", sstables_id, new_sstables_id, new_level
+    ));
+
+    delete_sstables(sstables, sstables_id)?;
+
+    Ok(size_before.saturating_sub(size_after))
+}
+
+//versions contains every version of the same key, ordered by txn id
+fn vacuum_key(
+    versions: &mut Vec<(Key, Bytes)>,
+    min_visible_txn_id: shared::TxnId,
+    options: &Arc<shared::SimpleDbOptions>,
+    memtables: &Arc<Memtables>,
+    new_sstables: &mut NewSSTables,
+) -> Result<(), shared::SimpleDbError> {
+    let n_old_versions = versions.iter()
+        .take_while(|(key, _)| key.txn_id() < min_visible_txn_id)
+        .count();
+    let new_versions = versions.split_off(n_old_versions);
+
+    if let Some((key, value)) = merge_versions(versions.drain(..), options, new_sstables.keyspace_desc) {
+        let is_tombstone = value.eq(&TOMBSTONE);
+        //A memtable might contain a version of the key written by a transaction older than the tombstone
+        let is_tombstone_needed = !new_versions.is_empty() || memtables.contains_key(key.as_bytes());
+
+        if !(is_tombstone && !is_tombstone_needed) && !is_expired(options, new_sstables.keyspace_desc, &value) {
+            new_sstables.add_entry(key, value)?;
+        }
+    }
+
+    for (key, value) in new_versions {
+        new_sstables.add_entry(key, value)?;
+    }
+
+    Ok(())
+}
+
+//Returns the version read by transactions newer than every version. If there is no storage value merger,
+//it will be the last version, otherwise, the result of merging all of them
+fn merge_versions(
+    versions: impl Iterator<Item = (Key, Bytes)>,
+    options: &Arc<shared::SimpleDbOptions>,
+    keyspace_desc: KeyspaceDescriptor,
+) -> Option<(Key, Bytes)> {
+    let mut merged: Option<(Key, Bytes)> = None;
+
+    for (key, value) in versions {
+        merged = match (merged.take(), options.storage_value_merger) {
+            (Some((_, prev_value)), Some(merge_fn)) => {
+                match merge_fn(&prev_value, &value, keyspace_desc.flags, keyspace_desc.key_type) {
+                    StorageValueMergeResult::Ok(merged_value) => Some((key, merged_value)),
+                    StorageValueMergeResult::DiscardPreviousKeepNew => Some((key, value)),
+                    StorageValueMergeResult::DiscardPreviousAndNew => None,
+                }
+            },
+            _ => Some((key, value)),
+        };
+    }
+
+    merged
+}
+
+//SSTables written by the vacuum. They are split by SimpleDbOptions::sst_size_bytes
+struct NewSSTables<'a> {
+    builder: SSTableBuilder,
+    sstables_id: Vec<shared::SSTableId>,
+    new_level: usize,
+    keyspace_desc: KeyspaceDescriptor,
+    sstables: &'a Arc<SSTables>,
+    throttle: &'a CompactionThrottle,
+    options: &'a Arc<shared::SimpleDbOptions>,
+}
+
+impl<'a> NewSSTables<'a> {
+    fn add_entry(&mut self, key: Key, value: Bytes) -> Result<(), shared::SimpleDbError> {
+        self.builder.add_entry(key, value);
+
+        if self.builder.estimated_size_bytes() > self.options.sst_size_bytes {
+            self.flush()?;
+        }
+
+        Ok(())
+    }
+
+    //Returns the new SSTables id
+    fn finish(mut self) -> Result<Vec<shared::SSTableId>, shared::SimpleDbError> {
+        if self.builder.n_entries() > 0 {
+            self.flush()?;
+        }
+
+        Ok(self.sstables_id)
+    }
+
+    fn flush(&mut self) -> Result<(), shared::SimpleDbError> {
+        let builder = std::mem::replace(&mut self.builder, SSTableBuilder::create(
+            self.options.clone(), self.keyspace_desc, self.new_level as u32
+        ));
+        self.throttle.throttle(builder.estimated_size_bytes());
+
+        self.sstables_id.push(self.sstables.flush_to_disk(builder)?);
+
+        Ok(())
+    }
+}
+
+fn get_size_bytes(sstables: &Arc<SSTables>, level: usize, sstables_id: &Vec<shared::SSTableId>) -> usize {
+    sstables.get_sstables(level).iter()
+        .filter(|sstable| sstables_id.contains(&sstable.sstable_id))
+        .map(|sstable| sstable.size())
+        .sum()
+}
+
+fn delete_sstables(sstables: &Arc<SSTables>, sstables_id: Vec<Vec<shared::SSTableId>>) -> Result<(), shared::SimpleDbError> {
+    for (level, sstables_id) in sstables_id.into_iter().enumerate() {
+        sstables.delete_sstables(level, sstables_id)?;
+    }
+
+    Ok(())
+}
@@ -5,6 +5,7 @@ use crate::memtables::memtable::MemTable;
 use crate::memtables::memtables::Memtables;
 use crate::sst::sstable_builder::SSTableBuilder;
 use crate::sst::sstables::SSTables;
+use crate::transactions::savepoint::SavepointWrite;
 use crate::transactions::transaction::Transaction;
 use crate::transactions::transaction_manager::{IsolationLevel, TransactionManager};
 use crate::utils::storage_engine_iterator::StorageEngineIterator;
@@ -17,6 +18,8 @@ use std::fs;
 use std::sync::Arc;
 use shared::logger::logger;
 use shared::logger::SimpleDbLayer::StorageKeyspace;
+use shared::key::Key;
+use crate::utils::key_range::merge_key_ranges;
 
 pub struct Keyspace {
     keyspace_id: shared::KeyspaceId,
@@ -24,7 +27,7 @@ pub struct Keyspace {
     options: Arc<shared::SimpleDbOptions>,
     compaction: Arc<Compaction>,
     sstables: Arc<SSTables>,
-    memtables: Memtables,
+    memtables: Arc<Memtables>,
     manifest: Arc<Manifest>,
     descriptor: KeyspaceDescriptor,
 }
@@ -37,11 +40,12 @@ impl Keyspace {
         options: Arc<shared::SimpleDbOptions>,
         flags: Flag,
         key_type: Type,
+        ttl_seconds: u64,
     ) -> Result<Arc<Keyspace>, shared::SimpleDbError> {
         let path = shared::get_directory_usize(&options.base_path, keyspace_id);
         fs::create_dir(path.as_path())
             .map_err(|e| shared::SimpleDbError::CannotCreateKeyspaceDirectory(keyspace_id, e))?;
-        KeyspaceDescriptor::create(flags, path.clone(), keyspace_id, key_type)?;
+        KeyspaceDescriptor::create(flags, path.clone(), keyspace_id, key_type, ttl_seconds)?;
 
         Self::create_and_load(keyspace_id, transaction_manager, options)
     }
@@ -55,9 +59,9 @@ impl Keyspace {
         let descriptor = KeyspaceDescriptor::load_from_disk(keyspace_id, path)?;
         let manifest = Arc::new(Manifest::create(options.clone(), descriptor)?);
         let sstables = Arc::new(SSTables::open(options.clone(), descriptor, manifest.clone())?);
-        let memtables = Memtables::create_and_recover_from_wal(options.clone(), descriptor)?;
+        let memtables = Arc::new(Memtables::create_and_recover_from_wal(options.clone(), descriptor)?);
         let compaction = Compaction::create(transaction_manager.clone(), options.clone(),
-                                            sstables.clone(), manifest.clone(), descriptor);
+                                            sstables.clone(), memtables.clone(), manifest.clone(), descriptor);
 
         Ok(Arc::new(Keyspace {
             transaction_manager,
@@ -71,6 +75,32 @@ impl Keyspace {
         }))
     }
 
+    //Returns the corrupted parts found in the keyspace descriptor, manifest and SSTables files.
+    //The keyspace doesn't need to be loaded
+    pub fn verify_files(
+        keyspace_id: shared::KeyspaceId,
+        options: &Arc<shared::SimpleDbOptions>
+    ) -> Result<Vec<shared::SimpleDbError>, shared::SimpleDbError> {
+        let path = shared::get_directory_usize(&options.base_path, keyspace_id);
+        let descriptor = match KeyspaceDescriptor::load_from_disk(keyspace_id, path) {
+            Ok(descriptor) => descriptor,
+            Err(error) => return Ok(vec![error]),
+        };
+        let mut corrupted_parts = Vec::new();
+
+        if let Err(error) = Manifest::create(options.clone(), descriptor).and_then(|manifest| manifest.verify()) {
+            corrupted_parts.push(error);
+        }
+
+        corrupted_parts.extend(SSTables::verify_files(options, descriptor)?);
+
+        Ok(corrupted_parts)
+    }
+
+    pub fn check_integrity(&self) -> Vec<shared::SimpleDbError> {
+        self.sstables.check_integrity()
+    }
+
     pub fn scan_from_key_with_transaction(
         &self,
         transaction: &Transaction,
@@ -103,6 +133,16 @@ impl Keyspace {
         )
     }
 
+    //Returns the iterator placed after the last key, keys will be returned in descending order by calling prev()
+    pub fn scan_all_backwards_with_transaction(
+        &self,
+        transaction: &Transaction
+    ) -> SimpleDbStorageIterator {
+        let mut iterator = self.scan_all_with_transaction(transaction);
+        iterator.seek_to_end();
+        iterator
+    }
+
     pub fn get_with_transaction(
         &self,
         transaction: &Transaction,
@@ -121,7 +161,8 @@ impl Keyspace {
         value: &[u8],
     ) -> Result<(), shared::SimpleDbError> {
         self.transaction_manager.mark_write(transaction)?;
-        match self.memtables.set(key, value, transaction) {
+        self.add_savepoint_write(transaction, &key);
+        match self.memtables.set(key, value, transaction)? {
             Some(memtable_to_flush) => self.flush_memtable(memtable_to_flush),
             None => Ok(())
         }
@@ -141,12 +182,34 @@ impl Keyspace {
         key: Bytes,
     ) -> Result<(), shared::SimpleDbError> {
         self.transaction_manager.mark_write(transaction)?;
-        match self.memtables.delete(key, transaction) {
+        self.add_savepoint_write(transaction, &key);
+        match self.memtables.delete(key, transaction)? {
             Some(memtable_to_flush) => self.flush_memtable(memtable_to_flush),
             None => Ok(()),
         }
     }
 
+    fn add_savepoint_write(&self, transaction: &Transaction, key: &Bytes) {
+        if self.transaction_manager.has_savepoints(transaction) {
+            let (memtable_id, previous_value) = self.memtables.get_own_write(key, transaction);
+            self.transaction_manager.add_savepoint_write(transaction, SavepointWrite {
+                keyspace_id: self.keyspace_id,
+                key: key.clone(),
+                memtable_id,
+            }, previous_value);
+        }
+    }
+
+    //Returns false if the write has already been flushed to disk
+    pub fn restore_savepoint_write(
+        &self,
+        transaction: &Transaction,
+        write: &SavepointWrite,
+        previous_value: &Option<Bytes>,
+    ) -> Result<bool, shared::SimpleDbError> {
+        self.memtables.restore_own_write(write.memtable_id, &write.key, transaction, previous_value)
+    }
+
     fn flush_memtable(&self, memtable: Arc<MemTable>) -> Result<(), shared::SimpleDbError> {
         let sstable_builder_ready: SSTableBuilder = memtable.to_sst(&self.transaction_manager);
         let sstable_id = self.sstables.flush_memtable_to_disk(sstable_builder_ready)?;
@@ -159,10 +222,111 @@ impl Keyspace {
         Ok(())
     }
 
+    //Removes all the keyspace data. The memtables are replaced by an empty one and all SSTables are deleted
+    //Expect the keyspace to not be used by any active transaction. Use Keyspaces::truncate_keyspaces(), so it is crash-atomic
+    pub fn truncate(&self) -> Result<(), shared::SimpleDbError> {
+        let _compaction_lock = self.compaction.lock_compactions();
+        self.memtables.truncate(&self.transaction_manager);
+        self.sstables.delete_all(&self.transaction_manager)?;
+
+        logger().info(StorageKeyspace(self.keyspace_id), "Truncated keyspace");
+
+        Ok(())
+    }
+
+    //Compacts all the SSTables, removing deleted keys and overwritten versions that no active transaction can read.
+    //Returns the nº of bytes reclaimed
+    pub fn vacuum(&self) -> Result<usize, shared::SimpleDbError> {
+        let bytes_reclaimed = self.compaction.vacuum()?;
+
+        logger().info(StorageKeyspace(self.keyspace_id), &format!("Vacuumed keyspace. Reclaimed {} bytes", bytes_reclaimed));
+
+        Ok(bytes_reclaimed)
+    }
+
+    //Writes the data visible by the transaction as a new keyspace in snapshot_options' base path.
+    //Memtables are dumped to SSTables. Compactions are blocked until the snapshot finishes,
+    //so the SSTables being read are not deleted
+    pub fn snapshot(
+        &self,
+        transaction: &Transaction,
+        snapshot_options: &Arc<shared::SimpleDbOptions>
+    ) -> Result<(), shared::SimpleDbError> {
+        let _compaction_lock = self.compaction.lock_compactions();
+
+        let path = shared::get_directory_usize(&snapshot_options.base_path, self.keyspace_id);
+        fs::create_dir(path.as_path())
+            .map_err(|e| shared::SimpleDbError::CannotCreateKeyspaceDirectory(self.keyspace_id, e))?;
+        let descriptor = KeyspaceDescriptor::create(self.descriptor.flags, path, self.keyspace_id,
+                                                    self.descriptor.key_type, self.descriptor.ttl_seconds)?;
+        let manifest = Arc::new(Manifest::create(snapshot_options.clone(), descriptor)?);
+        let snapshot_sstables = SSTables::open(snapshot_options.clone(), descriptor, manifest)?;
+
+        let mut iterator = self.scan_all_with_transaction(transaction);
+        let mut sstable_builder = SSTableBuilder::create(snapshot_options.clone(), descriptor, 0);
+        while iterator.next() {
+            sstable_builder.add_entry(iterator.key().clone(), Bytes::copy_from_slice(iterator.value()));
+
+            if sstable_builder.estimated_size_bytes() > snapshot_options.sst_size_bytes {
+                let new_sstable_builder = SSTableBuilder::create(snapshot_options.clone(), descriptor, 0);
+                snapshot_sstables.flush_to_disk(std::mem::replace(&mut sstable_builder, new_sstable_builder))?;
+            }
+        }
+        if sstable_builder.n_entries() > 0 {
+            snapshot_sstables.flush_to_disk(sstable_builder)?;
+        }
+
+        logger().info(StorageKeyspace(self.keyspace_id), &format!(
+            "Created snapshot in {}", snapshot_options.base_path
+        ));
+
+        Ok(())
+    }
+
+    //The number of keys is stored in SSTables when they are created by flushes and compactions.
+    //Deleted keys and keys written multiple times might be counted more than once
+    pub fn estimate_n_keys(&self) -> Option<usize> {
+        self.sstables.estimate_n_keys()
+            .map(|n_keys_sstables| n_keys_sstables + self.memtables.get_n_keys())
+    }
+
+    //Returns the smallest and the biggest key. Deleted keys might be returned
+    pub fn get_key_range(&self) -> Option<(Key, Key)> {
+        let sstables_key_range = self.sstables.get_key_range();
+        match self.memtables.get_key_range() {
+            Some((first_key, last_key)) => merge_key_ranges(sstables_key_range, &first_key, &last_key),
+            None => sstables_key_range,
+        }
+    }
+
+    //Memtables keys are not taken into account, they will belong to the partition containing their key
+    pub fn get_partition_keys(&self) -> Vec<Key> {
+        self.sstables.get_partition_keys()
+    }
+
+    pub fn get_n_sstables_per_level(&self) -> Vec<usize> {
+        self.sstables.get_n_sstables_per_level()
+    }
+
+    //Only the SSTables are included, memtables are not stored on disk yet
+    pub fn get_size_bytes(&self) -> usize {
+        self.sstables.get_size_bytes()
+    }
+
     pub fn start_compaction_thread(&self) {
         self.compaction.start_compaction_thread();
     }
 
+    //Stops the compaction thread and deletes all the keyspace files.
+    //The keyspace shouldn't be used after calling this function
+    pub fn delete_files(&self) -> Result<(), shared::SimpleDbError> {
+        self.compaction.stop_compaction_thread();
+
+        let path = shared::get_directory_usize(&self.options.base_path, self.keyspace_id);
+        fs::remove_dir_all(path.as_path())
+            .map_err(|e| shared::SimpleDbError::CannotDeleteKeyspace(self.keyspace_id, e))
+    }
+
     pub fn keyspace_id(&self) -> shared::KeyspaceId {
         self.keyspace_id
     }
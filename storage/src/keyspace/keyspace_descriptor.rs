@@ -7,6 +7,7 @@ use std::path::PathBuf;
 pub struct KeyspaceDescriptor {
     pub(crate) flags: Flag,
     pub(crate) key_type: Type,
+    pub(crate) ttl_seconds: u64, //Entries older than this are dropped in compactions. 0 if they never expire
     pub(crate) keyspace_id: KeyspaceId //Not serialized
 }
 
@@ -16,6 +17,7 @@ impl KeyspaceDescriptor {
     ) -> KeyspaceDescriptor {
         KeyspaceDescriptor {
             keyspace_id: 0,
+            ttl_seconds: 0,
             key_type,
             flags: 0,
         }
@@ -25,10 +27,12 @@ impl KeyspaceDescriptor {
         flags: Flag,
         keyspace_path: PathBuf,
         keyspace_id: KeyspaceId,
-        key_type: Type
+        key_type: Type,
+        ttl_seconds: u64
     ) -> Result<KeyspaceDescriptor, SimpleDbError> {
         let keyspace_descriptor = KeyspaceDescriptor {
             keyspace_id,
+            ttl_seconds,
             key_type,
             flags,
         };
@@ -59,10 +63,20 @@ impl KeyspaceDescriptor {
         let mut serialized: Vec<u8> = Vec::new();
         serialized.put_u8(self.key_type.serialize() as u8);
         serialized.put_u64_le(self.flags as u64);
+        serialized.put_u64_le(self.ttl_seconds);
         serialized
     }
 
     pub fn deserialize(bytes: &mut &[u8], keyspace_id: KeyspaceId) -> Result<KeyspaceDescriptor, SimpleDbError> {
+        //Key type (u8) & flags (u64), followed by the TTL (u64) in descriptors written after TTLs were supported
+        if bytes.len() != 9 && bytes.len() != 17 {
+            return Err(CannotDecodeKeyspaceDescriptor(keyspace_id, DecodeError{
+                offset: 0,
+                index: 0,
+                error_type: DecodeErrorType::IllegalSize(17, bytes.len())
+            }));
+        }
+
         let key_type = Type::deserialize(bytes.get_u8())
             .map_err(|unknown_flag| CannotDecodeKeyspaceDescriptor(keyspace_id, DecodeError{
                 offset: 0,
@@ -70,9 +84,12 @@ impl KeyspaceDescriptor {
                 error_type: DecodeErrorType::UnknownFlag(unknown_flag as usize)
             }))?;
         let flags = bytes.get_u64_le();
+        //Descriptors written before TTLs were supported don't contain it
+        let ttl_seconds = if bytes.has_remaining() { bytes.get_u64_le() } else { 0 };
 
         Ok(KeyspaceDescriptor {
             keyspace_id,
+            ttl_seconds,
             key_type,
             flags
         })
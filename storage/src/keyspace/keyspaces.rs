@@ -1,4 +1,5 @@
 use crate::keyspace::keyspace::Keyspace;
+use crate::transactions::transaction::Transaction;
 use crate::transactions::transaction_manager::TransactionManager;
 use crossbeam_skiplist::SkipMap;
 use std::cmp::max;
@@ -6,12 +7,16 @@ use std::fs;
 use std::path::PathBuf;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering::Relaxed;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use shared::{Flag, Type};
+use shared::logger::{logger, SimpleDbLayer};
+use bytes::{Buf, BufMut};
 
 pub struct Keyspaces {
     keyspaces: SkipMap<shared::KeyspaceId, Arc<Keyspace>>,
     next_keyspace_id: AtomicUsize,
+    //There is only one TRUNCATE file, so truncations of different keyspaces are done one at a time
+    truncate_lock: Mutex<()>,
 
     transaction_manager: Arc<TransactionManager>,
     options: Arc<shared::SimpleDbOptions>
@@ -22,6 +27,7 @@ impl Keyspaces {
         Keyspaces {
             keyspaces: SkipMap::new(),
             next_keyspace_id: AtomicUsize::new(0),
+            truncate_lock: Mutex::new(()),
             transaction_manager: Arc::new(TransactionManager::create_mock(options.clone())),
             options
         }
@@ -55,12 +61,41 @@ impl Keyspaces {
 
         Ok(Keyspaces{
             next_keyspace_id: AtomicUsize::new(max_keyspace_id + 1),
+            truncate_lock: Mutex::new(()),
             transaction_manager,
             options,
             keyspaces
         })
     }
 
+    pub fn verify_keyspaces(
+        options: &Arc<shared::SimpleDbOptions>
+    ) -> Result<Vec<shared::SimpleDbError>, shared::SimpleDbError> {
+        let path = PathBuf::from(options.base_path.as_str());
+        let mut corrupted_parts = Vec::new();
+
+        for file in fs::read_dir(path.as_path()).map_err(|e| shared::SimpleDbError::CannotReadKeyspacesDirectories(e))? {
+            let file = file.unwrap();
+            if let Ok(keyspace_id) = file.file_name().to_str().unwrap().parse::<usize>() {
+                let keyspace_id = keyspace_id as shared::KeyspaceId;
+                let is_keyspace = file.metadata()
+                    .map_err(|e| shared::SimpleDbError::CannotReadKeyspaceFile(keyspace_id, e))?
+                    .is_dir();
+                if is_keyspace {
+                    corrupted_parts.extend(Keyspace::verify_files(keyspace_id, options)?);
+                }
+            }
+        }
+
+        Ok(corrupted_parts)
+    }
+
+    pub fn check_integrity(&self) -> Vec<shared::SimpleDbError> {
+        self.keyspaces.iter()
+            .flat_map(|entry| entry.value().check_integrity())
+            .collect()
+    }
+
     pub fn get_keyspaces_id(&self) -> Vec<shared::KeyspaceId> {
         let mut keyspaces = Vec::new();
 
@@ -78,20 +113,102 @@ impl Keyspaces {
         }
     }
 
-    pub fn create_keyspace(&self, flags: Flag, key_type: Type) -> Result<Arc<Keyspace>, shared::SimpleDbError> {
+    pub fn create_keyspace(&self, flags: Flag, key_type: Type, ttl_seconds: u64) -> Result<Arc<Keyspace>, shared::SimpleDbError> {
         let keyspace_id = self.next_keyspace_id.fetch_add(1, Relaxed) as shared::KeyspaceId;
         let keyspace = Keyspace::create_new(
             keyspace_id,
             self.transaction_manager.clone(),
             self.options.clone(),
             flags,
-            key_type
+            key_type,
+            ttl_seconds
         )?;
         self.keyspaces.insert(keyspace_id, keyspace.clone());
 
         Ok(keyspace)
     }
 
+    pub fn delete_keyspace(&self, keyspace_id: shared::KeyspaceId) -> Result<(), shared::SimpleDbError> {
+        match self.keyspaces.remove(&keyspace_id) {
+            Some(entry) => entry.value().delete_files(),
+            None => Err(shared::SimpleDbError::KeyspaceNotFound(keyspace_id))
+        }
+    }
+
+    //Every keyspace is truncated or none of them, even if there is a crash in the middle. The keyspaces to truncate
+    //are written to a temporary file, which is fsynced and renamed to TRUNCATE. If the TRUNCATE file exists when
+    //the storage is started, the truncation is completed by recover_truncation()
+    pub fn truncate_keyspaces(&self, keyspaces_id: &[shared::KeyspaceId]) -> Result<(), shared::SimpleDbError> {
+        let keyspaces = keyspaces_id.iter()
+            .map(|keyspace_id| self.get_keyspace(*keyspace_id))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let _truncate_lock = self.truncate_lock.lock().unwrap();
+        self.write_truncate_file(keyspaces_id)
+            .map_err(shared::SimpleDbError::CannotWriteTruncateFile)?;
+        for keyspace in keyspaces {
+            keyspace.truncate()?;
+        }
+
+        fs::remove_file(self.truncate_file_path())
+            .map_err(shared::SimpleDbError::CannotWriteTruncateFile)
+    }
+
+    pub fn recover_truncation(&self) -> Result<(), shared::SimpleDbError> {
+        let truncate_file_path = self.truncate_file_path();
+        if !truncate_file_path.exists() {
+            return Ok(());
+        }
+
+        let bytes = fs::read(truncate_file_path.as_path())
+            .map_err(shared::SimpleDbError::CannotWriteTruncateFile)?;
+        let mut current_ptr = bytes.as_slice();
+        while current_ptr.has_remaining() {
+            //The keyspace might have been deleted after the crash
+            if let Ok(keyspace) = self.get_keyspace(current_ptr.get_u64_le() as shared::KeyspaceId) {
+                keyspace.truncate()?;
+            }
+        }
+
+        logger().info(SimpleDbLayer::Storage, "Completed truncation interrupted by a crash");
+
+        fs::remove_file(truncate_file_path.as_path())
+            .map_err(shared::SimpleDbError::CannotWriteTruncateFile)
+    }
+
+    fn write_truncate_file(&self, keyspaces_id: &[shared::KeyspaceId]) -> Result<(), std::io::Error> {
+        let mut temporary_path = PathBuf::from(self.options.base_path.as_str());
+        temporary_path.push("TRUNCATE-tmp");
+        let mut serialized = Vec::new();
+        for keyspace_id in keyspaces_id {
+            serialized.put_u64_le(*keyspace_id as u64);
+        }
+
+        let _ = fs::remove_file(temporary_path.as_path());
+        let temporary_file = shared::SimpleDbFile::create(temporary_path.as_path(), &serialized, shared::SimpleDbFileMode::AppendOnly)?;
+        temporary_file.fsync()?;
+        fs::rename(temporary_path.as_path(), self.truncate_file_path())?;
+        shared::utils::fsync_directory(PathBuf::from(self.options.base_path.as_str()).as_path())
+    }
+
+    fn truncate_file_path(&self) -> PathBuf {
+        let mut path = PathBuf::from(self.options.base_path.as_str());
+        path.push("TRUNCATE");
+        path
+    }
+
+    pub fn snapshot(
+        &self,
+        transaction: &Transaction,
+        snapshot_options: &Arc<shared::SimpleDbOptions>
+    ) -> Result<(), shared::SimpleDbError> {
+        for keyspace in self.keyspaces.iter() {
+            keyspace.value().snapshot(transaction, snapshot_options)?;
+        }
+
+        Ok(())
+    }
+
     pub fn start_keyspaces_compaction_threads(&self) {
         for keyspace in self.keyspaces.iter() {
             let keyspace = keyspace.value();
@@ -11,6 +11,7 @@ use crate::keyspace::keyspace_descriptor::KeyspaceDescriptor;
 pub struct Manifest {
     file: Mutex<shared::SimpleDbFile>,
     last_manifest_record_id: AtomicUsize,
+    n_operations_since_checkpoint: AtomicUsize,
     options: Arc<shared::SimpleDbOptions>,
     keyspace_desc: KeyspaceDescriptor
 }
@@ -42,6 +43,7 @@ impl Manifest {
         match shared::SimpleDbFile::open(Self::manifest_path(&options, keyspace_desc.keyspace_id).as_path(), shared::SimpleDbFileMode::AppendOnly) {
             Ok(file) => Ok(Manifest {
                 last_manifest_record_id: AtomicUsize::new(0),
+                n_operations_since_checkpoint: AtomicUsize::new(0),
                 file: Mutex::new(file),
                 keyspace_desc,
                 options
@@ -51,35 +53,78 @@ impl Manifest {
     }
 
     pub fn read_uncompleted_operations(&self) -> Result<Vec<ManifestOperationContent>, shared::SimpleDbError> {
-        let mut all_records = self.read_all_operations_from_disk()?;
-        let uncompleted_operations = self.get_uncompleted_operations(&mut all_records);
-        self.rewrite_manifest(&uncompleted_operations)?;
+        let mut file = self.file.lock().unwrap();
+        let mut all_records = self.read_all_operations(&file)?;
+        let uncompleted_operations: Vec<ManifestOperationContent> = self.get_uncompleted_operations(&mut all_records).into_iter()
+            .map(|operation| operation.content)
+            .collect();
+
+        //The uncompleted operations will be restarted, so they are given new ids
+        let new_operations = uncompleted_operations.iter()
+            .map(|content| ManifestOperation {
+                manifest_operation_id: self.last_manifest_record_id.fetch_add(1, Relaxed),
+                content: content.clone(),
+            })
+            .collect();
+        self.replace_manifest(&mut file, &new_operations)?;
 
         Ok(uncompleted_operations)
     }
 
-    fn rewrite_manifest(&self, uncompleted_operations: &Vec<ManifestOperationContent>) -> Result<(), shared::SimpleDbError> {
-        self.clear_manifest()?;
+    //Removes the completed operations from the manifest, so that recovery only needs to replay the operations
+    //done since the last checkpoint. Called every SimpleDbOptions::manifest_checkpoint_n_operations operations
+    pub fn checkpoint(&self) -> Result<(), shared::SimpleDbError> {
+        let mut file = self.file.lock().unwrap();
+        let mut all_records = self.read_all_operations(&file)?;
+        let uncompleted_operations = self.get_uncompleted_operations(&mut all_records);
+        self.replace_manifest(&mut file, &uncompleted_operations)?;
+        self.n_operations_since_checkpoint.store(0, Relaxed);
 
-        for uncompleted_operation in uncompleted_operations {
-            self.append_operation(uncompleted_operation.clone())?;
-        }
+        Ok(())
+    }
 
+    //Checks that every operation can be decoded, without rewriting the manifest
+    pub fn verify(&self) -> Result<(), shared::SimpleDbError> {
+        let file = self.file.lock().unwrap();
+        self.read_all_operations(&file)?;
         Ok(())
     }
 
-    fn clear_manifest(&self) -> Result<(), shared::SimpleDbError> {
-        let path = Self::manifest_path(&self.options, self.keyspace_desc.keyspace_id);
-        let mut file = shared::SimpleDbFile::open(path.as_path(), shared::SimpleDbFileMode::RandomWrites)
-            .map_err(|e| shared::SimpleDbError::CannotResetManifest(self.keyspace_desc.keyspace_id, e))?;
+    //The new manifest is written to a temporary file, fsynced, and then renamed to the manifest path.
+    //If there is a crash before the directory is fsynced, the previous manifest might be kept
+    fn replace_manifest(
+        &self,
+        file: &mut shared::SimpleDbFile,
+        operations: &Vec<ManifestOperation>
+    ) -> Result<(), shared::SimpleDbError> {
+        let keyspace_id = self.keyspace_desc.keyspace_id;
+        let manifest_path = Self::manifest_path(&self.options, keyspace_id);
+        let checkpoint_path = shared::get_file_usize(&self.options.base_path, keyspace_id, "MANIFEST-checkpoint");
+        let serialized: Vec<u8> = operations.iter()
+            .map(|operation| Self::serialize_operation(operation))
+            .flatten()
+            .collect();
 
-        file.clear()
-            .map_err(|e| shared::SimpleDbError::CannotResetManifest(self.keyspace_desc.keyspace_id, e))
+        let _ = std::fs::remove_file(checkpoint_path.as_path());
+        let checkpoint_file = shared::SimpleDbFile::create(checkpoint_path.as_path(), &serialized, shared::SimpleDbFileMode::AppendOnly)
+            .map_err(|e| shared::SimpleDbError::CannotResetManifest(keyspace_id, e))?;
+        checkpoint_file.fsync()
+            .map_err(|e| shared::SimpleDbError::CannotResetManifest(keyspace_id, e))?;
+        std::fs::rename(checkpoint_path.as_path(), manifest_path.as_path())
+            .map_err(|e| shared::SimpleDbError::CannotResetManifest(keyspace_id, e))?;
+        //Persists the rename
+        shared::utils::fsync_directory(manifest_path.parent().unwrap())
+            .map_err(|e| shared::SimpleDbError::CannotResetManifest(keyspace_id, e))?;
+
+        *file = shared::SimpleDbFile::open(manifest_path.as_path(), shared::SimpleDbFileMode::AppendOnly)
+            .map_err(|e| shared::SimpleDbError::CannotResetManifest(keyspace_id, e))?;
+
+        Ok(())
     }
 
-    fn get_uncompleted_operations(&self, all_operations: &mut Vec<ManifestOperation>) -> Vec<ManifestOperationContent> {
+    fn get_uncompleted_operations(&self, all_operations: &mut Vec<ManifestOperation>) -> Vec<ManifestOperation> {
         let mut operations_by_id: HashMap<usize, ManifestOperation> = HashMap::new();
-        let mut to_return: Vec<ManifestOperationContent> = Vec::new();
+        let mut to_return: Vec<ManifestOperation> = Vec::new();
 
         while let Some(operation) = shared::pop_front(all_operations) {
             match operation.content {
@@ -88,26 +133,24 @@ impl Manifest {
             };
         }
 
-        let operations_id_uncompleted: Vec<usize> = operations_by_id.keys()
+        let mut operations_id_uncompleted: Vec<usize> = operations_by_id.keys()
             .into_iter()
             .map(|key| *key)
             .collect();
+        operations_id_uncompleted.sort();
 
         for operation_id in operations_id_uncompleted {
             let operation = operations_by_id.remove(&operation_id)
                 .unwrap();
-            to_return.push(operation.content);
+            to_return.push(operation);
         }
 
         to_return
     }
 
-    fn read_all_operations_from_disk(&self) -> Result<Vec<ManifestOperation>, shared::SimpleDbError> {
-        let mut file_lock_result = self.file.lock();
-        let file = file_lock_result
-            .as_mut()
-            .unwrap();
-        let records_bytes = file.read_all()
+    fn read_all_operations(&self, file: &shared::SimpleDbFile) -> Result<Vec<ManifestOperation>, shared::SimpleDbError> {
+        //Read from the start, the file cursor is at the end after appending operations
+        let records_bytes = file.read(0, file.size())
             .map_err(|e| shared::SimpleDbError::CannotReadManifestOperations(self.keyspace_desc.keyspace_id, e))?;
         let mut records_bytes_ptr = records_bytes.as_slice();
         let mut all_records: Vec<ManifestOperation> = Vec::new();
@@ -143,7 +186,14 @@ impl Manifest {
     }
 
     pub fn mark_as_completed(&self, operation_id: usize) -> Result<usize, shared::SimpleDbError> {
-        self.append_operation(ManifestOperationContent::Completed(operation_id))
+        let manifest_record_id = self.append_operation(ManifestOperationContent::Completed(operation_id))?;
+
+        let checkpoint_n_operations = self.options.manifest_checkpoint_n_operations;
+        if checkpoint_n_operations > 0 && self.n_operations_since_checkpoint.load(Relaxed) >= checkpoint_n_operations {
+            self.checkpoint()?;
+        }
+
+        Ok(manifest_record_id)
     }
 
     pub fn append_operation(&self, content: ManifestOperationContent) -> Result<usize, shared::SimpleDbError> {
@@ -154,17 +204,22 @@ impl Manifest {
             .unwrap();
         let manifest_record = ManifestOperation { manifest_operation_id: manifest_record_id, content, };
 
-        match serde_json::to_vec(&manifest_record) {
+        file.write(&Self::serialize_operation(&manifest_record))
+            .map_err(|e| shared::SimpleDbError::CannotWriteManifestOperation(self.keyspace_desc.keyspace_id, e))?;
+        let _ = file.fsync(); //We dont care if it fails to fysnc
+        self.n_operations_since_checkpoint.fetch_add(1, Relaxed);
+
+        Ok(manifest_record_id)
+    }
+
+    fn serialize_operation(operation: &ManifestOperation) -> Vec<u8> {
+        match serde_json::to_vec(operation) {
             Ok(record_json_serialized) => {
                 let mut serialized: Vec<u8> = Vec::new();
                 serialized.put_u32_le(record_json_serialized.len() as u32);
                 serialized.put_u32_le(crc32fast::hash(&record_json_serialized));
                 serialized.extend(record_json_serialized);
-
-                file.write(&serialized)
-                    .map_err(|e| shared::SimpleDbError::CannotWriteManifestOperation(self.keyspace_desc.keyspace_id, e))?;
-                let _ = file.fsync(); //We dont care if it fails to fysnc
-                Ok(manifest_record_id)
+                serialized
             }
             //This won't happen since manifest_record does not contain a map with non string keys
             //and Serialization implementation doesn't fail
@@ -175,4 +230,53 @@ impl Manifest {
     fn manifest_path(options: &Arc<shared::SimpleDbOptions>, keyspace_id: shared::KeyspaceId) -> PathBuf {
         shared::get_file_usize(&options.base_path, keyspace_id, "MANIFEST")
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod test {
+    use crate::keyspace::keyspace_descriptor::KeyspaceDescriptor;
+    use crate::manifest::manifest::{Manifest, ManifestOperationContent, MemtableFlushManifestOperation};
+    use shared::Type;
+    use std::sync::Arc;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn checkpoint() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("simpledb-manifest-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()));
+        let options = Arc::new(shared::start_simpledb_options_builder_from(&shared::SimpleDbOptions::default())
+            .base_path(path.to_str().unwrap())
+            .manifest_checkpoint_n_operations(0)
+            .build());
+        let keyspace_desc = KeyspaceDescriptor::create_mock(Type::String);
+        std::fs::create_dir_all(shared::get_file_usize(&options.base_path, keyspace_desc.keyspace_id, "")).unwrap();
+        let flush = |id: usize| ManifestOperationContent::MemtableFlush(MemtableFlushManifestOperation { memtable_id: id, sstable_id: id });
+
+        let manifest = Manifest::create(options.clone(), keyspace_desc).unwrap();
+        for id in 0..100 {
+            let operation_id = manifest.append_operation(flush(id)).unwrap();
+            if id != 10 && id != 50 {
+                manifest.mark_as_completed(operation_id).unwrap();
+            }
+        }
+        assert_eq!(manifest.read_all_operations(&manifest.file.lock().unwrap()).unwrap().len(), 198);
+
+        manifest.checkpoint().unwrap();
+        assert_eq!(manifest.read_all_operations(&manifest.file.lock().unwrap()).unwrap().len(), 2);
+        //Operations appended after the checkpoint are still written to the manifest
+        let operation_id = manifest.append_operation(flush(100)).unwrap();
+        manifest.mark_as_completed(operation_id).unwrap();
+        drop(manifest);
+
+        let manifest = Manifest::create(options.clone(), keyspace_desc).unwrap();
+        assert_eq!(manifest.read_all_operations(&manifest.file.lock().unwrap()).unwrap().len(), 4);
+        let uncompleted_memtable_ids: Vec<usize> = manifest.read_uncompleted_operations().unwrap().into_iter()
+            .map(|operation| match operation {
+                ManifestOperationContent::MemtableFlush(flush) => flush.memtable_id,
+                _ => panic!()
+            })
+            .collect();
+        assert_eq!(uncompleted_memtable_ids, vec![10, 50]);
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+}
@@ -1,7 +1,7 @@
 use crate::keyspace::keyspace_descriptor::KeyspaceDescriptor;
 use crate::memtables::memtable::MemtableState::{Active, Flushed, Flushing, Inactive, RecoveringFromWal};
 use crate::memtables::memtable_iterator::MemtableIterator;
-use crate::memtables::wal::Wal;
+use crate::memtables::wal::{Wal, WalEntryType};
 use crate::sst::sstable_builder::SSTableBuilder;
 use crate::transactions::transaction::Transaction;
 use crate::transactions::transaction_manager::TransactionManager;
@@ -17,7 +17,7 @@ use std::io::Read;
 use std::ops::Bound::Excluded;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering::Relaxed;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 
 pub struct MemTable {
     pub(crate) data: Arc<SkipMap<Key, Bytes>>,
@@ -27,7 +27,10 @@ pub struct MemTable {
     pub(crate) state: UnsafeCell<MemtableState>,
     pub(crate) wal: UnsafeCell<Wal>,
     pub(crate) options: Arc<shared::SimpleDbOptions>,
-    pub(crate) keyspace_desc: KeyspaceDescriptor
+    pub(crate) keyspace_desc: KeyspaceDescriptor,
+    //Held by ROLLBACK TO SAVEPOINT while restoring writes. Taken exclusively before flushing, so a restore
+    //either ends before the memtable is written to an SSTable or sees that it is being flushed
+    pub(crate) flush_lock: RwLock<()>,
 }
 
 pub(crate) enum MemtableState {
@@ -51,6 +54,7 @@ impl MemTable {
             current_size_bytes: AtomicUsize::new(0),
             state: UnsafeCell::new(MemtableState::New),
             data: Arc::new(SkipMap::new()),
+            flush_lock: RwLock::new(()),
             keyspace_desc,
             memtable_id,
             options,
@@ -69,6 +73,7 @@ impl MemTable {
             state: UnsafeCell::new(MemtableState::New),
             data: Arc::new(SkipMap::new()),
             wal: UnsafeCell::new(wal),
+            flush_lock: RwLock::new(()),
             keyspace_desc,
             memtable_id,
             options
@@ -90,6 +95,7 @@ impl MemTable {
             current_size_bytes: AtomicUsize::new(0),
             state: UnsafeCell::new(MemtableState::Active),
             data: Arc::new(SkipMap::new()),
+            flush_lock: RwLock::new(()),
             keyspace_desc,
             memtable_id,
             options,
@@ -105,6 +111,7 @@ impl MemTable {
     }
 
     pub fn set_flushing(&self) {
+        let _flush_lock = self.flush_lock.write().unwrap();
         unsafe { (* self.state.get()) = Flushing; }
     }
 
@@ -155,6 +162,60 @@ impl MemTable {
             TOMBSTONE)
     }
 
+    pub fn get_key_range(&self) -> Option<(Key, Key)> {
+        let first_key = self.data.front()?.key().clone();
+        let last_key = self.data.back()?.key().clone();
+        Some((first_key, last_key))
+    }
+
+    //Returns the nº of different keys, without counting its versions, like SSTables do.
+    //Versions of the same key are stored next to each other, as keys are sorted by bytes and then by txn id
+    pub fn get_n_keys(&self) -> usize {
+        let mut n_keys = 0;
+        let mut previous_key: Option<Key> = None;
+        for entry in self.data.iter() {
+            if previous_key.as_ref().is_none_or(|previous_key| !previous_key.bytes_eq(entry.key())) {
+                n_keys += 1;
+            }
+            previous_key = Some(entry.key().clone());
+        }
+        n_keys
+    }
+
+    //Returns the value written by the transaction to the key in this memtable
+    pub fn get_own_write(&self, key: &Bytes, transaction: &Transaction) -> Option<Bytes> {
+        self.data.get(&Key::create(key.clone(), self.keyspace_desc.key_type, transaction.txn_id))
+            .map(|entry| entry.value().clone())
+    }
+
+    //Used by ROLLBACK TO SAVEPOINT. If value is None, the write of the transaction is removed, so readers
+    //will see the previous versions of the key. The restore is logged in the WAL, so it survives a restart.
+    //Returns false if the memtable is being flushed or has already been flushed to an SSTable
+    pub fn restore_own_write(&self, key: &Bytes, transaction: &Transaction, value: &Option<Bytes>) -> Result<bool, shared::SimpleDbError> {
+        let _flush_lock = self.flush_lock.read().unwrap();
+        if !matches!(unsafe { &*self.state.get() }, Active | Inactive) {
+            return Ok(false);
+        }
+
+        let key = Key::create(key.clone(), self.keyspace_desc.key_type, transaction.txn_id);
+        //Inactive memtables keep their WAL until they are flushed
+        let wal: &mut Wal = unsafe { &mut *self.wal.get() };
+        wal.add_restore_own_write_entry(&key, value)?;
+
+        self.current_size_bytes.fetch_add(key.len() + value.as_ref().map_or(0, |value| value.len()), Relaxed);
+
+        self.restore_into_skiplist(key, value.clone());
+
+        Ok(true)
+    }
+
+    fn restore_into_skiplist(&self, key: Key, value: Option<Bytes>) {
+        match value {
+            Some(value) => { self.data.insert(key, value); },
+            None => { self.data.remove(&key); },
+        };
+    }
+
     fn write(&self, key: &Key, value: Bytes) -> Result<(), shared::SimpleDbError> {
         if !self.can_memtable_be_written() {
             return Ok(());
@@ -210,6 +271,8 @@ impl MemTable {
     }
 
     pub fn to_sst(self: &Arc<MemTable>, transaction_manager: &Arc<TransactionManager>) -> SSTableBuilder {
+        self.set_flushing();
+
         let mut memtable_iterator = MemtableIterator::create(&self, &Transaction::none(), self.keyspace_desc);
         let mut sstable_builder = SSTableBuilder::create(self.options.clone(), self.keyspace_desc, 0);
         sstable_builder.set_memtable_id(self.memtable_id);
@@ -227,23 +290,46 @@ impl MemTable {
         sstable_builder
     }
 
+    //Called when the memtable is dropped without being flushed. Its rolledback writes won't reach
+    //the SSTables, so they are discarded here, otherwise they would be tracked by the transaction manager forever
+    pub fn discard(self: &Arc<MemTable>, transaction_manager: &Arc<TransactionManager>) {
+        let mut memtable_iterator = MemtableIterator::create(self, &Transaction::none(), self.keyspace_desc);
+        while memtable_iterator.next() {
+            let _ = transaction_manager.on_write_key(memtable_iterator.key());
+        }
+
+        self.set_flushed();
+    }
+
     fn recover_from_wal(&mut self) -> Result<(), shared::SimpleDbError> {
         self.set_recovering_from_wal();
         let wal: &Wal = unsafe { &*self.wal.get() };
-        let mut entries = wal.read_entries()?;
+        let entries = wal.read_entries()?;
 
         logger().info(SimpleDbLayer::StorageKeyspace(self.keyspace_desc.keyspace_id), &format!(
             "Applying {} operations from WAL to memtable with ID: {}", entries.len(), wal.get_memtable_id())
         );
 
-        while let Some(entry) = entries.pop() {
+        //Entries are applied in the order they were written, since writes of a transaction to the same key
+        //are merged and savepoint restores replace the writes logged before them
+        for entry in entries {
             logger().debug(SimpleDbLayer::StorageKeyspace(self.keyspace_desc.keyspace_id), &format!(
-                "Applying write operation from WAL Key = (TxnID: {}, Bytes: {}) Value = {}",
-                entry.key.txn_id(), utils::bytes_to_u8_array_string(entry.key.as_bytes()),
+                "Applying {:?} operation from WAL Key = (TxnID: {}, Bytes: {}) Value = {}",
+                entry.entry_type, entry.key.txn_id(), utils::bytes_to_u8_array_string(entry.key.as_bytes()),
                 utils::bytes_to_u8_array_string(&entry.value)
             ));
 
-            self.write(&entry.key, entry.value)?;
+            match entry.entry_type {
+                WalEntryType::Write => self.write(&entry.key, entry.value)?,
+                WalEntryType::RestoreOwnWrite => {
+                    self.current_size_bytes.fetch_add(entry.key.len() + entry.value.len(), Relaxed);
+                    self.restore_into_skiplist(entry.key, Some(entry.value));
+                },
+                WalEntryType::RemoveOwnWrite => {
+                    self.current_size_bytes.fetch_add(entry.key.len(), Relaxed);
+                    self.restore_into_skiplist(entry.key, None);
+                },
+            };
         }
 
         self.set_active();
@@ -298,6 +384,21 @@ mod test {
         assert!(memtable.get(&Bytes::from("nombre"), &Transaction::none()).is_some());
     }
 
+    #[test]
+    fn get_n_keys() {
+        let memtable = MemTable::create_mock(Arc::new(shared::SimpleDbOptions::default()), 0,
+                                             KeyspaceDescriptor::create_mock(Type::String)).unwrap();
+        memtable.set_active();
+        assert_eq!(memtable.get_n_keys(), 0);
+
+        memtable.set(&transaction(1), Bytes::from("alberto"), &[1]).unwrap();
+        memtable.set(&transaction(2), Bytes::from("alberto"), &[2]).unwrap();
+        memtable.delete(&transaction(3), Bytes::from("alberto")).unwrap();
+        memtable.set(&transaction(1), Bytes::from("jaime"), &[3]).unwrap();
+
+        assert_eq!(memtable.get_n_keys(), 2);
+    }
+
     #[test]
     fn get_set_delete_transactions() {
         let memtable = Arc::new(MemTable::create_mock(Arc::new(shared::SimpleDbOptions::default()), 0,
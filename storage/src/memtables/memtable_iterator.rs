@@ -81,6 +81,35 @@ impl MemtableIterator {
         }
     }
 
+    fn get_prev_readable_key(&self) -> Option<(Key, Bytes)> {
+        let mut current_key = self.current_key.clone();
+
+        loop {
+            match self.get_prev_key(&current_key) {
+                Some((key, value)) => {
+                    if self.transaction.can_read(&key) {
+                        return Some((key, value));
+                    } else {
+                        current_key = Some(key);
+                    }
+                },
+                None => {
+                    return None
+                }
+            }
+        }
+    }
+
+    //After seek_to_end() the current key is None, so the previous key is the max key of the memtable
+    fn get_prev_key(&self, next_key: &Option<Key>) -> Option<(Key, Bytes)> {
+        let entry = match next_key {
+            Some(next_key) => self.memtable.data.upper_bound(Excluded(next_key)),
+            None => self.memtable.data.back(),
+        };
+
+        entry.map(|entry| (entry.key().clone(), entry.value().clone()))
+    }
+
     fn is_higher(&self, key: &Key) -> bool {
         if let Some(max_entry) = self.memtable.data.back() {
             return max_entry.key().lt(key)
@@ -141,6 +170,26 @@ impl StorageIterator for MemtableIterator {
             }
         }
     }
+
+    fn seek_to_end(&mut self) {
+        self.current_value = None;
+        self.current_key = None;
+    }
+
+    fn prev(&mut self) -> bool {
+        match self.get_prev_readable_key() {
+            Some((prev_key, prev_value)) => {
+                self.current_key = Some(prev_key);
+                self.current_value = Some(prev_value);
+                true
+            },
+            None => false
+        }
+    }
+
+    fn has_prev(&self) -> bool {
+        self.get_prev_readable_key().is_some()
+    }
 }
 
 #[cfg(test)]
@@ -286,6 +335,30 @@ mod test {
         );
     }
 
+    #[test]
+    fn reversed_iterator_snapshot_isolation() {
+        let memtable = Arc::new(MemTable::create_mock(Arc::new(shared::SimpleDbOptions::default()), 0, KeyspaceDescriptor::create_mock(Type::String))
+            .unwrap());
+        memtable.set_active();
+        memtable.set(&transaction(1), Bytes::from("alberto"), &[]).unwrap();
+        memtable.set(&transaction(4), Bytes::from("alberto"), &[]).unwrap(); //Cannot be read by the transaction, should be ignored
+        memtable.set(&transaction(1), Bytes::from("gonchi"), &[]).unwrap();
+        memtable.set(&transaction(2), Bytes::from("gonchi"), &[]).unwrap();
+        memtable.set(&transaction(5), Bytes::from("wili"), &[]).unwrap(); //Cannot be read by the transaction, should be ignored
+
+        let mut iterator = MemtableIterator::create(&memtable, &transaction_with_iso(3, IsolationLevel::SnapshotIsolation), KeyspaceDescriptor::create_mock(Type::String));
+        iterator.seek_to_end();
+
+        assert!(iterator.prev());
+        assert_eq!(iterator.key().clone(), Key::create_from_str("gonchi", 2));
+        assert!(iterator.prev());
+        assert_eq!(iterator.key().clone(), Key::create_from_str("gonchi", 1));
+        assert!(iterator.prev());
+        assert_eq!(iterator.key().clone(), Key::create_from_str("alberto", 1));
+        assert!(!iterator.has_prev());
+        assert!(!iterator.prev());
+    }
+
     fn transaction(txn_id: shared::TxnId) -> Transaction {
         let mut transaction = Transaction::none();
         transaction.txn_id = txn_id;
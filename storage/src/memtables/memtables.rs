@@ -3,11 +3,14 @@ use crate::memtables::memtable::MemTable;
 use crate::memtables::memtable_iterator::MemtableIterator;
 use crate::memtables::wal::Wal;
 use crate::transactions::transaction::Transaction;
+use crate::transactions::transaction_manager::TransactionManager;
 use bytes::Bytes;
 use shared::iterators::merge_iterator::MergeIterator;
 use std::sync::atomic::Ordering::{Acquire, Relaxed, Release};
 use std::sync::atomic::{AtomicPtr, AtomicUsize};
 use std::sync::{Arc, RwLock};
+use shared::key::Key;
+use crate::utils::key_range::merge_key_ranges;
 
 pub struct Memtables {
     inactive_memtables: AtomicPtr<RwLock<Vec<Arc<MemTable>>>>,
@@ -48,26 +51,75 @@ impl Memtables {
         }
     }
 
-    pub fn set(&self, key: Bytes, value: &[u8], transaction: &Transaction) -> Option<Arc<MemTable>> {
+    //Returns true if any version of the key is stored in the memtables, including tombstones
+    pub fn contains_key(&self, key: &Bytes) -> bool {
+        let mut transaction = Transaction::none();
+        transaction.txn_id = shared::TxnId::MAX - 1;
+        self.get(key, &transaction).is_some()
+    }
+
+    //Returns the memtable to flush if the current one was full
+    pub fn set(&self, key: Bytes, value: &[u8], transaction: &Transaction) -> Result<Option<Arc<MemTable>>, shared::SimpleDbError> {
         unsafe {
             let memtable_ref = (*self.current_memtable.load(Acquire)).clone();
-            let set_result = memtable_ref.set(transaction, key, value);
+            let set_result = memtable_ref.set(transaction, key.clone(), value);
 
             match set_result {
-                Err(_) => self.set_current_memtable_as_inactive(),
-                _ => None
+                //The memtable is full, the write is done in the new one
+                Err(_) => {
+                    let memtable_to_flush = self.set_current_memtable_as_inactive();
+                    (*self.current_memtable.load(Acquire)).set(transaction, key, value)?;
+                    Ok(memtable_to_flush)
+                },
+                _ => Ok(None)
             }
         }
     }
 
-    pub fn delete(&self, key: Bytes, transaction: &Transaction) -> Option<Arc<MemTable>> {
+    //Returns the memtable to flush if the current one was full
+    pub fn delete(&self, key: Bytes, transaction: &Transaction) -> Result<Option<Arc<MemTable>>, shared::SimpleDbError> {
         unsafe {
             let memtable_ref = (*self.current_memtable.load(Acquire)).clone();
-            let delete_result = memtable_ref.delete(transaction, key);
+            let delete_result = memtable_ref.delete(transaction, key.clone());
 
             match delete_result {
-                Err(_) => self.set_current_memtable_as_inactive(),
-                _ => None,
+                //The memtable is full, the write is done in the new one
+                Err(_) => {
+                    let memtable_to_flush = self.set_current_memtable_as_inactive();
+                    (*self.current_memtable.load(Acquire)).delete(transaction, key)?;
+                    Ok(memtable_to_flush)
+                },
+                _ => Ok(None),
+            }
+        }
+    }
+
+    //Returns the ID of the current memtable and the value written to the key by the transaction in it
+    pub fn get_own_write(&self, key: &Bytes, transaction: &Transaction) -> (shared::MemtableId, Option<Bytes>) {
+        unsafe {
+            let memtable_ref = (*self.current_memtable.load(Acquire)).clone();
+            (memtable_ref.get_id(), memtable_ref.get_own_write(key, transaction))
+        }
+    }
+
+    //Returns false if the memtable has already been flushed
+    pub fn restore_own_write(
+        &self,
+        memtable_id: shared::MemtableId,
+        key: &Bytes,
+        transaction: &Transaction,
+        value: &Option<Bytes>,
+    ) -> Result<bool, shared::SimpleDbError> {
+        unsafe {
+            let current_memtable = (*self.current_memtable.load(Acquire)).clone();
+            if current_memtable.get_id() == memtable_id {
+                return current_memtable.restore_own_write(key, transaction, value);
+            }
+
+            let inactive_memtables = (*self.inactive_memtables.load(Acquire)).read().unwrap();
+            match inactive_memtables.iter().find(|memtable| memtable.get_id() == memtable_id) {
+                Some(memtable) => memtable.restore_own_write(key, transaction, value),
+                None => Ok(false),
             }
         }
     }
@@ -91,6 +143,57 @@ impl Memtables {
         }
     }
 
+    //Replaces current_memtable with a new empty one, and discards all memtables with their WALs.
+    //Expect no writes to be running concurrently
+    pub fn truncate(&self, transaction_manager: &Arc<TransactionManager>) {
+        let new_memtable_id = self.next_memtable_id.fetch_add(1, Relaxed) as shared::MemtableId;
+        let new_memtable = MemTable::create_new(self.options.clone(), new_memtable_id, self.keyspace_desc)
+            .expect("Failed to create memtable");
+        new_memtable.set_active();
+        let new_memtable = Box::into_raw(Box::new(Arc::new(new_memtable)));
+        let prev_memtable = self.current_memtable.swap(new_memtable, Release);
+
+        unsafe {
+            let mut lock_result = self.inactive_memtables.load(Acquire).as_mut().unwrap().write();
+            let inactive_memtables = lock_result.as_mut().unwrap();
+            for inactive_memtable in inactive_memtables.drain(..) {
+                inactive_memtable.discard(transaction_manager);
+            }
+
+            (*prev_memtable).discard(transaction_manager);
+        }
+    }
+
+    //Versions of a key are counted once per memtable. A key written in multiple memtables is counted more than once
+    pub fn get_n_keys(&self) -> usize {
+        unsafe {
+            let mut n_keys = (&*self.current_memtable.load(Acquire)).get_n_keys();
+
+            let inactive_memtables_rw_lock = &*self.inactive_memtables.load(Acquire);
+            for memtable in inactive_memtables_rw_lock.read().unwrap().iter() {
+                n_keys += memtable.get_n_keys();
+            }
+
+            n_keys
+        }
+    }
+
+    //Returns the smallest and the biggest key stored in the memtables
+    pub fn get_key_range(&self) -> Option<(Key, Key)> {
+        unsafe {
+            let mut key_range = (&*self.current_memtable.load(Acquire)).get_key_range();
+
+            let inactive_memtables_rw_lock = &*self.inactive_memtables.load(Acquire);
+            for memtable in inactive_memtables_rw_lock.read().unwrap().iter() {
+                if let Some((first_key, last_key)) = memtable.get_key_range() {
+                    key_range = merge_key_ranges(key_range, &first_key, &last_key);
+                }
+            }
+
+            key_range
+        }
+    }
+
     fn create_iterators(&self, transaction: &Transaction) -> Vec<Box<MemtableIterator>> {
         unsafe {
             let mut memtable_iterators: Vec<Box<MemtableIterator>> = Vec::new();
@@ -130,17 +233,29 @@ impl Memtables {
     //Replaces current_memtable with a new one, and moves old current_memtable to self::inactive_memtables vector
     //Returns a memtable to flush
     //This might be called by concurrently, it might fail returing None
+    //Memtable IDs are never given back, so two memtables will never share the same WAL file
     fn set_current_memtable_as_inactive(&self) -> Option<Arc<MemTable>> {
         let new_memtable_id = self.next_memtable_id.fetch_add(1, Relaxed) as shared::MemtableId;
         let new_memtable = MemTable::create_new(self.options.clone(), new_memtable_id, self.keyspace_desc)
             .expect("Failed to create memtable");
         new_memtable.set_active();
-        let new_memtable = Box::into_raw(Box::new(Arc::new(new_memtable)));
         let current_memtable = self.current_memtable.load(Acquire);
 
+        self.replace_current_memtable(current_memtable, new_memtable)
+    }
+
+    //Fails if current_memtable is no longer the current memtable, as another thread has already replaced it
+    fn replace_current_memtable(&self, current_memtable: *mut Arc<MemTable>, new_memtable: MemTable) -> Option<Arc<MemTable>> {
+        let new_memtable = Box::into_raw(Box::new(Arc::new(new_memtable)));
+
         match self.current_memtable.compare_exchange(current_memtable, new_memtable, Release, Relaxed) {
             Ok(prev_memtable) => unsafe { self.move_current_memtable_inactive_list(prev_memtable) },
-            Err(_) => { self.next_memtable_id.fetch_sub(1, Relaxed); None }
+            Err(_) => {
+                //Another thread has already replaced the memtable. The new one was never used, so its WAL can be removed
+                let new_memtable = unsafe { Box::from_raw(new_memtable) };
+                new_memtable.set_flushed();
+                None
+            }
         }
     }
 
@@ -175,7 +290,7 @@ impl Memtables {
     ) -> Result<Memtables, shared::SimpleDbError> {
         let mut active_memtable = None;
         let mut inactive_memtables: Vec<Arc<MemTable>> = Vec::new();
-        let next_memtable_id = max_memtable_id + 1;
+        let mut next_memtable_id = max_memtable_id + 1;
 
         for wal in wals {
             let memtable_id = wal.get_memtable_id();
@@ -197,8 +312,9 @@ impl Memtables {
         let active_memtable = match active_memtable {
             Some(active_memtable) => active_memtable,
             None => {
-                let active_memtable = MemTable::create_new(options.clone(), max_memtable_id + 1, keyspace_desc)?;
+                let active_memtable = MemTable::create_new(options.clone(), next_memtable_id, keyspace_desc)?;
                 active_memtable.set_active();
+                next_memtable_id += 1;
                 active_memtable
             }
         };
@@ -227,4 +343,148 @@ impl Memtables {
             options
         })
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use crate::keyspace::keyspace_descriptor::KeyspaceDescriptor;
+    use crate::memtables::memtable::MemTable;
+    use crate::memtables::memtables::Memtables;
+    use crate::memtables::wal::Wal;
+    use crate::transactions::transaction::Transaction;
+    use bytes::Bytes;
+    use shared::logger::Logger;
+    use shared::{DurabilityLevel, Type};
+    use std::sync::atomic::Ordering::{Acquire, Relaxed};
+    use std::sync::Barrier;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn ids_continue_after_reopen() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("simpledb-memtables-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()));
+        let keyspace_desc = KeyspaceDescriptor::create_mock(Type::String);
+        std::fs::create_dir_all(shared::get_directory_usize(&path.to_str().unwrap().to_string(), keyspace_desc.keyspace_id)).unwrap();
+        //Every write will fill the memtable
+        let options = shared::start_simpledb_options_builder()
+            .base_path(path.to_str().unwrap())
+            .memtable_max_size_bytes(1)
+            .build_arc();
+        Logger::init(options.clone());
+
+        let memtables = Memtables::create_and_recover_from_wal(options.clone(), keyspace_desc).unwrap();
+        memtables.set(Bytes::from("a"), &[1], &Transaction::none()).unwrap();
+        memtables.set(Bytes::from("b"), &[1], &Transaction::none()).unwrap(); //Memtable 0 is full, b is written in memtable 1
+        assert_eq!(current_memtable_id(&memtables), 1);
+
+        //Both WALs are full, so a new memtable will be created
+        let memtables = Memtables::create_and_recover_from_wal(options.clone(), keyspace_desc).unwrap();
+        assert_eq!(current_memtable_id(&memtables), 2);
+        memtables.set(Bytes::from("d"), &[1], &Transaction::none()).unwrap();
+        memtables.set(Bytes::from("e"), &[1], &Transaction::none()).unwrap();
+        assert_eq!(current_memtable_id(&memtables), 3);
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn write_retried_when_memtable_full() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("simpledb-memtables-full-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()));
+        let keyspace_desc = KeyspaceDescriptor::create_mock(Type::String);
+        std::fs::create_dir_all(shared::get_directory_usize(&path.to_str().unwrap().to_string(), keyspace_desc.keyspace_id)).unwrap();
+        //Every write will fill the memtable
+        let options = shared::start_simpledb_options_builder()
+            .base_path(path.to_str().unwrap())
+            .memtable_max_size_bytes(1)
+            .build_arc();
+        Logger::init(options.clone());
+        let memtables = Memtables::create_and_recover_from_wal(options.clone(), keyspace_desc).unwrap();
+
+        memtables.set(Bytes::from("a"), &[1], &Transaction::none()).unwrap();
+        //Memtable 0 is full, b is written in memtable 1
+        memtables.set(Bytes::from("b"), &[2], &Transaction::none()).unwrap();
+        assert_eq!(current_memtable_id(&memtables), 1);
+        assert_eq!(memtables.get(&Bytes::from("b"), &Transaction::none()), Some(Bytes::from(vec![2])));
+
+        //Memtable 1 is full, the delete is done in memtable 2
+        memtables.delete(Bytes::from("a"), &Transaction::none()).unwrap();
+        assert_eq!(current_memtable_id(&memtables), 2);
+        assert!(memtables.contains_key(&Bytes::from("a")));
+        assert_eq!(memtables.get(&Bytes::from("a"), &Transaction::none()), Some(Bytes::new())); //Tombstone
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn lost_rotation_race() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("simpledb-memtables-race-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()));
+        let keyspace_desc = KeyspaceDescriptor::create_mock(Type::String);
+        std::fs::create_dir_all(shared::get_directory_usize(&path.to_str().unwrap().to_string(), keyspace_desc.keyspace_id)).unwrap();
+        let options = shared::start_simpledb_options_builder()
+            .base_path(path.to_str().unwrap())
+            .memtable_max_size_bytes(1)
+            .build_arc();
+        Logger::init(options.clone());
+
+        let memtables = Memtables::create_and_recover_from_wal(options.clone(), keyspace_desc).unwrap();
+        let stale_memtable = memtables.current_memtable.load(Acquire);
+        memtables.set(Bytes::from("a"), &[1], &Transaction::none()).unwrap();
+        memtables.set(Bytes::from("b"), &[1], &Transaction::none()).unwrap(); //Memtable 0 is full, memtable 1 is created
+
+        //Another writer that saw memtable 0 as the current one loses the race with memtable 2
+        let lost_memtable_id = memtables.next_memtable_id.fetch_add(1, Relaxed);
+        let lost_memtable = MemTable::create_new(options.clone(), lost_memtable_id, keyspace_desc).unwrap();
+        assert!(memtables.replace_current_memtable(stale_memtable, lost_memtable).is_none());
+        assert_eq!(current_memtable_id(&memtables), 1);
+
+        //Its WAL is removed and its ID is not given back
+        let (wals, _) = Wal::get_persisted_wal_id(&options, keyspace_desc).unwrap();
+        let mut wal_memtable_ids: Vec<usize> = wals.iter().map(|wal| wal.get_memtable_id()).collect();
+        wal_memtable_ids.sort();
+        assert_eq!(wal_memtable_ids, vec![0, 1]);
+        memtables.set(Bytes::from("c"), &[1], &Transaction::none()).unwrap();
+        assert_eq!(current_memtable_id(&memtables), 3);
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn group_commit() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("simpledb-memtables-group-commit-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()));
+        let keyspace_desc = KeyspaceDescriptor::create_mock(Type::String);
+        std::fs::create_dir_all(shared::get_directory_usize(&path.to_str().unwrap().to_string(), keyspace_desc.keyspace_id)).unwrap();
+        //Writers never wait long enough to fsync on their own
+        let options = shared::start_simpledb_options_builder()
+            .base_path(path.to_str().unwrap())
+            .durability_level(DurabilityLevel::Group)
+            .wal_group_commit(5, 60 * 60 * 1000)
+            .build_arc();
+        Logger::init(options.clone());
+        let memtables = Memtables::create_and_recover_from_wal(options.clone(), keyspace_desc).unwrap();
+        let barrier = Barrier::new(10);
+
+        std::thread::scope(|scope| {
+            for i in 0..10 {
+                let memtables = &memtables;
+                let barrier = &barrier;
+                scope.spawn(move || {
+                    barrier.wait();
+                    memtables.set(Bytes::from(format!("{:02}", i)), &[1], &Transaction::none()).unwrap();
+                });
+            }
+        });
+
+        let current_memtable = unsafe { (*memtables.current_memtable.load(Acquire)).clone() };
+        assert_eq!(unsafe { (*current_memtable.wal.get()).get_n_group_commit_fsyncs() }, 2);
+        assert_eq!(memtables.get_n_keys(), 10);
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    fn current_memtable_id(memtables: &Memtables) -> usize {
+        unsafe { (*memtables.current_memtable.load(Acquire)).get_id() }
+    }
+}
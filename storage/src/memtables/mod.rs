@@ -3,3 +3,4 @@ pub mod memtable;
 pub mod memtable_iterator;
 
 mod wal;
+mod wal_group_commit;
@@ -7,17 +7,35 @@ use std::sync::Arc;
 use shared::key::Key;
 use shared::{SimpleDbError, SimpleDbFile, Type};
 use crate::keyspace::keyspace_descriptor::KeyspaceDescriptor;
+use crate::memtables::wal_group_commit::WalGroupCommit;
 
 pub struct Wal {
     options: Arc<shared::SimpleDbOptions>,
     memtable_id: shared::MemtableId,
     file: shared::SimpleDbFile,
-    keyspace_desc: KeyspaceDescriptor
+    keyspace_desc: KeyspaceDescriptor,
+    group_commit: Option<WalGroupCommit>, //Some if durability level is DurabilityLevel::Group
 }
 
+//Stored in the high bits of the value length. Entries written before ROLLBACK TO SAVEPOINT was logged don't have them
+const WAL_RESTORE_OWN_WRITE_FLAG: u32 = 0x8000_0000;
+const WAL_REMOVE_OWN_WRITE_FLAG: u32 = 0x4000_0000;
+const WAL_VALUE_LENGTH_MASK: u32 = 0x3FFF_FFFF;
+
 pub(crate) struct WalEntry {
     pub key: Key,
-    pub value: Bytes
+    pub value: Bytes,
+    pub entry_type: WalEntryType,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum WalEntryType {
+    //Regular write. It gets merged with the previous write of the transaction to the key
+    Write,
+    //ROLLBACK TO SAVEPOINT. The write of the transaction to the key is replaced with the value
+    RestoreOwnWrite,
+    //ROLLBACK TO SAVEPOINT. The write of the transaction to the key is removed
+    RemoveOwnWrite,
 }
 
 impl Wal {
@@ -29,6 +47,7 @@ impl Wal {
         Ok(Wal {
             file: SimpleDbFile::open(Self::to_wal_file_path(&options, memtable_id, keyspace_desc.keyspace_id).as_path(), shared::SimpleDbFileMode::AppendOnly)
                 .map_err(|e| shared::SimpleDbError::CannotCreateWal(keyspace_desc.keyspace_id, memtable_id, e))?,
+            group_commit: Self::create_group_commit(&options),
             options,
             keyspace_desc,
             memtable_id,
@@ -42,23 +61,53 @@ impl Wal {
         Ok(Wal {
             keyspace_desc: KeyspaceDescriptor::create_mock(Type::String),
             file: shared::SimpleDbFile::create_mock(),
+            group_commit: Self::create_group_commit(&options),
             memtable_id,
             options,
         })
     }
 
     pub fn add_entry(&mut self, key: &Key, value: &[u8]) -> Result<(), shared::SimpleDbError> {
-        let encoded = self.encode(key, value);
-        self.file.write(&encoded)
+        let encoded = self.encode(key, value, 0);
+        self.write_encoded_entry(&encoded)
+    }
+
+    //Compensating entry of ROLLBACK TO SAVEPOINT, so that the rollback survives a restart.
+    //If value is None, the write of the transaction to the key is removed
+    pub fn add_restore_own_write_entry(&mut self, key: &Key, value: &Option<Bytes>) -> Result<(), shared::SimpleDbError> {
+        let encoded = match value {
+            Some(value) => self.encode(key, value, WAL_RESTORE_OWN_WRITE_FLAG),
+            None => self.encode(key, &[], WAL_REMOVE_OWN_WRITE_FLAG),
+        };
+        self.write_encoded_entry(&encoded)
+    }
+
+    fn write_encoded_entry(&mut self, encoded: &[u8]) -> Result<(), shared::SimpleDbError> {
+        self.file.write(encoded)
             .map_err(|e| shared::SimpleDbError::CannotWriteWalEntry(self.keyspace_desc.keyspace_id, self.memtable_id, e))?;
 
-        if matches!(self.options.durability_level, shared::DurabilityLevel::Strong) {
-            let _ = self.file.fsync();
+        match self.options.durability_level {
+            shared::DurabilityLevel::Strong => { let _ = self.file.fsync(); },
+            shared::DurabilityLevel::Group => self.group_commit.as_ref().unwrap().on_entry_written(&|| self.file.fsync())
+                .map_err(|e| shared::SimpleDbError::CannotWriteWalEntry(self.keyspace_desc.keyspace_id, self.memtable_id, e))?,
+            shared::DurabilityLevel::Weak => {},
         }
 
         Ok(())
     }
 
+    fn create_group_commit(options: &Arc<shared::SimpleDbOptions>) -> Option<WalGroupCommit> {
+        match options.durability_level {
+            shared::DurabilityLevel::Group => Some(WalGroupCommit::create(options)),
+            _ => None
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn get_n_group_commit_fsyncs(&self) -> usize {
+        self.group_commit.as_ref().map_or(0, |group_commit| group_commit.get_n_fsyncs())
+    }
+
     pub(crate) fn read_entries(&self) -> Result<Vec<WalEntry>, shared::SimpleDbError> {
         let entries = self.file.read_all()
             .map_err(|e| shared::SimpleDbError::CannotReadWalEntries(self.keyspace_desc.keyspace_id, self.memtable_id, e))?;
@@ -79,7 +128,8 @@ impl Wal {
             entry_bytes_size = entry_bytes_size + key_len;
             let key = Key::create(Bytes::from(key_bytes.to_vec()), self.keyspace_desc.key_type, key_timestmap);
 
-            let value_len = current_ptr.get_u32_le() as usize;
+            let value_len_flags = current_ptr.get_u32_le();
+            let value_len = (value_len_flags & WAL_VALUE_LENGTH_MASK) as usize;
             entry_bytes_size = entry_bytes_size + 4;
             let value_bytes = &current_ptr[..value_len];
             current_ptr.advance(value_len);
@@ -99,6 +149,7 @@ impl Wal {
 
             entries.push(WalEntry{
                 value: Bytes::copy_from_slice(value_bytes),
+                entry_type: Self::decode_entry_type(value_len_flags),
                 key
             });
 
@@ -137,6 +188,7 @@ impl Wal {
                 wals.push(Wal{
                     file: shared::SimpleDbFile::open(file.path().as_path(), shared::SimpleDbFileMode::AppendOnly)
                         .map_err(|e| shared::SimpleDbError::CannotReadWalFiles(keyspace_desc.keyspace_id, e))?,
+                    group_commit: Self::create_group_commit(options),
                     options: options.clone(),
                     keyspace_desc,
                     memtable_id,
@@ -147,14 +199,24 @@ impl Wal {
         Ok((wals, max_memtable_id))
     }
 
-    fn encode(&self, key: &Key, value: &[u8]) -> Vec<u8> {
+    fn decode_entry_type(value_len_flags: u32) -> WalEntryType {
+        if value_len_flags & WAL_REMOVE_OWN_WRITE_FLAG != 0 {
+            WalEntryType::RemoveOwnWrite
+        } else if value_len_flags & WAL_RESTORE_OWN_WRITE_FLAG != 0 {
+            WalEntryType::RestoreOwnWrite
+        } else {
+            WalEntryType::Write
+        }
+    }
+
+    fn encode(&self, key: &Key, value: &[u8], flags: u32) -> Vec<u8> {
         let mut encoded: Vec<u8> = Vec::new();
         //Key
         encoded.put_u32_le(key.len() as u32);
         encoded.put_u64_le(key.txn_id() as u64);
         encoded.extend(key.as_bytes());
         //Value
-        encoded.put_u32_le(value.len() as u32);
+        encoded.put_u32_le(value.len() as u32 | flags);
         encoded.extend(value);
 
         encoded.put_u32_le(crc32fast::hash(&encoded));
@@ -178,4 +240,4 @@ impl Wal {
         let wal_file_name = format!("wal-{}", memtable_id);
         shared::get_file_usize(&options.base_path, keyspace_id, wal_file_name.as_str())
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,146 @@
+use std::cmp::max;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+//Used by the WAL when the durability level is DurabilityLevel::Group. Every writer waits until its entry has been
+//fsynced, but writers don't fsync one by one. Entries are grouped until n_entries_per_fsync of them are pending, or
+//until a writer has waited interval. Then one of the waiting writers becomes the leader and fsyncs the file, which
+//persists every entry written until then. Writers that arrive while the fsync is running wait for the next group
+pub(crate) struct WalGroupCommit {
+    state: Mutex<WalGroupCommitState>,
+    group_changed: Condvar,
+    n_entries_per_fsync: u64,
+    interval: Duration,
+    n_fsyncs: AtomicUsize,
+}
+
+struct WalGroupCommitState {
+    n_written_entries: u64,
+    n_fsynced_entries: u64, //Entries persisted by the last finished fsync
+    fsync_in_progress: bool,
+}
+
+impl WalGroupCommit {
+    pub(crate) fn create(options: &shared::SimpleDbOptions) -> WalGroupCommit {
+        WalGroupCommit {
+            state: Mutex::new(WalGroupCommitState {
+                n_written_entries: 0,
+                n_fsynced_entries: 0,
+                fsync_in_progress: false,
+            }),
+            group_changed: Condvar::new(),
+            n_entries_per_fsync: max(options.wal_group_commit_n_entries, 1) as u64,
+            interval: Duration::from_millis(options.wal_group_commit_interval_ms as u64),
+            n_fsyncs: AtomicUsize::new(0),
+        }
+    }
+
+    //Expect the entry to have been written to the WAL file. Blocks until it has been fsynced.
+    //fsync is only called by the leader of each group
+    pub(crate) fn on_entry_written(
+        &self,
+        fsync: &dyn Fn() -> Result<(), std::io::Error>
+    ) -> Result<(), std::io::Error> {
+        let deadline = Instant::now() + self.interval;
+        let mut state = self.state.lock().unwrap();
+        state.n_written_entries += 1;
+        let entry_number = state.n_written_entries;
+
+        while state.n_fsynced_entries < entry_number {
+            if state.fsync_in_progress {
+                state = self.group_changed.wait(state).unwrap();
+                continue;
+            }
+
+            let now = Instant::now();
+            let n_pending_entries = state.n_written_entries - state.n_fsynced_entries;
+            if n_pending_entries < self.n_entries_per_fsync && now < deadline {
+                //The writer that completes the group will take the lead, no need to notify the others
+                state = self.group_changed.wait_timeout(state, deadline - now).unwrap().0;
+                continue;
+            }
+
+            //Entries counted before the fsync starts have already been written to the file
+            state.fsync_in_progress = true;
+            let n_entries_to_fsync = state.n_written_entries;
+            drop(state);
+
+            let result = fsync();
+            self.n_fsyncs.fetch_add(1, Ordering::Relaxed);
+
+            state = self.state.lock().unwrap();
+            state.fsync_in_progress = false;
+            if result.is_ok() {
+                state.n_fsynced_entries = n_entries_to_fsync;
+            }
+            self.group_changed.notify_all();
+            //The other waiting writers will try to fsync again
+            result?;
+        }
+
+        Ok(())
+    }
+
+    #[cfg(test)]
+    pub(crate) fn get_n_fsyncs(&self) -> usize {
+        self.n_fsyncs.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::memtables::wal_group_commit::WalGroupCommit;
+    use std::sync::{Arc, Barrier};
+
+    #[test]
+    fn writers_grouped_by_n_entries() {
+        //Writers never wait long enough to fsync on their own
+        let options = shared::start_simpledb_options_builder()
+            .wal_group_commit(4, 60 * 60 * 1000)
+            .build();
+        let group_commit = Arc::new(WalGroupCommit::create(&options));
+        let barrier = Arc::new(Barrier::new(8));
+        let mut writers = Vec::new();
+
+        for _ in 0..8 {
+            let group_commit = group_commit.clone();
+            let barrier = barrier.clone();
+            writers.push(std::thread::spawn(move || {
+                barrier.wait();
+                group_commit.on_entry_written(&|| Ok(()))
+            }));
+        }
+        for writer in writers {
+            writer.join().unwrap().unwrap();
+        }
+
+        assert_eq!(group_commit.get_n_fsyncs(), 2);
+    }
+
+    #[test]
+    fn writer_fsyncs_after_interval() {
+        let options = shared::start_simpledb_options_builder()
+            .wal_group_commit(64, 1)
+            .build();
+        let group_commit = WalGroupCommit::create(&options);
+
+        //The group is never completed, so every entry is fsynced once its writer has waited the interval
+        group_commit.on_entry_written(&|| Ok(())).unwrap();
+        group_commit.on_entry_written(&|| Ok(())).unwrap();
+
+        assert_eq!(group_commit.get_n_fsyncs(), 2);
+    }
+
+    #[test]
+    fn failed_fsync() {
+        let options = shared::start_simpledb_options_builder()
+            .wal_group_commit(1, 0)
+            .build();
+        let group_commit = WalGroupCommit::create(&options);
+        assert!(group_commit.on_entry_written(&|| Err(std::io::Error::other("fsync"))).is_err());
+        //The entry wasn't persisted, so the next writer fsyncs it again
+        group_commit.on_entry_written(&|| Ok(())).unwrap();
+        assert_eq!(group_commit.get_n_fsyncs(), 2);
+    }
+}
@@ -12,11 +12,15 @@ pub const NORMAL_BLOCK: Flag = 0x00;
 //If the block is annotated with, last entry is overflow (the next blocks will contain the entry bytes)
 pub const OVERFLOW_BLOCK: Flag = 0x01;
 pub const LAST_OVERFLOW_BLOCK: Flag = 0x02;
+//Since this SSTable format version, the first byte of the serialized flags contains the compression flag and the
+//next ones the block flag. Before, they only contained the compression flag, so the block flag was lost
+pub const BLOCK_FLAG_FORMAT_VERSION: u8 = 1;
 
 pub const BLOCK_FOOTER_LENGTH: usize =
     std::mem::size_of::<u16>() + //Nº Entries
         std::mem::size_of::<u16>() + //Offset entries in the block
-        std::mem::size_of::<u64>(); //Flags
+        std::mem::size_of::<u64>() + //Flags
+        std::mem::size_of::<u32>(); //CRC
 
 #[derive(Clone)]
 pub struct Block {
@@ -28,6 +32,23 @@ pub struct Block {
 }
 
 impl Block {
+    pub(crate) fn create_empty(keyspace_desc: KeyspaceDescriptor) -> Block {
+        Block {
+            entries: Vec::new(),
+            offsets: Vec::new(),
+            flag: NORMAL_BLOCK,
+            keyspace_desc,
+        }
+    }
+
+    //The CRC covers every byte of the block except the CRC itself, which is stored just before the flags
+    pub(crate) fn calculate_crc(encoded: &Vec<u8>, block_size_bytes: usize) -> u32 {
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(&encoded[..block_size_bytes - 16]);
+        hasher.update(&encoded[block_size_bytes - 12..]);
+        hasher.finalize()
+    }
+
     pub fn has_flag(&self, value: Flag) -> bool {
         self.flag.has(value)
     }
@@ -168,8 +189,9 @@ impl Block {
 #[cfg(test)]
 mod test {
     use crate::keyspace::keyspace_descriptor::KeyspaceDescriptor;
-    use crate::sst::block::block::Block;
+    use crate::sst::block::block::{Block, NORMAL_BLOCK, OVERFLOW_BLOCK, PREFIX_COMPRESSED};
     use crate::sst::block::block_builder::BlockBuilder;
+    use crate::sst::sstable::{LEGACY_SSTABLE_FORMAT_VERSION, SSTABLE_FORMAT_VERSION};
     use bytes::Bytes;
     use shared::key::Key;
     use shared::Type;
@@ -190,7 +212,7 @@ mod test {
 
         let encoded = block.serialize(&Arc::new(shared::SimpleDbOptions::default()));
 
-        let decoded_block_to_test = Block::deserialize(&encoded, &Arc::new(shared::SimpleDbOptions::default()), KeyspaceDescriptor::create_mock(Type::String))
+        let decoded_block_to_test = Block::deserialize(&encoded, shared::SimpleDbOptions::default().block_size_bytes, SSTABLE_FORMAT_VERSION, KeyspaceDescriptor::create_mock(Type::String))
             .unwrap();
 
         assert_eq!(decoded_block_to_test.get_key_by_index(0).to_string(), String::from("Jaime"));
@@ -208,4 +230,27 @@ mod test {
         assert_eq!(decoded_block_to_test.get_key_by_index(6).to_string(), String::from("Kia"));
         assert_eq!(decoded_block_to_test.get_value_by_index(6).0, vec![7]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn serialize_deserialize_block_flag() {
+        let options = Arc::new(shared::SimpleDbOptions::default());
+        let mut block_builder = BlockBuilder::create(options.clone(), KeyspaceDescriptor::create_mock(Type::String));
+        block_builder.add_entry(&Key::create_from_str("Jaime", 1), &Bytes::from(vec![1])).unwrap();
+        block_builder.add_entry(&Key::create_from_str("Javier", 1), &Bytes::from(vec![2])).unwrap();
+        let mut block = block_builder.build().remove(0);
+        block.flag = OVERFLOW_BLOCK;
+        let mut encoded = block.serialize(&options);
+
+        let decoded = Block::deserialize(&encoded, options.block_size_bytes, SSTABLE_FORMAT_VERSION, KeyspaceDescriptor::create_mock(Type::String))
+            .unwrap();
+        assert!(decoded.has_flag(OVERFLOW_BLOCK));
+        assert_eq!(decoded.get_key_by_index(1).to_string(), String::from("Javier"));
+
+        //Blocks of legacy SSTables only stored the compression flag
+        shared::u64_to_u8_le(PREFIX_COMPRESSED, options.block_size_bytes - 12, &mut encoded);
+        let decoded = Block::deserialize(&encoded, options.block_size_bytes, LEGACY_SSTABLE_FORMAT_VERSION, KeyspaceDescriptor::create_mock(Type::String))
+            .unwrap();
+        assert_eq!(decoded.flag, NORMAL_BLOCK);
+        assert_eq!(decoded.get_key_by_index(1).to_string(), String::from("Javier"));
+    }
+}
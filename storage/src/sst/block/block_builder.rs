@@ -8,6 +8,7 @@ use std::sync::Arc;
 pub struct BlockBuilder {
     entries: Vec<Entry>,
     current_size_bytes: usize,
+    has_overflow_entry: bool,
     options: Arc<shared::SimpleDbOptions>,
     keyspace_desc: KeyspaceDescriptor
 }
@@ -21,22 +22,29 @@ impl BlockBuilder {
     pub fn create(options: Arc<shared::SimpleDbOptions>, keyspace_desc: KeyspaceDescriptor) -> BlockBuilder {
         BlockBuilder {
             current_size_bytes: BLOCK_FOOTER_LENGTH,
+            has_overflow_entry: false,
             entries: Vec::new(),
             keyspace_desc,
             options,
         }
     }
 
-    //Returns err if the block cannot contain more values. This occurs when the max block size have been exceeded
+    //Returns err if the entry hasn't been added, because the max block size would be exceeded. An entry whose value
+    //doesn't fit in one block is added, but it has to be the last one, as it will be split between multiple blocks
     pub fn add_entry(&mut self, key: &Key, value: &Bytes) -> Result<(), ()> {
         //The block is full, there is no room for a new key
-        if self.current_size_bytes + self.calculate_key_size(&key) >= self.options.block_size_bytes {
+        if self.has_overflow_entry || self.current_size_bytes + self.calculate_key_size(&key) >= self.options.block_size_bytes {
             return Err(());
         }
         //This entry overflows a block size
         if self.does_entry_overflows_block(&key, &value) {
+            //At least one byte of the value has to fit in the current block
+            if self.current_size_bytes + self.calculate_entry_size(&key, &Bytes::new()) >= self.options.block_size_bytes {
+                return Err(());
+            }
             self.entries.push(Entry { key: key.clone(), value: value.clone() });
-            return Err(());
+            self.has_overflow_entry = true;
+            return Ok(());
         }
 
         let new_size = self.current_size_bytes + self.calculate_entry_size(&key, &value);
@@ -98,8 +106,9 @@ impl BlockBuilder {
 
         while current_overflow_value_bytes_written < total_overflow_value_bytes_to_write {
             //Can add part of the overflow bytes in the current block?
-            if current_block_size + self.calculate_key_size(&overflow_entry.key) < self.options.block_size_bytes {
-                let value_size_bytes_available_to_write = self.options.block_size_bytes - current_block_size - self.calculate_key_size(&overflow_entry.key);
+            let entry_size_without_value = self.calculate_entry_size(&overflow_entry.key, &Bytes::new());
+            if current_block_size + entry_size_without_value < self.options.block_size_bytes {
+                let value_size_bytes_available_to_write = self.options.block_size_bytes - current_block_size - entry_size_without_value;
                 let bytes_to_write = overflow_entry.value.slice(
                     current_overflow_value_bytes_written..
                      min(current_overflow_value_bytes_written + value_size_bytes_available_to_write, total_overflow_value_bytes_to_write)
@@ -183,16 +192,19 @@ mod test {
             .block_size_bytes(64)
             .build_arc();
 
-        let mut block_builder = BlockBuilder::create(options, KeyspaceDescriptor::create_mock(Type::String));
-        block_builder.add_entry(&Key::create_from_str("a", 1), &Bytes::from(vec![1, 2])); //Doest not overflow
-        block_builder.add_entry(&Key::create_from_str("b", 1), &Bytes::from(vec![4])); //Doest not overflows
-        block_builder.add_entry(&Key::create_from_str("c", 1), &Bytes::from("a".repeat(64))); //Overflows
+        let mut block_builder = BlockBuilder::create(options.clone(), KeyspaceDescriptor::create_mock(Type::String));
+        block_builder.add_entry(&Key::create_from_str("a", 1), &Bytes::from(vec![1])).unwrap(); //Doest not overflow
+        block_builder.add_entry(&Key::create_from_str("b", 1), &Bytes::from(vec![4])).unwrap(); //Doest not overflows
+        block_builder.add_entry(&Key::create_from_str("c", 1), &Bytes::from("a".repeat(64))).unwrap(); //Overflows
 
         let blocks = block_builder.build();
         assert_eq!(blocks.len(), 3);
+        for block in &blocks {
+            assert_eq!(block.serialize(&options).len(), 64);
+        }
 
         let (a_value, a_value_is_overflow) = blocks[0].get_value(&Bytes::from("a"), &Transaction::create(1)).unwrap();
-        assert_eq!(a_value, &Bytes::from(vec![1, 2]));
+        assert_eq!(a_value, &Bytes::from(vec![1]));
         assert!(!a_value_is_overflow);
 
         let (b_value, b_value_is_overflow) = blocks[0].get_value(&Bytes::from("b"), &Transaction::create(1)).unwrap();
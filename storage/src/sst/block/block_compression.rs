@@ -0,0 +1,49 @@
+use bytes::BufMut;
+use shared::{DecodeErrorType, SSTableCompression};
+
+//Stored in the SSTable file
+pub const NO_COMPRESSION_ID: u8 = 0x00;
+pub const LZ4_COMPRESSION_ID: u8 = 0x01;
+
+//Blocks of SSTables without compression are stored with block_size_bytes. Compressed blocks are stored as
+//compressed size (u32) + compressed bytes, so their size in the file depends on their content
+pub(crate) fn compress_block(serialized_block: Vec<u8>, compression: SSTableCompression) -> Vec<u8> {
+    match compression {
+        SSTableCompression::None => serialized_block,
+        SSTableCompression::Lz4 => {
+            let compressed = lz4_flex::block::compress(&serialized_block);
+            let mut stored = Vec::with_capacity(compressed.len() + 4);
+            stored.put_u32_le(compressed.len() as u32);
+            stored.extend(compressed);
+            stored
+        },
+    }
+}
+
+//compressed doesn't include the compressed size. Returns the serialized block with block_size_bytes
+pub(crate) fn decompress_block(
+    compressed: &[u8],
+    compression: SSTableCompression,
+    block_size_bytes: usize
+) -> Result<Vec<u8>, DecodeErrorType> {
+    match compression {
+        SSTableCompression::None => Ok(compressed.to_vec()),
+        SSTableCompression::Lz4 => lz4_flex::block::decompress(compressed, block_size_bytes)
+            .map_err(|e| DecodeErrorType::Decompression(e.to_string())),
+    }
+}
+
+pub(crate) fn compression_to_id(compression: SSTableCompression) -> u8 {
+    match compression {
+        SSTableCompression::None => NO_COMPRESSION_ID,
+        SSTableCompression::Lz4 => LZ4_COMPRESSION_ID,
+    }
+}
+
+pub(crate) fn compression_from_id(id: u8) -> Result<SSTableCompression, DecodeErrorType> {
+    match id {
+        NO_COMPRESSION_ID => Ok(SSTableCompression::None),
+        LZ4_COMPRESSION_ID => Ok(SSTableCompression::Lz4),
+        _ => Err(DecodeErrorType::UnknownFlag(id as usize)),
+    }
+}
@@ -1,30 +1,46 @@
-use std::sync::Arc;
 use bytes::{BufMut, Bytes};
-use block::{NOT_COMPRESSED, PREFIX_COMPRESSED};
+use block::{BLOCK_FLAG_FORMAT_VERSION, NORMAL_BLOCK, NOT_COMPRESSED, PREFIX_COMPRESSED};
 use shared::key::Key;
 use shared::{Flag, Type};
 use crate::keyspace::keyspace_descriptor::KeyspaceDescriptor;
 use crate::sst::block::block;
 use crate::sst::block::block::Block;
+use crate::sst::sstable::LEGACY_SSTABLE_FORMAT_VERSION;
 
 impl Block {
+    //format_version is the one of the SSTable. Blocks of legacy SSTables don't have CRC, and before
+    //BLOCK_FLAG_FORMAT_VERSION, their flags only contain the compression flag
     pub fn deserialize(
         encoded: &Vec<u8>,
-        options: &Arc<shared::SimpleDbOptions>,
+        block_size_bytes: usize,
+        format_version: u8,
         keyspace_desc: KeyspaceDescriptor
     ) -> Result<Block, shared::DecodeErrorType> {
-        if encoded.len() != options.block_size_bytes {
-            return Err(shared::DecodeErrorType::IllegalSize(options.block_size_bytes, encoded.len()));
+        if encoded.len() != block_size_bytes {
+            return Err(shared::DecodeErrorType::IllegalSize(block_size_bytes, encoded.len()));
         }
 
-        let flag: Flag = shared::u8_vec_to_u64_le(&encoded, options.block_size_bytes - 12);
-        let offsets_offset: u16 = shared::u8_vec_to_u16_le(&encoded, options.block_size_bytes - 2);
-        let n_entries: u16 = shared::u8_vec_to_u16_le(&encoded, options.block_size_bytes - 4);
+        if format_version != LEGACY_SSTABLE_FORMAT_VERSION {
+            let expected_crc = shared::u8_vec_to_u32_le(encoded, block_size_bytes - 16);
+            let actual_crc = Block::calculate_crc(encoded, block_size_bytes);
+            if expected_crc != actual_crc {
+                return Err(shared::DecodeErrorType::CorruptedCrc(expected_crc, actual_crc));
+            }
+        }
+
+        let serialized_flag: Flag = shared::u8_vec_to_u64_le(encoded, block_size_bytes - 12);
+        let (compression_flag, flag) = if format_version < BLOCK_FLAG_FORMAT_VERSION {
+            (serialized_flag, NORMAL_BLOCK)
+        } else {
+            (serialized_flag & 0xFF, serialized_flag >> 8)
+        };
+        let offsets_offset: u16 = shared::u8_vec_to_u16_le(&encoded, block_size_bytes - 2);
+        let n_entries: u16 = shared::u8_vec_to_u16_le(&encoded, block_size_bytes - 4);
         let offsets = Self::deserialize_offsets(encoded, offsets_offset, n_entries);
-        let (entries, new_offsets) = match flag {
+        let (entries, new_offsets) = match compression_flag {
             PREFIX_COMPRESSED => Ok(Self::deserialize_entries_prefix_compressed(encoded, &offsets, keyspace_desc.key_type)),
             NOT_COMPRESSED => Ok((Self::deserialize_entries_not_compressed(encoded, offsets_offset), offsets)),
-            _ => Err(shared::DecodeErrorType::UnknownFlag(flag as usize)),
+            _ => Err(shared::DecodeErrorType::UnknownFlag(compression_flag as usize)),
         }?;
 
         Ok(Block{ offsets: new_offsets, entries, keyspace_desc, flag })
@@ -1,5 +1,5 @@
 use crate::keyspace::keyspace_descriptor::KeyspaceDescriptor;
-use crate::sst::block::block::Block;
+use crate::sst::block::block::{Block, LAST_OVERFLOW_BLOCK};
 use bytes::Bytes;
 use shared::iterators::storage_iterator::StorageIterator;
 use shared::key::Key;
@@ -35,6 +35,11 @@ impl BlockIterator {
         self.is_current_value_overflow
     }
 
+    //The only entry of the block is the last part of an overflow value, which starts in previous blocks
+    pub fn is_last_overflow_block(&self) -> bool {
+        self.block.has_flag(LAST_OVERFLOW_BLOCK)
+    }
+
     fn finish_iterator(&mut self) {
         self.current_items_iterated = self.block.offsets.len();
         self.current_index = self.block.offsets.len();
@@ -75,6 +80,9 @@ impl StorageIterator for BlockIterator {
 
     //Expect call before seek(), to make sure that the key is included in the block
     fn seek(&mut self, key_bytes: &Bytes, inclusive: bool) {
+        if self.block.offsets.is_empty() {
+            return;
+        }
         let txn_id = if inclusive { MAX_TXN_ID } else { 0 };
         let key = &Key::create(key_bytes.clone(), self.keyspace_desc.key_type, txn_id);
 
@@ -92,6 +100,30 @@ impl StorageIterator for BlockIterator {
         }
     }
 
+    fn seek_to_end(&mut self) {
+        self.current_items_iterated = self.block.offsets.len();
+        self.current_index = self.block.offsets.len();
+        self.current_value = None;
+        self.current_key = None;
+    }
+
+    fn prev(&mut self) -> bool {
+        let has_prev = self.has_prev();
+
+        if has_prev {
+            self.current_index = self.current_index - 1;
+            let (value, is_overflow) = self.block.get_value_by_index(self.current_index);
+            self.is_current_value_overflow = is_overflow;
+            self.current_value = Some(value);
+            self.current_key = Some(self.block.get_key_by_index(self.current_index));
+        }
+
+        has_prev
+    }
+
+    fn has_prev(&self) -> bool {
+        self.current_index > 0
+    }
 }
 
 #[cfg(test)]
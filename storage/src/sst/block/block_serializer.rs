@@ -1,5 +1,6 @@
 use crate::sst::block::block::{Block, BLOCK_FOOTER_LENGTH, NOT_COMPRESSED, PREFIX_COMPRESSED};
 use bytes::BufMut;
+use std::cmp::max;
 use std::sync::Arc;
 use shared::key::Key;
 
@@ -45,6 +46,11 @@ impl Block {
         let mut current_size: usize = BLOCK_FOOTER_LENGTH + (self.offsets.len() * std::mem::size_of::<u16>());
 
         for current_index in 0..self.offsets.len() {
+            //Every block_restart_interval entries the key is stored in full, without depending on the previous one
+            if current_index % max(options.block_restart_interval, 1) == 0 {
+                prev_key = None;
+            }
+
             let (current_value, _) = self.get_value_by_index(current_index);
             let current_key = self.get_key_by_index(current_index);
             new_offsets.push(serialized.len() as u16);
@@ -97,8 +103,11 @@ impl Block {
         options: &Arc<shared::SimpleDbOptions>
     ) {
         let n_entries: u16 = self.offsets.len() as u16;
-        shared::u64_to_u8_le(flags, options.block_size_bytes - 12, serialized);
+        //The first byte contains the compression flag, the next ones the block flag (see BLOCK_FLAG_FORMAT_VERSION)
+        shared::u64_to_u8_le(flags | (self.flag << 8), options.block_size_bytes - 12, serialized);
         shared::u16_to_u8_le(n_entries, options.block_size_bytes - 4, serialized);
         shared::u16_to_u8_le(start_offsets_offset as u16, options.block_size_bytes - 2, serialized);
+        let crc = Block::calculate_crc(serialized, options.block_size_bytes);
+        shared::u32_to_u8_le(crc, options.block_size_bytes - 16, serialized);
     }
 }
\ No newline at end of file
@@ -1,9 +1,9 @@
 use std::sync::{Arc, Mutex};
 use bytes::Bytes;
-use shared::{SSTableId, SimpleDbFile, SimpleDbFileWrapper, SimpleDbOptions};
-use shared::key::Key;
+use shared::{SSTableCompression, SSTableId, SimpleDbFile, SimpleDbFileWrapper, SimpleDbOptions};
 use crate::keyspace::keyspace_descriptor::KeyspaceDescriptor;
 use crate::sst::block::block::Block;
+use crate::sst::block::block_compression::decompress_block;
 use crate::sst::block_cache::BlockCache;
 use crate::sst::block_metadata::BlockMetadata;
 use crate::transactions::transaction::Transaction;
@@ -15,6 +15,9 @@ pub struct Blocks {
     pub(crate) options: Arc<SimpleDbOptions>,
     pub(crate) file: SimpleDbFileWrapper,
     pub(crate) sstable_id: SSTableId,
+    pub(crate) block_size_bytes: usize, //Stored in the SSTable file, it might be different from the one in options
+    pub(crate) compression: SSTableCompression, //Stored in the SSTable file, it might be different from the one in options
+    pub(crate) format_version: u8, //Format version of the SSTable, blocks are decoded with it
 }
 
 impl Blocks {
@@ -23,12 +26,18 @@ impl Blocks {
         block_metadata: Vec<BlockMetadata>,
         options: Arc<SimpleDbOptions>,
         file: SimpleDbFileWrapper,
-        sstable_id: SSTableId
+        sstable_id: SSTableId,
+        block_size_bytes: usize,
+        compression: SSTableCompression,
+        format_version: u8,
     ) -> Blocks {
         Blocks {
+            format_version,
+            compression,
             block_cache: Mutex::new(BlockCache::create(options.clone())),
             keyspace_desc,
             block_metadata,
+            block_size_bytes,
             sstable_id,
             options,
             file
@@ -36,7 +45,7 @@ impl Blocks {
     }
 
     pub fn get(&self, key: &Bytes, transaction: &Transaction) -> Result<Option<bytes::Bytes>, shared::SimpleDbError> {
-        match self.get_blocks_metadata(key, transaction) {
+        match self.get_blocks_metadata(key) {
             Some(block_metadata_index) => {
                 match self.load_block(block_metadata_index)?.get_value(key, transaction) {
                     Some((value, is_overflow)) => {
@@ -77,27 +86,15 @@ impl Blocks {
         }
     }
 
-    fn get_blocks_metadata(&self, key: &Bytes, transaction: &Transaction) -> Option<usize> {
-        let lookup_key = Key::create(key.clone(), self.keyspace_desc.key_type, transaction.txn_id);
-        let mut right = self.block_metadata.len() - 1;
-        let mut left = 0;
+    //Returns the first block whose key range includes the key bytes. Versions are compared by the block,
+    //as the txn id of the lookup key might be bigger than the ones stored
+    fn get_blocks_metadata(&self, key: &Bytes) -> Option<usize> {
+        let block_metadata_index = self.block_metadata
+            .partition_point(|block_metadata| block_metadata.last_key.bytes_lt_bytes(key));
 
-        loop {
-            let current_index = (left + right) / 2;
-            let current_block_metadata = &self.block_metadata[current_index];
-
-            if left == right {
-                return None;
-            }
-            if current_block_metadata.contains(key, &transaction) {
-                return Some(current_index);
-            }
-            if current_block_metadata.first_key.gt(&lookup_key) {
-                right = current_index;
-            }
-            if current_block_metadata.last_key.lt(&lookup_key) {
-                left = current_index;
-            }
+        match self.block_metadata.get(block_metadata_index) {
+            Some(block_metadata) if !block_metadata.first_key.bytes_gt_bytes(key) => Some(block_metadata_index),
+            _ => None,
         }
     }
 
@@ -116,10 +113,12 @@ impl Blocks {
         //Read from disk
         let metadata: &BlockMetadata = &self.block_metadata[block_id];
         let file: &mut SimpleDbFile = unsafe { &mut *self.file.file.get() };
-        let encoded_block = file.read(metadata.offset, self.options.block_size_bytes)
+        let stored_block = Self::read_stored_block(file, metadata.offset, self.block_size_bytes, self.compression)
             .map_err(|e| shared::SimpleDbError::CannotReadSSTableFile(self.keyspace_desc.keyspace_id, self.sstable_id, e))?;
 
-        let block = Block::deserialize(&encoded_block, &self.options, self.keyspace_desc)
+        let block = stored_block
+            .and_then(|stored_block| decompress_block(&stored_block, self.compression, self.block_size_bytes))
+            .and_then(|encoded_block| Block::deserialize(&encoded_block, self.block_size_bytes, self.format_version, self.keyspace_desc))
             .map_err(|error_type| shared::SimpleDbError::CannotDecodeSSTable(
                 self.keyspace_desc.keyspace_id,
                 self.sstable_id,
@@ -142,4 +141,27 @@ impl Blocks {
 
         Ok(block)
     }
+
+    //Compressed blocks are preceded by their size. A corrupted size is returned as a decode error, so the
+    //SSTable can be quarantined, instead of reading past the end of the file
+    fn read_stored_block(
+        file: &SimpleDbFile,
+        offset: usize,
+        block_size_bytes: usize,
+        compression: SSTableCompression
+    ) -> Result<Result<Vec<u8>, shared::DecodeErrorType>, std::io::Error> {
+        match compression {
+            SSTableCompression::None => file.read(offset, block_size_bytes).map(Ok),
+            _ => {
+                if offset + 4 > file.size() {
+                    return Ok(Err(shared::DecodeErrorType::IllegalSize(offset + 4, file.size())));
+                }
+                let stored_size = shared::u8_vec_to_u32_le(&file.read(offset, 4)?, 0) as usize;
+                if offset + 4 + stored_size > file.size() {
+                    return Ok(Err(shared::DecodeErrorType::IllegalSize(offset + 4 + stored_size, file.size())));
+                }
+                file.read(offset + 4, stored_size).map(Ok)
+            }
+        }
+    }
 }
\ No newline at end of file
@@ -3,4 +3,5 @@ pub mod block;
 pub mod block_iterator;
 mod block_serializer;
 mod block_deserializer;
-pub mod blocks;
\ No newline at end of file
+pub mod blocks;
+pub(crate) mod block_compression;
\ No newline at end of file
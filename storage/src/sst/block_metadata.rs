@@ -16,6 +16,7 @@ impl BlockMetadata {
         start_index: usize,
         key_type: Type
     ) -> Result<Vec<BlockMetadata>, shared::DecodeErrorType> {
+        Self::check_size(serialized, start_index + 8)?;
         let expected_crc = shared::u8_vec_to_u32_le(serialized, start_index);
         let n_blocks_metadata = shared::u8_vec_to_u32_le(serialized, start_index + 4);
 
@@ -58,10 +59,12 @@ impl BlockMetadata {
     ) -> Result<(usize, BlockMetadata), shared::DecodeErrorType> {
         let mut current_index = start_index;
 
+        Self::check_size(bytes, current_index + 12)?;
         let first_key_length = shared::u8_vec_to_u32_le(&bytes, current_index) as usize;
         current_index = current_index + 4;
         let first_key_txn_id = shared::u8_vec_to_u64_le(&bytes, current_index) as shared::TxnId;
         current_index = current_index + 8;
+        Self::check_size(bytes, current_index + first_key_length + 12)?;
         let first_key = Bytes::from(bytes[current_index..(current_index + first_key_length)].to_vec());
         current_index = current_index + first_key_length;
 
@@ -69,6 +72,7 @@ impl BlockMetadata {
         current_index = current_index + 4;
         let last_key_txn_id = shared::u8_vec_to_u64_le(&bytes, current_index) as shared::TxnId;
         current_index = current_index + 8;
+        Self::check_size(bytes, current_index + last_key_length + 4)?;
         let last_key = Bytes::from(bytes[current_index..(current_index + last_key_length)].to_vec());
 
         current_index = current_index + last_key_length;
@@ -83,6 +87,14 @@ impl BlockMetadata {
         }))
     }
 
+    //Corrupted lengths might point outside the file
+    fn check_size(bytes: &[u8], end_index: usize) -> Result<(), shared::DecodeErrorType> {
+        if end_index > bytes.len() {
+            return Err(shared::DecodeErrorType::IllegalSize(end_index, bytes.len()));
+        }
+        Ok(())
+    }
+
     pub fn serialize(&self) -> Vec<u8> {
         let mut metadata_encoded: Vec<u8> = Vec::new();
         //First key
@@ -1,21 +1,35 @@
 use crate::keyspace::keyspace_descriptor::KeyspaceDescriptor;
+use crate::sst::block::block::Block;
 use crate::sst::block::blocks::Blocks;
+use crate::sst::block::block_compression::{compression_from_id, decompress_block};
 use crate::sst::block_metadata::BlockMetadata;
 use crate::transactions::transaction::Transaction;
 use crate::utils::bloom_filter::BloomFilter;
 use bytes::Bytes;
 use shared::key::Key;
 use shared::SimpleDbError::CannotDeleteSSTable;
-use shared::{SimpleDbFile, SimpleDbFileWrapper};
+use shared::{SSTableCompression, SimpleDbFile, SimpleDbFileWrapper};
 use std::cell::UnsafeCell;
 use std::path::Path;
-use std::sync::atomic::AtomicU8;
-use std::sync::atomic::Ordering::Release;
+use shared::logger::logger;
+use shared::logger::SimpleDbLayer::StorageKeyspace;
+use std::sync::atomic::{AtomicBool, AtomicU8};
+use std::sync::atomic::Ordering::{AcqRel, Acquire, Release};
 use std::sync::Arc;
 
 pub const SSTABLE_DELETED: u8 = 2;
 pub const SSTABLE_ACTIVE: u8 = 1;
 
+//Stored in the footer of the SSTable, before the state. SSTables without the magic number were written before the
+//format version was stored. Their footer only contains the state, level and offsets, and their blocks don't have CRC
+pub const SSTABLE_FORMAT_MAGIC: u32 = 0x5354_4246;
+pub const LEGACY_SSTABLE_FORMAT_VERSION: u8 = 0;
+//Version 1 stores the CRC and the block flag of each block (see BLOCK_FLAG_FORMAT_VERSION)
+//Version 2 stores bloom filters with multiple hashes per key
+pub const SSTABLE_FORMAT_VERSION: u8 = 2;
+//State (u8), level (u32), bloom filter offset (u32) & blocks metadata offset (u32)
+const SSTABLE_TRAILER_LENGTH: usize = 13;
+
 pub struct SSTable {
     pub(crate) sstable_id: shared::SSTableId,
     pub(crate) bloom_filter: BloomFilter,
@@ -23,8 +37,11 @@ pub struct SSTable {
     pub(crate) blocks: Blocks,
     pub(crate) level: u32,
     pub(crate) state: AtomicU8,
+    pub(crate) quarantined: AtomicBool, //The SSTable file has been moved aside because some of its blocks are corrupted
     pub(crate) first_key: Key,
     pub(crate) last_key: Key,
+    //None if the SSTable was written before the number of keys was stored in the file
+    pub(crate) n_keys: Option<usize>,
 
     pub(crate) keyspace_desc: KeyspaceDescriptor,
 }
@@ -40,21 +57,27 @@ impl SSTable {
         level: u32,
         sstable_id: shared::SSTableId,
         state: u8,
-        keyspace_desc: KeyspaceDescriptor
+        keyspace_desc: KeyspaceDescriptor,
+        n_keys: Option<usize>,
+        block_size_bytes: usize,
+        compression: SSTableCompression,
+        format_version: u8,
     ) -> SSTable {
         SSTable {
             blocks: Blocks::create(
                 keyspace_desc, block_metadata, options.clone(),
                 SimpleDbFileWrapper {file: UnsafeCell::new(file.clone())},
-                sstable_id
+                sstable_id, block_size_bytes, compression, format_version
             ),
             file: SimpleDbFileWrapper {file: UnsafeCell::new(file)},
             state: AtomicU8::new(state),
+            quarantined: AtomicBool::new(false),
             keyspace_desc,
             bloom_filter,
             sstable_id,
             first_key,
             last_key,
+            n_keys,
             level,
         }
     }
@@ -73,6 +96,122 @@ impl SSTable {
         Self::deserialize(&sst_bytes, sstable_id, options, sst_file, keyspace_desc)
     }
 
+    //Decodes every part of the SSTable file. Returns all the corrupted parts found instead of failing on the first one
+    pub fn verify_file(
+        sstable_id: shared::SSTableId,
+        path: &Path,
+        options: &Arc<shared::SimpleDbOptions>,
+        keyspace_desc: KeyspaceDescriptor
+    ) -> Result<Vec<shared::SimpleDbError>, shared::SimpleDbError> {
+        //Files that cannot be read are reported as corrupted, so the rest of the files are still checked
+        let bytes = match shared::SimpleDbFile::open(path, shared::SimpleDbFileMode::RandomWrites)
+            .and_then(|sst_file| sst_file.read_all()) {
+            Ok(bytes) => bytes,
+            Err(e) => return Ok(vec![shared::SimpleDbError::CannotOpenSSTableFile(keyspace_desc.keyspace_id, sstable_id, e)]),
+        };
+        if let Err(error_type) = Self::check_trailer_size(&bytes) {
+            return Ok(vec![Self::decode_error(keyspace_desc, sstable_id, shared::SSTableCorruptedPart::Footer, 0, error_type)]);
+        }
+        let meta_offset = shared::u8_vec_to_u32_le(&bytes, bytes.len() - 4) as usize;
+        let bloom_offset = shared::u8_vec_to_u32_le(&bytes, bytes.len() - 8) as usize;
+        let mut corrupted_parts = Vec::new();
+
+        let block_metadata = match BlockMetadata::deserialize_all(&bytes, meta_offset, keyspace_desc.key_type) {
+            Ok(block_metadata) => block_metadata,
+            Err(error_type) => {
+                //Blocks cannot be located without their metadata
+                corrupted_parts.push(Self::decode_error(keyspace_desc, sstable_id, shared::SSTableCorruptedPart::BlockMetadata, meta_offset, error_type));
+                return Ok(corrupted_parts);
+            }
+        };
+
+        let (_, block_size_bytes, compression, format_version) = match Self::decode_footer(&bytes, bloom_offset, options) {
+            Ok(footer) => footer,
+            Err(error_type) => {
+                //Blocks and the bloom filter cannot be decoded without the format version and compression
+                corrupted_parts.push(Self::decode_error(keyspace_desc, sstable_id, shared::SSTableCorruptedPart::Footer, bloom_offset, error_type));
+                return Ok(corrupted_parts);
+            }
+        };
+
+        if let Err(error_type) = BloomFilter::decode(&bytes, bloom_offset, format_version) {
+            corrupted_parts.push(Self::decode_error(keyspace_desc, sstable_id, shared::SSTableCorruptedPart::BloomFilter, bloom_offset, error_type));
+        }
+        let mut previous_key: Option<Key> = None;
+        for (block_id, metadata) in block_metadata.iter().enumerate() {
+            let decoded_block = Self::stored_block_range(&bytes, metadata.offset, block_size_bytes, compression)
+                .and_then(|(block_start, block_end)| decompress_block(&bytes[block_start..block_end], compression, block_size_bytes))
+                .and_then(|encoded_block| Block::deserialize(&encoded_block, block_size_bytes, format_version, keyspace_desc));
+
+            match decoded_block {
+                Ok(block) => {
+                    if let Err(error_type) = Self::verify_keys_sorted(&block, &mut previous_key) {
+                        corrupted_parts.push(Self::decode_error(keyspace_desc, sstable_id, shared::SSTableCorruptedPart::Block(block_id), metadata.offset, error_type));
+                    }
+                },
+                Err(error_type) => {
+                    corrupted_parts.push(Self::decode_error(keyspace_desc, sstable_id, shared::SSTableCorruptedPart::Block(block_id), metadata.offset, error_type));
+                }
+            }
+        }
+
+        Ok(corrupted_parts)
+    }
+
+    //Returns the range of the stored block in the file. Corrupted offsets or sizes might point outside the file
+    fn stored_block_range(
+        bytes: &Vec<u8>,
+        offset: usize,
+        block_size_bytes: usize,
+        compression: SSTableCompression
+    ) -> Result<(usize, usize), shared::DecodeErrorType> {
+        let (block_start, block_end) = match compression {
+            SSTableCompression::None => (offset, std::cmp::min(offset + block_size_bytes, bytes.len())),
+            _ if offset + 4 > bytes.len() => return Err(shared::DecodeErrorType::IllegalSize(offset + 4, bytes.len())),
+            _ => (offset + 4, offset + 4 + shared::u8_vec_to_u32_le(bytes, offset) as usize),
+        };
+        if block_start >= bytes.len() || block_end > bytes.len() {
+            return Err(shared::DecodeErrorType::IllegalSize(block_end, bytes.len()));
+        }
+
+        Ok((block_start, block_end))
+    }
+
+    fn check_trailer_size(bytes: &[u8]) -> Result<(), shared::DecodeErrorType> {
+        if bytes.len() < SSTABLE_TRAILER_LENGTH {
+            return Err(shared::DecodeErrorType::IllegalSize(SSTABLE_TRAILER_LENGTH, bytes.len()));
+        }
+        Ok(())
+    }
+
+    //previous_key is the last key of the previous block. Overflow blocks repeat the same key, so equal keys are allowed
+    fn verify_keys_sorted(block: &Block, previous_key: &mut Option<Key>) -> Result<(), shared::DecodeErrorType> {
+        for index in 0..block.offsets.len() {
+            let key = block.get_key_by_index(index);
+            if previous_key.as_ref().is_some_and(|previous_key| key.lt(previous_key)) {
+                return Err(shared::DecodeErrorType::UnsortedKeys(index));
+            }
+            *previous_key = Some(key);
+        }
+
+        Ok(())
+    }
+
+    fn decode_error(
+        keyspace_desc: KeyspaceDescriptor,
+        sstable_id: shared::SSTableId,
+        corrupted_part: shared::SSTableCorruptedPart,
+        offset: usize,
+        error_type: shared::DecodeErrorType
+    ) -> shared::SimpleDbError {
+        shared::SimpleDbError::CannotDecodeSSTable(
+            keyspace_desc.keyspace_id,
+            sstable_id,
+            corrupted_part,
+            shared::DecodeError { offset, error_type, index: 0 }
+        )
+    }
+
     fn deserialize(
         bytes: &Vec<u8>,
         sstable_id: shared::SSTableId,
@@ -80,6 +219,8 @@ impl SSTable {
         file: shared::SimpleDbFile,
         keyspace_desc: KeyspaceDescriptor
     ) -> Result<Arc<SSTable>, shared::SimpleDbError> {
+        Self::check_trailer_size(bytes)
+            .map_err(|error_type| Self::decode_error(keyspace_desc, sstable_id, shared::SSTableCorruptedPart::Footer, 0, error_type))?;
         let meta_offset = shared::u8_vec_to_u32_le(bytes, bytes.len() - 4);
         let bloom_offset = shared::u8_vec_to_u32_le(bytes, bytes.len() - 8);
         let level = shared::u8_vec_to_u32_le(bytes, bytes.len() - 12);
@@ -97,11 +238,13 @@ impl SSTable {
                 }
             ))?;
 
-        let bloom_filter = BloomFilter::decode(bytes, bloom_offset as usize)
+        let first_key = Self::get_first_key(&block_metadata);
+        let last_key = Self::get_last_key(&block_metadata);
+        let (n_keys, block_size_bytes, compression, format_version) = Self::decode_footer(bytes, bloom_offset as usize, &options)
             .map_err(|error_type| shared::SimpleDbError::CannotDecodeSSTable(
                 keyspace_desc.keyspace_id,
                 sstable_id,
-                shared::SSTableCorruptedPart::BloomFilter,
+                shared::SSTableCorruptedPart::Footer,
                 shared::DecodeError {
                     offset: bloom_offset as usize,
                     error_type,
@@ -109,8 +252,17 @@ impl SSTable {
                 }
             ))?;
 
-        let first_key = Self::get_first_key(&block_metadata);
-        let last_key = Self::get_last_key(&block_metadata);
+        let bloom_filter = BloomFilter::decode(bytes, bloom_offset as usize, format_version)
+            .map_err(|error_type| shared::SimpleDbError::CannotDecodeSSTable(
+                keyspace_desc.keyspace_id,
+                sstable_id,
+                shared::SSTableCorruptedPart::BloomFilter,
+                shared::DecodeError {
+                    offset: bloom_offset as usize,
+                    error_type,
+                    index: 0,
+                }
+            ))?;
 
         Ok(Arc::new(SSTable::create(
             block_metadata,
@@ -122,10 +274,44 @@ impl SSTable {
             level,
             sstable_id,
             state,
-            keyspace_desc
+            keyspace_desc,
+            n_keys,
+            block_size_bytes,
+            compression,
+            format_version,
         )))
     }
 
+    //Returns the nº of keys, block size, compression and format version. Stored between the bloom filter and the state:
+    //nº keys (u64), block size (u32), compression (u8), format version (u8), magic (u32)
+    fn decode_footer(
+        bytes: &Vec<u8>,
+        bloom_offset: usize,
+        options: &Arc<shared::SimpleDbOptions>
+    ) -> Result<(Option<usize>, usize, SSTableCompression, u8), shared::DecodeErrorType> {
+        let trailer_offset = bytes.len() - SSTABLE_TRAILER_LENGTH;
+        let has_format_version = trailer_offset >= bloom_offset + 5 &&
+            shared::u8_vec_to_u32_le(bytes, trailer_offset - 4) == SSTABLE_FORMAT_MAGIC;
+        if !has_format_version {
+            return Ok((None, options.block_size_bytes, SSTableCompression::None, LEGACY_SSTABLE_FORMAT_VERSION));
+        }
+
+        let format_version = bytes[trailer_offset - 5];
+        if format_version == LEGACY_SSTABLE_FORMAT_VERSION || format_version > SSTABLE_FORMAT_VERSION {
+            return Err(shared::DecodeErrorType::UnknownFlag(format_version as usize));
+        }
+        let footer_offset = trailer_offset - 5 - 13;
+        if footer_offset < bloom_offset {
+            return Err(shared::DecodeErrorType::IllegalSize(bloom_offset + 18 + SSTABLE_TRAILER_LENGTH, bytes.len()));
+        }
+
+        let n_keys = u64::from_le_bytes(bytes[footer_offset..footer_offset + 8].try_into().unwrap()) as usize;
+        let block_size_bytes = shared::u8_vec_to_u32_le(bytes, footer_offset + 8) as usize;
+        let compression = compression_from_id(bytes[footer_offset + 12])?;
+
+        Ok((Some(n_keys), block_size_bytes, compression, format_version))
+    }
+
     fn get_last_key(block_metadata: &Vec<BlockMetadata>) -> Key {
         block_metadata.get(block_metadata.len() - 1).unwrap().last_key.clone()
     }
@@ -152,11 +338,40 @@ impl SSTable {
 
     pub fn delete(&self) -> Result<(), shared::SimpleDbError> {
         self.state.store(SSTABLE_DELETED, Release);
+        //Quarantined files are kept, so they can be inspected
+        if self.quarantined.load(Acquire) {
+            return Ok(());
+        }
         let file: &mut SimpleDbFile = unsafe { &mut *self.file.file.get() };
         file.delete()
             .map_err(|e| CannotDeleteSSTable(self.keyspace_desc.keyspace_id, self.sstable_id, e))
     }
 
+    //Called when a block cannot be decoded and SimpleDbOptions::quarantine_corrupted_sstables is enabled.
+    //The file is renamed to quarantine-sst-<id>, so it won't be loaded after a restart, and its id won't be reused.
+    //Until then, the rest of the blocks will keep being read, since the file descriptor stays valid after the rename
+    pub(crate) fn quarantine(&self, error: &shared::SimpleDbError) {
+        logger().warn(StorageKeyspace(self.keyspace_desc.keyspace_id), &format!(
+            "Skipping corrupted part of SSTable {}: {:?}", self.sstable_id, error
+        ));
+
+        if self.quarantined.swap(true, AcqRel) {
+            return;
+        }
+
+        let file: &SimpleDbFile = unsafe { &*self.file.file.get() };
+        let path = file.path();
+        let quarantine_path = path.with_file_name(format!("quarantine-{}", path.file_name().unwrap().to_str().unwrap()));
+        match std::fs::rename(&path, &quarantine_path) {
+            Ok(_) => logger().warn(StorageKeyspace(self.keyspace_desc.keyspace_id), &format!(
+                "SSTable {} quarantined in {:?}", self.sstable_id, quarantine_path
+            )),
+            Err(e) => logger().error(StorageKeyspace(self.keyspace_desc.keyspace_id), &format!(
+                "Cannot quarantine SSTable {}: {:?}", self.sstable_id, e
+            )),
+        }
+    }
+
     pub fn size(&self) -> shared::SSTableId {
         let file: &mut SimpleDbFile = unsafe { &mut *self.file.file.get() };
         file.size()
@@ -170,6 +385,258 @@ impl SSTable {
             return Ok(None);
         }
 
-        self.blocks.get(key, transaction)
+        //Unlike scans, reads of a key in a corrupted block fail, as an older version of the key could be returned
+        let result = self.blocks.get(key, transaction);
+        if let Err(error @ shared::SimpleDbError::CannotDecodeSSTable(..)) = &result {
+            if self.blocks.options.quarantine_corrupted_sstables {
+                self.quarantine(error);
+            }
+        }
+
+        result
+    }
+}
+#[cfg(test)]
+mod test {
+    use crate::keyspace::keyspace_descriptor::KeyspaceDescriptor;
+    use crate::sst::sstable::{SSTable, LEGACY_SSTABLE_FORMAT_VERSION};
+    use crate::sst::sstable_builder::SSTableBuilder;
+    use crate::transactions::transaction::Transaction;
+    use bytes::Bytes;
+    use shared::key::Key;
+    use shared::{SSTableCompression, Type};
+    use std::sync::Arc;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn n_keys() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("simpledb-sstable-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()));
+        let options = Arc::new(shared::SimpleDbOptions::default());
+        let keyspace_desc = KeyspaceDescriptor::create_mock(Type::String);
+
+        let mut builder = SSTableBuilder::create(options.clone(), keyspace_desc, 0);
+        builder.add_entry(Key::create_from_str("a", 1), Bytes::from(vec![1]));
+        builder.add_entry(Key::create_from_str("a", 2), Bytes::from(vec![2]));
+        builder.add_entry(Key::create_from_str("b", 1), Bytes::from(vec![3]));
+        builder.add_entry(Key::create_from_str("c", 3), Bytes::from(vec![4]));
+        let sstable = builder.build(1, path.as_path()).unwrap();
+        assert_eq!(sstable.n_keys, Some(3));
+
+        let sstable = SSTable::from_file(1, path.as_path(), options, keyspace_desc).unwrap();
+        assert_eq!(sstable.n_keys, Some(3));
+        assert_eq!(sstable.level, 0);
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn get_from_many_blocks() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("simpledb-sstable-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()));
+        let options = Arc::new(shared::start_simpledb_options_builder_from(&shared::SimpleDbOptions::default())
+            .block_size_bytes(64)
+            .build());
+        let keyspace_desc = KeyspaceDescriptor::create_mock(Type::String);
+
+        //Only even keys are stored, so odd ones fall between the keys of a block or between two blocks
+        let mut builder = SSTableBuilder::create(options.clone(), keyspace_desc, 0);
+        for i in (10..90).step_by(2) {
+            builder.add_entry(Key::create_from_str(&format!("k{}", i), 1), Bytes::from(vec![i as u8]));
+        }
+        let sstable = builder.build(1, path.as_path()).unwrap();
+        assert!(sstable.blocks.block_metadata.len() > 2);
+
+        //The txn id of the lookup key is bigger than the one stored
+        let transaction = Transaction::create(10);
+        for i in (10..90).step_by(2) {
+            assert_eq!(sstable.get(&Bytes::from(format!("k{}", i)), &transaction).unwrap(), Some(Bytes::from(vec![i as u8])));
+        }
+        for i in (11..90).step_by(2) {
+            assert_eq!(sstable.get(&Bytes::from(format!("k{}", i)), &transaction).unwrap(), None);
+        }
+        //Before the first block and after the last one
+        assert_eq!(sstable.get(&Bytes::from("a"), &transaction).unwrap(), None);
+        assert_eq!(sstable.get(&Bytes::from("z"), &transaction).unwrap(), None);
+        //Not visible to older transactions
+        assert_eq!(sstable.get(&Bytes::from("k10"), &Transaction::create(0)).unwrap(), None);
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn legacy_format() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("simpledb-sstable-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()));
+        let options = Arc::new(shared::SimpleDbOptions::default());
+        let keyspace_desc = KeyspaceDescriptor::create_mock(Type::String);
+
+        let mut builder = SSTableBuilder::create(options.clone(), keyspace_desc, 0);
+        builder.add_entry(Key::create_from_str("a", 1), Bytes::from(vec![1]));
+        builder.add_entry(Key::create_from_str("b", 1), Bytes::from(vec![2]));
+        builder.build(1, path.as_path()).unwrap();
+
+        //SSTables written before the format version was stored don't have the nº of keys, block size,
+        //compression, format version and magic between the bloom filter and the state
+        let mut bytes = std::fs::read(&path).unwrap();
+        let trailer = bytes.split_off(bytes.len() - 13);
+        bytes.truncate(bytes.len() - 18);
+        //Their bloom filters don't store the nº of hashes, and set the first bit of the slot of each key
+        let bloom_offset = u32::from_le_bytes(trailer[5..9].try_into().unwrap()) as usize;
+        let bloom_n_bytes = bytes.len() - bloom_offset - 9;
+        bytes.truncate(bloom_offset);
+        bytes.extend(crc32fast::hash(&vec![0x01; bloom_n_bytes]).to_le_bytes());
+        bytes.extend((bloom_n_bytes as u32).to_le_bytes());
+        bytes.extend(vec![0x01; bloom_n_bytes]);
+        bytes.extend(trailer);
+        std::fs::write(&path, bytes).unwrap();
+
+        let sstable = SSTable::from_file(1, path.as_path(), options.clone(), keyspace_desc).unwrap();
+        assert_eq!(sstable.blocks.format_version, LEGACY_SSTABLE_FORMAT_VERSION);
+        assert_eq!(sstable.n_keys, None);
+        assert_eq!(sstable.get(&Bytes::from("a"), &Transaction::create(1)).unwrap(), Some(Bytes::from(vec![1])));
+        assert_eq!(sstable.get(&Bytes::from("b"), &Transaction::create(1)).unwrap(), Some(Bytes::from(vec![2])));
+        assert!(SSTable::verify_file(1, path.as_path(), &options, keyspace_desc).unwrap().is_empty());
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn bloom_filter_sized_by_n_keys() {
+        let options = Arc::new(shared::start_simpledb_options_builder_from(&shared::SimpleDbOptions::default())
+            .bloom_filter_bits_per_key(16)
+            .build());
+        let keyspace_desc = KeyspaceDescriptor::create_mock(Type::String);
+
+        let mut bloom_sizes = Vec::new();
+        for n_keys in [100, 1000] {
+            let mut path = std::env::temp_dir();
+            path.push(format!("simpledb-sstable-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()));
+            let mut builder = SSTableBuilder::create(options.clone(), keyspace_desc, 0);
+            for i in 0..n_keys {
+                builder.add_entry(Key::create_from_str(&format!("{:05}", i), 1), Bytes::from(vec![1]));
+            }
+            builder.build(1, path.as_path()).unwrap();
+
+            let sstable = SSTable::from_file(1, path.as_path(), options.clone(), keyspace_desc).unwrap();
+            for i in 0..n_keys {
+                assert!(sstable.bloom_filter.may_contain(shared::hash(format!("{:05}", i).as_bytes())));
+            }
+            bloom_sizes.push(sstable.bloom_filter.encode().len() - 9);
+
+            let _ = std::fs::remove_file(path);
+        }
+
+        //100 * 16 bits rounded to 2048 bits, 1000 * 16 bits rounded to 16384 bits
+        assert_eq!(bloom_sizes, vec![256, 2048]);
+    }
+
+    #[test]
+    fn block_sizes() {
+        let keyspace_desc = KeyspaceDescriptor::create_mock(Type::String);
+        let entries: Vec<(String, Bytes)> = (0..100)
+            .map(|i| (format!("key{:03}", i), Bytes::from(vec![i as u8; if i == 50 { 1000 } else { 10 }])))
+            .collect();
+
+        for (block_size_bytes, block_restart_interval) in [(128, 1), (8192, 4)] {
+            let mut path = std::env::temp_dir();
+            path.push(format!("simpledb-sstable-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()));
+            let options = shared::start_simpledb_options_builder_from(&shared::SimpleDbOptions::default())
+                .block_size_bytes(block_size_bytes)
+                .block_restart_interval(block_restart_interval)
+                .build_arc();
+            let mut builder = SSTableBuilder::create(options, keyspace_desc, 0);
+            for (key, value) in &entries {
+                builder.add_entry(Key::create_from_str(key, 1), value.clone());
+            }
+            builder.build(1, path.as_path()).unwrap();
+
+            //The block size is read from the file, not from the options
+            let sstable = SSTable::from_file(1, path.as_path(), Arc::new(shared::SimpleDbOptions::default()), keyspace_desc).unwrap();
+            assert_eq!(sstable.blocks.block_size_bytes, block_size_bytes);
+            for (key, value) in &entries {
+                assert_eq!(sstable.get(&Bytes::from(key.clone()), &Transaction::create(1)).unwrap(), Some(value.clone()));
+            }
+
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    #[test]
+    fn compression() {
+        let keyspace_desc = KeyspaceDescriptor::create_mock(Type::String);
+        //Values bigger than the block size are stored in overflow blocks, which are also compressed
+        let entries: Vec<(String, Bytes)> = (0..100)
+            .map(|i| (format!("key{:03}", i), Bytes::from(vec![i as u8; if i % 10 == 0 { 10000 } else { 1000 }])))
+            .collect();
+
+        let mut sizes = Vec::new();
+        for compression in [SSTableCompression::None, SSTableCompression::Lz4] {
+            let mut path = std::env::temp_dir();
+            path.push(format!("simpledb-sstable-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()));
+            let options = shared::start_simpledb_options_builder_from(&shared::SimpleDbOptions::default())
+                .sstable_compression(compression)
+                .build_arc();
+            let mut builder = SSTableBuilder::create(options, keyspace_desc, 0);
+            for (key, value) in &entries {
+                builder.add_entry(Key::create_from_str(key, 1), value.clone());
+            }
+            sizes.push(builder.build(1, path.as_path()).unwrap().size());
+
+            //The compression is read from the file, not from the options
+            let options = Arc::new(shared::SimpleDbOptions::default());
+            assert!(SSTable::verify_file(1, path.as_path(), &options, keyspace_desc).unwrap().is_empty());
+            let sstable = SSTable::from_file(1, path.as_path(), options, keyspace_desc).unwrap();
+            assert_eq!(sstable.blocks.compression, compression);
+            for (key, value) in &entries {
+                assert_eq!(sstable.get(&Bytes::from(key.clone()), &Transaction::create(1)).unwrap(), Some(value.clone()));
+            }
+
+            let _ = std::fs::remove_file(path);
+        }
+
+        assert!(sizes[1] * 10 < sizes[0]);
+    }
+    #[test]
+    fn corrupted_sizes() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("simpledb-sstable-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()));
+        let keyspace_desc = KeyspaceDescriptor::create_mock(Type::String);
+        let options = shared::start_simpledb_options_builder_from(&shared::SimpleDbOptions::default())
+            .sstable_compression(SSTableCompression::Lz4)
+            .build_arc();
+        let mut builder = SSTableBuilder::create(options.clone(), keyspace_desc, 0);
+        builder.add_entry(Key::create_from_str("a", 1), Bytes::from(vec![1]));
+        builder.build(1, path.as_path()).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+
+        //The stored size of the first compressed block points past the end of the file
+        let mut corrupted_bytes = bytes.clone();
+        corrupted_bytes[0..4].copy_from_slice(&u32::MAX.to_le_bytes());
+        std::fs::write(&path, &corrupted_bytes).unwrap();
+        let corrupted_parts = SSTable::verify_file(1, path.as_path(), &options, keyspace_desc).unwrap();
+        assert!(matches!(corrupted_parts.as_slice(), [shared::SimpleDbError::CannotDecodeSSTable(_, _, shared::SSTableCorruptedPart::Block(0), _)]));
+        let sstable = SSTable::from_file(1, path.as_path(), options.clone(), keyspace_desc).unwrap();
+        assert!(matches!(
+            sstable.get(&Bytes::from("a"), &Transaction::create(1)),
+            Err(shared::SimpleDbError::CannotDecodeSSTable(_, _, shared::SSTableCorruptedPart::Block(0), _))
+        ));
+
+        //The metadata offset points past the end of the file
+        let mut corrupted_bytes = bytes.clone();
+        let len = corrupted_bytes.len();
+        corrupted_bytes[len - 4..].copy_from_slice(&u32::MAX.to_le_bytes());
+        std::fs::write(&path, &corrupted_bytes).unwrap();
+        let corrupted_parts = SSTable::verify_file(1, path.as_path(), &options, keyspace_desc).unwrap();
+        assert!(matches!(corrupted_parts.as_slice(), [shared::SimpleDbError::CannotDecodeSSTable(_, _, shared::SSTableCorruptedPart::BlockMetadata, _)]));
+        assert!(SSTable::from_file(1, path.as_path(), options.clone(), keyspace_desc).is_err());
+
+        //Truncated file
+        std::fs::write(&path, &bytes[..5]).unwrap();
+        let corrupted_parts = SSTable::verify_file(1, path.as_path(), &options, keyspace_desc).unwrap();
+        assert!(matches!(corrupted_parts.as_slice(), [shared::SimpleDbError::CannotDecodeSSTable(_, _, shared::SSTableCorruptedPart::Footer, _)]));
+        assert!(SSTable::from_file(1, path.as_path(), options, keyspace_desc).is_err());
+
+        let _ = std::fs::remove_file(path);
     }
 }
\ No newline at end of file
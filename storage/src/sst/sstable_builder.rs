@@ -1,7 +1,8 @@
 use crate::keyspace::keyspace_descriptor::KeyspaceDescriptor;
 use crate::sst::block::block_builder::BlockBuilder;
+use crate::sst::block::block_compression::{compress_block, compression_to_id};
 use crate::sst::block_metadata::BlockMetadata;
-use crate::sst::sstable::{SSTable, SSTABLE_ACTIVE};
+use crate::sst::sstable::{SSTable, SSTABLE_ACTIVE, SSTABLE_FORMAT_MAGIC, SSTABLE_FORMAT_VERSION};
 use crate::utils::bloom_filter::BloomFilter;
 use bytes::{BufMut, Bytes};
 use shared::key::Key;
@@ -21,6 +22,7 @@ pub struct SSTableBuilder {
     built_encoded_blocks: Vec<u8>,
 
     key_hashes: Vec<u32>,
+    n_keys: usize, //Number of different keys, without counting its versions
 
     options: Arc<shared::SimpleDbOptions>,
     level: u32,
@@ -41,6 +43,7 @@ impl SSTableBuilder {
             level,
             keyspace_desc,
             key_hashes: Vec::new(),
+            n_keys: 0,
             built_block_metadata: Vec::new(),
             built_encoded_blocks: Vec::new(),
             first_key_current_block: None,
@@ -61,25 +64,28 @@ impl SSTableBuilder {
     }
 
     pub fn add_entry(&mut self, key: Key, value: Bytes) {
+        //Entries are added sorted, so the versions of a key are added one after the other
+        let is_new_key = self.last_key.as_ref()
+            .map_or(true, |last_key| !last_key.bytes_eq_bytes(key.as_bytes()));
+        if is_new_key {
+            self.n_keys += 1;
+        }
         if self.first_key.is_none() {
             self.first_key = Some(key.clone());
         }
         self.last_key = Some(key.clone());
 
+        self.key_hashes.push(shared::hash(key.as_bytes()));
+
+        if self.current_block_builder.add_entry(&key, &value).is_err() {
+            self.build_current_block();
+            let _ = self.current_block_builder.add_entry(&key, &value);
+        }
+
         self.last_key_current_block = Some(key.clone());
         if self.first_key_current_block.is_none() {
-            self.first_key_current_block = Some(key.clone());
+            self.first_key_current_block = Some(key);
         }
-
-        self.key_hashes.push(shared::hash(key.as_bytes()));
-
-        match self.current_block_builder.add_entry(&key, &value) {
-            Err(_) => {
-                self.build_current_block();
-                self.current_block_builder.add_entry(&key, &value);
-            },
-            Ok(_) => {}
-        };
     }
 
     pub fn n_entries(&self) -> usize {
@@ -97,9 +103,13 @@ impl SSTableBuilder {
     ) -> Result<SSTable, shared::SimpleDbError> {
         self.build_current_block();
 
+        let bloom_filter_n_entries = match self.options.bloom_filter_bits_per_key {
+            0 => self.options.bloom_filter_n_entries,
+            bits_per_key => BloomFilter::n_entries_for(self.key_hashes.len(), bits_per_key),
+        };
         let bloom_filter: BloomFilter = BloomFilter::create(
             &self.key_hashes,
-            self.options.bloom_filter_n_entries
+            bloom_filter_n_entries
         );
 
         let mut encoded = self.built_encoded_blocks;
@@ -114,15 +124,24 @@ impl SSTableBuilder {
         let bloom_encoded = bloom_filter.encode();
         encoded.extend(bloom_encoded);
 
-        //Bloom & blocks metadata offsets, state
+        //Number of keys, block size, compression, format version, state, level, bloom & blocks metadata offsets
+        encoded.put_u64_le(self.n_keys as u64);
+        encoded.put_u32_le(self.options.block_size_bytes as u32);
+        encoded.push(compression_to_id(self.options.sstable_compression));
+        encoded.push(SSTABLE_FORMAT_VERSION);
+        encoded.put_u32_le(SSTABLE_FORMAT_MAGIC);
         encoded.push(SSTABLE_ACTIVE);
         encoded.put_u32_le(self.level);
         encoded.put_u32_le(bloom_offset as u32);
         encoded.put_u32_le(meta_offset as u32);
 
-        match shared::SimpleDbFile::create(path, &encoded, shared::SimpleDbFileMode::ReadOnly) {
+        let block_size_bytes = self.options.block_size_bytes;
+        let compression = self.options.sstable_compression;
+        //Opening a file that doesn't exist yet requires write access, so it cannot be created in read only mode
+        match shared::SimpleDbFile::create(path, &encoded, shared::SimpleDbFileMode::RandomWrites) {
             Ok(lsm_file) => Ok(SSTable::create(self.built_block_metadata, self.options, bloom_filter, self.first_key.unwrap(),
                                                self.last_key.unwrap(), lsm_file, self.level, id, SSTABLE_ACTIVE, self.keyspace_desc,
+                                               Some(self.n_keys), block_size_bytes, compression, SSTABLE_FORMAT_VERSION,
             )),
             Err(e) => Err(CannotCreateSSTableFile(self.keyspace_desc.keyspace_id, id, e))
         }
@@ -138,19 +157,24 @@ impl SSTableBuilder {
             return
         }
 
-        for block_built in self.current_block_builder.build() {
-            let serialized_block = block_built.serialize(&self.options);
+        let first_key = self.first_key_current_block.take().unwrap();
+        let last_key = self.last_key_current_block.take().unwrap();
+
+        //If the last entry overflows the block size, multiple blocks are built. The ones after the first
+        //only contain parts of the overflow value, whose key is the last key
+        for (index, block_built) in self.current_block_builder.build().into_iter().enumerate() {
+            let serialized_block = compress_block(block_built.serialize(&self.options), self.options.sstable_compression);
 
             self.built_block_metadata.push(BlockMetadata {
-                first_key: self.first_key_current_block.take().unwrap(),
-                last_key: self.last_key_current_block.take().unwrap(),
+                first_key: if index == 0 { first_key.clone() } else { last_key.clone() },
+                last_key: last_key.clone(),
                 offset: self.built_encoded_blocks.len(),
             });
             let crc = crc32fast::hash(&serialized_block);
             self.built_encoded_blocks.extend(serialized_block);
             self.built_encoded_blocks.put_u32_le(crc);
-
-            self.current_block_builder = BlockBuilder::create(self.options.clone(), self.keyspace_desc);
         }
+
+        self.current_block_builder = BlockBuilder::create(self.options.clone(), self.keyspace_desc);
     }
 }
\ No newline at end of file
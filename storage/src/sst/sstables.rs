@@ -2,11 +2,13 @@ use crate::keyspace::keyspace_descriptor::KeyspaceDescriptor;
 use crate::manifest::manifest::{Manifest, ManifestOperationContent, MemtableFlushManifestOperation};
 use crate::sst::sstable::{SSTable, SSTABLE_ACTIVE};
 use crate::sst::sstable_builder::SSTableBuilder;
-use crate::sst::sstables_files::{extract_sstable_id_from_file, is_sstable_file, to_sstable_file_name};
+use crate::sst::sstables_files::{extract_sstable_id_from_file, is_quarantined_sstable_file, is_sstable_file, to_sstable_file_name};
 use crate::sst::ssttable_iterator::SSTableIterator;
 use crate::transactions::transaction::Transaction;
+use crate::transactions::transaction_manager::TransactionManager;
 use bytes::Bytes;
 use shared::iterators::merge_iterator::MergeIterator;
+use shared::iterators::storage_iterator::StorageIterator;
 use shared::logger::logger;
 use shared::logger::SimpleDbLayer::StorageKeyspace;
 use std::cmp::max;
@@ -16,6 +18,8 @@ use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering::{Acquire, Relaxed};
 use std::sync::{Arc, RwLock};
 use shared::SimpleDbError::CannotReadSSTablesFiles;
+use shared::key::Key;
+use crate::utils::key_range::merge_key_ranges;
 
 pub struct SSTables {
     //For each level one index entry
@@ -67,6 +71,13 @@ impl SSTables {
         for file in fs::read_dir(path).map_err(|e| CannotReadSSTablesFiles(keyspace_desc.keyspace_id, e))? {
             let file = file.unwrap();
 
+            //Quarantined SSTables are not loaded, but their ids are not reused, so their files are not overwritten
+            if is_quarantined_sstable_file(&file) {
+                if let Ok(sstable_id) = extract_sstable_id_from_file(&file) {
+                    max_sstable_id = max(max_sstable_id, sstable_id);
+                }
+                continue;
+            }
             if !is_sstable_file(&file) {
                 continue;
             }
@@ -95,6 +106,54 @@ impl SSTables {
         Ok((levels, max_sstable_id))
     }
 
+    //Returns the corrupted parts of every SSTable file of the keyspace
+    pub fn verify_files(
+        options: &Arc<shared::SimpleDbOptions>,
+        keyspace_desc: KeyspaceDescriptor,
+    ) -> Result<Vec<shared::SimpleDbError>, shared::SimpleDbError> {
+        let path = shared::get_directory_usize(&options.base_path, keyspace_desc.keyspace_id);
+        let mut corrupted_parts = Vec::new();
+
+        for file in fs::read_dir(path.as_path()).map_err(|e| CannotReadSSTablesFiles(keyspace_desc.keyspace_id, e))? {
+            let file = file.unwrap();
+
+            if !is_sstable_file(&file) {
+                continue;
+            }
+
+            if let Ok(sstable_id) = extract_sstable_id_from_file(&file) {
+                corrupted_parts.extend(SSTable::verify_file(
+                    sstable_id, file.path().as_path(), options, keyspace_desc
+                )?);
+            }
+        }
+
+        Ok(corrupted_parts)
+    }
+
+    //Like verify_files, but only checks the SSTables in use. SSTables deleted by a compaction while being checked are skipped
+    pub fn check_integrity(&self) -> Vec<shared::SimpleDbError> {
+        let mut corrupted_parts = Vec::new();
+
+        for level in 0..self.sstables.len() {
+            for sstable in self.get_sstables(level) {
+                //Quarantined SSTables files have already been reported and moved aside
+                if sstable.quarantined.load(Acquire) {
+                    continue;
+                }
+
+                let path = self.to_sstable_file_path(sstable.sstable_id, self.keyspace_desc.keyspace_id);
+                match SSTable::verify_file(sstable.sstable_id, path.as_path(), &self.options, self.keyspace_desc) {
+                    Ok(sstable_corrupted_parts) => corrupted_parts.extend(sstable_corrupted_parts),
+                    Err(_) if !self.contains_sstable_id(sstable.sstable_id) => {},
+                    Err(error) => corrupted_parts.push(error),
+                }
+            }
+        }
+
+        corrupted_parts
+    }
+
     pub fn scan_from_level(&self, levels_id: &Vec<usize>) -> MergeIterator<SSTableIterator> {
         let mut iterators: Vec<Box<SSTableIterator>> = Vec::new();
 
@@ -110,6 +169,33 @@ impl SSTables {
         MergeIterator::create(iterators)
     }
 
+    pub fn scan_sstables(&self, level_id: usize, sstables_id: &Vec<shared::SSTableId>) -> MergeIterator<SSTableIterator> {
+        let mut iterators: Vec<Box<SSTableIterator>> = Vec::new();
+
+        for sstable in self.sstables[level_id].read().unwrap().iter() {
+            if sstables_id.contains(&sstable.sstable_id) {
+                iterators.push(Box::new(SSTableIterator::create(sstable.clone(), &Transaction::none(), self.keyspace_desc)))
+            }
+        }
+
+        MergeIterator::create(iterators)
+    }
+
+    //sstables_id is indexed by level
+    pub fn scan_from_sstables(&self, sstables_id: &Vec<Vec<shared::SSTableId>>) -> MergeIterator<SSTableIterator> {
+        let mut iterators: Vec<Box<SSTableIterator>> = Vec::new();
+
+        for (level_id, sstables_id_in_level) in sstables_id.iter().enumerate() {
+            for sstable in self.sstables[level_id].read().unwrap().iter() {
+                if sstables_id_in_level.contains(&sstable.sstable_id) {
+                    iterators.push(Box::new(SSTableIterator::create(sstable.clone(), &Transaction::none(), self.keyspace_desc)))
+                }
+            }
+        }
+
+        MergeIterator::create(iterators)
+    }
+
     pub fn scan_all(&self, transaction: &Transaction) -> MergeIterator<SSTableIterator> {
         let iterators = self.create_iterators(transaction);
         MergeIterator::create(iterators)
@@ -157,6 +243,28 @@ impl SSTables {
         };
     }
 
+    //Removes all SSTables in all levels and deletes their files.
+    //Their rolledback writes are discarded before deleting any file, so they are discarded even if a delete fails
+    pub fn delete_all(&self, transaction_manager: &Arc<TransactionManager>) -> Result<(), shared::SimpleDbError> {
+        let mut sstables_to_delete = Vec::new();
+        for sstables_lock in &self.sstables {
+            let mut lock_result = sstables_lock.write();
+            sstables_to_delete.extend(lock_result.as_mut().unwrap().drain(..));
+        }
+
+        for sstable in &sstables_to_delete {
+            let mut iterator = SSTableIterator::create(sstable.clone(), &Transaction::none(), self.keyspace_desc);
+            while iterator.next() {
+                let _ = transaction_manager.on_write_key(iterator.key());
+            }
+        }
+        for sstable in sstables_to_delete {
+            sstable.delete()?;
+        }
+
+        Ok(())
+    }
+
     pub fn contains_sstable_id(&self, sstable_id: shared::SSTableId) -> bool {
         for lock_sstables_level in &self.sstables {
             let read_lock_result = lock_sstables_level.read().unwrap();
@@ -220,6 +328,86 @@ impl SSTables {
         }
     }
 
+    //Sum of the number of keys of each SSTable. Keys present in multiple SSTables are counted multiple times
+    //Returns None if some SSTable doesn't store its number of keys
+    pub fn estimate_n_keys(&self) -> Option<usize> {
+        let mut n_keys = 0;
+        for sstables_lock in &self.sstables {
+            for sstable in sstables_lock.read().unwrap().iter() {
+                n_keys += sstable.n_keys?;
+            }
+        }
+
+        Some(n_keys)
+    }
+
+    //Returns the smallest and the biggest key stored in the SSTables
+    pub fn get_key_range(&self) -> Option<(Key, Key)> {
+        let mut key_range: Option<(Key, Key)> = None;
+        for sstables_lock in &self.sstables {
+            for sstable in sstables_lock.read().unwrap().iter() {
+                key_range = merge_key_ranges(key_range, &sstable.first_key, &sstable.last_key);
+            }
+        }
+
+        key_range
+    }
+
+    //Returns the keys in which the keyspace can be split, so that every SSTable is contained in only one partition.
+    //SSTables with overlapping key ranges are grouped together. The first key of every group, except the first one, is returned in ascending order
+    pub fn get_partition_keys(&self) -> Vec<Key> {
+        let mut key_ranges: Vec<(Key, Key)> = Vec::new();
+        for sstables_lock in &self.sstables {
+            for sstable in sstables_lock.read().unwrap().iter() {
+                key_ranges.push((sstable.first_key.clone(), sstable.last_key.clone()));
+            }
+        }
+        key_ranges.sort_by(|a, b| a.0.get_value().cmp(b.0.get_value()));
+
+        let mut partition_keys = Vec::new();
+        let mut group_last_key: Option<Key> = None;
+        for (first_key, last_key) in key_ranges {
+            match group_last_key.take() {
+                Some(current_last_key) if first_key.get_value() > current_last_key.get_value() => {
+                    partition_keys.push(first_key);
+                    group_last_key = Some(last_key);
+                },
+                Some(current_last_key) => {
+                    group_last_key = Some(std::cmp::max_by(current_last_key, last_key, |a, b| a.get_value().cmp(b.get_value())));
+                },
+                None => group_last_key = Some(last_key),
+            }
+        }
+
+        partition_keys
+    }
+
+    //Nº of SSTables of each level, up to the last level with SSTables
+    pub fn get_n_sstables_per_level(&self) -> Vec<usize> {
+        let mut n_sstables_per_level: Vec<usize> = self.sstables.iter()
+            .map(|sstables_lock| sstables_lock.read().unwrap().len())
+            .collect();
+        while n_sstables_per_level.last() == Some(&0) {
+            n_sstables_per_level.pop();
+        }
+
+        n_sstables_per_level
+    }
+
+    pub fn get_size_bytes(&self) -> usize {
+        self.sstables.iter()
+            .map(|sstables_lock| sstables_lock.read().unwrap().iter()
+                .map(|sstable| sstable.size())
+                .sum::<usize>())
+            .sum()
+    }
+
+    pub fn get_n_sstables_all_levels(&self) -> usize {
+        self.sstables.iter()
+            .map(|sstables_lock| sstables_lock.read().unwrap().len())
+            .sum()
+    }
+
     pub fn get_n_levels(&self) -> usize {
         self.n_current_levels
     }
@@ -4,6 +4,11 @@ pub(crate) fn is_sstable_file(file: &DirEntry) -> bool {
     file.file_name().to_str().unwrap().starts_with("sst-")
 }
 
+//Files of SSTables moved aside by SSTable::quarantine()
+pub(crate) fn is_quarantined_sstable_file(file: &DirEntry) -> bool {
+    file.file_name().to_str().unwrap().starts_with("quarantine-sst-")
+}
+
 pub(crate) fn to_sstable_file_name(sstable_id: shared::SSTableId) -> String {
     let result = format!("sst-{}", sstable_id);
     result
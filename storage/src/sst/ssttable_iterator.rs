@@ -93,8 +93,15 @@ impl SSTableIterator {
     }
 
     fn load_block(&mut self, block_id: usize) -> Arc<Block> {
-        self.sstable.blocks.load_block(block_id)
-            .expect("Cannot load block")
+        match self.sstable.blocks.load_block(block_id) {
+            Ok(block) => block,
+            //The corrupted block is read as an empty block
+            Err(error) if self.sstable.blocks.options.quarantine_corrupted_sstables => {
+                self.sstable.quarantine(&error);
+                Arc::new(Block::create_empty(self.key_desc))
+            },
+            Err(error) => panic!("Cannot load block: {:?}", error),
+        }
     }
 
     fn finish_iterator(&mut self) {
@@ -103,6 +110,49 @@ impl SSTableIterator {
         self.current_block_iterator = None;
     }
 
+    fn prev_key_iterator(&mut self) -> bool {
+        loop {
+            if let Some(current_block_iterator) = self.current_block_iterator.as_mut() {
+                if current_block_iterator.prev() {
+                    return true;
+                }
+            }
+            if self.current_block_id <= 0 {
+                self.finish_iterator();
+                return false;
+            }
+
+            self.prev_block();
+        }
+    }
+
+    fn prev_block(&mut self) {
+        self.current_block_id = self.current_block_id - 1;
+        let block_metadata = self.sstable.blocks.block_metadata[self.current_block_id as usize].clone();
+        self.set_iterating_block(block_metadata);
+        self.current_block_iterator.as_mut().unwrap().seek_to_end();
+    }
+
+    //When iterating backwards, the last block of an overflow value is found first. The value starts in the last entry
+    //of the previous blocks whose last key is the same
+    fn read_prev_overflow_value(&mut self, key: &Key, last_value: Bytes) -> Bytes {
+        let mut values = vec![last_value];
+
+        while self.current_block_id > 0 &&
+            self.sstable.blocks.block_metadata[self.current_block_id as usize - 1].last_key.eq(key) {
+            self.prev_block();
+            let block_iterator = self.current_block_iterator.as_mut().unwrap();
+            block_iterator.prev();
+            values.push(Bytes::copy_from_slice(block_iterator.value()));
+        }
+
+        let mut final_bytes = Vec::new();
+        for value in values.into_iter().rev() {
+            final_bytes.extend(value);
+        }
+        Bytes::from(final_bytes)
+    }
+
     fn read_current_overflow_value(&mut self, first_value: Bytes) -> Bytes {
         let mut final_bytes = first_value.to_vec();
 
@@ -122,10 +172,13 @@ impl SSTableIterator {
 impl StorageIterator for SSTableIterator {
     fn next(&mut self) -> bool {
         loop {
-            let advanced = self.next_key_iterator();
+            //The iterator might have been exhausted by a seek past the SSTable's last key
+            if !self.next_key_iterator() {
+                return false;
+            }
             let block_iterator = self.current_block_iterator.as_ref().unwrap();
 
-            if advanced && self.transaction.can_read(block_iterator.key()) {
+            if self.transaction.can_read(block_iterator.key()) {
                 //Key
                 self.current_key = Some(block_iterator.key().clone());
                 //Value
@@ -139,8 +192,6 @@ impl StorageIterator for SSTableIterator {
                 }
 
                 return true
-            } else if !advanced {
-                return false;
             }
         }
     }
@@ -199,6 +250,38 @@ impl StorageIterator for SSTableIterator {
             self.finish_iterator();
         }
     }
+
+    fn seek_to_end(&mut self) {
+        self.finish_iterator();
+        self.current_block_id = self.sstable.blocks.block_metadata.len() as i32;
+    }
+
+    fn prev(&mut self) -> bool {
+        loop {
+            if !self.prev_key_iterator() {
+                return false;
+            }
+
+            let block_iterator = self.current_block_iterator.as_ref().unwrap();
+            let key = block_iterator.key().clone();
+            let mut value = Bytes::copy_from_slice(block_iterator.value());
+            if block_iterator.is_last_overflow_block() {
+                value = self.read_prev_overflow_value(&key, value);
+            }
+
+            if self.transaction.can_read(&key) {
+                self.current_key = Some(key);
+                self.current_value = Some(value);
+                return true;
+            }
+        }
+    }
+
+    fn has_prev(&self) -> bool {
+        self.current_block_id > 0 || (
+            self.current_block_iterator.is_some() &&
+            self.current_block_iterator.as_ref().unwrap().has_prev())
+    }
 }
 
 #[cfg(test)]
@@ -207,7 +290,7 @@ mod test {
     use crate::sst::block::block_builder::BlockBuilder;
     use crate::sst::block_cache::BlockCache;
     use crate::sst::block_metadata::BlockMetadata;
-    use crate::sst::sstable::{SSTable, SSTABLE_ACTIVE};
+    use crate::sst::sstable::{SSTable, SSTABLE_ACTIVE, SSTABLE_FORMAT_VERSION};
     use crate::sst::ssttable_iterator::SSTableIterator;
     use crate::transactions::transaction::Transaction;
     use crate::utils::bloom_filter::BloomFilter;
@@ -216,7 +299,7 @@ mod test {
     use shared::key::Key;
     use shared::{assertions, SimpleDbFileWrapper, Type};
     use std::cell::UnsafeCell;
-    use std::sync::atomic::AtomicU8;
+    use std::sync::atomic::{AtomicBool, AtomicU8};
     use std::sync::{Arc, Mutex};
     use crate::sst::block::blocks::Blocks;
 
@@ -285,6 +368,21 @@ mod test {
         );
     }
 
+    //SSTable:
+    //Block1: [Alberto, Berto]
+    //Block2: [Cigu, De]
+    //Block3: [Estonia, Gibraltar, Zi]
+    #[test]
+    fn seek_after_last_key() {
+        let mut iterator = build_sstable_iterator();
+        iterator.seek(&Bytes::from("Zz"), true);
+        assert!(!iterator.next());
+
+        let mut iterator = build_sstable_iterator();
+        iterator.seek(&Bytes::from("Zi"), false);
+        assert!(!iterator.next());
+    }
+
     //SSTable:
     //Block1: [Alberto, Berto]
     //Block2: [Cigu, De]
@@ -305,7 +403,46 @@ mod test {
         );
     }
 
+    //SSTable:
+    //Block1: [Alberto, Berto]
+    //Block2: [Cigu, De]
+    //Block3: [Estonia, Gibraltar, Zi]
+    #[test]
+    fn prev_has_prev() {
+        assertions::assert_reversed_iterator_str_seq(
+            build_sstable_iterator(),
+            vec![
+                "Zi",
+                "Gibraltar",
+                "Estonia",
+                "De",
+                "Cigu",
+                "Berto",
+                "Alberto"
+            ]
+        );
+    }
+
+    //Only Block3 can be loaded, reading from the end should only need the last block
+    #[test]
+    fn prev_reads_only_last_blocks() {
+        let mut iterator = build_sstable_iterator_with_cached_blocks(vec![2]);
+        iterator.seek_to_end();
+
+        assert!(iterator.prev());
+        assert_eq!(iterator.key().clone(), Key::create_from_str("Zi", 0));
+        assert!(iterator.prev());
+        assert_eq!(iterator.key().clone(), Key::create_from_str("Gibraltar", 0));
+        assert!(iterator.prev());
+        assert_eq!(iterator.key().clone(), Key::create_from_str("Estonia", 0));
+        assert!(iterator.has_prev());
+    }
+
     fn build_sstable_iterator() -> SSTableIterator {
+        build_sstable_iterator_with_cached_blocks(vec![0, 1, 2])
+    }
+
+    fn build_sstable_iterator_with_cached_blocks(cached_blocks: Vec<usize>) -> SSTableIterator {
         let keyspace_desc = KeyspaceDescriptor::create_mock(Type::String);
 
         let mut block1 = BlockBuilder::create(Arc::new(shared::SimpleDbOptions::default()), keyspace_desc);
@@ -325,9 +462,11 @@ mod test {
         let block3 = Arc::new(block3.build().remove(0));
 
         let mut block_cache = BlockCache::create(Arc::new(shared::SimpleDbOptions::default()));
-        block_cache.put(0, block1);
-        block_cache.put(1, block2);
-        block_cache.put(2, block3);
+        for (block_id, block) in vec![block1, block2, block3].into_iter().enumerate() {
+            if cached_blocks.contains(&block_id) {
+                block_cache.put(block_id, block);
+            }
+        }
 
         let sstable = Arc::new(SSTable{
             sstable_id: 1,
@@ -343,12 +482,17 @@ mod test {
                 block_cache: Mutex::new(block_cache),
                 options: Arc::new(shared::SimpleDbOptions::default()),
                 file: SimpleDbFileWrapper{ file: UnsafeCell::new(shared::SimpleDbFile::create_mock()) },
+                block_size_bytes: shared::SimpleDbOptions::default().block_size_bytes,
+                compression: shared::SSTableCompression::None,
+                format_version: SSTABLE_FORMAT_VERSION,
                 sstable_id: 1
             },
             level: 0,
             state: AtomicU8::new(SSTABLE_ACTIVE),
+            quarantined: AtomicBool::new(false),
             first_key: Key::create_from_str("Alberto", 1),
             last_key: Key::create_from_str("Zi", 1),
+            n_keys: None,
             keyspace_desc
         });
 
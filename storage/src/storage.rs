@@ -2,12 +2,12 @@ use crate::keyspace::keyspaces::Keyspaces;
 use crate::memtables::memtable_iterator::MemtableIterator;
 use crate::sst::ssttable_iterator::SSTableIterator;
 use crate::transactions::transaction::Transaction;
-use crate::transactions::transaction_manager::{IsolationLevel, TransactionManager};
+use crate::transactions::transaction_manager::{IsolationLevel, TransactionAbortListener, TransactionDesc, TransactionManager};
 use shared::iterators::merge_iterator::MergeIterator;
 use crate::utils::storage_engine_iterator::StorageEngineIterator;
 use shared::iterators::two_merge_iterators::TwoMergeIterator;
 use bytes::Bytes;
-use shared::{Flag, KeyspaceId, SimpleDbError, SimpleDbOptions, Type};
+use shared::{Flag, KeyspaceId, SimpleDbError, SimpleDbOptions, TxnId, Type};
 use std::collections::VecDeque;
 use std::sync::Arc;
 use shared::logger::{logger, SimpleDbLayer};
@@ -18,6 +18,7 @@ pub struct Storage {
     transaction_manager: Arc<TransactionManager>,
     temporary_spaces: TemporarySpaces,
     keyspaces: Keyspaces,
+    options: Arc<SimpleDbOptions>,
 }
 
 //Key value
@@ -34,6 +35,11 @@ impl Storage {
     pub fn create(options: Arc<SimpleDbOptions>) -> Result<Storage, SimpleDbError> {
         logger().info(SimpleDbLayer::Storage, "Starting storage engine!");
 
+        options.validate()?;
+        if options.verify_on_open {
+            Self::verify_files(&options)?;
+        }
+
         let transaction_manager = Arc::new(
             TransactionManager::create_recover_from_log(options.clone())?
         );
@@ -45,24 +51,57 @@ impl Storage {
             temporary_spaces: TemporarySpaces::create(options.clone())?,
             transaction_manager,
             keyspaces,
+            options,
         };
 
         storage.keyspaces.recover_from_manifest();
+        storage.keyspaces.recover_truncation()?;
         storage.keyspaces.start_keyspaces_compaction_threads();
+        TransactionManager::start_transaction_timeout_thread(&storage.transaction_manager);
 
         logger().info(SimpleDbLayer::Storage, "Storage engine started!");
 
         Ok(storage)
     }
 
+    //Decodes every keyspace file before loading them, so corruption is reported at startup instead of in the first read
+    fn verify_files(options: &Arc<SimpleDbOptions>) -> Result<(), SimpleDbError> {
+        logger().info(SimpleDbLayer::Storage, "Verifying storage files");
+
+        let corrupted_parts = Keyspaces::verify_keyspaces(options)?;
+        if !corrupted_parts.is_empty() {
+            return Err(SimpleDbError::CorruptedFiles(corrupted_parts));
+        }
+
+        Ok(())
+    }
+
     pub fn create_mock(simple_db_options: &Arc<SimpleDbOptions>) -> Storage {
         Storage {
             transaction_manager: Arc::new(TransactionManager::create_mock(simple_db_options.clone())),
             keyspaces: Keyspaces::mock(simple_db_options.clone()),
             temporary_spaces: TemporarySpaces::create_mock(),
+            options: simple_db_options.clone(),
         }
     }
 
+    //Writes a consistent copy of the storage data into snapshot_path, which can be opened as any other storage base path.
+    //Only the data visible when the snapshot starts is written
+    pub fn snapshot(&self, snapshot_path: &str) -> Result<(), SimpleDbError> {
+        shared::utils::create_paths(&snapshot_path.to_string())
+            .map_err(|e| SimpleDbError::CannotCreateSnapshot(snapshot_path.to_string(), e))?;
+        let snapshot_options = shared::start_simpledb_options_builder_from(&self.options)
+            .base_path(snapshot_path)
+            .build_arc();
+
+        let transaction = self.transaction_manager.start_transaction(IsolationLevel::SnapshotIsolation);
+        let snapshot_result = self.keyspaces.snapshot(&transaction, &snapshot_options)
+            .and_then(|_| self.transaction_manager.create_snapshot_log(&transaction, &snapshot_options));
+        self.transaction_manager.commit(&transaction)?;
+
+        snapshot_result
+    }
+
     pub fn scan_all(&self, keyspace_id: KeyspaceId) -> Result<SimpleDbStorageIterator, SimpleDbError> {
         let transaction = self.transaction_manager.start_transaction(IsolationLevel::SnapshotIsolation);
         let mut iterator = self.scan_all_with_transaction(&transaction, keyspace_id)?;
@@ -89,6 +128,7 @@ impl Storage {
         key: &Bytes,
         inclusive: bool,
     ) -> Result<SimpleDbStorageIterator, SimpleDbError> {
+        self.transaction_manager.check_not_aborted(transaction)?;
         let keyspace = self.keyspaces.get_keyspace(keyspace_id)?;
         Ok(keyspace.scan_from_key_with_transaction(transaction, key, inclusive))
     }
@@ -98,10 +138,21 @@ impl Storage {
         transaction: &Transaction,
         keyspace_id: KeyspaceId,
     ) -> Result<SimpleDbStorageIterator, SimpleDbError> {
+        self.transaction_manager.check_not_aborted(transaction)?;
         let keyspace = self.keyspaces.get_keyspace(keyspace_id)?;
         Ok(keyspace.scan_all_with_transaction(transaction))
     }
 
+    pub fn scan_all_backwards_with_transaction(
+        &self,
+        transaction: &Transaction,
+        keyspace_id: KeyspaceId,
+    ) -> Result<SimpleDbStorageIterator, SimpleDbError> {
+        self.transaction_manager.check_not_aborted(transaction)?;
+        let keyspace = self.keyspaces.get_keyspace(keyspace_id)?;
+        Ok(keyspace.scan_all_backwards_with_transaction(transaction))
+    }
+
     pub fn get(
         &self,
         keyspace_id: KeyspaceId,
@@ -117,6 +168,7 @@ impl Storage {
         transaction: &Transaction,
         key: &Bytes,
     ) -> Result<Option<Bytes>, SimpleDbError> {
+        self.transaction_manager.check_not_aborted(transaction)?;
         let keyspace = self.keyspaces.get_keyspace(keyspace_id)?;
         keyspace.get_with_transaction(transaction, key)
     }
@@ -138,6 +190,7 @@ impl Storage {
         key: Bytes,
         value: &[u8],
     ) -> Result<(), SimpleDbError> {
+        self.transaction_manager.check_not_aborted(transaction)?;
         let keyspace = self.keyspaces.get_keyspace(keyspace_id)?;
         keyspace.set_with_transaction(transaction, key, value)
     }
@@ -157,6 +210,7 @@ impl Storage {
         transaction: &Transaction,
         key: Bytes,
     ) -> Result<(), SimpleDbError> {
+        self.transaction_manager.check_not_aborted(transaction)?;
         let keyspace = self.keyspaces.get_keyspace(keyspace_id)?;
         keyspace.delete_with_transaction(transaction, key)
     }
@@ -200,12 +254,130 @@ impl Storage {
         self.transaction_manager.rollback(transaction)
     }
 
+    pub fn create_savepoint(&self, transaction: &Transaction, name: &str) {
+        self.transaction_manager.create_savepoint(transaction, name)
+    }
+
+    //Writes made after the savepoint are undone in the memtables. If some of them have already been
+    //flushed to disk, they can't be undone, so CannotRollbackToSavepoint is returned
+    pub fn rollback_to_savepoint(&self, transaction: &Transaction, name: &str) -> Result<(), SimpleDbError> {
+        let writes_to_restore = self.transaction_manager.rollback_to_savepoint(transaction, name)?;
+        let mut all_restored = true;
+
+        for savepoint_writes in writes_to_restore {
+            for (write, previous_value) in &savepoint_writes {
+                let keyspace = self.keyspaces.get_keyspace(write.keyspace_id)?;
+                all_restored &= keyspace.restore_savepoint_write(transaction, write, previous_value)?;
+            }
+        }
+
+        if all_restored {
+            Ok(())
+        } else {
+            Err(SimpleDbError::CannotRollbackToSavepoint(name.to_string()))
+        }
+    }
+
+    pub fn release_savepoint(&self, transaction: &Transaction, name: &str) -> Result<(), SimpleDbError> {
+        self.transaction_manager.release_savepoint(transaction, name)
+    }
+
+    pub fn get_active_transactions(&self) -> Vec<TxnId> {
+        self.transaction_manager.get_active_transactions()
+    }
+
+    //See TransactionManager::lock_key()
+    pub fn lock_key(&self, transaction: &Transaction, keyspace_id: KeyspaceId, key: &Bytes) -> Result<(), SimpleDbError> {
+        self.transaction_manager.lock_key(transaction, keyspace_id, key)
+    }
+
+    pub fn describe_transaction(&self, transaction: &Transaction) -> TransactionDesc {
+        self.transaction_manager.describe_transaction(transaction)
+    }
+
+    pub fn describe_active_transactions(&self) -> Vec<TransactionDesc> {
+        self.transaction_manager.describe_active_transactions()
+    }
+
+    pub fn set_transaction_abort_listener(&self, abort_listener: TransactionAbortListener) {
+        self.transaction_manager.set_abort_listener(abort_listener)
+    }
+
+    //Returns true if the transaction has been rolledback because of the transaction timeout
+    pub fn is_transaction_aborted(&self, txn_id: TxnId) -> bool {
+        self.transaction_manager.is_aborted(txn_id)
+    }
+
+    pub fn reads_last_committed_values(&self, transaction: &Transaction) -> bool {
+        self.transaction_manager.reads_last_committed_values(transaction)
+    }
+
+    pub fn last_committed_values_transaction(&self, transaction: &Transaction) -> Transaction {
+        self.transaction_manager.last_committed_values_transaction(transaction)
+    }
+
     pub fn create_keyspace(&self, flag: Flag, key_type: Type) -> Result<KeyspaceId, SimpleDbError> {
-        let keyspace = self.keyspaces.create_keyspace(flag, key_type)?;
+        self.create_keyspace_with_ttl(flag, key_type, 0)
+    }
+
+    //Entries older than ttl_seconds will be dropped in compactions. The age of an entry is obtained with
+    //SimpleDbOptions::storage_value_insert_time
+    pub fn create_keyspace_with_ttl(&self, flag: Flag, key_type: Type, ttl_seconds: u64) -> Result<KeyspaceId, SimpleDbError> {
+        let keyspace = self.keyspaces.create_keyspace(flag, key_type, ttl_seconds)?;
         keyspace.start_compaction_thread();
         Ok(keyspace.keyspace_id())
     }
 
+    //Expect the keyspace to not be used by any active transaction
+    pub fn delete_keyspace(&self, keyspace_id: KeyspaceId) -> Result<(), SimpleDbError> {
+        self.keyspaces.delete_keyspace(keyspace_id)
+    }
+
+    //Every keyspace is truncated or none of them, even if there is a crash in the middle.
+    //Expect the keyspaces to not be used by any active transaction
+    pub fn truncate_keyspaces(&self, keyspaces_id: &[KeyspaceId]) -> Result<(), SimpleDbError> {
+        self.keyspaces.truncate_keyspaces(keyspaces_id)
+    }
+
+    //Compacts all the SSTables of the keyspace, removing deleted keys and overwritten versions that no active
+    //transaction can read. Returns the nº of bytes reclaimed
+    pub fn vacuum_keyspace(&self, keyspace_id: KeyspaceId) -> Result<usize, SimpleDbError> {
+        self.keyspaces.get_keyspace(keyspace_id)?.vacuum()
+    }
+
+    //Decodes every block and bloom filter of the SSTables in use, checking their CRCs and the order of their keys.
+    //Returns the corrupted parts found instead of failing on the first one
+    pub fn check_integrity(&self) -> Vec<SimpleDbError> {
+        self.keyspaces.check_integrity()
+    }
+
+    //Approximate number of keys in the keyspace. Returns None if it cannot be estimated
+    pub fn estimate_n_keys(&self, keyspace_id: KeyspaceId) -> Result<Option<usize>, SimpleDbError> {
+        Ok(self.keyspaces.get_keyspace(keyspace_id)?.estimate_n_keys())
+    }
+
+    //Returns the smallest and the biggest key of the keyspace. Returns None if the keyspace is empty
+    pub fn get_key_range(&self, keyspace_id: KeyspaceId) -> Result<Option<(Bytes, Bytes)>, SimpleDbError> {
+        Ok(self.keyspaces.get_keyspace(keyspace_id)?.get_key_range()
+            .map(|(first_key, last_key)| (first_key.as_bytes().clone(), last_key.as_bytes().clone())))
+    }
+
+    //Returns the keys in which the keyspace can be split to be scanned in parallel. SSTables won't span multiple partitions
+    pub fn get_partition_keys(&self, keyspace_id: KeyspaceId) -> Result<Vec<Bytes>, SimpleDbError> {
+        Ok(self.keyspaces.get_keyspace(keyspace_id)?.get_partition_keys().iter()
+            .map(|partition_key| partition_key.as_bytes().clone())
+            .collect())
+    }
+
+    pub fn get_n_sstables_per_level(&self, keyspace_id: KeyspaceId) -> Result<Vec<usize>, SimpleDbError> {
+        Ok(self.keyspaces.get_keyspace(keyspace_id)?.get_n_sstables_per_level())
+    }
+
+    //Returns the nº of bytes stored on disk by the SSTables of the keyspace
+    pub fn get_size_bytes(&self, keyspace_id: KeyspaceId) -> Result<usize, SimpleDbError> {
+        Ok(self.keyspaces.get_keyspace(keyspace_id)?.get_size_bytes())
+    }
+
     pub fn create_temporary_space(&self) -> Result<TemporarySpace, SimpleDbError> {
         self.temporary_spaces.create_temporary_space()
     }
@@ -213,4 +385,411 @@ impl Storage {
     pub fn get_keyspaces_id(&self) -> Vec<KeyspaceId> {
         self.keyspaces.get_keyspaces_id()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use crate::storage::Storage;
+    use bytes::Bytes;
+    use shared::iterators::storage_iterator::StorageIterator;
+    use shared::logger::Logger;
+    use shared::{SSTableCorruptedPart, SimpleDbError, Type};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn invalid_block_size() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("simpledb-storage-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()));
+        std::fs::create_dir_all(&path).unwrap();
+        let options = shared::start_simpledb_options_builder()
+            .base_path(path.to_str().unwrap())
+            .block_size_bytes(65536)
+            .build_arc();
+        Logger::init(options.clone());
+
+        assert!(matches!(Storage::create(options), Err(SimpleDbError::InvalidOption(option_name)) if option_name == "block_size_bytes"));
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn transaction_timeout() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("simpledb-storage-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()));
+        std::fs::create_dir_all(&path).unwrap();
+        let options = shared::start_simpledb_options_builder()
+            .base_path(path.to_str().unwrap())
+            .transaction_timeout_ms(5)
+            .build_arc();
+        Logger::init(options.clone());
+
+        let storage = Storage::create(options.clone()).unwrap();
+        let keyspace_id = storage.create_keyspace(0, Type::String).unwrap();
+        let aborted_txn_ids = Arc::new(Mutex::new(Vec::new()));
+        let aborted_txn_ids_listener = aborted_txn_ids.clone();
+        storage.set_transaction_abort_listener(Box::new(move |txn_id| aborted_txn_ids_listener.lock().unwrap().push(txn_id)));
+
+        let transaction = storage.start_transaction();
+        storage.set_with_transaction(keyspace_id, &transaction, Bytes::from("a"), &vec![1]).unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+
+        assert!(!storage.get_active_transactions().contains(&transaction.id()));
+        assert_eq!(*aborted_txn_ids.lock().unwrap(), vec![transaction.id()]);
+        assert!(matches!(storage.set_with_transaction(keyspace_id, &transaction, Bytes::from("b"), &vec![2]), Err(SimpleDbError::TransactionAborted(_))));
+        //Writes that passed the abort check before the transaction was aborted are not counted
+        assert!(matches!(storage.transaction_manager.mark_write(&transaction), Err(SimpleDbError::TransactionAborted(_))));
+        assert!(matches!(storage.commit_transaction(&transaction), Err(SimpleDbError::TransactionAborted(_))));
+        assert!(!storage.is_transaction_aborted(transaction.id()));
+        assert!(storage.rollback_transaction(&transaction).is_ok());
+        assert_eq!(storage.get(keyspace_id, &Bytes::from("a")).unwrap(), None);
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn rollback_to_savepoint() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("simpledb-storage-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()));
+        std::fs::create_dir_all(&path).unwrap();
+        let options = shared::start_simpledb_options_builder()
+            .base_path(path.to_str().unwrap())
+            .build_arc();
+        Logger::init(options.clone());
+
+        let storage = Storage::create(options.clone()).unwrap();
+        let keyspace_id = storage.create_keyspace(0, Type::String).unwrap();
+
+        let transaction = storage.start_transaction();
+        storage.set_with_transaction(keyspace_id, &transaction, Bytes::from("a"), &vec![1]).unwrap();
+        storage.set_with_transaction(keyspace_id, &transaction, Bytes::from("b"), &vec![2]).unwrap();
+        storage.create_savepoint(&transaction, "savepoint");
+        storage.set_with_transaction(keyspace_id, &transaction, Bytes::from("b"), &vec![3]).unwrap();
+        storage.set_with_transaction(keyspace_id, &transaction, Bytes::from("c"), &vec![4]).unwrap();
+        storage.rollback_to_savepoint(&transaction, "savepoint").unwrap();
+        storage.commit_transaction(&transaction).unwrap();
+
+        assert_eq!(storage.get(keyspace_id, &Bytes::from("a")).unwrap(), Some(Bytes::from(vec![1])));
+        assert_eq!(storage.get(keyspace_id, &Bytes::from("b")).unwrap(), Some(Bytes::from(vec![2])));
+        assert_eq!(storage.get(keyspace_id, &Bytes::from("c")).unwrap(), None);
+        assert!(matches!(storage.release_savepoint(&transaction, "savepoint"), Err(SimpleDbError::SavepointNotFound(_))));
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn rollback_to_savepoint_recover_from_wal() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("simpledb-storage-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()));
+        std::fs::create_dir_all(&path).unwrap();
+        let options = shared::start_simpledb_options_builder()
+            .base_path(path.to_str().unwrap())
+            .build_arc();
+        Logger::init(options.clone());
+
+        let storage = Storage::create(options.clone()).unwrap();
+        let keyspace_id = storage.create_keyspace(0, Type::String).unwrap();
+
+        let transaction = storage.start_transaction();
+        storage.set_with_transaction(keyspace_id, &transaction, Bytes::from("a"), &[1]).unwrap();
+        storage.create_savepoint(&transaction, "savepoint");
+        storage.set_with_transaction(keyspace_id, &transaction, Bytes::from("a"), &[2]).unwrap();
+        storage.set_with_transaction(keyspace_id, &transaction, Bytes::from("b"), &[3]).unwrap();
+        storage.rollback_to_savepoint(&transaction, "savepoint").unwrap();
+        storage.commit_transaction(&transaction).unwrap();
+        drop(storage);
+
+        //Memtables are rebuilt from the WAL
+        let storage = Storage::create(options.clone()).unwrap();
+        assert_eq!(storage.get(keyspace_id, &Bytes::from("a")).unwrap(), Some(Bytes::from(vec![1])));
+        assert_eq!(storage.get(keyspace_id, &Bytes::from("b")).unwrap(), None);
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn truncate_keyspaces() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("simpledb-storage-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()));
+        std::fs::create_dir_all(&path).unwrap();
+        let options = shared::start_simpledb_options_builder()
+            .base_path(path.to_str().unwrap())
+            .memtable_max_size_bytes(1)
+            .max_memtables_inactive(0)
+            .build_arc();
+        Logger::init(options.clone());
+
+        let storage = Storage::create(options.clone()).unwrap();
+        let keyspace_id = storage.create_keyspace(0, Type::String).unwrap();
+        storage.set(keyspace_id, Bytes::from("a"), &vec![1]).unwrap();
+        storage.set(keyspace_id, Bytes::from("b"), &vec![2]).unwrap(); //Memtable with "a" is flushed to a SSTable
+
+        let rolledback_transaction = storage.start_transaction();
+        storage.set_with_transaction(keyspace_id, &rolledback_transaction, Bytes::from("c"), &[3]).unwrap();
+        storage.rollback_transaction(&rolledback_transaction).unwrap();
+
+        storage.truncate_keyspaces(&[keyspace_id]).unwrap();
+        assert_eq!(storage.get(keyspace_id, &Bytes::from("a")).unwrap(), None);
+        assert_eq!(storage.get(keyspace_id, &Bytes::from("b")).unwrap(), None);
+        //The truncated rolledback write is no longer tracked
+        assert!(!storage.start_transaction().active_transactions.contains(&rolledback_transaction.txn_id));
+
+        let mut sstable_path = shared::get_directory_usize(&options.base_path, keyspace_id);
+        sstable_path.push("sst-1");
+        assert!(!sstable_path.exists());
+
+        //Truncated data is not recovered when the keyspace is loaded again
+        let storage = Storage::create(options.clone()).unwrap();
+        assert_eq!(storage.get(keyspace_id, &Bytes::from("a")).unwrap(), None);
+        assert_eq!(storage.get(keyspace_id, &Bytes::from("b")).unwrap(), None);
+
+        //Crash after deciding to truncate the keyspace, but before truncating it
+        storage.set(keyspace_id, Bytes::from("d"), &[4]).unwrap();
+        let mut truncate_file_path = path.clone();
+        truncate_file_path.push("TRUNCATE");
+        std::fs::write(truncate_file_path.as_path(), (keyspace_id as u64).to_le_bytes()).unwrap();
+        drop(storage);
+
+        let storage = Storage::create(options.clone()).unwrap();
+        assert_eq!(storage.get(keyspace_id, &Bytes::from("d")).unwrap(), None);
+        assert!(!truncate_file_path.exists());
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn snapshot() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("simpledb-storage-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()));
+        std::fs::create_dir_all(&path).unwrap();
+        let options = shared::start_simpledb_options_builder()
+            .base_path(path.to_str().unwrap())
+            .build_arc();
+        Logger::init(options.clone());
+
+        let storage = Storage::create(options.clone()).unwrap();
+        let keyspace_id = storage.create_keyspace(0, Type::String).unwrap();
+        let transaction = storage.start_transaction();
+        storage.set_with_transaction(keyspace_id, &transaction, Bytes::from("a"), &vec![1]).unwrap();
+        storage.set_with_transaction(keyspace_id, &transaction, Bytes::from("b"), &vec![2]).unwrap();
+        storage.commit_transaction(&transaction).unwrap();
+        //Not committed when the snapshot is created
+        let active_transaction = storage.start_transaction();
+        storage.set_with_transaction(keyspace_id, &active_transaction, Bytes::from("c"), &vec![3]).unwrap();
+
+        let mut snapshot_path = path.clone();
+        snapshot_path.push("snapshot");
+        storage.snapshot(snapshot_path.to_str().unwrap()).unwrap();
+        storage.commit_transaction(&active_transaction).unwrap();
+
+        let snapshot_storage = Storage::create(shared::start_simpledb_options_builder_from(&options)
+            .base_path(snapshot_path.to_str().unwrap())
+            .build_arc()).unwrap();
+        let transaction = snapshot_storage.start_transaction();
+        assert_eq!(snapshot_storage.get_with_transaction(keyspace_id, &transaction, &Bytes::from("a")).unwrap(), Some(Bytes::from(vec![1])));
+        assert_eq!(snapshot_storage.get_with_transaction(keyspace_id, &transaction, &Bytes::from("b")).unwrap(), Some(Bytes::from(vec![2])));
+        assert_eq!(snapshot_storage.get_with_transaction(keyspace_id, &transaction, &Bytes::from("c")).unwrap(), None);
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn keyspace_statistics() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("simpledb-storage-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()));
+        std::fs::create_dir_all(&path).unwrap();
+        let options = shared::start_simpledb_options_builder()
+            .base_path(path.to_str().unwrap())
+            .build_arc();
+        Logger::init(options.clone());
+
+        let storage = Storage::create(options.clone()).unwrap();
+        let keyspace_id = storage.create_keyspace(0, Type::String).unwrap();
+        assert_eq!(storage.estimate_n_keys(keyspace_id).unwrap(), Some(0));
+        assert_eq!(storage.get_key_range(keyspace_id).unwrap(), None);
+
+        storage.set(keyspace_id, Bytes::from("b"), &vec![2]).unwrap();
+        storage.set(keyspace_id, Bytes::from("a"), &vec![1]).unwrap();
+        assert_eq!(storage.estimate_n_keys(keyspace_id).unwrap(), Some(2));
+        assert_eq!(storage.get_key_range(keyspace_id).unwrap(), Some((Bytes::from("a"), Bytes::from("b"))));
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn verify_on_open() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("simpledb-storage-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()));
+        std::fs::create_dir_all(&path).unwrap();
+        let options = shared::start_simpledb_options_builder()
+            .base_path(path.to_str().unwrap())
+            .memtable_max_size_bytes(1)
+            .max_memtables_inactive(0)
+            .build_arc();
+        Logger::init(options.clone());
+
+        let storage = Storage::create(options.clone()).unwrap();
+        let keyspace_id = storage.create_keyspace(0, Type::String).unwrap();
+        storage.set(keyspace_id, Bytes::from("a"), &vec![1]).unwrap();
+        storage.set(keyspace_id, Bytes::from("b"), &vec![2]).unwrap(); //Memtable with "a" is flushed to a SSTable
+
+        //Corrupt the first block of the SSTable
+        let mut sstable_path = shared::get_directory_usize(&options.base_path, keyspace_id);
+        sstable_path.push("sst-1");
+        let mut sstable_bytes = std::fs::read(&sstable_path).unwrap();
+        sstable_bytes[4] = !sstable_bytes[4];
+        std::fs::write(&sstable_path, sstable_bytes).unwrap();
+
+        //Without verifying, the corruption won't be found until the block is read
+        assert!(Storage::create(options.clone()).is_ok());
+
+        let verify_options = shared::start_simpledb_options_builder_from(&options)
+            .verify_on_open(true)
+            .build_arc();
+        match Storage::create(verify_options) {
+            Err(SimpleDbError::CorruptedFiles(corrupted_parts)) => {
+                assert_eq!(corrupted_parts.len(), 1);
+                assert!(matches!(corrupted_parts[0], SimpleDbError::CannotDecodeSSTable(_, 1, SSTableCorruptedPart::Block(0), _)));
+            },
+            _ => panic!("Expected corrupted files")
+        }
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn verify_on_open_keyspace_descriptor() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("simpledb-storage-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()));
+        std::fs::create_dir_all(&path).unwrap();
+        let options = shared::start_simpledb_options_builder()
+            .base_path(path.to_str().unwrap())
+            .verify_on_open(true)
+            .build_arc();
+        Logger::init(options.clone());
+
+        let storage = Storage::create(options.clone()).unwrap();
+        let keyspace_id = storage.create_keyspace(0, Type::String).unwrap();
+
+        //Truncate the keyspace descriptor
+        let mut descriptor_path = shared::get_directory_usize(&options.base_path, keyspace_id);
+        descriptor_path.push("desc");
+        std::fs::write(&descriptor_path, vec![0, 0, 0]).unwrap();
+
+        match Storage::create(options) {
+            Err(SimpleDbError::CorruptedFiles(corrupted_parts)) => {
+                assert_eq!(corrupted_parts.len(), 1);
+                assert!(matches!(corrupted_parts[0], SimpleDbError::CannotDecodeKeyspaceDescriptor(_, _)));
+            },
+            _ => panic!("Expected corrupted files")
+        }
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn quarantine_corrupted_sstables() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("simpledb-storage-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()));
+        std::fs::create_dir_all(&path).unwrap();
+        let options = shared::start_simpledb_options_builder()
+            .base_path(path.to_str().unwrap())
+            .block_size_bytes(64)
+            .memtable_max_size_bytes(100)
+            .max_memtables_inactive(0)
+            .compaction_task_frequency_ms(60 * 60 * 1000)
+            .build_arc();
+        Logger::init(options.clone());
+
+        let storage = Storage::create(options.clone()).unwrap();
+        let keyspace_id = storage.create_keyspace(0, Type::String).unwrap();
+        let value = vec![1; 16];
+        let transaction = storage.start_transaction();
+        for key in ["a", "b", "c", "d", "e", "f"] {
+            storage.set_with_transaction(keyspace_id, &transaction, Bytes::from(key), &value).unwrap();
+        }
+        storage.commit_transaction(&transaction).unwrap();
+        storage.set(keyspace_id, Bytes::from("z"), &value).unwrap(); //Writing to the full memtable makes it be flushed to a SSTable
+        drop(storage);
+
+        //Corrupt the second block of the SSTable
+        let mut sstable_path = shared::get_directory_usize(&options.base_path, keyspace_id);
+        sstable_path.push("sst-1");
+        let mut sstable_bytes = std::fs::read(&sstable_path).unwrap();
+        sstable_bytes[64 + 4] = !sstable_bytes[64 + 4];
+        std::fs::write(&sstable_path, sstable_bytes).unwrap();
+
+        let quarantine_options = shared::start_simpledb_options_builder_from(&options)
+            .quarantine_corrupted_sstables(true)
+            .build_arc();
+        let storage = Storage::create(quarantine_options).unwrap();
+        let mut iterator = storage.scan_all(keyspace_id).unwrap();
+        let mut keys = Vec::new();
+        while iterator.next() {
+            keys.push(String::from_utf8(iterator.key().as_bytes().to_vec()).unwrap());
+        }
+        //Key b was stored in the corrupted block
+        assert!(!keys.contains(&String::from("b")));
+        for surviving_key in ["a", "c", "d", "e"] {
+            assert!(keys.contains(&String::from(surviving_key)));
+        }
+
+        let mut quarantine_path = shared::get_directory_usize(&options.base_path, keyspace_id);
+        quarantine_path.push("quarantine-sst-1");
+        assert!(quarantine_path.exists());
+        assert!(!sstable_path.exists());
+        //Reading a key of the corrupted block fails, instead of returning an older version of it
+        assert!(matches!(storage.get(keyspace_id, &Bytes::from("b")), Err(SimpleDbError::CannotDecodeSSTable(..))));
+        drop(storage);
+
+        //The id of the quarantined SSTable is not reused
+        let storage = Storage::create(options.clone()).unwrap();
+        for key in ["g", "h", "i", "j", "k", "l"] {
+            storage.set(keyspace_id, Bytes::from(key), &value).unwrap();
+        }
+        assert!(!sstable_path.exists());
+        assert!(sstable_path.with_file_name("sst-2").exists());
+        drop(storage);
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn check_integrity() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("simpledb-storage-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()));
+        std::fs::create_dir_all(&path).unwrap();
+        let options = shared::start_simpledb_options_builder()
+            .base_path(path.to_str().unwrap())
+            .block_size_bytes(64)
+            .memtable_max_size_bytes(100)
+            .max_memtables_inactive(0)
+            .compaction_task_frequency_ms(60 * 60 * 1000)
+            .build_arc();
+        Logger::init(options.clone());
+
+        let storage = Storage::create(options.clone()).unwrap();
+        let keyspace_id = storage.create_keyspace(0, Type::String).unwrap();
+        let value = vec![1; 16];
+        let transaction = storage.start_transaction();
+        for key in ["a", "b", "c", "d", "e", "f"] {
+            storage.set_with_transaction(keyspace_id, &transaction, Bytes::from(key), &value).unwrap();
+        }
+        storage.commit_transaction(&transaction).unwrap();
+        storage.set(keyspace_id, Bytes::from("z"), &value).unwrap(); //Writing to the full memtable makes it be flushed to a SSTable
+        assert!(storage.check_integrity().is_empty());
+
+        //Corrupt the second block of the SSTable while the storage is running
+        let mut sstable_path = shared::get_directory_usize(&options.base_path, keyspace_id);
+        sstable_path.push("sst-1");
+        let mut sstable_bytes = std::fs::read(&sstable_path).unwrap();
+        sstable_bytes[64 + 4] = !sstable_bytes[64 + 4];
+        std::fs::write(&sstable_path, sstable_bytes).unwrap();
+
+        let corrupted_parts = storage.check_integrity();
+        assert_eq!(corrupted_parts.len(), 1);
+        assert!(matches!(corrupted_parts[0], SimpleDbError::CannotDecodeSSTable(_, 1, SSTableCorruptedPart::Block(1), _)));
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+}
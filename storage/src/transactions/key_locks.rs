@@ -0,0 +1,304 @@
+use bytes::Bytes;
+use shared::{KeyspaceId, SimpleDbError, TxnId};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Condvar, Mutex};
+
+//Once a transaction holds more keys than this in a keyspace that no other transaction has keys locked, it holds
+//the whole keyspace instead, so the memory used by bulk transactions is bounded
+const MAX_LOCKED_KEYS_PER_KEYSPACE: usize = 4096;
+
+//Keys written by active transactions. A transaction writing a key held by another transaction waits until the holder
+//finishes. Waiting transactions form a wait-for graph, which is checked every time a transaction starts waiting.
+//If it contains a cycle, the youngest transaction of the cycle is chosen as the victim, so deadlocks are broken
+//instead of waiting forever
+pub struct KeyLocks {
+    state: Mutex<KeyLocksState>,
+}
+
+//A None key locks the whole keyspace
+type LockedResource = (KeyspaceId, Option<Bytes>);
+
+struct Lock {
+    holder: TxnId,
+    waiters: Vec<TxnId>, //Woken up when the lock gets released
+}
+
+enum HeldKeys {
+    Keys(Vec<Bytes>),
+    Keyspace,
+}
+
+struct KeyLocksState {
+    locks: HashMap<LockedResource, Lock>,
+    held_by_holder: HashMap<TxnId, HashMap<KeyspaceId, HeldKeys>>,
+    n_locked_keys_by_keyspace: HashMap<KeyspaceId, usize>, //Keys locked by every transaction, without counting whole keyspaces
+    waits_for: HashMap<TxnId, TxnId>, //Waiting transaction -> Holder transaction
+    //Each waiting transaction waits on its own Condvar, so only the transactions that can continue are woken up
+    waiting: HashMap<TxnId, Arc<Condvar>>,
+    deadlock_victims: HashSet<TxnId>,
+}
+
+impl KeyLocks {
+    pub fn create() -> KeyLocks {
+        KeyLocks {
+            state: Mutex::new(KeyLocksState {
+                n_locked_keys_by_keyspace: HashMap::new(),
+                held_by_holder: HashMap::new(),
+                deadlock_victims: HashSet::new(),
+                waits_for: HashMap::new(),
+                waiting: HashMap::new(),
+                locks: HashMap::new(),
+            }),
+        }
+    }
+
+    //Blocks until the key can be held by txn_id. Returns DeadlockDetected if txn_id has been chosen as a deadlock victim,
+    //and TransactionAborted if it has been aborted while it was waiting. In both cases it won't hold the key
+    pub fn lock(
+        &self,
+        txn_id: TxnId,
+        keyspace_id: KeyspaceId,
+        key: &Bytes,
+        is_aborted: &dyn Fn(TxnId) -> bool,
+    ) -> Result<(), SimpleDbError> {
+        let keyspace_resource: LockedResource = (keyspace_id, None);
+        let key_resource: LockedResource = (keyspace_id, Some(key.clone()));
+        let mut state = self.state.lock().unwrap();
+
+        //Ok(true) if the key has to be locked, Ok(false) if txn_id already holds it
+        let result = loop {
+            if state.deadlock_victims.remove(&txn_id) {
+                break Err(SimpleDbError::DeadlockDetected(txn_id));
+            }
+            if is_aborted(txn_id) {
+                break Err(SimpleDbError::TransactionAborted(txn_id));
+            }
+
+            let resource = if state.locks.contains_key(&keyspace_resource) {
+                &keyspace_resource
+            } else if state.locks.contains_key(&key_resource) {
+                &key_resource
+            } else {
+                break Ok(true);
+            };
+            let lock = state.locks.get_mut(resource).unwrap();
+            let holder_txn_id = lock.holder;
+            if holder_txn_id == txn_id {
+                break Ok(false);
+            }
+            if !lock.waiters.contains(&txn_id) {
+                lock.waiters.push(txn_id);
+            }
+
+            state.waits_for.insert(txn_id, holder_txn_id);
+            if let Some(victim_txn_id) = Self::find_deadlock_victim(&state, txn_id) {
+                state.deadlock_victims.insert(victim_txn_id);
+                if let Some(victim_condvar) = state.waiting.get(&victim_txn_id) {
+                    victim_condvar.notify_one();
+                }
+                continue;
+            }
+
+            let condvar = state.waiting.entry(txn_id)
+                .or_insert_with(|| Arc::new(Condvar::new()))
+                .clone();
+            state = condvar.wait(state).unwrap();
+        };
+
+        state.waits_for.remove(&txn_id);
+        state.waiting.remove(&txn_id);
+
+        match result {
+            Ok(true) => {
+                Self::hold_key(&mut state, txn_id, keyspace_id, key);
+                Ok(())
+            },
+            Ok(false) => Ok(()),
+            Err(error) => {
+                for lock in state.locks.values_mut() {
+                    lock.waiters.retain(|waiter_txn_id| *waiter_txn_id != txn_id);
+                }
+                Err(error)
+            }
+        }
+    }
+
+    //Called when the transaction is committed or rolledback
+    pub fn release(&self, txn_id: TxnId) {
+        let mut state = self.state.lock().unwrap();
+
+        if let Some(held_by_keyspace) = state.held_by_holder.remove(&txn_id) {
+            for (keyspace_id, held_keys) in held_by_keyspace {
+                match held_keys {
+                    HeldKeys::Keyspace => Self::release_lock(&mut state, &(keyspace_id, None)),
+                    HeldKeys::Keys(keys) => {
+                        let n_locked_keys_keyspace = state.n_locked_keys_by_keyspace.get_mut(&keyspace_id).unwrap();
+                        *n_locked_keys_keyspace -= keys.len();
+                        if *n_locked_keys_keyspace == 0 {
+                            state.n_locked_keys_by_keyspace.remove(&keyspace_id);
+                        }
+                        for key in keys {
+                            Self::release_lock(&mut state, &(keyspace_id, Some(key)));
+                        }
+                    }
+                }
+            }
+        }
+        //The transaction might have been aborted while waiting
+        if let Some(condvar) = state.waiting.get(&txn_id) {
+            condvar.notify_one();
+        }
+    }
+
+    //Called when txn_id doesn't hold the key nor its keyspace
+    fn hold_key(state: &mut KeyLocksState, txn_id: TxnId, keyspace_id: KeyspaceId, key: &Bytes) {
+        state.locks.insert((keyspace_id, Some(key.clone())), Lock { holder: txn_id, waiters: Vec::new() });
+        let n_locked_keys_keyspace = state.n_locked_keys_by_keyspace.entry(keyspace_id).or_insert(0);
+        *n_locked_keys_keyspace += 1;
+        let n_locked_keys_keyspace = *n_locked_keys_keyspace;
+
+        let held_keys = state.held_by_holder.entry(txn_id).or_default()
+            .entry(keyspace_id)
+            .or_insert_with(|| HeldKeys::Keys(Vec::new()));
+        let n_held_keys = match held_keys {
+            HeldKeys::Keys(keys) => {
+                keys.push(key.clone());
+                keys.len()
+            },
+            HeldKeys::Keyspace => panic!("Illegal code path"),
+        };
+
+        //Only escalated if every locked key of the keyspace is held by txn_id
+        if n_held_keys > MAX_LOCKED_KEYS_PER_KEYSPACE && n_held_keys == n_locked_keys_keyspace {
+            let keys = std::mem::replace(held_keys, HeldKeys::Keyspace);
+            Self::escalate_to_keyspace(state, txn_id, keyspace_id, keys);
+        }
+    }
+
+    //The transactions waiting for the keys will wait for the whole keyspace, which has the same holder
+    fn escalate_to_keyspace(state: &mut KeyLocksState, txn_id: TxnId, keyspace_id: KeyspaceId, keys: HeldKeys) {
+        let mut waiters = Vec::new();
+        if let HeldKeys::Keys(keys) = keys {
+            for key in keys {
+                if let Some(lock) = state.locks.remove(&(keyspace_id, Some(key))) {
+                    waiters.extend(lock.waiters);
+                }
+            }
+        }
+
+        state.n_locked_keys_by_keyspace.remove(&keyspace_id);
+        state.locks.insert((keyspace_id, None), Lock { holder: txn_id, waiters });
+    }
+
+    fn release_lock(state: &mut KeyLocksState, resource: &LockedResource) {
+        if let Some(lock) = state.locks.remove(resource) {
+            for waiter_txn_id in lock.waiters {
+                if let Some(condvar) = state.waiting.get(&waiter_txn_id) {
+                    condvar.notify_one();
+                }
+            }
+        }
+    }
+
+    //Follows the wait-for graph from txn_id. Each transaction waits on only one holder, so if there is a cycle,
+    //it will contain txn_id, as the graph had no cycles before txn_id started waiting
+    fn find_deadlock_victim(state: &KeyLocksState, txn_id: TxnId) -> Option<TxnId> {
+        let mut youngest_txn_id = txn_id;
+        let mut current_txn_id = txn_id;
+
+        while let Some(holder_txn_id) = state.waits_for.get(&current_txn_id) {
+            if *holder_txn_id == txn_id {
+                return Some(youngest_txn_id);
+            }
+            //Already chosen as a victim, it will stop waiting
+            if state.deadlock_victims.contains(holder_txn_id) {
+                return None;
+            }
+
+            youngest_txn_id = youngest_txn_id.max(*holder_txn_id);
+            current_txn_id = *holder_txn_id;
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::transactions::key_locks::{KeyLocks, MAX_LOCKED_KEYS_PER_KEYSPACE};
+    use bytes::Bytes;
+    use shared::SimpleDbError;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn wait_for_holder() {
+        let key_locks = Arc::new(KeyLocks::create());
+        let key = Bytes::from(vec![1]);
+        key_locks.lock(1, 1, &key, &|_| false).unwrap();
+        //Held keys can be locked again by its holder
+        key_locks.lock(1, 1, &key, &|_| false).unwrap();
+
+        let waiter = {
+            let key_locks = key_locks.clone();
+            let key = key.clone();
+            std::thread::spawn(move || key_locks.lock(2, 1, &key, &|_| false))
+        };
+        //Releasing other transactions doesn't wake the waiter up
+        std::thread::sleep(Duration::from_millis(50));
+        key_locks.release(3);
+        assert!(!waiter.is_finished());
+
+        key_locks.release(1);
+        assert!(waiter.join().unwrap().is_ok());
+    }
+
+    #[test]
+    fn deadlock() {
+        let key_locks = Arc::new(KeyLocks::create());
+        let key_a = Bytes::from(vec![1]);
+        let key_b = Bytes::from(vec![2]);
+        key_locks.lock(1, 1, &key_a, &|_| false).unwrap();
+        key_locks.lock(2, 1, &key_b, &|_| false).unwrap();
+
+        let waiter = {
+            let key_locks = key_locks.clone();
+            let key_b = key_b.clone();
+            std::thread::spawn(move || key_locks.lock(1, 1, &key_b, &|_| false))
+        };
+        while !key_locks.state.lock().unwrap().waits_for.contains_key(&1) {
+            std::thread::yield_now();
+        }
+
+        //The youngest transaction is the victim
+        assert!(matches!(key_locks.lock(2, 1, &key_a, &|_| false), Err(SimpleDbError::DeadlockDetected(2))));
+        key_locks.release(2);
+        assert!(waiter.join().unwrap().is_ok());
+    }
+
+    #[test]
+    fn escalate_to_keyspace() {
+        let key_locks = KeyLocks::create();
+        key_locks.lock(1, 2, &Bytes::from(vec![1]), &|_| false).unwrap();
+        for key in 0..=MAX_LOCKED_KEYS_PER_KEYSPACE as u32 {
+            key_locks.lock(2, 1, &Bytes::from(key.to_le_bytes().to_vec()), &|_| false).unwrap();
+        }
+
+        //The keys of transaction 2 in keyspace 1 are replaced by a lock of the whole keyspace
+        let state = key_locks.state.lock().unwrap();
+        assert_eq!(state.locks.len(), 2);
+        assert_eq!(state.locks.get(&(1, None)).unwrap().holder, 2);
+        assert_eq!(state.locks.get(&(2, Some(Bytes::from(vec![1])))).unwrap().holder, 1);
+        drop(state);
+
+        //Unlocked keys of the keyspace are also held
+        assert!(matches!(key_locks.lock(1, 1, &Bytes::from(vec![0xff; 8]), &|txn_id| txn_id == 1),
+            Err(SimpleDbError::TransactionAborted(1))));
+
+        key_locks.release(2);
+        key_locks.lock(1, 1, &Bytes::from(vec![0xff; 8]), &|_| false).unwrap();
+        key_locks.release(1);
+        assert!(key_locks.state.lock().unwrap().locks.is_empty());
+        assert!(key_locks.state.lock().unwrap().n_locked_keys_by_keyspace.is_empty());
+    }
+}
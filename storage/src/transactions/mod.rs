@@ -1,3 +1,5 @@
 pub mod transaction_manager;
 pub mod transaction;
+pub mod savepoint;
+mod key_locks;
 mod transaction_log;
\ No newline at end of file
@@ -0,0 +1,24 @@
+use bytes::Bytes;
+use shared::{KeyspaceId, MemtableId};
+use std::collections::HashMap;
+
+//Writes made by a transaction since the savepoint was created. For each written key, it stores the value
+//the transaction had written before in the same memtable, or None if it hadn't, so they can be restored
+//with ROLLBACK TO SAVEPOINT
+pub struct Savepoint {
+    pub(crate) name: String,
+    pub(crate) writes: HashMap<SavepointWrite, Option<Bytes>>,
+}
+
+#[derive(Clone, Hash, PartialEq, Eq)]
+pub struct SavepointWrite {
+    pub(crate) keyspace_id: KeyspaceId,
+    pub(crate) memtable_id: MemtableId,
+    pub(crate) key: Bytes,
+}
+
+impl Savepoint {
+    pub fn create(name: &str) -> Savepoint {
+        Savepoint { name: name.to_string(), writes: HashMap::new() }
+    }
+}
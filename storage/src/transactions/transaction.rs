@@ -38,6 +38,15 @@ impl Transaction {
     pub fn id(&self) -> TxnId {
         self.txn_id
     }
+
+    pub fn isolation_level(&self) -> &IsolationLevel {
+        &self.isolation_level
+    }
+
+    //Transactions that were active when this one started. Their writes won't be visible
+    pub fn active_transactions(&self) -> &HashSet<TxnId> {
+        &self.active_transactions
+    }
 }
 
 impl Clone for Transaction {
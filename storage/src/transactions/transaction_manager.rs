@@ -1,29 +1,77 @@
+use crate::transactions::key_locks::KeyLocks;
+use crate::transactions::savepoint::{Savepoint, SavepointWrite};
 use crate::transactions::transaction::Transaction;
 use crate::transactions::transaction_log::{TransactionLog, TransactionLogEntry};
-use crossbeam_skiplist::SkipMap;
-use shared::{SimpleDbError, TxnId};
+use crossbeam_skiplist::{SkipMap, SkipSet};
+use shared::logger::{logger, SimpleDbLayer};
+use shared::{KeyspaceId, SimpleDbError, TxnId};
 use std::cmp::max;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::atomic::Ordering::Relaxed;
 use std::sync::atomic::{AtomicU64, AtomicUsize};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::time::{Duration, Instant, SystemTime};
 use shared::key::Key;
+use bytes::Bytes;
 
-#[derive(Clone)]
+//Called with the ID of the transactions rolledback by the timeout thread or to break a deadlock
+pub type TransactionAbortListener = Box<dyn Fn(TxnId) + Send + Sync>;
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum IsolationLevel {
     ReadUncommited,
     SnapshotIsolation //MVCC
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub enum TransactionState {
+    Active,
+    Aborted, //Rolledback by the timeout thread, but not yet by its client
+    Finished, //Committed or rolledback
+}
+
+//Returned by SHOW TRANSACTION and SHOW TRANSACTIONS
+#[derive(Clone, Debug)]
+pub struct TransactionDesc {
+    pub txn_id: TxnId,
+    pub isolation_level: IsolationLevel,
+    pub state: TransactionState,
+    pub start_time: Option<SystemTime>, //None if the transaction is no longer active
+    pub n_writes: usize,
+}
+
+struct ActiveTransaction {
+    n_writes: AtomicUsize,
+    //Set when the transaction is rolledback. Writes are counted while holding it, so a write won't be counted
+    //after the rollback has read n_writes
+    rolledback: Mutex<bool>,
+    //The transaction can see all the versions written by older transactions
+    min_visible_txn_id: TxnId,
+    isolation_level: IsolationLevel,
+    start_time: SystemTime,
+}
+
 pub struct TransactionManager {
     rolledback_transactions: SkipMap<TxnId, AtomicUsize>,
-    active_transactions: SkipMap<TxnId, AtomicUsize>,
+    active_transactions: SkipMap<TxnId, ActiveTransaction>,
+    //Savepoints of the active transactions, in creation order
+    savepoints: SkipMap<TxnId, Mutex<Vec<Savepoint>>>,
+    //Start time of the active transactions. Removing the entry claims the right to finish the transaction,
+    //so a transaction won't be committed and aborted by the timeout thread at the same time
+    transactions_start_time: SkipMap<TxnId, Instant>,
+    //Rolledback by the timeout thread or to break a deadlock, but not yet committed or rolledback by its client
+    aborted_transactions: SkipSet<TxnId>,
+    //Called after the timeout thread or a deadlock aborts a transaction, as its client might never roll it back
+    abort_listener: RwLock<Option<TransactionAbortListener>>,
+    key_locks: KeyLocks,
+    transaction_timeout_ms: usize,
     next_txn_id: AtomicU64,
     log: TransactionLog,
 }
 
 impl TransactionManager {
     pub fn create_recover_from_log(options: Arc<shared::SimpleDbOptions>) -> Result<TransactionManager, shared::SimpleDbError> {
+        let transaction_timeout_ms = options.transaction_timeout_ms;
         let log = TransactionLog::create(options)?;
         let transaction_log_entries = log.read_entries()?;
         let (active_transactions, pending_to_rollback, max_txn_id) =
@@ -39,10 +87,27 @@ impl TransactionManager {
             rolledback_transactions: Self::pending_transactions_to_txnids(&active_transactions, &pending_to_rollback),
             next_txn_id: AtomicU64::new((max_txn_id + 1) as u64),
             active_transactions: SkipMap::new(),
+            savepoints: SkipMap::new(),
+            transactions_start_time: SkipMap::new(),
+            aborted_transactions: SkipSet::new(),
+            abort_listener: RwLock::new(None),
+            key_locks: KeyLocks::create(),
+            transaction_timeout_ms,
             log,
         })
     }
 
+    //Creates the transaction log of a snapshot. Transactions started after restoring it will have bigger IDs
+    //than the ones of the snapshot entries, which will be visible to them
+    pub fn create_snapshot_log(
+        &self,
+        transaction: &Transaction,
+        snapshot_options: &Arc<shared::SimpleDbOptions>
+    ) -> Result<(), shared::SimpleDbError> {
+        let log = TransactionLog::create(snapshot_options.clone())?;
+        log.add_entry(TransactionLogEntry::MaxTxnId(transaction.id()))
+    }
+
     pub fn get_active_transactions(&self) -> Vec<shared::TxnId> {
         let mut active_transactions = Vec::new();
 
@@ -53,36 +118,180 @@ impl TransactionManager {
         active_transactions
     }
 
+    //Versions written by transactions older than the returned one are visible by every active transaction.
+    //Rolledback transactions are not taken into account, their writes are never visible
+    pub fn get_min_visible_txn_id(&self) -> TxnId {
+        self.active_transactions.iter()
+            .map(|entry| entry.value().min_visible_txn_id)
+            .min()
+            .unwrap_or(self.next_txn_id.load(Relaxed) as TxnId)
+    }
+
     pub fn create_mock(options: Arc<shared::SimpleDbOptions>) -> TransactionManager {
         TransactionManager {
+            transaction_timeout_ms: options.transaction_timeout_ms,
             log: TransactionLog::create_mock(options),
             rolledback_transactions: SkipMap::new(),
             active_transactions: SkipMap::new(),
+            savepoints: SkipMap::new(),
+            transactions_start_time: SkipMap::new(),
+            aborted_transactions: SkipSet::new(),
+            abort_listener: RwLock::new(None),
+            key_locks: KeyLocks::create(),
             next_txn_id: AtomicU64::new(0),
         }
     }
 
     pub fn commit(&self, transaction: &Transaction) -> Result<(), SimpleDbError> {
+        //Already rolledback by the timeout thread or to break a deadlock. The client won't use the transaction anymore
+        if !self.claim_transaction(transaction.txn_id) {
+            self.aborted_transactions.remove(&transaction.txn_id);
+            return Err(SimpleDbError::TransactionAborted(transaction.txn_id));
+        }
+
+        self.savepoints.remove(&transaction.txn_id);
         self.active_transactions.remove(&transaction.txn_id);
+        self.key_locks.release(transaction.txn_id);
         self.log.add_entry(TransactionLogEntry::Commit(transaction.txn_id))
     }
 
     //Before calling this function it is expected that the written keys have been removed
     pub fn rollback(&self, transaction: &Transaction) -> Result<(), SimpleDbError> {
-        self.log.add_entry(TransactionLogEntry::StartRollback(transaction.txn_id))?;
-        let n_writes = self.active_transactions.get(&transaction.txn_id)
-            .unwrap()
-            .value()
-            .load(Relaxed);
-        self.active_transactions.remove(&transaction.txn_id);
+        //Already rolledback by the timeout thread, or already finished by its client
+        if !self.is_active(transaction.txn_id) || !self.claim_transaction(transaction.txn_id) {
+            self.aborted_transactions.remove(&transaction.txn_id);
+            return Ok(());
+        }
+
+        self.rollback_txn_id(transaction.txn_id)
+    }
 
+    fn rollback_txn_id(&self, txn_id: TxnId) -> Result<(), SimpleDbError> {
+        self.log.add_entry(TransactionLogEntry::StartRollback(txn_id))?;
+        self.savepoints.remove(&txn_id);
+        let active_transaction = self.active_transactions.get(&txn_id)
+            .unwrap();
+        let mut rolledback = active_transaction.value().rolledback.lock().unwrap();
+        *rolledback = true;
+        let n_writes = active_transaction.value().n_writes.load(Relaxed);
+        drop(rolledback);
+
+        //Inserted before removing it from active transactions, so its writes are never visible by new transactions
         if n_writes > 0 {
-            self.rolledback_transactions.insert(transaction.txn_id, AtomicUsize::new(n_writes));
+            self.rolledback_transactions.insert(txn_id, AtomicUsize::new(n_writes));
+        }
+        self.active_transactions.remove(&txn_id);
+        self.key_locks.release(txn_id);
+
+        Ok(())
+    }
+
+    //Called before writing a key. If another active transaction has written the key, it waits until that transaction
+    //finishes. If waiting would cause a deadlock, the youngest transaction of the deadlock is aborted and
+    //DeadlockDetected is returned to it. Keys are held until the transaction is committed or rolledback
+    pub fn lock_key(&self, transaction: &Transaction, keyspace_id: KeyspaceId, key: &Bytes) -> Result<(), SimpleDbError> {
+        //Transaction::none() or already finished transactions
+        if !self.is_active(transaction.txn_id) {
+            return Ok(());
+        }
+
+        let lock_result = self.key_locks.lock(transaction.txn_id, keyspace_id, key, &|txn_id| self.is_aborted(txn_id));
+        if let Err(SimpleDbError::DeadlockDetected(txn_id)) = &lock_result {
+            self.abort_deadlock_victim(*txn_id);
+        }
+
+        lock_result
+    }
+
+    //The victim is waiting for a key, so its client can't commit or rollback it at the same time
+    fn abort_deadlock_victim(&self, txn_id: TxnId) {
+        logger().info(SimpleDbLayer::Storage, &format!("Aborting transaction {} to resolve a deadlock", txn_id));
+
+        self.aborted_transactions.insert(txn_id);
+        //Already aborted by the timeout thread
+        if self.transaction_timeout_ms > 0 && self.transactions_start_time.remove(&txn_id).is_none() {
+            return;
+        }
+        if let Err(error) = self.rollback_txn_id(txn_id) {
+            logger().error(SimpleDbLayer::Storage, &format!("Error while aborting transaction {}: {:?}", txn_id, error));
+        }
+        self.notify_aborted(txn_id);
+    }
+
+    pub fn set_abort_listener(&self, abort_listener: TransactionAbortListener) {
+        *self.abort_listener.write().unwrap() = Some(abort_listener);
+    }
+
+    fn notify_aborted(&self, txn_id: TxnId) {
+        if let Some(abort_listener) = self.abort_listener.read().unwrap().as_ref() {
+            abort_listener(txn_id);
+        }
+    }
+
+    //Returns false if the transaction has been aborted by the timeout thread or to break a deadlock
+    fn claim_transaction(&self, txn_id: TxnId) -> bool {
+        self.transactions_start_time.remove(&txn_id).is_some() || !self.aborted_transactions.contains(&txn_id)
+    }
+
+    pub fn is_aborted(&self, txn_id: TxnId) -> bool {
+        self.aborted_transactions.contains(&txn_id)
+    }
+
+    pub fn check_not_aborted(&self, transaction: &Transaction) -> Result<(), SimpleDbError> {
+        if self.aborted_transactions.contains(&transaction.txn_id) {
+            return Err(SimpleDbError::TransactionAborted(transaction.txn_id));
         }
 
         Ok(())
     }
 
+    //Periodically rolls back the transactions active for longer than SimpleDbOptions::transaction_timeout_ms
+    //The thread will stop once the transaction manager is dropped
+    pub fn start_transaction_timeout_thread(transaction_manager: &Arc<TransactionManager>) {
+        if transaction_manager.transaction_timeout_ms == 0 {
+            return;
+        }
+
+        let transaction_manager: Weak<TransactionManager> = Arc::downgrade(transaction_manager);
+        std::thread::spawn(move || {
+            loop {
+                let transaction_manager = match transaction_manager.upgrade() {
+                    Some(transaction_manager) => transaction_manager,
+                    None => return,
+                };
+                transaction_manager.abort_timed_out_transactions();
+
+                let sleep_time_ms = max(transaction_manager.transaction_timeout_ms / 2, 1);
+                drop(transaction_manager);
+                std::thread::sleep(Duration::from_millis(sleep_time_ms as u64));
+            }
+        });
+    }
+
+    fn abort_timed_out_transactions(&self) {
+        let timeout = Duration::from_millis(self.transaction_timeout_ms as u64);
+
+        for entry in self.transactions_start_time.iter() {
+            if entry.value().elapsed() < timeout {
+                continue;
+            }
+
+            let txn_id = *entry.key();
+            //Inserted before claiming it, so it will be seen by the client if it tries to commit it at the same time
+            self.aborted_transactions.insert(txn_id);
+            if self.transactions_start_time.remove(&txn_id).is_none() {
+                self.aborted_transactions.remove(&txn_id);
+                continue;
+            }
+
+            logger().info(SimpleDbLayer::Storage, &format!("Aborting transaction {} because it exceeded the timeout", txn_id));
+            if let Err(error) = self.rollback_txn_id(txn_id) {
+                logger().error(SimpleDbLayer::Storage, &format!("Error while aborting transaction {}: {:?}", txn_id, error));
+            }
+            self.notify_aborted(txn_id);
+        }
+    }
+
     //This function is called when there is a memtable flush or sstable compaction
     //Returns Ok if the key with that transaction ID hasn't been rolledback
     //Returns Err if it has been rolledback
@@ -105,8 +314,18 @@ impl TransactionManager {
 
     pub fn start_transaction(&self, isolation_level: IsolationLevel) -> Transaction {
         let active_transactions = self.copy_active_transactions();
+        let oldest_active_txn_id = self.active_transactions.front().map(|entry| *entry.key());
         let txn_id = self.next_txn_id.fetch_add(1, Relaxed) as shared::TxnId;
-        self.active_transactions.insert(txn_id, AtomicUsize::new(0));
+        self.active_transactions.insert(txn_id, ActiveTransaction {
+            min_visible_txn_id: oldest_active_txn_id.unwrap_or(txn_id).min(txn_id),
+            isolation_level: isolation_level.clone(),
+            start_time: SystemTime::now(),
+            n_writes: AtomicUsize::new(0),
+            rolledback: Mutex::new(false),
+        });
+        if self.transaction_timeout_ms > 0 {
+            self.transactions_start_time.insert(txn_id, Instant::now());
+        }
 
         Transaction {
             active_transactions,
@@ -115,14 +334,155 @@ impl TransactionManager {
         }
     }
 
+    //Returns TransactionAborted if the transaction has been aborted, even if it happened after check_not_aborted()
     pub fn mark_write(&self, transaction: &Transaction) -> Result<(), SimpleDbError> {
         match self.active_transactions.get(&transaction.txn_id) {
-            Some(n_writes) => {
+            Some(active_transaction) => {
+                let rolledback = active_transaction.value().rolledback.lock().unwrap();
+                if *rolledback {
+                    return Err(SimpleDbError::TransactionAborted(transaction.txn_id));
+                }
                 self.log.add_entry(TransactionLogEntry::Write(transaction.txn_id))?;
-                n_writes.value().fetch_add(1, Relaxed);
+                active_transaction.value().n_writes.fetch_add(1, Relaxed);
                 Ok(())
             }
-            None => Ok(())
+            None => self.check_not_aborted(transaction),
+        }
+    }
+
+    //Returns true if the transaction is the last one started, there were no other active transactions when it started,
+    //there are none now and it hasn't written anything. In this case, the values read by the transaction are the
+    //last committed ones. Transactions active when it started might have committed since then, without being visible
+    pub fn reads_last_committed_values(&self, transaction: &Transaction) -> bool {
+        let is_last_transaction_started = self.next_txn_id.load(Relaxed) == (transaction.txn_id + 1) as u64;
+        let has_written = match self.active_transactions.get(&transaction.txn_id) {
+            Some(active_transaction) => active_transaction.value().n_writes.load(Relaxed) > 0,
+            None => return false,
+        };
+
+        is_last_transaction_started && !has_written && self.active_transactions.len() == 1 &&
+            transaction.active_transactions.is_empty()
+    }
+
+    //Returns a transaction that reads the last committed values and the writes of the given transaction. It is not
+    //registered as active, so it can only be used to read. Writes of other active and rolledback transactions are not visible
+    pub fn last_committed_values_transaction(&self, transaction: &Transaction) -> Transaction {
+        let mut active_transactions = self.copy_active_transactions();
+        active_transactions.remove(&transaction.txn_id);
+
+        Transaction {
+            isolation_level: IsolationLevel::SnapshotIsolation,
+            txn_id: TxnId::MAX,
+            active_transactions,
+        }
+    }
+
+    //If there is already a savepoint with the same name, the newest one will be used
+    pub fn create_savepoint(&self, transaction: &Transaction, name: &str) {
+        let entry = self.savepoints.get_or_insert(transaction.txn_id, Mutex::new(Vec::new()));
+        entry.value().lock().unwrap().push(Savepoint::create(name));
+    }
+
+    pub fn has_savepoints(&self, transaction: &Transaction) -> bool {
+        self.savepoints.contains_key(&transaction.txn_id)
+    }
+
+    //Only the first write to a key since the last savepoint is stored. previous_value is the value written by
+    //the transaction to the key in the same memtable before this write
+    pub fn add_savepoint_write(
+        &self,
+        transaction: &Transaction,
+        write: SavepointWrite,
+        previous_value: Option<Bytes>,
+    ) {
+        if let Some(entry) = self.savepoints.get(&transaction.txn_id) {
+            let mut savepoints = entry.value().lock().unwrap();
+            if let Some(last_savepoint) = savepoints.last_mut() {
+                last_savepoint.writes.entry(write).or_insert(previous_value);
+            }
+        }
+    }
+
+    //Removes the savepoints created after the given one, which is kept. Returns the writes to restore,
+    //the most recent ones first
+    pub fn rollback_to_savepoint(
+        &self,
+        transaction: &Transaction,
+        name: &str
+    ) -> Result<Vec<HashMap<SavepointWrite, Option<Bytes>>>, SimpleDbError> {
+        let entry = self.savepoints.get(&transaction.txn_id)
+            .ok_or(SimpleDbError::SavepointNotFound(name.to_string()))?;
+        let mut savepoints = entry.value().lock().unwrap();
+        let savepoint_index = Self::find_savepoint(&savepoints, name)?;
+
+        let mut writes_to_restore: Vec<HashMap<SavepointWrite, Option<Bytes>>> = savepoints.drain(savepoint_index + 1..)
+            .map(|savepoint| savepoint.writes)
+            .collect();
+        writes_to_restore.insert(0, std::mem::take(&mut savepoints[savepoint_index].writes));
+        writes_to_restore.reverse();
+
+        Ok(writes_to_restore)
+    }
+
+    //Removes the savepoint and the ones created after it. Its writes are kept
+    pub fn release_savepoint(&self, transaction: &Transaction, name: &str) -> Result<(), SimpleDbError> {
+        let entry = self.savepoints.get(&transaction.txn_id)
+            .ok_or(SimpleDbError::SavepointNotFound(name.to_string()))?;
+        let mut savepoints = entry.value().lock().unwrap();
+        let savepoint_index = Self::find_savepoint(&savepoints, name)?;
+        let released_savepoints: Vec<Savepoint> = savepoints.drain(savepoint_index..).collect();
+
+        //The previous savepoint has to be able to restore the writes of the released ones
+        if let Some(previous_savepoint) = savepoints.last_mut() {
+            for released_savepoint in released_savepoints {
+                for (write, previous_value) in released_savepoint.writes {
+                    previous_savepoint.writes.entry(write).or_insert(previous_value);
+                }
+            }
+        }
+
+        let has_savepoints = !savepoints.is_empty();
+        drop(savepoints);
+        if !has_savepoints {
+            self.savepoints.remove(&transaction.txn_id);
+        }
+
+        Ok(())
+    }
+
+    fn find_savepoint(savepoints: &Vec<Savepoint>, name: &str) -> Result<usize, SimpleDbError> {
+        savepoints.iter()
+            .rposition(|savepoint| savepoint.name == name)
+            .ok_or(SimpleDbError::SavepointNotFound(name.to_string()))
+    }
+
+    pub fn describe_transaction(&self, transaction: &Transaction) -> TransactionDesc {
+        match self.active_transactions.get(&transaction.txn_id) {
+            Some(entry) => Self::describe_active_transaction(transaction.txn_id, entry.value()),
+            None => TransactionDesc {
+                state: if self.is_aborted(transaction.txn_id) { TransactionState::Aborted } else { TransactionState::Finished },
+                isolation_level: transaction.isolation_level.clone(),
+                txn_id: transaction.txn_id,
+                start_time: None,
+                n_writes: 0,
+            }
+        }
+    }
+
+    //Sorted by transaction ID, so the oldest transactions are returned first
+    pub fn describe_active_transactions(&self) -> Vec<TransactionDesc> {
+        self.active_transactions.iter()
+            .map(|entry| Self::describe_active_transaction(*entry.key(), entry.value()))
+            .collect()
+    }
+
+    fn describe_active_transaction(txn_id: TxnId, active_transaction: &ActiveTransaction) -> TransactionDesc {
+        TransactionDesc {
+            isolation_level: active_transaction.isolation_level.clone(),
+            n_writes: active_transaction.n_writes.load(Relaxed),
+            start_time: Some(active_transaction.start_time),
+            state: TransactionState::Active,
+            txn_id,
         }
     }
 
@@ -130,12 +490,17 @@ impl TransactionManager {
         self.active_transactions.get(&txn_id).is_some()
     }
 
+    //Rolledback transactions whose writes haven't been discarded yet are also included,
+    //so new transactions won't be able to see their writes
     fn copy_active_transactions(&self) -> HashSet<TxnId> {
         let mut active_transactions: HashSet<TxnId> = HashSet::new();
 
         for active_transaction in &self.active_transactions {
             active_transactions.insert(*active_transaction.key());
         }
+        for rolledback_transaction in &self.rolledback_transactions {
+            active_transactions.insert(*rolledback_transaction.key());
+        }
 
         active_transactions
     }
@@ -1,69 +1,128 @@
 use bytes::BufMut;
 
+//SSTables written before this format version have bloom filters that set a single bit per key
+pub const BLOOM_FILTER_N_HASHES_FORMAT_VERSION: u8 = 2;
+const MAX_N_HASHES: usize = 30;
+
 pub struct BloomFilter {
     bitmap: Vec<u8>,
+    n_hashes: usize,
+    legacy: bool, //Written before BLOOM_FILTER_N_HASHES_FORMAT_VERSION
 }
 
 impl BloomFilter {
     pub fn may_contain(&self, hash: u32) -> bool {
+        if self.legacy {
+            return self.legacy_may_contain(hash);
+        }
+
+        let n_bits = (self.bitmap.len() * 8) as u32;
+        Self::bit_positions(hash, self.n_hashes, n_bits)
+            .all(|bit_position| self.bitmap[(bit_position / 8) as usize] >> (bit_position % 8) & 0x01 == 0x01)
+    }
+
+    //Legacy filters select the slot with the lower bits of the hash, and always set its first bit
+    fn legacy_may_contain(&self, hash: u32) -> bool {
         let slot_index = hash & (self.bitmap.len() - 1) as u32;
-        let bit_index_in_slot = hash & (std::mem::size_of::<u8>() - 1) as u32;
         let slot: u8 = self.bitmap[slot_index as usize];
 
-        slot >> bit_index_in_slot & 0x01 == 0x01
+        slot & 0x01 == 0x01
+    }
+
+    //The k positions are derived from a single hash with double hashing: hash + i * delta
+    fn bit_positions(hash: u32, n_hashes: usize, n_bits: u32) -> impl Iterator<Item = u32> {
+        let delta = hash.rotate_right(17);
+        (0..n_hashes as u32).map(move |i| hash.wrapping_add(i.wrapping_mul(delta)) % n_bits)
     }
 
     pub fn encode(&self) -> Vec<u8> {
-        let crc = crc32fast::hash(&self.bitmap);
-        let mut encoded: Vec<u8> = Vec::with_capacity(self.bitmap.len() + 8);
+        let crc = Self::crc(&self.bitmap, self.n_hashes);
+        let mut encoded: Vec<u8> = Vec::with_capacity(self.bitmap.len() + 9);
         encoded.put_u32_le(crc);
         encoded.put_u32_le(self.bitmap.len() as u32);
         encoded.extend(&self.bitmap);
+        encoded.push(self.n_hashes as u8);
         encoded
     }
 
-    pub fn decode(bytes: &Vec<u8>, start_offset: usize) -> Result<BloomFilter, shared::DecodeErrorType> {
+    //format_version is the one of the SSTable. The nº of hashes is stored after the bitmap since BLOOM_FILTER_N_HASHES_FORMAT_VERSION
+    pub fn decode(bytes: &Vec<u8>, start_offset: usize, format_version: u8) -> Result<BloomFilter, shared::DecodeErrorType> {
+        if start_offset + 8 > bytes.len() {
+            return Err(shared::DecodeErrorType::IllegalSize(start_offset + 8, bytes.len()));
+        }
         let expected_crc = shared::u8_vec_to_u32_le(bytes, start_offset);
         let n_bytes = shared::u8_vec_to_u32_le(bytes, start_offset + 4);
 
+        let legacy = format_version < BLOOM_FILTER_N_HASHES_FORMAT_VERSION;
         let bitmap_start_index = start_offset + 8;
         let bitmap_end_index = start_offset + 8 + n_bytes as usize;
+        let end_index = if legacy { bitmap_end_index } else { bitmap_end_index + 1 };
+        if end_index > bytes.len() {
+            return Err(shared::DecodeErrorType::IllegalSize(end_index, bytes.len()));
+        }
         let bloom_bitmap = bytes[bitmap_start_index..bitmap_end_index].to_vec();
-        let actual_crc = crc32fast::hash(&bloom_bitmap);
 
+        if legacy {
+            let actual_crc = crc32fast::hash(&bloom_bitmap);
+            if actual_crc != expected_crc {
+                return Err(shared::DecodeErrorType::CorruptedCrc(expected_crc, actual_crc));
+            }
+            return Ok(BloomFilter { bitmap: bloom_bitmap, n_hashes: 1, legacy: true });
+        }
+
+        let n_hashes = bytes[bitmap_end_index] as usize;
+        let actual_crc = Self::crc(&bloom_bitmap, n_hashes);
         if actual_crc != expected_crc {
             return Err(shared::DecodeErrorType::CorruptedCrc(expected_crc, actual_crc));
         }
+        if n_hashes == 0 || n_hashes > MAX_N_HASHES || bloom_bitmap.is_empty() {
+            return Err(shared::DecodeErrorType::UnknownFlag(n_hashes));
+        }
 
-        Ok(Self::from_botmap(bloom_bitmap))
+        Ok(BloomFilter { bitmap: bloom_bitmap, n_hashes, legacy: false })
     }
 
-    pub fn from_botmap(bitmap: Vec<u8>) -> BloomFilter {
-        BloomFilter{ bitmap }
+    fn crc(bitmap: &[u8], n_hashes: usize) -> u32 {
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(bitmap);
+        hasher.update(&[n_hashes as u8]);
+        hasher.finalize()
+    }
+
+    //Returns the nº of entries (bits) of a bloom filter holding n_keys. The higher bits_per_key is,
+    //the lower the false positive rate will be. Rounded up to a power of 2
+    pub fn n_entries_for(n_keys: usize, bits_per_key: usize) -> usize {
+        (n_keys * bits_per_key).max(64).next_power_of_two()
+    }
+
+    //The nº of hashes that minimizes the false positive rate is bits per key * ln(2)
+    fn n_hashes_for(n_keys: usize, n_entries: usize) -> usize {
+        let bits_per_key = n_entries as f64 / n_keys.max(1) as f64;
+        ((bits_per_key * std::f64::consts::LN_2).round() as usize).clamp(1, MAX_N_HASHES)
     }
 
     pub fn create(
         hashes: &Vec<u32>,
-        n_entries: usize //Expect power of 2
+        n_entries: usize //Expect multiple of 8
     ) -> BloomFilter {
-        let n_vec_slots = (n_entries / 8) as u32;
-        let mut bitmap: Vec<u8> = Vec::with_capacity(n_vec_slots as usize);
-        shared::fill_vec(&mut bitmap, n_vec_slots as usize, 0);
+        let n_vec_slots = n_entries / 8;
+        let n_hashes = Self::n_hashes_for(hashes.len(), n_entries);
+        let mut bitmap: Vec<u8> = Vec::with_capacity(n_vec_slots);
+        shared::fill_vec(&mut bitmap, n_vec_slots, 0);
 
         for hash in hashes {
-            let slot_index = hash & (n_vec_slots - 1);
-            let bit_index_in_slot = hash & (std::mem::size_of::<u8>() - 1) as u32;
-            let slot: u8 = bitmap[slot_index as usize];
-            let updated_slot: u8 = slot | (0x01 << bit_index_in_slot);
-            bitmap[slot_index as usize] = updated_slot;
+            for bit_position in Self::bit_positions(*hash, n_hashes, (n_vec_slots * 8) as u32) {
+                bitmap[(bit_position / 8) as usize] |= 0x01 << (bit_position % 8);
+            }
         }
 
-        BloomFilter { bitmap }
+        BloomFilter { bitmap, n_hashes, legacy: false }
     }
 }
 
 #[cfg(test)]
 mod test {
+    use crate::sst::sstable::SSTABLE_FORMAT_VERSION;
     use crate::utils::bloom_filter::BloomFilter;
 
     #[test]
@@ -84,7 +143,7 @@ mod test {
     fn decode_encode() {
         let encoded = BloomFilter::create(&vec![101212, 1389172819, 182971, 12, 1729187291], 64)
             .encode();
-        let decoded_result = BloomFilter::decode(&encoded, 0);
+        let decoded_result = BloomFilter::decode(&encoded, 0, SSTABLE_FORMAT_VERSION);
 
         assert!(decoded_result.is_ok());
         let decoded_result = decoded_result.unwrap();
@@ -95,4 +154,35 @@ mod test {
         assert!(decoded_result.may_contain(12));
         assert!(decoded_result.may_contain(1729187291));
     }
+
+    #[test]
+    fn false_positive_rate() {
+        let n_keys = 10000;
+        let hashes: Vec<u32> = (0..n_keys).map(|i| shared::hash(format!("key{}", i).as_bytes())).collect();
+        let bloom = BloomFilter::create(&hashes, BloomFilter::n_entries_for(n_keys, 10));
+
+        for hash in &hashes {
+            assert!(bloom.may_contain(*hash));
+        }
+        //10 bits per key with 7 hashes is expected to have a false positive rate of ~1%
+        let n_false_positives = (0..n_keys)
+            .filter(|i| bloom.may_contain(shared::hash(format!("other{}", i).as_bytes())))
+            .count();
+        assert!(n_false_positives < n_keys / 50, "False positives: {}", n_false_positives);
+    }
+
+    #[test]
+    fn decode_legacy() {
+        //Legacy filters set the first bit of the slot selected with the lower bits of the hash
+        let mut bitmap = vec![0u8; 8];
+        bitmap[12 & 7] = 0x01;
+        let mut encoded = Vec::new();
+        encoded.extend(crc32fast::hash(&bitmap).to_le_bytes());
+        encoded.extend((bitmap.len() as u32).to_le_bytes());
+        encoded.extend(&bitmap);
+
+        let decoded = BloomFilter::decode(&encoded, 0, 1).unwrap();
+        assert!(decoded.may_contain(12));
+        assert!(!decoded.may_contain(13));
+    }
 }
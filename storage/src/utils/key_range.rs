@@ -0,0 +1,12 @@
+use shared::key::Key;
+
+//Extends key_range, so that it also contains [first_key, last_key]
+pub fn merge_key_ranges(key_range: Option<(Key, Key)>, first_key: &Key, last_key: &Key) -> Option<(Key, Key)> {
+    match key_range {
+        Some((min_key, max_key)) => Some((
+            std::cmp::min(min_key, first_key.clone()),
+            std::cmp::max(max_key, last_key.clone())
+        )),
+        None => Some((first_key.clone(), last_key.clone())),
+    }
+}
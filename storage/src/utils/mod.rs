@@ -1,3 +1,4 @@
 pub mod bloom_filter;
+pub mod key_range;
 pub mod storage_engine_iterator;
 pub mod tombstone;
@@ -31,6 +31,8 @@ pub struct StorageEngineIterator<I: StorageIterator> {
     keyspace_desc: KeyspaceDescriptor,
 
     first_iteration: bool,
+    //Set by seek_to_end(), the inner iterator will be moved with prev()
+    is_reversed: bool,
 }
 
 impl<I: StorageIterator> StorageEngineIterator<I> {
@@ -51,6 +53,7 @@ impl<I: StorageIterator> StorageEngineIterator<I> {
             inner_iterator: iterator,
             options: options.clone(),
             first_iteration: true,
+            is_reversed: false,
             current_value: None,
             current_key: None,
             transaction: None,
@@ -108,6 +111,53 @@ impl<I: StorageIterator> StorageEngineIterator<I> {
         }
     }
 
+    //Same as find_entries() but moving the inner iterator backwards. The versions of the same key are found
+    //in descending order, so they are reversed before being merged
+    fn find_prev_entries(&mut self) -> bool {
+        loop {
+            if self.is_finished {
+                return false;
+            }
+
+            let mut entries = vec![(
+                self.inner_iterator.key().clone(),
+                Bytes::copy_from_slice(self.inner_iterator.value())
+            )];
+
+            let current_key_bytes = Bytes::copy_from_slice(self.inner_iterator.key().as_bytes());
+
+            loop {
+                if !self.inner_iterator.prev() {
+                    self.is_finished = true;
+                    break;
+                }
+                if !self.inner_iterator.key().bytes_eq_bytes(&current_key_bytes) {
+                    break;
+                }
+
+                entries.push((
+                    self.inner_iterator.key().clone(),
+                    Bytes::copy_from_slice(self.inner_iterator.value())
+                ));
+            }
+
+            self.entries_to_return.clear();
+            self.entries_to_return.extend(entries.into_iter().rev());
+
+            if self.merge_entry_values() {
+                return true;
+            }
+        }
+    }
+
+    fn find_entries_in_direction(&mut self) -> bool {
+        if self.is_reversed {
+            self.find_prev_entries()
+        } else {
+            self.find_entries()
+        }
+    }
+
     //Returns true if it merged a value that can be returned to the user of the iterator
     fn merge_entry_values(&mut self) -> bool {
         if self.options.storage_value_merger.is_none() || self.entries_to_return.len() <= 1 {
@@ -154,10 +204,7 @@ impl<I: StorageIterator> StorageEngineIterator<I> {
     }
 
     fn do_do_next(&mut self) -> bool {
-        if self.is_finished {
-            return false;
-        }
-        if self.entries_to_return.is_empty() && !self.find_entries() {
+        if self.entries_to_return.is_empty() && !self.find_entries_in_direction() {
             return false;
         }
 
@@ -165,26 +212,90 @@ impl<I: StorageIterator> StorageEngineIterator<I> {
         self.current_value = Some(next_value);
         self.current_key = Some(next_key);
 
+        self.prefetch_entries();
+
         true
     }
+
+    //Moves the inner iterator to the first entry and reads the first group of entries with a readable value
+    fn prefetch_first_entries(&mut self) {
+        self.first_iteration = false;
+
+        let is_positioned = if self.is_reversed {
+            self.inner_iterator.prev()
+        } else {
+            self.inner_iterator.next()
+        };
+        if !is_positioned {
+            self.is_finished = true;
+        }
+        if !self.find_entries_in_direction() {
+            self.entries_to_return.clear();
+        }
+    }
+
+    //Reads the next group of entries ahead of time, so that has_next() can answer
+    //without advancing the inner iterator
+    fn prefetch_entries(&mut self) {
+        if !self.check_some_keys_in_entries_to_return_readable() {
+            self.entries_to_return.clear();
+            if !self.find_entries_in_direction() {
+                self.entries_to_return.clear();
+            }
+        }
+    }
 }
 
-impl<I: StorageIterator> StorageIterator for StorageEngineIterator<I> {
+impl<I: StorageIterator + Clone> StorageEngineIterator<I> {
+    //The inner iterator hasn't been positioned yet. It might only have tombstones left, so the first entries
+    //are read with a copy of the inner iterator, which won't be committed when dropped, unlike Clone
+    fn has_first_entry(&self) -> bool {
+        if self.is_finished {
+            return false;
+        }
+
+        let mut lookahead_iterator = StorageEngineIterator {
+            options: self.options.clone(),
+            inner_iterator: self.inner_iterator.clone(),
+            entries_to_return: VecDeque::new(),
+            current_value: None,
+            current_key: None,
+            transaction_manager: None,
+            transaction: None,
+            is_finished: self.is_finished,
+            keyspace_desc: self.keyspace_desc,
+            first_iteration: true,
+            is_reversed: self.is_reversed,
+        };
+        lookahead_iterator.prefetch_first_entries();
+        lookahead_iterator.check_some_keys_in_entries_to_return_readable()
+    }
+
+    //Entries read before a seek are discarded, the iterator will be positioned again in the next call to next() or prev()
+    fn reset_entries(&mut self) {
+        self.entries_to_return.clear();
+        self.current_value = None;
+        self.current_key = None;
+        self.first_iteration = true;
+    }
+}
+
+impl<I: StorageIterator + Clone> StorageIterator for StorageEngineIterator<I> {
     fn next(&mut self) -> bool {
         if self.first_iteration {
-            self.first_iteration = false;
-
-            if !self.inner_iterator.next() {
-                self.is_finished = true;
-                return false;
-            }
+            self.prefetch_first_entries();
         }
 
         self.do_do_next()
     }
 
+    //Tombstones are skipped when reading the entries ahead of time, so they are never counted as a next entry
     fn has_next(&self) -> bool {
-        todo!()
+        if self.first_iteration {
+            return self.has_first_entry();
+        }
+
+        self.check_some_keys_in_entries_to_return_readable()
     }
 
     fn key(&self) -> &Key {
@@ -197,6 +308,32 @@ impl<I: StorageIterator> StorageIterator for StorageEngineIterator<I> {
 
     fn seek(&mut self, key: &Bytes, inclusive: bool) {
         self.inner_iterator.seek(key, inclusive);
+        self.reset_entries();
+        self.is_reversed = false;
+        self.is_finished = !self.inner_iterator.has_next();
+    }
+
+    fn seek_to_end(&mut self) {
+        self.inner_iterator.seek_to_end();
+        self.reset_entries();
+        self.is_reversed = true;
+        self.is_finished = !self.inner_iterator.has_prev();
+    }
+
+    fn prev(&mut self) -> bool {
+        if self.first_iteration {
+            self.prefetch_first_entries();
+        }
+
+        self.do_do_next()
+    }
+
+    fn has_prev(&self) -> bool {
+        if self.first_iteration {
+            return self.has_first_entry();
+        }
+
+        self.check_some_keys_in_entries_to_return_readable()
     }
 }
 
@@ -220,7 +357,8 @@ impl<I: StorageIterator + Clone> Clone for StorageEngineIterator<I> {
             transaction: self.transaction.clone(),
             is_finished: self.is_finished,
             keyspace_desc: self.keyspace_desc,
-            first_iteration: self.first_iteration
+            first_iteration: self.first_iteration,
+            is_reversed: self.is_reversed,
         }
     }
 }
@@ -376,6 +514,166 @@ mod test {
         assert!(!iterator.next());
     }
 
+    #[test]
+    fn iterator_merger_fn_has_next() {
+        let options = shared::start_simpledb_options_builder_from(&shared::SimpleDbOptions::default())
+            .storage_value_merger(|a, b, _, _| merge_values(a, b))
+            .build_arc();
+
+        let memtable = Arc::new(MemTable::create_mock(options.clone(), 0, KeyspaceDescriptor::create_mock(Type::String)).unwrap());
+        memtable.set(&transaction(10), Bytes::from("aa"), &[1]).unwrap();
+        memtable.set(&transaction(1), Bytes::from("alberto"), &[1]).unwrap();
+        memtable.set(&transaction(3), Bytes::from("alberto"), &[1]).unwrap();
+        memtable.set(&transaction(4), Bytes::from("alberto"), &[1]).unwrap();
+        memtable.set(&transaction(1), Bytes::from("gonchi"), &[1]).unwrap();
+        memtable.set(&transaction(5), Bytes::from("javier"), &[1]).unwrap();
+        memtable.set(&transaction(5), Bytes::from("jaime"), &[1]).unwrap();
+        memtable.set(&transaction(1), Bytes::from("wili"), &[1]).unwrap();
+        memtable.set(&transaction(1), Bytes::from("wili"), &[10]).unwrap(); //10 Equivalent of tombstone
+        memtable.set(&transaction(1), Bytes::from("wili"), &[2]).unwrap();
+
+        let mut iterator = StorageEngineIterator::create(
+            KeyspaceDescriptor::create_mock(Type::String),
+            &options,
+            MemtableIterator::create(&memtable, &Transaction::none(), KeyspaceDescriptor::create_mock(Type::String)),
+        );
+
+        assert!(iterator.has_next());
+        assert!(iterator.next());
+        assert!(iterator.key().eq(&Key::create_from_str("aa", 10)));
+
+        assert!(iterator.has_next());
+        assert!(iterator.has_next());
+        assert!(iterator.next());
+        assert!(iterator.key().eq(&Key::create_from_str("alberto", 4)));
+        assert!(iterator.value().eq(&vec![3]));
+
+        assert!(iterator.has_next());
+        assert!(iterator.next());
+        assert!(iterator.key().eq(&Key::create_from_str("gonchi", 1)));
+
+        assert!(iterator.has_next());
+        assert!(iterator.next());
+        assert!(iterator.key().eq(&Key::create_from_str("jaime", 5)));
+
+        assert!(iterator.has_next());
+        assert!(iterator.next());
+        assert!(iterator.key().eq(&Key::create_from_str("javier", 5)));
+
+        assert!(iterator.has_next());
+        assert!(iterator.next());
+        assert!(iterator.key().eq(&Key::create_from_str("wili", 1)));
+        assert!(iterator.value().eq(&vec![2]));
+
+        assert!(!iterator.has_next());
+        assert!(!iterator.next());
+    }
+
+    #[test]
+    fn iterator_merger_fn_reversed() {
+        let options = shared::start_simpledb_options_builder_from(&shared::SimpleDbOptions::default())
+            .storage_value_merger(|a, b, _, _| merge_values(a, b))
+            .build_arc();
+
+        let memtable = Arc::new(MemTable::create_mock(options.clone(), 0, KeyspaceDescriptor::create_mock(Type::String)).unwrap());
+        memtable.set(&transaction(10), Bytes::from("aa"), &[1]).unwrap();
+        memtable.set(&transaction(1), Bytes::from("alberto"), &[1]).unwrap();
+        memtable.set(&transaction(3), Bytes::from("alberto"), &[1]).unwrap();
+        memtable.set(&transaction(4), Bytes::from("alberto"), &[1]).unwrap();
+        memtable.set(&transaction(1), Bytes::from("gonchi"), &[1]).unwrap();
+        memtable.set(&transaction(2), Bytes::from("gonchi"), &[10]).unwrap(); //10 Equivalent of tombstone
+        memtable.set(&transaction(3), Bytes::from("gonchi"), &[2]).unwrap();
+        memtable.set(&transaction(5), Bytes::from("jaime"), &[1]).unwrap();
+
+        let mut iterator = StorageEngineIterator::create(
+            KeyspaceDescriptor::create_mock(Type::String),
+            &options,
+            MemtableIterator::create(&memtable, &Transaction::none(), KeyspaceDescriptor::create_mock(Type::String)),
+        );
+        iterator.seek_to_end();
+
+        assert!(iterator.has_prev());
+        assert!(iterator.prev());
+        assert!(iterator.key().eq(&Key::create_from_str("jaime", 5)));
+
+        assert!(iterator.has_prev());
+        assert!(iterator.prev());
+        assert!(iterator.key().eq(&Key::create_from_str("gonchi", 3)));
+        assert!(iterator.value().eq(&vec![2]));
+
+        assert!(iterator.has_prev());
+        assert!(iterator.prev());
+        assert!(iterator.key().eq(&Key::create_from_str("alberto", 4)));
+        assert!(iterator.value().eq(&vec![3]));
+
+        assert!(iterator.has_prev());
+        assert!(iterator.prev());
+        assert!(iterator.key().eq(&Key::create_from_str("aa", 10)));
+
+        assert!(!iterator.has_prev());
+        assert!(!iterator.prev());
+    }
+
+    #[test]
+    fn iterator_empty_has_next() {
+        let options = Arc::new(shared::SimpleDbOptions::default());
+        let memtable = Arc::new(MemTable::create_mock(Arc::new(shared::SimpleDbOptions::default()), 0, KeyspaceDescriptor::create_mock(Type::String))
+            .unwrap());
+
+        let mut iterator = StorageEngineIterator::create(
+            KeyspaceDescriptor::create_mock(Type::String),
+            &options,
+            MemtableIterator::create(&memtable, &Transaction::none(), KeyspaceDescriptor::create_mock(Type::String)),
+        );
+
+        assert!(!iterator.has_next());
+        assert!(!iterator.next());
+    }
+
+    #[test]
+    fn iterator_only_tombstones_has_next() {
+        let options = Arc::new(shared::SimpleDbOptions::default());
+        let memtable = Arc::new(MemTable::create_mock(options.clone(), 0, KeyspaceDescriptor::create_mock(Type::String)).unwrap());
+        memtable.delete(&transaction(1), Bytes::from("alberto")).unwrap();
+        memtable.delete(&transaction(1), Bytes::from("gonchi")).unwrap();
+
+        let mut iterator = StorageEngineIterator::create(
+            KeyspaceDescriptor::create_mock(Type::String),
+            &options,
+            MemtableIterator::create(&memtable, &Transaction::none(), KeyspaceDescriptor::create_mock(Type::String)),
+        );
+
+        assert!(!iterator.has_next());
+        assert!(!iterator.next());
+    }
+
+    #[test]
+    fn iterator_seek_after_next() {
+        let options = Arc::new(shared::SimpleDbOptions::default());
+        let memtable = Arc::new(MemTable::create_mock(options.clone(), 0, KeyspaceDescriptor::create_mock(Type::String)).unwrap());
+        memtable.set(&transaction(1), Bytes::from("aa"), &[1]).unwrap();
+        memtable.set(&transaction(1), Bytes::from("bb"), &[2]).unwrap();
+        memtable.set(&transaction(1), Bytes::from("cc"), &[3]).unwrap();
+
+        let mut iterator = StorageEngineIterator::create(
+            KeyspaceDescriptor::create_mock(Type::String),
+            &options,
+            MemtableIterator::create(&memtable, &Transaction::none(), KeyspaceDescriptor::create_mock(Type::String)),
+        );
+
+        assert!(iterator.next());
+        assert!(iterator.key().eq(&Key::create_from_str("aa", 1)));
+        assert!(iterator.has_next());
+
+        //"bb" has already been read ahead, but it won't be returned after the seek
+        iterator.seek(&Bytes::from("cc"), true);
+        assert!(iterator.has_next());
+        assert!(iterator.next());
+        assert!(iterator.key().eq(&Key::create_from_str("cc", 1)));
+        assert!(!iterator.has_next());
+        assert!(!iterator.next());
+    }
+
     fn transaction(txn_id: shared::TxnId) -> Transaction {
         let mut transaction = Transaction::none();
         transaction.txn_id = txn_id;